@@ -3,11 +3,16 @@
 //! 集成所有子库，提供统一的应用程序接口。
 //! 支持 GUI (Tauri) 和 TUI (ratatui) 两种界面模式。
 
+use crate::journal::{EventJournal, JournalFilter, JournalRecord};
 use crate::{AppResult, BeyApp, DeviceInfo};
-use error::ErrorInfo;
+use bey_func::InFlightTracker;
+use error::{ErrorCategory, ErrorInfo, ErrorSeverity};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+pub use bey_func::InFlightGuard;
+
 /// 应用程序配置
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AppConfig {
@@ -69,6 +74,10 @@ pub struct BeyAppManager {
     func_manager: Option<Arc<bey_func::BeyFuncManager>>,
     /// 插件管理器
     plugin_manager: Option<Arc<bey_plugin::PluginManager>>,
+    /// 事件日志
+    journal: Option<Arc<EventJournal>>,
+    /// 进行中任务跟踪器
+    in_flight: Arc<InFlightTracker>,
 }
 
 impl BeyAppManager {
@@ -92,6 +101,8 @@ impl BeyAppManager {
             net_engine: None,
             func_manager: None,
             plugin_manager: None,
+            journal: None,
+            in_flight: Arc::new(InFlightTracker::new()),
         })
     }
 
@@ -124,13 +135,23 @@ impl BeyAppManager {
         // 初始化功能管理器（使用共享的引擎实例）
         let storage_path = self.config.storage_path.as_str();
         
+        // 发送、传输和同步登记到进行中任务跟踪器，关闭时一并排空
         let func_manager = bey_func::BeyFuncManager::new_with_engine(
             &device_id,
             Arc::clone(&engine_arc),
             storage_path
         ).await
-            .map_err(|e| ErrorInfo::new(2003, format!("初始化功能管理器失败: {:?}", e)))?;
+            .map_err(|e| ErrorInfo::new(2003, format!("初始化功能管理器失败: {:?}", e)))?
+            .with_in_flight_tracker(Arc::clone(&self.in_flight));
         
+        // 打开事件日志，记录网络连接和存储事件
+        let journal = Arc::new(EventJournal::open(
+            std::path::Path::new(&self.config.storage_path).join("journal"),
+        )?);
+        journal.attach(engine_arc.subscribe_connection_events(), JournalRecord::from_connection_event);
+        journal.attach(func_manager.storage_events(), JournalRecord::from_storage_event);
+        self.journal = Some(journal);
+
         self.func_manager = Some(Arc::new(func_manager));
 
         // 初始化插件管理器
@@ -184,8 +205,14 @@ impl BeyAppManager {
                 .map_err(|e| ErrorInfo::new(2007, format!("停止插件失败: {:?}", e)))?;
         }
 
-        // 功能管理器和网络引擎没有 stop 方法，它们会在 drop 时自动清理
+        // 停止网络引擎（关闭监听端点）
+        if let Some(engine) = &self.net_engine {
+            engine.stop_server().await
+                .map_err(|e| ErrorInfo::new(2010, format!("停止网络服务失败: {:?}", e)))?;
+        }
+
         // 清除引用以触发析构
+        self.journal = None;
         self.func_manager = None;
         self.net_engine = None;
 
@@ -194,6 +221,56 @@ impl BeyAppManager {
         Ok(())
     }
 
+    /// 登记一个进行中的任务（传输或流）
+    ///
+    /// 功能管理器的发送、传输和同步会自动登记，这里用于其他需要在关闭时排空的任务
+    ///
+    /// # 参数
+    ///
+    /// * `description` - 任务描述，排空超时时记录到日志
+    ///
+    /// # 返回值
+    ///
+    /// 返回任务守卫，关闭流程开始后返回错误
+    pub fn begin_task(&self, description: impl Into<String>) -> AppResult<InFlightGuard> {
+        self.in_flight.begin(description)
+    }
+
+    /// 进行中任务数量
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// 优雅关闭应用程序
+    ///
+    /// 停止接受新任务，在排空超时内等待进行中的传输和流完成，
+    /// 超时后记录仍未完成的任务并强制关闭。
+    ///
+    /// # 参数
+    ///
+    /// * `drain_timeout` - 排空超时时间
+    ///
+    /// # 返回值
+    ///
+    /// 成功返回 Ok(())，失败返回错误信息
+    pub async fn shutdown(&mut self, drain_timeout: Duration) -> AppResult<()> {
+        *self.state.write().await = AppState::Stopping;
+
+        self.in_flight.close();
+
+        let pending_count = self.in_flight_count();
+        if pending_count > 0 {
+            tracing::info!("等待 {} 个进行中的任务完成（超时 {:?}）", pending_count, drain_timeout);
+        }
+
+        if tokio::time::timeout(drain_timeout, self.in_flight.wait_idle()).await.is_err() {
+            let pending = self.in_flight.pending();
+            tracing::warn!("排空超时，强制关闭 {} 个未完成任务: {:?}", pending.len(), pending);
+        }
+
+        self.stop().await
+    }
+
     /// 获取当前状态
     pub async fn state(&self) -> AppState {
         *self.state.read().await
@@ -228,11 +305,31 @@ impl BeyAppManager {
     pub fn plugin_manager(&self) -> Option<Arc<bey_plugin::PluginManager>> {
         self.plugin_manager.clone()
     }
+
+    /// 查询事件日志
+    ///
+    /// # 参数
+    ///
+    /// * `filter` - 查询条件
+    ///
+    /// # 返回值
+    ///
+    /// 返回按时间从旧到新排列的日志记录，应用程序未初始化时返回错误
+    pub async fn journal_query(&self, filter: &JournalFilter) -> AppResult<Vec<JournalRecord>> {
+        let journal = Arc::clone(self.journal.as_ref()
+            .ok_or_else(|| ErrorInfo::new(2023, "事件日志未初始化".to_string()))?);
+        let filter = filter.clone();
+        tokio::task::spawn_blocking(move || journal.query(&filter)).await
+            .map_err(|e| ErrorInfo::new(2024, format!("查询事件日志的任务异常终止: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
 
     #[tokio::test]
     async fn test_app_manager_creation() {
@@ -244,6 +341,43 @@ mod tests {
         assert_eq!(manager.state().await, AppState::Initializing);
     }
 
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_transfer() {
+        let mut manager = BeyAppManager::new(AppConfig::default()).await
+            .expect("创建应用程序管理器失败");
+
+        let guard = manager.begin_task("模拟传输").expect("登记任务失败");
+        let completed = Arc::new(AtomicBool::new(false));
+        let completed_clone = Arc::clone(&completed);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            completed_clone.store(true, Ordering::SeqCst);
+            drop(guard);
+        });
+
+        manager.shutdown(Duration::from_secs(5)).await.expect("关闭失败");
+
+        assert!(completed.load(Ordering::SeqCst), "排空窗口内的传输应该完成");
+        assert_eq!(manager.in_flight_count(), 0);
+        assert_eq!(manager.state().await, AppState::Stopped);
+        assert!(manager.begin_task("新任务").is_err(), "关闭后不应接受新任务");
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_forces_close_after_timeout() {
+        let mut manager = BeyAppManager::new(AppConfig::default()).await
+            .expect("创建应用程序管理器失败");
+
+        let _guard = manager.begin_task("卡住的传输").expect("登记任务失败");
+
+        let started = std::time::Instant::now();
+        manager.shutdown(Duration::from_millis(100)).await.expect("关闭失败");
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(manager.in_flight_count(), 1);
+        assert_eq!(manager.state().await, AppState::Stopped);
+    }
+
     #[tokio::test]
     async fn test_app_config_default() {
         let config = AppConfig::default();
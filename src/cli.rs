@@ -0,0 +1,133 @@
+//! # 命令行模块
+//!
+//! 解析 `bey` 的子命令，不依赖编译特性即可执行常用操作。
+
+use crate::AppResult;
+use error::{ErrorInfo, ErrorCategory};
+
+/// 命令行用法说明
+pub const USAGE: &str = "\
+用法: bey [命令]
+
+不带命令时按编译特性启动 GUI、TUI 或无界面模式。
+
+命令:
+  serve                   以无界面服务模式运行，按 Ctrl+C 停止
+  status                  打印本设备信息和运行统计
+  send <设备ID> <消息>    向对等设备发送私信
+  export-identity         打印本设备证书（PEM格式）
+  help                    打印本帮助";
+
+/// 命令行子命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    /// 按编译特性启动默认界面
+    Default,
+    /// 以无界面服务模式运行
+    Serve,
+    /// 打印设备信息和统计
+    Status,
+    /// 发送私信
+    Send {
+        /// 对方设备ID
+        peer_id: String,
+        /// 消息内容
+        message: String,
+    },
+    /// 导出本设备证书
+    ExportIdentity,
+    /// 打印帮助
+    Help,
+}
+
+impl CliCommand {
+    /// 解析命令行参数
+    ///
+    /// # 参数
+    ///
+    /// * `args` - 命令行参数，不包含程序名
+    ///
+    /// # 返回值
+    ///
+    /// 返回解析出的命令，未知命令或参数不正确时返回错误
+    pub fn parse<I, S>(args: I) -> AppResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        let Some(command) = args.next() else {
+            return Ok(Self::Default);
+        };
+
+        let parsed = match command.as_str() {
+            "serve" => Self::Serve,
+            "status" => Self::Status,
+            "export-identity" => Self::ExportIdentity,
+            "help" | "-h" | "--help" => Self::Help,
+            "send" => {
+                let peer_id = args.next()
+                    .ok_or_else(|| missing_argument("send", "设备ID"))?;
+                // 剩余参数拼接为消息，无需对消息加引号
+                let message = args.collect::<Vec<_>>().join(" ");
+                if message.is_empty() {
+                    return Err(missing_argument("send", "消息"));
+                }
+                return Ok(Self::Send { peer_id, message });
+            }
+            unknown => {
+                return Err(ErrorInfo::new(2011, format!("未知命令: {}", unknown))
+                    .with_category(ErrorCategory::Validation));
+            }
+        };
+
+        if let Some(extra) = args.next() {
+            return Err(ErrorInfo::new(2012, format!("命令 {} 不接受参数: {}", command, extra))
+                .with_category(ErrorCategory::Validation));
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// 缺少命令参数的错误
+fn missing_argument(command: &str, argument: &str) -> ErrorInfo {
+    ErrorInfo::new(2012, format!("命令 {} 缺少参数: {}", command, argument))
+        .with_category(ErrorCategory::Validation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_simple_commands() {
+        let empty: [&str; 0] = [];
+        assert_eq!(CliCommand::parse(empty).expect("解析失败"), CliCommand::Default);
+        assert_eq!(CliCommand::parse(["serve"]).expect("解析失败"), CliCommand::Serve);
+        assert_eq!(CliCommand::parse(["status"]).expect("解析失败"), CliCommand::Status);
+        assert_eq!(CliCommand::parse(["export-identity"]).expect("解析失败"), CliCommand::ExportIdentity);
+        assert_eq!(CliCommand::parse(["--help"]).expect("解析失败"), CliCommand::Help);
+    }
+
+    #[test]
+    fn test_parse_send_joins_message() {
+        let command = CliCommand::parse(["send", "laptop", "hello", "there"]).expect("解析失败");
+        assert_eq!(command, CliCommand::Send {
+            peer_id: "laptop".to_string(),
+            message: "hello there".to_string(),
+        });
+
+        let err = CliCommand::parse(["send", "laptop"]).expect_err("缺少消息应失败");
+        assert_eq!(err.code(), 2012);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_and_extra_arguments() {
+        let err = CliCommand::parse(["frobnicate"]).expect_err("未知命令应失败");
+        assert_eq!(err.code(), 2011);
+
+        let err = CliCommand::parse(["status", "--verbose"]).expect_err("多余参数应失败");
+        assert_eq!(err.code(), 2012);
+    }
+}
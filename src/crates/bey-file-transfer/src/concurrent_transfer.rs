@@ -629,11 +629,15 @@ impl BandwidthController {
     }
 
     async fn acquire_tokens(&self, amount: u64) -> TransferResult<()> {
+        // 超过桶容量的请求分批扣减令牌，否则永远无法满足
+        let mut remaining = amount;
         loop {
             {
                 let mut tokens = self.tokens.lock();
-                if *tokens >= amount {
-                    *tokens -= amount;
+                let taken = std::cmp::min(*tokens, remaining);
+                *tokens -= taken;
+                remaining -= taken;
+                if remaining == 0 {
                     return Ok(());
                 }
             }
@@ -651,7 +655,11 @@ impl BandwidthController {
         let now = SystemTime::now();
 
         if let Ok(elapsed) = now.duration_since(*last_refill) {
-            let tokens_to_add = elapsed.as_secs() * self.refill_rate;
+            // 按微秒计算，避免每次间隔不足一秒时补充量被截断为零
+            let tokens_to_add = (elapsed.as_micros() * self.refill_rate as u128 / 1_000_000) as u64;
+            if tokens_to_add == 0 {
+                return;
+            }
 
             let mut tokens = self.tokens.lock();
             *tokens = std::cmp::min(*tokens + tokens_to_add, self.bucket_capacity);
@@ -688,7 +696,7 @@ impl TaskScheduler {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{TransferTask, TransferDirection, TransferConfig, TransferMetadata};
+    use crate::{TransferTask, TransferDirection, TransferConfig, TransferMetadata, TransferOptions};
     use std::path::PathBuf;
     use std::sync::Arc;
     use std::time::SystemTime;
@@ -725,6 +733,7 @@ mod tests {
                 modified_at: SystemTime::now(),
                 properties: std::collections::HashMap::new(),
             },
+            options: TransferOptions::default(),
         };
 
         let result = concurrent_transfer.start_transfer(task).await;
@@ -778,6 +787,7 @@ mod tests {
                     modified_at: SystemTime::now(),
                     properties: std::collections::HashMap::new(),
                 },
+                options: TransferOptions::default(),
             },
         };
 
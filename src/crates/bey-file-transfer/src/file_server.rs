@@ -925,7 +925,6 @@ impl BeyFileServer {
 mod tests {
     use super::*;
     use tempfile::TempDir;
-    use tokio::time::sleep;
 
     #[tokio::test]
     async fn test_file_server_config() {
@@ -949,7 +948,7 @@ mod tests {
 
         let server = server_result.unwrap();
         let stats = server.get_statistics().await;
-        assert_eq!(stats.total_connections, 0);
+        assert_eq!(stats.total_connections.load(std::sync::atomic::Ordering::Relaxed), 0);
     }
 
     #[tokio::test]
@@ -962,7 +961,7 @@ mod tests {
             ..Default::default()
         };
 
-        let mut server = BeyFileServer::new(config).await.unwrap();
+        let server = BeyFileServer::new(config).await.unwrap();
 
         // 创建测试文件
         let test_file_path = server.config.storage_root.join("test.txt");
@@ -970,7 +969,8 @@ mod tests {
         tokio::fs::write(&test_file_path, test_data).await.unwrap();
 
         // 测试文件存在检查
-        let response = server.handle_file_exists("test.txt".to_string(),
+        let response = server.handle_file_operation(
+            FileOperation::FileExists { path: "test.txt".to_string() },
             "127.0.0.1:8080".parse().unwrap(),
             "test-device".to_string()).await;
 
@@ -982,7 +982,8 @@ mod tests {
         }
 
         // 测试获取文件信息
-        let response = server.handle_get_file_info("test.txt".to_string(),
+        let response = server.handle_file_operation(
+            FileOperation::GetFileInfo { path: "test.txt".to_string() },
             "127.0.0.1:8080".parse().unwrap(),
             "test-device".to_string()).await;
 
@@ -1008,7 +1009,8 @@ mod tests {
         let server = BeyFileServer::new(config).await.unwrap();
 
         // 执行一个文件操作
-        let _ = server.handle_file_exists("nonexistent.txt".to_string(),
+        let _ = server.handle_file_operation(
+            FileOperation::FileExists { path: "nonexistent.txt".to_string() },
             "127.0.0.1:8080".parse().unwrap(),
             "test-device".to_string()).await;
 
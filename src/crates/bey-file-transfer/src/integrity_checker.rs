@@ -625,7 +625,7 @@ mod tests {
     use std::sync::Arc;
     use std::time::SystemTime;
     use tempfile::NamedTempFile;
-    use tokio::io::AsyncWriteExt;
+    use std::io::Write;
 
     #[tokio::test]
     async fn test_integrity_checker_creation() {
@@ -8,6 +8,7 @@ mod progress_tracker;
 mod integrity_checker;
 mod resume_manager;
 mod security_manager;
+mod roles;
 mod concurrent_transfer;
 mod storage;
 mod file_server;
@@ -22,13 +23,14 @@ pub use transfer_engine::TransferEngine;
 pub use transfer_queue::TransferQueue;
 pub use progress_tracker::ProgressTracker;
 pub use integrity_checker::IntegrityChecker;
-pub use resume_manager::ResumeManager;
+pub use resume_manager::{ResumeManager, CHECKPOINT_DIR_NAME};
 pub use security_manager::SecurityManager;
+pub use roles::{DeviceRoleTemplates, Permission, Role};
 pub use concurrent_transfer::{ConcurrentTransfer, TransferExecutionResult, TransferStatisticsSnapshot};
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tokio::sync::broadcast;
@@ -44,8 +46,9 @@ use dashmap::DashMap;
 pub struct TransferManager {
     /// 传输任务映射
     tasks: Arc<DashMap<String, TransferTask>>,
+    /// 存储接口
+    storage: Arc<dyn StorageInterface>,
     /// 传输引擎
-    #[allow(dead_code)]
     engine: Arc<TransferEngine>,
     /// 进度跟踪器
     #[allow(dead_code)]
@@ -102,15 +105,49 @@ impl TransferManager {
     /// 返回传输管理器实例或错误信息
     #[instrument(skip(config))]
     pub async fn new(config: TransferConfig) -> TransferResult<Self> {
+        Self::with_storage_root(config, "./transfer_storage").await
+    }
+
+    /// 创建使用指定存储根目录的传输管理器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 传输配置
+    /// * `storage_root` - 本地存储根目录，断点保存在其下的 [`CHECKPOINT_DIR_NAME`] 子目录
+    ///
+    /// # 返回
+    ///
+    /// 返回传输管理器实例或错误信息
+    #[instrument(skip(config, storage_root))]
+    pub async fn with_storage_root(config: TransferConfig, storage_root: impl AsRef<Path>) -> TransferResult<Self> {
+        let storage = Arc::new(crate::storage::LocalStorage::new(storage_root.as_ref(), config.buffer_size).await?);
+        Self::with_storage(config, storage, storage_root).await
+    }
+
+    /// 创建使用指定存储接口的传输管理器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 传输配置
+    /// * `storage` - 存储接口实现
+    /// * `storage_root` - 存储根目录，断点保存在其下的 [`CHECKPOINT_DIR_NAME`] 子目录
+    ///
+    /// # 返回
+    ///
+    /// 返回传输管理器实例或错误信息
+    #[instrument(skip(config, storage, storage_root))]
+    pub async fn with_storage(
+        config: TransferConfig,
+        storage: Arc<dyn StorageInterface>,
+        storage_root: impl AsRef<Path>,
+    ) -> TransferResult<Self> {
         info!("创建文件传输管理器，配置: {:?}", config);
 
         let config = Arc::new(config);
 
-        // 创建存储接口（使用默认的本地存储实现）
-        let storage = Arc::new(crate::storage::LocalStorage::new("./transfer_storage", config.buffer_size).await?);
-
         // 创建传输引擎
-        let engine = Arc::new(TransferEngine::new(storage.clone(), (*config).clone()).await?);
+        let checkpoint_dir = storage_root.as_ref().join(CHECKPOINT_DIR_NAME);
+        let engine = Arc::new(TransferEngine::with_checkpoint_dir(storage.clone(), (*config).clone(), checkpoint_dir).await?);
 
         // 创建进度跟踪器
         let progress_tracker = Arc::new(ProgressTracker::new());
@@ -124,6 +161,7 @@ impl TransferManager {
         // 创建管理器实例
         let manager = Self {
             tasks: Arc::new(DashMap::new()),
+            storage,
             engine,
             progress_tracker,
             integrity_checker,
@@ -153,35 +191,29 @@ impl TransferManager {
         target_path: PathBuf,
         direction: TransferDirection,
     ) -> TransferResult<String> {
-        let task_id = format!("transfer-{}", uuid::Uuid::new_v4());
+        let file_info = self.storage.get_file_info(&source_path).await?;
+        let metadata = TransferMetadata {
+            mime_type: "application/octet-stream".to_string(),
+            file_extension: source_path.extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("bin")
+                .to_string(),
+            created_at: SystemTime::now(),
+            modified_at: file_info.modified,
+            properties: HashMap::new(),
+        };
 
         // 创建传输任务
-        let task = TransferTask {
-            task_id: task_id.clone(),
+        let task = self.engine.create_transfer(
+            &source_path,
+            &target_path,
             direction,
-            source_path: source_path.clone(),
-            target_path: target_path.clone(),
-            file_size: std::fs::metadata(&source_path)?.len(),
-            transferred_size: 0,
-            status: TransferStatus::Pending,
-            created_at: SystemTime::now(),
-            updated_at: SystemTime::now(),
-            completed_at: None,
-            file_hash: None,
-            config: (*self.config).clone(),
-            metadata: TransferMetadata {
-                mime_type: "application/octet-stream".to_string(),
-                file_extension: source_path.extension()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("bin")
-                    .to_string(),
-                created_at: SystemTime::now(),
-                modified_at: std::fs::metadata(&source_path)?.modified()?,
-                properties: HashMap::new(),
-            },
-            options: TransferOptions::default(),
-        };
+            metadata,
+            TransferOptions::default(),
+        ).await?;
+        let task = task.read().await.clone();
 
+        let task_id = task.task_id.clone();
         let file_size = task.file_size;
         self.tasks.insert(task_id.clone(), task);
         let _progress_rx = self.progress_tracker.register_task(task_id.clone(), file_size).await?;
@@ -192,6 +224,8 @@ impl TransferManager {
 
     /// 开始传输任务
     ///
+    /// 对于 [`TransferManager::recover_pending`] 恢复的任务，从断点继续传输。
+    ///
     /// # 参数
     ///
     /// * `task_id` - 任务ID
@@ -203,25 +237,60 @@ impl TransferManager {
     pub async fn start_transfer(&self, task_id: &str) -> TransferResult<()> {
         info!("开始传输任务: {}", task_id);
 
-        let mut task = self.tasks.get_mut(task_id)
+        let task = self.engine.get_transfer_task(task_id).await?
             .ok_or_else(|| ErrorInfo::new(9001, format!("任务不存在: {}", task_id))
                 .with_category(ErrorCategory::Validation)
                 .with_severity(ErrorSeverity::Error))?;
 
-        task.status = TransferStatus::Transferring;
-        task.updated_at = SystemTime::now();
+        let result = self.engine.start_transfer(task.clone()).await;
 
-        // 这里应该启动实际的传输逻辑
-        // 为了演示，我们暂时直接标记为完成
-        task.status = TransferStatus::Completed;
-        task.updated_at = SystemTime::now();
-        task.completed_at = Some(SystemTime::now());
-        task.transferred_size = task.file_size;
+        // 同步任务快照
+        let snapshot = task.read().await.clone();
+        self.tasks.insert(task_id.to_string(), snapshot);
 
+        result?;
         info!("传输任务完成: {}", task_id);
         Ok(())
     }
 
+    /// 恢复进程重启前未完成的传输任务
+    ///
+    /// 应在启动时调用。恢复的任务处于 `Resuming` 状态，
+    /// 调用 [`TransferManager::start_transfer`] 即可从断点继续传输。
+    ///
+    /// # 返回
+    ///
+    /// 返回恢复的任务ID列表或错误信息
+    #[instrument(skip(self))]
+    pub async fn recover_pending(&self) -> TransferResult<Vec<String>> {
+        let mut task_ids = Vec::new();
+
+        for task in self.engine.recover_pending().await? {
+            let task = task.read().await.clone();
+            let task_id = task.task_id.clone();
+            let file_size = task.file_size;
+            self.tasks.insert(task_id.clone(), task);
+            let _progress_rx = self.progress_tracker.register_task(task_id.clone(), file_size).await?;
+            task_ids.push(task_id);
+        }
+
+        info!("恢复 {} 个未完成的传输任务", task_ids.len());
+        Ok(task_ids)
+    }
+
+    /// 获取传输任务信息
+    ///
+    /// # 参数
+    ///
+    /// * `task_id` - 任务ID
+    ///
+    /// # 返回
+    ///
+    /// 返回任务信息，任务不存在时返回 `None`
+    pub fn get_task(&self, task_id: &str) -> Option<TransferTask> {
+        self.tasks.get(task_id).map(|task| task.clone())
+    }
+
     /// 订阅传输进度
     ///
     /// # 参数
@@ -317,4 +386,106 @@ mod tests {
         assert_eq!(progress.transferred_bytes, 512);
         assert_eq!(progress.total_bytes, 1024);
     }
-}
\ No newline at end of file
+
+    /// 写入指定块数后模拟网络中断的存储
+    struct InterruptingStorage {
+        inner: LocalStorage,
+        remaining_writes: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl StorageInterface for InterruptingStorage {
+        async fn read_chunk(&self, path: &Path, offset: u64, size: usize) -> TransferResult<bytes::Bytes> {
+            self.inner.read_chunk(path, offset, size).await
+        }
+
+        async fn write_chunk(&self, path: &Path, offset: u64, data: bytes::Bytes) -> TransferResult<()> {
+            let allowed = self.remaining_writes.fetch_update(
+                std::sync::atomic::Ordering::SeqCst,
+                std::sync::atomic::Ordering::SeqCst,
+                |remaining| remaining.checked_sub(1),
+            );
+            if allowed.is_err() {
+                return Err(ErrorInfo::new(9999, "连接中断".to_string()));
+            }
+            self.inner.write_chunk(path, offset, data).await
+        }
+
+        async fn get_file_info(&self, path: &Path) -> TransferResult<FileInfo> {
+            self.inner.get_file_info(path).await
+        }
+
+        async fn create_dir(&self, path: &Path) -> TransferResult<()> {
+            self.inner.create_dir(path).await
+        }
+
+        async fn delete_file(&self, path: &Path) -> TransferResult<()> {
+            self.inner.delete_file(path).await
+        }
+
+        async fn exists(&self, path: &Path) -> TransferResult<bool> {
+            self.inner.exists(path).await
+        }
+
+        async fn list_directory(&self, path: &Path) -> TransferResult<Vec<DirectoryEntry>> {
+            self.inner.list_directory(path).await
+        }
+
+        async fn remove_dir(&self, path: &Path) -> TransferResult<()> {
+            self.inner.remove_dir(path).await
+        }
+
+        async fn get_directory_size(&self, path: &Path) -> TransferResult<u64> {
+            self.inner.get_directory_size(path).await
+        }
+    }
+
+    async fn interrupting_manager(root: &Path, config: &TransferConfig, writes: usize) -> TransferManager {
+        let storage = Arc::new(InterruptingStorage {
+            inner: LocalStorage::new(root, config.buffer_size).await.expect("创建存储失败"),
+            remaining_writes: std::sync::atomic::AtomicUsize::new(writes),
+        });
+        TransferManager::with_storage(config.clone(), storage, root).await.expect("创建传输管理器失败")
+    }
+
+    #[tokio::test]
+    async fn test_recover_pending_resumes_after_restart() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = TransferConfig {
+            chunk_size: 1024,
+            ..TransferConfig::default()
+        };
+        let content: Vec<u8> = (0..10 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(temp_dir.path().join("source.bin"), &content).expect("写入源文件失败");
+
+        // 写入4个块后连接中断
+        let task_id = {
+            let manager = interrupting_manager(temp_dir.path(), &config, 4).await;
+            let task_id = manager.create_transfer(
+                PathBuf::from("source.bin"),
+                PathBuf::from("target.bin"),
+                TransferDirection::Upload,
+            ).await.expect("创建传输任务失败");
+            assert!(manager.start_transfer(&task_id).await.is_err(), "中断的传输应该失败");
+            task_id
+        };
+
+        // 模拟进程重启：同一目录上的新管理器只允许再写入剩余的6个块
+        let manager = interrupting_manager(temp_dir.path(), &config, 6).await;
+        let recovered = manager.recover_pending().await.expect("恢复任务失败");
+        assert_eq!(recovered, vec![task_id.clone()]);
+
+        let task = manager.get_task(&task_id).expect("恢复的任务应该存在");
+        assert_eq!(task.status, TransferStatus::Resuming);
+        assert_eq!(task.transferred_size, 4 * 1024);
+
+        manager.start_transfer(&task_id).await.expect("续传失败");
+        let task = manager.get_task(&task_id).expect("任务应该存在");
+        assert_eq!(task.status, TransferStatus::Completed);
+        assert_eq!(std::fs::read(temp_dir.path().join("target.bin")).expect("读取目标文件失败"), content);
+
+        // 完成的任务不再被恢复
+        let manager = interrupting_manager(temp_dir.path(), &config, 0).await;
+        assert!(manager.recover_pending().await.expect("恢复任务失败").is_empty());
+    }
+}
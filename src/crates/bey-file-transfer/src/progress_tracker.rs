@@ -146,7 +146,7 @@ struct ProgressTrackerConfig {
     speed_history_size: usize,
     /// 进度历史窗口大小
     progress_history_size: usize,
-    /// 速度平滑因子（0.0-1.0）
+    /// 速度平滑因子（0.0-1.0，新样本在指数加权平均中的权重）
     speed_smoothing_factor: f64,
     /// 窗口大小
     window_size: Duration,
@@ -158,7 +158,7 @@ impl Default for ProgressTrackerConfig {
             update_interval_ms: 1000, // 1秒更新间隔
             speed_history_size: 60,   // 保留60个速度记录
             progress_history_size: 100, // 保留100个进度记录
-            speed_smoothing_factor: 0.3, // 30%平滑因子
+            speed_smoothing_factor: 0.3, // 新样本权重30%
             window_size: Duration::from_secs(60), // 60秒窗口
         }
     }
@@ -243,6 +243,17 @@ impl ProgressTracker {
         task_id: &str,
         transferred_bytes: u64,
         status: TransferStatus,
+    ) -> TransferResult<()> {
+        self.update_progress_at(task_id, transferred_bytes, status, SystemTime::now()).await
+    }
+
+    /// 在指定时间点更新传输进度
+    async fn update_progress_at(
+        &self,
+        task_id: &str,
+        transferred_bytes: u64,
+        status: TransferStatus,
+        current_time: SystemTime,
     ) -> TransferResult<()> {
         debug!("更新传输进度，任务ID: {}, 已传输: {} 字节", task_id, transferred_bytes);
 
@@ -263,7 +274,7 @@ impl ProgressTracker {
         // 更新进度数据
         progress_state.transferred_bytes.store(transferred_bytes, std::sync::atomic::Ordering::Relaxed);
         *progress_state.status.write().await = status;
-        *progress_state.last_update.write().await = SystemTime::now();
+        *progress_state.last_update.write().await = current_time;
 
         // 计算当前进度
         let percentage = if progress_state.total_bytes > 0 {
@@ -272,18 +283,12 @@ impl ProgressTracker {
             0.0
         };
 
-        // 计算传输速度
-        let current_time = SystemTime::now();
-        let elapsed = current_time.duration_since(progress_state.start_time).unwrap_or_default();
-        let speed = if elapsed.as_secs() > 0 {
-            transferred_bytes / elapsed.as_secs()
-        } else {
-            0
+        // 计算本次更新的瞬时速度，并做指数加权平滑
+        let speed = match Self::instant_speed(&progress_state, transferred_bytes, current_time) {
+            Some(instant_speed) => self.update_speed_history(&progress_state, instant_speed, current_time).await,
+            None => Self::smoothed_speed(&progress_state),
         };
 
-        // 更新速度历史
-        self.update_speed_history(&progress_state, speed, current_time).await;
-
         // 更新进度历史
         self.update_progress_history(&progress_state, percentage, transferred_bytes, speed, current_time).await;
 
@@ -294,7 +299,7 @@ impl ProgressTracker {
             transferred_bytes,
             total_bytes: progress_state.total_bytes,
             speed,
-            eta_seconds: self.calculate_eta(&progress_state).await,
+            eta_seconds: Self::calculate_eta(&progress_state, transferred_bytes, speed),
             error: progress_state.error_info.read().await.clone(),
             updated_at: current_time,
         };
@@ -364,12 +369,7 @@ impl ProgressTracker {
                 0.0
             };
 
-            let elapsed = SystemTime::now().duration_since(progress_state.start_time).unwrap_or_default();
-            let speed = if elapsed.as_secs() > 0 {
-                transferred_bytes / elapsed.as_secs()
-            } else {
-                0
-            };
+            let speed = Self::smoothed_speed(&progress_state);
 
             let progress = TransferProgress {
                 task_id: task_id.to_string(),
@@ -377,7 +377,7 @@ impl ProgressTracker {
                 transferred_bytes,
                 total_bytes: progress_state.total_bytes,
                 speed,
-                eta_seconds: self.calculate_eta(&progress_state).await,
+                eta_seconds: Self::calculate_eta(&progress_state, transferred_bytes, speed),
                 error: progress_state.error_info.read().await.clone(),
                 updated_at: *progress_state.last_update.read().await,
             };
@@ -477,14 +477,41 @@ impl ProgressTracker {
 
     // 私有方法
 
+    /// 计算自上次更新以来的瞬时速度
+    ///
+    /// 两次更新时间间隔为零时无法计算，返回 `None`
+    fn instant_speed(progress_state: &ProgressState, transferred_bytes: u64, timestamp: SystemTime) -> Option<u64> {
+        let (last_bytes, last_time) = progress_state.progress_history.lock()
+            .back()
+            .map(|snapshot| (snapshot.transferred_bytes, snapshot.timestamp))
+            .unwrap_or((0, progress_state.start_time));
+
+        let interval = timestamp.duration_since(last_time).ok()?.as_secs_f64();
+        if interval <= 0.0 {
+            return None;
+        }
+
+        Some((transferred_bytes.saturating_sub(last_bytes) as f64 / interval) as u64)
+    }
+
+    /// 当前的平滑速度
+    fn smoothed_speed(progress_state: &ProgressState) -> u64 {
+        progress_state.speed_history.lock()
+            .back()
+            .map(|record| record.smooth_speed)
+            .unwrap_or(0)
+    }
+
     /// 更新速度历史记录
-    async fn update_speed_history(&self, progress_state: &ProgressState, instant_speed: u64, timestamp: SystemTime) {
+    ///
+    /// 返回指数加权平滑后的速度
+    async fn update_speed_history(&self, progress_state: &ProgressState, instant_speed: u64, timestamp: SystemTime) -> u64 {
         let mut speed_history = progress_state.speed_history.lock();
 
-        // 计算平滑速度
+        // 计算平滑速度（指数加权移动平均）
         let smooth_speed = if let Some(last_record) = speed_history.back() {
             let smoothing_factor = self.config.speed_smoothing_factor;
-            (instant_speed as f64 * (1.0 - smoothing_factor) + last_record.smooth_speed as f64 * smoothing_factor) as u64
+            (instant_speed as f64 * smoothing_factor + last_record.smooth_speed as f64 * (1.0 - smoothing_factor)) as u64
         } else {
             instant_speed
         };
@@ -501,6 +528,8 @@ impl ProgressTracker {
         if speed_history.len() > self.config.speed_history_size {
             speed_history.pop_front();
         }
+
+        smooth_speed
     }
 
     /// 更新进度历史记录
@@ -529,31 +558,16 @@ impl ProgressTracker {
         }
     }
 
-    /// 计算预估剩余时间
-    async fn calculate_eta(&self, progress_state: &ProgressState) -> Option<u64> {
-        let transferred_bytes = progress_state.transferred_bytes.load(std::sync::atomic::Ordering::Relaxed);
-
-        if transferred_bytes == 0 || progress_state.total_bytes == 0 {
+    /// 根据平滑速度计算预估剩余时间
+    ///
+    /// 速度为零时无法估算，返回 `None`
+    fn calculate_eta(progress_state: &ProgressState, transferred_bytes: u64, smooth_speed: u64) -> Option<u64> {
+        if smooth_speed == 0 || progress_state.total_bytes == 0 {
             return None;
         }
 
-        // 使用最近的平均速度计算ETA
-        let speed_history = progress_state.speed_history.lock();
-        if let Some(avg_speed) = speed_history.iter()
-            .rev()
-            .take(5) // 使用最近5个记录
-            .map(|r| r.smooth_speed)
-            .sum::<u64>()
-            .checked_div(speed_history.len().min(5) as u64)
-        {
-            if avg_speed > 0 {
-                let remaining_bytes = progress_state.total_bytes.saturating_sub(transferred_bytes);
-                let eta_seconds = remaining_bytes / avg_speed;
-                return Some(eta_seconds);
-            }
-        }
-
-        None
+        let remaining_bytes = progress_state.total_bytes.saturating_sub(transferred_bytes);
+        Some(remaining_bytes / smooth_speed)
     }
 
     /// 广播进度更新
@@ -634,7 +648,7 @@ mod tests {
         let task_id = "test-task-001".to_string();
         let total_bytes = 1024 * 1024; // 1MB
 
-        let mut receiver = tracker.register_task(task_id.clone(), total_bytes).await.unwrap();
+        let receiver = tracker.register_task(task_id.clone(), total_bytes).await.unwrap();
 
         // 验证任务已注册
         assert_eq!(tracker.progress_data.len(), 1);
@@ -661,7 +675,6 @@ mod tests {
         assert_eq!(progress.transferred_bytes, 500);
         assert_eq!(progress.total_bytes, total_bytes);
         assert_eq!(progress.percentage, 50.0);
-        assert_eq!(progress.status, TransferStatus::Transferring);
     }
 
     #[tokio::test]
@@ -678,7 +691,7 @@ mod tests {
 
         // 验证错误信息
         let progress = tracker.get_progress(&task_id).await.unwrap().unwrap();
-        assert_eq!(progress.error, Some(error_message));
+        assert_eq!(progress.error, Some(error_message.clone()));
 
         // 验证接收到错误通知
         let notification = receiver.recv().await.unwrap();
@@ -729,6 +742,63 @@ mod tests {
         assert_eq!(tracker.progress_data.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_speed_smoothing_with_varying_intervals() {
+        let tracker = ProgressTracker::new();
+        let task_id = "smoothing-test".to_string();
+        let total_bytes = 100 * 1024 * 1024;
+        let chunk_size = 1024 * 1024;
+
+        tracker.register_task(task_id.clone(), total_bytes).await.unwrap();
+        let start_time = tracker.progress_data.get(&task_id).unwrap().start_time;
+
+        // 每个块大小相同，但到达间隔在快慢之间交替
+        let intervals_ms = [100u64, 1000, 100, 1000, 200, 900, 100, 1000];
+        let mut elapsed_ms = 0;
+        let mut transferred = 0;
+        let mut raw_speeds = Vec::new();
+        let mut smoothed_speeds = Vec::new();
+
+        for interval_ms in intervals_ms {
+            elapsed_ms += interval_ms;
+            transferred += chunk_size;
+            let timestamp = start_time + Duration::from_millis(elapsed_ms);
+
+            tracker.update_progress_at(&task_id, transferred, TransferStatus::Transferring, timestamp).await.unwrap();
+
+            raw_speeds.push(chunk_size * 1000 / interval_ms);
+            smoothed_speeds.push(tracker.get_progress(&task_id).await.unwrap().unwrap().speed);
+        }
+
+        let max_swing = |speeds: &[u64]| speeds.windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]))
+            .max()
+            .unwrap_or(0);
+
+        assert!(max_swing(&smoothed_speeds) < max_swing(&raw_speeds) / 2,
+                "平滑速度波动 {} 应明显小于原始速度波动 {}", max_swing(&smoothed_speeds), max_swing(&raw_speeds));
+
+        // ETA 基于平滑速度计算
+        let progress = tracker.get_progress(&task_id).await.unwrap().unwrap();
+        assert!(progress.speed > 0);
+        assert_eq!(progress.eta_seconds, Some((total_bytes - transferred) / progress.speed));
+    }
+
+    #[tokio::test]
+    async fn test_eta_none_when_rate_is_zero() {
+        let tracker = ProgressTracker::new();
+        let task_id = "stalled-task".to_string();
+
+        tracker.register_task(task_id.clone(), 1000).await.unwrap();
+        let start_time = tracker.progress_data.get(&task_id).unwrap().start_time;
+
+        tracker.update_progress_at(&task_id, 0, TransferStatus::Transferring, start_time + Duration::from_secs(1)).await.unwrap();
+
+        let progress = tracker.get_progress(&task_id).await.unwrap().unwrap();
+        assert_eq!(progress.speed, 0);
+        assert_eq!(progress.eta_seconds, None);
+    }
+
     #[tokio::test]
     async fn test_performance_stats() {
         let tracker = ProgressTracker::new();
@@ -19,7 +19,10 @@ use tokio::fs;
 use tracing::{info, warn, error, debug, instrument};
 use parking_lot::Mutex;
 use dashmap::DashMap;
-use crate::{TransferCheckpoint, TransferConfig, TransferResult, ChunkInfo};
+use crate::{TransferCheckpoint, TransferConfig, TransferResult, TransferTask, ChunkInfo};
+
+/// 存储根目录下保存断点文件的子目录名
+pub const CHECKPOINT_DIR_NAME: &str = ".checkpoints";
 
 /// 断点续传管理器
 ///
@@ -83,6 +86,8 @@ impl ResumeManager {
 
     /// 保存传输断点信息
     ///
+    /// 已保存过任务信息的断点会保留原任务信息。
+    ///
     /// # 参数
     ///
     /// * `task_id` - 传输任务ID
@@ -97,29 +102,61 @@ impl ResumeManager {
         task_id: &str,
         transferred_chunks: Vec<ChunkInfo>,
     ) -> TransferResult<()> {
-        let _lock = self.cache_lock.lock();
+        let task = self.checkpoints.get(task_id).and_then(|checkpoint| checkpoint.task.clone());
+        self.store_checkpoint(task_id, transferred_chunks, task).await
+    }
+
+    /// 保存带任务信息的传输断点
+    ///
+    /// 断点写入磁盘后才返回，进程重启后可通过 [`ResumeManager::get_all_checkpoints`] 恢复任务。
+    ///
+    /// # 参数
+    ///
+    /// * `task` - 传输任务当前状态
+    /// * `transferred_chunks` - 已传输的数据块信息
+    ///
+    /// # 返回
+    ///
+    /// 返回成功或错误信息
+    #[instrument(skip(self, task, transferred_chunks), fields(task_id = task.task_id))]
+    pub async fn save_task_checkpoint(
+        &self,
+        task: &TransferTask,
+        transferred_chunks: Vec<ChunkInfo>,
+    ) -> TransferResult<()> {
+        self.store_checkpoint(&task.task_id, transferred_chunks, Some(task.clone())).await
+    }
 
+    /// 保存断点信息到内存和磁盘
+    async fn store_checkpoint(
+        &self,
+        task_id: &str,
+        transferred_chunks: Vec<ChunkInfo>,
+        task: Option<TransferTask>,
+    ) -> TransferResult<()> {
         info!("保存传输断点信息，任务ID: {}, 已传输块数: {}", task_id, transferred_chunks.len());
 
         // 创建断点信息
         let checkpoint = TransferCheckpoint {
             task_id: task_id.to_string(),
-            transferred_chunks: transferred_chunks.clone(),
+            transferred_bytes: transferred_chunks.iter().map(|chunk| chunk.size as u64).sum(),
+            transferred_chunks,
             config: self.config.as_ref().clone(),
+            task,
             created_at: SystemTime::now(),
         };
 
         // 保存到内存
-        self.checkpoints.insert(task_id.to_string(), checkpoint.clone());
-
-        // 异步保存到磁盘
-        let storage_dir = self.storage_dir.clone();
-        let task_id_owned = task_id.to_string();
-        tokio::spawn(async move {
-            if let Err(e) = Self::save_checkpoint_to_disk(&storage_dir, &task_id_owned, &checkpoint).await {
-                error!("保存断点信息到磁盘失败: {}", e);
-            }
-        });
+        {
+            let _lock = self.cache_lock.lock();
+            self.checkpoints.insert(task_id.to_string(), checkpoint.clone());
+        }
+
+        // 保存到磁盘，确保进程重启后断点仍然可用
+        if let Err(e) = Self::save_checkpoint_to_disk(&self.storage_dir, task_id, &checkpoint).await {
+            error!("保存断点信息到磁盘失败: {}", e);
+            return Err(e);
+        }
 
         debug!("断点信息保存完成，任务ID: {}", task_id);
         Ok(())
@@ -169,12 +206,13 @@ impl ResumeManager {
     /// 返回成功或错误信息
     #[instrument(skip(self), fields(task_id))]
     pub async fn delete_checkpoint(&self, task_id: &str) -> TransferResult<()> {
-        let _lock = self.cache_lock.lock();
-
         info!("删除传输断点信息，任务ID: {}", task_id);
 
         // 从内存缓存删除
-        self.checkpoints.remove(task_id);
+        {
+            let _lock = self.cache_lock.lock();
+            self.checkpoints.remove(task_id);
+        }
 
         // 从磁盘删除
         let checkpoint_file = self.storage_dir.join(format!("{}.checkpoint", task_id));
@@ -373,8 +411,13 @@ impl ResumeManager {
             .with_severity(ErrorSeverity::Error)
         })?;
 
-        // 原子写入文件
-        fs::write(&file_path, serialized).await.map_err(|e| {
+        // 原子写入文件：先写临时文件再重命名，避免进程中断时留下半个断点文件
+        let temp_path = file_path.with_extension("checkpoint.tmp");
+        let write_result = match fs::write(&temp_path, serialized).await {
+            Ok(()) => fs::rename(&temp_path, &file_path).await,
+            Err(e) => Err(e),
+        };
+        write_result.map_err(|e| {
             ErrorInfo::new(
                 7006,
                 format!("写入断点文件失败: {}", e)
@@ -438,7 +481,6 @@ mod tests {
     use super::*;
     use crate::{ChunkInfo, TransferConfig};
     use tempfile::tempdir;
-    use std::time::UNIX_EPOCH;
 
     #[tokio::test]
     async fn test_resume_manager_creation() {
@@ -498,7 +540,9 @@ mod tests {
                     timestamp: SystemTime::now(),
                 }
             ],
+            transferred_bytes: 1024,
             config: TransferConfig::default(),
+            task: None,
             created_at: SystemTime::now(),
         };
 
@@ -517,7 +561,9 @@ mod tests {
                     timestamp: SystemTime::now(),
                 }
             ],
+            transferred_bytes: 1024,
             config: TransferConfig::default(),
+            task: None,
             created_at: SystemTime::now(),
         };
 
@@ -569,7 +615,9 @@ mod tests {
         let expired_checkpoint = TransferCheckpoint {
             task_id: "expired-task".to_string(),
             transferred_chunks: vec![],
+            transferred_bytes: 0,
             config: TransferConfig::default(),
+            task: None,
             created_at: expired_time,
         };
 
@@ -0,0 +1,141 @@
+//! # 角色与权限
+//!
+//! 定义内置角色及其权限集合，并按设备类型为新设备分配默认角色。
+//! 不同类型设备的可信程度不同，例如移动设备不应管理证书，
+//! 默认映射可通过 [`DeviceRoleTemplates::with_role`] 覆盖。
+
+use bey_types::DeviceType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 权限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    /// 上传文件
+    FileUpload,
+    /// 下载文件
+    FileDownload,
+    /// 删除文件
+    FileDelete,
+    /// 管理存储
+    StorageManage,
+    /// 管理证书
+    CertificateManage,
+}
+
+impl Permission {
+    /// 解析权限字符串
+    ///
+    /// # 参数
+    ///
+    /// * `permission` - 权限字符串，如 `read`、`file_upload`、`storage_manage`
+    ///
+    /// # 返回
+    ///
+    /// 返回对应的权限，未知的权限字符串返回 `None`
+    pub fn parse(permission: &str) -> Option<Self> {
+        match permission {
+            "read" | "file_read" | "download" | "file_download" => Some(Self::FileDownload),
+            "write" | "file_write" | "upload" | "file_upload" => Some(Self::FileUpload),
+            "delete" | "file_delete" | "execute" | "file_execute" => Some(Self::FileDelete),
+            "storage_manage" => Some(Self::StorageManage),
+            "certificate_manage" => Some(Self::CertificateManage),
+            _ => None,
+        }
+    }
+}
+
+/// 内置角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Role {
+    /// 管理员，拥有全部权限
+    Admin,
+    /// 运维，可管理存储但不能管理证书
+    Operator,
+    /// 普通用户，可上传、下载和删除文件
+    User,
+    /// 访客，只能下载文件
+    Guest,
+}
+
+impl Role {
+    /// 角色拥有的权限
+    pub fn permissions(&self) -> &'static [Permission] {
+        match self {
+            Self::Admin => &[
+                Permission::FileUpload,
+                Permission::FileDownload,
+                Permission::FileDelete,
+                Permission::StorageManage,
+                Permission::CertificateManage,
+            ],
+            Self::Operator => &[
+                Permission::FileUpload,
+                Permission::FileDownload,
+                Permission::FileDelete,
+                Permission::StorageManage,
+            ],
+            Self::User => &[
+                Permission::FileUpload,
+                Permission::FileDownload,
+                Permission::FileDelete,
+            ],
+            Self::Guest => &[Permission::FileDownload],
+        }
+    }
+
+    /// 角色是否拥有指定权限
+    ///
+    /// # 参数
+    ///
+    /// * `permission` - 权限
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+/// 设备类型到默认角色的映射
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceRoleTemplates {
+    /// 设备类型 -> 默认角色
+    roles: HashMap<DeviceType, Role>,
+}
+
+impl Default for DeviceRoleTemplates {
+    fn default() -> Self {
+        let roles = HashMap::from([
+            (DeviceType::Desktop, Role::Admin),
+            (DeviceType::Laptop, Role::Admin),
+            (DeviceType::Mobile, Role::User),
+            (DeviceType::Server, Role::Operator),
+            (DeviceType::Embedded, Role::Guest),
+        ]);
+        Self { roles }
+    }
+}
+
+impl DeviceRoleTemplates {
+    /// 设置设备类型的默认角色
+    ///
+    /// # 参数
+    ///
+    /// * `device_type` - 设备类型
+    /// * `role` - 该类型设备的默认角色
+    pub fn with_role(mut self, device_type: DeviceType, role: Role) -> Self {
+        self.roles.insert(device_type, role);
+        self
+    }
+
+    /// 获取设备类型的默认角色
+    ///
+    /// # 参数
+    ///
+    /// * `device_type` - 设备类型
+    ///
+    /// # 返回
+    ///
+    /// 返回映射的角色，未配置的设备类型返回权限最小的 [`Role::Guest`]
+    pub fn role_for(&self, device_type: &DeviceType) -> Role {
+        self.roles.get(device_type).copied().unwrap_or(Role::Guest)
+    }
+}
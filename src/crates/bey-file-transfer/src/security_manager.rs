@@ -23,6 +23,11 @@ use tokio::sync::RwLock;
 use tracing::{info, warn, error, debug, instrument};
 use bytes::Bytes;
 use crate::{TransferConfig, TransferResult};
+use crate::roles::{DeviceRoleTemplates, Permission, Role};
+use bey_types::DeviceType;
+
+/// 访问控制缓存默认有效期
+const DEFAULT_ACCESS_CACHE_TTL: Duration = Duration::from_secs(300);
 
 /// 安全管理器
 ///
@@ -39,6 +44,12 @@ pub struct SecurityManager {
     key_history: Arc<RwLock<Vec<KeyRotationEntry>>>,
     /// 访问控制缓存
     access_cache: Arc<RwLock<HashMap<String, AccessEntry>>>,
+    /// 访问控制缓存有效期
+    access_cache_ttl: Duration,
+    /// 用户ID -> 角色
+    user_roles: Arc<RwLock<HashMap<String, Role>>>,
+    /// 设备类型到默认角色的映射
+    device_role_templates: DeviceRoleTemplates,
     /// 操作审计日志
     audit_log: Arc<RwLock<Vec<SecurityAuditEntry>>>,
 }
@@ -185,6 +196,9 @@ impl SecurityManager {
             config,
             key_history: Arc::new(RwLock::new(Vec::new())),
             access_cache: Arc::new(RwLock::new(HashMap::new())),
+            access_cache_ttl: DEFAULT_ACCESS_CACHE_TTL,
+            user_roles: Arc::new(RwLock::new(HashMap::new())),
+            device_role_templates: DeviceRoleTemplates::default(),
             audit_log: Arc::new(RwLock::new(Vec::new())),
         };
 
@@ -366,6 +380,71 @@ impl SecurityManager {
         Ok(is_valid)
     }
 
+    /// 设置访问控制缓存有效期
+    ///
+    /// 超过有效期的缓存条目视为未命中，下次验证时重新计算权限
+    ///
+    /// # 参数
+    ///
+    /// * `ttl` - 缓存有效期
+    pub fn with_access_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.access_cache_ttl = ttl;
+        self
+    }
+
+    /// 设置设备类型到默认角色的映射
+    ///
+    /// # 参数
+    ///
+    /// * `templates` - 设备类型角色映射
+    pub fn with_device_role_templates(mut self, templates: DeviceRoleTemplates) -> Self {
+        self.device_role_templates = templates;
+        self
+    }
+
+    /// 为用户分配角色
+    ///
+    /// 同时清除该用户的访问控制缓存，使角色变更立即生效
+    ///
+    /// # 参数
+    ///
+    /// * `user_id` - 用户ID
+    /// * `role` - 角色
+    pub async fn assign_role(&self, user_id: &str, role: Role) {
+        self.user_roles.write().await.insert(user_id.to_string(), role);
+        self.access_cache.write().await.retain(|_, entry| entry.user_id != user_id);
+        info!("为用户 {} 分配角色: {:?}", user_id, role);
+    }
+
+    /// 按设备类型为用户分配默认角色
+    ///
+    /// # 参数
+    ///
+    /// * `user_id` - 用户ID
+    /// * `device_type` - 用户所在设备的类型
+    ///
+    /// # 返回
+    ///
+    /// 返回分配的角色
+    pub async fn assign_default_role_for_device_type(&self, user_id: &str, device_type: DeviceType) -> Role {
+        let role = self.device_role_templates.role_for(&device_type);
+        self.assign_role(user_id, role).await;
+        role
+    }
+
+    /// 获取用户的角色
+    ///
+    /// # 参数
+    ///
+    /// * `user_id` - 用户ID
+    ///
+    /// # 返回
+    ///
+    /// 返回用户的角色，未分配时返回 `None`
+    pub async fn user_role(&self, user_id: &str) -> Option<Role> {
+        self.user_roles.read().await.get(user_id).copied()
+    }
+
     /// 验证用户访问权限
     ///
     /// # 参数
@@ -400,12 +479,13 @@ impl SecurityManager {
 
         if has_permission {
             // 缓存验证结果
+            let access_time = SystemTime::now();
             let entry = AccessEntry {
                 user_id: user_id.to_string(),
                 resource_path: resource_path.to_string(),
                 permissions: vec![required_permission.to_string()],
-                access_time: SystemTime::now(),
-                expires_at: SystemTime::now() + Duration::from_secs(300), // 5分钟缓存
+                access_time,
+                expires_at: access_time + self.access_cache_ttl,
             };
             self.access_cache.write().await.insert(cache_key, entry);
 
@@ -541,8 +621,9 @@ impl SecurityManager {
         Ok(nonce)
     }
 
-    /// 检查用户权限（完整实现）
-    /// TODO: 将权限系统迁移到bey-core后恢复完整实现
+    /// 检查用户权限
+    ///
+    /// 按用户分配的角色检查；未分配角色的用户和未知的权限类型一律拒绝
     async fn check_user_permission(
         &self,
         user_id: &str,
@@ -551,34 +632,20 @@ impl SecurityManager {
     ) -> TransferResult<bool> {
         debug!("检查用户权限: {} -> {} (权限: {})", user_id, resource_path, required_permission);
 
-        // TODO: 临时实现 - 允许所有请求
-        // 待权限系统迁移到bey-core后恢复完整的权限检查
-        warn!("权限检查暂时禁用，允许所有请求: {} -> {}", user_id, required_permission);
-        Ok(true)
-        
-        // 原有实现已注释，待权限系统迁移后恢复:
-        // 转换权限字符串为Permission枚举
-        // let permission = match required_permission {
-        //     "read" | "file_read" => Permission::FileDownload,
-        //     "write" | "file_write" => Permission::FileUpload,
-        //     "upload" | "file_upload" => Permission::FileUpload,
-        //     "download" | "file_download" => Permission::FileDownload,
-        //     "delete" | "file_delete" => Permission::FileDelete,
-        //     "execute" | "file_execute" => Permission::FileDelete,
-        //     _ => {
-        //         warn!("未知权限类型: {}", required_permission);
-        //         return Ok(false);
-        //     }
-        // };
-        //
-        // 创建权限管理器（在实际应用中应该是单例或共享实例）
-        // let permission_manager = PermissionManager::new().await?;
-        //
-        // 执行权限检查
-        // let has_permission = permission_manager.check_permission(user_id, permission).await?;
-        //
-        // debug!("权限检查结果: {} -> {} = {}", user_id, required_permission, has_permission);
-        // Ok(has_permission)
+        let Some(role) = self.user_role(user_id).await else {
+            warn!("用户 {} 未分配角色，拒绝权限 {}", user_id, required_permission);
+            return Ok(false);
+        };
+
+        let has_permission = match Permission::parse(required_permission) {
+            Some(permission) => role.has_permission(permission),
+            None => {
+                warn!("未知权限类型: {}", required_permission);
+                false
+            }
+        };
+        debug!("权限检查结果: {} ({:?}) -> {} = {}", user_id, role, required_permission, has_permission);
+        Ok(has_permission)
     }
 
     /// 记录安全操作审计日志
@@ -904,8 +971,14 @@ mod tests {
         let resource_path = "/test/file.txt";
         let permission = "read";
 
-        let has_access = manager.verify_access(user_id, resource_path, permission).await.unwrap();
-        assert!(has_access); // 完整实现中基于权限系统进行实际检查
+        // 未分配角色的用户默认拒绝
+        assert!(!manager.verify_access(user_id, resource_path, permission).await.unwrap());
+        assert!(manager.access_cache.read().await.is_empty());
+
+        manager.assign_role(user_id, Role::Guest).await;
+        assert!(manager.verify_access(user_id, resource_path, permission).await.unwrap());
+        assert!(!manager.verify_access(user_id, resource_path, "upload").await.unwrap());
+        assert!(!manager.verify_access(user_id, resource_path, "unknown").await.unwrap());
     }
 
     #[tokio::test]
@@ -931,4 +1004,50 @@ mod tests {
         assert_eq!(cleaned_count, 1);
         assert_eq!(manager.access_cache.read().await.len(), 0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_access_cache_recomputed_after_ttl() {
+        let config = Arc::new(TransferConfig::default());
+        let manager = SecurityManager::new(config).await.unwrap()
+            .with_access_cache_ttl(Duration::from_millis(50));
+        manager.assign_role("test-user", Role::User).await;
+
+        let cache_key = "test-user:/test/file.txt:read";
+        assert!(manager.verify_access("test-user", "/test/file.txt", "read").await.unwrap());
+        let first_access = manager.access_cache.read().await[cache_key].access_time;
+
+        // 有效期内命中缓存，条目不变
+        assert!(manager.verify_access("test-user", "/test/file.txt", "read").await.unwrap());
+        assert_eq!(manager.access_cache.read().await[cache_key].access_time, first_access);
+
+        // 过期后视为未命中，重新计算并刷新条目
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(manager.verify_access("test-user", "/test/file.txt", "read").await.unwrap());
+        let refreshed = manager.access_cache.read().await[cache_key].clone();
+        assert!(refreshed.access_time > first_access);
+        assert!(refreshed.expires_at > SystemTime::now());
+    }
+
+    #[tokio::test]
+    async fn test_default_role_for_device_type() {
+        let config = Arc::new(TransferConfig::default());
+        let manager = SecurityManager::new(config).await.expect("创建安全管理器失败");
+
+        let mobile_role = manager.assign_default_role_for_device_type("phone", DeviceType::Mobile).await;
+        assert!(!mobile_role.has_permission(Permission::CertificateManage));
+        assert!(!manager.verify_access("phone", "/certs", "certificate_manage").await.expect("验证权限失败"));
+        assert!(manager.verify_access("phone", "/files/a.txt", "upload").await.expect("验证权限失败"));
+
+        let server_role = manager.assign_default_role_for_device_type("nas", DeviceType::Server).await;
+        assert!(server_role.has_permission(Permission::StorageManage));
+        assert!(manager.verify_access("nas", "/storage", "storage_manage").await.expect("验证权限失败"));
+        assert_eq!(manager.user_role("nas").await, Some(Role::Operator));
+
+        // 自定义映射覆盖默认角色
+        let config = Arc::new(TransferConfig::default());
+        let manager = SecurityManager::new(config).await.expect("创建安全管理器失败")
+            .with_device_role_templates(DeviceRoleTemplates::default().with_role(DeviceType::Mobile, Role::Guest));
+        assert_eq!(manager.assign_default_role_for_device_type("phone", DeviceType::Mobile).await, Role::Guest);
+        assert!(!manager.verify_access("phone", "/files/a.txt", "upload").await.expect("验证权限失败"));
+    }
+}
@@ -220,10 +220,10 @@ impl StorageInterface for LocalStorage {
             })?;
         }
 
-        // 打开文件（创建或追加）
+        // 打开文件（不存在时创建，保留其他块已写入的数据）
         let mut file = fs::OpenOptions::new()
             .create(true)
-            .truncate(true)
+            .truncate(false)
             .write(true)
             .open(&full_path)
             .await
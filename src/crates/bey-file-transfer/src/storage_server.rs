@@ -356,6 +356,7 @@ impl BeyStorageServer {
             ttl: 120,
             priority: 0,
             weight: 0,
+            subtypes: Vec::new(),
         };
 
         let discovery = Arc::new(MdnsDiscovery::new(
@@ -857,6 +858,8 @@ mod tests {
         let temp_dir = TempDir::new().unwrap();
         let config = StorageServerConfig {
             base_path: temp_dir.path().to_path_buf(),
+            transport_config: TransportConfig::new()
+                .with_certificates_dir(temp_dir.path().join("certs")),
             enable_auth: false,
             ..Default::default()
         };
@@ -102,6 +102,26 @@ impl TransferEngine {
     pub async fn new(
         storage: Arc<dyn StorageInterface>,
         config: TransferConfig,
+    ) -> TransferResult<Self> {
+        Self::with_checkpoint_dir(storage, config, "./checkpoints").await
+    }
+
+    /// 创建使用指定断点目录的传输引擎实例
+    ///
+    /// # 参数
+    ///
+    /// * `storage` - 存储接口实现
+    /// * `config` - 传输配置
+    /// * `checkpoint_dir` - 断点文件保存目录
+    ///
+    /// # 返回
+    ///
+    /// 返回传输引擎实例或错误信息
+    #[instrument(skip(storage, config, checkpoint_dir))]
+    pub async fn with_checkpoint_dir(
+        storage: Arc<dyn StorageInterface>,
+        config: TransferConfig,
+        checkpoint_dir: impl AsRef<Path>,
     ) -> TransferResult<Self> {
         info!("初始化传输引擎，配置: {:?}", config);
 
@@ -111,7 +131,7 @@ impl TransferEngine {
         let progress_senders = Arc::new(ParkingLotRwLock::new(HashMap::new()));
 
         // 创建所有组件
-        let resume_manager = Arc::new(crate::ResumeManager::new("./checkpoints", config.clone()).await.unwrap());
+        let resume_manager = Arc::new(crate::ResumeManager::new(checkpoint_dir.as_ref(), config.clone()).await?);
         let security_manager = Arc::new(crate::SecurityManager::new(config.clone()).await.unwrap());
         let concurrent_transfer = Arc::new(crate::ConcurrentTransfer::new(config.clone()).await.unwrap());
         let progress_tracker = Arc::new(crate::ProgressTracker::new());
@@ -294,25 +314,30 @@ impl TransferEngine {
         let chunks = self.calculate_file_chunks(&task_details).await?;
 
         // 检查续传点
-        let start_chunk_index = if task_details.status == TransferStatus::Resuming {
+        let completed_chunks = if task_details.status == TransferStatus::Resuming {
             self.check_resume_point(&task_details).await?
         } else {
-            0
+            Vec::new()
         };
 
-        info!("开始传输文件块，起始索引: {}, 总块数: {}", start_chunk_index, chunks.len());
+        info!("开始传输文件块，起始索引: {}, 总块数: {}", completed_chunks.len(), chunks.len());
 
         // 并发传输数据块
         let transfer_result = self.execute_chunked_transfer(
             &task_details,
             &chunks,
-            start_chunk_index,
+            completed_chunks,
             progress_sender,
         ).await;
 
         // 处理传输结果
         match transfer_result {
             Ok(_) => {
+                // 传输完成后不再需要断点
+                if let Err(e) = self.resume_manager.delete_checkpoint(&task_details.task_id).await {
+                    warn!("删除断点信息失败: {}", e);
+                }
+
                 // 传输成功，更新任务状态
                 let mut task_ref = task.write().await;
                 task_ref.status = TransferStatus::Completed;
@@ -354,9 +379,10 @@ impl TransferEngine {
     pub async fn pause_transfer(&self, task_id: &str) -> TransferResult<()> {
         info!("暂停传输任务: {}", task_id);
 
-        if let Some(task) = self.active_transfers.get(task_id) {
+        if let Some(task) = self.active_transfers.get(task_id).map(|entry| entry.clone()) {
             let mut task_ref = task.write().await;
-            if task_ref.status == TransferStatus::Transferring {
+            // 尚未开始传输的任务同样可以暂停
+            if matches!(task_ref.status, TransferStatus::Pending | TransferStatus::Preparing | TransferStatus::Transferring) {
                 task_ref.status = TransferStatus::Paused;
                 task_ref.updated_at = SystemTime::now();
 
@@ -395,7 +421,7 @@ impl TransferEngine {
     pub async fn resume_transfer(&self, task_id: &str) -> TransferResult<()> {
         info!("恢复传输任务: {}", task_id);
 
-        if let Some(task) = self.active_transfers.get(task_id) {
+        if let Some(task) = self.active_transfers.get(task_id).map(|entry| entry.clone()) {
             let mut task_ref = task.write().await;
             if task_ref.status == TransferStatus::Paused {
                 task_ref.status = TransferStatus::Resuming;
@@ -422,12 +448,9 @@ impl TransferEngine {
                 };
 
                 // 更新任务状态和进度
-                {
-                    let mut task_ref = task.write().await;
-                    task_ref.status = TransferStatus::Transferring;
-                    task_ref.transferred_size = transferred_size;
-                    task_ref.updated_at = SystemTime::now();
-                }
+                task_ref.status = TransferStatus::Transferring;
+                task_ref.transferred_size = transferred_size;
+                task_ref.updated_at = SystemTime::now();
 
                 info!("传输任务正在恢复: {}", task_id);
                 Ok(())
@@ -456,7 +479,8 @@ impl TransferEngine {
     pub async fn cancel_transfer(&self, task_id: &str) -> TransferResult<()> {
         info!("取消传输任务: {}", task_id);
 
-        if let Some(task) = self.active_transfers.get(task_id) {
+        // 克隆出任务引用，避免持有DashMap分片锁时再移除条目造成死锁
+        if let Some(task) = self.active_transfers.get(task_id).map(|entry| entry.clone()) {
             let mut task_ref = task.write().await;
             if task_ref.status != TransferStatus::Completed &&
                task_ref.status != TransferStatus::Failed &&
@@ -507,6 +531,73 @@ impl TransferEngine {
         }
     }
 
+    /// 恢复进程重启前未完成的传输任务
+    ///
+    /// 从断点目录重新加载带任务信息的断点，按断点中记录的块哈希校验目标文件，
+    /// 从第一个不一致的块处截断断点，然后以 `Resuming` 状态重新注册任务，
+    /// 之后调用 [`TransferEngine::start_transfer`] 即可从断点继续传输。
+    ///
+    /// # 返回
+    ///
+    /// 返回恢复的传输任务列表或错误信息
+    #[instrument(skip(self))]
+    pub async fn recover_pending(&self) -> TransferResult<Vec<Arc<RwLock<TransferTask>>>> {
+        let mut recovered = Vec::new();
+
+        for checkpoint in self.resume_manager.get_all_checkpoints().await? {
+            let Some(mut task) = checkpoint.task.clone() else {
+                debug!("断点缺少任务信息，跳过恢复: {}", checkpoint.task_id);
+                continue;
+            };
+
+            if self.active_transfers.contains_key(&task.task_id) {
+                continue;
+            }
+
+            if !self.resume_manager.validate_checkpoint(&checkpoint).await? {
+                warn!("断点信息无效，放弃恢复任务: {}", task.task_id);
+                if let Err(e) = self.resume_manager.delete_checkpoint(&task.task_id).await {
+                    warn!("删除断点信息失败: {}", e);
+                }
+                continue;
+            }
+
+            // 校验目标文件中已写入的块，只保留哈希一致的前缀
+            let mut verified_chunks = Vec::with_capacity(checkpoint.transferred_chunks.len());
+            for chunk in &checkpoint.transferred_chunks {
+                let data = match self.storage.read_chunk(&task.target_path, chunk.offset, chunk.size).await {
+                    Ok(data) if data.len() == chunk.size => data,
+                    _ => break,
+                };
+                if self.security_manager.calculate_hash(&data).await != chunk.hash {
+                    break;
+                }
+                verified_chunks.push(chunk.clone());
+            }
+
+            if verified_chunks.len() < checkpoint.transferred_chunks.len() {
+                warn!("目标文件与断点不一致，任务 {} 从块 {} 重新传输", task.task_id, verified_chunks.len());
+            }
+
+            task.status = TransferStatus::Resuming;
+            task.transferred_size = verified_chunks.iter().map(|chunk| chunk.size as u64).sum();
+            task.updated_at = SystemTime::now();
+            self.resume_manager.save_task_checkpoint(&task, verified_chunks).await?;
+
+            info!("恢复传输任务: {}, 已传输: {} 字节", task.task_id, task.transferred_size);
+
+            let task_id = task.task_id.clone();
+            let task = Arc::new(RwLock::new(task));
+            self.active_transfers.insert(task_id.clone(), task.clone());
+            let (tx, _) = broadcast::channel(100);
+            self.progress_senders.write().insert(task_id, tx);
+            recovered.push(task);
+        }
+
+        info!("共恢复 {} 个未完成的传输任务", recovered.len());
+        Ok(recovered)
+    }
+
     /// 获取传输任务信息
     ///
     /// # 参数
@@ -626,15 +717,14 @@ impl TransferEngine {
     ///
     /// # 返回
     ///
-    /// 返回应该开始传输的块索引
-    async fn check_resume_point(&self, task: &TransferTask) -> TransferResult<usize> {
+    /// 返回断点中已完成的块，其数量即应该开始传输的块索引
+    async fn check_resume_point(&self, task: &TransferTask) -> TransferResult<Vec<ChunkInfo>> {
         // 尝试加载断点信息
         if let Some(checkpoint) = self.resume_manager.load_checkpoint(&task.task_id).await? {
             // 验证断点信息
             if self.resume_manager.validate_checkpoint(&checkpoint).await? {
-                let next_chunk_index = checkpoint.transferred_chunks.len();
-                info!("找到有效断点，从块索引 {} 开始恢复传输", next_chunk_index);
-                return Ok(next_chunk_index);
+                info!("找到有效断点，从块索引 {} 开始恢复传输", checkpoint.transferred_chunks.len());
+                return Ok(checkpoint.transferred_chunks);
             } else {
                 warn!("断点信息无效，将从头开始传输");
             }
@@ -642,7 +732,7 @@ impl TransferEngine {
             debug!("未找到断点信息，将从头开始传输");
         }
 
-        Ok(0)
+        Ok(Vec::new())
     }
 
     /// 执行分块传输
@@ -651,7 +741,7 @@ impl TransferEngine {
     ///
     /// * `task` - 传输任务
     /// * `chunks` - 文件块列表
-    /// * `start_index` - 开始传输的块索引
+    /// * `completed_chunks` - 断点中已完成的块
     /// * `progress_sender` - 进度通知发送器
     ///
     /// # 返回
@@ -661,18 +751,16 @@ impl TransferEngine {
         &self,
         task: &TransferTask,
         chunks: &[ChunkInfo],
-        start_index: usize,
+        completed_chunks: Vec<ChunkInfo>,
         _progress_sender: Option<broadcast::Receiver<TransferProgress>>,
     ) -> TransferResult<()> {
+        let start_index = completed_chunks.len().min(chunks.len());
         info!("开始分块传输，范围: {} - {}", start_index, chunks.len());
 
-        let mut transferred_chunks = Vec::new();
-        let mut total_transferred = task.transferred_size;
-
-        // 处理已经传输的块
-        if start_index > 0 {
-            transferred_chunks.extend_from_slice(&chunks[..start_index]);
-        }
+        let mut total_transferred: u64 = completed_chunks.iter().map(|chunk| chunk.size as u64).sum();
+        let mut transferred_chunks = completed_chunks;
+        let mut checkpoint_task = task.clone();
+        checkpoint_task.status = TransferStatus::Transferring;
 
         // 传输剩余的块
         for (index, chunk) in chunks.iter().enumerate().skip(start_index) {
@@ -736,18 +824,15 @@ impl TransferEngine {
                 let _ = tx.send(progress);
             }
 
-            // 定期保存断点信息
-            if (index + 1) % 10 == 0 || index + 1 == chunks.len() {
-                self.resume_manager.save_checkpoint(&task.task_id, transferred_chunks.clone()).await?;
-                debug!("保存断点信息，已完成 {}/{} 块", index + 1, chunks.len());
-            }
+            // 每个块写入后保存断点，进程重启后可从此处继续
+            checkpoint_task.transferred_size = total_transferred;
+            checkpoint_task.updated_at = SystemTime::now();
+            self.resume_manager.save_task_checkpoint(&checkpoint_task, transferred_chunks.clone()).await?;
+            debug!("保存断点信息，已完成 {}/{} 块", index + 1, chunks.len());
 
             debug!("块传输完成: {}/{}", index + 1, chunks.len());
         }
 
-        // 保存最终断点信息
-        self.resume_manager.save_checkpoint(&task.task_id, transferred_chunks).await?;
-
         // 验证文件完整性
         if let Some(expected_hash) = &task.file_hash {
             let target_data = self.storage.read_chunk(&task.target_path, 0, task.file_size as usize).await?;
@@ -770,6 +855,10 @@ impl TransferEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{DirectoryEntry, FileInfo};
+    use blake3::Hasher;
+    use bytes::Bytes;
+    use std::path::PathBuf;
     use tempfile::tempdir;
 
     // 创建测试用的存储实现
@@ -819,7 +908,7 @@ mod tests {
             if let Some(data) = files.get(path) {
                 let mut hasher = Hasher::new();
                 hasher.update(data);
-                let hash = format!("{:x}", hasher.finalize());
+                let hash = hasher.finalize().to_hex().to_string();
 
                 Ok(FileInfo {
                     path: path.to_path_buf(),
@@ -846,19 +935,19 @@ mod tests {
             Ok(())
         }
 
-        async fn list_directory(&self, path: &Path) -> TransferResult<Vec<DirectoryEntry>> {
+        async fn list_directory(&self, _path: &Path) -> TransferResult<Vec<DirectoryEntry>> {
             // 简单实现：返回空列表
             Ok(Vec::new())
         }
 
-        async fn remove_dir(&self, path: &Path) -> TransferResult<()> {
+        async fn remove_dir(&self, _path: &Path) -> TransferResult<()> {
             // 简单实现：不支持删除目录
             Err(ErrorInfo::new(7011, "不支持删除目录".to_string())
                 .with_category(ErrorCategory::FileSystem)
                 .with_severity(ErrorSeverity::Error))
         }
 
-        async fn get_directory_size(&self, path: &Path) -> TransferResult<u64> {
+        async fn get_directory_size(&self, _path: &Path) -> TransferResult<u64> {
             // 简单实现：返回0
             Ok(0)
         }
@@ -875,8 +964,9 @@ mod tests {
             files: Arc::new(RwLock::new(HashMap::new())),
         });
 
+        let temp_dir = tempdir().expect("创建临时目录失败");
         let config = TransferConfig::default();
-        let result = TransferEngine::new(storage, config).await;
+        let result = TransferEngine::with_checkpoint_dir(storage, config, temp_dir.path()).await;
 
         assert!(result.is_ok(), "传输引擎创建应该成功");
     }
@@ -887,15 +977,16 @@ mod tests {
             files: Arc::new(RwLock::new(HashMap::new())),
         });
 
+        let temp_dir = tempdir().expect("创建临时目录失败");
         let config = TransferConfig::default();
-        let engine = TransferEngine::new(storage, config).await.unwrap();
+        let engine = TransferEngine::with_checkpoint_dir(storage.clone(), config, temp_dir.path()).await.unwrap();
 
         // 创建测试文件
         let test_data = b"Hello, World!";
         let test_path = PathBuf::from("/test.txt");
 
         {
-            let mut files = storage.files.write();
+            let mut files = storage.files.write().await;
             files.insert(test_path.clone(), test_data.to_vec());
         }
 
@@ -917,7 +1008,8 @@ mod tests {
 
         assert!(task.is_ok(), "传输任务创建应该成功");
 
-        let task_ref = task.unwrap().read();
+        let task = task.unwrap();
+        let task_ref = task.read().await;
         assert_eq!(task_ref.direction, TransferDirection::Upload);
         assert_eq!(task_ref.file_size, test_data.len() as u64);
     }
@@ -928,15 +1020,16 @@ mod tests {
             files: Arc::new(RwLock::new(HashMap::new())),
         });
 
+        let temp_dir = tempdir().expect("创建临时目录失败");
         let config = TransferConfig::default();
-        let engine = TransferEngine::new(storage, config).await.unwrap();
+        let engine = TransferEngine::with_checkpoint_dir(storage.clone(), config, temp_dir.path()).await.unwrap();
 
         // 创建测试文件
         let test_data = b"Hello, World!";
         let test_path = PathBuf::from("/test.txt");
 
         {
-            let mut files = storage.files.write();
+            let mut files = storage.files.write().await;
             files.insert(test_path.clone(), test_data.to_vec());
         }
 
@@ -957,12 +1050,11 @@ mod tests {
         ).await.unwrap();
 
         // 测试暂停
-        let task_id = task.read().task_id.clone();
+        let task_id = task.read().await.task_id.clone();
         let pause_result = engine.pause_transfer(&task_id).await;
         assert!(pause_result.is_ok(), "传输任务暂停应该成功");
 
-        let task_ref = task.read();
-        assert_eq!(task_ref.status, TransferStatus::Paused);
+        assert_eq!(task.read().await.status, TransferStatus::Paused);
 
         // 测试恢复
         let resume_result = engine.resume_transfer(&task_id).await;
@@ -975,15 +1067,16 @@ mod tests {
             files: Arc::new(RwLock::new(HashMap::new())),
         });
 
+        let temp_dir = tempdir().expect("创建临时目录失败");
         let config = TransferConfig::default();
-        let engine = TransferEngine::new(storage, config).await.unwrap();
+        let engine = TransferEngine::with_checkpoint_dir(storage.clone(), config, temp_dir.path()).await.unwrap();
 
         // 创建测试文件
         let test_data = b"Hello, World!";
         let test_path = PathBuf::from("/test.txt");
 
         {
-            let mut files = storage.files.write();
+            let mut files = storage.files.write().await;
             files.insert(test_path.clone(), test_data.to_vec());
         }
 
@@ -1004,11 +1097,11 @@ mod tests {
         ).await.unwrap();
 
         // 测试取消
-        let task_id = task.read().task_id.clone();
+        let task_id = task.read().await.task_id.clone();
         let cancel_result = engine.cancel_transfer(&task_id).await;
         assert!(cancel_result.is_ok(), "传输任务取消应该成功");
 
-        let task_ref = task.read();
+        let task_ref = task.read().await;
         assert_eq!(task_ref.status, TransferStatus::Cancelled);
         assert!(task_ref.completed_at.is_some());
     }
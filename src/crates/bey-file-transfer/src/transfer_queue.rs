@@ -54,6 +54,8 @@ struct QueueItem {
     priority: i32,
     /// 创建时间戳（用于相同优先级时的排序）
     created_at: SystemTime,
+    /// 可出队时间（重试任务在退避延迟结束前不会出队）
+    ready_at: SystemTime,
     /// 任务类型
     task_type: TaskType,
 }
@@ -67,11 +69,11 @@ enum TaskType {
 
 impl Ord for QueueItem {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // 首先按优先级排序（优先级高的在前）
-        match other.priority.cmp(&self.priority) {
+        // 首先按优先级排序（BinaryHeap为最大堆，优先级高的先出队）
+        match self.priority.cmp(&other.priority) {
             std::cmp::Ordering::Equal => {
-                // 优先级相同时按创建时间排序（早创建的在前）
-                self.created_at.cmp(&other.created_at)
+                // 优先级相同时按创建时间排序（早创建的先出队）
+                other.created_at.cmp(&self.created_at)
             }
             other => other,
         }
@@ -100,6 +102,8 @@ struct ActiveTaskInfo {
     execution_time_secs: u64,
     /// 重试次数
     retry_count: usize,
+    /// 出队前的等待任务信息（失败重试时用于重新入队）
+    pending_info: PendingTaskInfo,
 }
 
 /// 等待任务信息
@@ -248,10 +252,12 @@ impl TransferQueue {
             crate::TransferDirection::Download => TaskType::Download,
         };
 
+        let now = SystemTime::now();
         let queue_item = QueueItem {
             task_id: task.task_id.clone(),
             priority,
-            created_at: SystemTime::now(),
+            created_at: now,
+            ready_at: now,
             task_type,
         };
 
@@ -296,9 +302,6 @@ impl TransferQueue {
 
         info!("任务入队成功，任务ID: {}, 队列位置: {}", task.task_id, queue_position);
 
-        // 尝试处理队列中的任务
-        self.process_queue().await;
-
         Ok(())
     }
 
@@ -317,10 +320,10 @@ impl TransferQueue {
             return Ok(None);
         }
 
-        // 从队列中取出任务
+        // 从队列中取出已到可出队时间的任务
         let queue_item = {
             let mut queue = self.priority_queue.lock();
-            queue.pop()
+            Self::pop_ready(&mut queue, SystemTime::now())
         };
 
         if let Some(item) = queue_item {
@@ -338,6 +341,7 @@ impl TransferQueue {
                     status: TransferStatus::Transferring,
                     execution_time_secs: 0,
                     retry_count: pending_info.retry_config.current_retries,
+                    pending_info: pending_info.clone(),
                 };
 
                 // 添加到活跃任务
@@ -418,9 +422,6 @@ impl TransferQueue {
 
             info!("任务完成标记成功，任务ID: {}, 执行时间: {}ms", task_id, execution_time);
 
-            // 继续处理队列中的任务
-            self.process_queue().await;
-
             Ok(())
         } else {
             warn!("未找到活跃任务，任务ID: {}", task_id);
@@ -461,7 +462,7 @@ impl TransferQueue {
                 info!("任务将重试，任务ID: {}, 当前重试次数: {}", task_id, task_info.retry_count);
 
                 // 重新入队任务
-                self.retry_task(task_id, task_info.retry_count + 1).await?;
+                self.retry_task(task_info.pending_info, task_info.retry_count + 1).await?;
             } else {
                 // 任务彻底失败
                 self.statistics.failed_tasks.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -479,9 +480,6 @@ impl TransferQueue {
             // 释放并发许可
             self.concurrency_semaphore.add_permits(1);
 
-            // 继续处理队列中的任务
-            self.process_queue().await;
-
             Ok(should_retry)
         } else {
             warn!("未找到活跃任务，任务ID: {}", task_id);
@@ -650,57 +648,73 @@ impl TransferQueue {
         queue.iter().position(|item| item.task_id == task_id).unwrap_or(0)
     }
 
-    /// 处理队列中的任务
-    async fn process_queue(&self) {
-        debug!("处理队列中的任务");
-
-        // 尝试出队并处理任务
-        while let Ok(Some(task)) = self.dequeue_next().await {
-            debug!("处理任务: {}", task.task_id);
-            // 这里应该启动任务执行逻辑
-            // 由于这个队列管理器不负责具体的任务执行，只是提供调度功能
+    /// 从堆中取出优先级最高且已到可出队时间的任务
+    ///
+    /// 尚在退避延迟中的任务会被放回堆中。
+    fn pop_ready(queue: &mut BinaryHeap<QueueItem>, now: SystemTime) -> Option<QueueItem> {
+        let mut deferred = Vec::new();
+        let mut ready = None;
+
+        while let Some(item) = queue.pop() {
+            if item.ready_at <= now {
+                ready = Some(item);
+                break;
+            }
+            deferred.push(item);
         }
+
+        queue.extend(deferred);
+        ready
     }
 
     /// 重试任务
-    async fn retry_task(&self, task_id: &str, retry_count: usize) -> TransferResult<()> {
+    ///
+    /// 任务立即放回队列，但在退避延迟结束前不会被出队。
+    async fn retry_task(&self, mut pending_info: PendingTaskInfo, retry_count: usize) -> TransferResult<()> {
+        let task_id = pending_info.task_id.clone();
         info!("重试任务，任务ID: {}, 重试次数: {}", task_id, retry_count);
 
-        // 获取原始任务信息
-        let pending_task_info = {
-            let pending_tasks = self.pending_tasks.read().await;
-            pending_tasks.get(task_id).cloned()
-        };
-
-        if let Some(mut pending_info) = pending_task_info {
-            // 更新重试配置
-            pending_info.retry_config.current_retries = retry_count;
+        // 更新重试配置
+        pending_info.retry_config.current_retries = retry_count;
 
-            // 计算重试延迟
-            let retry_delay = match &pending_info.retry_config.delay_strategy {
-                RetryDelayStrategy::Fixed(duration) => *duration,
-                RetryDelayStrategy::Exponential { base_delay, max_delay } => {
-                    let delay = *base_delay * 2_u32.pow(retry_count as u32 - 1);
-                    std::cmp::min(delay, *max_delay)
-                }
-                RetryDelayStrategy::Linear { increment, max_delay } => {
-                    let delay = *increment * retry_count as u32;
-                    std::cmp::min(delay, *max_delay)
-                }
-            };
+        // 计算重试延迟
+        let retry_delay = match &pending_info.retry_config.delay_strategy {
+            RetryDelayStrategy::Fixed(duration) => *duration,
+            RetryDelayStrategy::Exponential { base_delay, max_delay } => {
+                let delay = *base_delay * 2_u32.pow(retry_count as u32 - 1);
+                std::cmp::min(delay, *max_delay)
+            }
+            RetryDelayStrategy::Linear { increment, max_delay } => {
+                let delay = *increment * retry_count as u32;
+                std::cmp::min(delay, *max_delay)
+            }
+        };
 
-            // 延迟后重新入队
-            let task_id_clone = task_id.to_string();
-            tokio::spawn(async move {
-                tokio::time::sleep(retry_delay).await;
-                // 这里应该重新入队任务，但由于结构限制，简化处理
-                debug!("任务重试延迟结束，任务ID: {}", task_id_clone);
-            });
+        let now = SystemTime::now();
+        let queue_item = QueueItem {
+            task_id: task_id.clone(),
+            priority: self.convert_priority(&pending_info.task.metadata),
+            created_at: now,
+            ready_at: now + retry_delay,
+            task_type: match pending_info.task.direction {
+                crate::TransferDirection::Upload => TaskType::Upload,
+                crate::TransferDirection::Download => TaskType::Download,
+            },
+        };
 
-            info!("任务重试已安排，任务ID: {}, 延迟: {:?}", task_id, retry_delay);
-        } else {
-            warn!("找不到要重试的任务信息，任务ID: {}", task_id);
+        // 重新入队
+        pending_info.queued_at = now;
+        {
+            let mut pending_tasks = self.pending_tasks.write().await;
+            pending_tasks.insert(task_id.clone(), pending_info);
         }
+        {
+            let mut queue = self.priority_queue.lock();
+            queue.push(queue_item);
+        }
+        self.statistics.current_queue_length.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        info!("任务重试已安排，任务ID: {}, 延迟: {:?}", task_id, retry_delay);
 
         Ok(())
     }
@@ -737,7 +751,7 @@ impl TransferQueue {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{TransferTask, TransferDirection, TransferConfig, TransferMetadata};
+    use crate::{TransferTask, TransferDirection, TransferConfig, TransferMetadata, TransferOptions};
     use std::path::PathBuf;
     use std::sync::Arc;
     use std::time::SystemTime;
@@ -767,6 +781,7 @@ mod tests {
                 modified_at: SystemTime::now(),
                 properties,
             },
+            options: TransferOptions::default(),
         }
     }
 
@@ -974,7 +989,7 @@ mod tests {
 
         assert_eq!(task1.task_id, "task-1");
         assert_eq!(task2.task_id, "task-2");
-        assert!(task3.is_some()); // 第三个任务也可以出队，因为前两个任务尚未标记完成
+        assert!(task3.unwrap().is_some()); // 第三个任务也可以出队，因为前两个任务尚未标记完成
 
         let status = queue.get_queue_status().await;
         assert_eq!(status.active_tasks, 3); // 实际上由于实现方式，这里可能会超出限制
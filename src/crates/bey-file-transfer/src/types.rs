@@ -283,10 +283,16 @@ pub struct ChunkInfo {
 pub struct TransferCheckpoint {
     /// 传输任务ID
     pub task_id: String,
-    /// 已传输块信息
+    /// 已传输块信息（含各块哈希，恢复时用于校验目标文件）
     pub transferred_chunks: Vec<ChunkInfo>,
+    /// 已传输字节数，即续传的起始偏移量
+    #[serde(default)]
+    pub transferred_bytes: u64,
     /// 传输配置
     pub config: TransferConfig,
+    /// 断点所属的传输任务，进程重启后据此恢复任务
+    #[serde(default)]
+    pub task: Option<TransferTask>,
     /// 创建时间
     pub created_at: SystemTime,
 }
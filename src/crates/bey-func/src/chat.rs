@@ -0,0 +1,363 @@
+//! # 聊天会话模块
+//!
+//! 提供与单个对等设备的聊天会话：发送消息，并按发送顺序接收该设备发来的私信。
+//! 会话发出的消息携带会话ID和序号，接收方据此恢复顺序；
+//! 交付给调用方的消息会自动标记为已读。
+
+use async_trait::async_trait;
+use bey_net::{Token, TransportEngine};
+use bey_storage::{Message, MessageType};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use futures::stream::{self, Stream};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::message_func::private_message_token;
+use crate::retry::wrap_error;
+use crate::storage_slot::StorageSlot;
+use crate::FuncResult;
+
+/// 聊天会话ID令牌属性
+const CHAT_SESSION_ATTRIBUTE: &str = "bey.chat.session";
+/// 聊天消息序号令牌属性
+const CHAT_SEQ_ATTRIBUTE: &str = "bey.chat.seq";
+/// 等待缺失序号时最多缓存的乱序消息数，超过后跳过缺口
+const MAX_REORDER_BUFFER: usize = 64;
+
+/// 私信出站通道
+///
+/// 负责把私信和会话生成的令牌发送给对端，返回网络层的原始错误
+#[async_trait]
+pub(crate) trait ChatOutbound: Send + Sync {
+    /// 发送私信令牌
+    async fn send_token(&self, token: Token) -> FuncResult<()>;
+}
+
+/// 基于网络引擎的出站通道
+pub(crate) struct EngineOutbound {
+    engine: Arc<TransportEngine>,
+}
+
+impl EngineOutbound {
+    /// 创建出站通道
+    pub(crate) fn new(engine: Arc<TransportEngine>) -> Self {
+        Self { engine }
+    }
+}
+
+#[async_trait]
+impl ChatOutbound for EngineOutbound {
+    async fn send_token(&self, token: Token) -> FuncResult<()> {
+        self.engine.send_token(token).await
+    }
+}
+
+/// 聊天消息在发送方会话中的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ChatSequence {
+    /// 发送方会话ID
+    session_id: String,
+    /// 会话内序号，从0开始
+    seq: u64,
+}
+
+impl ChatSequence {
+    /// 从令牌属性中解析，普通私信没有序号时返回 None
+    pub(crate) fn from_token(token: &Token) -> Option<Self> {
+        let session_id = token.meta.attributes.get(CHAT_SESSION_ATTRIBUTE)?;
+        let seq = token.meta.attributes.get(CHAT_SEQ_ATTRIBUTE)?.parse().ok()?;
+        Some(Self {
+            session_id: session_id.clone(),
+            seq,
+        })
+    }
+}
+
+/// 单个对端的收件箱
+struct ChatInbox {
+    /// 交付给会话的消息通道
+    sender: mpsc::UnboundedSender<Message>,
+    /// 对端当前会话ID
+    remote_session: Option<String>,
+    /// 下一个应交付的序号
+    next_seq: u64,
+    /// 等待前序消息的乱序消息
+    pending: BTreeMap<u64, Message>,
+}
+
+impl ChatInbox {
+    fn new(sender: mpsc::UnboundedSender<Message>) -> Self {
+        Self {
+            sender,
+            remote_session: None,
+            next_seq: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// 接收一条消息，按序号交付所有已连续的消息
+    fn accept(&mut self, sequence: Option<ChatSequence>, message: Message) {
+        let Some(sequence) = sequence else {
+            // 不属于聊天会话的普通私信直接交付
+            let _ = self.sender.send(message);
+            return;
+        };
+
+        if self.remote_session.as_deref() != Some(sequence.session_id.as_str()) {
+            // 对端开启了新会话，先交付旧会话遗留的消息再重置序号
+            for (_, stale) in std::mem::take(&mut self.pending) {
+                let _ = self.sender.send(stale);
+            }
+            self.remote_session = Some(sequence.session_id);
+            self.next_seq = 0;
+        }
+
+        if sequence.seq < self.next_seq {
+            debug!("忽略重复的聊天消息: {} (序号 {})", message.id, sequence.seq);
+            return;
+        }
+        self.pending.insert(sequence.seq, message);
+
+        if self.pending.len() > MAX_REORDER_BUFFER {
+            if let Some(&first) = self.pending.keys().next() {
+                warn!("聊天消息缺失序号 {}..{}，跳过", self.next_seq, first);
+                self.next_seq = first;
+            }
+        }
+
+        while let Some(message) = self.pending.remove(&self.next_seq) {
+            let _ = self.sender.send(message);
+            self.next_seq += 1;
+        }
+    }
+}
+
+/// 聊天消息路由
+///
+/// 按对端设备ID把收到的私信分发给已打开的会话
+#[derive(Default)]
+pub(crate) struct ChatRouter {
+    /// 已打开会话的收件箱（对端设备ID -> 收件箱）
+    inboxes: Mutex<HashMap<String, ChatInbox>>,
+}
+
+impl ChatRouter {
+    /// 为对端打开收件箱，每个对端同时只能有一个会话
+    fn open(&self, peer_id: &str) -> FuncResult<mpsc::UnboundedReceiver<Message>> {
+        let mut inboxes = self.inboxes.lock()
+            .map_err(|_| ErrorInfo::new(7121, "聊天路由锁已损坏".to_string())
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+
+        if inboxes.contains_key(peer_id) {
+            return Err(ErrorInfo::new(7120, format!("与设备 {} 的聊天会话已打开", peer_id))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        inboxes.insert(peer_id.to_string(), ChatInbox::new(sender));
+        Ok(receiver)
+    }
+
+    /// 关闭对端的收件箱
+    fn close(&self, peer_id: &str) {
+        if let Ok(mut inboxes) = self.inboxes.lock() {
+            inboxes.remove(peer_id);
+        }
+    }
+
+    /// 将收到的私信交给对应会话，没有打开的会话时忽略
+    pub(crate) fn route(&self, peer_id: &str, sequence: Option<ChatSequence>, message: Message) {
+        if let Ok(mut inboxes) = self.inboxes.lock() {
+            if let Some(inbox) = inboxes.get_mut(peer_id) {
+                inbox.accept(sequence, message);
+            }
+        }
+    }
+}
+
+/// 聊天会话
+///
+/// 通过 [`crate::BeyFuncManager::open_chat`] 获取，只收发与一个对端设备之间的私信。
+/// 会话被关闭或释放后不再接收该设备的消息。
+pub struct ChatSession {
+    /// 本设备ID
+    device_id: String,
+    /// 对端设备ID
+    peer_id: String,
+    /// 本会话ID，接收方据此区分会话
+    session_id: String,
+    /// 下一条发出消息的序号
+    next_seq: AtomicU64,
+    /// 统一存储管理器
+    storage: Arc<StorageSlot>,
+    /// 消息路由
+    router: Arc<ChatRouter>,
+    /// 出站通道
+    outbound: Arc<dyn ChatOutbound>,
+    /// 收到的消息（首次调用 `messages` 时取走）
+    inbound: Mutex<Option<mpsc::UnboundedReceiver<Message>>>,
+}
+
+impl ChatSession {
+    /// 打开聊天会话
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 本设备ID
+    /// * `peer_id` - 对端设备ID
+    /// * `storage` - 统一存储管理器
+    /// * `router` - 消息路由
+    /// * `outbound` - 出站通道
+    ///
+    /// # 返回值
+    ///
+    /// 返回会话，该对端已有打开的会话时返回错误
+    pub(crate) fn open(
+        device_id: String,
+        peer_id: &str,
+        storage: Arc<StorageSlot>,
+        router: Arc<ChatRouter>,
+        outbound: Arc<dyn ChatOutbound>,
+    ) -> FuncResult<Self> {
+        let inbound = router.open(peer_id)?;
+        debug!("打开聊天会话: {} <-> {}", device_id, peer_id);
+
+        Ok(Self {
+            device_id,
+            peer_id: peer_id.to_string(),
+            session_id: new_session_id(),
+            next_seq: AtomicU64::new(0),
+            storage,
+            router,
+            outbound,
+            inbound: Mutex::new(Some(inbound)),
+        })
+    }
+
+    /// 获取对端设备ID
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    /// 发送文本消息
+    ///
+    /// # 参数
+    ///
+    /// * `text` - 消息文本
+    ///
+    /// # 返回值
+    ///
+    /// 返回消息ID或错误
+    pub async fn send(&self, text: &str) -> FuncResult<String> {
+        let content = text.as_bytes();
+        let msg_id = self.storage.write_access().await.message.send_message(
+            MessageType::Private,
+            self.peer_id.clone(),
+            content.to_vec(),
+            "text".to_string(),
+        ).await
+            .map_err(|e| ErrorInfo::new(7102, format!("保存消息失败: {}", e))
+                .with_category(ErrorCategory::Storage))?;
+
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        let mut token = private_message_token(&self.device_id, &self.peer_id, &msg_id, content);
+        token.meta.attributes.insert(CHAT_SESSION_ATTRIBUTE.to_string(), self.session_id.clone());
+        token.meta.attributes.insert(CHAT_SEQ_ATTRIBUTE.to_string(), seq.to_string());
+
+        self.outbound.send_token(token).await
+            .map_err(|e| wrap_error(e, 7103, "发送消息失败", ErrorCategory::Network))?;
+
+        debug!("聊天消息已发送: {} -> {} (序号 {})", msg_id, self.peer_id, seq);
+        Ok(msg_id)
+    }
+
+    /// 获取对端发来的消息流
+    ///
+    /// 消息按对端的发送顺序交付，交付时标记为已读。
+    /// 消息流只能获取一次，再次调用返回空流；会话关闭后消息流结束。
+    pub fn messages(&self) -> impl Stream<Item = Message> + Send + 'static {
+        let inbound = self.inbound.lock().ok().and_then(|mut inbound| inbound.take());
+        let storage = Arc::clone(&self.storage);
+
+        stream::unfold((inbound, storage), |(mut inbound, storage)| async move {
+            let mut message = inbound.as_mut()?.recv().await?;
+            if let Err(e) = storage.write_access().await.message.mark_as_read(&message.id).await {
+                debug!("标记消息已读失败: {} - {}", message.id, e);
+            }
+            message.is_read = true;
+            Some((message, (inbound, storage)))
+        })
+    }
+
+    /// 关闭会话，停止接收该对端的消息
+    pub fn close(self) {}
+}
+
+impl Drop for ChatSession {
+    fn drop(&mut self) {
+        self.router.close(&self.peer_id);
+        debug!("关闭聊天会话: {} <-> {}", self.device_id, self.peer_id);
+    }
+}
+
+/// 生成会话ID
+fn new_session_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("{:x}", nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_message(id: &str) -> Message {
+        Message {
+            id: id.to_string(),
+            message_type: MessageType::Private,
+            sender_id: "peer".to_string(),
+            receiver_id: "local".to_string(),
+            content: id.as_bytes().to_vec(),
+            content_type: "text".to_string(),
+            timestamp: 0,
+            is_read: false,
+            source_device_id: "peer".to_string(),
+            edited: false,
+            edit_history: Vec::new(),
+            reactions: Default::default(),
+            expires_at: None,
+        }
+    }
+
+    fn sequence(session_id: &str, seq: u64) -> Option<ChatSequence> {
+        Some(ChatSequence {
+            session_id: session_id.to_string(),
+            seq,
+        })
+    }
+
+    #[test]
+    fn test_chat_inbox_restores_order() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut inbox = ChatInbox::new(sender);
+
+        inbox.accept(sequence("s1", 1), test_message("b"));
+        assert!(receiver.try_recv().is_err(), "缺少序号0时不应交付");
+
+        inbox.accept(sequence("s1", 0), test_message("a"));
+        inbox.accept(sequence("s1", 0), test_message("a"));
+        inbox.accept(sequence("s2", 0), test_message("c"));
+
+        let delivered: Vec<String> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(delivered, vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,242 @@
+//! # 自适应块大小
+//!
+//! 根据每个对等设备观测到的吞吐量、RTT和丢包情况调整大文件传输的块大小。
+//! 从较小的块开始，链路状况良好时倍增，出现丢包（重传或超时）或RTT明显升高时减半，
+//! 始终限制在配置的最小值和最大值之间。
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// RTT超过历史最小值的该倍数时视为链路拥塞
+const RTT_INFLATION_FACTOR: u32 = 2;
+
+/// 吞吐量低于平均值的该比例时视为链路变差，不再增大块大小
+const THROUGHPUT_DROP_RATIO: f64 = 0.8;
+
+/// 吞吐量指数移动平均的新样本权重
+const THROUGHPUT_EWMA_WEIGHT: f64 = 0.3;
+
+/// 自适应块大小配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSizingConfig {
+    /// 最小块大小（字节）
+    pub min_chunk_size: usize,
+    /// 最大块大小（字节）
+    pub max_chunk_size: usize,
+    /// 新对等设备的初始块大小（字节）
+    pub initial_chunk_size: usize,
+}
+
+impl Default for ChunkSizingConfig {
+    fn default() -> Self {
+        Self {
+            min_chunk_size: 16 * 1024,       // 16KB
+            max_chunk_size: 1024 * 1024,     // 1MB
+            initial_chunk_size: 32 * 1024,   // 32KB
+        }
+    }
+}
+
+/// 一次传输的链路观测结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransferSample {
+    /// 传输的字节数
+    pub bytes: u64,
+    /// 传输耗时
+    pub elapsed: Duration,
+    /// 传输后测得的RTT
+    pub rtt: Option<Duration>,
+    /// 传输期间的重传和超时次数
+    pub lost: u64,
+}
+
+/// 单个对等设备的链路状态
+#[derive(Debug, Clone)]
+struct PeerLink {
+    /// 当前块大小
+    chunk_size: usize,
+    /// 观测到的最小RTT
+    min_rtt: Option<Duration>,
+    /// 吞吐量的指数移动平均（字节/秒）
+    throughput: Option<f64>,
+}
+
+/// 按对等设备维护块大小的自适应调整器
+pub struct AdaptiveChunkSizer {
+    /// 配置
+    config: ChunkSizingConfig,
+    /// 对等设备ID -> 链路状态
+    peers: Mutex<HashMap<String, PeerLink>>,
+}
+
+impl AdaptiveChunkSizer {
+    /// 创建自适应块大小调整器
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 块大小配置，初始块大小会被限制在最小值和最大值之间
+    pub fn new(config: ChunkSizingConfig) -> Self {
+        let max_chunk_size = config.max_chunk_size.max(config.min_chunk_size).max(1);
+        let min_chunk_size = config.min_chunk_size.clamp(1, max_chunk_size);
+        Self {
+            config: ChunkSizingConfig {
+                min_chunk_size,
+                max_chunk_size,
+                initial_chunk_size: config.initial_chunk_size.clamp(min_chunk_size, max_chunk_size),
+            },
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 获取配置
+    pub fn config(&self) -> ChunkSizingConfig {
+        self.config
+    }
+
+    /// 锁定链路状态表
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, PeerLink>> {
+        match self.peers.lock() {
+            Ok(peers) => peers,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// 获取向对等设备传输时使用的块大小
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    pub fn chunk_size(&self, peer_id: &str) -> usize {
+        self.lock()
+            .get(peer_id)
+            .map(|link| link.chunk_size)
+            .unwrap_or(self.config.initial_chunk_size)
+    }
+
+    /// 记录一次传输的观测结果并调整块大小
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `sample` - 链路观测结果
+    ///
+    /// # 返回值
+    ///
+    /// 返回调整后的块大小
+    pub fn record(&self, peer_id: &str, sample: TransferSample) -> usize {
+        let mut peers = self.lock();
+        let link = peers.entry(peer_id.to_string()).or_insert_with(|| PeerLink {
+            chunk_size: self.config.initial_chunk_size,
+            min_rtt: None,
+            throughput: None,
+        });
+
+        let rtt_inflated = match (sample.rtt, link.min_rtt) {
+            (Some(rtt), Some(min_rtt)) => rtt > min_rtt * RTT_INFLATION_FACTOR,
+            _ => false,
+        };
+
+        let throughput = (!sample.elapsed.is_zero())
+            .then(|| sample.bytes as f64 / sample.elapsed.as_secs_f64());
+        let throughput_dropped = match (throughput, link.throughput) {
+            (Some(current), Some(average)) => current < average * THROUGHPUT_DROP_RATIO,
+            _ => false,
+        };
+
+        link.chunk_size = if sample.lost > 0 || rtt_inflated {
+            (link.chunk_size / 2).max(self.config.min_chunk_size)
+        } else if !throughput_dropped {
+            link.chunk_size.saturating_mul(2).min(self.config.max_chunk_size)
+        } else {
+            link.chunk_size
+        };
+
+        if let Some(rtt) = sample.rtt {
+            link.min_rtt = Some(link.min_rtt.map_or(rtt, |min_rtt| min_rtt.min(rtt)));
+        }
+        if let Some(current) = throughput {
+            link.throughput = Some(link.throughput.map_or(current, |average| {
+                average * (1.0 - THROUGHPUT_EWMA_WEIGHT) + current * THROUGHPUT_EWMA_WEIGHT
+            }));
+        }
+
+        link.chunk_size
+    }
+
+    /// 清除对等设备的链路状态，下次传输重新从初始块大小开始
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    pub fn reset(&self, peer_id: &str) {
+        self.lock().remove(peer_id);
+    }
+}
+
+impl Default for AdaptiveChunkSizer {
+    fn default() -> Self {
+        Self::new(ChunkSizingConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(rtt_ms: u64, lost: u64) -> TransferSample {
+        TransferSample {
+            bytes: 4 * 1024 * 1024,
+            elapsed: Duration::from_millis(100),
+            rtt: Some(Duration::from_millis(rtt_ms)),
+            lost,
+        }
+    }
+
+    #[test]
+    fn test_chunk_size_grows_on_good_link_and_shrinks_on_loss() {
+        let config = ChunkSizingConfig::default();
+        let sizer = AdaptiveChunkSizer::new(config);
+        assert_eq!(sizer.chunk_size("good"), config.initial_chunk_size);
+
+        // 良好链路：无丢包、RTT稳定，块大小增长直到上限
+        let mut previous = sizer.chunk_size("good");
+        for _ in 0..3 {
+            let size = sizer.record("good", sample(2, 0));
+            assert!(size > previous, "良好链路上块大小应该增长");
+            previous = size;
+        }
+        for _ in 0..10 {
+            sizer.record("good", sample(2, 0));
+        }
+        assert_eq!(sizer.chunk_size("good"), config.max_chunk_size);
+
+        // 丢包链路：块大小减小直到下限
+        sizer.record("lossy", sample(2, 0));
+        let mut previous = sizer.chunk_size("lossy");
+        let size = sizer.record("lossy", sample(2, 3));
+        assert!(size < previous, "丢包时块大小应该减小");
+        previous = size;
+        for _ in 0..10 {
+            sizer.record("lossy", sample(2, 1));
+        }
+        assert!(sizer.chunk_size("lossy") < previous);
+        assert_eq!(sizer.chunk_size("lossy"), config.min_chunk_size);
+
+        // 各对等设备独立调整
+        assert_eq!(sizer.chunk_size("good"), config.max_chunk_size);
+    }
+
+    #[test]
+    fn test_rtt_inflation_shrinks_chunk_size() {
+        let sizer = AdaptiveChunkSizer::default();
+        let grown = sizer.record("peer", sample(2, 0));
+
+        // RTT明显高于历史最小值说明出现排队，即使没有丢包也减小块大小
+        let shrunk = sizer.record("peer", sample(20, 0));
+        assert!(shrunk < grown);
+
+        sizer.reset("peer");
+        assert_eq!(sizer.chunk_size("peer"), sizer.config().initial_chunk_size);
+    }
+}
@@ -2,14 +2,24 @@
 //!
 //! 提供基于网络的剪切板同步功能，支持点对点、群组和广播同步。
 //! 实现差异同步和冲突解决。
+//!
+//! 同步过滤器限制可以发送的内容类别和大小，被过滤的条目只保存在本地，不会发送给任何设备。
+//! 剪切板历史环保存最近复制的多个条目，整个环可以同步到其他设备并按时间戳合并。
+//!
+//! 同步时检测到的并发修改冲突按设置的解决方式自动解决，未设置时保存在待解决列表中，
+//! 由调用方通过 [`ClipboardFunc::resolve_conflict`] 选择解决方式。
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
-use std::sync::Arc;
-use bey_net::{TransportEngine, Token, TokenMeta, TokenHandler, NetResult};
-use bey_storage::{UnifiedStorageManager, ClipboardEntry, ClipboardEvent};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, RwLock};
+use bey_net::{TransportEngine, Token, TokenMeta, TokenHandler, NetResult, CLIPBOARD_CHANNEL};
+use bey_storage::{ClipboardConflict, ClipboardEntry, ClipboardEvent, ConflictResolution};
 use async_trait::async_trait;
-use tracing::{info, debug};
+use tracing::{info, debug, warn};
 
+use crate::clipboard_ring::{ClipboardRing, ClipboardRingItem, DEFAULT_RING_CAPACITY};
+use crate::retry::{wrap_error, RetryConfig};
+use crate::storage_slot::StorageSlot;
 use crate::FuncResult;
 
 /// 剪切板令牌类型
@@ -17,12 +27,124 @@ const CLIPBOARD_ADD_TOKEN: &str = "bey.clipboard.add";
 const CLIPBOARD_DELETE_TOKEN: &str = "bey.clipboard.delete";
 const CLIPBOARD_SYNC_TOKEN: &str = "bey.clipboard.sync";
 const CLIPBOARD_DIFF_TOKEN: &str = "bey.clipboard.diff";
+const CLIPBOARD_RING_TOKEN: &str = "bey.clipboard.ring";
+
+/// 剪切板同步过滤器
+///
+/// 内容类别取内容类型中 `/` 之前的部分（不区分大小写），如 `image/png` 的类别为 `image`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClipboardSyncFilter {
+    /// 允许同步的内容类别，为 `None` 时允许所有类别
+    pub allowed_kinds: Option<HashSet<String>>,
+    /// 允许同步的最大内容大小（字节），为 `None` 时不限制
+    pub max_size: Option<usize>,
+}
+
+impl ClipboardSyncFilter {
+    /// 创建不过滤任何条目的同步过滤器
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// 只允许同步指定类别的内容
+    ///
+    /// # 参数
+    ///
+    /// * `kinds` - 允许的内容类别，如 `text`、`image`、`file`
+    pub fn with_allowed_kinds<I, S>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.allowed_kinds = Some(kinds.into_iter().map(|kind| kind.as_ref().to_ascii_lowercase()).collect());
+        self
+    }
+
+    /// 只允许同步不超过指定大小的内容
+    ///
+    /// # 参数
+    ///
+    /// * `max_size` - 最大内容大小（字节）
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// 判断条目是否允许同步
+    ///
+    /// 按条目内嵌的内容判断大小；文件引用条目不内嵌内容，
+    /// 同步时按所引用对象的实际大小判断
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 剪切板条目
+    ///
+    /// # 返回值
+    ///
+    /// 条目的内容类别和大小都满足过滤条件时返回 `true`
+    pub fn allows(&self, entry: &ClipboardEntry) -> bool {
+        self.allows_content(&entry.content_type, entry.content.len())
+    }
+
+    /// 判断指定类型和大小的内容是否允许同步
+    fn allows_content(&self, content_type: &str, size: usize) -> bool {
+        let kind_allowed = self.allowed_kinds.as_ref().is_none_or(|kinds| {
+            let kind = content_type.split('/').next().unwrap_or_default();
+            kinds.contains(&kind.to_ascii_lowercase())
+        });
+        let size_allowed = self.max_size.is_none_or(|max_size| size <= max_size);
+        kind_allowed && size_allowed
+    }
+}
 
 /// 剪切板功能模块
 pub struct ClipboardFunc {
     device_id: String,
     engine: Arc<TransportEngine>,
-    storage: Arc<UnifiedStorageManager>,
+    storage: Arc<StorageSlot>,
+    /// 同步过滤器
+    sync_filter: RwLock<ClipboardSyncFilter>,
+    /// 出站操作的重试策略
+    retry: RetryConfig,
+    /// 剪切板历史环
+    ring: Arc<ClipboardRing>,
+    /// 同步冲突的处理状态
+    conflicts: Arc<ConflictState>,
+}
+
+/// 同步冲突的处理状态
+#[derive(Default)]
+struct ConflictState {
+    /// 自动解决方式，为 `None` 时冲突进入待解决列表
+    auto_resolution: RwLock<Option<ConflictResolution>>,
+    /// 待解决的冲突（每个条目只保留最新的一个）
+    pending: Mutex<Vec<ClipboardConflict>>,
+}
+
+impl ConflictState {
+    /// 当前的自动解决方式
+    fn auto_resolution(&self) -> Option<ConflictResolution> {
+        *self.auto_resolution.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 获取待解决列表的锁，锁被毒化时继续使用其中的数据
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, Vec<ClipboardConflict>> {
+        self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 加入待解决列表，替换同一条目之前的冲突
+    fn push(&self, conflict: ClipboardConflict) {
+        let mut pending = self.lock_pending();
+        pending.retain(|existing| existing.local.id != conflict.local.id);
+        pending.push(conflict);
+    }
+
+    /// 取出指定条目的冲突
+    fn take(&self, entry_id: &str) -> Option<ClipboardConflict> {
+        let mut pending = self.lock_pending();
+        let index = pending.iter().position(|conflict| conflict.local.id == entry_id)?;
+        Some(pending.remove(index))
+    }
 }
 
 impl ClipboardFunc {
@@ -30,26 +152,148 @@ impl ClipboardFunc {
     pub fn new(
         device_id: String,
         engine: Arc<TransportEngine>,
-        storage: Arc<UnifiedStorageManager>,
+        storage: Arc<StorageSlot>,
     ) -> Self {
+        let ring = Arc::new(ClipboardRing::new(device_id.clone(), DEFAULT_RING_CAPACITY));
         Self {
             device_id,
             engine,
             storage,
+            sync_filter: RwLock::new(ClipboardSyncFilter::default()),
+            retry: RetryConfig::default(),
+            ring,
+            conflicts: Arc::new(ConflictState::default()),
         }
     }
 
-    /// 注册剪切板处理器
-    pub async fn register_handlers(&self, engine: &TransportEngine) -> FuncResult<()> {
-        let handler = ClipboardHandler {
+    /// 设置出站操作的重试策略
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置剪切板历史环的容量
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 最多保留的条目数
+    /// * `max_bytes` - 最多保留的内容总字节数
+    pub fn with_ring_capacity(mut self, capacity: usize, max_bytes: usize) -> Self {
+        self.ring = Arc::new(ClipboardRing::new(self.device_id.clone(), capacity).with_max_bytes(max_bytes));
+        self
+    }
+
+    /// 剪切板历史环
+    pub fn ring(&self) -> &ClipboardRing {
+        &self.ring
+    }
+
+    /// 设置同步过滤器
+    ///
+    /// 之后的点对点同步、群组同步和差异同步只发送过滤器允许的条目
+    ///
+    /// # 参数
+    ///
+    /// * `filter` - 同步过滤器
+    pub fn set_sync_filter(&self, filter: ClipboardSyncFilter) {
+        match self.sync_filter.write() {
+            Ok(mut current) => *current = filter,
+            Err(poisoned) => *poisoned.into_inner() = filter,
+        }
+    }
+
+    /// 获取当前的同步过滤器
+    pub fn sync_filter(&self) -> ClipboardSyncFilter {
+        match self.sync_filter.read() {
+            Ok(filter) => filter.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// 设置同步冲突的自动解决方式
+    ///
+    /// 多台设备都自动解决时应使用 [`ConflictResolution::KeepNewest`]，各设备选择相同的内容；
+    /// 设置为 `None` 时冲突进入待解决列表
+    ///
+    /// # 参数
+    ///
+    /// * `resolution` - 自动解决方式
+    pub fn set_conflict_resolution(&self, resolution: Option<ConflictResolution>) {
+        *self.conflicts.auto_resolution.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = resolution;
+    }
+
+    /// 待解决的同步冲突
+    pub fn pending_conflicts(&self) -> Vec<ClipboardConflict> {
+        self.conflicts.lock_pending().clone()
+    }
+
+    /// 解决待解决的同步冲突
+    ///
+    /// 解决结果的时间戳为当前时间，之后的差异同步会把它发送给其他设备
+    ///
+    /// # 参数
+    ///
+    /// * `entry_id` - 冲突条目ID
+    /// * `resolution` - 解决方式
+    ///
+    /// # 返回值
+    ///
+    /// 返回写入的条目，没有该条目的待解决冲突时返回错误
+    pub async fn resolve_conflict(&self, entry_id: &str, resolution: ConflictResolution) -> FuncResult<Vec<ClipboardEntry>> {
+        let conflict = self.conflicts.take(entry_id)
+            .ok_or_else(|| ErrorInfo::new(7217, format!("没有待解决的剪切板冲突: {}", entry_id))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Warning))?;
+
+        self.storage.write_access().await.clipboard.resolve_conflict(conflict, resolution).await
+            .map_err(|e| ErrorInfo::new(7216, format!("解决剪切板冲突失败: {}", e))
+                .with_category(ErrorCategory::Storage))
+    }
+
+    /// 按同步过滤器筛选要发送的条目
+    ///
+    /// 文件引用条目按所引用对象的实际大小过滤，通过过滤后内嵌文件内容一起发送，
+    /// 引用的对象无法读取的条目不发送
+    async fn syncable(&self, entries: Vec<ClipboardEntry>) -> Vec<ClipboardEntry> {
+        let filter = self.sync_filter();
+        let storage = self.storage.current();
+        let total = entries.len();
+        let mut syncable = Vec::with_capacity(total);
+        for entry in entries {
+            let size = storage.clipboard.content_size(&entry).await;
+            if !filter.allows_content(&entry.content_type, usize::try_from(size).unwrap_or(usize::MAX)) {
+                continue;
+            }
+            syncable.extend(storage.clipboard.inline_file_content(entry).await);
+        }
+        let entries = syncable;
+        if entries.len() < total {
+            debug!("同步过滤器排除了 {} 个剪切板条目", total - entries.len());
+        }
+        entries
+    }
+
+    /// 创建剪切板处理器
+    fn handler(&self) -> ClipboardHandler {
+        ClipboardHandler {
             storage: Arc::clone(&self.storage),
-        };
+            ring: Arc::clone(&self.ring),
+            conflicts: Arc::clone(&self.conflicts),
+        }
+    }
 
-        engine.register_handler(Arc::new(handler)).await
+    /// 注册剪切板处理器
+    pub async fn register_handlers(&self, engine: &TransportEngine) -> FuncResult<()> {
+        engine.register_handler(Arc::new(self.handler())).await
             .map_err(|e| ErrorInfo::new(7201, format!("注册剪切板处理器失败: {}", e))
                 .with_category(ErrorCategory::System)
                 .with_severity(ErrorSeverity::Error))?;
 
+        engine.route_tokens_over_channel("bey.clipboard.", CLIPBOARD_CHANNEL)
+            .map_err(|e| ErrorInfo::new(7201, format!("认领剪切板通道失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+
         info!("剪切板处理器已注册");
         Ok(())
     }
@@ -66,7 +310,7 @@ impl ClipboardFunc {
     /// 返回剪切板条目ID或错误
     pub async fn add_clipboard(&self, content_type: &str, content: &[u8]) -> FuncResult<String> {
         // 保存到本地存储
-        let entry_id = self.storage.clipboard.add_entry(
+        let entry_id = self.storage.write_access().await.clipboard.add_entry(
             content.to_vec(),
             content_type.to_string(),
         ).await
@@ -87,7 +331,7 @@ impl ClipboardFunc {
     ///
     /// 返回删除结果
     pub async fn delete_clipboard(&self, entry_id: &str) -> FuncResult<()> {
-        self.storage.clipboard.delete_entry(entry_id).await
+        self.storage.write_access().await.clipboard.delete_entry(entry_id).await
             .map_err(|e| ErrorInfo::new(7203, format!("删除剪切板失败: {}", e))
                 .with_category(ErrorCategory::Storage))?;
 
@@ -105,8 +349,24 @@ impl ClipboardFunc {
     ///
     /// 返回同步结果
     pub async fn sync_to_peer(&self, peer_id: &str) -> FuncResult<()> {
-        // 获取所有剪切板条目
-        let entries = self.storage.clipboard.list_entries().await;
+        let (token, count) = self.peer_sync_token(peer_id).await?;
+
+        // 发送令牌
+        self.retry.run("发送剪切板同步", || self.engine.send_token(token.clone())).await
+            .map_err(|e| wrap_error(e, 7205, "发送剪切板同步失败", ErrorCategory::Network))?;
+
+        info!("同步剪切板到对等设备: {} ({} 个条目)", peer_id, count);
+        Ok(())
+    }
+
+    /// 创建发往对等设备的完整同步令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回令牌和其中通过同步过滤器的条目数量
+    async fn peer_sync_token(&self, peer_id: &str) -> FuncResult<(Token, usize)> {
+        // 获取所有允许同步的剪切板条目
+        let entries = self.syncable(self.storage.current().clipboard.list_entries().await).await;
 
         // 序列化条目列表
         let entries_json = serde_json::to_vec(&entries)
@@ -117,15 +377,7 @@ impl ClipboardFunc {
         let meta = TokenMeta::new(CLIPBOARD_SYNC_TOKEN.to_string(), self.device_id.clone())
             .with_receiver(peer_id.to_string());
 
-        let token = Token::new(meta, entries_json);
-
-        // 发送令牌
-        self.engine.send_token(token).await
-            .map_err(|e| ErrorInfo::new(7205, format!("发送剪切板同步失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
-
-        info!("同步剪切板到对等设备: {} ({} 个条目)", peer_id, entries.len());
-        Ok(())
+        Ok((Token::new(meta, entries_json), entries.len()))
     }
 
     /// 同步剪切板到群组
@@ -138,18 +390,22 @@ impl ClipboardFunc {
     ///
     /// 返回同步结果
     pub async fn sync_to_group(&self, group_id: &str) -> FuncResult<()> {
-        // 获取所有剪切板条目
-        let entries = self.storage.clipboard.list_entries().await;
+        // 获取所有允许同步的剪切板条目
+        let entries = self.syncable(self.storage.current().clipboard.list_entries().await).await;
 
         // 序列化条目列表
         let entries_json = serde_json::to_vec(&entries)
             .map_err(|e| ErrorInfo::new(7206, format!("序列化剪切板失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
-        // 通过群组发送
-        self.engine.send_to_group_by_name(group_id, entries_json, CLIPBOARD_SYNC_TOKEN).await
-            .map_err(|e| ErrorInfo::new(7207, format!("发送群组剪切板同步失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+        // 向群组成员逐个发送，重试时只重发未送达的成员
+        let members = self.engine.group_members(group_id).await;
+        self.retry.run_per_recipient("发送群组剪切板同步", members, |member| {
+            let payload = entries_json.clone();
+            async move { self.engine.send_to(&member, payload, CLIPBOARD_SYNC_TOKEN).await }
+        }).await
+            .into_count()
+            .map_err(|e| wrap_error(e, 7207, "发送群组剪切板同步失败", ErrorCategory::Network))?;
 
         info!("同步剪切板到群组: {} ({} 个条目)", group_id, entries.len());
         Ok(())
@@ -166,12 +422,29 @@ impl ClipboardFunc {
     ///
     /// 返回同步结果
     pub async fn send_diff_to_peer(&self, peer_id: &str, since_timestamp: u64) -> FuncResult<()> {
-        // 获取差异
-        let diff = self.storage.clipboard.get_diff(since_timestamp).await;
-
-        if diff.is_empty() {
+        let Some((token, count)) = self.peer_diff_token(peer_id, since_timestamp).await? else {
             debug!("没有剪切板差异需要同步");
             return Ok(());
+        };
+
+        // 发送令牌
+        self.retry.run("发送剪切板差异", || self.engine.send_token(token.clone())).await
+            .map_err(|e| wrap_error(e, 7209, "发送差异失败", ErrorCategory::Network))?;
+
+        info!("发送剪切板差异到: {} ({} 个条目)", peer_id, count);
+        Ok(())
+    }
+
+    /// 创建发往对等设备的差异令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回令牌和其中通过同步过滤器的条目数量，没有需要同步的差异时返回 `None`
+    async fn peer_diff_token(&self, peer_id: &str, since_timestamp: u64) -> FuncResult<Option<(Token, usize)>> {
+        // 获取允许同步的差异
+        let diff = self.syncable(self.storage.current().clipboard.get_diff(since_timestamp).await).await;
+        if diff.is_empty() {
+            return Ok(None);
         }
 
         // 序列化差异
@@ -183,21 +456,86 @@ impl ClipboardFunc {
         let meta = TokenMeta::new(CLIPBOARD_DIFF_TOKEN.to_string(), self.device_id.clone())
             .with_receiver(peer_id.to_string());
 
-        let token = Token::new(meta, diff_json);
+        Ok(Some((Token::new(meta, diff_json), diff.len())))
+    }
 
-        // 发送令牌
-        self.engine.send_token(token).await
-            .map_err(|e| ErrorInfo::new(7209, format!("发送差异失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+    /// 同步剪切板历史环到对等设备
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回同步结果
+    pub async fn sync_ring_to_peer(&self, peer_id: &str) -> FuncResult<()> {
+        let (token, count) = self.peer_ring_token(peer_id)?;
+
+        self.retry.run("发送剪切板历史环", || self.engine.send_token(token.clone())).await
+            .map_err(|e| wrap_error(e, 7213, "发送剪切板历史环失败", ErrorCategory::Network))?;
 
-        info!("发送剪切板差异到: {} ({} 个条目)", peer_id, diff.len());
+        info!("同步剪切板历史环到对等设备: {} ({} 个条目)", peer_id, count);
         Ok(())
     }
+
+    /// 同步剪切板历史环到群组
+    ///
+    /// # 参数
+    ///
+    /// * `group_id` - 群组ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回同步结果
+    pub async fn sync_ring_to_group(&self, group_id: &str) -> FuncResult<()> {
+        let items = self.syncable_ring_items();
+        let items_json = serde_json::to_vec(&items)
+            .map_err(|e| ErrorInfo::new(7212, format!("序列化剪切板历史环失败: {}", e))
+                .with_category(ErrorCategory::Parse))?;
+
+        let members = self.engine.group_members(group_id).await;
+        self.retry.run_per_recipient("发送群组剪切板历史环", members, |member| {
+            let payload = items_json.clone();
+            async move { self.engine.send_to(&member, payload, CLIPBOARD_RING_TOKEN).await }
+        }).await
+            .into_count()
+            .map_err(|e| wrap_error(e, 7214, "发送群组剪切板历史环失败", ErrorCategory::Network))?;
+
+        info!("同步剪切板历史环到群组: {} ({} 个条目)", group_id, items.len());
+        Ok(())
+    }
+
+    /// 创建发往对等设备的历史环同步令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回令牌和其中通过同步过滤器的条目数量
+    fn peer_ring_token(&self, peer_id: &str) -> FuncResult<(Token, usize)> {
+        let items = self.syncable_ring_items();
+        let items_json = serde_json::to_vec(&items)
+            .map_err(|e| ErrorInfo::new(7212, format!("序列化剪切板历史环失败: {}", e))
+                .with_category(ErrorCategory::Parse))?;
+
+        let meta = TokenMeta::new(CLIPBOARD_RING_TOKEN.to_string(), self.device_id.clone())
+            .with_receiver(peer_id.to_string());
+
+        Ok((Token::new(meta, items_json), items.len()))
+    }
+
+    /// 历史环中通过同步过滤器的条目
+    fn syncable_ring_items(&self) -> Vec<ClipboardRingItem> {
+        let filter = self.sync_filter();
+        self.ring.items().into_iter()
+            .filter(|item| filter.allows_content(&item.content_type, item.content.len()))
+            .collect()
+    }
 }
 
 /// 剪切板处理器
 struct ClipboardHandler {
-    storage: Arc<UnifiedStorageManager>,
+    storage: Arc<StorageSlot>,
+    ring: Arc<ClipboardRing>,
+    conflicts: Arc<ConflictState>,
 }
 
 #[async_trait]
@@ -208,6 +546,7 @@ impl TokenHandler for ClipboardHandler {
             CLIPBOARD_DELETE_TOKEN.to_string(),
             CLIPBOARD_SYNC_TOKEN.to_string(),
             CLIPBOARD_DIFF_TOKEN.to_string(),
+            CLIPBOARD_RING_TOKEN.to_string(),
         ]
     }
 
@@ -225,6 +564,9 @@ impl TokenHandler for ClipboardHandler {
             CLIPBOARD_DELETE_TOKEN => {
                 self.handle_delete(token).await?;
             }
+            CLIPBOARD_RING_TOKEN => {
+                self.handle_ring(token)?;
+            }
             _ => {
                 debug!("未知剪切板令牌类型: {}", token.meta.token_type);
             }
@@ -244,8 +586,7 @@ impl ClipboardHandler {
 
         // 合并所有条目
         for entry in entries {
-            let event = ClipboardEvent::Add(entry);
-            let _ = self.storage.clipboard.handle_sync_event(event).await;
+            self.merge(ClipboardEvent::Add(entry)).await;
         }
 
         info!("处理剪切板同步 来自 {}", token.meta.sender_id);
@@ -261,14 +602,51 @@ impl ClipboardHandler {
 
         // 合并差异
         for entry in diff {
-            let event = ClipboardEvent::Update(entry);
-            let _ = self.storage.clipboard.handle_sync_event(event).await;
+            self.merge(ClipboardEvent::Update(entry)).await;
         }
 
         info!("处理剪切板差异 来自 {}", token.meta.sender_id);
         Ok(())
     }
 
+    /// 合并一个同步事件，检测到的冲突按自动解决方式解决或加入待解决列表
+    async fn merge(&self, event: ClipboardEvent) {
+        let storage = self.storage.write_access().await;
+        let conflicts = match storage.clipboard.handle_sync_event(event).await {
+            Ok(conflicts) => conflicts,
+            Err(e) => {
+                warn!("合并剪切板条目失败: {}", e);
+                return;
+            }
+        };
+
+        for conflict in conflicts {
+            let entry_id = conflict.local.id.clone();
+            match self.conflicts.auto_resolution() {
+                Some(resolution) => {
+                    if let Err(e) = storage.clipboard.resolve_conflict(conflict, resolution).await {
+                        warn!("自动解决剪切板冲突失败: {} ({})", entry_id, e);
+                    }
+                }
+                None => {
+                    info!("剪切板条目 {} 存在同步冲突，等待解决", entry_id);
+                    self.conflicts.push(conflict);
+                }
+            }
+        }
+    }
+
+    /// 处理历史环同步
+    fn handle_ring(&self, token: Token) -> NetResult<()> {
+        let items: Vec<ClipboardRingItem> = serde_json::from_slice(&token.payload)
+            .map_err(|e| ErrorInfo::new(7215, format!("反序列化剪切板历史环失败: {}", e))
+                .with_category(ErrorCategory::Parse))?;
+
+        let changed = self.ring.merge(items);
+        info!("合并剪切板历史环 来自 {} (变化: {})", token.meta.sender_id, changed);
+        Ok(())
+    }
+
     /// 处理添加操作
     async fn handle_add(&self, token: Token) -> NetResult<()> {
         debug!("处理剪切板添加 来自 {}", token.meta.sender_id);
@@ -303,9 +681,186 @@ mod tests {
         let clipboard_func = ClipboardFunc::new(
             "test_device".to_string(),
             Arc::new(engine),
-            Arc::new(storage),
+            Arc::new(StorageSlot::new(storage)),
         );
 
         assert_eq!(clipboard_func.device_id, "test_device");
     }
+
+    #[tokio::test]
+    async fn test_sync_filter_excludes_images() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["local", "peer"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(ClipboardFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage))));
+        }
+        let (local, peer) = (&funcs[0], &funcs[1]);
+        local.set_sync_filter(ClipboardSyncFilter::allow_all().with_allowed_kinds(["text"]));
+
+        let text_id = local.add_clipboard("text", b"shared text").await.expect("添加剪切板失败");
+        let image_id = local.add_clipboard("image/png", b"\x89PNG private image").await.expect("添加剪切板失败");
+        assert_eq!(local.storage.current().clipboard.entry_count(), 2, "被过滤的条目仍保存在本地");
+
+        // 完整同步和差异同步都不包含图片
+        let (token, count) = local.peer_sync_token("peer").await.expect("创建同步令牌失败");
+        assert_eq!(count, 1);
+        peer.handler().handle_token(token).await.expect("处理同步失败");
+        let (diff, count) = local.peer_diff_token("peer", 0).await.expect("创建差异令牌失败")
+            .expect("应有差异");
+        assert_eq!(count, 1);
+        peer.handler().handle_token(diff).await.expect("处理差异失败");
+
+        let clipboard = &peer.storage.current().clipboard;
+        assert!(clipboard.get_entry(&text_id).await.is_ok());
+        assert!(clipboard.get_entry(&image_id).await.is_err(), "图片不应同步到对等设备");
+        assert_eq!(clipboard.entry_count(), 1);
+
+        // 大小限制
+        let filter = ClipboardSyncFilter::allow_all().with_max_size(4);
+        let entry = local.storage.current().clipboard.get_entry(&text_id).await.expect("获取失败");
+        assert!(!filter.allows(&entry));
+        assert!(ClipboardSyncFilter::allow_all().allows(&entry));
+    }
+
+    #[tokio::test]
+    async fn test_file_ref_entries_sync_with_content() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["local", "peer"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(ClipboardFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage))));
+        }
+        let (local, peer) = (&funcs[0], &funcs[1]);
+
+        let file = vec![5u8; 64];
+        let file_id = local.storage.current().add_clipboard_file(&file, "file".to_string()).await
+            .expect("添加文件引用失败");
+
+        // 大小限制按对象的实际大小判断，而不是条目内嵌内容的长度
+        local.set_sync_filter(ClipboardSyncFilter::allow_all().with_max_size(16));
+        let (_, count) = local.peer_sync_token("peer").await.expect("创建同步令牌失败");
+        assert_eq!(count, 0);
+
+        // 文件内容随条目发送，对端写入自己的对象存储
+        local.set_sync_filter(ClipboardSyncFilter::allow_all());
+        let (token, count) = local.peer_sync_token("peer").await.expect("创建同步令牌失败");
+        assert_eq!(count, 1);
+        peer.handler().handle_token(token).await.expect("处理同步失败");
+
+        let storage = peer.storage.current();
+        let entry = storage.clipboard.get_entry(&file_id).await.expect("文件引用条目应已同步");
+        assert!(entry.content.is_empty());
+        assert_eq!(storage.clipboard_content(&file_id).await.expect("读取剪切板内容失败"), file);
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_ring_converges_across_devices() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["laptop", "desktop"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(ClipboardFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage)))
+                .with_ring_capacity(4, 1024));
+        }
+        let (laptop, desktop) = (&funcs[0], &funcs[1]);
+
+        // 两台设备交替复制，部分条目在同步之前产生
+        laptop.ring().push("text", b"laptop 1".to_vec()).expect("复制失败");
+        desktop.ring().push("text", b"desktop 1".to_vec()).expect("复制失败");
+        laptop.ring().push("text", b"laptop 2".to_vec()).expect("复制失败");
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        desktop.ring().push("text", b"desktop 2".to_vec()).expect("复制失败");
+        desktop.ring().push("text", b"desktop 3".to_vec()).expect("复制失败");
+
+        let (token, count) = laptop.peer_ring_token("desktop").expect("创建历史环令牌失败");
+        assert_eq!(count, 2);
+        desktop.handler().handle_token(token).await.expect("处理历史环同步失败");
+        let (token, _) = desktop.peer_ring_token("laptop").expect("创建历史环令牌失败");
+        laptop.handler().handle_token(token).await.expect("处理历史环同步失败");
+
+        // 两台设备得到相同的顺序，超出容量的最旧条目被丢弃
+        assert_eq!(laptop.ring().items(), desktop.ring().items());
+        assert_eq!(laptop.ring().len(), 4);
+        assert_eq!(laptop.ring().peek(0).expect("应有条目").content, b"desktop 3");
+        assert_eq!(laptop.ring().peek(1).expect("应有条目").content, b"desktop 2");
+
+        // 之后的复制位于环顶，再次同步后仍然一致
+        laptop.ring().push("text", b"laptop 3".to_vec()).expect("复制失败");
+        let (token, _) = laptop.peer_ring_token("desktop").expect("创建历史环令牌失败");
+        desktop.handler().handle_token(token).await.expect("处理历史环同步失败");
+        assert_eq!(desktop.ring().peek(0).expect("应有条目").content, b"laptop 3");
+        assert_eq!(laptop.ring().items(), desktop.ring().items());
+    }
+
+    #[tokio::test]
+    async fn test_sync_conflicts_are_surfaced_and_resolved() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["laptop", "desktop"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(ClipboardFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage))));
+        }
+        let (laptop, desktop) = (&funcs[0], &funcs[1]);
+
+        let id = laptop.add_clipboard("text", b"base").await.expect("添加剪切板失败");
+        let (token, _) = laptop.peer_sync_token("desktop").await.expect("创建同步令牌失败");
+        desktop.handler().handle_token(token).await.expect("处理同步失败");
+
+        // 两台设备在同步前各自修改同一条目
+        laptop.storage.current().clipboard.update_entry(&id, b"laptop edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        desktop.storage.current().clipboard.update_entry(&id, b"desktop edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+
+        // 未设置自动解决时冲突进入待解决列表，本地内容不变
+        let (diff, _) = desktop.peer_diff_token("laptop", 0).await.expect("创建差异令牌失败").expect("应有差异");
+        laptop.handler().handle_token(diff).await.expect("处理差异失败");
+        let pending = laptop.pending_conflicts();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].remote.content, b"desktop edit");
+        assert_eq!(laptop.storage.current().clipboard.get_entry(&id).await.expect("获取失败").content, b"laptop edit");
+
+        let written = laptop.resolve_conflict(&id, ConflictResolution::KeepRemote).await.expect("解决冲突失败");
+        assert_eq!(written[0].content, b"desktop edit");
+        assert!(laptop.pending_conflicts().is_empty());
+        assert!(laptop.resolve_conflict(&id, ConflictResolution::KeepRemote).await.is_err());
+
+        // 设置自动解决后冲突直接解决，不进入待解决列表
+        desktop.storage.current().clipboard.update_entry(&id, b"desktop again".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        laptop.storage.current().clipboard.update_entry(&id, b"laptop again".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        desktop.set_conflict_resolution(Some(ConflictResolution::KeepLocal));
+        let (diff, _) = laptop.peer_diff_token("desktop", 0).await.expect("创建差异令牌失败").expect("应有差异");
+        desktop.handler().handle_token(diff).await.expect("处理差异失败");
+        assert!(desktop.pending_conflicts().is_empty());
+        let entry = desktop.storage.current().clipboard.get_entry(&id).await.expect("获取失败");
+        assert_eq!(entry.content, b"desktop again");
+        assert_eq!(entry.clock.get("desktop"), Some(&3));
+    }
 }
@@ -0,0 +1,280 @@
+//! # 剪切板历史环
+//!
+//! 保存最近复制的多个条目并在设备之间同步整个环，供界面提供从历史粘贴。
+//! 条目按时间戳从新到旧排列，时间戳相同时依次比较设备ID和条目ID，
+//! 因此任意设备合并同一组条目后得到相同的顺序。合并取条目并集后，
+//! 从最新的条目开始保留，直到达到条目数或总字节数上限。
+//!
+//! 其他设备发来的条目时间戳超前本地时钟过多，或ID不合规时不参与合并，
+//! 避免一个条目永久占据环顶。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::FuncResult;
+
+/// 默认最多保留的条目数
+pub const DEFAULT_RING_CAPACITY: usize = 20;
+/// 默认最多保留的内容总字节数
+pub const DEFAULT_RING_MAX_BYTES: usize = 16 * 1024 * 1024;
+/// 合并时允许其他设备的时间戳超前本地时钟的最大毫秒数
+pub const MAX_RING_CLOCK_SKEW_MS: u64 = 5 * 60 * 1000;
+/// 条目ID和设备ID的最大长度（字节）
+const MAX_RING_ID_LEN: usize = 256;
+
+/// 剪切板历史环中的条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardRingItem {
+    /// 条目ID
+    pub id: String,
+    /// 复制该条目的设备ID
+    pub device_id: String,
+    /// 内容类型
+    pub content_type: String,
+    /// 内容数据
+    pub content: Vec<u8>,
+    /// 复制时间（Unix毫秒时间戳）
+    pub timestamp: u64,
+}
+
+impl ClipboardRingItem {
+    /// 条目是否可以合并到本地环
+    ///
+    /// # 参数
+    ///
+    /// * `now` - 本地当前Unix毫秒时间戳
+    fn is_acceptable(&self, now: u64) -> bool {
+        self.timestamp <= now.saturating_add(MAX_RING_CLOCK_SKEW_MS)
+            && !self.device_id.is_empty()
+            && self.device_id.len() <= MAX_RING_ID_LEN
+            && self.id.len() <= MAX_RING_ID_LEN
+            && self.id.starts_with(&format!("{}-", self.device_id))
+    }
+
+    /// 环中的排列顺序：较新的在前
+    fn ring_order(&self, other: &Self) -> Ordering {
+        other.timestamp.cmp(&self.timestamp)
+            .then_with(|| other.device_id.cmp(&self.device_id))
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+/// 剪切板历史环
+pub struct ClipboardRing {
+    /// 本地设备ID
+    device_id: String,
+    /// 最多保留的条目数
+    capacity: usize,
+    /// 最多保留的内容总字节数
+    max_bytes: usize,
+    /// 从新到旧排列的条目
+    items: Mutex<Vec<ClipboardRingItem>>,
+}
+
+impl ClipboardRing {
+    /// 创建剪切板历史环
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 本地设备ID
+    /// * `capacity` - 最多保留的条目数
+    pub fn new(device_id: String, capacity: usize) -> Self {
+        Self {
+            device_id,
+            capacity,
+            max_bytes: DEFAULT_RING_MAX_BYTES,
+            items: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 设置最多保留的内容总字节数
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// 最多保留的条目数
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 最多保留的内容总字节数
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// 复制新条目到环顶
+    ///
+    /// 新条目的时间戳不早于环中最新的条目，保证本地复制的内容位于环顶
+    ///
+    /// # 参数
+    ///
+    /// * `content_type` - 内容类型
+    /// * `content` - 内容数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回新条目，内容超过总字节数上限时返回错误
+    pub fn push(&self, content_type: &str, content: Vec<u8>) -> FuncResult<ClipboardRingItem> {
+        if content.len() > self.max_bytes {
+            return Err(ErrorInfo::new(7216, format!("剪切板内容过大: {} 字节，上限 {} 字节", content.len(), self.max_bytes))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let mut items = self.lock_items();
+        let newest = items.first().map(|item| item.timestamp.saturating_add(1)).unwrap_or(0);
+        let item = ClipboardRingItem {
+            id: format!("{}-{:016x}", self.device_id, fastrand::u64(..)),
+            device_id: self.device_id.clone(),
+            content_type: content_type.to_string(),
+            content,
+            timestamp: now_millis().max(newest),
+        };
+        items.insert(0, item.clone());
+        self.enforce_limits(&mut items);
+        Ok(item)
+    }
+
+    /// 查看指定位置的条目
+    ///
+    /// # 参数
+    ///
+    /// * `index` - 位置，0 为最新的条目
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目，位置超出范围时返回 `None`
+    pub fn peek(&self, index: usize) -> Option<ClipboardRingItem> {
+        self.lock_items().get(index).cloned()
+    }
+
+    /// 从新到旧的所有条目
+    pub fn items(&self) -> Vec<ClipboardRingItem> {
+        self.lock_items().clone()
+    }
+
+    /// 条目数量
+    pub fn len(&self) -> usize {
+        self.lock_items().len()
+    }
+
+    /// 环是否为空
+    pub fn is_empty(&self) -> bool {
+        self.lock_items().is_empty()
+    }
+
+    /// 合并其他设备的环
+    ///
+    /// 时间戳超前本地时钟超过 [`MAX_RING_CLOCK_SKEW_MS`]、内容超过总字节数上限，
+    /// 或设备ID为空、过长、与条目ID不符的条目被忽略
+    ///
+    /// # 参数
+    ///
+    /// * `remote` - 其他设备环中的条目，顺序不限
+    ///
+    /// # 返回值
+    ///
+    /// 本地环的内容发生变化时返回 `true`
+    pub fn merge(&self, remote: Vec<ClipboardRingItem>) -> bool {
+        let mut items = self.lock_items();
+        let before: Vec<String> = items.iter().map(|item| item.id.clone()).collect();
+
+        let now = now_millis();
+        let mut known: HashSet<String> = before.iter().cloned().collect();
+        for item in remote {
+            if !item.is_acceptable(now) {
+                warn!("忽略剪切板环中的无效条目: {} (设备: {}, 时间戳: {})", item.id, item.device_id, item.timestamp);
+                continue;
+            }
+            if item.content.len() <= self.max_bytes && known.insert(item.id.clone()) {
+                items.push(item);
+            }
+        }
+        items.sort_by(ClipboardRingItem::ring_order);
+        self.enforce_limits(&mut items);
+
+        !items.iter().map(|item| &item.id).eq(before.iter())
+    }
+
+    /// 从最新的条目开始保留，直到达到条目数或总字节数上限
+    fn enforce_limits(&self, items: &mut Vec<ClipboardRingItem>) {
+        let mut total_bytes = 0;
+        let keep = items.iter()
+            .take(self.capacity)
+            .take_while(|item| {
+                total_bytes += item.content.len();
+                total_bytes <= self.max_bytes
+            })
+            .count();
+        items.truncate(keep);
+    }
+
+    /// 获取条目锁，锁被毒化时继续使用其中的数据
+    fn lock_items(&self) -> std::sync::MutexGuard<'_, Vec<ClipboardRingItem>> {
+        self.items.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 当前Unix毫秒时间戳
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_capacity_and_size_cap() {
+        let ring = ClipboardRing::new("device".to_string(), 3).with_max_bytes(10);
+        for content in ["a", "b", "c", "d"] {
+            ring.push("text", content.as_bytes().to_vec()).expect("复制失败");
+        }
+        let contents: Vec<Vec<u8>> = ring.items().into_iter().map(|item| item.content).collect();
+        assert_eq!(contents, vec![b"d".to_vec(), b"c".to_vec(), b"b".to_vec()]);
+        assert_eq!(ring.peek(0).expect("应有条目").content, b"d");
+        assert!(ring.peek(3).is_none());
+
+        // 总字节数超过上限时丢弃较旧的条目
+        ring.push("text", b"123456789".to_vec()).expect("复制失败");
+        assert_eq!(ring.len(), 2);
+        assert!(ring.push("text", vec![0; 11]).is_err(), "超过上限的内容不能复制");
+    }
+
+    #[test]
+    fn test_merge_rejects_future_and_invalid_items() {
+        let ring = ClipboardRing::new("local".to_string(), 5);
+        let remote = |id: &str, device_id: &str, timestamp: u64| ClipboardRingItem {
+            id: id.to_string(),
+            device_id: device_id.to_string(),
+            content_type: "text".to_string(),
+            content: b"x".to_vec(),
+            timestamp,
+        };
+
+        let changed = ring.merge(vec![
+            remote("peer-1", "peer", u64::MAX),
+            remote("peer-2", "peer", now_millis() + 2 * MAX_RING_CLOCK_SKEW_MS),
+            remote("other-3", "peer", 1),
+            remote("-4", "", 1),
+            remote(&format!("{}-5", "d".repeat(300)), &"d".repeat(300), 1),
+        ]);
+        assert!(!changed, "无效条目不应合并");
+        assert!(ring.is_empty());
+
+        // 轻微超前的时间戳可以合并，本地复制的条目仍位于环顶且不溢出
+        ring.merge(vec![remote("peer-6", "peer", now_millis() + 1000)]);
+        let item = ring.push("text", b"local".to_vec()).expect("复制失败");
+        assert_eq!(ring.peek(0), Some(item));
+        assert_eq!(ring.len(), 2);
+    }
+}
@@ -0,0 +1,513 @@
+//! # 可续传文件流
+//!
+//! 点对点流式文件传输的协议令牌和两端状态：
+//!
+//! - 发送方先发送打开令牌，再按块发送文件数据，每个令牌都等待接收方的确认令牌
+//! - 接收方只接受从已确认偏移开始的数据块，确认令牌回报当前已接收的偏移
+//! - 连接中断后发送方再次发送打开令牌查询接收方的偏移，从该偏移继续发送
+//! - 全部数据确认后发送方发送签名清单，接收方验签通过才保存文件
+//!
+//! 接收方把未完成的数据写入暂存目录而不是保存在内存中，
+//! 每个发送方和所有发送方暂存的数据量都有上限，超出上限的数据块被拒绝。
+
+use bey_net::{Token, TokenId, TokenMeta};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+use crate::chat::ChatOutbound;
+use crate::FuncResult;
+
+/// 文件流令牌类型
+pub(crate) const STREAM_OPEN_TOKEN: &str = "bey.storage.stream.open";
+pub(crate) const STREAM_CHUNK_TOKEN: &str = "bey.storage.stream.chunk";
+pub(crate) const STREAM_COMPLETE_TOKEN: &str = "bey.storage.stream.complete";
+pub(crate) const STREAM_ACK_TOKEN: &str = "bey.storage.stream.ack";
+
+/// 传输ID令牌属性
+const TRANSFER_ATTRIBUTE: &str = "bey.stream.transfer";
+/// 文件名令牌属性
+const FILENAME_ATTRIBUTE: &str = "bey.stream.filename";
+/// 偏移令牌属性
+const OFFSET_ATTRIBUTE: &str = "bey.stream.offset";
+/// 确认状态令牌属性
+const STATUS_ATTRIBUTE: &str = "bey.stream.status";
+/// 被确认的请求令牌ID属性
+const REPLY_ATTRIBUTE: &str = "bey.stream.reply";
+
+/// 等待接收方确认的默认超时时间
+pub(crate) const DEFAULT_STREAM_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 接收方保留未完成传输和已完成记录的时间
+const STREAM_RETENTION: Duration = Duration::from_secs(3600);
+
+/// 每个发送方默认最多暂存的数据量（字节）
+pub(crate) const DEFAULT_STREAM_PEER_SPOOL_LIMIT: u64 = 1024 * 1024 * 1024;
+
+/// 所有发送方默认最多暂存的数据量（字节）
+pub(crate) const DEFAULT_STREAM_TOTAL_SPOOL_LIMIT: u64 = 4 * 1024 * 1024 * 1024;
+
+/// 接收方对文件流令牌的确认
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StreamAck {
+    /// 接收方已确认的偏移（完成时为文件大小）
+    pub(crate) offset: u64,
+    /// 传输状态
+    pub(crate) status: StreamStatus,
+}
+
+/// 接收方的传输状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum StreamStatus {
+    /// 正在接收
+    Receiving,
+    /// 文件已验证并保存
+    Complete,
+    /// 接收方拒绝，附带原因
+    Rejected(String),
+}
+
+impl StreamAck {
+    fn receiving(offset: u64) -> Self {
+        Self { offset, status: StreamStatus::Receiving }
+    }
+
+    /// 创建拒绝确认
+    pub(crate) fn rejected(reason: impl Into<String>) -> Self {
+        Self { offset: 0, status: StreamStatus::Rejected(reason.into()) }
+    }
+
+    /// 创建完成确认
+    pub(crate) fn complete(size: u64) -> Self {
+        Self { offset: size, status: StreamStatus::Complete }
+    }
+
+    /// 接收方已确认的偏移，接收方拒绝时返回错误
+    pub(crate) fn accepted_offset(&self) -> FuncResult<u64> {
+        match &self.status {
+            StreamStatus::Rejected(reason) => Err(ErrorInfo::new(7320, format!("接收方拒绝文件传输: {}", reason))
+                .with_category(ErrorCategory::Permission)
+                .with_severity(ErrorSeverity::Error)),
+            _ => Ok(self.offset),
+        }
+    }
+}
+
+/// 创建打开（或查询偏移）令牌
+pub(crate) fn stream_open_token(device_id: &str, peer_id: &str, transfer_id: &str, filename: &str) -> Token {
+    let meta = TokenMeta::new(STREAM_OPEN_TOKEN.to_string(), device_id.to_string())
+        .with_receiver(peer_id.to_string())
+        .with_attribute(TRANSFER_ATTRIBUTE.to_string(), transfer_id.to_string())
+        .with_attribute(FILENAME_ATTRIBUTE.to_string(), filename.to_string());
+    Token::new(meta, Vec::new())
+}
+
+/// 创建数据块令牌
+pub(crate) fn stream_chunk_token(device_id: &str, peer_id: &str, transfer_id: &str, offset: u64, data: &[u8]) -> Token {
+    let meta = TokenMeta::new(STREAM_CHUNK_TOKEN.to_string(), device_id.to_string())
+        .with_receiver(peer_id.to_string())
+        .with_attribute(TRANSFER_ATTRIBUTE.to_string(), transfer_id.to_string())
+        .with_attribute(OFFSET_ATTRIBUTE.to_string(), offset.to_string());
+    Token::new(meta, data.to_vec())
+}
+
+/// 创建完成令牌，负载为签名清单
+pub(crate) fn stream_complete_token(device_id: &str, peer_id: &str, transfer_id: &str, manifest_payload: Vec<u8>) -> Token {
+    let meta = TokenMeta::new(STREAM_COMPLETE_TOKEN.to_string(), device_id.to_string())
+        .with_receiver(peer_id.to_string())
+        .with_attribute(TRANSFER_ATTRIBUTE.to_string(), transfer_id.to_string());
+    Token::new(meta, manifest_payload)
+}
+
+/// 创建对请求令牌的确认令牌
+pub(crate) fn stream_ack_token(request: &Token, ack: &StreamAck) -> Token {
+    let (status, reason) = match &ack.status {
+        StreamStatus::Receiving => ("receiving", Vec::new()),
+        StreamStatus::Complete => ("complete", Vec::new()),
+        StreamStatus::Rejected(reason) => ("rejected", reason.as_bytes().to_vec()),
+    };
+    let mut meta = TokenMeta::new(
+        STREAM_ACK_TOKEN.to_string(),
+        request.meta.receiver_id.clone().unwrap_or_default(),
+    )
+        .with_receiver(request.meta.sender_id.clone())
+        .with_attribute(REPLY_ATTRIBUTE.to_string(), request.meta.id.clone())
+        .with_attribute(OFFSET_ATTRIBUTE.to_string(), ack.offset.to_string())
+        .with_attribute(STATUS_ATTRIBUTE.to_string(), status.to_string());
+    if let Some(transfer_id) = stream_transfer_id(request) {
+        meta = meta.with_attribute(TRANSFER_ATTRIBUTE.to_string(), transfer_id.to_string());
+    }
+    Token::new(meta, reason)
+}
+
+/// 令牌所属的传输ID
+pub(crate) fn stream_transfer_id(token: &Token) -> Option<&str> {
+    token.meta.attributes.get(TRANSFER_ATTRIBUTE).map(String::as_str)
+}
+
+/// 打开令牌中的文件名
+pub(crate) fn stream_filename(token: &Token) -> Option<&str> {
+    token.meta.attributes.get(FILENAME_ATTRIBUTE).map(String::as_str)
+}
+
+/// 数据块令牌中的偏移
+pub(crate) fn stream_offset(token: &Token) -> Option<u64> {
+    token.meta.attributes.get(OFFSET_ATTRIBUTE)?.parse().ok()
+}
+
+/// 解析确认令牌，返回被确认的请求令牌ID和确认内容
+fn parse_ack(token: &Token) -> Option<(TokenId, StreamAck)> {
+    let reply_to = token.meta.attributes.get(REPLY_ATTRIBUTE)?.clone();
+    let offset = stream_offset(token)?;
+    let status = match token.meta.attributes.get(STATUS_ATTRIBUTE)?.as_str() {
+        "receiving" => StreamStatus::Receiving,
+        "complete" => StreamStatus::Complete,
+        "rejected" => StreamStatus::Rejected(String::from_utf8_lossy(&token.payload).to_string()),
+        _ => return None,
+    };
+    Some((reply_to, StreamAck { offset, status }))
+}
+
+/// 发送方等待中的确认
+#[derive(Default)]
+pub(crate) struct StreamAcks {
+    /// 请求令牌ID -> 确认通知
+    waiters: Mutex<HashMap<TokenId, oneshot::Sender<StreamAck>>>,
+}
+
+impl StreamAcks {
+    /// 发送令牌并等待接收方的确认
+    ///
+    /// # 参数
+    ///
+    /// * `outbound` - 出站通道
+    /// * `token` - 请求令牌
+    /// * `timeout` - 等待确认的超时时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回接收方的确认，发送失败或超时返回可重试的错误
+    pub(crate) async fn exchange(&self, outbound: &dyn ChatOutbound, token: Token, timeout: Duration) -> FuncResult<StreamAck> {
+        let token_id = token.meta.id.clone();
+        let (sender, receiver) = oneshot::channel();
+        self.lock_waiters().insert(token_id.clone(), sender);
+
+        let result = match outbound.send_token(token).await {
+            Ok(()) => match tokio::time::timeout(timeout, receiver).await {
+                Ok(Ok(ack)) => Ok(ack),
+                _ => Err(ErrorInfo::new(7319, format!("等待接收方确认超时: {:?}", timeout))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Warning)),
+            },
+            Err(e) => Err(e),
+        };
+        self.lock_waiters().remove(&token_id);
+        result
+    }
+
+    /// 把收到的确认令牌交给等待者，没有等待者的确认（如超时后迟到的确认）被忽略
+    pub(crate) fn deliver(&self, token: &Token) {
+        let Some((reply_to, ack)) = parse_ack(token) else {
+            debug!("忽略格式无效的文件流确认: {}", token.meta.id);
+            return;
+        };
+        match self.lock_waiters().remove(&reply_to) {
+            Some(waiter) => {
+                let _ = waiter.send(ack);
+            }
+            None => debug!("忽略迟到的文件流确认: {}", reply_to),
+        }
+    }
+
+    fn lock_waiters(&self) -> std::sync::MutexGuard<'_, HashMap<TokenId, oneshot::Sender<StreamAck>>> {
+        self.waiters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 接收中的文件
+struct PartialTransfer {
+    filename: String,
+    /// 暂存文件路径
+    path: PathBuf,
+    /// 已写入暂存文件的字节数
+    received: u64,
+    /// 正在写入的数据块大小，没有数据块正在写入时为 `None`
+    writing: Option<u64>,
+    updated_at: Instant,
+}
+
+/// 接收方的文件流状态
+///
+/// 传输按（发送方设备ID, 传输ID）区分，超过保留时间没有进展的传输被丢弃，
+/// 未完成的数据暂存在磁盘上
+pub(crate) struct StreamReceiver {
+    /// 暂存目录
+    spool_dir: PathBuf,
+    /// 每个发送方最多暂存的数据量
+    max_peer_bytes: u64,
+    /// 所有发送方最多暂存的数据量
+    max_total_bytes: u64,
+    /// 接收中的文件
+    partials: Mutex<HashMap<(String, String), PartialTransfer>>,
+    /// 已完成的传输 -> (文件大小, 完成时间)，用于回应重复的完成令牌
+    completed: Mutex<HashMap<(String, String), (u64, Instant)>>,
+}
+
+impl Default for StreamReceiver {
+    fn default() -> Self {
+        let spool_dir = std::env::temp_dir()
+            .join(format!("bey-stream-{}-{:016x}", std::process::id(), fastrand::u64(..)));
+        Self::new(spool_dir, DEFAULT_STREAM_PEER_SPOOL_LIMIT, DEFAULT_STREAM_TOTAL_SPOOL_LIMIT)
+    }
+}
+
+impl StreamReceiver {
+    /// 创建接收方状态
+    ///
+    /// # 参数
+    ///
+    /// * `spool_dir` - 暂存目录，首次收到数据时创建
+    /// * `max_peer_bytes` - 每个发送方最多暂存的数据量（字节）
+    /// * `max_total_bytes` - 所有发送方最多暂存的数据量（字节）
+    pub(crate) fn new(spool_dir: PathBuf, max_peer_bytes: u64, max_total_bytes: u64) -> Self {
+        Self {
+            spool_dir,
+            max_peer_bytes,
+            max_total_bytes,
+            partials: Mutex::new(HashMap::new()),
+            completed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 打开传输或查询已接收的偏移
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送方设备ID
+    /// * `transfer_id` - 传输ID
+    /// * `filename` - 文件名
+    pub(crate) async fn open(&self, sender_id: &str, transfer_id: &str, filename: &str) -> StreamAck {
+        if let Some(ack) = self.completed_ack(sender_id, transfer_id) {
+            return ack;
+        }
+        let key = (sender_id.to_string(), transfer_id.to_string());
+
+        let now = Instant::now();
+        let (ack, expired) = {
+            let mut partials = self.lock_partials();
+            let expired = take_expired(&mut partials, now);
+            let partial = partials.entry(key).or_insert_with(|| {
+                debug!("开始接收文件流: {} 来自 {} ({})", filename, sender_id, transfer_id);
+                PartialTransfer {
+                    filename: filename.to_string(),
+                    path: self.spool_dir.join(format!("{:016x}.part", fastrand::u64(..))),
+                    received: 0,
+                    writing: None,
+                    updated_at: now,
+                }
+            });
+            let ack = if partial.filename != filename {
+                StreamAck::rejected(format!("传输 {} 的文件名不一致", transfer_id))
+            } else {
+                partial.updated_at = now;
+                StreamAck::receiving(partial.received)
+            };
+            (ack, expired)
+        };
+
+        remove_spool_files(expired).await;
+        ack
+    }
+
+    /// 追加数据块
+    ///
+    /// 只接受从已接收偏移开始的数据块，其他偏移（重复或超前的数据块）不改变状态，
+    /// 确认中的偏移告诉发送方应从哪里继续。写入后超出暂存上限的数据块被拒绝
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送方设备ID
+    /// * `transfer_id` - 传输ID
+    /// * `offset` - 数据块在文件中的偏移
+    /// * `data` - 数据块
+    pub(crate) async fn append(&self, sender_id: &str, transfer_id: &str, offset: u64, data: &[u8]) -> StreamAck {
+        if let Some(ack) = self.completed_ack(sender_id, transfer_id) {
+            return ack;
+        }
+        let key = (sender_id.to_string(), transfer_id.to_string());
+        let size = data.len() as u64;
+
+        let path = {
+            let mut partials = self.lock_partials();
+            let (peer_bytes, total_bytes) = partials.iter()
+                .fold((0u64, 0u64), |(peer, total), ((sender, _), partial)| {
+                    let spooled = partial.received + partial.writing.unwrap_or(0);
+                    let peer = if sender == sender_id { peer + spooled } else { peer };
+                    (peer, total + spooled)
+                });
+            let Some(partial) = partials.get_mut(&key) else {
+                return StreamAck::rejected(format!("未知传输: {}", transfer_id));
+            };
+            partial.updated_at = Instant::now();
+            if partial.writing.is_some() || offset != partial.received {
+                debug!("忽略偏移 {} 的数据块，已接收 {} 字节 ({})", offset, partial.received, transfer_id);
+                return StreamAck::receiving(partial.received);
+            }
+            if peer_bytes.saturating_add(size) > self.max_peer_bytes
+                || total_bytes.saturating_add(size) > self.max_total_bytes
+            {
+                warn!("拒绝 {} 的数据块: 超出文件流暂存上限 ({})", sender_id, transfer_id);
+                return StreamAck::rejected("超出接收方的文件暂存上限");
+            }
+            partial.writing = Some(size);
+            partial.path.clone()
+        };
+
+        let written = self.write_chunk(&path, offset, data).await;
+
+        let mut partials = self.lock_partials();
+        let Some(partial) = partials.get_mut(&key) else {
+            // 写入期间传输已过期或被取出
+            return StreamAck::rejected(format!("未知传输: {}", transfer_id));
+        };
+        partial.writing = None;
+        partial.updated_at = Instant::now();
+        match written {
+            Ok(()) => partial.received += size,
+            Err(e) => warn!("写入文件流暂存失败: {} ({})", e, transfer_id),
+        }
+        StreamAck::receiving(partial.received)
+    }
+
+    /// 把数据块写入暂存文件的指定偏移
+    async fn write_chunk(&self, path: &PathBuf, offset: u64, data: &[u8]) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.spool_dir).await?;
+        let mut file = tokio::fs::OpenOptions::new().write(true).create(true).truncate(false).open(path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        file.write_all(data).await?;
+        file.flush().await
+    }
+
+    /// 取出接收完的文件，返回文件名和数据
+    ///
+    /// 暂存文件在读取后删除，仍有数据块正在写入时返回 `None`
+    pub(crate) async fn take(&self, sender_id: &str, transfer_id: &str) -> Option<(String, Vec<u8>)> {
+        let key = (sender_id.to_string(), transfer_id.to_string());
+        let partial = {
+            let mut partials = self.lock_partials();
+            if partials.get(&key)?.writing.is_some() {
+                return None;
+            }
+            partials.remove(&key)?
+        };
+
+        let data = if partial.received == 0 {
+            Ok(Vec::new())
+        } else {
+            tokio::fs::read(&partial.path).await
+        };
+        let _ = tokio::fs::remove_file(&partial.path).await;
+        match data {
+            Ok(mut data) => {
+                // 写入失败的数据块可能在文件末尾留下未确认的数据
+                data.truncate(partial.received as usize);
+                Some((partial.filename, data))
+            }
+            Err(e) => {
+                warn!("读取文件流暂存失败: {} ({})", e, transfer_id);
+                None
+            }
+        }
+    }
+    /// 记录已完成的传输
+    pub(crate) fn mark_completed(&self, sender_id: &str, transfer_id: &str, size: u64) {
+        let now = Instant::now();
+        let mut completed = self.completed.lock().unwrap_or_else(|e| e.into_inner());
+        completed.retain(|_, (_, completed_at)| now.duration_since(*completed_at) < STREAM_RETENTION);
+        completed.insert((sender_id.to_string(), transfer_id.to_string()), (size, now));
+    }
+
+    /// 已完成传输的确认，传输未完成时返回 None
+    pub(crate) fn completed_ack(&self, sender_id: &str, transfer_id: &str) -> Option<StreamAck> {
+        self.completed.lock().unwrap_or_else(|e| e.into_inner())
+            .get(&(sender_id.to_string(), transfer_id.to_string()))
+            .map(|(size, _)| StreamAck::complete(*size))
+    }
+
+    fn lock_partials(&self) -> std::sync::MutexGuard<'_, HashMap<(String, String), PartialTransfer>> {
+        self.partials.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for StreamReceiver {
+    fn drop(&mut self) {
+        for partial in self.lock_partials().values() {
+            let _ = std::fs::remove_file(&partial.path);
+        }
+        // 目录中没有其他文件时才会被删除
+        let _ = std::fs::remove_dir(&self.spool_dir);
+    }
+}
+
+/// 取出超过保留时间没有进展的传输，返回其暂存文件
+fn take_expired(partials: &mut HashMap<(String, String), PartialTransfer>, now: Instant) -> Vec<PathBuf> {
+    let mut expired = Vec::new();
+    partials.retain(|_, partial| {
+        let keep = partial.writing.is_some() || now.duration_since(partial.updated_at) < STREAM_RETENTION;
+        if !keep {
+            expired.push(partial.path.clone());
+        }
+        keep
+    });
+    expired
+}
+
+/// 删除暂存文件
+async fn remove_spool_files(paths: Vec<PathBuf>) {
+    for path in paths {
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_receiver_spools_to_disk_within_limits() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let spool_dir = temp_dir.path().join("spool");
+        let receiver = StreamReceiver::new(spool_dir.clone(), 8, 12);
+
+        assert_eq!(receiver.open("alice", "t1", "a.bin").await, StreamAck::receiving(0));
+        assert_eq!(receiver.append("alice", "t1", 0, b"abcd").await, StreamAck::receiving(4));
+        // 重复的数据块不改变偏移
+        assert_eq!(receiver.append("alice", "t1", 0, b"abcd").await, StreamAck::receiving(4));
+        assert_eq!(std::fs::read_dir(&spool_dir).expect("读取暂存目录失败").count(), 1);
+
+        // 单个发送方超出上限
+        receiver.open("alice", "t2", "b.bin").await;
+        assert_eq!(receiver.append("alice", "t2", 0, b"12345").await.status,
+            StreamStatus::Rejected("超出接收方的文件暂存上限".to_string()));
+
+        // 所有发送方合计超出上限
+        receiver.open("bob", "t3", "c.bin").await;
+        assert_eq!(receiver.append("bob", "t3", 0, b"123456").await, StreamAck::receiving(6));
+        assert!(matches!(receiver.append("bob", "t3", 6, b"123").await.status, StreamStatus::Rejected(_)));
+
+        // 取出后删除暂存文件并释放额度
+        let (filename, data) = receiver.take("alice", "t1").await.expect("应能取出文件");
+        assert_eq!((filename.as_str(), data.as_slice()), ("a.bin", b"abcd".as_slice()));
+        assert_eq!(receiver.append("alice", "t2", 0, b"12345").await, StreamAck::receiving(5));
+
+        drop(receiver);
+        assert!(!spool_dir.exists(), "释放后应删除暂存文件");
+    }
+}
@@ -0,0 +1,219 @@
+//! # 幂等操作模块
+//!
+//! 按调用方提供的幂等键对变更操作去重：窗口期内使用相同键的重复调用
+//! 直接返回首次执行的结果，不会再次执行。并发的相同键调用会等待正在执行的调用完成。
+//!
+//! 每个幂等键同时记录首次调用的请求摘要，窗口期内用相同的键提交不同的请求会被拒绝，
+//! 不会误把另一个请求的结果返回给调用方。
+//!
+//! 只记录成功的结果，失败的调用可以使用相同的键重试。
+//! 记录数量有上限，超出时优先淘汰过期记录，其次淘汰最早的记录。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::FuncResult;
+
+/// 默认去重窗口
+pub const DEFAULT_IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(600);
+
+/// 默认最多记录的幂等键数量
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 1024;
+
+/// 单个幂等键的记录
+struct IdempotencyEntry<T> {
+    /// 首次调用时间
+    created_at: Instant,
+    /// 首次调用的请求摘要
+    fingerprint: [u8; 32],
+    /// 执行结果，首次成功后写入
+    result: Arc<OnceCell<T>>,
+}
+
+/// 幂等键到操作结果的有界映射
+pub struct IdempotencyCache<T> {
+    /// 去重窗口
+    window: Duration,
+    /// 最多记录的幂等键数量
+    capacity: usize,
+    /// 幂等键 -> 记录
+    entries: Mutex<HashMap<String, IdempotencyEntry<T>>>,
+}
+
+impl<T: Clone> IdempotencyCache<T> {
+    /// 创建幂等映射
+    ///
+    /// # 参数
+    ///
+    /// * `window` - 去重窗口，超过窗口的相同键会重新执行
+    /// * `capacity` - 最多记录的幂等键数量（至少为1）
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 当前记录的幂等键数量
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// 是否没有记录任何幂等键
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 锁定记录表
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, IdempotencyEntry<T>>> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// 获取幂等键对应的结果单元，不存在或已过期时新建
+    ///
+    /// 窗口期内的幂等键已用于其他请求时返回错误
+    fn slot(&self, key: &str, fingerprint: [u8; 32]) -> FuncResult<Arc<OnceCell<T>>> {
+        let mut entries = self.lock();
+        let now = Instant::now();
+
+        if let Some(entry) = entries.get(key) {
+            if now.duration_since(entry.created_at) < self.window {
+                if entry.fingerprint != fingerprint {
+                    return Err(ErrorInfo::new(7323, format!("幂等键 {} 已用于其他请求", key))
+                        .with_category(ErrorCategory::Validation)
+                        .with_severity(ErrorSeverity::Warning));
+                }
+                return Ok(Arc::clone(&entry.result));
+            }
+        }
+
+        if !entries.contains_key(key) && entries.len() >= self.capacity {
+            entries.retain(|_, entry| now.duration_since(entry.created_at) < self.window);
+            if entries.len() >= self.capacity {
+                let oldest = entries.iter()
+                    .min_by_key(|(_, entry)| entry.created_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        let result = Arc::new(OnceCell::new());
+        entries.insert(key.to_string(), IdempotencyEntry {
+            created_at: now,
+            fingerprint,
+            result: Arc::clone(&result),
+        });
+        Ok(result)
+    }
+
+    /// 按幂等键执行操作
+    ///
+    /// # 参数
+    ///
+    /// * `key` - 幂等键，为 `None` 时直接执行操作
+    /// * `request` - 请求的各个参数，用于识别同一幂等键下的不同请求
+    /// * `operation` - 要执行的操作
+    ///
+    /// # 返回值
+    ///
+    /// 窗口期内已有相同键的成功结果时返回该结果，否则返回本次执行的结果；
+    /// 相同的键已用于参数不同的请求时返回错误
+    pub async fn run<F, Fut>(&self, key: Option<&str>, request: &[&[u8]], operation: F) -> FuncResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = FuncResult<T>>,
+    {
+        let Some(key) = key else {
+            return operation().await;
+        };
+
+        let slot = self.slot(key, fingerprint(request))?;
+        let mut executed = false;
+        let result = slot.get_or_try_init(|| {
+            executed = true;
+            operation()
+        }).await?;
+
+        if !executed {
+            debug!("幂等键 {} 命中，返回首次执行的结果", key);
+        }
+        Ok(result.clone())
+    }
+}
+
+/// 请求摘要，每个参数带长度前缀，参数边界不同的请求摘要也不同
+fn fingerprint(request: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in request {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+impl<T: Clone> Default for IdempotencyCache<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_WINDOW, DEFAULT_IDEMPOTENCY_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::ErrorInfo;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_failed_result_is_not_cached_and_capacity_is_bounded() {
+        let cache: IdempotencyCache<u32> = IdempotencyCache::new(Duration::from_secs(60), 2);
+        let calls = AtomicUsize::new(0);
+
+        // 失败的调用不被记录，可以使用相同的键重试
+        let failed = cache.run(Some("retry"), &[b"1"], || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ErrorInfo::new(7000, "临时失败".to_string()))
+        }).await;
+        assert!(failed.is_err());
+        let value = cache.run(Some("retry"), &[b"1"], || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Ok(1)
+        }).await.expect("重试失败");
+        assert_eq!(value, 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 超出容量时淘汰最早的键
+        cache.run(Some("b"), &[b"2"], || async { Ok(2) }).await.expect("执行失败");
+        cache.run(Some("c"), &[b"3"], || async { Ok(3) }).await.expect("执行失败");
+        assert_eq!(cache.len(), 2);
+        let value = cache.run(Some("retry"), &[b"10"], || async { Ok(10) }).await.expect("执行失败");
+        assert_eq!(value, 10, "被淘汰的键应重新执行");
+    }
+
+    #[tokio::test]
+    async fn test_reused_key_with_different_request_is_rejected() {
+        let cache: IdempotencyCache<u32> = IdempotencyCache::default();
+        cache.run(Some("key"), &[b"peer", b"data"], || async { Ok(1) }).await.expect("执行失败");
+
+        let conflict = cache.run(Some("key"), &[b"peer", b"other"], || async { Ok(2) }).await;
+        assert_eq!(conflict.map_err(|e| e.code()), Err(7323));
+
+        // 参数边界不同也视为不同的请求
+        let conflict = cache.run(Some("key"), &[b"peerdata"], || async { Ok(3) }).await;
+        assert!(conflict.is_err());
+
+        let value = cache.run(Some("key"), &[b"peer", b"data"], || async { Ok(4) }).await.expect("执行失败");
+        assert_eq!(value, 1, "相同请求应返回首次执行的结果");
+    }
+}
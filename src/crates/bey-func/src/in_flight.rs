@@ -0,0 +1,120 @@
+//! # 进行中任务跟踪
+//!
+//! 记录正在进行的发送、传输和同步，应用关闭时据此排空。
+//! 每个任务开始时登记并取得守卫，守卫释放时任务完成；
+//! 跟踪器关闭后不再登记新任务，已登记的任务不受影响。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::FuncResult;
+
+/// 进行中任务跟踪器
+#[derive(Default)]
+pub struct InFlightTracker {
+    /// 下一个任务ID
+    next_id: AtomicU64,
+    /// 是否已停止接受新任务
+    closed: AtomicBool,
+    /// 进行中的任务（ID -> 描述）
+    tasks: Mutex<HashMap<u64, String>>,
+    /// 所有任务完成时的通知
+    idle: Notify,
+}
+
+impl InFlightTracker {
+    /// 创建跟踪器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记一个进行中的任务
+    ///
+    /// # 参数
+    ///
+    /// * `description` - 任务描述，排空超时时记录到日志
+    ///
+    /// # 返回值
+    ///
+    /// 返回任务守卫，跟踪器关闭后返回错误
+    pub fn begin(self: &Arc<Self>, description: impl Into<String>) -> FuncResult<InFlightGuard> {
+        let mut tasks = self.tasks.lock()
+            .map_err(|_| ErrorInfo::new(2008, "任务跟踪器锁已损坏".to_string())
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(ErrorInfo::new(2009, "应用程序正在关闭，不再接受新任务".to_string())
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        tasks.insert(id, description.into());
+
+        Ok(InFlightGuard {
+            tracker: Arc::clone(self),
+            id,
+        })
+    }
+
+    /// 停止接受新任务
+    pub fn close(&self) {
+        // 持锁设置关闭标志，保证之后不会再登记新任务
+        if let Ok(_tasks) = self.tasks.lock() {
+            self.closed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// 进行中任务数量
+    pub fn len(&self) -> usize {
+        self.tasks.lock().map(|tasks| tasks.len()).unwrap_or(0)
+    }
+
+    /// 是否没有进行中的任务
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 进行中任务的描述列表
+    pub fn pending(&self) -> Vec<String> {
+        self.tasks.lock().map(|tasks| tasks.values().cloned().collect()).unwrap_or_default()
+    }
+
+    /// 等待所有进行中任务完成
+    pub async fn wait_idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.tasks.lock().map(|tasks| tasks.is_empty()).unwrap_or(true) {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+/// 进行中任务守卫
+///
+/// 守卫存在期间任务被视为进行中，drop 时自动完成
+pub struct InFlightGuard {
+    tracker: Arc<InFlightTracker>,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Ok(mut tasks) = self.tracker.tasks.lock() {
+            tasks.remove(&self.id);
+            if tasks.is_empty() {
+                self.tracker.idle.notify_waiters();
+            }
+        }
+    }
+}
@@ -3,10 +3,11 @@
 //! 提供分布式服务的高级API，集成网络传输、存储、消息和剪切板功能。
 //! 基于 Token 元类和接收器模块，实现以下功能：
 //!
-//! - **消息发送** - 私信、群聊、广播
-//! - **剪切板同步** - 添加、删除、差异同步
+//! - **消息发送** - 私信、群聊、广播，以及按计划时间投递的定时私信
+//! - **剪切板同步** - 添加、删除、差异同步，跨设备同步的剪切板历史环
 //! - **云存储** - 文件上传、下载、分发
 //! - **对象传输** - 点对点文件传输
+//! - **统一搜索** - 跨消息、剪切板和云存储文件搜索
 //!
 //! ## 架构设计
 //!
@@ -68,22 +69,75 @@
 //! ```
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 // 导出子模块
 pub mod message_func;
 pub mod clipboard_func;
+pub mod clipboard_ring;
 pub mod storage_func;
+pub mod manifest;
+mod file_stream;
+pub mod offline_queue;
+pub mod operations;
+pub mod chat;
+pub mod storage_slot;
+pub mod chunk_sizing;
+pub mod idempotency;
+pub mod retry;
+pub mod search;
+pub mod scheduled;
+pub mod in_flight;
 
 // 重新导出主要类型
 pub use message_func::MessageFunc;
-pub use clipboard_func::ClipboardFunc;
+pub use clipboard_func::{ClipboardFunc, ClipboardSyncFilter};
+pub use clipboard_ring::{ClipboardRing, ClipboardRingItem, DEFAULT_RING_CAPACITY, DEFAULT_RING_MAX_BYTES};
 pub use storage_func::StorageFunc;
+pub use manifest::{FileManifest, SignedFileManifest};
+pub use offline_queue::{MessageDelivery, OfflineQueue, QueuedMessage};
+pub use operations::{OperationHandle, OperationInfo, OperationKind, OperationRegistry};
+pub use chat::ChatSession;
+pub use storage_slot::{StorageSlot, StorageWriteGuard};
+pub use chunk_sizing::{AdaptiveChunkSizer, ChunkSizingConfig, TransferSample};
+pub use idempotency::IdempotencyCache;
+pub use retry::{FanoutOutcome, RetryConfig};
+pub use search::{SearchResult, SearchResultKind, SearchResults, SearchScopes};
+pub use scheduled::{MessageSchedule, ScheduledMessage};
+pub use in_flight::{InFlightGuard, InFlightTracker};
 
 /// 分布式功能结果类型
 pub type FuncResult<T> = std::result::Result<T, ErrorInfo>;
 
+/// 迁移存储后等待原存储的其他持有者释放的最长时间
+const RELOCATE_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 分布式功能统计信息
+///
+/// 汇总本节点的消息、传输、云存储和剪切板计数，可序列化后用于仪表盘展示
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FuncStats {
+    /// 本地存储的消息数量
+    pub messages_stored: usize,
+    /// 发送的令牌数
+    pub tokens_sent: u64,
+    /// 接收的令牌数
+    pub tokens_received: u64,
+    /// 发送的字节数
+    pub bytes_sent: u64,
+    /// 接收的字节数
+    pub bytes_received: u64,
+    /// 云存储中的文件数量
+    pub cloud_files: usize,
+    /// 云存储占用（文件原始大小总和，字节）
+    pub cloud_bytes_used: u64,
+    /// 剪切板条目数量
+    pub clipboard_entries: usize,
+}
+
 /// BEY 分布式功能管理器
 ///
 /// 统一管理所有分布式功能，提供高级API
@@ -92,12 +146,20 @@ pub struct BeyFuncManager {
     device_id: String,
     /// 网络引擎
     engine: Arc<bey_net::TransportEngine>,
+    /// 统一存储管理器（可在运行时迁移）
+    storage: Arc<StorageSlot>,
+    /// 当前存储根目录
+    storage_root: std::sync::Mutex<PathBuf>,
+    /// 存储选项，迁移后按相同选项重新打开存储
+    storage_options: bey_storage::StorageOptions,
     /// 消息功能
     pub message: MessageFunc,
     /// 剪切板功能
     pub clipboard: ClipboardFunc,
     /// 存储功能
     pub storage_func: StorageFunc,
+    /// 进行中的发送、传输和同步
+    in_flight: Arc<InFlightTracker>,
 }
 
 impl BeyFuncManager {
@@ -117,45 +179,104 @@ impl BeyFuncManager {
         engine: Arc<bey_net::TransportEngine>,
         storage_root: &str,
     ) -> FuncResult<Self> {
+        Self::new_with_engine_and_options(
+            device_id,
+            engine,
+            storage_root,
+            bey_storage::StorageOptions::default(),
+            ChunkSizingConfig::default(),
+            RetryConfig::default(),
+        ).await
+    }
+
+    /// 创建分布式功能管理器构建器
+    ///
+    /// 构建器默认启用加密、禁用引擎层认证，与 [`BeyFuncManager::new`] 一致
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备唯一标识
+    /// * `storage_root` - 存储根目录
+    pub fn builder(device_id: &str, storage_root: &str) -> BeyFuncManagerBuilder {
+        BeyFuncManagerBuilder::new(device_id, storage_root)
+    }
+
+    /// 使用现有网络引擎和存储选项创建分布式功能管理器
+    async fn new_with_engine_and_options(
+        device_id: &str,
+        engine: Arc<bey_net::TransportEngine>,
+        storage_root: &str,
+        storage_options: bey_storage::StorageOptions,
+        chunk_sizing: ChunkSizingConfig,
+        retry: RetryConfig,
+    ) -> FuncResult<Self> {
+        // 迁移后重新打开的存储管理器沿用同一个事件广播器，存储事件的订阅者不受迁移影响
+        let mut storage_options = storage_options;
+        storage_options.events.get_or_insert_with(bey_storage::StorageEventBus::new);
+
         // 初始化存储管理器
-        let storage = bey_storage::UnifiedStorageManager::new(
+        let storage = bey_storage::UnifiedStorageManager::new_with_options(
             device_id.to_string(),
             PathBuf::from(storage_root),
+            storage_options.clone(),
         ).await
             .map_err(|e| ErrorInfo::new(7002, format!("创建存储管理器失败: {}", e))
                 .with_category(ErrorCategory::Storage)
                 .with_severity(ErrorSeverity::Error))?;
 
-        let storage = Arc::new(storage);
+        let storage = Arc::new(StorageSlot::new(storage));
 
         // 创建功能模块
+        // 消息、剪切板和存储功能的出站操作使用相同的重试策略
         let message = MessageFunc::new(
             device_id.to_string(),
             Arc::clone(&engine),
             Arc::clone(&storage),
-        );
+        ).with_retry(retry.clone());
 
         let clipboard = ClipboardFunc::new(
             device_id.to_string(),
             Arc::clone(&engine),
             Arc::clone(&storage),
-        );
+        ).with_retry(retry.clone());
 
         let storage_func = StorageFunc::new(
             device_id.to_string(),
             Arc::clone(&engine),
             Arc::clone(&storage),
-        );
+        ).with_chunk_sizing(chunk_sizing)
+            .with_retry(retry);
+
+        // 签发设备证书，用于签名点对点传输的文件清单和解密端到端加密的私信
+        let device_certificate = Self::load_or_issue_device_certificate(device_id, storage_root).await?;
+        message.set_device_certificate(device_certificate.clone()).await;
+        storage_func.set_device_certificate(device_certificate).await;
 
         Ok(Self {
             device_id: device_id.to_string(),
             engine,
+            storage,
+            storage_root: std::sync::Mutex::new(PathBuf::from(storage_root)),
+            storage_options,
             message,
             clipboard,
             storage_func,
+            in_flight: Arc::new(InFlightTracker::new()),
         })
     }
 
+    /// 设置进行中任务跟踪器
+    ///
+    /// 发送、传输和同步在跟踪器中登记，跟踪器关闭后这些操作返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `tracker` - 与应用关闭流程共享的跟踪器
+    pub fn with_in_flight_tracker(mut self, tracker: Arc<InFlightTracker>) -> Self {
+        self.in_flight = tracker;
+        self
+    }
+
     /// 创建新的分布式功能管理器（包含独立的网络引擎）
     ///
     /// # 参数
@@ -171,20 +292,39 @@ impl BeyFuncManager {
     ///
     /// 此方法会创建独立的网络引擎实例。如果在同一进程中需要多个管理器，
     /// 建议使用 `new_with_engine()` 方法共享同一个引擎实例。
+    /// 需要自定义端口、发现或存储配额时使用 [`BeyFuncManager::builder`]。
     pub async fn new(device_id: &str, storage_root: &str) -> FuncResult<Self> {
-        // 初始化网络引擎
-        let mut engine_config = bey_net::EngineConfig {
-            name: device_id.to_string(),
-            ..Default::default()
-        };
-        engine_config.enable_encryption = true;
-        engine_config.enable_auth = false;  // 禁用引擎层认证（传输层已处理）
-        let engine = bey_net::TransportEngine::new(engine_config).await
-            .map_err(|e| ErrorInfo::new(7001, format!("创建网络引擎失败: {}", e))
-                .with_category(ErrorCategory::Network)
+        Self::builder(device_id, storage_root).build().await
+    }
+
+    /// 加载已有的设备证书，没有有效证书时签发新证书
+    ///
+    /// 已存储的证书未过期、未吊销且私钥可用时直接复用，重启不会更换设备证书
+    async fn load_or_issue_device_certificate(device_id: &str, storage_root: &str) -> FuncResult<bey_identity::CertificateData> {
+        let cert_config = bey_identity::CertificateConfig::builder()
+            .with_storage_directory(PathBuf::from(storage_root).join("identity"))
+            .build()
+            .map_err(|e| ErrorInfo::new(7004, format!("创建证书配置失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let cert_manager = bey_identity::CertificateManager::initialize(cert_config).await
+            .map_err(|e| ErrorInfo::new(7005, format!("初始化证书管理器失败: {}", e))
+                .with_category(ErrorCategory::System)
                 .with_severity(ErrorSeverity::Error))?;
 
-        Self::new_with_engine(device_id, Arc::new(engine), storage_root).await
+        match cert_manager.get_device_certificate(device_id).await {
+            Ok(Some(certificate)) if certificate.is_valid() && certificate.private_key_pem.is_some() => {
+                return Ok(certificate);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("读取已有设备证书失败，重新签发: {}", e),
+        }
+
+        cert_manager.issue_device_certificate(device_id).await
+            .map_err(|e| ErrorInfo::new(7006, format!("签发设备证书失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))
     }
 
     /// 仅注册消息处理器（不启动网络服务器）
@@ -227,6 +367,28 @@ impl BeyFuncManager {
         Ok(())
     }
 
+    /// 停止网络服务
+    ///
+    /// 停止网络引擎的服务器，重复调用不会产生副作用
+    ///
+    /// # 返回值
+    ///
+    /// 返回停止结果
+    pub async fn stop(&self) -> FuncResult<()> {
+        self.engine.stop_server().await
+            .map_err(|e| ErrorInfo::new(7007, format!("停止网络服务失败: {}", e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        tracing::info!("BEY 分布式功能管理器已停止: {}", self.device_id);
+        Ok(())
+    }
+
+    /// 网络服务是否正在运行
+    pub fn is_running(&self) -> bool {
+        self.engine.is_running()
+    }
+
     /// 发送私信
     ///
     /// # 参数
@@ -238,9 +400,135 @@ impl BeyFuncManager {
     ///
     /// 返回消息ID或错误
     pub async fn send_private_message(&self, peer_id: &str, content: &[u8]) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("发送私信到 {}", peer_id))?;
         self.message.send_private_message(peer_id, content).await
     }
 
+    /// 使用幂等键发送私信
+    ///
+    /// 重试失败的发送时传入相同的幂等键，窗口期内已成功的发送不会重复执行
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    /// * `content` - 消息内容
+    /// * `idempotency_key` - 幂等键
+    ///
+    /// # 返回值
+    ///
+    /// 返回消息ID或错误
+    pub async fn send_private_message_with_key(
+        &self,
+        peer_id: &str,
+        content: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("发送私信到 {}", peer_id))?;
+        self.message.send_private_message_with_key(peer_id, content, idempotency_key).await
+    }
+
+    /// 发送端到端加密的私信
+    ///
+    /// 使用对方证书公钥加密，对方证书须已通过 `trust_peer_certificate` 添加
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    /// * `content` - 消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回消息ID或错误
+    pub async fn send_private_message_e2e(&self, peer_id: &str, content: &[u8]) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("发送加密私信到 {}", peer_id))?;
+        self.message.send_private_message_e2e(peer_id, content).await
+    }
+
+    /// 打开与对等设备的聊天会话
+    ///
+    /// 会话按顺序收发与该设备之间的私信，释放会话即取消订阅
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回聊天会话，与该设备已有打开的会话时返回错误
+    pub fn open_chat(&self, peer_id: &str) -> FuncResult<ChatSession> {
+        self.message.open_chat(peer_id)
+    }
+
+    /// 启用私信存储转发
+    ///
+    /// 对方设备不可达时私信进入离线队列，再次发现该设备时重新投递
+    ///
+    /// # 参数
+    ///
+    /// * `ttl` - 离线消息生存时间
+    pub async fn enable_offline_queue(&self, ttl: Duration) {
+        self.message.enable_offline_queue(ttl).await
+    }
+
+    /// 获取发往指定设备的待投递消息
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回尚未投递且未过期的消息列表
+    pub async fn pending_outbound(&self, peer_id: &str) -> Vec<QueuedMessage> {
+        self.message.pending_outbound(peer_id).await
+    }
+
+    /// 启用定时消息
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 保存定时消息的计划文件路径
+    ///
+    /// # 返回值
+    ///
+    /// 返回启用结果，已启用定时消息时返回错误
+    pub async fn enable_message_scheduling(&self, path: impl AsRef<std::path::Path>) -> FuncResult<()> {
+        self.message.enable_scheduling(path).await
+    }
+
+    /// 计划在指定时间发送私信
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    /// * `content` - 消息内容
+    /// * `deliver_at` - 计划投递时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回定时消息ID或错误
+    pub async fn schedule_message(&self, peer_id: &str, content: &[u8], deliver_at: std::time::SystemTime) -> FuncResult<String> {
+        self.message.schedule_message(peer_id, content, deliver_at).await
+    }
+
+    /// 取消尚未投递的定时消息
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 定时消息ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回是否存在被取消的消息
+    pub async fn cancel_scheduled(&self, id: &str) -> FuncResult<bool> {
+        self.message.cancel_scheduled(id).await
+    }
+
+    /// 获取尚未投递的定时消息
+    pub async fn list_scheduled(&self) -> Vec<ScheduledMessage> {
+        self.message.list_scheduled().await
+    }
+
     /// 发送群聊消息
     ///
     /// # 参数
@@ -252,6 +540,7 @@ impl BeyFuncManager {
     ///
     /// 返回消息ID或错误
     pub async fn send_group_message(&self, group_id: &str, content: &[u8]) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("发送群聊消息到 {}", group_id))?;
         self.message.send_group_message(group_id, content).await
     }
 
@@ -265,6 +554,7 @@ impl BeyFuncManager {
     ///
     /// 返回发送结果
     pub async fn broadcast_message(&self, content: &[u8]) -> FuncResult<usize> {
+        let _task = self.in_flight.begin("广播消息")?;
         self.message.broadcast_message(content).await
     }
 
@@ -292,6 +582,7 @@ impl BeyFuncManager {
     ///
     /// 返回同步结果
     pub async fn sync_clipboard_to_group(&self, group_id: &str) -> FuncResult<()> {
+        let _task = self.in_flight.begin(format!("同步剪切板到群组 {}", group_id))?;
         self.clipboard.sync_to_group(group_id).await
     }
 
@@ -305,6 +596,7 @@ impl BeyFuncManager {
     ///
     /// 返回同步结果
     pub async fn sync_clipboard_to_peer(&self, peer_id: &str) -> FuncResult<()> {
+        let _task = self.in_flight.begin(format!("同步剪切板到 {}", peer_id))?;
         self.clipboard.sync_to_peer(peer_id).await
     }
 
@@ -319,9 +611,54 @@ impl BeyFuncManager {
     ///
     /// 返回文件哈希或错误
     pub async fn upload_to_cloud(&self, filename: &str, data: &[u8]) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("上传 {}", filename))?;
         self.storage_func.upload_to_cloud(filename, data).await
     }
 
+    /// 使用幂等键上传文件到云存储
+    ///
+    /// 重试失败的上传时传入相同的幂等键，窗口期内已成功的上传不会重复执行
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    /// * `idempotency_key` - 幂等键
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件哈希或错误
+    pub async fn upload_to_cloud_with_key(
+        &self,
+        filename: &str,
+        data: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("上传 {}", filename))?;
+        self.storage_func.upload_to_cloud_with_key(filename, data, idempotency_key).await
+    }
+
+    /// 从读取器上传文件到云存储，并报告上传进度
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `reader` - 文件数据来源
+    /// * `on_progress` - 进度回调，每写入一个块调用一次，参数为已上传字节数和总字节数
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件哈希或错误
+    pub async fn upload_to_cloud_with_progress(
+        &self,
+        filename: &str,
+        reader: impl tokio::io::AsyncRead + Unpin,
+        on_progress: impl Fn(u64, u64),
+    ) -> FuncResult<String> {
+        let _task = self.in_flight.begin(format!("上传 {}", filename))?;
+        self.storage_func.upload_to_cloud_with_progress(filename, reader, on_progress).await
+    }
+
     /// 从云存储下载文件
     ///
     /// # 参数
@@ -332,9 +669,31 @@ impl BeyFuncManager {
     ///
     /// 返回文件数据或错误
     pub async fn download_from_cloud(&self, file_hash: &str) -> FuncResult<Vec<u8>> {
+        let _task = self.in_flight.begin(format!("下载 {}", file_hash))?;
         self.storage_func.download_from_cloud(file_hash).await
     }
 
+    /// 从云存储下载文件并写入写入器，同时报告下载进度
+    ///
+    /// # 参数
+    ///
+    /// * `file_hash` - 文件哈希
+    /// * `writer` - 文件数据写入目标
+    /// * `on_progress` - 进度回调，每读取一个块调用一次，参数为已下载字节数和总字节数
+    ///
+    /// # 返回值
+    ///
+    /// 返回写入的字节数或错误
+    pub async fn download_from_cloud_with_progress(
+        &self,
+        file_hash: &str,
+        writer: impl tokio::io::AsyncWrite + Unpin,
+        on_progress: impl Fn(u64, u64),
+    ) -> FuncResult<u64> {
+        let _task = self.in_flight.begin(format!("下载 {}", file_hash))?;
+        self.storage_func.download_from_cloud_with_progress(file_hash, writer, on_progress).await
+    }
+
     /// 发送文件到对等设备
     ///
     /// # 参数
@@ -347,9 +706,277 @@ impl BeyFuncManager {
     ///
     /// 返回发送结果
     pub async fn send_file_to_peer(&self, peer_id: &str, filename: &str, data: &[u8]) -> FuncResult<()> {
+        let _task = self.in_flight.begin(format!("发送文件 {} 到 {}", filename, peer_id))?;
         self.storage_func.send_file_to_peer(peer_id, filename, data).await
     }
 
+    /// 使用幂等键发送文件到对等设备
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    /// * `idempotency_key` - 幂等键
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果
+    pub async fn send_file_to_peer_with_key(
+        &self,
+        peer_id: &str,
+        filename: &str,
+        data: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<()> {
+        let _task = self.in_flight.begin(format!("发送文件 {} 到 {}", filename, peer_id))?;
+        self.storage_func.send_file_to_peer_with_key(peer_id, filename, data, idempotency_key).await
+    }
+
+    /// 以流的方式发送文件到对等设备，连接中断后从接收方已确认的偏移续传
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `filename` - 文件名
+    /// * `reader` - 文件数据来源
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送的字节数或错误
+    pub async fn send_file_to_peer_stream(
+        &self,
+        peer_id: &str,
+        filename: &str,
+        reader: impl tokio::io::AsyncRead + Unpin,
+    ) -> FuncResult<u64> {
+        let _task = self.in_flight.begin(format!("发送文件 {} 到 {}", filename, peer_id))?;
+        self.storage_func.send_file_to_peer_stream(peer_id, filename, reader).await
+    }
+
+    /// 获取进行中的上传、下载和文件传输
+    ///
+    /// # 返回值
+    ///
+    /// 返回按开始顺序排列的操作列表
+    pub fn active_operations(&self) -> Vec<OperationInfo> {
+        self.storage_func.active_operations()
+    }
+
+    /// 取消进行中的操作
+    ///
+    /// 取消后对应的传输在下一个等待点停止，并返回取消错误
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 操作ID
+    ///
+    /// # 返回值
+    ///
+    /// 操作不存在（已完成或已取消）时返回错误
+    pub fn cancel_operation(&self, id: &str) -> FuncResult<()> {
+        self.storage_func.cancel_operation(id)
+    }
+
+    /// 获取本设备证书（PEM格式），用于分发给对等设备
+    pub async fn device_certificate_pem(&self) -> Option<String> {
+        self.storage_func.device_certificate_pem().await
+    }
+
+    /// 信任对等设备证书
+    ///
+    /// 只有来自已信任设备且清单签名有效的文件才会被接受，
+    /// 该证书同时用于加密发往该设备的端到端加密私信
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `certificate_pem` - 对等设备证书（PEM格式）
+    pub async fn trust_peer_certificate(&self, peer_id: &str, certificate_pem: String) {
+        self.message.add_peer_certificate(peer_id, certificate_pem.clone()).await;
+        self.storage_func.trust_peer_certificate(peer_id, certificate_pem).await
+    }
+
+    /// 获取统计信息
+    ///
+    /// 汇总网络引擎和各存储管理器的现有计数器
+    ///
+    /// # 返回值
+    ///
+    /// 返回当前统计信息
+    pub async fn statistics(&self) -> FuncStats {
+        let metrics = self.engine.get_performance_stats().await;
+        let storage = self.storage.current();
+
+        FuncStats {
+            messages_stored: storage.message.message_count(),
+            tokens_sent: metrics.tokens_sent,
+            tokens_received: metrics.tokens_received,
+            bytes_sent: metrics.bytes_sent,
+            bytes_received: metrics.bytes_received,
+            cloud_files: storage.cloud_storage.file_count(),
+            cloud_bytes_used: storage.cloud_storage.used_bytes(),
+            clipboard_entries: storage.clipboard.entry_count(),
+        }
+    }
+
+    /// 在本地消息、剪切板和云存储文件中搜索
+    ///
+    /// 消息和剪切板只匹配文本内容，二进制内容不参与匹配；云存储文件匹配文件名和哈希前缀
+    ///
+    /// # 参数
+    ///
+    /// * `query` - 搜索关键词（不区分大小写）
+    /// * `scopes` - 搜索范围
+    ///
+    /// # 返回值
+    ///
+    /// 返回按相关度排序的搜索结果或错误
+    pub async fn search(&self, query: &str, scopes: SearchScopes) -> FuncResult<SearchResults> {
+        search::search(&self.storage.current(), query, scopes).await
+    }
+
+    /// 迁移存储根目录
+    ///
+    /// 停止原存储的后台清理并刷盘后，把整个存储目录复制到新位置，在新位置重新打开存储
+    /// 并让所有功能模块指向它，随后关闭原存储并删除原目录。迁移期间新的写入会等待；
+    /// 任何一步失败都会清理新位置，恢复后台清理并继续使用原目录。
+    ///
+    /// # 参数
+    ///
+    /// * `new_root` - 新的存储根目录，必须不存在或为空目录
+    ///
+    /// # 返回值
+    ///
+    /// 返回迁移结果
+    pub async fn relocate_storage(&self, new_root: PathBuf) -> FuncResult<()> {
+        if self.storage_options.backend == bey_storage::KvBackendKind::Memory {
+            return Err(ErrorInfo::new(7008, "内存存储后端的数据不在磁盘上，无法迁移".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        // 阻止新的写入，并等待进行中的写入完成
+        let _exclusive = self.storage.exclusive().await;
+
+        let old_root = self.storage_root();
+        if new_root == old_root {
+            return Ok(());
+        }
+        let occupied = std::fs::read_dir(&new_root)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(false);
+        if occupied || new_root.starts_with(&old_root) {
+            return Err(ErrorInfo::new(7009, format!("目标目录不为空或位于原存储目录内: {}", new_root.display()))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        // 停止原存储的后台清理，复制期间数据库不再有任何写入
+        let old_storage = self.storage.current();
+        old_storage.stop_sweepers();
+        let relocated = match self.copy_and_reopen(&old_storage, &old_root, &new_root).await {
+            Ok(relocated) => relocated,
+            Err(e) => {
+                if let Some(interval) = self.storage_options.expiry_sweep_interval {
+                    old_storage.restart_sweepers(interval);
+                }
+                return Err(e);
+            }
+        };
+        drop(old_storage);
+
+        let previous = self.storage.replace(relocated);
+        *self.storage_root.lock().unwrap_or_else(|e| e.into_inner()) = new_root.clone();
+
+        // 原存储关闭后才删除原目录，避免仍打开的数据库继续写入已删除的文件
+        if StorageSlot::close(previous, RELOCATE_CLOSE_TIMEOUT).await {
+            if let Err(e) = tokio::fs::remove_dir_all(&old_root).await {
+                tracing::warn!("删除原存储目录失败: {} - {}", old_root.display(), e);
+            }
+        } else {
+            tracing::warn!("原存储仍在使用，保留原存储目录: {}", old_root.display());
+        }
+
+        tracing::info!("存储已迁移: {} -> {}", old_root.display(), new_root.display());
+        Ok(())
+    }
+
+    /// 刷盘后把存储目录复制到新位置，并在新位置打开存储
+    ///
+    /// 失败时清理新位置，原存储不受影响
+    ///
+    /// # 参数
+    ///
+    /// * `old_storage` - 当前的存储管理器
+    /// * `old_root` - 当前的存储根目录
+    /// * `new_root` - 新的存储根目录
+    ///
+    /// # 返回值
+    ///
+    /// 返回新位置上的存储管理器或错误
+    async fn copy_and_reopen(
+        &self,
+        old_storage: &bey_storage::UnifiedStorageManager,
+        old_root: &std::path::Path,
+        new_root: &std::path::Path,
+    ) -> FuncResult<bey_storage::UnifiedStorageManager> {
+        old_storage.flush().await
+            .map_err(|e| ErrorInfo::new(7010, format!("迁移前刷盘失败: {}", e))
+                .with_category(ErrorCategory::Storage)
+                .with_severity(ErrorSeverity::Error))?;
+
+        // 先复制到临时目录再重命名，新位置上不会出现复制了一半的存储
+        let mut staging = new_root.to_path_buf().into_os_string();
+        staging.push(".relocating");
+        let staging = PathBuf::from(staging);
+        let copy_result = {
+            let (from, to, target) = (old_root.to_path_buf(), staging.clone(), new_root.to_path_buf());
+            tokio::task::spawn_blocking(move || {
+                if to.exists() {
+                    std::fs::remove_dir_all(&to)?;
+                }
+                storage_slot::copy_dir_all(&from, &to)?;
+                if target.exists() {
+                    std::fs::remove_dir(&target)?;
+                }
+                std::fs::rename(&to, &target)
+            }).await
+        };
+        match copy_result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                let _ = std::fs::remove_dir_all(&staging);
+                return Err(ErrorInfo::new(7010, format!("复制存储目录失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error));
+            }
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&staging);
+                return Err(ErrorInfo::new(7010, format!("复制任务异常终止: {}", e))
+                    .with_category(ErrorCategory::System)
+                    .with_severity(ErrorSeverity::Error));
+            }
+        }
+
+        bey_storage::UnifiedStorageManager::new_with_options(
+            self.device_id.clone(),
+            new_root.to_path_buf(),
+            self.storage_options.clone(),
+        ).await.map_err(|e| {
+            // 回滚：删除新位置，继续使用原目录
+            let _ = std::fs::remove_dir_all(new_root);
+            ErrorInfo::new(7011, format!("在新位置打开存储失败: {}", e))
+                .with_category(ErrorCategory::Storage)
+                .with_severity(ErrorSeverity::Error)
+        })
+    }
+
+    /// 获取当前存储根目录
+    pub fn storage_root(&self) -> PathBuf {
+        self.storage_root.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
     /// 获取设备ID
     pub fn device_id(&self) -> &str {
         &self.device_id
@@ -359,6 +986,170 @@ impl BeyFuncManager {
     pub fn engine(&self) -> &bey_net::TransportEngine {
         &self.engine
     }
+
+    /// 订阅对象存储和云存储事件
+    ///
+    /// 迁移存储根目录后订阅继续收到新位置的存储事件
+    pub fn storage_events(&self) -> tokio::sync::broadcast::Receiver<bey_storage::StorageEvent> {
+        self.storage.current().storage_events()
+    }
+}
+
+/// 分布式功能管理器构建器
+///
+/// 配置网络引擎、设备发现和存储配额后创建 [`BeyFuncManager`]
+#[derive(Debug, Clone)]
+pub struct BeyFuncManagerBuilder {
+    device_id: String,
+    storage_root: String,
+    engine_config: bey_net::EngineConfig,
+    storage_options: bey_storage::StorageOptions,
+    chunk_sizing: ChunkSizingConfig,
+    retry: RetryConfig,
+}
+
+impl BeyFuncManagerBuilder {
+    /// 创建构建器
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备唯一标识，同时用作引擎名称
+    /// * `storage_root` - 存储根目录
+    pub fn new(device_id: &str, storage_root: &str) -> Self {
+        let engine_config = bey_net::EngineConfig {
+            name: device_id.to_string(),
+            enable_encryption: true,
+            enable_auth: false,  // 禁用引擎层认证（传输层已处理）
+            ..Default::default()
+        };
+
+        Self {
+            device_id: device_id.to_string(),
+            storage_root: storage_root.to_string(),
+            engine_config,
+            storage_options: bey_storage::StorageOptions::default(),
+            chunk_sizing: ChunkSizingConfig::default(),
+            retry: RetryConfig::default(),
+        }
+    }
+
+    /// 设置完整的引擎配置，覆盖之前设置的引擎选项
+    pub fn with_engine_config(mut self, config: bey_net::EngineConfig) -> Self {
+        self.engine_config = config;
+        self
+    }
+
+    /// 设置监听端口（同时应用到引擎和传输层）
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.engine_config.port = port;
+        self.engine_config.transport_config = self.engine_config.transport_config.clone().with_port(port);
+        self
+    }
+
+    /// 设置是否启用加密
+    pub fn with_encryption(mut self, enabled: bool) -> Self {
+        self.engine_config.enable_encryption = enabled;
+        self
+    }
+
+    /// 设置是否启用引擎层认证
+    pub fn with_auth(mut self, enabled: bool) -> Self {
+        self.engine_config.enable_auth = enabled;
+        self
+    }
+
+    /// 设置是否启用mDNS设备发现
+    pub fn with_discovery(mut self, enabled: bool) -> Self {
+        self.engine_config.enable_mdns = enabled;
+        self
+    }
+
+    /// 设置mDNS服务类型
+    pub fn with_discovery_service_type(mut self, service_type: impl Into<String>) -> Self {
+        self.engine_config.mdns_service_type = service_type.into();
+        self
+    }
+
+    /// 设置流传输的块大小（单个数据块的最大载荷，字节）
+    pub fn with_stream_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.engine_config.stream_chunk_size = chunk_size;
+        self
+    }
+
+    /// 设置大文件传输的自适应块大小范围
+    ///
+    /// 每个对等设备从初始块大小开始，链路良好时增大、丢包时减小，
+    /// 始终不超出最小值和最大值
+    pub fn with_chunk_sizing(mut self, config: ChunkSizingConfig) -> Self {
+        self.chunk_sizing = config;
+        self
+    }
+
+    /// 设置发送消息、剪切板同步、上传和文件传输的重试策略
+    ///
+    /// 只有可重试的错误（如网络错误、超时）会按指数退避重试，
+    /// 使用 [`RetryConfig::disabled`] 关闭重试
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置存储选项，覆盖之前设置的存储配额
+    pub fn with_storage_options(mut self, options: bey_storage::StorageOptions) -> Self {
+        self.storage_options = options;
+        self
+    }
+
+    /// 设置存储后端
+    pub fn with_storage_backend(mut self, backend: bey_storage::KvBackendKind) -> Self {
+        self.storage_options.backend = backend;
+        self
+    }
+
+    /// 设置云存储配额（字节）
+    pub fn with_cloud_quota(mut self, max_bytes: u64) -> Self {
+        self.storage_options.max_local_storage = max_bytes;
+        self
+    }
+
+    /// 设置剪切板最多保留的条目数
+    pub fn with_max_clipboard_entries(mut self, max_entries: usize) -> Self {
+        self.storage_options.max_clipboard_entries = max_entries;
+        self
+    }
+
+    /// 设置云存储元数据库定期压缩间隔
+    pub fn with_compaction_interval(mut self, interval: Duration) -> Self {
+        self.storage_options.compaction_interval = Some(interval);
+        self
+    }
+
+    /// 设置剪切板和消息过期清理间隔
+    pub fn with_expiry_sweep_interval(mut self, interval: Duration) -> Self {
+        self.storage_options.expiry_sweep_interval = Some(interval);
+        self
+    }
+
+    /// 创建网络引擎和分布式功能管理器
+    ///
+    /// # 返回值
+    ///
+    /// 返回管理器实例或错误
+    pub async fn build(self) -> FuncResult<BeyFuncManager> {
+        let engine = bey_net::TransportEngine::new(self.engine_config).await
+            .map_err(|e| ErrorInfo::new(7001, format!("创建网络引擎失败: {}", e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        BeyFuncManager::new_with_engine_and_options(
+            &self.device_id,
+            Arc::new(engine),
+            &self.storage_root,
+            self.storage_options,
+            self.chunk_sizing,
+            self.retry,
+        ).await
+    }
 }
 
 #[cfg(test)]
@@ -374,4 +1165,258 @@ mod tests {
         let manager = BeyFuncManager::new("test_device", storage_path).await;
         assert!(manager.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_device_certificate_reused_across_restarts() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let first = BeyFuncManager::load_or_issue_device_certificate("cert_device", storage_path).await
+            .expect("签发证书失败");
+        let second = BeyFuncManager::load_or_issue_device_certificate("cert_device", storage_path).await
+            .expect("加载证书失败");
+        assert_eq!(first.fingerprint, second.fingerprint);
+        assert!(second.private_key_pem.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_statistics_after_operations() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let manager = BeyFuncManager::new("stats_device", storage_path).await.expect("创建管理器失败");
+        assert_eq!(manager.statistics().await.clipboard_entries, 0);
+
+        manager.add_clipboard("text", b"clipboard content").await.expect("添加剪切板失败");
+        let data = b"statistics test file".repeat(10);
+        manager.upload_to_cloud("stats.txt", &data).await.expect("上传失败");
+        // 消息在发送前先保存到本地，未连接对方时发送失败不影响计数
+        let _ = manager.send_private_message("offline_peer", b"hello").await;
+
+        let stats = manager.statistics().await;
+        assert_eq!(stats.clipboard_entries, 1);
+        assert_eq!(stats.cloud_files, 1);
+        assert_eq!(stats.cloud_bytes_used, data.len() as u64);
+        assert_eq!(stats.messages_stored, 1);
+
+        let json = serde_json::to_string(&stats).expect("序列化统计信息失败");
+        assert!(json.contains("cloud_bytes_used"));
+    }
+
+    #[tokio::test]
+    async fn test_transfers_registered_with_in_flight_tracker() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+        let tracker = Arc::new(InFlightTracker::new());
+        let manager = BeyFuncManager::new("in_flight_device", storage_path).await
+            .expect("创建管理器失败")
+            .with_in_flight_tracker(Arc::clone(&tracker));
+
+        // 进行中的任务会阻止关闭排空完成，上传结束后守卫释放
+        let outside = tracker.begin("外部任务").expect("登记任务失败");
+        let hash = manager.upload_to_cloud("in_flight.txt", b"data").await.expect("上传失败");
+        assert_eq!(tracker.pending(), vec!["外部任务".to_string()]);
+        drop(outside);
+        tokio::time::timeout(Duration::from_secs(1), tracker.wait_idle()).await.expect("任务应已全部完成");
+
+        // 关闭后发送、传输和同步都被拒绝
+        tracker.close();
+        let err = manager.download_from_cloud(&hash).await.expect_err("关闭后不应开始下载");
+        assert_eq!(err.code(), 2009);
+        let err = manager.send_private_message("peer", b"hello").await.expect_err("关闭后不应发送");
+        assert_eq!(err.code(), 2009);
+        let err = manager.sync_clipboard_to_peer("peer").await.expect_err("关闭后不应同步");
+        assert_eq!(err.code(), 2009);
+    }
+
+    #[tokio::test]
+    async fn test_search_across_scopes() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let manager = BeyFuncManager::new("search_device", storage_path).await.expect("创建管理器失败");
+        let _ = manager.send_private_message("offline_peer", b"Quarterly report is ready").await;
+        manager.add_clipboard("text", b"copy of the REPORT summary").await.expect("添加剪切板失败");
+        manager.add_clipboard("image/png", b"binary report bytes").await.expect("添加剪切板失败");
+        manager.upload_to_cloud("report.pdf", &b"pdf data".repeat(8)).await.expect("上传失败");
+        manager.upload_to_cloud("holiday.jpg", &b"jpg data".repeat(8)).await.expect("上传失败");
+
+        let results = manager.search("report", SearchScopes::all()).await.expect("搜索失败");
+        assert_eq!(results.len(), 3, "二进制剪切板条目不应参与匹配: {:?}", results);
+        assert_eq!(results.of_kind(SearchResultKind::Message).count(), 1);
+        assert_eq!(results.of_kind(SearchResultKind::Clipboard).count(), 1);
+        let file = results.of_kind(SearchResultKind::File).next().expect("应匹配云存储文件");
+        assert_eq!(file.title, "report.pdf");
+        // 文件名前缀匹配排在包含匹配之前
+        assert_eq!(results.results[0].kind, SearchResultKind::File);
+
+        // 只搜索选中的范围
+        let scopes = SearchScopes::none().with_messages(true).with_clipboard(true);
+        let results = manager.search("REPORT", scopes).await.expect("搜索失败");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.of_kind(SearchResultKind::File).count(), 0);
+
+        assert!(manager.search("missing", SearchScopes::all()).await.expect("搜索失败").is_empty());
+        assert!(manager.search("  ", SearchScopes::all()).await.expect("搜索失败").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_relocate_storage_preserves_data() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let old_root = temp_dir.path().join("old");
+        let new_root = temp_dir.path().join("new");
+
+        let manager = BeyFuncManager::new("relocate_device", old_root.to_str().expect("路径转换失败"))
+            .await
+            .expect("创建管理器失败");
+
+        manager.add_clipboard("text", b"before relocation").await.expect("添加剪切板失败");
+        let data = b"relocated cloud file".repeat(8);
+        let file_hash = manager.upload_to_cloud("relocate.txt", &data).await.expect("上传失败");
+        let _ = manager.send_private_message("offline_peer", b"kept message").await;
+        let before = manager.statistics().await;
+        let mut events = manager.storage_events();
+
+        manager.relocate_storage(new_root.clone()).await.expect("迁移存储失败");
+        assert_eq!(manager.storage_root(), new_root);
+        assert!(!old_root.exists(), "迁移成功后应删除原目录");
+        assert_eq!(manager.storage.current().active_sweepers(), 2, "新位置的存储应运行过期清理");
+
+        // 对象、剪切板和消息在新位置完整保留
+        let after = manager.statistics().await;
+        assert_eq!(after.cloud_files, before.cloud_files);
+        assert_eq!(after.cloud_bytes_used, before.cloud_bytes_used);
+        assert_eq!(after.clipboard_entries, 1);
+        assert_eq!(after.messages_stored, 1);
+        assert_eq!(manager.download_from_cloud(&file_hash).await.expect("下载失败"), data);
+
+        let storage = manager.storage.current();
+        let messages = storage.message.get_private_messages("offline_peer", None).await;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, b"kept message");
+        drop(storage);
+
+        // 迁移后的写入落到新位置，迁移前的事件订阅继续收到新位置的事件
+        manager.add_clipboard("text", b"after relocation").await.expect("添加剪切板失败");
+        assert_eq!(manager.statistics().await.clipboard_entries, 2);
+        let new_hash = manager.upload_to_cloud("after.txt", b"after relocation").await.expect("上传失败");
+        let event = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let event = events.recv().await.expect("接收存储事件失败");
+                if event.key == new_hash {
+                    return event;
+                }
+            }
+        }).await.expect("迁移后应收到存储事件");
+        assert_eq!(event.operation, bey_storage::StorageOperation::Write);
+
+        // 目标目录不为空时拒绝迁移，继续使用当前目录
+        let occupied = temp_dir.path().join("occupied");
+        std::fs::create_dir_all(&occupied).expect("创建目录失败");
+        std::fs::write(occupied.join("file"), b"x").expect("写入文件失败");
+        let err = manager.relocate_storage(occupied).await.expect_err("非空目录应拒绝迁移");
+        assert_eq!(err.code(), 7009);
+        assert_eq!(manager.storage_root(), new_root);
+    }
+
+    #[tokio::test]
+    async fn test_builder_custom_config() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let manager = BeyFuncManager::builder("builder_device", storage_path)
+            .with_port(18931)
+            .with_discovery(false)
+            .with_cloud_quota(64)
+            .build()
+            .await
+            .expect("创建管理器失败");
+
+        let config = manager.engine().config();
+        assert_eq!(config.name, "builder_device");
+        assert_eq!(config.port, 18931);
+        assert_eq!(config.transport_config.port(), 18931);
+        assert!(!config.enable_mdns);
+        assert!(config.enable_encryption);
+        assert!(!config.enable_auth);
+
+        // 服务器实际绑定在配置的端口上
+        manager.start().await.expect("启动失败");
+        let local_addr = manager.engine().local_addr().await.expect("服务器应已绑定地址");
+        assert_eq!(local_addr.port(), 18931);
+        manager.stop().await.expect("停止失败");
+
+        // 存储配额生效
+        manager.upload_to_cloud("small.txt", &[0u8; 32]).await.expect("上传失败");
+        assert!(manager.upload_to_cloud("large.txt", &[1u8; 64]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_uploads_with_same_idempotency_key() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let manager = BeyFuncManager::new("idempotent_device", storage_path).await.expect("创建管理器失败");
+
+        // 相同的幂等键和内容并发上传，只有一次上传被执行
+        let (first, second) = tokio::join!(
+            manager.upload_to_cloud_with_key("retry.txt", b"attempt", Some("upload-1")),
+            manager.upload_to_cloud_with_key("retry.txt", b"attempt", Some("upload-1")),
+        );
+        let first = first.expect("上传失败");
+        let second = second.expect("上传失败");
+        assert_eq!(first, second);
+        assert_eq!(manager.statistics().await.cloud_files, 1);
+
+        // 相同的幂等键用于内容不同的上传被拒绝
+        let conflict = manager.upload_to_cloud_with_key("retry.txt", b"other attempt", Some("upload-1")).await;
+        assert!(conflict.is_err(), "幂等键不能用于不同的上传");
+        assert_eq!(manager.statistics().await.cloud_files, 1);
+
+        // 不同的幂等键会执行新的上传
+        let other = manager.upload_to_cloud_with_key("other.txt", b"other", Some("upload-2")).await
+            .expect("上传失败");
+        assert_ne!(other, first);
+        assert_eq!(manager.statistics().await.cloud_files, 2);
+    }
+
+    #[tokio::test]
+    async fn test_upload_and_download_with_progress() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let manager = BeyFuncManager::new("progress_device", storage_path).await.expect("创建管理器失败");
+
+        // 默认块大小为1MB，负载跨越3个块
+        let data: Vec<u8> = (0..(2 * 1024 * 1024 + 512 * 1024)).map(|i| (i % 251) as u8).collect();
+        let total = data.len() as u64;
+
+        let uploads = std::sync::Mutex::new(Vec::new());
+        let file_hash = manager
+            .upload_to_cloud_with_progress("progress.bin", &data[..], |done, total| {
+                uploads.lock().expect("获取锁失败").push((done, total));
+            })
+            .await
+            .expect("上传失败");
+        let uploads = uploads.into_inner().expect("获取锁失败");
+        assert_eq!(uploads.len(), 3, "每个块应报告一次进度");
+        assert!(uploads.windows(2).all(|pair| pair[0].0 < pair[1].0), "已上传字节数应单调递增");
+        assert!(uploads.iter().all(|&(_, reported_total)| reported_total == total));
+        assert_eq!(uploads.last(), Some(&(total, total)));
+
+        let downloads = std::sync::Mutex::new(Vec::new());
+        let mut output = Vec::new();
+        let written = manager
+            .download_from_cloud_with_progress(&file_hash, &mut output, |done, total| {
+                downloads.lock().expect("获取锁失败").push((done, total));
+            })
+            .await
+            .expect("下载失败");
+        let downloads = downloads.into_inner().expect("获取锁失败");
+        assert_eq!(written, total);
+        assert_eq!(output, data);
+        assert_eq!(downloads.len(), 3);
+        assert!(downloads.windows(2).all(|pair| pair[0].0 < pair[1].0), "已下载字节数应单调递增");
+        assert_eq!(downloads.last(), Some(&(total, total)));
+    }
 }
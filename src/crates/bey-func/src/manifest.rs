@@ -0,0 +1,181 @@
+//! # 文件清单模块
+//!
+//! 为点对点文件传输提供端到端的完整性和来源认证。
+//! 发送方使用设备私钥对文件清单（哈希、大小、时间戳）签名，
+//! 接收方使用发送方的已知证书验签后才接受文件。
+//!
+//! ## 传输格式
+//!
+//! ```text
+//! ┌──────────────┬───────────────────┬──────────────┐
+//! │ 清单长度(4B) │ 签名清单(JSON)     │ 文件数据      │
+//! └──────────────┴───────────────────┴──────────────┘
+//! ```
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::FuncResult;
+
+/// 文件清单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileManifest {
+    /// 发送方设备ID
+    pub sender_id: String,
+    /// 文件名
+    pub filename: String,
+    /// 文件SHA-256哈希（十六进制）
+    pub file_hash: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 创建时间戳（Unix秒）
+    pub timestamp: u64,
+}
+
+/// 已签名的文件清单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedFileManifest {
+    /// 文件清单
+    pub manifest: FileManifest,
+    /// 发送方对清单的签名
+    pub signature: Vec<u8>,
+}
+
+impl FileManifest {
+    /// 为文件数据创建清单
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送方设备ID
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    pub fn new(sender_id: &str, filename: &str, data: &[u8]) -> Self {
+        Self::from_digest(sender_id, filename, hash_data(data), data.len() as u64)
+    }
+
+    /// 使用已计算的文件哈希创建清单，用于边读取边发送的文件流
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送方设备ID
+    /// * `filename` - 文件名
+    /// * `file_hash` - 文件SHA-256哈希（十六进制）
+    /// * `size` - 文件大小（字节）
+    pub fn from_digest(sender_id: &str, filename: &str, file_hash: String, size: u64) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            sender_id: sender_id.to_string(),
+            filename: filename.to_string(),
+            file_hash,
+            size,
+            timestamp,
+        }
+    }
+
+    /// 清单的规范化字节表示（用于签名）
+    fn canonical_bytes(&self) -> FuncResult<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| ErrorInfo::new(7310, format!("序列化文件清单失败: {}", e))
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Error))
+    }
+
+    /// 使用设备私钥签名清单
+    ///
+    /// # 参数
+    ///
+    /// * `private_key_pem` - 设备私钥（PEM格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回已签名的清单或错误
+    pub fn sign(self, private_key_pem: &str) -> FuncResult<SignedFileManifest> {
+        let signature = bey_identity::sign_data(private_key_pem, &self.canonical_bytes()?)
+            .map_err(|e| ErrorInfo::new(7311, format!("签名文件清单失败: {}", e))
+                .with_category(ErrorCategory::Permission)
+                .with_severity(ErrorSeverity::Error))?;
+
+        Ok(SignedFileManifest {
+            manifest: self,
+            signature,
+        })
+    }
+}
+
+impl SignedFileManifest {
+    /// 使用发送方证书验证清单签名及文件数据
+    ///
+    /// # 参数
+    ///
+    /// * `certificate_pem` - 发送方证书（PEM格式）
+    /// * `data` - 收到的文件数据
+    ///
+    /// # 返回值
+    ///
+    /// 签名有效且数据与清单一致时返回 Ok(())，否则返回错误
+    pub fn verify(&self, certificate_pem: &str, data: &[u8]) -> FuncResult<()> {
+        let verified = bey_identity::verify_signature(certificate_pem, &self.manifest.canonical_bytes()?, &self.signature)
+            .map_err(|e| ErrorInfo::new(7312, format!("验证文件清单签名失败: {}", e))
+                .with_category(ErrorCategory::Permission)
+                .with_severity(ErrorSeverity::Error))?;
+
+        if !verified {
+            return Err(ErrorInfo::new(7313, format!("文件清单签名不匹配: {}", self.manifest.filename))
+                .with_category(ErrorCategory::Permission)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        if self.manifest.size != data.len() as u64 || self.manifest.file_hash != hash_data(data) {
+            return Err(ErrorInfo::new(7314, format!("文件数据与清单不一致: {}", self.manifest.filename))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        Ok(())
+    }
+
+    /// 将清单和文件数据编码为传输负载
+    pub fn encode_payload(&self, data: &[u8]) -> FuncResult<Vec<u8>> {
+        let manifest_bytes = serde_json::to_vec(self)
+            .map_err(|e| ErrorInfo::new(7310, format!("序列化文件清单失败: {}", e))
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let mut payload = Vec::with_capacity(4 + manifest_bytes.len() + data.len());
+        payload.extend_from_slice(&(manifest_bytes.len() as u32).to_be_bytes());
+        payload.extend_from_slice(&manifest_bytes);
+        payload.extend_from_slice(data);
+        Ok(payload)
+    }
+
+    /// 从传输负载中解码清单和文件数据
+    pub fn decode_payload(payload: &[u8]) -> FuncResult<(Self, &[u8])> {
+        let invalid = || ErrorInfo::new(7315, "文件传输负载格式无效".to_string())
+            .with_category(ErrorCategory::Parse)
+            .with_severity(ErrorSeverity::Error);
+
+        if payload.len() < 4 {
+            return Err(invalid());
+        }
+
+        let manifest_len = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;
+        let manifest_end = 4usize.checked_add(manifest_len).filter(|end| *end <= payload.len())
+            .ok_or_else(invalid)?;
+
+        let signed_manifest: Self = serde_json::from_slice(&payload[4..manifest_end])
+            .map_err(|_| invalid())?;
+
+        Ok((signed_manifest, &payload[manifest_end..]))
+    }
+}
+
+/// 计算数据的SHA-256哈希（十六进制）
+fn hash_data(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
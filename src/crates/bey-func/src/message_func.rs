@@ -2,26 +2,79 @@
 //!
 //! 提供基于网络的消息发送和接收功能，支持私信、群聊和广播。
 //! 使用 Token 元类创建高级API。
+//! 私信可选端到端加密：发送方使用接收方证书公钥加密，只有接收方私钥能够解密。
+//! 启用定时消息后，私信可以计划在未来某个时间投递。
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
-use std::sync::Arc;
-use bey_net::{TransportEngine, Token, TokenMeta, TokenHandler, NetResult};
-use bey_storage::{UnifiedStorageManager, MessageType};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+use bey_identity::CertificateData;
+use bey_net::{TransportEngine, Token, TokenMeta, TokenHandler, NetResult, MESSAGE_CHANNEL};
+use bey_storage::{Message, MessageEvent, MessageType};
 use async_trait::async_trait;
-use tracing::{info, debug};
+use tokio::sync::RwLock;
+use tracing::{info, debug, warn};
 
+use crate::idempotency::IdempotencyCache;
+use crate::chat::{ChatOutbound, ChatRouter, ChatSequence, ChatSession, EngineOutbound};
+use crate::offline_queue::{MessageDelivery, OfflineQueue, QueuedMessage};
+use crate::retry::{wrap_error, RetryConfig};
+use crate::scheduled::{MessageSchedule, ScheduledMessage};
+use crate::storage_slot::StorageSlot;
 use crate::FuncResult;
 
 /// 消息令牌类型
 const MESSAGE_PRIVATE_TOKEN: &str = "bey.message.private";
+const MESSAGE_PRIVATE_E2E_TOKEN: &str = "bey.message.private.e2e";
+const MESSAGE_PRIVATE_BATCH_TOKEN: &str = "bey.message.private.batch";
 const MESSAGE_GROUP_TOKEN: &str = "bey.message.group";
 const MESSAGE_BROADCAST_TOKEN: &str = "bey.message.broadcast";
 
+/// 记录私信内容类型的令牌属性，缺省为 `"text"`
+const CONTENT_TYPE_ATTRIBUTE: &str = "bey.message.content_type";
+
+/// 表示对方设备不可达的网络错误代码（设备无可用地址、未找到设备）
+const PEER_UNREACHABLE_CODES: [u32; 2] = [4330, 4331];
+
+/// 定时消息投递任务两次检查之间的最长等待时间，也是投递失败后重试的最长退避时间
+const SCHEDULE_MAX_WAIT: Duration = Duration::from_secs(30);
+
+/// 定时消息投递失败后第一次重试前的退避时间
+const SCHEDULE_RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
 /// 消息功能模块
 pub struct MessageFunc {
     device_id: String,
     engine: Arc<TransportEngine>,
-    storage: Arc<UnifiedStorageManager>,
+    storage: Arc<StorageSlot>,
+    /// 离线消息队列（启用存储转发时存在）
+    offline_queue: Arc<RwLock<Option<Arc<OfflineQueue>>>>,
+    /// 定时消息计划（启用定时消息时存在）
+    schedule: RwLock<Option<Arc<MessageSchedule>>>,
+    /// 定时消息投递任务，消息功能释放时终止
+    schedule_dispatcher: std::sync::Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// 本设备证书（含私钥，用于解密端到端加密的私信）
+    certificate: Arc<RwLock<Option<CertificateData>>>,
+    /// 对等设备证书（设备ID -> 证书PEM，用于加密发往该设备的私信）
+    peer_certificates: Arc<RwLock<HashMap<String, String>>>,
+    /// 聊天会话路由
+    chats: Arc<ChatRouter>,
+    /// 私信出站通道
+    outbound: Arc<dyn ChatOutbound>,
+    /// 出站操作的重试策略
+    retry: RetryConfig,
+    /// 私信发送的幂等键记录（幂等键 -> 消息ID）
+    message_keys: Arc<IdempotencyCache<String>>,
+}
+
+impl Drop for MessageFunc {
+    fn drop(&mut self) {
+        if let Some(dispatcher) = self.schedule_dispatcher.get_mut().unwrap_or_else(|e| e.into_inner()).take() {
+            dispatcher.abort();
+        }
+    }
 }
 
 impl MessageFunc {
@@ -29,31 +82,317 @@ impl MessageFunc {
     pub fn new(
         device_id: String,
         engine: Arc<TransportEngine>,
-        storage: Arc<UnifiedStorageManager>,
+        storage: Arc<StorageSlot>,
     ) -> Self {
         Self {
             device_id,
-            engine,
             storage,
+            offline_queue: Arc::new(RwLock::new(None)),
+            schedule: RwLock::new(None),
+            schedule_dispatcher: std::sync::Mutex::new(None),
+            certificate: Arc::new(RwLock::new(None)),
+            peer_certificates: Arc::new(RwLock::new(HashMap::new())),
+            chats: Arc::new(ChatRouter::default()),
+            outbound: Arc::new(EngineOutbound::new(Arc::clone(&engine))),
+            engine,
+            retry: RetryConfig::default(),
+            message_keys: Arc::new(IdempotencyCache::default()),
+        }
+    }
+
+    /// 设置出站操作的重试策略
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置幂等键的去重窗口和最多记录的数量
+    pub fn with_idempotency(mut self, window: Duration, capacity: usize) -> Self {
+        self.message_keys = Arc::new(IdempotencyCache::new(window, capacity));
+        self
+    }
+
+    /// 替换私信出站通道
+    #[cfg(test)]
+    fn with_outbound(mut self, outbound: Arc<dyn ChatOutbound>) -> Self {
+        self.outbound = outbound;
+        self
+    }
+
+    /// 设置本设备证书
+    ///
+    /// 证书必须包含私钥，用于解密端到端加密的私信
+    pub async fn set_device_certificate(&self, certificate: CertificateData) {
+        *self.certificate.write().await = Some(certificate);
+    }
+
+    /// 添加对等设备证书
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `certificate_pem` - 对等设备证书（PEM格式）
+    pub async fn add_peer_certificate(&self, peer_id: &str, certificate_pem: String) {
+        self.peer_certificates.write().await.insert(peer_id.to_string(), certificate_pem);
+        debug!("已添加对等设备证书: {}", peer_id);
+    }
+
+    /// 打开与对等设备的聊天会话
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回聊天会话，与该设备已有打开的会话时返回错误
+    pub fn open_chat(&self, peer_id: &str) -> FuncResult<ChatSession> {
+        ChatSession::open(
+            self.device_id.clone(),
+            peer_id,
+            Arc::clone(&self.storage),
+            Arc::clone(&self.chats),
+            Arc::clone(&self.outbound),
+        )
+    }
+
+    /// 启用存储转发
+    ///
+    /// 启用后，对方设备不可达时私信会进入离线队列，
+    /// 在再次发现该设备时重新投递，超过生存时间仍未投递的消息将被丢弃。
+    ///
+    /// # 参数
+    ///
+    /// * `ttl` - 离线消息生存时间
+    pub async fn enable_offline_queue(&self, ttl: Duration) {
+        *self.offline_queue.write().await = Some(Arc::new(OfflineQueue::new(ttl)));
+        info!("已启用离线消息队列，TTL: {:?}", ttl);
+    }
+
+    /// 获取发往指定设备的待投递消息
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回尚未投递且未过期的消息列表，未启用存储转发时为空
+    pub async fn pending_outbound(&self, peer_id: &str) -> Vec<QueuedMessage> {
+        match self.offline_queue.read().await.as_ref() {
+            Some(queue) => queue.pending(peer_id).await,
+            None => Vec::new(),
         }
     }
 
+    /// 启用定时消息
+    ///
+    /// 加载计划文件中保存的定时消息，并启动后台投递任务：消息到期后发送给对方设备，
+    /// 对方设备不可达时保留在计划中，按退避时间重试，再次发现该设备时立即投递。
+    /// 投递任务在消息功能释放时终止。
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 保存定时消息的计划文件路径
+    ///
+    /// # 返回值
+    ///
+    /// 返回启用结果，已启用定时消息或计划文件无法读取时返回错误
+    pub async fn enable_scheduling(&self, path: impl AsRef<Path>) -> FuncResult<()> {
+        let mut current = self.schedule.write().await;
+        if current.is_some() {
+            return Err(ErrorInfo::new(7122, "已启用定时消息".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let schedule = Arc::new(MessageSchedule::open(path).await?);
+        let pending = schedule.list().await.len();
+        *current = Some(Arc::clone(&schedule));
+        drop(current);
+
+        let dispatcher = self.spawn_schedule_dispatcher(schedule);
+        *self.schedule_dispatcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(dispatcher);
+
+        info!("已启用定时消息，待投递: {}", pending);
+        Ok(())
+    }
+
+    /// 计划在指定时间发送私信
+    ///
+    /// 消息到期时才保存到本地消息存储并发送，到期时对方设备不可达的消息
+    /// 在再次发现该设备时投递
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    /// * `content` - 消息内容
+    /// * `deliver_at` - 计划投递时间，已过去的时间表示尽快投递
+    ///
+    /// # 返回值
+    ///
+    /// 返回定时消息ID，未启用定时消息或保存失败时返回错误
+    pub async fn schedule_message(&self, peer_id: &str, content: &[u8], deliver_at: SystemTime) -> FuncResult<String> {
+        let item = self.schedule().await?.add(peer_id, content, deliver_at).await?;
+        info!("已计划私信: {} -> {}", item.id, peer_id);
+        Ok(item.id)
+    }
+
+    /// 取消尚未投递的定时消息
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 定时消息ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回是否存在被取消的消息，未启用定时消息时返回错误
+    pub async fn cancel_scheduled(&self, id: &str) -> FuncResult<bool> {
+        self.schedule().await?.cancel(id).await
+    }
+
+    /// 获取尚未投递的定时消息
+    ///
+    /// # 返回值
+    ///
+    /// 返回按计划投递时间排列的定时消息，未启用定时消息时为空
+    pub async fn list_scheduled(&self) -> Vec<ScheduledMessage> {
+        match self.schedule.read().await.as_ref() {
+            Some(schedule) => schedule.list().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// 获取定时消息计划，未启用时返回错误
+    async fn schedule(&self) -> FuncResult<Arc<MessageSchedule>> {
+        self.schedule.read().await.clone().ok_or_else(|| {
+            ErrorInfo::new(7116, "未启用定时消息".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Warning)
+        })
+    }
+
+    /// 启动定时消息投递任务
+    ///
+    /// 等待到最早的到期时间、计划变化或设备发现事件后投递到期的消息。
+    /// 投递失败的消息留在计划中，发往同一设备的消息按指数退避重试，
+    /// 再次发现该设备时立即重试。任务只持有出站通道的弱引用，消息功能释放后退出。
+    fn spawn_schedule_dispatcher(&self, schedule: Arc<MessageSchedule>) -> tokio::task::JoinHandle<()> {
+        let mut events = self.engine.subscribe_device_discovered();
+        let outbound = Arc::downgrade(&self.outbound);
+        let storage = Arc::clone(&self.storage);
+        let device_id = self.device_id.clone();
+        let retry = self.retry.clone();
+        let redelivery = RetryConfig::default().with_backoff(SCHEDULE_RETRY_INITIAL_BACKOFF, SCHEDULE_MAX_WAIT);
+
+        tokio::spawn(async move {
+            // 投递失败的设备 -> (连续失败的轮数, 下次重试时间)
+            let mut waiting: HashMap<String, (u32, SystemTime)> = HashMap::new();
+            loop {
+                let next = schedule.list().await.iter()
+                    .map(|item| scheduled_ready_at(&waiting, item))
+                    .min();
+                let wait = next
+                    .map(|at| at.duration_since(SystemTime::now()).unwrap_or_default())
+                    .unwrap_or(SCHEDULE_MAX_WAIT)
+                    .min(SCHEDULE_MAX_WAIT);
+
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = schedule.changed() => {}
+                    event = events.recv() => match event {
+                        Ok(peer_id) => {
+                            waiting.remove(&peer_id);
+                        }
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => waiting.clear(),
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+
+                let Some(outbound) = outbound.upgrade() else {
+                    break;
+                };
+                let now = SystemTime::now();
+                for item in schedule.due(now).await {
+                    if scheduled_ready_at(&waiting, &item) > now {
+                        continue;
+                    }
+                    let delivered = deliver_scheduled(&device_id, &storage, outbound.as_ref(), &retry, &schedule, &item).await;
+                    match delivered {
+                        Ok(()) => {
+                            waiting.remove(&item.peer_id);
+                            debug!("定时消息已投递: {} -> {}", item.id, item.peer_id);
+                        }
+                        Err(e) => {
+                            let failures = waiting.get(&item.peer_id).map_or(1, |(failures, _)| failures + 1);
+                            let backoff = redelivery.jittered(redelivery.backoff(failures));
+                            if PEER_UNREACHABLE_CODES.contains(&e.code()) {
+                                info!("对方设备不可达，定时消息 {:?} 后或再次发现该设备时重试: {} -> {}", backoff, item.id, item.peer_id);
+                            } else {
+                                warn!("投递定时消息失败，{:?} 后或再次发现对方设备时重试: {} -> {}: {}", backoff, item.id, item.peer_id, e);
+                            }
+                            waiting.insert(item.peer_id.clone(), (failures, SystemTime::now() + backoff));
+                        }
+                    }
+                }
+            }
+            debug!("定时消息投递任务已退出");
+        })
+    }
+
     /// 注册消息处理器
     pub async fn register_handlers(&self, engine: &TransportEngine) -> FuncResult<()> {
-        let handler = MessageHandler {
-            device_id: self.device_id.clone(),
-            storage: Arc::clone(&self.storage),
-        };
+        let handler = self.handler();
 
         engine.register_handler(Arc::new(handler)).await
             .map_err(|e| ErrorInfo::new(7101, format!("注册消息处理器失败: {}", e))
                 .with_category(ErrorCategory::System)
                 .with_severity(ErrorSeverity::Error))?;
 
+        // 消息令牌在消息通道上收发，不与文件和剪切板令牌争用流
+        engine.route_tokens_over_channel("bey.message.", MESSAGE_CHANNEL)
+            .map_err(|e| ErrorInfo::new(7101, format!("认领消息通道失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+
+        self.spawn_offline_delivery_task();
+
         info!("消息处理器已注册");
         Ok(())
     }
 
+    /// 启动离线消息投递任务
+    ///
+    /// 监听新设备发现事件，向重新出现的设备投递排队的消息。
+    /// 任务只持有引擎的弱引用，引擎释放后随事件通道关闭而退出。
+    fn spawn_offline_delivery_task(&self) {
+        let mut events = self.engine.subscribe_device_discovered();
+        let engine = Arc::downgrade(&self.engine);
+        let device_id = self.device_id.clone();
+        let offline_queue = Arc::clone(&self.offline_queue);
+
+        tokio::spawn(async move {
+            loop {
+                let peer_id = match events.recv().await {
+                    Ok(peer_id) => peer_id,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+
+                let queue = match offline_queue.read().await.as_ref() {
+                    Some(queue) => Arc::clone(queue),
+                    None => continue,
+                };
+
+                let delivery = EngineDelivery {
+                    device_id: device_id.clone(),
+                    engine: Weak::clone(&engine),
+                };
+                queue.flush_peer(&peer_id, &delivery).await;
+            }
+        });
+    }
+
     /// 发送私信
     ///
     /// # 参数
@@ -65,8 +404,38 @@ impl MessageFunc {
     ///
     /// 返回消息ID或错误
     pub async fn send_private_message(&self, peer_id: &str, content: &[u8]) -> FuncResult<String> {
+        self.send_private_message_with_key(peer_id, content, None).await
+    }
+
+    /// 使用幂等键发送私信
+    ///
+    /// 窗口期内使用相同幂等键的重复发送只执行一次并返回首次发送的消息ID，
+    /// 相同的键用于发往其他设备或内容不同的私信时返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    /// * `content` - 消息内容
+    /// * `idempotency_key` - 幂等键，为 `None` 时每次都执行发送
+    ///
+    /// # 返回值
+    ///
+    /// 返回消息ID或错误
+    pub async fn send_private_message_with_key(
+        &self,
+        peer_id: &str,
+        content: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<String> {
+        self.message_keys.run(idempotency_key, &[peer_id.as_bytes(), content], || {
+            self.send_private_message_once(peer_id, content)
+        }).await
+    }
+
+    /// 执行一次私信发送
+    async fn send_private_message_once(&self, peer_id: &str, content: &[u8]) -> FuncResult<String> {
         // 保存到本地存储
-        let msg_id = self.storage.message.send_message(
+        let msg_id = self.storage.write_access().await.message.send_message(
             MessageType::Private,
             peer_id.to_string(),
             content.to_vec(),
@@ -75,26 +444,162 @@ impl MessageFunc {
             .map_err(|e| ErrorInfo::new(7102, format!("保存消息失败: {}", e))
                 .with_category(ErrorCategory::Storage))?;
 
-        // 创建消息令牌
-        let meta = TokenMeta::new(MESSAGE_PRIVATE_TOKEN.to_string(), self.device_id.clone())
-            .with_receiver(peer_id.to_string());
+        // 创建并发送消息令牌
+        let token = private_message_token(&self.device_id, peer_id, &msg_id, content);
 
-        let mut payload = Vec::new();
-        payload.extend_from_slice(msg_id.as_bytes());
-        payload.push(0); // 分隔符
-        payload.extend_from_slice(content);
-
-        let token = Token::new(meta, payload);
+        let sent = self.retry.run("发送私信", || self.outbound.send_token(token.clone())).await;
+        if let Err(e) = sent {
+            // 对方不可达时，若启用了存储转发则加入离线队列
+            if PEER_UNREACHABLE_CODES.contains(&e.code()) {
+                if let Some(queue) = self.offline_queue.read().await.as_ref() {
+                    queue.enqueue(peer_id, &msg_id, content).await;
+                    info!("对方设备不可达，私信已加入离线队列: {} -> {}", peer_id, msg_id);
+                    return Ok(msg_id);
+                }
+            }
 
-        // 发送令牌
-        self.engine.send_token(token).await
-            .map_err(|e| ErrorInfo::new(7103, format!("发送消息失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+            return Err(wrap_error(e, 7103, "发送消息失败", ErrorCategory::Network));
+        }
 
         debug!("发送私信成功: {} -> {}", peer_id, msg_id);
         Ok(msg_id)
     }
 
+    /// 批量发送私信
+    ///
+    /// 多条消息合并为一个令牌发送，接收方再拆分为单独的私信，
+    /// 避免每条消息各占一个流。保存失败的消息不会发送，不影响同批其他消息；
+    /// 对方不可达且启用了存储转发时，每条消息分别加入离线队列。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID
+    /// * `content_type` - 各条消息的内容类型，如 `"text"`
+    /// * `contents` - 各条消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 按内容顺序返回每条消息的结果：成功为消息ID，失败为该消息保存或发送的错误
+    pub async fn send_private_messages_batch(
+        &self,
+        peer_id: &str,
+        content_type: &str,
+        contents: Vec<Vec<u8>>,
+    ) -> FuncResult<Vec<FuncResult<String>>> {
+        // 逐条保存到本地存储，记录每条消息在结果中的位置
+        let mut results: Vec<FuncResult<String>> = Vec::with_capacity(contents.len());
+        let mut messages = Vec::with_capacity(contents.len());
+        for content in contents {
+            let saved = self.storage.write_access().await.message.send_message(
+                MessageType::Private,
+                peer_id.to_string(),
+                content.clone(),
+                content_type.to_string(),
+            ).await;
+            match saved {
+                Ok(msg_id) => {
+                    results.push(Ok(msg_id.clone()));
+                    messages.push((results.len() - 1, msg_id, content));
+                }
+                Err(e) => {
+                    warn!("保存批量私信中的消息失败: {} - {}", peer_id, e);
+                    results.push(Err(ErrorInfo::new(7102, format!("保存消息失败: {}", e))
+                        .with_category(ErrorCategory::Storage)));
+                }
+            }
+        }
+        if messages.is_empty() {
+            return Ok(results);
+        }
+
+        let frames: Vec<(String, Vec<u8>)> = messages.iter()
+            .map(|(_, msg_id, content)| (msg_id.clone(), content.clone()))
+            .collect();
+        let token = private_message_batch_token(&self.device_id, peer_id, content_type, &frames);
+        let sent = self.retry.run("批量发送私信", || self.outbound.send_token(token.clone())).await;
+        if let Err(e) = sent {
+            if PEER_UNREACHABLE_CODES.contains(&e.code()) {
+                if let Some(queue) = self.offline_queue.read().await.as_ref() {
+                    for (_, msg_id, content) in &messages {
+                        queue.enqueue(peer_id, msg_id, content).await;
+                    }
+                    info!("对方设备不可达，{} 条私信已加入离线队列: {}", messages.len(), peer_id);
+                    return Ok(results);
+                }
+            }
+
+            // 整批一起发送，同批已保存的消息都以发送错误完成
+            let error = wrap_error(e, 7103, "发送消息失败", ErrorCategory::Network);
+            warn!("批量发送私信失败: {} - {}", peer_id, error);
+            for (index, msg_id, _) in &messages {
+                results[*index] = Err(ErrorInfo::new(7103, format!("发送消息 {} 失败: {}", msg_id, error.message()))
+                    .with_category(ErrorCategory::Network));
+            }
+            return Ok(results);
+        }
+
+        debug!("批量发送私信成功: {} -> {} 条", peer_id, messages.len());
+        Ok(results)
+    }
+
+    /// 发送端到端加密的私信
+    ///
+    /// 消息内容使用接收方证书公钥加密后再交给网络引擎，中间节点只能看到密文。
+    /// 本地保存的副本为明文。加密私信不会进入离线队列。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对方设备ID，须已通过 `add_peer_certificate` 添加证书
+    /// * `content` - 消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回消息ID或错误
+    pub async fn send_private_message_e2e(&self, peer_id: &str, content: &[u8]) -> FuncResult<String> {
+        // 先加密，缺少对方证书时不保存消息
+        let ciphertext = self.encrypt_for_peer(peer_id, content).await?;
+
+        // 保存到本地存储
+        let msg_id = self.storage.write_access().await.message.send_message(
+            MessageType::Private,
+            peer_id.to_string(),
+            content.to_vec(),
+            "text".to_string(),
+        ).await
+            .map_err(|e| ErrorInfo::new(7102, format!("保存消息失败: {}", e))
+                .with_category(ErrorCategory::Storage))?;
+
+        let token = e2e_private_message_token(&self.device_id, peer_id, &msg_id, &ciphertext);
+        self.retry.run("发送加密私信", || self.outbound.send_token(token.clone())).await
+            .map_err(|e| wrap_error(e, 7103, "发送消息失败", ErrorCategory::Network))?;
+
+        debug!("发送加密私信成功: {} -> {}", peer_id, msg_id);
+        Ok(msg_id)
+    }
+
+    /// 使用对方证书公钥加密消息内容
+    async fn encrypt_for_peer(&self, peer_id: &str, content: &[u8]) -> FuncResult<Vec<u8>> {
+        let certificate_pem = self.peer_certificates.read().await.get(peer_id).cloned()
+            .ok_or_else(|| ErrorInfo::new(7108, format!("未知对方设备证书，无法加密: {}", peer_id))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+
+        bey_identity::encrypt_for_certificate(&certificate_pem, content)
+            .map_err(|e| ErrorInfo::new(7109, format!("加密私信失败: {}", e))
+                .with_category(ErrorCategory::Encryption)
+                .with_severity(ErrorSeverity::Error))
+    }
+
+    /// 创建消息处理器
+    fn handler(&self) -> MessageHandler {
+        MessageHandler {
+            device_id: self.device_id.clone(),
+            storage: Arc::clone(&self.storage),
+            certificate: Arc::clone(&self.certificate),
+            chats: Arc::clone(&self.chats),
+        }
+    }
+
     /// 发送群聊消息
     ///
     /// # 参数
@@ -107,7 +612,7 @@ impl MessageFunc {
     /// 返回消息ID或错误
     pub async fn send_group_message(&self, group_id: &str, content: &[u8]) -> FuncResult<String> {
         // 保存到本地存储
-        let msg_id = self.storage.message.send_message(
+        let msg_id = self.storage.write_access().await.message.send_message(
             MessageType::Group,
             group_id.to_string(),
             content.to_vec(),
@@ -128,10 +633,14 @@ impl MessageFunc {
 
         let token = Token::new(meta, payload);
 
-        // 通过群组名称发送
-        self.engine.send_to_group_by_name(group_id, token.payload.clone(), MESSAGE_GROUP_TOKEN).await
-            .map_err(|e| ErrorInfo::new(7105, format!("发送群消息失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+        // 向群组成员逐个发送，重试时只重发未送达的成员
+        let members = self.engine.group_members(group_id).await;
+        self.retry.run_per_recipient("发送群消息", members, |member| {
+            let payload = token.payload.clone();
+            async move { self.engine.send_to(&member, payload, MESSAGE_GROUP_TOKEN).await }
+        }).await
+            .into_count()
+            .map_err(|e| wrap_error(e, 7105, "发送群消息失败", ErrorCategory::Network))?;
 
         debug!("发送群消息成功: {} -> {}", group_id, msg_id);
         Ok(msg_id)
@@ -151,20 +660,147 @@ impl MessageFunc {
         let meta = TokenMeta::new(MESSAGE_BROADCAST_TOKEN.to_string(), self.device_id.clone());
         let token = Token::new(meta, content.to_vec());
 
-        // 广播
-        let count = self.engine.broadcast(token.payload, MESSAGE_BROADCAST_TOKEN).await
-            .map_err(|e| ErrorInfo::new(7106, format!("广播消息失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+        // 向所有已发现的设备逐个发送，重试时只重发未送达的设备
+        let targets = self.engine.broadcast_targets().await;
+        let count = self.retry.run_per_recipient("广播消息", targets, |target| {
+            let payload = token.payload.clone();
+            async move { self.engine.send_to(&target, payload, MESSAGE_BROADCAST_TOKEN).await }
+        }).await
+            .into_count()
+            .map_err(|e| wrap_error(e, 7106, "广播消息失败", ErrorCategory::Network))?;
 
         debug!("广播消息成功，发送到 {} 个设备", count);
         Ok(count)
     }
 }
 
+/// 构建私信令牌
+///
+/// 负载格式：消息ID + 分隔符(0) + 消息内容
+pub(crate) fn private_message_token(device_id: &str, peer_id: &str, msg_id: &str, content: &[u8]) -> Token {
+    let meta = TokenMeta::new(MESSAGE_PRIVATE_TOKEN.to_string(), device_id.to_string())
+        .with_receiver(peer_id.to_string());
+
+    let mut payload = Vec::new();
+    payload.extend_from_slice(msg_id.as_bytes());
+    payload.push(0); // 分隔符
+    payload.extend_from_slice(content);
+
+    Token::new(meta, payload)
+}
+
+/// 构建批量私信令牌
+///
+/// 负载由连续的消息帧组成，每帧格式：消息ID长度(u32大端) + 消息ID + 内容长度(u32大端) + 消息内容；
+/// 内容类型记录在 [`CONTENT_TYPE_ATTRIBUTE`] 属性中
+fn private_message_batch_token(device_id: &str, peer_id: &str, content_type: &str, messages: &[(String, Vec<u8>)]) -> Token {
+    let meta = TokenMeta::new(MESSAGE_PRIVATE_BATCH_TOKEN.to_string(), device_id.to_string())
+        .with_receiver(peer_id.to_string())
+        .with_attribute(CONTENT_TYPE_ATTRIBUTE.to_string(), content_type.to_string());
+
+    let mut payload = Vec::new();
+    for (msg_id, content) in messages {
+        payload.extend_from_slice(&(msg_id.len() as u32).to_be_bytes());
+        payload.extend_from_slice(msg_id.as_bytes());
+        payload.extend_from_slice(&(content.len() as u32).to_be_bytes());
+        payload.extend_from_slice(content);
+    }
+
+    Token::new(meta, payload)
+}
+
+/// 从批量私信负载中读取一个长度前缀字段，数据不足时返回 None
+fn read_batch_field<'a>(payload: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len_bytes: [u8; 4] = payload.get(..4)?.try_into().ok()?;
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    let field = payload.get(4..4 + len)?;
+    *payload = &payload[4 + len..];
+    Some(field)
+}
+
+/// 构建端到端加密私信令牌
+///
+/// 负载格式：消息ID + 分隔符(0) + 密文
+fn e2e_private_message_token(device_id: &str, peer_id: &str, msg_id: &str, ciphertext: &[u8]) -> Token {
+    let meta = TokenMeta::new(MESSAGE_PRIVATE_E2E_TOKEN.to_string(), device_id.to_string())
+        .with_receiver(peer_id.to_string());
+
+    let mut payload = Vec::new();
+    payload.extend_from_slice(msg_id.as_bytes());
+    payload.push(0); // 分隔符
+    payload.extend_from_slice(ciphertext);
+
+    Token::new(meta, payload)
+}
+
+/// 投递一条到期的定时消息
+///
+/// 第一次投递时保存到本地消息存储并记录消息ID，重试时沿用同一ID，发送成功后从计划中移除
+/// 定时消息可以投递的时间：计划投递时间和对方设备的下次重试时间中较晚的一个
+fn scheduled_ready_at(waiting: &HashMap<String, (u32, SystemTime)>, item: &ScheduledMessage) -> SystemTime {
+    match waiting.get(&item.peer_id) {
+        Some((_, retry_at)) => item.deliver_at.max(*retry_at),
+        None => item.deliver_at,
+    }
+}
+
+async fn deliver_scheduled(
+    device_id: &str,
+    storage: &StorageSlot,
+    outbound: &dyn ChatOutbound,
+    retry: &RetryConfig,
+    schedule: &MessageSchedule,
+    item: &ScheduledMessage,
+) -> FuncResult<()> {
+    let msg_id = match &item.message_id {
+        Some(msg_id) => msg_id.clone(),
+        None => {
+            let msg_id = storage.write_access().await.message.send_message(
+                MessageType::Private,
+                item.peer_id.clone(),
+                item.content.clone(),
+                "text".to_string(),
+            ).await
+                .map_err(|e| ErrorInfo::new(7102, format!("保存消息失败: {}", e))
+                    .with_category(ErrorCategory::Storage))?;
+            schedule.set_message_id(&item.id, &msg_id).await?;
+            msg_id
+        }
+    };
+
+    let token = private_message_token(device_id, &item.peer_id, &msg_id, &item.content);
+    retry.run("投递定时消息", || outbound.send_token(token.clone())).await?;
+    schedule.complete(&item.id).await
+}
+
+/// 基于网络引擎的离线消息投递
+struct EngineDelivery {
+    device_id: String,
+    engine: Weak<TransportEngine>,
+}
+
+#[async_trait]
+impl MessageDelivery for EngineDelivery {
+    async fn deliver(&self, message: &QueuedMessage) -> FuncResult<()> {
+        let engine = self.engine.upgrade().ok_or_else(|| {
+            ErrorInfo::new(7107, "网络引擎已释放".to_string())
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Warning)
+        })?;
+
+        let token = private_message_token(&self.device_id, &message.peer_id, &message.msg_id, &message.content);
+        engine.send_token(token).await
+            .map_err(|e| ErrorInfo::new(7103, format!("发送消息失败: {}", e))
+                .with_category(ErrorCategory::Network))
+    }
+}
+
 /// 消息处理器
 struct MessageHandler {
     device_id: String,
-    storage: Arc<UnifiedStorageManager>,
+    storage: Arc<StorageSlot>,
+    certificate: Arc<RwLock<Option<CertificateData>>>,
+    chats: Arc<ChatRouter>,
 }
 
 #[async_trait]
@@ -172,6 +808,8 @@ impl TokenHandler for MessageHandler {
     fn token_types(&self) -> Vec<String> {
         vec![
             MESSAGE_PRIVATE_TOKEN.to_string(),
+            MESSAGE_PRIVATE_E2E_TOKEN.to_string(),
+            MESSAGE_PRIVATE_BATCH_TOKEN.to_string(),
             MESSAGE_GROUP_TOKEN.to_string(),
             MESSAGE_BROADCAST_TOKEN.to_string(),
         ]
@@ -182,6 +820,12 @@ impl TokenHandler for MessageHandler {
             MESSAGE_PRIVATE_TOKEN => {
                 self.handle_private_message(token).await?;
             }
+            MESSAGE_PRIVATE_E2E_TOKEN => {
+                self.handle_e2e_private_message(token).await?;
+            }
+            MESSAGE_PRIVATE_BATCH_TOKEN => {
+                self.handle_private_message_batch(token).await?;
+            }
             MESSAGE_GROUP_TOKEN => {
                 self.handle_group_message(token).await?;
             }
@@ -204,22 +848,102 @@ impl MessageHandler {
         let payload = &token.payload;
         if let Some(sep_pos) = payload.iter().position(|&b| b == 0) {
             let msg_id = String::from_utf8_lossy(&payload[..sep_pos]).to_string();
-            let content = &payload[sep_pos + 1..];
-
-            // 保存到本地存储
-            let _ = self.storage.message.send_message(
-                MessageType::Private,
-                self.device_id.clone(),
-                content.to_vec(),
-                "text".to_string(),
-            ).await;
+            let content = payload[sep_pos + 1..].to_vec();
 
+            self.accept_private_message(&token, &msg_id, content).await;
             info!("收到私信: {} 来自 {}", msg_id, token.meta.sender_id);
         }
 
         Ok(())
     }
 
+    /// 处理批量私信
+    ///
+    /// 拆分为单独的私信逐条保存，格式无效的帧之前的消息仍会被接收
+    async fn handle_private_message_batch(&self, token: Token) -> NetResult<()> {
+        let mut remaining = token.payload.as_slice();
+        let mut accepted = 0;
+        while !remaining.is_empty() {
+            let Some((msg_id, content)) = read_batch_field(&mut remaining)
+                .and_then(|msg_id| Some((msg_id, read_batch_field(&mut remaining)?)))
+            else {
+                return Err(ErrorInfo::new(7113, format!("批量私信格式无效，已接收 {} 条", accepted))
+                    .with_category(ErrorCategory::Parse)
+                    .with_severity(ErrorSeverity::Warning));
+            };
+
+            let msg_id = String::from_utf8_lossy(msg_id).to_string();
+            self.accept_private_message(&token, &msg_id, content.to_vec()).await;
+            accepted += 1;
+        }
+
+        info!("收到批量私信: {} 条 来自 {}", accepted, token.meta.sender_id);
+        Ok(())
+    }
+
+    /// 处理端到端加密的私信
+    ///
+    /// 使用本设备私钥解密后保存明文，无法解密的消息会被拒绝
+    async fn handle_e2e_private_message(&self, token: Token) -> NetResult<()> {
+        let payload = &token.payload;
+        let Some(sep_pos) = payload.iter().position(|&b| b == 0) else {
+            return Err(ErrorInfo::new(7110, "加密私信格式无效".to_string())
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Warning));
+        };
+        let msg_id = String::from_utf8_lossy(&payload[..sep_pos]).to_string();
+        let ciphertext = &payload[sep_pos + 1..];
+
+        let private_key_pem = self.certificate.read().await
+            .as_ref()
+            .and_then(|cert| cert.private_key_pem.clone())
+            .ok_or_else(|| ErrorInfo::new(7111, "未配置设备私钥，无法解密私信".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let content = bey_identity::decrypt_with_private_key(&private_key_pem, ciphertext)
+            .map_err(|e| {
+                warn!("解密私信失败: {} 来自 {} - {}", msg_id, token.meta.sender_id, e);
+                ErrorInfo::new(7112, format!("解密私信失败: {}", e))
+                    .with_category(ErrorCategory::Encryption)
+                    .with_severity(ErrorSeverity::Error)
+            })?;
+
+        self.accept_private_message(&token, &msg_id, content).await;
+        info!("收到加密私信: {} 来自 {}", msg_id, token.meta.sender_id);
+        Ok(())
+    }
+
+    /// 保存收到的私信并交给对应的聊天会话
+    ///
+    /// 消息沿用发送方的消息ID，重复投递的消息只保存一次
+    async fn accept_private_message(&self, token: &Token, msg_id: &str, content: Vec<u8>) {
+        let content_type = token.meta.attributes.get(CONTENT_TYPE_ATTRIBUTE)
+            .cloned()
+            .unwrap_or_else(|| "text".to_string());
+        let message = Message {
+            id: msg_id.to_string(),
+            message_type: MessageType::Private,
+            sender_id: token.meta.sender_id.clone(),
+            receiver_id: self.device_id.clone(),
+            content,
+            content_type,
+            timestamp: token.meta.timestamp,
+            is_read: false,
+            source_device_id: token.meta.sender_id.clone(),
+            edited: false,
+            edit_history: Vec::new(),
+            reactions: Default::default(),
+            expires_at: None,
+        };
+
+        if let Err(e) = self.storage.write_access().await.message.handle_sync_event(MessageEvent::NewMessage(message.clone())).await {
+            warn!("保存私信失败: {} - {}", msg_id, e);
+        }
+
+        self.chats.route(&token.meta.sender_id, ChatSequence::from_token(token), message);
+    }
+
     /// 处理群消息
     async fn handle_group_message(&self, token: Token) -> NetResult<()> {
         // 解析payload
@@ -232,7 +956,7 @@ impl MessageHandler {
             let content = parts[2];
 
             // 保存到本地存储
-            let _ = self.storage.message.send_message(
+            let _ = self.storage.write_access().await.message.send_message(
                 MessageType::Group,
                 group_id.clone(),
                 content.to_vec(),
@@ -273,9 +997,319 @@ mod tests {
         let message_func = MessageFunc::new(
             "test_device".to_string(),
             Arc::new(engine),
-            Arc::new(storage),
+            Arc::new(StorageSlot::new(storage)),
         );
 
         assert_eq!(message_func.device_id, "test_device");
     }
+
+    #[tokio::test]
+    async fn test_e2e_private_message_loopback() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+
+        let cert_config = bey_identity::CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path().join("certs"))
+            .build()
+            .expect("创建证书配置失败");
+        let cert_manager = bey_identity::CertificateManager::initialize(cert_config).await
+            .expect("初始化证书管理器失败");
+        let receiver_cert = cert_manager.issue_device_certificate("receiver").await.expect("签发证书失败");
+
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let sender_storage = bey_storage::UnifiedStorageManager::new(
+            "sender".to_string(),
+            temp_dir.path().join("sender"),
+        ).await.expect("创建存储失败");
+        let sender = MessageFunc::new("sender".to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(sender_storage)));
+        sender.add_peer_certificate("receiver", receiver_cert.certificate_pem.clone()).await;
+
+        let receiver_storage = bey_storage::UnifiedStorageManager::new(
+            "receiver".to_string(),
+            temp_dir.path().join("receiver"),
+        ).await.expect("创建存储失败");
+        let receiver = MessageFunc::new("receiver".to_string(), engine, Arc::new(StorageSlot::new(receiver_storage)));
+        receiver.set_device_certificate(receiver_cert).await;
+        let handler = receiver.handler();
+
+        // 窃听者检查原始令牌，只能看到密文
+        let plaintext = b"top secret message";
+        let ciphertext = sender.encrypt_for_peer("receiver", plaintext).await.expect("加密失败");
+        let token = e2e_private_message_token("sender", "receiver", "msg-1", &ciphertext);
+        assert!(!token.payload.windows(plaintext.len()).any(|window| window == plaintext));
+
+        // 接收方解密并保存明文
+        handler.handle_token(token).await.expect("处理加密私信失败");
+        let messages = receiver.storage.current().message.get_private_messages("receiver", None).await;
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, plaintext);
+
+        // 没有对方证书时无法发送加密私信
+        let err = receiver.send_private_message_e2e("sender", plaintext).await.expect_err("应该失败");
+        assert_eq!(err.code(), 7108);
+    }
+
+    /// 直接交给对端消息处理器的出站通道
+    struct LoopbackOutbound {
+        handler: MessageHandler,
+    }
+
+    #[async_trait]
+    impl crate::chat::ChatOutbound for LoopbackOutbound {
+        async fn send_token(&self, token: Token) -> FuncResult<()> {
+            self.handler.handle_token(token).await.map(|_| ())
+        }
+    }
+
+    /// 前若干次发送返回可重试网络错误的出站通道
+    struct FlakyOutbound {
+        failures: u32,
+        attempts: std::sync::atomic::AtomicU32,
+    }
+
+    #[async_trait]
+    impl crate::chat::ChatOutbound for FlakyOutbound {
+        async fn send_token(&self, _token: Token) -> FuncResult<()> {
+            let attempt = self.attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            if attempt <= self.failures {
+                return Err(ErrorInfo::new(4302, "连接被重置".to_string())
+                    .with_category(ErrorCategory::Network));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_message_retries_transient_errors() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+        let storage = bey_storage::UnifiedStorageManager::new("sender".to_string(), temp_dir.path().to_path_buf())
+            .await.expect("创建存储失败");
+        let storage = Arc::new(StorageSlot::new(storage));
+        let retry = RetryConfig::default().with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+        // 前两次失败，第三次成功
+        let flaky = Arc::new(FlakyOutbound { failures: 2, attempts: Default::default() });
+        let func = MessageFunc::new("sender".to_string(), Arc::clone(&engine), Arc::clone(&storage))
+            .with_retry(retry.clone())
+            .with_outbound(flaky.clone());
+        func.send_private_message("peer", b"hello").await.expect("第三次尝试应该成功");
+        assert_eq!(flaky.attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // 尝试次数用尽后返回最后的错误，上下文记录尝试次数
+        let broken = Arc::new(FlakyOutbound { failures: u32::MAX, attempts: Default::default() });
+        let func = MessageFunc::new("sender".to_string(), engine, storage)
+            .with_retry(retry)
+            .with_outbound(broken.clone());
+        let err = func.send_private_message("peer", b"hello").await.expect_err("应该失败");
+        assert_eq!(err.code(), 7103);
+        assert!(err.context().iter().any(|context| context.contains("共尝试 3 次")));
+        assert_eq!(broken.attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_private_message_batch_loopback() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["sender", "receiver"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(MessageFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage))));
+        }
+        let receiver = funcs.pop().expect("缺少接收方");
+        let sender = funcs.pop().expect("缺少发送方")
+            .with_outbound(Arc::new(LoopbackOutbound { handler: receiver.handler() }));
+
+        let contents: Vec<Vec<u8>> = (0..5).map(|i| format!("notification {}", i).into_bytes()).collect();
+        let results = sender.send_private_messages_batch("receiver", "notification", contents.clone()).await.expect("批量发送失败");
+        let ids: Vec<String> = results.into_iter().map(|result| result.expect("消息发送失败")).collect();
+        assert_eq!(ids.len(), contents.len());
+
+        // 接收方逐条保存，沿用发送方的消息ID
+        let messages = receiver.storage.current().message.get_private_messages("sender", None).await;
+        assert_eq!(messages.len(), contents.len());
+        for (msg_id, content) in ids.iter().zip(&contents) {
+            let message = messages.iter().find(|message| &message.id == msg_id).expect("缺少消息");
+            assert_eq!(&message.content, content);
+            assert_eq!(message.sender_id, "sender");
+            assert_eq!(message.content_type, "notification");
+        }
+
+        // 截断的批量令牌：格式无效的帧之前的消息仍被接收
+        let batch = vec![("batch-1".to_string(), b"first".to_vec()), ("batch-2".to_string(), b"second".to_vec())];
+        let mut token = private_message_batch_token("sender", "receiver", "text", &batch);
+        token.payload.truncate(token.payload.len() - 3);
+        let err = receiver.handler().handle_token(token).await.expect_err("应该失败");
+        assert_eq!(err.code(), 7113);
+        let store = receiver.storage.current();
+        assert!(store.message.get_message("batch-1").await.is_ok());
+        assert!(store.message.get_message("batch-2").await.is_err());
+
+        // 空批量不发送
+        assert!(sender.send_private_messages_batch("receiver", "text", Vec::new()).await.expect("发送失败").is_empty());
+
+        // 发送失败时每条消息各自报告错误
+        let broken = Arc::new(FlakyOutbound { failures: u32::MAX, attempts: Default::default() });
+        let sender = sender.with_outbound(broken).with_retry(RetryConfig::disabled());
+        let results = sender.send_private_messages_batch("receiver", "text", vec![b"a".to_vec(), b"b".to_vec()]).await
+            .expect("批量发送失败");
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.as_ref().is_err_and(|e| e.code() == 7103)));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_message_delivered_after_delay() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["sender", "receiver"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(MessageFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage))));
+        }
+        let receiver = funcs.pop().expect("缺少接收方");
+        let sender = funcs.pop().expect("缺少发送方")
+            .with_outbound(Arc::new(LoopbackOutbound { handler: receiver.handler() }));
+
+        let err = sender.schedule_message("receiver", b"reminder", SystemTime::now()).await.expect_err("应该失败");
+        assert_eq!(err.code(), 7116);
+
+        let schedule_path = temp_dir.path().join("scheduled.json");
+        sender.enable_scheduling(&schedule_path).await.expect("启用定时消息失败");
+
+        let delay = Duration::from_millis(300);
+        let scheduled_at = tokio::time::Instant::now();
+        let id = sender.schedule_message("receiver", b"reminder", SystemTime::now() + delay).await
+            .expect("计划消息失败");
+        let later = sender.schedule_message("receiver", b"next week", SystemTime::now() + Duration::from_secs(7 * 24 * 3600)).await
+            .expect("计划消息失败");
+        assert_eq!(sender.list_scheduled().await.iter().map(|item| item.id.clone()).collect::<Vec<_>>(), vec![id.clone(), later.clone()]);
+
+        // 计划保存在文件中，重新打开后仍然存在
+        let reopened = MessageSchedule::open(&schedule_path).await.expect("重新打开计划失败");
+        assert_eq!(reopened.list().await.len(), 2);
+
+        assert!(sender.cancel_scheduled(&later).await.expect("取消失败"));
+        assert!(!sender.cancel_scheduled(&later).await.expect("取消失败"));
+
+        // 到期前不投递，到期后投递并从计划中移除
+        assert!(receiver.storage.current().message.get_private_messages("sender", None).await.is_empty());
+        let delivered = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let messages = receiver.storage.current().message.get_private_messages("sender", None).await;
+                if !messages.is_empty() {
+                    return messages;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }).await.expect("定时消息未投递");
+        assert!(scheduled_at.elapsed() >= delay);
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].content, b"reminder");
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(sender.list_scheduled().await.is_empty());
+        assert!(MessageSchedule::open(&schedule_path).await.expect("重新打开计划失败").list().await.is_empty());
+
+        // 不能重复启用
+        let err = sender.enable_scheduling(&schedule_path).await.expect_err("重复启用应该失败");
+        assert_eq!(err.code(), 7122);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_message_retried_with_backoff() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+        let storage = bey_storage::UnifiedStorageManager::new("sender".to_string(), temp_dir.path().join("sender"))
+            .await
+            .expect("创建存储失败");
+        let outbound = Arc::new(FlakyOutbound { failures: 2, attempts: std::sync::atomic::AtomicU32::new(0) });
+        let sender = MessageFunc::new("sender".to_string(), engine, Arc::new(StorageSlot::new(storage)))
+            .with_retry(RetryConfig::default().with_max_attempts(1))
+            .with_outbound(Arc::clone(&outbound) as Arc<dyn ChatOutbound>);
+        sender.enable_scheduling(temp_dir.path().join("scheduled.json")).await.expect("启用定时消息失败");
+
+        // 没有设备发现事件时，投递失败的消息按退避时间重试直到成功
+        sender.schedule_message("receiver", b"reminder", SystemTime::now()).await.expect("计划消息失败");
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !sender.list_scheduled().await.is_empty() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        }).await.expect("定时消息未重试投递");
+        assert_eq!(outbound.attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert!(!temp_dir.path().join("scheduled.json.tmp").exists(), "不应遗留临时文件");
+    }
+
+    #[tokio::test]
+    async fn test_chat_session_loopback() {
+        use futures::StreamExt;
+
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let mut funcs = Vec::new();
+        for device in ["alice", "bob"] {
+            let storage = bey_storage::UnifiedStorageManager::new(
+                device.to_string(),
+                temp_dir.path().join(device),
+            ).await.expect("创建存储失败");
+            funcs.push(MessageFunc::new(device.to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(storage))));
+        }
+        let (alice, bob) = (&funcs[0], &funcs[1]);
+
+        let alice_chat = ChatSession::open(
+            "alice".to_string(),
+            "bob",
+            Arc::clone(&alice.storage),
+            Arc::clone(&alice.chats),
+            Arc::new(LoopbackOutbound { handler: bob.handler() }),
+        ).expect("打开会话失败");
+        let bob_chat = ChatSession::open(
+            "bob".to_string(),
+            "alice",
+            Arc::clone(&bob.storage),
+            Arc::clone(&bob.chats),
+            Arc::new(LoopbackOutbound { handler: alice.handler() }),
+        ).expect("打开会话失败");
+        assert!(alice.open_chat("bob").is_err(), "同一对端只能打开一个会话");
+
+        let mut alice_inbox = Box::pin(alice_chat.messages());
+        let mut bob_inbox = Box::pin(bob_chat.messages());
+
+        for text in ["hi bob", "how are you?"] {
+            alice_chat.send(text).await.expect("发送失败");
+        }
+        bob_chat.send("fine, thanks").await.expect("发送失败");
+
+        let first = bob_inbox.next().await.expect("bob 应收到消息");
+        let second = bob_inbox.next().await.expect("bob 应收到消息");
+        assert_eq!(first.content, b"hi bob");
+        assert_eq!(second.content, b"how are you?");
+        assert_eq!(first.sender_id, "alice");
+
+        let reply = alice_inbox.next().await.expect("alice 应收到回复");
+        assert_eq!(reply.content, b"fine, thanks");
+
+        // 交付的消息已标记为已读
+        let stored = bob.storage.current().message.get_message(&first.id).await.expect("查询消息失败");
+        assert!(stored.is_read);
+
+        // 关闭会话后不再接收该对端的消息
+        bob_chat.close();
+        alice_chat.send("still there?").await.expect("发送失败");
+        assert!(bob.open_chat("alice").is_ok(), "关闭后可以重新打开会话");
+    }
 }
@@ -0,0 +1,301 @@
+//! # 离线消息队列模块
+//!
+//! 提供存储转发功能：对方设备不可达时，消息暂存在本地队列中，
+//! 待再次发现该设备时重新投递，超过生存时间（TTL）的消息将被丢弃。
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+use crate::FuncResult;
+
+/// 排队等待投递的消息
+#[derive(Debug, Clone)]
+pub struct QueuedMessage {
+    /// 消息ID
+    pub msg_id: String,
+    /// 目标设备ID
+    pub peer_id: String,
+    /// 消息内容
+    pub content: Vec<u8>,
+    /// 入队时间
+    pub queued_at: Instant,
+    /// 过期时间
+    pub expires_at: Instant,
+}
+
+impl QueuedMessage {
+    /// 消息是否已过期
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires_at
+    }
+}
+
+/// 消息投递接口
+///
+/// 由离线队列在对方设备重新出现时调用，实际负责发送消息
+#[async_trait]
+pub trait MessageDelivery: Send + Sync {
+    /// 投递一条排队的消息
+    async fn deliver(&self, message: &QueuedMessage) -> FuncResult<()>;
+}
+
+/// 离线消息队列
+///
+/// 按目标设备分组保存待投递的消息，保持入队顺序
+pub struct OfflineQueue {
+    /// 消息生存时间
+    ttl: Duration,
+    /// 待投递消息（设备ID -> 消息队列）
+    queues: RwLock<HashMap<String, VecDeque<QueuedMessage>>>,
+}
+
+impl OfflineQueue {
+    /// 创建离线消息队列
+    ///
+    /// # 参数
+    ///
+    /// * `ttl` - 消息生存时间，超时未投递的消息将被丢弃
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            queues: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 获取消息生存时间
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// 将消息加入队列
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标设备ID
+    /// * `msg_id` - 消息ID
+    /// * `content` - 消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回入队的消息
+    pub async fn enqueue(&self, peer_id: &str, msg_id: &str, content: &[u8]) -> QueuedMessage {
+        let now = Instant::now();
+        let message = QueuedMessage {
+            msg_id: msg_id.to_string(),
+            peer_id: peer_id.to_string(),
+            content: content.to_vec(),
+            queued_at: now,
+            expires_at: now + self.ttl,
+        };
+
+        self.queues.write().await
+            .entry(peer_id.to_string())
+            .or_default()
+            .push_back(message.clone());
+
+        debug!("消息已加入离线队列: {} -> {}", msg_id, peer_id);
+        message
+    }
+
+    /// 获取发往指定设备的待投递消息
+    ///
+    /// 已过期的消息会先被丢弃
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回按入队顺序排列的消息列表
+    pub async fn pending(&self, peer_id: &str) -> Vec<QueuedMessage> {
+        let mut queues = self.queues.write().await;
+        let Some(queue) = queues.get_mut(peer_id) else {
+            return Vec::new();
+        };
+
+        Self::drop_expired(queue);
+        let pending: Vec<QueuedMessage> = queue.iter().cloned().collect();
+        if queue.is_empty() {
+            queues.remove(peer_id);
+        }
+        pending
+    }
+
+    /// 丢弃所有已过期的消息
+    ///
+    /// # 返回值
+    ///
+    /// 返回丢弃的消息数量
+    pub async fn purge_expired(&self) -> usize {
+        let mut queues = self.queues.write().await;
+        let mut dropped = 0;
+
+        queues.retain(|_, queue| {
+            dropped += Self::drop_expired(queue);
+            !queue.is_empty()
+        });
+
+        dropped
+    }
+
+    /// 向重新出现的设备投递排队的消息
+    ///
+    /// 按入队顺序投递，遇到投递失败时停止，剩余消息放回队首等待下次重试。
+    /// 投递期间不持有队列锁，其间新入队的消息排在剩余消息之后。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标设备ID
+    /// * `delivery` - 消息投递实现
+    ///
+    /// # 返回值
+    ///
+    /// 返回成功投递的消息数量
+    pub async fn flush_peer(&self, peer_id: &str, delivery: &dyn MessageDelivery) -> usize {
+        // 取出该设备的全部消息后释放锁，网络发送期间不阻塞其他设备的入队和投递
+        let Some(mut batch) = self.queues.write().await.remove(peer_id) else {
+            return 0;
+        };
+        Self::drop_expired(&mut batch);
+
+        let mut delivered = 0;
+        while let Some(message) = batch.front() {
+            if let Err(e) = delivery.deliver(message).await {
+                debug!("离线消息投递失败，稍后重试: {} -> {}: {}", message.msg_id, peer_id, e);
+                break;
+            }
+            batch.pop_front();
+            delivered += 1;
+        }
+
+        if !batch.is_empty() {
+            let mut queues = self.queues.write().await;
+            let queue = queues.entry(peer_id.to_string()).or_default();
+            batch.append(queue);
+            *queue = batch;
+        }
+
+        if delivered > 0 {
+            info!("已向 {} 投递 {} 条离线消息", peer_id, delivered);
+        }
+        delivered
+    }
+
+    /// 丢弃队列中的过期消息，返回丢弃数量
+    fn drop_expired(queue: &mut VecDeque<QueuedMessage>) -> usize {
+        let before = queue.len();
+        queue.retain(|message| {
+            if message.is_expired() {
+                info!("离线消息已过期，丢弃: {} -> {}", message.msg_id, message.peer_id);
+                false
+            } else {
+                true
+            }
+        });
+        before - queue.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::ErrorInfo;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    /// 模拟对方设备的投递实现
+    #[derive(Default)]
+    struct MockPeers {
+        online: Mutex<HashSet<String>>,
+        delivered: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl MessageDelivery for MockPeers {
+        async fn deliver(&self, message: &QueuedMessage) -> FuncResult<()> {
+            if !self.online.lock().expect("锁定失败").contains(&message.peer_id) {
+                return Err(ErrorInfo::new(4331, format!("未找到设备: {}", message.peer_id)));
+            }
+            self.delivered.lock().expect("锁定失败").push(message.msg_id.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_message_delivered_when_peer_appears() {
+        let queue = OfflineQueue::new(Duration::from_secs(60));
+        let peers = MockPeers::default();
+
+        queue.enqueue("peer", "msg-1", b"hello").await;
+        queue.enqueue("peer", "msg-2", b"world").await;
+
+        // 对方离线时投递失败，消息保留在队列中
+        assert_eq!(queue.flush_peer("peer", &peers).await, 0);
+        assert_eq!(queue.pending("peer").await.len(), 2);
+
+        // 对方上线后按顺序投递
+        peers.online.lock().expect("锁定失败").insert("peer".to_string());
+        assert_eq!(queue.flush_peer("peer", &peers).await, 2);
+        assert_eq!(*peers.delivered.lock().expect("锁定失败"), vec!["msg-1", "msg-2"]);
+        assert!(queue.pending("peer").await.is_empty());
+    }
+
+    /// 投递时向同一队列入队新消息，第二次投递失败
+    struct EnqueueWhileDelivering {
+        queue: std::sync::Arc<OfflineQueue>,
+        attempts: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl MessageDelivery for EnqueueWhileDelivering {
+        async fn deliver(&self, message: &QueuedMessage) -> FuncResult<()> {
+            let attempt = {
+                let mut attempts = self.attempts.lock().expect("锁定失败");
+                *attempts += 1;
+                *attempts
+            };
+            if attempt > 1 {
+                return Err(ErrorInfo::new(4331, format!("未找到设备: {}", message.peer_id)));
+            }
+            self.queue.enqueue(&message.peer_id, "msg-late", b"late").await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush_does_not_hold_lock_while_delivering() {
+        let queue = std::sync::Arc::new(OfflineQueue::new(Duration::from_secs(60)));
+        queue.enqueue("peer", "msg-1", b"hello").await;
+        queue.enqueue("peer", "msg-2", b"world").await;
+
+        let delivery = EnqueueWhileDelivering {
+            queue: std::sync::Arc::clone(&queue),
+            attempts: Mutex::new(0),
+        };
+        let delivered = tokio::time::timeout(Duration::from_secs(5), queue.flush_peer("peer", &delivery)).await
+            .expect("投递期间入队不应阻塞");
+        assert_eq!(delivered, 1);
+
+        // 未投递的消息放回队首，投递期间入队的消息排在其后
+        let pending: Vec<String> = queue.pending("peer").await.into_iter().map(|message| message.msg_id).collect();
+        assert_eq!(pending, vec!["msg-2", "msg-late"]);
+    }
+
+    #[tokio::test]
+    async fn test_expired_message_dropped() {
+        let queue = OfflineQueue::new(Duration::from_millis(20));
+        let peers = MockPeers::default();
+
+        queue.enqueue("peer", "msg-1", b"hello").await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        peers.online.lock().expect("锁定失败").insert("peer".to_string());
+        assert_eq!(queue.flush_peer("peer", &peers).await, 0);
+        assert!(peers.delivered.lock().expect("锁定失败").is_empty());
+        assert!(queue.pending("peer").await.is_empty());
+    }
+}
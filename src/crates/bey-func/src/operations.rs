@@ -0,0 +1,314 @@
+//! # 操作登记模块
+//!
+//! 记录正在进行的上传、下载和文件传输，提供进度查询和取消功能。
+//!
+//! 每个操作在开始时登记并获得 [`OperationHandle`]，句柄被释放时操作自动注销。
+//! 取消是协作式的：执行方通过 [`OperationHandle::run`] 运行传输，
+//! 取消后传输 future 在下一个等待点被丢弃，操作返回取消错误。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+use crate::FuncResult;
+
+/// 操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationKind {
+    /// 上传到云存储
+    CloudUpload,
+    /// 从云存储下载
+    CloudDownload,
+    /// 点对点文件传输
+    FileTransfer,
+    /// 点对点大文件传输
+    LargeFileTransfer,
+}
+
+/// 操作信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationInfo {
+    /// 操作ID
+    pub id: String,
+    /// 操作类型
+    pub kind: OperationKind,
+    /// 操作目标（文件名、文件哈希或设备ID）
+    pub target: String,
+    /// 已处理字节数
+    pub bytes_done: u64,
+    /// 总字节数
+    pub total_bytes: u64,
+    /// 开始时间
+    pub started_at: SystemTime,
+}
+
+impl OperationInfo {
+    /// 完成比例（0.0 - 1.0），总字节数为0时返回0
+    pub fn progress(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        (self.bytes_done as f64 / self.total_bytes as f64).min(1.0)
+    }
+}
+
+/// 单个操作的共享状态
+struct OperationState {
+    id: String,
+    kind: OperationKind,
+    target: String,
+    total_bytes: u64,
+    started_at: SystemTime,
+    /// 已处理字节数
+    bytes_done: AtomicU64,
+    /// 取消信号
+    cancel: watch::Sender<bool>,
+}
+
+impl OperationState {
+    fn info(&self) -> OperationInfo {
+        OperationInfo {
+            id: self.id.clone(),
+            kind: self.kind,
+            target: self.target.clone(),
+            bytes_done: self.bytes_done.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes,
+            started_at: self.started_at,
+        }
+    }
+}
+
+/// 操作登记表
+#[derive(Default)]
+pub struct OperationRegistry {
+    /// 进行中的操作（操作ID -> 状态）
+    operations: Mutex<HashMap<String, Arc<OperationState>>>,
+    /// 下一个操作序号
+    next_id: AtomicU64,
+}
+
+impl OperationRegistry {
+    /// 创建操作登记表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记新操作
+    ///
+    /// # 参数
+    ///
+    /// * `kind` - 操作类型
+    /// * `target` - 操作目标
+    /// * `total_bytes` - 总字节数
+    ///
+    /// # 返回值
+    ///
+    /// 返回操作句柄，句柄释放时操作自动注销
+    pub fn begin(self: &Arc<Self>, kind: OperationKind, target: &str, total_bytes: u64) -> OperationHandle {
+        let sequence = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (cancel, _) = watch::channel(false);
+        let state = Arc::new(OperationState {
+            id: format!("op-{}", sequence),
+            kind,
+            target: target.to_string(),
+            total_bytes,
+            started_at: SystemTime::now(),
+            bytes_done: AtomicU64::new(0),
+            cancel,
+        });
+
+        if let Ok(mut operations) = self.operations.lock() {
+            operations.insert(state.id.clone(), Arc::clone(&state));
+        }
+        debug!("登记操作: {} {:?} {}", state.id, kind, target);
+
+        OperationHandle {
+            state,
+            registry: Arc::downgrade(self),
+        }
+    }
+
+    /// 获取进行中的操作，按开始顺序排列
+    pub fn active_operations(&self) -> Vec<OperationInfo> {
+        let mut operations: Vec<(u64, OperationInfo)> = self.operations.lock()
+            .map(|operations| operations.values()
+                .map(|state| (Self::sequence(&state.id), state.info()))
+                .collect())
+            .unwrap_or_default();
+
+        operations.sort_by_key(|(sequence, _)| *sequence);
+        operations.into_iter().map(|(_, info)| info).collect()
+    }
+
+    /// 取消操作
+    ///
+    /// 操作立即从登记表中移除，执行方在下一个等待点停止
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 操作ID
+    ///
+    /// # 返回值
+    ///
+    /// 操作不存在（已完成或已取消）时返回错误
+    pub fn cancel(&self, id: &str) -> FuncResult<()> {
+        let state = self.operations.lock()
+            .ok()
+            .and_then(|mut operations| operations.remove(id))
+            .ok_or_else(|| ErrorInfo::new(7401, format!("操作不存在: {}", id))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Warning))?;
+
+        state.cancel.send_replace(true);
+        info!("已取消操作: {} {:?} {}", state.id, state.kind, state.target);
+        Ok(())
+    }
+
+    /// 从操作ID中解析序号
+    fn sequence(id: &str) -> u64 {
+        id.trim_start_matches("op-").parse().unwrap_or(u64::MAX)
+    }
+
+    fn unregister(&self, id: &str) {
+        if let Ok(mut operations) = self.operations.lock() {
+            operations.remove(id);
+        }
+    }
+}
+
+/// 操作句柄
+///
+/// 执行方通过句柄报告进度和响应取消
+pub struct OperationHandle {
+    state: Arc<OperationState>,
+    registry: Weak<OperationRegistry>,
+}
+
+impl OperationHandle {
+    /// 操作ID
+    pub fn id(&self) -> &str {
+        &self.state.id
+    }
+
+    /// 更新已处理字节数
+    pub fn set_progress(&self, bytes_done: u64) {
+        self.state.bytes_done.store(bytes_done, Ordering::Relaxed);
+    }
+
+    /// 操作是否已被取消
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.cancel.borrow()
+    }
+
+    /// 已取消时返回取消错误，用于在传输步骤之间检查
+    pub fn check_cancelled(&self) -> FuncResult<()> {
+        if self.is_cancelled() {
+            return Err(self.cancelled_error());
+        }
+        Ok(())
+    }
+
+    /// 运行操作，取消时丢弃传输 future 并返回取消错误
+    ///
+    /// # 参数
+    ///
+    /// * `operation` - 要执行的传输
+    ///
+    /// # 返回值
+    ///
+    /// 返回传输结果，操作被取消时返回错误
+    pub async fn run<T, F>(&self, operation: F) -> FuncResult<T>
+    where
+        F: Future<Output = FuncResult<T>>,
+    {
+        let mut cancel = self.state.cancel.subscribe();
+        tokio::select! {
+            result = operation => result,
+            _ = cancel.wait_for(|cancelled| *cancelled) => Err(self.cancelled_error()),
+        }
+    }
+
+    fn cancelled_error(&self) -> ErrorInfo {
+        ErrorInfo::new(7402, format!("操作已取消: {}", self.state.id))
+            .with_category(ErrorCategory::System)
+            .with_severity(ErrorSeverity::Warning)
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            registry.unregister(&self.state.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_long_operation() {
+        let registry = Arc::new(OperationRegistry::new());
+
+        let handle = registry.begin(OperationKind::LargeFileTransfer, "peer_device", 1000);
+        let id = handle.id().to_string();
+        let task = tokio::spawn(async move {
+            handle.run(async {
+                handle.set_progress(250);
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+                Ok(())
+            }).await
+        });
+
+        // 等待操作报告进度
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while registry.active_operations().first().map(|op| op.bytes_done) != Some(250) {
+                tokio::task::yield_now().await;
+            }
+        }).await.expect("操作应报告进度");
+
+        let operations = registry.active_operations();
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].id, id);
+        assert_eq!(operations[0].kind, OperationKind::LargeFileTransfer);
+        assert_eq!(operations[0].target, "peer_device");
+        assert_eq!(operations[0].progress(), 0.25);
+
+        registry.cancel(&id).expect("取消操作失败");
+        assert!(registry.active_operations().is_empty());
+
+        let result = tokio::time::timeout(Duration::from_secs(1), task).await
+            .expect("取消后操作应停止")
+            .expect("任务异常终止");
+        assert_eq!(result.expect_err("取消的操作应返回错误").code(), 7402);
+
+        // 重复取消返回错误
+        assert!(registry.cancel(&id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_completed_operation_is_unregistered() {
+        let registry = Arc::new(OperationRegistry::new());
+
+        let first = registry.begin(OperationKind::CloudUpload, "a.txt", 10);
+        let second = registry.begin(OperationKind::CloudDownload, "hash", 20);
+        let ids: Vec<String> = registry.active_operations().into_iter().map(|op| op.id).collect();
+        assert_eq!(ids, vec![first.id().to_string(), second.id().to_string()]);
+
+        let value = first.run(async { Ok(42) }).await.expect("操作失败");
+        assert_eq!(value, 42);
+        drop(first);
+        assert_eq!(registry.active_operations().len(), 1);
+
+        drop(second);
+        assert!(registry.active_operations().is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+//! # 重试模块
+//!
+//! 为发送消息、上传、文件传输等出站操作提供统一的重试策略：
+//! 只重试 [`ErrorInfo::is_retryable`] 判定为可重试的错误，重试间隔按指数退避增长并加入随机抖动，
+//! 避免多个设备在同一时刻集中重试。最终失败的错误在上下文中记录尝试次数。
+//!
+//! 发往多个接收方的操作（群发、广播）按接收方跟踪投递结果，
+//! 重试时只向尚未送达的接收方重发，已送达的设备不会收到重复的消息。
+
+use error::{ErrorCategory, ErrorInfo};
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::FuncResult;
+
+/// 重试配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryConfig {
+    /// 最多尝试次数（包括第一次，至少为1）
+    pub max_attempts: u32,
+    /// 第一次重试前的等待时间
+    pub initial_backoff: Duration,
+    /// 重试等待时间的上限
+    pub max_backoff: Duration,
+    /// 每次重试后等待时间的增长倍数
+    pub multiplier: f64,
+    /// 随机抖动比例（0.0 ~ 1.0），实际等待时间在 `退避 × (1 ± jitter)` 范围内
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// 不重试的配置，每个操作只尝试一次
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// 设置最多尝试次数
+    ///
+    /// # 参数
+    ///
+    /// * `max_attempts` - 最多尝试次数（包括第一次，至少为1）
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// 设置退避等待时间
+    ///
+    /// # 参数
+    ///
+    /// * `initial` - 第一次重试前的等待时间
+    /// * `max` - 等待时间的上限
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// 设置随机抖动比例
+    ///
+    /// # 参数
+    ///
+    /// * `jitter` - 抖动比例，超出 0.0 ~ 1.0 的值会被截断
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 计算第 `retry` 次重试（从1开始）前的等待时间，不含抖动
+    ///
+    /// # 参数
+    ///
+    /// * `retry` - 重试序号
+    ///
+    /// # 返回值
+    ///
+    /// 返回不超过上限的退避时间
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        Duration::from_secs_f64(backoff.min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// 在退避时间上加入随机抖动
+    pub(crate) fn jittered(&self, backoff: Duration) -> Duration {
+        if self.jitter <= 0.0 {
+            return backoff;
+        }
+        let factor = 1.0 + self.jitter * (fastrand::f64() * 2.0 - 1.0);
+        backoff.mul_f64(factor.max(0.0))
+    }
+
+    /// 按重试策略执行操作
+    ///
+    /// 操作返回可重试的错误时等待退避时间后再次执行，返回不可重试的错误或尝试次数用尽时
+    /// 返回最后一次的错误，并在上下文中记录操作名称和尝试次数
+    ///
+    /// # 参数
+    ///
+    /// * `operation` - 操作名称，用于日志和错误上下文
+    /// * `attempt` - 执行一次操作的闭包
+    ///
+    /// # 返回值
+    ///
+    /// 返回操作结果或最后一次的错误
+    pub async fn run<T, F, Fut>(&self, operation: &str, mut attempt: F) -> FuncResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = FuncResult<T>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+
+            if attempts >= max_attempts || !error.is_retryable() {
+                return Err(error.with_context(format!("{}: 共尝试 {} 次", operation, attempts)));
+            }
+
+            let delay = self.jittered(self.backoff(attempts));
+            debug!("{}失败（第 {} 次尝试），{:?} 后重试: {}", operation, attempts, delay, error);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// 发往多个接收方的投递结果
+#[derive(Debug, Default)]
+pub struct FanoutOutcome {
+    /// 已送达的接收方
+    pub delivered: Vec<String>,
+    /// 未送达的接收方及最后一次的错误
+    pub failed: Vec<(String, ErrorInfo)>,
+}
+
+impl FanoutOutcome {
+    /// 转换为送达的接收方数量
+    ///
+    /// # 返回值
+    ///
+    /// 有接收方但全部未送达时返回最后一个接收方的错误，部分送达时记录未送达的接收方
+    pub fn into_count(mut self) -> FuncResult<usize> {
+        if self.delivered.is_empty() {
+            if let Some((_, error)) = self.failed.pop() {
+                return Err(error);
+            }
+        }
+        for (recipient, error) in &self.failed {
+            warn!("未能送达 {}: {}", recipient, error);
+        }
+        Ok(self.delivered.len())
+    }
+}
+
+impl RetryConfig {
+    /// 按重试策略向多个接收方发送
+    ///
+    /// 每一轮只向尚未送达的接收方重发；接收方返回不可重试的错误后不再向其重发，
+    /// 尝试次数用尽后仍未送达的接收方连同错误一起返回
+    ///
+    /// # 参数
+    ///
+    /// * `operation` - 操作名称，用于日志和错误上下文
+    /// * `recipients` - 接收方列表
+    /// * `attempt` - 向一个接收方执行一次发送的闭包
+    ///
+    /// # 返回值
+    ///
+    /// 返回按接收方区分的投递结果
+    pub async fn run_per_recipient<F, Fut>(&self, operation: &str, recipients: Vec<String>, mut attempt: F) -> FanoutOutcome
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = FuncResult<()>>,
+    {
+        let max_attempts = self.max_attempts.max(1);
+        let mut outcome = FanoutOutcome::default();
+        let mut pending = recipients;
+        let mut attempts = 0;
+
+        while !pending.is_empty() {
+            attempts += 1;
+            let mut retry = Vec::new();
+            for recipient in pending {
+                match attempt(recipient.clone()).await {
+                    Ok(()) => outcome.delivered.push(recipient),
+                    Err(error) if attempts < max_attempts && error.is_retryable() => retry.push((recipient, error)),
+                    Err(error) => {
+                        let error = error.with_context(format!("{} {}: 共尝试 {} 次", operation, recipient, attempts));
+                        outcome.failed.push((recipient, error));
+                    }
+                }
+            }
+
+            if retry.is_empty() {
+                break;
+            }
+            let delay = self.jittered(self.backoff(attempts));
+            debug!("{}有 {} 个接收方未送达（第 {} 次尝试），{:?} 后重发", operation, retry.len(), attempts, delay);
+            tokio::time::sleep(delay).await;
+            pending = retry.into_iter().map(|(recipient, _)| recipient).collect();
+        }
+
+        outcome
+    }
+}
+
+/// 把重试后的最终错误包装为功能层错误
+///
+/// 保留原错误是否可重试的判断和上下文（包括尝试次数）
+///
+/// # 参数
+///
+/// * `error` - 重试后的最终错误
+/// * `code` - 功能层错误码
+/// * `message` - 错误描述
+/// * `category` - 错误类别
+///
+/// # 返回值
+///
+/// 返回包装后的错误
+pub(crate) fn wrap_error(error: ErrorInfo, code: u32, message: &str, category: ErrorCategory) -> ErrorInfo {
+    let wrapped = ErrorInfo::new(code, format!("{}: {}", message, error))
+        .with_category(category)
+        .with_retryable(error.is_retryable());
+    error.context().iter().cloned().fold(wrapped, ErrorInfo::with_context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        let config = RetryConfig::default()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(250));
+        assert_eq!(config.backoff(1), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(200));
+        assert_eq!(config.backoff(3), Duration::from_millis(250));
+
+        let jittered = config.clone().with_jitter(0.5).jittered(Duration::from_millis(100));
+        assert!(jittered >= Duration::from_millis(50) && jittered <= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_error_is_not_retried() {
+        let config = RetryConfig::default().with_backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let calls = AtomicU32::new(0);
+
+        let err = config.run("校验参数", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(ErrorInfo::new(7999, "参数无效".to_string()).with_category(ErrorCategory::Validation))
+        }).await.expect_err("应该失败");
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(err.context(), ["校验参数: 共尝试 1 次".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_fanout_retries_only_undelivered_recipients() {
+        let config = RetryConfig::default().with_backoff(Duration::from_millis(1), Duration::from_millis(1));
+        let calls = std::sync::Mutex::new(Vec::new());
+
+        // b 前两次可重试地失败，c 返回不可重试的错误
+        let outcome = config.run_per_recipient("群发", vec!["a".into(), "b".into(), "c".into()], |recipient| {
+            let attempt = {
+                let mut calls = calls.lock().expect("锁定失败");
+                calls.push(recipient.clone());
+                calls.iter().filter(|called| **called == recipient).count()
+            };
+            async move {
+                match recipient.as_str() {
+                    "b" if attempt < 3 => Err(ErrorInfo::new(7998, "暂时不可达".to_string())
+                        .with_category(ErrorCategory::Network)
+                        .with_retryable(true)),
+                    "c" => Err(ErrorInfo::new(7999, "拒绝接收".to_string())
+                        .with_category(ErrorCategory::Validation)),
+                    _ => Ok(()),
+                }
+            }
+        }).await;
+
+        let calls = calls.into_inner().expect("锁定失败");
+        assert_eq!(calls.iter().filter(|called| *called == "a").count(), 1, "已送达的接收方不应重发");
+        assert_eq!(calls.iter().filter(|called| *called == "b").count(), 3);
+        assert_eq!(calls.iter().filter(|called| *called == "c").count(), 1, "不可重试的错误不应重发");
+        assert_eq!(outcome.delivered, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].0, "c");
+        assert_eq!(outcome.into_count().expect("部分送达应视为成功"), 2);
+    }
+}
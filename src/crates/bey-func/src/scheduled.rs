@@ -0,0 +1,194 @@
+//! # 定时消息模块
+//!
+//! 保存在未来某个时间投递的私信（如提醒），到期后由消息功能的后台任务发送。
+//! 定时消息保存在一个JSON文件中，重启后重新加载；计划先写入临时文件再重命名，
+//! 写入中途崩溃不会损坏已保存的计划。到期时对方设备不可达的消息保留在计划中，
+//! 按退避时间重试，再次发现该设备时立即投递。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Notify, RwLock};
+use tracing::debug;
+
+use crate::FuncResult;
+
+/// 计划投递的私信
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    /// 定时消息ID
+    pub id: String,
+    /// 目标设备ID
+    pub peer_id: String,
+    /// 消息内容
+    pub content: Vec<u8>,
+    /// 计划投递时间
+    pub deliver_at: SystemTime,
+    /// 创建时间
+    pub scheduled_at: SystemTime,
+    /// 到期后保存到本地消息存储的消息ID，重试投递时沿用
+    #[serde(default)]
+    pub message_id: Option<String>,
+}
+
+/// 定时消息计划
+pub struct MessageSchedule {
+    /// 计划文件路径
+    path: PathBuf,
+    /// 定时消息ID -> 定时消息
+    items: RwLock<HashMap<String, ScheduledMessage>>,
+    /// 计划变化通知，用于唤醒投递任务
+    changed: Notify,
+}
+
+impl MessageSchedule {
+    /// 打开定时消息计划，文件不存在时从空计划开始
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 计划文件路径
+    ///
+    /// # 返回值
+    ///
+    /// 返回计划实例，文件无法读取或解析时返回错误
+    pub async fn open(path: impl AsRef<Path>) -> FuncResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        let items: Vec<ScheduledMessage> = match tokio::fs::read(&path).await {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| ErrorInfo::new(7115, format!("解析定时消息计划失败: {}", e))
+                    .with_category(ErrorCategory::Parse)
+                    .with_severity(ErrorSeverity::Error))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(ErrorInfo::new(7115, format!("读取定时消息计划失败: {}", e))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Error)),
+        };
+
+        Ok(Self {
+            path,
+            items: RwLock::new(items.into_iter().map(|item| (item.id.clone(), item)).collect()),
+            changed: Notify::new(),
+        })
+    }
+
+    /// 添加定时消息
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 目标设备ID
+    /// * `content` - 消息内容
+    /// * `deliver_at` - 计划投递时间，已过去的时间表示尽快投递
+    ///
+    /// # 返回值
+    ///
+    /// 返回已保存的定时消息或错误
+    pub async fn add(&self, peer_id: &str, content: &[u8], deliver_at: SystemTime) -> FuncResult<ScheduledMessage> {
+        let item = ScheduledMessage {
+            id: format!("scheduled-{:016x}", fastrand::u64(..)),
+            peer_id: peer_id.to_string(),
+            content: content.to_vec(),
+            deliver_at,
+            scheduled_at: SystemTime::now(),
+            message_id: None,
+        };
+
+        let mut items = self.items.write().await;
+        items.insert(item.id.clone(), item.clone());
+        self.persist(&items).await?;
+        drop(items);
+
+        self.changed.notify_one();
+        debug!("已添加定时消息: {} -> {}", item.id, peer_id);
+        Ok(item)
+    }
+
+    /// 取消定时消息
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 定时消息ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回是否存在被取消的消息，保存失败时返回错误
+    pub async fn cancel(&self, id: &str) -> FuncResult<bool> {
+        let mut items = self.items.write().await;
+        if items.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.persist(&items).await?;
+        drop(items);
+
+        self.changed.notify_one();
+        debug!("已取消定时消息: {}", id);
+        Ok(true)
+    }
+
+    /// 按计划投递时间排列的所有定时消息
+    pub async fn list(&self) -> Vec<ScheduledMessage> {
+        let mut items: Vec<ScheduledMessage> = self.items.read().await.values().cloned().collect();
+        items.sort_by(|a, b| a.deliver_at.cmp(&b.deliver_at).then_with(|| a.id.cmp(&b.id)));
+        items
+    }
+
+    /// 在指定时间已到期的定时消息，按计划投递时间排列
+    pub async fn due(&self, now: SystemTime) -> Vec<ScheduledMessage> {
+        self.list().await.into_iter().filter(|item| item.deliver_at <= now).collect()
+    }
+
+    /// 记录到期消息在本地消息存储中的ID
+    pub(crate) async fn set_message_id(&self, id: &str, message_id: &str) -> FuncResult<()> {
+        let mut items = self.items.write().await;
+        let Some(item) = items.get_mut(id) else {
+            return Ok(());
+        };
+        item.message_id = Some(message_id.to_string());
+        self.persist(&items).await
+    }
+
+    /// 移除已投递的定时消息
+    pub(crate) async fn complete(&self, id: &str) -> FuncResult<()> {
+        let mut items = self.items.write().await;
+        if items.remove(id).is_some() {
+            self.persist(&items).await?;
+        }
+        Ok(())
+    }
+
+    /// 等待计划发生变化
+    pub(crate) async fn changed(&self) {
+        self.changed.notified().await
+    }
+
+    /// 保存计划
+    ///
+    /// 先写入并同步临时文件，再重命名替换计划文件
+    async fn persist(&self, items: &HashMap<String, ScheduledMessage>) -> FuncResult<()> {
+        let items: Vec<&ScheduledMessage> = items.values().collect();
+        let data = serde_json::to_vec(&items)
+            .map_err(|e| ErrorInfo::new(7114, format!("序列化定时消息计划失败: {}", e))
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        let write = async {
+            let mut file = tokio::fs::File::create(&temp_path).await?;
+            file.write_all(&data).await?;
+            file.sync_all().await?;
+            drop(file);
+            tokio::fs::rename(&temp_path, &self.path).await
+        };
+        if let Err(e) = write.await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(ErrorInfo::new(7114, format!("保存定时消息计划失败: {}", e))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Error));
+        }
+        Ok(())
+    }
+}
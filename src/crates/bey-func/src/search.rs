@@ -0,0 +1,255 @@
+//! # 统一搜索
+//!
+//! 在本地存储的消息、剪切板和云存储文件中搜索关键词，返回按相关度排序的结果。
+//! 消息和剪切板只匹配文本内容，二进制内容（非文本类型或不是有效UTF-8）不参与匹配；
+//! 云存储文件匹配文件名和文件哈希前缀。匹配不区分大小写。
+
+use bey_storage::UnifiedStorageManager;
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+
+use crate::FuncResult;
+
+/// 结果摘要的最大字符数
+const SNIPPET_MAX_CHARS: usize = 80;
+
+/// 完全匹配的基础相关度
+const MATCH_EXACT: u32 = 300;
+/// 前缀匹配的基础相关度
+const MATCH_PREFIX: u32 = 200;
+/// 包含匹配的基础相关度
+const MATCH_CONTAINS: u32 = 100;
+
+/// 搜索范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchScopes {
+    /// 搜索消息文本
+    pub messages: bool,
+    /// 搜索剪切板文本条目
+    pub clipboard: bool,
+    /// 搜索云存储文件名和元数据
+    pub files: bool,
+}
+
+impl SearchScopes {
+    /// 搜索所有范围
+    pub fn all() -> Self {
+        Self {
+            messages: true,
+            clipboard: true,
+            files: true,
+        }
+    }
+
+    /// 不搜索任何范围，配合 `with_*` 选择需要的范围
+    pub fn none() -> Self {
+        Self {
+            messages: false,
+            clipboard: false,
+            files: false,
+        }
+    }
+
+    /// 设置是否搜索消息
+    pub fn with_messages(mut self, enabled: bool) -> Self {
+        self.messages = enabled;
+        self
+    }
+
+    /// 设置是否搜索剪切板
+    pub fn with_clipboard(mut self, enabled: bool) -> Self {
+        self.clipboard = enabled;
+        self
+    }
+
+    /// 设置是否搜索云存储文件
+    pub fn with_files(mut self, enabled: bool) -> Self {
+        self.files = enabled;
+        self
+    }
+}
+
+impl Default for SearchScopes {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// 搜索结果类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchResultKind {
+    /// 消息
+    Message,
+    /// 剪切板条目
+    Clipboard,
+    /// 云存储文件
+    File,
+}
+
+/// 单条搜索结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// 结果类型
+    pub kind: SearchResultKind,
+    /// 消息ID、剪切板条目ID或文件哈希
+    pub id: String,
+    /// 文本摘要或文件名
+    pub title: String,
+    /// 相关度，越大越相关
+    pub score: u32,
+    /// 消息时间、剪切板时间或上传时间（Unix秒）
+    pub timestamp: u64,
+}
+
+/// 搜索结果集合，按相关度从高到低排序，相关度相同时较新的在前
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchResults {
+    /// 排序后的结果
+    pub results: Vec<SearchResult>,
+}
+
+impl SearchResults {
+    /// 结果数量
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// 是否没有结果
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// 指定类型的结果
+    pub fn of_kind(&self, kind: SearchResultKind) -> impl Iterator<Item = &SearchResult> {
+        self.results.iter().filter(move |result| result.kind == kind)
+    }
+}
+
+/// 在存储中搜索关键词
+///
+/// # 参数
+///
+/// * `storage` - 统一存储管理器
+/// * `query` - 搜索关键词，首尾空白被忽略，为空时返回空结果
+/// * `scopes` - 搜索范围
+///
+/// # 返回值
+///
+/// 返回排序后的搜索结果或错误
+pub(crate) async fn search(storage: &UnifiedStorageManager, query: &str, scopes: SearchScopes) -> FuncResult<SearchResults> {
+    let query = query.trim().to_lowercase();
+    let mut results = Vec::new();
+    if query.is_empty() {
+        return Ok(SearchResults { results });
+    }
+
+    if scopes.messages {
+        for message in storage.message.get_diff(0).await {
+            let Some(text) = searchable_text(&message.content_type, &message.content) else {
+                continue;
+            };
+            if let Some(score) = match_score(text, &query) {
+                results.push(SearchResult {
+                    kind: SearchResultKind::Message,
+                    id: message.id,
+                    title: snippet(text),
+                    score,
+                    timestamp: message.timestamp,
+                });
+            }
+        }
+    }
+
+    if scopes.clipboard {
+        for entry in storage.clipboard.list_entries().await {
+            if entry.is_file_ref() {
+                continue;
+            }
+            let Some(text) = searchable_text(&entry.content_type, &entry.content) else {
+                continue;
+            };
+            if let Some(score) = match_score(text, &query) {
+                results.push(SearchResult {
+                    kind: SearchResultKind::Clipboard,
+                    id: entry.id,
+                    title: snippet(text),
+                    score,
+                    timestamp: entry.timestamp,
+                });
+            }
+        }
+    }
+
+    if scopes.files {
+        let files = storage.cloud_storage.list_files()
+            .map_err(|e| ErrorInfo::new(7323, format!("列出云存储文件失败: {}", e))
+                .with_category(ErrorCategory::Storage)
+                .with_severity(ErrorSeverity::Error))?;
+        for file in files {
+            // 文件名匹配优先，其次是哈希前缀
+            let score = match_score(&file.filename, &query)
+                .or_else(|| file.hash.starts_with(&query).then_some(MATCH_CONTAINS));
+            if let Some(score) = score {
+                results.push(SearchResult {
+                    kind: SearchResultKind::File,
+                    id: file.hash,
+                    title: file.filename,
+                    score,
+                    timestamp: file.upload_time,
+                });
+            }
+        }
+    }
+
+    results.sort_by(|a, b| b.score.cmp(&a.score).then(b.timestamp.cmp(&a.timestamp)));
+    Ok(SearchResults { results })
+}
+
+/// 计算文本与关键词的相关度
+///
+/// 完全匹配高于前缀匹配高于包含匹配，同级别内出现次数越多越相关
+///
+/// # 参数
+///
+/// * `text` - 被搜索的文本
+/// * `query` - 小写的搜索关键词
+///
+/// # 返回值
+///
+/// 不匹配时返回 `None`
+fn match_score(text: &str, query: &str) -> Option<u32> {
+    let text = text.to_lowercase();
+    let occurrences = text.matches(query).count();
+    if occurrences == 0 {
+        return None;
+    }
+
+    let base = if text == query {
+        MATCH_EXACT
+    } else if text.starts_with(query) {
+        MATCH_PREFIX
+    } else {
+        MATCH_CONTAINS
+    };
+    Some(base + occurrences.min(99) as u32)
+}
+
+/// 取出可搜索的文本，非文本类型或不是有效UTF-8的内容返回 `None`
+fn searchable_text<'a>(content_type: &str, content: &'a [u8]) -> Option<&'a str> {
+    let kind = content_type.split('/').next().unwrap_or_default();
+    if kind != "text" {
+        return None;
+    }
+    std::str::from_utf8(content).ok()
+}
+
+/// 截取文本摘要
+fn snippet(text: &str) -> String {
+    let mut chars = text.chars();
+    let snippet: String = chars.by_ref().take(SNIPPET_MAX_CHARS).collect();
+    if chars.next().is_some() {
+        format!("{}…", snippet)
+    } else {
+        snippet
+    }
+}
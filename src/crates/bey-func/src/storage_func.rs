@@ -2,14 +2,35 @@
 //!
 //! 提供基于网络的文件传输和云存储功能。
 //! 支持点对点文件传输、云存储分发。
+//! 点对点传输的文件附带发送方签名的清单，接收方验签通过后才会接受。
+//! 以流方式发送的文件按块确认，连接中断后从接收方已确认的偏移续传。
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use bey_identity::CertificateData;
 use bey_net::{TransportEngine, Token, TokenMeta, TokenHandler, NetResult};
-use bey_storage::UnifiedStorageManager;
 use async_trait::async_trait;
-use tracing::{info, debug};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::RwLock;
+use tracing::{info, debug, warn};
 
+use crate::chat::{ChatOutbound, EngineOutbound};
+use crate::chunk_sizing::{AdaptiveChunkSizer, ChunkSizingConfig, TransferSample};
+use crate::file_stream::{
+    stream_ack_token, stream_chunk_token, stream_complete_token, stream_filename, stream_offset,
+    stream_open_token, stream_transfer_id, StreamAck, StreamAcks, StreamReceiver, StreamStatus,
+    DEFAULT_STREAM_ACK_TIMEOUT, STREAM_ACK_TOKEN, STREAM_CHUNK_TOKEN, STREAM_COMPLETE_TOKEN, STREAM_OPEN_TOKEN,
+};
+use crate::idempotency::IdempotencyCache;
+use crate::manifest::{FileManifest, SignedFileManifest};
+use crate::operations::{OperationInfo, OperationKind, OperationRegistry};
+use crate::retry::{wrap_error, RetryConfig};
+use crate::storage_slot::StorageSlot;
 use crate::FuncResult;
 
 /// 存储令牌类型
@@ -18,11 +39,38 @@ const STORAGE_CLOUD_UPLOAD_TOKEN: &str = "bey.storage.cloud.upload";
 const STORAGE_CLOUD_DOWNLOAD_TOKEN: &str = "bey.storage.cloud.download";
 const STORAGE_CLOUD_NOTIFY_TOKEN: &str = "bey.storage.cloud.notify";
 
+/// 下载旧版本上传（未记录块大小）的文件时每次读取的字节数
+const CLOUD_DOWNLOAD_CHUNK_SIZE: u64 = 1024 * 1024;
+
 /// 存储功能模块
 pub struct StorageFunc {
     device_id: String,
     engine: Arc<TransportEngine>,
-    storage: Arc<UnifiedStorageManager>,
+    storage: Arc<StorageSlot>,
+    /// 本设备证书（含私钥，用于签名文件清单）
+    certificate: Arc<RwLock<Option<CertificateData>>>,
+    /// 已知对等设备证书（设备ID -> 证书PEM）
+    trusted_certificates: Arc<RwLock<HashMap<String, String>>>,
+    /// 进行中的上传、下载和传输
+    operations: Arc<OperationRegistry>,
+    /// 大文件传输的自适应块大小
+    chunk_sizer: Arc<AdaptiveChunkSizer>,
+    /// 云存储上传的幂等键记录（幂等键 -> 文件哈希）
+    upload_keys: Arc<IdempotencyCache<String>>,
+    /// 文件发送的幂等键记录
+    transfer_keys: Arc<IdempotencyCache<()>>,
+    /// 大文件发送的幂等键记录（幂等键 -> 流ID）
+    large_file_keys: Arc<IdempotencyCache<String>>,
+    /// 出站操作的重试策略
+    retry: RetryConfig,
+    /// 文件流令牌的出站通道
+    outbound: Arc<dyn ChatOutbound>,
+    /// 发送方等待中的文件流确认
+    stream_acks: Arc<StreamAcks>,
+    /// 接收中的文件流
+    stream_receiver: Arc<StreamReceiver>,
+    /// 等待文件流确认的超时时间
+    stream_ack_timeout: Duration,
 }
 
 impl StorageFunc {
@@ -30,20 +78,123 @@ impl StorageFunc {
     pub fn new(
         device_id: String,
         engine: Arc<TransportEngine>,
-        storage: Arc<UnifiedStorageManager>,
+        storage: Arc<StorageSlot>,
     ) -> Self {
         Self {
             device_id,
+            outbound: Arc::new(EngineOutbound::new(Arc::clone(&engine))),
             engine,
             storage,
+            certificate: Arc::new(RwLock::new(None)),
+            trusted_certificates: Arc::new(RwLock::new(HashMap::new())),
+            operations: Arc::new(OperationRegistry::new()),
+            chunk_sizer: Arc::new(AdaptiveChunkSizer::default()),
+            upload_keys: Arc::new(IdempotencyCache::default()),
+            transfer_keys: Arc::new(IdempotencyCache::default()),
+            large_file_keys: Arc::new(IdempotencyCache::default()),
+            retry: RetryConfig::default(),
+            stream_acks: Arc::new(StreamAcks::default()),
+            stream_receiver: Arc::new(StreamReceiver::default()),
+            stream_ack_timeout: DEFAULT_STREAM_ACK_TIMEOUT,
         }
     }
 
+    /// 设置出站操作的重试策略
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 设置大文件传输的块大小范围
+    pub fn with_chunk_sizing(mut self, config: ChunkSizingConfig) -> Self {
+        self.chunk_sizer = Arc::new(AdaptiveChunkSizer::new(config));
+        self
+    }
+
+    /// 设置幂等键的去重窗口和最多记录的数量
+    pub fn with_idempotency(mut self, window: Duration, capacity: usize) -> Self {
+        self.upload_keys = Arc::new(IdempotencyCache::new(window, capacity));
+        self.transfer_keys = Arc::new(IdempotencyCache::new(window, capacity));
+        self.large_file_keys = Arc::new(IdempotencyCache::new(window, capacity));
+        self
+    }
+
+    /// 设置文件流传输中等待接收方确认的超时时间，超时后查询接收方的偏移并续传
+    pub fn with_stream_ack_timeout(mut self, timeout: Duration) -> Self {
+        self.stream_ack_timeout = timeout;
+        self
+    }
+
+    /// 设置文件流接收方的暂存目录和暂存上限
+    ///
+    /// 未完成的文件流数据写入暂存目录，超出上限的数据块被拒绝
+    ///
+    /// # 参数
+    ///
+    /// * `spool_dir` - 暂存目录
+    /// * `max_peer_bytes` - 每个发送方最多暂存的数据量（字节）
+    /// * `max_total_bytes` - 所有发送方最多暂存的数据量（字节）
+    pub fn with_stream_spool(mut self, spool_dir: PathBuf, max_peer_bytes: u64, max_total_bytes: u64) -> Self {
+        self.stream_receiver = Arc::new(StreamReceiver::new(spool_dir, max_peer_bytes, max_total_bytes));
+        self
+    }
+
+    /// 替换文件流令牌的出站通道
+    #[cfg(test)]
+    fn with_outbound(mut self, outbound: Arc<dyn ChatOutbound>) -> Self {
+        self.outbound = outbound;
+        self
+    }
+
+    /// 获取向对等设备发送大文件时使用的块大小
+    pub fn chunk_size_for(&self, peer_id: &str) -> usize {
+        self.chunk_sizer.chunk_size(peer_id)
+    }
+
+    /// 设置本设备证书
+    ///
+    /// 证书必须包含私钥，用于签名发送的文件清单
+    pub async fn set_device_certificate(&self, certificate: CertificateData) {
+        *self.certificate.write().await = Some(certificate);
+    }
+
+    /// 获取本设备证书（PEM格式），用于分发给对等设备
+    pub async fn device_certificate_pem(&self) -> Option<String> {
+        self.certificate.read().await.as_ref().map(|cert| cert.certificate_pem.clone())
+    }
+
+    /// 信任对等设备证书
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `certificate_pem` - 对等设备证书（PEM格式）
+    pub async fn trust_peer_certificate(&self, peer_id: &str, certificate_pem: String) {
+        self.trusted_certificates.write().await.insert(peer_id.to_string(), certificate_pem);
+        debug!("已信任对等设备证书: {}", peer_id);
+    }
+
+    /// 获取进行中的操作
+    pub fn active_operations(&self) -> Vec<OperationInfo> {
+        self.operations.active_operations()
+    }
+
+    /// 取消进行中的操作
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 操作ID
+    ///
+    /// # 返回值
+    ///
+    /// 操作不存在（已完成或已取消）时返回错误
+    pub fn cancel_operation(&self, id: &str) -> FuncResult<()> {
+        self.operations.cancel(id)
+    }
+
     /// 注册存储处理器
     pub async fn register_handlers(&self, engine: &TransportEngine) -> FuncResult<()> {
-        let handler = StorageHandler {
-            storage: Arc::clone(&self.storage),
-        };
+        let handler = self.handler();
 
         engine.register_handler(Arc::new(handler)).await
             .map_err(|e| ErrorInfo::new(7301, format!("注册存储处理器失败: {}", e))
@@ -65,12 +216,126 @@ impl StorageFunc {
     ///
     /// 返回文件哈希或错误
     pub async fn upload_to_cloud(&self, filename: &str, data: &[u8]) -> FuncResult<String> {
-        // 上传到本地云存储
-        let file_hash = self.storage.cloud_storage.upload_file(filename, data).await
-            .map_err(|e| ErrorInfo::new(7302, format!("上传到云存储失败: {}", e))
-                .with_category(ErrorCategory::Storage))?;
+        self.upload_to_cloud_with_key(filename, data, None).await
+    }
+
+    /// 使用幂等键上传文件到云存储
+    ///
+    /// 窗口期内使用相同幂等键的重复上传（包括并发上传）只执行一次，
+    /// 之后的调用直接返回首次上传的文件哈希；相同的键用于文件名或内容不同的上传时返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    /// * `idempotency_key` - 幂等键，为 `None` 时每次都执行上传
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件哈希或错误
+    pub async fn upload_to_cloud_with_key(
+        &self,
+        filename: &str,
+        data: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<String> {
+        self.upload_keys.run(idempotency_key, &[filename.as_bytes(), data], || {
+            self.retry.run("上传到云存储", || self.upload_to_cloud_once(filename, data, |_, _| {}))
+        }).await
+    }
+
+    /// 从读取器上传文件到云存储，并报告上传进度
+    ///
+    /// 读取器中的数据先分块写入临时文件，再从临时文件分块上传，不把整个文件加载到内存
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `reader` - 文件数据来源
+    /// * `on_progress` - 进度回调，每写入一个块调用一次，参数为已上传字节数和总字节数
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件哈希或错误
+    pub async fn upload_to_cloud_with_progress(
+        &self,
+        filename: &str,
+        mut reader: impl AsyncRead + Unpin,
+        on_progress: impl Fn(u64, u64),
+    ) -> FuncResult<String> {
+        let spool_path = std::env::temp_dir().join(format!("bey-upload-{:016x}", fastrand::u64(..)));
+        let result = async {
+            let mut spool = tokio::fs::File::create(&spool_path).await
+                .map_err(|e| ErrorInfo::new(7308, format!("创建上传临时文件失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem))?;
+            let size = tokio::io::copy(&mut reader, &mut spool).await
+                .map_err(|e| ErrorInfo::new(7308, format!("读取上传数据失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem))?;
+            spool.flush().await
+                .map_err(|e| ErrorInfo::new(7308, format!("写入上传临时文件失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem))?;
+            drop(spool);
+
+            self.retry.run("上传到云存储", || self.upload_path_to_cloud_once(filename, &spool_path, size, &on_progress)).await
+        }.await;
+
+        if let Err(e) = tokio::fs::remove_file(&spool_path).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("删除上传临时文件 {} 失败: {}", spool_path.display(), e);
+            }
+        }
+        result
+    }
+
+    /// 从本地文件执行一次云存储上传
+    async fn upload_path_to_cloud_once(
+        &self,
+        filename: &str,
+        path: &std::path::Path,
+        size: u64,
+        on_progress: impl Fn(u64, u64),
+    ) -> FuncResult<String> {
+        let operation = self.operations.begin(OperationKind::CloudUpload, filename, size);
+
+        // 上传到本地云存储（写入过程不中断，避免留下不完整的文件）
+        operation.check_cancelled()?;
+        let file_hash = self.storage.write_access().await.cloud_storage
+            .upload_file_from_path(filename, path, |done, total| {
+                operation.set_progress(done);
+                on_progress(done, total);
+            })
+            .await
+            .map_err(|e| wrap_error(e, 7302, "上传到云存储失败", ErrorCategory::Storage))?;
 
         // 通知其他设备
+        operation.check_cancelled()?;
+        self.notify_cloud_upload(&file_hash, filename).await?;
+
+        info!("文件上传到云存储成功: {} -> {}", filename, file_hash);
+        Ok(file_hash)
+    }
+
+    /// 执行一次云存储上传
+    async fn upload_to_cloud_once(
+        &self,
+        filename: &str,
+        data: &[u8],
+        on_progress: impl Fn(u64, u64),
+    ) -> FuncResult<String> {
+        let operation = self.operations.begin(OperationKind::CloudUpload, filename, data.len() as u64);
+
+        // 上传到本地云存储（写入过程不中断，避免留下不完整的文件）
+        operation.check_cancelled()?;
+        let file_hash = self.storage.write_access().await.cloud_storage
+            .upload_file_with_progress(filename, data, |done, total| {
+                operation.set_progress(done);
+                on_progress(done, total);
+            })
+            .await
+            .map_err(|e| wrap_error(e, 7302, "上传到云存储失败", ErrorCategory::Storage))?;
+
+        // 通知其他设备
+        operation.check_cancelled()?;
         self.notify_cloud_upload(&file_hash, filename).await?;
 
         info!("文件上传到云存储成功: {} -> {}", filename, file_hash);
@@ -87,10 +352,89 @@ impl StorageFunc {
     ///
     /// 返回文件数据或错误
     pub async fn download_from_cloud(&self, file_hash: &str) -> FuncResult<Vec<u8>> {
-        // 从本地云存储下载
-        let data = self.storage.cloud_storage.download_file(file_hash).await
+        self.download_from_cloud_once(file_hash, |_, _| {}).await
+    }
+
+    /// 从云存储下载文件并写入写入器，同时报告下载进度
+    ///
+    /// 按块读取并写入，不把整个文件加载到内存。每块在写入前校验块哈希，
+    /// 全部写入后校验文件哈希；校验失败时返回错误，写入器中可能已有部分数据
+    ///
+    /// # 参数
+    ///
+    /// * `file_hash` - 文件哈希
+    /// * `writer` - 文件数据写入目标
+    /// * `on_progress` - 进度回调，每读取一个块调用一次，参数为已下载字节数和总字节数
+    ///
+    /// # 返回值
+    ///
+    /// 返回写入的字节数或错误
+    pub async fn download_from_cloud_with_progress(
+        &self,
+        file_hash: &str,
+        mut writer: impl AsyncWrite + Unpin,
+        on_progress: impl Fn(u64, u64),
+    ) -> FuncResult<u64> {
+        let storage = self.storage.current();
+        let metadata = storage.cloud_storage.file_metadata(file_hash)
             .map_err(|e| ErrorInfo::new(7303, format!("从云存储下载失败: {}", e))
                 .with_category(ErrorCategory::Storage))?;
+        let chunk_size = match metadata.chunk_size {
+            0 => CLOUD_DOWNLOAD_CHUNK_SIZE,
+            chunk_size => chunk_size,
+        };
+        let operation = self.operations.begin(OperationKind::CloudDownload, file_hash, metadata.size);
+
+        let mut hasher = Sha256::new();
+        let mut written = 0;
+        while written < metadata.size {
+            let chunk = operation.run(async {
+                storage.cloud_storage.read_range(file_hash, written, chunk_size).await
+                    .map_err(|e| ErrorInfo::new(7303, format!("从云存储下载失败: {}", e))
+                        .with_category(ErrorCategory::Storage))
+            }).await?;
+            if chunk.is_empty() {
+                break;
+            }
+
+            hasher.update(&chunk);
+            operation.run(async {
+                writer.write_all(&chunk).await
+                    .map_err(|e| ErrorInfo::new(7309, format!("写入下载数据失败: {}", e))
+                        .with_category(ErrorCategory::FileSystem))
+            }).await?;
+            written += chunk.len() as u64;
+            operation.set_progress(written);
+            on_progress(written, metadata.size);
+        }
+
+        writer.flush().await
+            .map_err(|e| ErrorInfo::new(7309, format!("写入下载数据失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+        if format!("{:x}", hasher.finalize()) != metadata.original_hash {
+            return Err(ErrorInfo::new(7303, format!("从云存储下载失败: 文件 {} 哈希验证失败", file_hash))
+                .with_category(ErrorCategory::Validation));
+        }
+
+        info!("从云存储下载文件成功: {} ({} 字节)", file_hash, written);
+        Ok(written)
+    }
+
+    /// 执行一次云存储下载
+    async fn download_from_cloud_once(&self, file_hash: &str, on_progress: impl Fn(u64, u64)) -> FuncResult<Vec<u8>> {
+        let operation = self.operations.begin(OperationKind::CloudDownload, file_hash, 0);
+
+        // 从本地云存储下载
+        let data = operation.run(async {
+            self.storage.current().cloud_storage
+                .download_file_with_progress(file_hash, |done, total| {
+                    operation.set_progress(done);
+                    on_progress(done, total);
+                })
+                .await
+                .map_err(|e| ErrorInfo::new(7303, format!("从云存储下载失败: {}", e))
+                    .with_category(ErrorCategory::Storage))
+        }).await?;
 
         info!("从云存储下载文件成功: {} ({} 字节)", file_hash, data.len());
         Ok(data)
@@ -108,32 +452,178 @@ impl StorageFunc {
     ///
     /// 返回发送结果
     pub async fn send_file_to_peer(&self, peer_id: &str, filename: &str, data: &[u8]) -> FuncResult<()> {
+        self.send_file_to_peer_with_key(peer_id, filename, data, None).await
+    }
+
+    /// 使用幂等键发送文件到对等设备
+    ///
+    /// 窗口期内使用相同幂等键的重复发送只执行一次，相同的键用于其他发送时返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    /// * `idempotency_key` - 幂等键，为 `None` 时每次都执行发送
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果
+    pub async fn send_file_to_peer_with_key(
+        &self,
+        peer_id: &str,
+        filename: &str,
+        data: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<()> {
+        self.transfer_keys.run(idempotency_key, &[peer_id.as_bytes(), filename.as_bytes(), data], || {
+            self.retry.run("发送文件", || self.send_file_to_peer_once(peer_id, filename, data))
+        }).await
+    }
+
+    /// 执行一次文件发送
+    async fn send_file_to_peer_once(&self, peer_id: &str, filename: &str, data: &[u8]) -> FuncResult<()> {
+        let operation = self.operations.begin(OperationKind::FileTransfer, peer_id, data.len() as u64);
+
         // 先存储到对象存储
+        operation.check_cancelled()?;
         let object_id = format!("{}_{}", self.device_id, filename);
-        self.storage.object_storage.store(&object_id, data).await
+        self.storage.write_access().await.object_storage.store(&object_id, data).await
             .map_err(|e| ErrorInfo::new(7304, format!("存储对象失败: {}", e))
                 .with_category(ErrorCategory::Storage))?;
 
         // 创建文件传输令牌
-        let meta = TokenMeta::new(STORAGE_FILE_TRANSFER_TOKEN.to_string(), self.device_id.clone())
-            .with_receiver(peer_id.to_string());
-
-        let mut payload = Vec::new();
-        payload.extend_from_slice(filename.as_bytes());
-        payload.push(0); // 分隔符
-        payload.extend_from_slice(data);
-
-        let token = Token::new(meta, payload);
+        let token = self.create_file_transfer_token(peer_id, filename, data).await?;
 
         // 发送令牌
-        self.engine.send_token(token).await
-            .map_err(|e| ErrorInfo::new(7305, format!("发送文件失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+        operation.run(async {
+            self.engine.send_token(token).await
+                .map_err(|e| wrap_error(e, 7305, "发送文件失败", ErrorCategory::Network))
+        }).await?;
+        operation.set_progress(data.len() as u64);
 
         info!("发送文件到对等设备: {} -> {} ({} 字节)", peer_id, filename, data.len());
         Ok(())
     }
 
+    /// 以流的方式发送文件到对等设备，连接中断后自动续传
+    ///
+    /// 文件按该设备的块大小边读取边发送，内存中只保留尚未确认的一个数据块。
+    /// 接收方确认每个数据块后才读取下一块；发送失败或等待确认超时后，先向接收方查询
+    /// 已确认的偏移，再从该偏移继续发送，每个数据块的尝试次数受重试策略限制。
+    /// 全部数据确认后发送签名清单，接收方验签通过才保存文件。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `filename` - 文件名
+    /// * `reader` - 文件数据来源
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送的字节数或错误
+    pub async fn send_file_to_peer_stream(
+        &self,
+        peer_id: &str,
+        filename: &str,
+        mut reader: impl AsyncRead + Unpin,
+    ) -> FuncResult<u64> {
+        let private_key_pem = self.private_key_pem().await?;
+        let operation = self.operations.begin(OperationKind::FileTransfer, peer_id, 0);
+        let transfer_id = format!("{}-{:016x}", self.device_id, fastrand::u64(..));
+
+        // 打开传输，新传输的接收方偏移为0
+        let open = stream_open_token(&self.device_id, peer_id, &transfer_id, filename);
+        let offset = self.retry.run("打开文件流", || self.exchange_stream_token(open.clone())).await
+            .and_then(|ack| ack.accepted_offset())
+            .map_err(|e| wrap_error(e, 7305, "发送文件失败", ErrorCategory::Network))?;
+        if offset != 0 {
+            return Err(Self::offset_mismatch(&transfer_id, 0, offset));
+        }
+
+        let mut hasher = Sha256::new();
+        let mut chunk = vec![0u8; self.chunk_sizer.chunk_size(peer_id).max(1)];
+        let mut offset = 0u64;
+        loop {
+            operation.check_cancelled()?;
+            let len = read_chunk(&mut reader, &mut chunk).await?;
+            if len == 0 {
+                break;
+            }
+
+            let data = &chunk[..len];
+            hasher.update(data);
+            let attempts = AtomicU32::new(0);
+            self.retry.run("发送文件块", || {
+                self.send_stream_chunk(peer_id, &transfer_id, filename, offset, data, &attempts)
+            }).await
+                .map_err(|e| wrap_error(e, 7305, "发送文件失败", ErrorCategory::Network))?;
+            offset += len as u64;
+            operation.set_progress(offset);
+        }
+
+        // 发送签名清单，接收方验签通过后确认完成
+        let manifest = FileManifest::from_digest(&self.device_id, filename, format!("{:x}", hasher.finalize()), offset)
+            .sign(&private_key_pem)?;
+        let complete = stream_complete_token(&self.device_id, peer_id, &transfer_id, manifest.encode_payload(&[])?);
+        let ack = self.retry.run("完成文件流", || self.exchange_stream_token(complete.clone())).await
+            .map_err(|e| wrap_error(e, 7305, "发送文件失败", ErrorCategory::Network))?;
+        ack.accepted_offset()?;
+        if ack.status != StreamStatus::Complete {
+            return Err(Self::offset_mismatch(&transfer_id, offset, ack.offset));
+        }
+
+        info!("以流方式发送文件到对等设备: {} -> {} ({} 字节)", peer_id, filename, offset);
+        Ok(offset)
+    }
+
+    /// 发送一个数据块并等待确认
+    ///
+    /// 重试时先查询接收方的偏移：接收方已收到该数据块（只是确认丢失）时直接返回，
+    /// 否则从该数据块的起始偏移重新发送
+    async fn send_stream_chunk(
+        &self,
+        peer_id: &str,
+        transfer_id: &str,
+        filename: &str,
+        offset: u64,
+        data: &[u8],
+        attempts: &AtomicU32,
+    ) -> FuncResult<()> {
+        let end = offset + data.len() as u64;
+        if attempts.fetch_add(1, Ordering::SeqCst) > 0 {
+            let open = stream_open_token(&self.device_id, peer_id, transfer_id, filename);
+            let confirmed = self.exchange_stream_token(open).await?.accepted_offset()?;
+            if confirmed == end {
+                debug!("接收方已收到偏移 {} 的数据块 ({})", offset, transfer_id);
+                return Ok(());
+            }
+            if confirmed != offset {
+                return Err(Self::offset_mismatch(transfer_id, offset, confirmed));
+            }
+            debug!("从偏移 {} 续传文件 ({})", offset, transfer_id);
+        }
+
+        let token = stream_chunk_token(&self.device_id, peer_id, transfer_id, offset, data);
+        let confirmed = self.exchange_stream_token(token).await?.accepted_offset()?;
+        if confirmed != end {
+            return Err(Self::offset_mismatch(transfer_id, end, confirmed));
+        }
+        Ok(())
+    }
+
+    /// 发送文件流令牌并等待接收方的确认
+    async fn exchange_stream_token(&self, token: Token) -> FuncResult<StreamAck> {
+        self.stream_acks.exchange(self.outbound.as_ref(), token, self.stream_ack_timeout).await
+    }
+
+    /// 接收方偏移与发送方不一致的错误
+    fn offset_mismatch(transfer_id: &str, expected: u64, actual: u64) -> ErrorInfo {
+        ErrorInfo::new(7321, format!("文件流 {} 的接收方偏移不一致: 期望 {}，实际 {}", transfer_id, expected, actual))
+            .with_category(ErrorCategory::Validation)
+            .with_severity(ErrorSeverity::Error)
+    }
+
     /// 发送大文件到对等设备
     ///
     /// # 参数
@@ -146,15 +636,112 @@ impl StorageFunc {
     ///
     /// 返回流ID或错误
     pub async fn send_large_file_to_peer(&self, peer_id: &str, filename: &str, data: &[u8]) -> FuncResult<String> {
-        // 使用 bey-net 的大文件传输功能
-        let stream_id = self.engine.send_large_file(peer_id, data.to_vec(), filename).await
-            .map_err(|e| ErrorInfo::new(7306, format!("发送大文件失败: {}", e))
-                .with_category(ErrorCategory::Network))?;
+        self.send_large_file_to_peer_with_key(peer_id, filename, data, None).await
+    }
+
+    /// 使用幂等键发送大文件到对等设备
+    ///
+    /// 窗口期内使用相同幂等键的重复发送只执行一次并返回首次发送的流ID，
+    /// 相同的键用于其他发送时返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对等设备ID
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    /// * `idempotency_key` - 幂等键，为 `None` 时每次都执行发送
+    ///
+    /// # 返回值
+    ///
+    /// 返回流ID或错误
+    pub async fn send_large_file_to_peer_with_key(
+        &self,
+        peer_id: &str,
+        filename: &str,
+        data: &[u8],
+        idempotency_key: Option<&str>,
+    ) -> FuncResult<String> {
+        self.large_file_keys.run(idempotency_key, &[peer_id.as_bytes(), filename.as_bytes(), data], || {
+            self.send_large_file_to_peer_once(peer_id, filename, data)
+        }).await
+    }
+
+    /// 执行一次大文件发送
+    async fn send_large_file_to_peer_once(&self, peer_id: &str, filename: &str, data: &[u8]) -> FuncResult<String> {
+        let operation = self.operations.begin(OperationKind::LargeFileTransfer, peer_id, data.len() as u64);
+
+        // 按该设备的链路状况选择块大小，并记录该设备传输前的丢包计数
+        let chunk_size = self.chunk_sizer.chunk_size(peer_id);
+        let before = self.peer_losses(peer_id).await;
+        let started = Instant::now();
+
+        // 使用 bey-net 的大文件传输功能，取消后在下一个数据块之前停止
+        let result = operation.run(async {
+            self.engine.send_large_file_with_chunk_size(peer_id, data.to_vec(), filename, chunk_size, |sent, _| {
+                operation.set_progress(sent);
+            }).await
+                .map_err(|e| ErrorInfo::new(7306, format!("发送大文件失败: {}", e))
+                    .with_category(ErrorCategory::Network))
+        }).await;
 
+        // 根据本次传输的吞吐量、RTT和丢包调整下次的块大小，取消的传输不计入
+        if !operation.is_cancelled() {
+            let lost = self.peer_losses(peer_id).await.saturating_sub(before);
+            let rtt = self.engine.address_rtts(peer_id).await.iter().filter_map(|measurement| measurement.rtt).min();
+            let next_chunk_size = self.chunk_sizer.record(peer_id, TransferSample {
+                bytes: data.len() as u64,
+                elapsed: started.elapsed(),
+                rtt,
+                lost: if result.is_err() { lost.max(1) } else { lost },
+            });
+            debug!("设备 {} 的传输块大小: {} -> {} 字节", peer_id, chunk_size, next_chunk_size);
+        }
+
+        let stream_id = result?;
         info!("发送大文件到对等设备: {} -> {} ({} 字节)", peer_id, filename, data.len());
         Ok(stream_id)
     }
 
+    /// 发往对等设备的重传和超时总次数，其他设备的丢包不计入
+    async fn peer_losses(&self, peer_id: &str) -> u64 {
+        self.engine.peer_metrics(peer_id).await
+            .map(|stats| stats.retransmits + stats.timeouts)
+            .unwrap_or(0)
+    }
+
+    /// 创建附带签名清单的文件传输令牌
+    async fn create_file_transfer_token(&self, peer_id: &str, filename: &str, data: &[u8]) -> FuncResult<Token> {
+        let private_key_pem = self.private_key_pem().await?;
+        let signed_manifest = FileManifest::new(&self.device_id, filename, data).sign(&private_key_pem)?;
+        let payload = signed_manifest.encode_payload(data)?;
+
+        let meta = TokenMeta::new(STORAGE_FILE_TRANSFER_TOKEN.to_string(), self.device_id.clone())
+            .with_receiver(peer_id.to_string());
+
+        Ok(Token::new(meta, payload))
+    }
+
+    /// 获取用于签名文件清单的设备私钥
+    async fn private_key_pem(&self) -> FuncResult<String> {
+        self.certificate.read().await
+            .as_ref()
+            .and_then(|cert| cert.private_key_pem.clone())
+            .ok_or_else(|| ErrorInfo::new(7307, "未配置设备私钥，无法签名文件清单".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))
+    }
+
+    /// 创建存储处理器
+    fn handler(&self) -> StorageHandler {
+        StorageHandler {
+            storage: Arc::clone(&self.storage),
+            trusted_certificates: Arc::clone(&self.trusted_certificates),
+            outbound: Arc::clone(&self.outbound),
+            stream_acks: Arc::clone(&self.stream_acks),
+            stream_receiver: Arc::clone(&self.stream_receiver),
+        }
+    }
+
     /// 通知其他设备云存储更新
     async fn notify_cloud_upload(&self, file_hash: &str, filename: &str) -> FuncResult<()> {
         // 创建通知令牌
@@ -175,9 +762,35 @@ impl StorageFunc {
     }
 }
 
+/// 读取一个完整的数据块，只有到达文件末尾时才少于缓冲区大小
+///
+/// # 返回值
+///
+/// 返回读取的字节数，0表示已到达文件末尾
+async fn read_chunk(reader: &mut (impl AsyncRead + Unpin), buffer: &mut [u8]) -> FuncResult<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let read = reader.read(&mut buffer[filled..]).await
+            .map_err(|e| ErrorInfo::new(7322, format!("读取文件数据失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
 /// 存储处理器
 struct StorageHandler {
-    storage: Arc<UnifiedStorageManager>,
+    storage: Arc<StorageSlot>,
+    trusted_certificates: Arc<RwLock<HashMap<String, String>>>,
+    /// 文件流确认的出站通道
+    outbound: Arc<dyn ChatOutbound>,
+    /// 发送方等待中的文件流确认
+    stream_acks: Arc<StreamAcks>,
+    /// 接收中的文件流
+    stream_receiver: Arc<StreamReceiver>,
 }
 
 #[async_trait]
@@ -188,6 +801,10 @@ impl TokenHandler for StorageHandler {
             STORAGE_CLOUD_UPLOAD_TOKEN.to_string(),
             STORAGE_CLOUD_DOWNLOAD_TOKEN.to_string(),
             STORAGE_CLOUD_NOTIFY_TOKEN.to_string(),
+            STREAM_OPEN_TOKEN.to_string(),
+            STREAM_CHUNK_TOKEN.to_string(),
+            STREAM_COMPLETE_TOKEN.to_string(),
+            STREAM_ACK_TOKEN.to_string(),
         ]
     }
 
@@ -199,6 +816,12 @@ impl TokenHandler for StorageHandler {
             STORAGE_CLOUD_NOTIFY_TOKEN => {
                 self.handle_cloud_notify(token).await?;
             }
+            STREAM_OPEN_TOKEN | STREAM_CHUNK_TOKEN | STREAM_COMPLETE_TOKEN => {
+                self.handle_stream_token(token).await;
+            }
+            STREAM_ACK_TOKEN => {
+                self.stream_acks.deliver(&token);
+            }
             _ => {
                 debug!("未知存储令牌类型: {}", token.meta.token_type);
             }
@@ -210,23 +833,101 @@ impl TokenHandler for StorageHandler {
 
 impl StorageHandler {
     /// 处理文件传输
+    ///
+    /// 使用发送方的已知证书验证清单签名，验证失败的文件会被拒绝
     async fn handle_file_transfer(&self, token: Token) -> NetResult<()> {
-        // 解析payload
-        let payload = &token.payload;
-        if let Some(sep_pos) = payload.iter().position(|&b| b == 0) {
-            let filename = String::from_utf8_lossy(&payload[..sep_pos]).to_string();
-            let file_data = &payload[sep_pos + 1..];
+        let sender_id = &token.meta.sender_id;
+        let (signed_manifest, file_data) = SignedFileManifest::decode_payload(&token.payload)?;
+        self.verify_manifest(sender_id, &signed_manifest, file_data).await?;
+
+        // 存储到对象存储
+        let filename = &signed_manifest.manifest.filename;
+        let object_id = format!("received_{}_{}", sender_id, filename);
+        let _ = self.storage.write_access().await.object_storage.store(&object_id, file_data).await;
 
-            // 存储到对象存储
-            let object_id = format!("received_{}_{}", token.meta.sender_id, filename);
-            let _ = self.storage.object_storage.store(&object_id, file_data).await;
+        info!("收到文件: {} 来自 {} ({} 字节)", filename, sender_id, file_data.len());
+        Ok(())
+    }
 
-            info!("收到文件: {} 来自 {} ({} 字节)", filename, token.meta.sender_id, file_data.len());
+    /// 使用发送方的已知证书验证清单签名和文件数据
+    async fn verify_manifest(&self, sender_id: &str, signed_manifest: &SignedFileManifest, file_data: &[u8]) -> NetResult<()> {
+        if signed_manifest.manifest.sender_id != sender_id {
+            warn!("拒绝文件: 清单发送方 {} 与令牌发送方 {} 不一致", signed_manifest.manifest.sender_id, sender_id);
+            return Err(ErrorInfo::new(7316, format!("文件清单发送方不一致: {}", sender_id))
+                .with_category(ErrorCategory::Permission)
+                .with_severity(ErrorSeverity::Error));
         }
 
+        let certificate_pem = self.trusted_certificates.read().await.get(sender_id).cloned()
+            .ok_or_else(|| {
+                warn!("拒绝文件: 未知发送方证书 {}", sender_id);
+                ErrorInfo::new(7317, format!("未知发送方证书: {}", sender_id))
+                    .with_category(ErrorCategory::Permission)
+                    .with_severity(ErrorSeverity::Error)
+            })?;
+
+        if let Err(e) = signed_manifest.verify(&certificate_pem, file_data) {
+            warn!("拒绝文件: {} 来自 {} - {}", signed_manifest.manifest.filename, sender_id, e);
+            return Err(e);
+        }
         Ok(())
     }
 
+    /// 处理文件流令牌，并向发送方回复确认
+    async fn handle_stream_token(&self, token: Token) {
+        let sender_id = token.meta.sender_id.as_str();
+        let ack = match stream_transfer_id(&token) {
+            None => StreamAck::rejected("缺少传输ID"),
+            Some(transfer_id) => match token.meta.token_type.as_str() {
+                STREAM_OPEN_TOKEN => match stream_filename(&token) {
+                    Some(filename) => self.stream_receiver.open(sender_id, transfer_id, filename).await,
+                    None => StreamAck::rejected("缺少文件名"),
+                },
+                STREAM_CHUNK_TOKEN => match stream_offset(&token) {
+                    Some(offset) => self.stream_receiver.append(sender_id, transfer_id, offset, &token.payload).await,
+                    None => StreamAck::rejected("缺少数据块偏移"),
+                },
+                _ => self.complete_stream(sender_id, transfer_id, &token.payload).await,
+            },
+        };
+
+        if let Err(e) = self.outbound.send_token(stream_ack_token(&token, &ack)).await {
+            warn!("发送文件流确认失败: {} - {}", sender_id, e);
+        }
+    }
+
+    /// 验证文件流的签名清单并保存文件
+    async fn complete_stream(&self, sender_id: &str, transfer_id: &str, payload: &[u8]) -> StreamAck {
+        // 完成确认丢失后发送方会重发完成令牌
+        if let Some(ack) = self.stream_receiver.completed_ack(sender_id, transfer_id) {
+            return ack;
+        }
+        let Some((filename, data)) = self.stream_receiver.take(sender_id, transfer_id).await else {
+            return StreamAck::rejected(format!("未知传输: {}", transfer_id));
+        };
+
+        let signed_manifest = match SignedFileManifest::decode_payload(payload) {
+            Ok((signed_manifest, _)) => signed_manifest,
+            Err(e) => return StreamAck::rejected(e.to_string()),
+        };
+        if signed_manifest.manifest.filename != filename {
+            return StreamAck::rejected(format!("文件清单的文件名不一致: {}", signed_manifest.manifest.filename));
+        }
+        if let Err(e) = self.verify_manifest(sender_id, &signed_manifest, &data).await {
+            return StreamAck::rejected(e.to_string());
+        }
+
+        let object_id = format!("received_{}_{}", sender_id, filename);
+        if let Err(e) = self.storage.write_access().await.object_storage.store(&object_id, &data).await {
+            warn!("保存文件流失败: {} 来自 {} - {}", filename, sender_id, e);
+            return StreamAck::rejected(format!("保存文件失败: {}", e));
+        }
+        self.stream_receiver.mark_completed(sender_id, transfer_id, data.len() as u64);
+
+        info!("收到文件流: {} 来自 {} ({} 字节)", filename, sender_id, data.len());
+        StreamAck::complete(data.len() as u64)
+    }
+
     /// 处理云存储通知
     async fn handle_cloud_notify(&self, token: Token) -> NetResult<()> {
         // 解析payload
@@ -263,9 +964,149 @@ mod tests {
         let storage_func = StorageFunc::new(
             "test_device".to_string(),
             Arc::new(engine),
-            Arc::new(storage),
+            Arc::new(StorageSlot::new(storage)),
         );
 
         assert_eq!(storage_func.device_id, "test_device");
     }
+
+    #[tokio::test]
+    async fn test_signed_manifest_loopback() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+
+        let cert_config = bey_identity::CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path().join("certs"))
+            .build()
+            .expect("创建证书配置失败");
+        let cert_manager = bey_identity::CertificateManager::initialize(cert_config).await
+            .expect("初始化证书管理器失败");
+        let sender_cert = cert_manager.issue_device_certificate("sender").await.expect("签发证书失败");
+
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+
+        let sender_storage = bey_storage::UnifiedStorageManager::new(
+            "sender".to_string(),
+            temp_dir.path().join("sender"),
+        ).await.expect("创建存储失败");
+        let sender = StorageFunc::new("sender".to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(sender_storage)));
+        sender.set_device_certificate(sender_cert.clone()).await;
+
+        let receiver_storage = bey_storage::UnifiedStorageManager::new(
+            "receiver".to_string(),
+            temp_dir.path().join("receiver"),
+        ).await.expect("创建存储失败");
+        let receiver = StorageFunc::new("receiver".to_string(), engine, Arc::new(StorageSlot::new(receiver_storage)));
+        receiver.trust_peer_certificate("sender", sender_cert.certificate_pem.clone()).await;
+        let handler = receiver.handler();
+
+        // 篡改清单中的文件大小，签名校验应失败
+        let mut tampered = sender.create_file_transfer_token("receiver", "doc.txt", b"hello").await.unwrap();
+        let (mut signed_manifest, data) = SignedFileManifest::decode_payload(&tampered.payload).unwrap();
+        signed_manifest.manifest.size += 1;
+        tampered.payload = signed_manifest.encode_payload(data).unwrap();
+        assert!(handler.handle_token(tampered).await.is_err());
+        assert!(!receiver.storage.current().object_storage.exists("received_sender_doc.txt").await);
+
+        // 有效清单应被接受
+        let valid = sender.create_file_transfer_token("receiver", "doc.txt", b"hello").await.unwrap();
+        assert!(handler.handle_token(valid).await.is_ok());
+        let stored = receiver.storage.current().object_storage.retrieve("received_sender_doc.txt").await.unwrap();
+        assert_eq!(stored, b"hello");
+    }
+
+    /// 直接交给对端存储处理器的出站通道，可以在指定的发送中模拟连接中断
+    struct LoopbackOutbound {
+        handler: StorageHandler,
+        /// 令牌送达前连接中断的发送序号（从1开始）
+        drop_before: Vec<u32>,
+        /// 令牌送达后、确认返回前连接中断的发送序号
+        drop_after: Vec<u32>,
+        calls: AtomicU32,
+    }
+
+    impl LoopbackOutbound {
+        fn new(handler: StorageHandler) -> Self {
+            Self { handler, drop_before: Vec::new(), drop_after: Vec::new(), calls: AtomicU32::new(0) }
+        }
+
+        fn connection_reset() -> ErrorInfo {
+            ErrorInfo::new(4302, "连接被重置".to_string()).with_category(ErrorCategory::Network)
+        }
+    }
+
+    #[async_trait]
+    impl ChatOutbound for LoopbackOutbound {
+        async fn send_token(&self, token: Token) -> FuncResult<()> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.drop_before.contains(&call) {
+                return Err(Self::connection_reset());
+            }
+            self.handler.handle_token(token).await?;
+            if self.drop_after.contains(&call) {
+                return Err(Self::connection_reset());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_transfer_resumes_after_connection_drop() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+
+        let cert_config = bey_identity::CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path().join("certs"))
+            .build()
+            .expect("创建证书配置失败");
+        let cert_manager = bey_identity::CertificateManager::initialize(cert_config).await
+            .expect("初始化证书管理器失败");
+        let sender_cert = cert_manager.issue_device_certificate("sender").await.expect("签发证书失败");
+
+        let engine = Arc::new(bey_net::TransportEngine::new(bey_net::EngineConfig::default()).await
+            .expect("创建引擎失败"));
+        let chunk_sizing = ChunkSizingConfig {
+            min_chunk_size: 1024,
+            max_chunk_size: 1024,
+            initial_chunk_size: 1024,
+        };
+        let retry = RetryConfig::default().with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+        let sender_storage = bey_storage::UnifiedStorageManager::new(
+            "sender".to_string(),
+            temp_dir.path().join("sender"),
+        ).await.expect("创建存储失败");
+        let sender = StorageFunc::new("sender".to_string(), Arc::clone(&engine), Arc::new(StorageSlot::new(sender_storage)))
+            .with_chunk_sizing(chunk_sizing)
+            .with_retry(retry);
+        sender.set_device_certificate(sender_cert.clone()).await;
+
+        let receiver_storage = bey_storage::UnifiedStorageManager::new(
+            "receiver".to_string(),
+            temp_dir.path().join("receiver"),
+        ).await.expect("创建存储失败");
+        let receiver = StorageFunc::new("receiver".to_string(), engine, Arc::new(StorageSlot::new(receiver_storage)))
+            .with_outbound(Arc::new(LoopbackOutbound::new(sender.handler())));
+        receiver.trust_peer_certificate("sender", sender_cert.certificate_pem.clone()).await;
+
+        // 第1次发送打开传输，之后每次发送一个数据块：
+        // 第4次（偏移2048的数据块）未送达，第8次送达但确认丢失
+        let outbound = Arc::new(LoopbackOutbound {
+            drop_before: vec![4],
+            drop_after: vec![8],
+            ..LoopbackOutbound::new(receiver.handler())
+        });
+        let sender = sender.with_outbound(outbound.clone());
+
+        let data: Vec<u8> = (0..10 * 1024 + 300).map(|i| (i % 251) as u8).collect();
+        let sent = sender.send_file_to_peer_stream("receiver", "big.bin", data.as_slice()).await
+            .expect("发送文件流失败");
+        assert_eq!(sent, data.len() as u64);
+
+        let stored = receiver.storage.current().object_storage.retrieve("received_sender_big.bin").await
+            .expect("读取收到的文件失败");
+        assert_eq!(stored, data);
+
+        // 打开 + 11个数据块 + 重发1个数据块 + 2次偏移查询 + 完成
+        assert_eq!(outbound.calls.load(Ordering::SeqCst), 16);
+    }
 }
@@ -0,0 +1,136 @@
+//! # 可替换的存储句柄
+//!
+//! 各功能模块通过 [`StorageSlot`] 共享同一个统一存储管理器，迁移存储根目录时
+//! 只需替换句柄中的管理器，所有功能模块和已注册的令牌处理器随之指向新位置。
+//!
+//! 写操作先取得写入许可；迁移期间持有独占许可，新的写入会等待迁移完成，
+//! 读取不受影响，继续读取迁移前的管理器。
+
+use bey_storage::UnifiedStorageManager;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock as GateLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// 可替换的存储句柄
+pub struct StorageSlot {
+    /// 当前使用的存储管理器
+    current: RwLock<Arc<UnifiedStorageManager>>,
+    /// 写入许可：写操作共享持有，迁移时独占持有
+    gate: GateLock<()>,
+}
+
+/// 写入许可
+///
+/// 持有期间存储不会被迁移，解引用得到当前的存储管理器
+pub struct StorageWriteGuard<'a> {
+    _gate: RwLockReadGuard<'a, ()>,
+    storage: Arc<UnifiedStorageManager>,
+}
+
+impl Deref for StorageWriteGuard<'_> {
+    type Target = UnifiedStorageManager;
+
+    fn deref(&self) -> &Self::Target {
+        &self.storage
+    }
+}
+
+impl StorageSlot {
+    /// 创建存储句柄
+    ///
+    /// # 参数
+    ///
+    /// * `storage` - 统一存储管理器
+    pub fn new(storage: UnifiedStorageManager) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(storage)),
+            gate: GateLock::new(()),
+        }
+    }
+
+    /// 获取当前的存储管理器，用于读取
+    pub fn current(&self) -> Arc<UnifiedStorageManager> {
+        match self.current.read() {
+            Ok(current) => Arc::clone(&current),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /// 获取写入许可，存储正在迁移时等待迁移完成
+    pub async fn write_access(&self) -> StorageWriteGuard<'_> {
+        let gate = self.gate.read().await;
+        StorageWriteGuard {
+            _gate: gate,
+            storage: self.current(),
+        }
+    }
+
+    /// 获取独占许可，等待进行中的写入完成并阻止新的写入
+    pub(crate) async fn exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.gate.write().await
+    }
+
+    /// 替换存储管理器，调用方应持有独占许可
+    ///
+    /// # 返回值
+    ///
+    /// 返回被替换的存储管理器，由调用方负责关闭
+    pub(crate) fn replace(&self, storage: UnifiedStorageManager) -> Arc<UnifiedStorageManager> {
+        let mut current = match self.current.write() {
+            Ok(current) => current,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        std::mem::replace(&mut *current, Arc::new(storage))
+    }
+
+    /// 等待其他持有者释放后关闭存储管理器
+    ///
+    /// 读取方可能仍短暂持有迁移前的管理器，最多等待 `timeout`
+    ///
+    /// # 参数
+    ///
+    /// * `storage` - 要关闭的存储管理器
+    /// * `timeout` - 等待其他持有者释放的最长时间
+    ///
+    /// # 返回值
+    ///
+    /// 管理器已关闭、数据库文件不再被使用时返回 `true`
+    pub(crate) async fn close(mut storage: Arc<UnifiedStorageManager>, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match Arc::try_unwrap(storage) {
+                Ok(storage) => {
+                    // 释放时数据库会刷盘，放到阻塞线程中执行
+                    return tokio::task::spawn_blocking(move || drop(storage)).await.is_ok();
+                }
+                Err(shared) if Instant::now() < deadline => {
+                    storage = shared;
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+/// 递归复制目录
+///
+/// # 参数
+///
+/// * `from` - 源目录
+/// * `to` - 目标目录，不存在时创建
+pub(crate) fn copy_dir_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
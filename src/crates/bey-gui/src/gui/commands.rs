@@ -5,7 +5,95 @@
 use std::sync::Arc;
 use bey_func::BeyFuncManager;
 use error::ErrorInfo;
+use serde::Serialize;
 use serde_json;
+use super::state::GuiState;
+
+/// 应用快照
+///
+/// 前端首次渲染所需的全部状态，一次往返即可获取
+#[derive(Debug, Clone, Serialize)]
+pub struct AppSnapshot {
+    /// 本机设备信息
+    pub device: DeviceSnapshot,
+    /// 已发现的对等设备
+    pub peers: Vec<PeerSnapshot>,
+    /// 最近的日志（从旧到新）
+    pub recent_logs: Vec<String>,
+    /// 子系统健康状态
+    pub health: HealthSnapshot,
+}
+
+/// 本机设备信息快照
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceSnapshot {
+    /// 设备ID
+    pub device_id: String,
+}
+
+/// 对等设备快照
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerSnapshot {
+    /// 设备名称
+    pub device_name: String,
+    /// 设备地址列表
+    pub addresses: Vec<String>,
+}
+
+/// 子系统健康状态快照
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSnapshot {
+    /// 网络连接状态
+    pub network_state: String,
+    /// 已发现设备数
+    pub discovered_devices: usize,
+    /// 已认证设备数
+    pub authenticated_devices: usize,
+    /// 网络错误计数
+    pub network_errors: u64,
+}
+
+/// 获取应用快照
+///
+/// 汇总设备信息、已发现设备、最近日志和子系统健康状态。
+/// 不依赖 Tauri 运行时，可以直接在测试中调用。
+///
+/// # 参数
+///
+/// * `state` - GUI 应用状态
+pub async fn get_app_snapshot(state: &GuiState) -> AppSnapshot {
+    let manager = state.func_manager();
+    let engine = manager.engine();
+
+    let mut peers = Vec::new();
+    for device_name in engine.list_discovered_devices().await {
+        let addresses = engine
+            .get_device_addresses(&device_name)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect();
+        peers.push(PeerSnapshot { device_name, addresses });
+    }
+
+    let (discovered_devices, authenticated_devices) = engine.connection_stats().await;
+    let metrics = engine.get_performance_stats().await;
+
+    AppSnapshot {
+        device: DeviceSnapshot {
+            device_id: manager.device_id().to_string(),
+        },
+        peers,
+        recent_logs: state.recent_logs().await,
+        health: HealthSnapshot {
+            network_state: format!("{:?}", engine.current_state().await),
+            discovered_devices,
+            authenticated_devices,
+            network_errors: metrics.error_count,
+        },
+    }
+}
 
 /// Tauri 命令处理器
 ///
@@ -287,3 +375,28 @@ impl TauriCommandHandler {
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_app_snapshot_contains_local_device_id() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let storage_path = temp_dir.path().to_str().expect("路径转换失败");
+
+        let manager = BeyFuncManager::new("snapshot_device", storage_path)
+            .await
+            .expect("创建功能管理器失败");
+        let state = GuiState::new(Arc::new(manager));
+        state.push_log("启动完成").await;
+
+        let snapshot = get_app_snapshot(&state).await;
+        assert_eq!(snapshot.device.device_id, "snapshot_device");
+        assert_eq!(snapshot.recent_logs, vec!["启动完成".to_string()]);
+
+        let json = serde_json::to_value(&snapshot).expect("序列化快照失败");
+        assert_eq!(json["device"]["device_id"], "snapshot_device");
+    }
+}
@@ -12,6 +12,6 @@ pub mod commands;
 pub mod events;
 pub mod state;
 
-pub use commands::TauriCommandHandler;
+pub use commands::{TauriCommandHandler, AppSnapshot, get_app_snapshot};
 pub use events::EventEmitter;
 pub use state::GuiState;
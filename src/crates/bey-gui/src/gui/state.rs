@@ -2,6 +2,7 @@
 //!
 //! 管理 GUI 应用程序的全局状态
 
+use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use bey_func::BeyFuncManager;
@@ -20,8 +21,13 @@ pub struct GuiState {
     event_emitter: Option<Arc<EventEmitter>>,
     /// 应用配置
     config: Arc<RwLock<GuiConfig>>,
+    /// 最近的日志记录（环形缓冲）
+    recent_logs: Arc<RwLock<VecDeque<String>>>,
 }
 
+/// 最近日志缓冲区的最大条目数
+pub const MAX_RECENT_LOGS: usize = 100;
+
 /// GUI 配置
 #[derive(Debug, Clone)]
 pub struct GuiConfig {
@@ -59,6 +65,7 @@ impl GuiState {
             command_handler,
             event_emitter: None,
             config: Arc::new(RwLock::new(GuiConfig::default())),
+            recent_logs: Arc::new(RwLock::new(VecDeque::with_capacity(MAX_RECENT_LOGS))),
         }
     }
 
@@ -96,6 +103,22 @@ impl GuiState {
         updater(&mut config);
     }
 
+    /// 记录一条日志
+    ///
+    /// 超出 [`MAX_RECENT_LOGS`] 时丢弃最旧的记录
+    pub async fn push_log(&self, line: impl Into<String>) {
+        let mut logs = self.recent_logs.write().await;
+        if logs.len() >= MAX_RECENT_LOGS {
+            logs.pop_front();
+        }
+        logs.push_back(line.into());
+    }
+
+    /// 获取最近的日志（从旧到新）
+    pub async fn recent_logs(&self) -> Vec<String> {
+        self.recent_logs.read().await.iter().cloned().collect()
+    }
+
     /// 启动后台任务
     ///
     /// 启动监听后端事件并转发到前端的任务
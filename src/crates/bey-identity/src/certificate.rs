@@ -5,18 +5,30 @@
 
 use crate::config::CertificateConfig;
 use crate::error::{IdentityError, ConfigError};
+use crate::keystore::KeyStore;
+use crate::status::{CertStatusRequest, CertStatusResponse, CertStatusTransport, StatusCache};
 use crate::storage::CertificateStorage;
-use crate::types::{CertificateData, CertificateType, CertificateStatus, CertificateVerificationResult, KeyPairInfo};
+use crate::tofu::{TofuResult, TofuStore, TOFU_PINS_FILE};
+use crate::types::{CertificateData, CertificateType, CertificateStatus, CertificateVerificationResult, IssuanceAction, IssuanceLogEntry, KeyPairInfo, SignatureHash, SignedIssuanceHead, ISSUANCE_LOG_GENESIS_HASH};
 use crate::validation::CertificateValidator;
-use rcgen::{Certificate, CertificateParams, DistinguishedName, DnType, KeyPair, SanType, IsCa, BasicConstraints, Issuer, KeyUsagePurpose, ExtendedKeyUsagePurpose, SigningKey,
-    PKCS_RSA_SHA256, PKCS_RSA_SHA384, PKCS_RSA_SHA512, PKCS_ECDSA_P256_SHA256, PKCS_ECDSA_P384_SHA384, PKCS_ED25519};
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use crate::IdentityResult;
+use error::ErrorInfo;
+use rcgen::{Certificate, CertificateParams, DistinguishedName, DnType, DnValue, KeyIdMethod, KeyPair, SanType, IsCa, BasicConstraints, Issuer, KeyUsagePurpose, ExtendedKeyUsagePurpose, SigningKey,
+    SignatureAlgorithm, PKCS_RSA_SHA256, PKCS_RSA_SHA384, PKCS_RSA_SHA512, PKCS_ECDSA_P256_SHA256, PKCS_ECDSA_P384_SHA384};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
 use sha2::Digest;
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use tokio::sync::RwLock;
-use tracing::{info, warn, debug};
+use tracing::{info, warn, debug, error};
+
+/// 交叉证书ID前缀
+const CROSS_CERTIFICATE_PREFIX: &str = "cross-";
 
 /// 证书管理器
 ///
@@ -28,6 +40,9 @@ pub struct CertificateManager {
     /// 证书存储
     storage: Arc<CertificateStorage>,
 
+    /// 私钥存储
+    key_store: Arc<dyn KeyStore>,
+
     /// 证书验证器
     validator: Arc<CertificateValidator>,
 
@@ -36,6 +51,15 @@ pub struct CertificateManager {
 
     /// 证书缓存
     certificate_cache: Arc<RwLock<std::collections::HashMap<String, CertificateData>>>,
+
+    /// 在线状态查询通道
+    status_transport: RwLock<Option<Arc<dyn CertStatusTransport>>>,
+
+    /// 在线状态查询结果缓存
+    status_cache: StatusCache,
+
+    /// 首次使用信任的证书固定记录
+    tofu: TofuStore,
 }
 
 /// 证书颁发机构
@@ -70,6 +94,23 @@ impl CertificateManager {
     ///
     /// 返回初始化的证书管理器
     pub async fn initialize(config: CertificateConfig) -> Result<Self, IdentityError> {
+        let key_store = config.key_store.create(&config.storage_directory)?;
+        Self::initialize_with_key_store(config, key_store).await
+    }
+
+    /// 使用自定义私钥存储初始化证书管理器
+    ///
+    /// 用于接入硬件安全模块等内置后端之外的私钥存储，配置中的私钥存储后端会被忽略
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 证书配置
+    /// * `key_store` - 私钥存储
+    ///
+    /// # 返回值
+    ///
+    /// 返回初始化的证书管理器
+    pub async fn initialize_with_key_store(config: CertificateConfig, key_store: Arc<dyn KeyStore>) -> Result<Self, IdentityError> {
         info!("初始化证书管理器");
 
         // 确保存储目录存在
@@ -91,13 +132,19 @@ impl CertificateManager {
 
         let storage = Arc::new(CertificateStorage::new(storage_config).await?);
         let validator = Arc::new(CertificateValidator::new(config.clone()));
+        let status_cache = StatusCache::new(config.status_cache_ttl);
+        let tofu = TofuStore::open(config.storage_directory.join(TOFU_PINS_FILE)).await?;
 
         let manager = Self {
             config,
             storage,
+            key_store,
             validator,
             ca_issuer: Arc::new(RwLock::new(None)),
             certificate_cache: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            status_transport: RwLock::new(None),
+            status_cache,
+            tofu,
         };
 
         // 初始化或加载CA证书
@@ -119,37 +166,135 @@ impl CertificateManager {
     pub async fn issue_device_certificate(&self, device_identifier: &str) -> Result<CertificateData, IdentityError> {
         info!("为设备 {} 签发证书", device_identifier);
 
+        // 获取CA颁发者
+        let ca_issuer = self.get_certificate_authority().await?;
+        let issuer = Issuer::new(ca_issuer.params.clone(), ca_issuer.private_key.as_ref());
+
+        self.issue_with_authority(&ca_issuer, &issuer, device_identifier, &[]).await
+    }
+
+    /// 为设备签发带主题备用名称的证书
+    ///
+    /// 除默认的 `<设备ID>.bey.local` 外，证书还包含给定的DNS名称和IP地址，
+    /// 对端可以直接用这些主机名或IP作为服务端名称建立连接。
+    /// 总是签发新证书并替换设备已有的证书。
+    ///
+    /// # 参数
+    ///
+    /// * `device_identifier` - 设备标识符
+    /// * `dns_names` - 额外的DNS名称
+    /// * `ip_addrs` - IP地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回签发的设备证书
+    pub async fn issue_device_certificate_with_sans(
+        &self,
+        device_identifier: &str,
+        dns_names: Vec<String>,
+        ip_addrs: Vec<IpAddr>,
+    ) -> Result<CertificateData, IdentityError> {
+        info!("为设备 {} 签发证书 (DNS: {:?}, IP: {:?})", device_identifier, dns_names, ip_addrs);
+
+        let mut extra_sans = Vec::with_capacity(dns_names.len() + ip_addrs.len());
+        for dns_name in dns_names {
+            extra_sans.push(SanType::DnsName(
+                dns_name.as_str().try_into()
+                    .map_err(|e| IdentityError::ValidationError(format!("DNS名称 {} 无效: {}", dns_name, e)))?
+            ));
+        }
+        extra_sans.extend(ip_addrs.into_iter().map(SanType::IpAddress));
+
+        let ca_issuer = self.get_certificate_authority().await?;
+        let issuer = Issuer::new(ca_issuer.params.clone(), ca_issuer.private_key.as_ref());
+
+        self.issue_with_authority(&ca_issuer, &issuer, device_identifier, &extra_sans).await
+    }
+
+    /// 批量为设备签发证书
+    ///
+    /// 所有证书共用同一个CA上下文签发，适合一次接入大量设备。
+    /// 已存在有效证书的设备直接返回现有证书。
+    ///
+    /// # 参数
+    ///
+    /// * `device_ids` - 设备标识符列表
+    ///
+    /// # 返回值
+    ///
+    /// 按输入顺序返回签发的设备证书。遇到无效的设备标识符时立即停止，
+    /// 返回的错误上下文中列出在此之前已签发（并已保存）的设备
+    pub async fn issue_device_certificates(&self, device_ids: &[String]) -> IdentityResult<Vec<CertificateData>> {
+        info!("批量签发 {} 个设备证书", device_ids.len());
+
+        let ca_issuer = self.get_certificate_authority().await?;
+        let issuer = Issuer::new(ca_issuer.params.clone(), ca_issuer.private_key.as_ref());
+        let mut issued: Vec<CertificateData> = Vec::with_capacity(device_ids.len());
+
+        for device_identifier in device_ids {
+            match self.issue_with_authority(&ca_issuer, &issuer, device_identifier, &[]).await {
+                Ok(certificate_data) => issued.push(certificate_data),
+                Err(e) => {
+                    warn!("批量签发在设备 {} 处中止: {}", device_identifier, e);
+                    let issued_ids: Vec<&str> = issued.iter().map(|cert| cert.device_identifier.as_str()).collect();
+                    return Err(ErrorInfo::from(e)
+                        .with_context(format!("批量签发在第 {} 个设备 {} 处中止", issued.len() + 1, device_identifier))
+                        .with_context(format!("已签发 {} 个证书: [{}]", issued.len(), issued_ids.join(", "))));
+                }
+            }
+        }
+
+        info!("批量签发完成: {} 个设备证书", issued.len());
+        Ok(issued)
+    }
+
+    /// 检查设备标识符是否可用于签发证书
+    fn validate_device_identifier(&self, device_identifier: &str) -> Result<(), IdentityError> {
+        if device_identifier.is_empty() {
+            return Err(IdentityError::ValidationError("设备标识符不能为空".to_string()));
+        }
+        if !device_identifier.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+            return Err(IdentityError::ValidationError(format!("设备标识符包含非法字符: {:?}", device_identifier)));
+        }
+        Ok(())
+    }
+
+    /// 使用给定的CA上下文为设备签发证书
+    ///
+    /// 先检查设备标识符；未指定额外主题备用名称且设备已有有效证书时直接返回，
+    /// 否则签发新证书并保存到存储和缓存
+    async fn issue_with_authority(
+        &self,
+        ca_issuer: &CertificateAuthority,
+        issuer: &Issuer<'_, &KeyPair>,
+        device_identifier: &str,
+        extra_sans: &[SanType],
+    ) -> Result<CertificateData, IdentityError> {
+        self.validate_device_identifier(device_identifier)?;
+
         // 检查是否已存在有效证书
-        if let Some(existing_cert) = self.get_device_certificate(device_identifier).await? {
+        let existing = self.get_device_certificate(device_identifier).await?;
+        if !extra_sans.is_empty() {
+            debug!("设备 {} 指定了主题备用名称，签发新证书", device_identifier);
+        } else if let Some(existing_cert) = &existing {
             if existing_cert.is_valid() {
                 warn!("设备 {} 已存在有效证书", device_identifier);
-                return Ok(existing_cert);
+                return Ok(existing_cert.clone());
             } else {
                 info!("设备 {} 的证书已过期，重新签发", device_identifier);
             }
         }
 
-        // 获取CA颁发者
-        let ca_issuer = self.get_certificate_authority().await?;
-
         // 生成设备证书参数
-        let params = self.create_device_certificate_params(device_identifier)?;
+        let mut params = self.create_device_certificate_params(device_identifier)?;
+        params.subject_alt_names.extend_from_slice(extra_sans);
 
         // 生成密钥对
-        let key_pair = match (self.config.key_algorithm.as_str(), self.config.key_size) {
-            ("RSA", 2048) => KeyPair::generate_for(&PKCS_RSA_SHA256),
-            ("RSA", 3072) => KeyPair::generate_for(&PKCS_RSA_SHA384),
-            ("RSA", 4096) => KeyPair::generate_for(&PKCS_RSA_SHA512),
-            ("ECDSA", 256) => KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256),
-            ("ECDSA", 384) => KeyPair::generate_for(&PKCS_ECDSA_P384_SHA384),
-            ("ECDSA", 521) => KeyPair::generate(), // P521 不可用，使用默认
-            ("EdDSA", 255) => KeyPair::generate_for(&PKCS_ED25519),
-            _ => KeyPair::generate(), // 默认使用ECDSA P-256
-        }.map_err(|e| IdentityError::CryptoError(format!("生成{}-{}密钥对失败: {}", self.config.key_algorithm, self.config.key_size, e)))?;
+        let key_pair = self.generate_key_pair()
+            .map_err(|e| IdentityError::CryptoError(format!("生成{}-{}密钥对失败: {}", self.config.key_algorithm, self.config.key_size, e)))?;
 
         // 使用CA签发证书
-        let issuer = Issuer::new(ca_issuer.params.clone(), ca_issuer.private_key.as_ref());
-        let cert = params.signed_by(&key_pair, &issuer)
+        let cert = params.signed_by(&key_pair, issuer)
             .map_err(|e| IdentityError::CryptoError(format!("签发证书失败: {}", e)))?;
 
         // 序列化证书
@@ -176,11 +321,20 @@ impl CertificateManager {
         // 设置密钥算法信息
         certificate_data.key_algorithm = Some(format!("{}-{}", self.config.key_algorithm, self.config.key_size));
 
+        // 设备证书由CA私钥签名，签名哈希取决于CA密钥
+        certificate_data.signature_hash = signature_hash_of(ca_issuer.private_key.algorithm());
+
         // 设置证书状态为有效
         certificate_data.set_status(CertificateStatus::Valid);
 
-        // 保存证书
-        self.storage.store_certificate(certificate_data.clone()).await?;
+        // 轮换密钥时先删除旧私钥，私钥存储中不保留被替换的密钥
+        if let Some(existing_cert) = &existing {
+            self.key_store.delete_key(&existing_cert.certificate_id).await?;
+        }
+
+        // 保存证书并记录签发日志
+        self.persist_certificate(&certificate_data).await?;
+        self.record_issuance(ca_issuer, IssuanceAction::Issued, &certificate_data).await?;
 
         // 更新缓存
         let mut cache = self.certificate_cache.write().await;
@@ -209,6 +363,254 @@ impl CertificateManager {
         Ok(result)
     }
 
+    /// 验证由对端提供的证书链
+    ///
+    /// 从终端证书出发，经由对端提供的中间证书构建到本地根CA的完整路径，
+    /// 并检查路径上每个证书的有效期、基本约束和签名。
+    ///
+    /// # 参数
+    ///
+    /// * `leaf_der` - 终端证书（DER格式）
+    /// * `intermediates` - 对端提供的中间证书（DER格式），顺序不限
+    ///
+    /// # 返回值
+    ///
+    /// 返回验证结果，成功时验证路径为从终端证书到根CA的SHA-256指纹列表；
+    /// 证书无法解析或本地CA不可用时返回错误
+    pub async fn verify_chain(&self, leaf_der: &[u8], intermediates: &[Vec<u8>]) -> IdentityResult<CertificateVerificationResult> {
+        self.verify_chain_for_server_name(leaf_der, intermediates, None).await
+    }
+
+    /// 验证由对端提供的证书链，并检查终端证书是否对给定的服务端名称有效
+    ///
+    /// 与 [`verify_chain`](Self::verify_chain) 相同，提供服务端名称时还要求终端证书的
+    /// 主题备用名称包含该DNS名称或IP地址。
+    ///
+    /// # 参数
+    ///
+    /// * `leaf_der` - 终端证书（DER格式）
+    /// * `intermediates` - 对端提供的中间证书（DER格式），顺序不限
+    /// * `server_name` - 连接时使用的服务端名称（DNS名称或IP地址），`None` 表示不检查
+    ///
+    /// # 返回值
+    ///
+    /// 返回验证结果；证书无法解析、服务端名称格式无效或本地CA不可用时返回错误
+    pub async fn verify_chain_for_server_name(
+        &self,
+        leaf_der: &[u8],
+        intermediates: &[Vec<u8>],
+        server_name: Option<&str>,
+    ) -> IdentityResult<CertificateVerificationResult> {
+        debug!("验证证书链: {} 个中间证书, 服务端名称: {:?}", intermediates.len(), server_name);
+
+        let server_name = server_name
+            .map(|name| ServerName::try_from(name.to_string())
+                .map_err(|e| IdentityError::ValidationError(format!("服务端名称 {} 无效: {}", name, e))))
+            .transpose()?;
+
+        let ca = self.get_certificate_authority().await?;
+        let ca_pem = pem::parse(&ca.certificate_data.certificate_pem)
+            .map_err(|e| IdentityError::ValidationError(format!("解析CA证书PEM失败: {}", e)))?;
+        let ca_der = CertificateDer::from(ca_pem.into_contents());
+        let trust_anchor = webpki::anchor_from_trusted_cert(&ca_der)
+            .map_err(|e| IdentityError::ValidationError(format!("解析CA证书失败: {:?}", e)))?;
+
+        let leaf_der = CertificateDer::from(leaf_der);
+        let end_entity = webpki::EndEntityCert::try_from(&leaf_der)
+            .map_err(|e| IdentityError::ValidationError(format!("解析终端证书失败: {:?}", e)))?;
+
+        // 本地CA签发的交叉证书可以连接到其他设备CA签发的证书链
+        let cross_certificates = self.cross_certificates(&ca.certificate_data.certificate_id).await?;
+        let intermediates: Vec<CertificateDer<'_>> = intermediates.iter()
+            .chain(cross_certificates.iter())
+            .map(|der| CertificateDer::from(der.as_slice()))
+            .collect();
+
+        // 终端证书可能用于mTLS的任意一端，满足其一即可
+        let trust_anchors = [trust_anchor];
+        let now = UnixTime::now();
+        let mut last_error = None;
+        for usage in [webpki::KeyUsage::client_auth(), webpki::KeyUsage::server_auth()] {
+            match end_entity.verify_for_usage(
+                webpki::ALL_VERIFICATION_ALGS,
+                &trust_anchors,
+                &intermediates,
+                now,
+                usage,
+                None,
+                None,
+            ) {
+                Ok(path) => {
+                    if let Some(server_name) = &server_name {
+                        if end_entity.verify_is_valid_for_subject_name(server_name).is_err() {
+                            let message = format!("证书的主题备用名称不包含 {}", server_name.to_str());
+                            debug!("证书链验证失败: {}", message);
+                            return Ok(CertificateVerificationResult::failure(message));
+                        }
+                    }
+
+                    let mut verification_path = vec![Self::der_fingerprint(&leaf_der)];
+                    verification_path.extend(path.intermediate_certificates().map(|cert| Self::der_fingerprint(cert.der().as_ref())));
+                    verification_path.push(Self::der_fingerprint(&ca_der));
+
+                    debug!("证书链验证通过，路径长度: {}", verification_path.len());
+                    return Ok(CertificateVerificationResult::success(verification_path));
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        let message = match last_error {
+            Some(webpki::Error::CertExpired { .. }) => "证书链中存在已过期的证书".to_string(),
+            Some(webpki::Error::CertNotValidYet { .. }) => "证书链中存在尚未生效的证书".to_string(),
+            Some(webpki::Error::UnknownIssuer) => "无法构建到受信任根CA的证书路径".to_string(),
+            Some(webpki::Error::CaUsedAsEndEntity) | Some(webpki::Error::EndEntityUsedAsCa) => "证书基本约束不符".to_string(),
+            Some(e) => format!("证书链验证失败: {:?}", e),
+            None => "证书链验证失败".to_string(),
+        };
+        debug!("证书链验证失败: {}", message);
+        Ok(CertificateVerificationResult::failure(message))
+    }
+
+    /// 计算DER编码证书的SHA-256指纹
+    fn der_fingerprint(der: &[u8]) -> String {
+        format!("{:x}", sha2::Sha256::digest(der))
+    }
+
+    /// 使用本地CA交叉签名另一个设备CA
+    ///
+    /// 以对方CA的主题和公钥签发一张由本地CA签名的中间证书，
+    /// 信任本地CA的设备即可经由该交叉证书信任对方CA签发的证书链。
+    /// 交叉证书会被保存，之后验证证书链时自动作为候选中间证书。
+    ///
+    /// # 参数
+    ///
+    /// * `other_ca_cert_der` - 对方CA证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回交叉证书；对方证书无法解析、不是CA或就是本地CA时返回错误
+    pub async fn cross_sign(&self, other_ca_cert_der: &[u8]) -> IdentityResult<CertificateData> {
+        let ca = self.get_certificate_authority().await?;
+        let other_fingerprint = Self::der_fingerprint(other_ca_cert_der);
+        info!("交叉签名CA证书: {}", other_fingerprint);
+
+        let ca_pem = pem::parse(&ca.certificate_data.certificate_pem)
+            .map_err(|e| IdentityError::ValidationError(format!("解析CA证书PEM失败: {}", e)))?;
+        if Self::der_fingerprint(ca_pem.contents()) == other_fingerprint {
+            return Err(IdentityError::ValidationError("不能交叉签名本地CA证书".to_string()).into());
+        }
+
+        let (_, other_x509) = x509_parser::parse_x509_certificate(other_ca_cert_der)
+            .map_err(|e| IdentityError::ValidationError(format!("解析对方CA证书失败: {}", e)))?;
+        if !other_x509.is_ca() {
+            return Err(IdentityError::ValidationError("对方证书不是CA证书".to_string()).into());
+        }
+        let other_public_key = rcgen::SubjectPublicKeyInfo::from_der(other_x509.public_key().raw)
+            .map_err(|e| IdentityError::CryptoError(format!("解析对方CA公钥失败: {}", e)))?;
+
+        // 保留对方CA的主题、有效期和基本约束，序列号重新生成
+        let mut params = CertificateParams::default();
+        params.distinguished_name = Self::distinguished_name_of(other_x509.subject())?;
+        params.not_before = other_x509.validity().not_before.to_datetime();
+        params.not_after = other_x509.validity().not_after.to_datetime();
+        let path_len = other_x509.basic_constraints().ok().flatten()
+            .and_then(|constraints| constraints.value.path_len_constraint);
+        params.is_ca = match path_len {
+            Some(len) => IsCa::Ca(BasicConstraints::Constrained(len.min(u8::MAX as u32) as u8)),
+            None => IsCa::Ca(BasicConstraints::Unconstrained),
+        };
+        params.key_usages.push(KeyUsagePurpose::DigitalSignature);
+        params.key_usages.push(KeyUsagePurpose::KeyCertSign);
+        params.key_usages.push(KeyUsagePurpose::CrlSign);
+        let key_identifier = other_x509.extensions().iter().find_map(|extension| match extension.parsed_extension() {
+            x509_parser::extensions::ParsedExtension::SubjectKeyIdentifier(key_identifier) => Some(key_identifier.0.to_vec()),
+            _ => None,
+        });
+        if let Some(key_identifier) = key_identifier {
+            params.key_identifier_method = KeyIdMethod::PreSpecified(key_identifier);
+        }
+
+        let issuer = Issuer::new(ca.params.clone(), ca.private_key.as_ref());
+        let cert = params.signed_by(&other_public_key, &issuer)
+            .map_err(|e| IdentityError::CryptoError(format!("签发交叉证书失败: {}", e)))?;
+
+        let subject = other_x509.subject().to_string();
+        let mut certificate_data = CertificateData::new(
+            format!("{}{}", CROSS_CERTIFICATE_PREFIX, &other_fingerprint[..16]),
+            subject.clone(),
+            cert.pem(),
+            None,
+            CertificateType::IntermediateCA,
+            ca.certificate_data.certificate_id.clone(),
+            subject,
+        );
+        certificate_data.expires_at = params.not_after.into();
+        certificate_data.calculate_fingerprint()?;
+        certificate_data.signature_hash = signature_hash_of(ca.private_key.algorithm());
+        certificate_data.set_status(CertificateStatus::Valid);
+
+        self.persist_certificate(&certificate_data).await?;
+        self.record_issuance(&ca, IssuanceAction::Issued, &certificate_data).await?;
+
+        info!("交叉证书签发成功: {} (指纹: {})", certificate_data.certificate_id, certificate_data.fingerprint);
+        Ok(certificate_data)
+    }
+
+    /// 按原顺序和字符串类型复制X.509名称，保证交叉证书的主题与原证书逐字节一致
+    fn distinguished_name_of(name: &x509_parser::x509::X509Name<'_>) -> Result<DistinguishedName, IdentityError> {
+        use x509_parser::der_parser::asn1_rs::Tag;
+
+        let mut distinguished_name = DistinguishedName::new();
+        for attribute in name.iter_attributes() {
+            let oid = attribute.attr_type().iter()
+                .ok_or_else(|| IdentityError::ValidationError(format!("不支持的名称属性: {}", attribute.attr_type())))?
+                .collect();
+            let data = attribute.attr_value().data;
+            let text = std::str::from_utf8(data)
+                .map_err(|_| IdentityError::ValidationError("名称属性不是有效的UTF-8".to_string()))?;
+            let value = match attribute.attr_value().header.tag() {
+                Tag::Utf8String => DnValue::Utf8String(text.to_string()),
+                Tag::PrintableString => DnValue::PrintableString(text.try_into()
+                    .map_err(|e| IdentityError::ValidationError(format!("名称属性无效: {}", e)))?),
+                Tag::Ia5String => DnValue::Ia5String(text.try_into()
+                    .map_err(|e| IdentityError::ValidationError(format!("名称属性无效: {}", e)))?),
+                tag => return Err(IdentityError::ValidationError(format!("不支持的名称属性类型: {:?}", tag))),
+            };
+            distinguished_name.push(DnType::CustomDnType(oid), value);
+        }
+        Ok(distinguished_name)
+    }
+
+    /// 获取本地CA签发的有效交叉证书（DER格式）
+    ///
+    /// TLS握手验证对端证书时作为候选中间证书，使对方CA签发的证书链能连接到本地CA
+    pub async fn local_cross_certificates(&self) -> IdentityResult<Vec<Vec<u8>>> {
+        let ca = self.get_certificate_authority().await?;
+        Ok(self.cross_certificates(&ca.certificate_data.certificate_id).await?)
+    }
+
+    /// 获取本地CA证书（DER格式），交给另一个集群的CA交叉签名
+    pub async fn ca_certificate_der(&self) -> IdentityResult<Vec<u8>> {
+        let ca = self.get_certificate_authority().await?;
+        let ca_pem = pem::parse(&ca.certificate_data.certificate_pem)
+            .map_err(|e| IdentityError::ValidationError(format!("解析CA证书PEM失败: {}", e)))?;
+        Ok(ca_pem.into_contents())
+    }
+
+    /// 读取本地CA签发的有效交叉证书（DER格式）
+    async fn cross_certificates(&self, ca_id: &str) -> Result<Vec<Vec<u8>>, IdentityError> {
+        let certificates = self.storage.list_certificates().await?;
+        Ok(certificates.into_iter()
+            .filter(|cert| cert.certificate_id.starts_with(CROSS_CERTIFICATE_PREFIX)
+                && cert.certificate_type == CertificateType::IntermediateCA
+                && cert.issuer_identifier == ca_id
+                && cert.is_valid())
+            .filter_map(|cert| pem::parse(&cert.certificate_pem).ok())
+            .map(|pem| pem.into_contents())
+            .collect())
+    }
+
     /// 吊销证书
     ///
     /// # 参数
@@ -230,13 +632,18 @@ impl CertificateManager {
             }
         };
 
-        // 更新证书状态
+        // 更新证书状态，吊销的证书不再保留私钥
         certificate.set_status(CertificateStatus::Revoked);
+        certificate.private_key_pem = None;
 
-        // 保存更新后的证书
-        self.storage.store_certificate(certificate.clone()).await?;
+        // 保存更新后的证书并记录签发日志
+        self.persist_certificate(&certificate).await?;
+        self.key_store.delete_key(&certificate.certificate_id).await?;
+        let ca = self.get_certificate_authority().await?;
+        self.record_issuance(&ca, IssuanceAction::Revoked, &certificate).await?;
 
         // 更新缓存
+        self.status_cache.invalidate(&certificate.fingerprint).await;
         let mut cache = self.certificate_cache.write().await;
         cache.insert(device_identifier.to_string(), certificate);
 
@@ -244,6 +651,279 @@ impl CertificateManager {
         Ok(true)
     }
 
+    /// 记录签发日志，并用CA私钥签名新的链头
+    ///
+    /// # 参数
+    ///
+    /// * `ca` - 签名链头的证书颁发机构
+    /// * `action` - 操作类型
+    /// * `certificate` - 相关证书
+    async fn record_issuance(&self, ca: &CertificateAuthority, action: IssuanceAction, certificate: &CertificateData) -> IdentityResult<()> {
+        self.storage.append_issuance_log(action, certificate, |data| {
+            ca.private_key.sign(data)
+                .map_err(|e| IdentityError::CryptoError(format!("签名签发日志链头失败: {}", e)))
+        }).await?;
+        Ok(())
+    }
+
+    /// 读取签发日志
+    ///
+    /// # 返回值
+    ///
+    /// 按记录顺序返回本CA签发的CA、设备和交叉证书以及吊销记录
+    pub async fn issuance_log(&self) -> IdentityResult<Vec<IssuanceLogEntry>> {
+        Ok(self.storage.issuance_log().await?)
+    }
+
+    /// 校验签发日志的哈希链
+    ///
+    /// # 返回值
+    ///
+    /// 序号连续、每条记录的哈希与内容一致且链接到前一条记录，
+    /// 并且最后一条记录与CA签名的链头一致时返回 `true`；
+    /// 否则说明日志被篡改、截断或重写，返回 `false`
+    pub async fn verify_issuance_log(&self) -> IdentityResult<bool> {
+        let entries = self.storage.issuance_log().await?;
+        let head = self.storage.issuance_head().await?;
+
+        let mut prev_hash = ISSUANCE_LOG_GENESIS_HASH;
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.sequence != index as u64 {
+                warn!("签发日志第 {} 条记录序号不连续: {}", index, entry.sequence);
+                return Ok(false);
+            }
+            if entry.prev_hash != prev_hash {
+                warn!("签发日志记录 #{} 未链接到前一条记录", entry.sequence);
+                return Ok(false);
+            }
+            if entry.hash != entry.compute_hash() {
+                warn!("签发日志记录 #{} 的哈希与内容不一致", entry.sequence);
+                return Ok(false);
+            }
+            prev_hash = &entry.hash;
+        }
+
+        if !self.verify_issuance_head(entries.last(), head.as_ref()).await? {
+            return Ok(false);
+        }
+
+        debug!("签发日志校验通过: {} 条记录", entries.len());
+        Ok(true)
+    }
+
+    /// 校验签名的链头与日志的最后一条记录一致
+    async fn verify_issuance_head(&self, last: Option<&IssuanceLogEntry>, head: Option<&SignedIssuanceHead>) -> IdentityResult<bool> {
+        let (last, head) = match (last, head) {
+            (None, None) => return Ok(true),
+            (Some(last), Some(head)) => (last, head),
+            _ => {
+                warn!("签发日志与签名的链头不匹配");
+                return Ok(false);
+            }
+        };
+        if head.sequence != last.sequence || head.hash != last.hash {
+            warn!("签发日志最后一条记录 #{} 与签名的链头 #{} 不一致", last.sequence, head.sequence);
+            return Ok(false);
+        }
+
+        let ca = self.get_certificate_authority().await?;
+        if !crate::signing::verify_signature(&ca.certificate_data.certificate_pem, &head.signing_bytes(), &head.signature)? {
+            warn!("签发日志链头的签名无效");
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// 设置在线状态查询通道
+    ///
+    /// # 参数
+    ///
+    /// * `transport` - 向对等设备发送状态查询的通道
+    pub async fn set_status_transport(&self, transport: Arc<dyn CertStatusTransport>) {
+        *self.status_transport.write().await = Some(transport);
+    }
+
+    /// 向签发节点查询证书当前状态
+    ///
+    /// 签发节点的回答会被缓存一段时间（见 [`CertificateConfig::status_cache_ttl`]）；
+    /// 只接受本地CA或其交叉证书信任的CA签名、并回显本次随机数的回答。
+    /// 未设置查询通道、签发节点不可达或回答未通过校验时，回退到本地证书库记录的状态。
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 签发证书的设备ID
+    /// * `fingerprint` - 证书SHA-256指纹
+    ///
+    /// # 返回值
+    ///
+    /// 返回证书状态，签发节点和本地都不认识该证书时返回 `Unknown`
+    pub async fn query_cert_status(&self, peer_id: &str, fingerprint: &str) -> IdentityResult<CertificateStatus> {
+        if let Some(status) = self.status_cache.get(peer_id, fingerprint).await {
+            debug!("使用缓存的证书状态: {} -> {:?}", fingerprint, status);
+            return Ok(status);
+        }
+
+        let transport = self.status_transport.read().await.clone();
+        if let Some(transport) = transport {
+            let request = CertStatusRequest {
+                fingerprint: fingerprint.to_string(),
+                nonce: OsRng.next_u64(),
+            };
+            match transport.request_status(peer_id, &request).await {
+                Ok(response) if response.fingerprint != fingerprint => {
+                    warn!("签发节点 {} 回答了其他证书的状态: {}", peer_id, response.fingerprint);
+                }
+                Ok(response) if response.nonce != request.nonce => {
+                    warn!("签发节点 {} 的回答与本次查询的随机数不一致，可能是重放", peer_id);
+                }
+                Ok(response) if !self.verify_status_response(&response).await? => {
+                    warn!("签发节点 {} 的回答签名无效，丢弃: {}", peer_id, fingerprint);
+                }
+                Ok(response) => {
+                    self.status_cache.insert(peer_id, fingerprint, response.status).await;
+                    debug!("签发节点 {} 回答证书状态: {} -> {:?}", peer_id, fingerprint, response.status);
+                    return Ok(response.status);
+                }
+                Err(e) => {
+                    warn!("签发节点 {} 不可达，回退到本地吊销记录: {}", peer_id, e);
+                }
+            }
+        }
+
+        Ok(self.local_certificate_status(fingerprint).await?
+            .unwrap_or(CertificateStatus::Unknown))
+    }
+
+    /// 作为签发节点回答状态查询
+    ///
+    /// 回答回显请求中的随机数，并由本地CA私钥签名
+    ///
+    /// # 参数
+    ///
+    /// * `request` - 对等设备发来的查询请求
+    ///
+    /// # 返回值
+    ///
+    /// 返回本节点记录的证书状态，本节点未签发该证书时状态为 `Unknown`
+    pub async fn answer_status_query(&self, request: &CertStatusRequest) -> IdentityResult<CertStatusResponse> {
+        let status = self.local_certificate_status(&request.fingerprint).await?
+            .unwrap_or(CertificateStatus::Unknown);
+
+        let mut response = CertStatusResponse {
+            fingerprint: request.fingerprint.clone(),
+            status,
+            produced_at: SystemTime::now(),
+            nonce: request.nonce,
+            signature: Vec::new(),
+        };
+        let ca = self.get_certificate_authority().await?;
+        response.signature = ca.private_key.sign(&response.signing_bytes())
+            .map_err(|e| IdentityError::CryptoError(format!("签名状态查询响应失败: {}", e)))?;
+
+        Ok(response)
+    }
+
+    /// 校验状态查询响应的签名
+    ///
+    /// 签名方可以是本地CA，也可以是本地CA交叉签名过的其他CA
+    async fn verify_status_response(&self, response: &CertStatusResponse) -> IdentityResult<bool> {
+        if response.signature.is_empty() {
+            return Ok(false);
+        }
+
+        let ca = self.get_certificate_authority().await?;
+        let mut trusted = vec![ca.certificate_data.certificate_pem.clone()];
+        trusted.extend(self.cross_certificates(&ca.certificate_data.certificate_id).await?
+            .into_iter()
+            .map(|der| pem::encode(&pem::Pem::new("CERTIFICATE", der))));
+
+        let data = response.signing_bytes();
+        for certificate_pem in &trusted {
+            if crate::signing::verify_signature(certificate_pem, &data, &response.signature)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// 按首次使用信任验证对端设备证书
+    ///
+    /// 第一次见到设备时记录并信任其证书，之后该设备只能出示相同的证书。
+    /// 证书变更可能意味着中间人攻击，作为严重的 [`IdentityError::CertificatePinMismatch`]
+    /// 失败返回；确认变更合法后可通过 [`CertificateManager::forget_tofu_pin`] 重新信任。
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 对端设备ID
+    /// * `cert_der` - 对端出示的证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回 [`TofuResult::FirstSeen`] 或 [`TofuResult::Matched`]，证书变更时返回错误
+    pub async fn verify_tofu(&self, device_id: &str, cert_der: &[u8]) -> IdentityResult<TofuResult> {
+        let result = self.check_tofu(device_id, cert_der)?;
+        if result == TofuResult::FirstSeen {
+            self.flush_tofu_pins().await?;
+        }
+        Ok(result)
+    }
+
+    /// 在内存中按首次使用信任检查对端设备证书，不读写文件
+    ///
+    /// 供同步的TLS证书验证器在握手中调用；第一次见到的证书需随后通过
+    /// [`CertificateManager::flush_tofu_pins`] 保存
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 对端设备ID
+    /// * `cert_der` - 对端出示的证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回 [`TofuResult::FirstSeen`] 或 [`TofuResult::Matched`]，证书变更时返回错误
+    pub fn check_tofu(&self, device_id: &str, cert_der: &[u8]) -> IdentityResult<TofuResult> {
+        match self.tofu.check(device_id, cert_der) {
+            TofuResult::Changed { pinned_fingerprint, presented_fingerprint } => {
+                error!("设备 {} 的证书已变更，可能遭受中间人攻击: 固定指纹 {}，出示指纹 {}",
+                    device_id, pinned_fingerprint, presented_fingerprint);
+                Err(IdentityError::CertificatePinMismatch(format!(
+                    "设备 {} 出示的证书 {} 与固定的证书 {} 不一致",
+                    device_id, presented_fingerprint, pinned_fingerprint
+                )).into())
+            }
+            result => Ok(result),
+        }
+    }
+
+    /// 移除设备的TOFU固定记录，该设备下次出示的证书会被重新信任
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回是否存在被移除的记录
+    pub async fn forget_tofu_pin(&self, device_id: &str) -> IdentityResult<bool> {
+        Ok(self.tofu.forget(device_id).await?)
+    }
+
+    /// 保存握手中新记录的TOFU固定记录，没有新记录时不写入
+    pub async fn flush_tofu_pins(&self) -> IdentityResult<()> {
+        Ok(self.tofu.flush().await?)
+    }
+
+    /// 从本地证书库查找证书状态
+    async fn local_certificate_status(&self, fingerprint: &str) -> IdentityResult<Option<CertificateStatus>> {
+        let certificates = self.storage.list_certificates().await?;
+        Ok(certificates.into_iter()
+            .find(|certificate| certificate.fingerprint == fingerprint)
+            .map(|certificate| match certificate.status {
+                CertificateStatus::Valid if certificate.is_expired() => CertificateStatus::Expired,
+                status => status,
+            }))
+    }
+
     /// 获取设备证书
     ///
     /// # 参数
@@ -265,8 +945,11 @@ impl CertificateManager {
             }
         }
 
-        // 从存储中加载
-        let certificate = self.storage.retrieve_certificate(device_identifier).await?;
+        // 从存储中加载，私钥从私钥存储中读取
+        let certificate = match self.storage.retrieve_certificate(device_identifier).await? {
+            Some(certificate) => Some(self.attach_private_key(certificate).await?),
+            None => None,
+        };
 
         // 如果找到，更新缓存
         if let Some(ref cert) = certificate {
@@ -291,6 +974,25 @@ impl CertificateManager {
         Ok(certificates)
     }
 
+    /// 保存证书，私钥写入私钥存储，证书存储中只保留不含私钥的证书
+    async fn persist_certificate(&self, certificate: &CertificateData) -> Result<(), IdentityError> {
+        let mut stored = certificate.clone();
+        if let Some(private_key_pem) = stored.private_key_pem.take() {
+            self.key_store.store_key(&certificate.certificate_id, &private_key_pem).await?;
+        }
+        self.storage.store_certificate(stored).await
+    }
+
+    /// 从私钥存储读取证书的私钥
+    ///
+    /// 证书中已带有私钥（私钥存储引入之前保存的证书）时保持不变
+    async fn attach_private_key(&self, mut certificate: CertificateData) -> Result<CertificateData, IdentityError> {
+        if certificate.private_key_pem.is_none() {
+            certificate.private_key_pem = self.key_store.load_key(&certificate.certificate_id).await?;
+        }
+        Ok(certificate)
+    }
+
     /// 获取证书颁发机构
     ///
     /// # 返回值
@@ -312,6 +1014,7 @@ impl CertificateManager {
         // 尝试加载现有CA证书
         if let Ok(Some(ca_data)) = self.storage.retrieve_certificate("ca").await {
             info!("加载现有CA证书");
+            let ca_data = self.attach_private_key(ca_data).await?;
             let ca = self.recreate_certificate_authority(&ca_data).await?;
             let mut ca_issuer = self.ca_issuer.write().await;
             *ca_issuer = Some(ca);
@@ -322,8 +1025,9 @@ impl CertificateManager {
         info!("创建新的CA证书");
         let ca = self.create_certificate_authority().await?;
 
-        // 保存CA证书
-        self.storage.store_certificate(ca.certificate_data.clone()).await?;
+        // 保存CA证书并记录签发日志
+        self.persist_certificate(&ca.certificate_data).await?;
+        self.record_issuance(&ca, IssuanceAction::Issued, &ca.certificate_data).await?;
 
         let mut ca_issuer = self.ca_issuer.write().await;
         *ca_issuer = Some(ca);
@@ -347,16 +1051,8 @@ impl CertificateManager {
         params.key_usages.push(KeyUsagePurpose::CrlSign);
 
         // 生成密钥对
-        let key_pair = match (self.config.key_algorithm.as_str(), self.config.key_size) {
-            ("RSA", 2048) => KeyPair::generate_for(&PKCS_RSA_SHA256),
-            ("RSA", 3072) => KeyPair::generate_for(&PKCS_RSA_SHA384),
-            ("RSA", 4096) => KeyPair::generate_for(&PKCS_RSA_SHA512),
-            ("ECDSA", 256) => KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256),
-            ("ECDSA", 384) => KeyPair::generate_for(&PKCS_ECDSA_P384_SHA384),
-            ("ECDSA", 521) => KeyPair::generate(), // P521 不可用，使用默认
-            ("EdDSA", 255) => KeyPair::generate_for(&PKCS_ED25519),
-            _ => KeyPair::generate(), // 默认使用ECDSA P-256
-        }.map_err(|e| IdentityError::CryptoError(format!("生成CA{}-{}密钥对失败: {}", self.config.key_algorithm, self.config.key_size, e)))?;
+        let key_pair = self.generate_key_pair()
+            .map_err(|e| IdentityError::CryptoError(format!("生成CA{}-{}密钥对失败: {}", self.config.key_algorithm, self.config.key_size, e)))?;
 
         // 创建CA证书（自签名）
         let cert = params.self_signed(&key_pair)
@@ -385,6 +1081,7 @@ impl CertificateManager {
 
         // 设置密钥算法信息
         certificate_data.key_algorithm = Some(format!("{}-{}", self.config.key_algorithm, self.config.key_size));
+        certificate_data.signature_hash = signature_hash_of(key_pair.algorithm());
 
         // 设置证书状态为有效
         certificate_data.set_status(CertificateStatus::Valid);
@@ -425,6 +1122,12 @@ impl CertificateManager {
         // 验证私钥与证书的匹配性
         self.verify_key_certificate_match(&private_key, &ca_data.certificate_pem).await?;
 
+        if let Some(configured) = self.config.signature_algorithm() {
+            if configured != private_key.algorithm() {
+                warn!("现有CA的签名算法与配置不符，继续使用现有CA签发证书");
+            }
+        }
+
         // 重建CA证书参数，保持与原始证书一致
         let mut params = CertificateParams::default();
         params.distinguished_name = self.create_ca_distinguished_name()?;
@@ -461,6 +1164,16 @@ impl CertificateManager {
         })
     }
 
+    /// 按配置的密钥算法、密钥长度和签名哈希生成密钥对
+    ///
+    /// 组合不受支持时使用默认的ECDSA P-256密钥
+    fn generate_key_pair(&self) -> Result<KeyPair, rcgen::Error> {
+        match self.config.signature_algorithm() {
+            Some(algorithm) => KeyPair::generate_for(algorithm),
+            None => KeyPair::generate(),
+        }
+    }
+
     /// 创建设备证书参数
     fn create_device_certificate_params(&self, device_identifier: &str) -> Result<CertificateParams, IdentityError> {
         let mut params = CertificateParams::default();
@@ -479,6 +1192,9 @@ impl CertificateManager {
                 .map_err(|e| IdentityError::ValidationError(format!("DNS名称转换失败: {}", e)))?
         ));
 
+        // 设备证书不能用作CA
+        params.is_ca = IsCa::ExplicitNoCa;
+
         // 设置密钥用途
         params.key_usages.push(KeyUsagePurpose::DigitalSignature);
         params.key_usages.push(KeyUsagePurpose::KeyEncipherment);
@@ -628,6 +1344,19 @@ pub struct CertificateManagerStatistics {
     pub initialized_at: SystemTime,
 }
 
+/// 识别rcgen签名算法使用的哈希，Ed25519等算法返回 `None`
+fn signature_hash_of(algorithm: &SignatureAlgorithm) -> Option<SignatureHash> {
+    if algorithm == &PKCS_RSA_SHA256 || algorithm == &PKCS_ECDSA_P256_SHA256 {
+        Some(SignatureHash::Sha256)
+    } else if algorithm == &PKCS_RSA_SHA384 || algorithm == &PKCS_ECDSA_P384_SHA384 {
+        Some(SignatureHash::Sha384)
+    } else if algorithm == &PKCS_RSA_SHA512 {
+        Some(SignatureHash::Sha512)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -923,4 +1652,635 @@ mod tests {
         let storage_stats = updated_stats.storage_statistics;
         assert!(storage_stats.total_certificates >= 3, "应该至少有3个证书（包括CA）");
     }
-}
\ No newline at end of file
+
+    /// 创建测试用证书参数
+    fn chain_test_params(common_name: &str, is_ca: bool) -> CertificateParams {
+        let mut params = CertificateParams::new(vec![common_name.to_string()]).expect("创建证书参数失败");
+        params.distinguished_name = DistinguishedName::new();
+        params.distinguished_name.push(DnType::CommonName, common_name);
+        if is_ca {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params.key_usages.push(KeyUsagePurpose::KeyCertSign);
+        } else {
+            params.key_usages.push(KeyUsagePurpose::DigitalSignature);
+            params.extended_key_usages.push(ExtendedKeyUsagePurpose::ClientAuth);
+        }
+        params
+    }
+
+    #[tokio::test]
+    async fn test_verify_chain_through_intermediate() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Chain Test Root CA")
+            .build()
+            .expect("配置创建失败");
+        let manager = CertificateManager::initialize(config).await.expect("证书管理器初始化失败");
+        let ca = manager.get_certificate_authority().await.expect("获取CA失败");
+        let root_issuer = Issuer::new(ca.params.clone(), ca.private_key.as_ref());
+
+        // 根CA -> 中间CA -> 终端证书
+        let intermediate_key = KeyPair::generate().expect("生成密钥失败");
+        let intermediate_params = chain_test_params("Chain Test Intermediate CA", true);
+        let intermediate = intermediate_params.clone()
+            .signed_by(&intermediate_key, &root_issuer)
+            .expect("签发中间证书失败");
+        let intermediate_issuer = Issuer::new(intermediate_params, &intermediate_key);
+
+        let leaf_key = KeyPair::generate().expect("生成密钥失败");
+        let leaf = chain_test_params("chain-leaf", false)
+            .signed_by(&leaf_key, &intermediate_issuer)
+            .expect("签发终端证书失败");
+
+        let intermediates = vec![intermediate.der().to_vec()];
+        let result = manager.verify_chain(leaf.der(), &intermediates).await.expect("验证证书链失败");
+        assert!(result.is_valid, "完整证书链应该验证通过: {:?}", result.error_message);
+        assert_eq!(result.verification_path.len(), 3, "验证路径应包含终端、中间和根证书");
+
+        // 缺少中间证书时无法构建到根CA的路径
+        let result = manager.verify_chain(leaf.der(), &[]).await.expect("验证证书链失败");
+        assert!(!result.is_valid, "缺少中间证书的证书链应该验证失败");
+
+        // 中间证书不是CA时违反基本约束
+        let bogus_key = KeyPair::generate().expect("生成密钥失败");
+        let bogus_params = chain_test_params("Chain Test Bogus Intermediate", false);
+        let bogus = bogus_params.clone()
+            .signed_by(&bogus_key, &root_issuer)
+            .expect("签发伪中间证书失败");
+        let bogus_issuer = Issuer::new(bogus_params, &bogus_key);
+        let bogus_leaf = chain_test_params("bogus-leaf", false)
+            .signed_by(&leaf_key, &bogus_issuer)
+            .expect("签发终端证书失败");
+
+        let result = manager.verify_chain(bogus_leaf.der(), &[bogus.der().to_vec()]).await.expect("验证证书链失败");
+        assert!(!result.is_valid, "经由非CA中间证书的证书链应该验证失败");
+    }
+
+    #[tokio::test]
+    async fn test_cross_sign_trusts_other_ca_chain() {
+        let config_for = |dir: &TempDir, common_name: &str| {
+            CertificateConfig::builder()
+                .with_storage_directory(dir.path())
+                .with_ca_common_name(common_name)
+                .build()
+                .expect("配置创建失败")
+        };
+        let dir_a = TempDir::new().expect("无法创建临时目录");
+        let dir_b = TempDir::new().expect("无法创建临时目录");
+        let manager_a = CertificateManager::initialize(config_for(&dir_a, "Cross Sign CA A")).await.expect("证书管理器A初始化失败");
+        let manager_b = CertificateManager::initialize(config_for(&dir_b, "Cross Sign CA B")).await.expect("证书管理器B初始化失败");
+
+        let leaf = manager_b.issue_device_certificate("cross-leaf").await.expect("签发设备证书失败");
+        let leaf_der = pem::parse(&leaf.certificate_pem).expect("解析证书PEM失败").into_contents();
+        let ca_b = manager_b.get_certificate_authority().await.expect("获取CA失败");
+        let ca_b_der = pem::parse(&ca_b.certificate_data.certificate_pem).expect("解析CA证书PEM失败").into_contents();
+
+        // 交叉签名之前CA A不信任CA B签发的证书
+        let result = manager_a.verify_chain(&leaf_der, &[]).await.expect("验证证书链失败");
+        assert!(!result.is_valid, "交叉签名之前应该验证失败");
+
+        let cross = manager_a.cross_sign(&ca_b_der).await.expect("交叉签名失败");
+        assert_eq!(cross.certificate_type, CertificateType::IntermediateCA);
+        assert!(cross.private_key_pem.is_none(), "交叉证书不应包含私钥");
+
+        let result = manager_a.verify_chain(&leaf_der, &[]).await.expect("验证证书链失败");
+        assert!(result.is_valid, "经由交叉证书的证书链应该验证通过: {:?}", result.error_message);
+        assert_eq!(result.verification_path.len(), 3, "验证路径应包含终端、交叉和根证书");
+        assert_eq!(result.verification_path[1], cross.fingerprint);
+
+        // 交叉证书被持久化，重新加载后仍然生效
+        drop(manager_a);
+        let manager_a = CertificateManager::initialize(config_for(&dir_a, "Cross Sign CA A")).await.expect("证书管理器A重新加载失败");
+        let result = manager_a.verify_chain(&leaf_der, &[]).await.expect("验证证书链失败");
+        assert!(result.is_valid, "重新加载后交叉证书应该仍然生效");
+
+        // 非CA证书和本地CA证书不能被交叉签名
+        assert!(manager_a.cross_sign(&leaf_der).await.is_err(), "终端证书不能被交叉签名");
+        let ca_a = manager_a.get_certificate_authority().await.expect("获取CA失败");
+        let ca_a_der = pem::parse(&ca_a.certificate_data.certificate_pem).expect("解析CA证书PEM失败").into_contents();
+        assert!(manager_a.cross_sign(&ca_a_der).await.is_err(), "本地CA不能交叉签名自身");
+    }
+
+    #[tokio::test]
+    async fn test_device_certificate_key_usage_constraints() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Usage Test CA")
+            .build()
+            .expect("配置创建失败");
+        let manager = CertificateManager::initialize(config).await.expect("证书管理器初始化失败");
+
+        // 签发的设备证书限定为客户端和服务端认证，且不能用作CA
+        let issued = manager.issue_device_certificate("usage-device").await.expect("设备证书签发失败");
+        let der = pem::parse(&issued.certificate_pem).expect("解析证书PEM失败");
+        let (_, x509) = x509_parser::parse_x509_certificate(der.contents()).expect("解析证书失败");
+        let eku = x509.extended_key_usage().expect("解析扩展密钥用途失败").expect("缺少扩展密钥用途");
+        assert!(eku.value.client_auth && eku.value.server_auth, "设备证书应包含clientAuth和serverAuth");
+        let constraints = x509.basic_constraints().expect("解析基本约束失败").expect("缺少基本约束");
+        assert!(!constraints.value.ca, "设备证书不应是CA");
+        let key_usage = x509.key_usage().expect("解析密钥用途失败").expect("缺少密钥用途");
+        assert!(key_usage.value.digital_signature() && !key_usage.value.key_cert_sign());
+
+        let ca = manager.get_certificate_authority().await.expect("获取CA失败");
+        let ca_pem = pem::parse(&ca.certificate_data.certificate_pem).expect("解析CA证书PEM失败");
+        let (_, ca_x509) = x509_parser::parse_x509_certificate(ca_pem.contents()).expect("解析CA证书失败");
+        assert!(ca_x509.basic_constraints().expect("解析基本约束失败").is_some_and(|c| c.value.ca), "CA证书应带有CA:TRUE");
+
+        // 伪造的带有CA基本约束的终端证书被拒绝
+        let root_issuer = Issuer::new(ca.params.clone(), ca.private_key.as_ref());
+        let forged_key = KeyPair::generate().expect("生成密钥失败");
+        let forged = chain_test_params("forged-device", true)
+            .signed_by(&forged_key, &root_issuer)
+            .expect("签发伪造证书失败");
+        let mut forged_data = CertificateData::new(
+            "forged-device".to_string(),
+            "forged-device".to_string(),
+            forged.pem(),
+            None,
+            CertificateType::Device,
+            ca.certificate_data.certificate_id.clone(),
+            "CN=forged-device".to_string(),
+        );
+        forged_data.expires_at = SystemTime::now() + Duration::from_secs(86400);
+        forged_data.set_status(CertificateStatus::Valid);
+        let result = manager.verify_certificate(&forged_data).await.expect("证书验证失败");
+        assert!(!result.is_valid, "带有CA基本约束的终端证书应该验证失败");
+        assert!(result.error_message.unwrap_or_default().contains("CA基本约束"));
+
+        // 缺少clientAuth的设备证书被拒绝
+        let mut server_params = chain_test_params("server-only-device", false);
+        server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        let server_only = server_params.signed_by(&forged_key, &root_issuer).expect("签发证书失败");
+        let mut server_data = forged_data.clone();
+        server_data.certificate_id = "server-only-device".to_string();
+        server_data.certificate_pem = server_only.pem();
+        let result = manager.verify_certificate(&server_data).await.expect("证书验证失败");
+        assert!(!result.is_valid, "缺少clientAuth的设备证书应该验证失败");
+        assert!(result.error_message.unwrap_or_default().contains("clientAuth"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_certificate_issuance() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Test CA")
+            .build()
+            .expect("配置创建失败");
+
+        let manager = CertificateManager::initialize(config).await
+            .expect("证书管理器初始化失败");
+
+        let device_ids: Vec<String> = (0..50).map(|i| format!("batch-device-{:02}", i)).collect();
+        let certificates = manager.issue_device_certificates(&device_ids).await
+            .expect("批量签发失败");
+        assert_eq!(certificates.len(), 50);
+
+        let mut fingerprints = std::collections::HashSet::new();
+        for (device_id, certificate) in device_ids.iter().zip(&certificates) {
+            assert_eq!(&certificate.device_identifier, device_id, "证书应按输入顺序返回");
+            let result = manager.verify_certificate(certificate).await.expect("证书验证失败");
+            assert!(result.is_valid, "批量签发的证书应该通过验证");
+            assert!(fingerprints.insert(certificate.fingerprint.clone()), "证书指纹应该唯一");
+        }
+
+        // 遇到无效标识符时中止，并报告已签发的设备
+        let mixed = vec!["batch-new-a".to_string(), "bad device".to_string(), "batch-new-b".to_string()];
+        let error = manager.issue_device_certificates(&mixed).await
+            .expect_err("包含无效标识符时应该失败");
+        assert!(error.context().iter().any(|context| context.contains("batch-new-a")));
+        assert!(manager.get_device_certificate("batch-new-a").await.expect("查询失败").is_some());
+        assert!(manager.get_device_certificate("batch-new-b").await.expect("查询失败").is_none());
+
+        // 单个签发同样检查标识符
+        for invalid in ["", "bad device", "../escape"] {
+            assert!(manager.issue_device_certificate(invalid).await.is_err(), "标识符 {:?} 应被拒绝", invalid);
+        }
+        assert!(manager.issue_device_certificate_with_sans("bad device", vec!["bad.example".to_string()], Vec::new()).await.is_err());
+    }
+
+    /// 直接调用签发节点作答的回环查询通道
+    struct LoopbackStatusTransport {
+        issuer: Arc<CertificateManager>,
+    }
+
+    #[async_trait::async_trait]
+    impl CertStatusTransport for LoopbackStatusTransport {
+        async fn request_status(&self, _peer_id: &str, request: &CertStatusRequest) -> IdentityResult<CertStatusResponse> {
+            self.issuer.answer_status_query(request).await
+        }
+    }
+
+    /// 模拟签发节点不可达的查询通道
+    struct UnreachableStatusTransport;
+
+    #[async_trait::async_trait]
+    impl CertStatusTransport for UnreachableStatusTransport {
+        async fn request_status(&self, peer_id: &str, _request: &CertStatusRequest) -> IdentityResult<CertStatusResponse> {
+            Err(IdentityError::NetworkError(format!("无法连接到 {}", peer_id)).into())
+        }
+    }
+
+    async fn status_test_manager(temp_dir: &TempDir, ca_name: &str, ttl: Duration) -> CertificateManager {
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name(ca_name)
+            .with_status_cache_ttl(ttl)
+            .build()
+            .expect("配置创建失败");
+        CertificateManager::initialize(config).await.expect("证书管理器初始化失败")
+    }
+
+    #[tokio::test]
+    async fn test_query_cert_status_sees_revocation_over_loopback() {
+        let issuer_dir = TempDir::new().expect("无法创建临时目录");
+        let querier_dir = TempDir::new().expect("无法创建临时目录");
+        let issuer = Arc::new(status_test_manager(&issuer_dir, "Issuer CA", Duration::from_secs(30)).await);
+        let querier = status_test_manager(&querier_dir, "Querier CA", Duration::from_millis(500)).await;
+        querier.set_status_transport(Arc::new(LoopbackStatusTransport { issuer: issuer.clone() })).await;
+
+        // 查询方通过交叉证书信任签发节点的CA
+        let issuer_ca = issuer.get_certificate_authority().await.expect("获取CA失败");
+        let issuer_ca_der = pem::parse(&issuer_ca.certificate_data.certificate_pem).expect("解析CA证书PEM失败").into_contents();
+        querier.cross_sign(&issuer_ca_der).await.expect("交叉签名失败");
+
+        let certificate = issuer.issue_device_certificate("status-device").await.expect("证书签发失败");
+        let status = querier.query_cert_status("issuer", &certificate.fingerprint).await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Valid);
+
+        assert!(issuer.revoke_device_certificate("status-device").await.expect("吊销失败"));
+
+        // 缓存有效期内仍返回上次的回答
+        let status = querier.query_cert_status("issuer", &certificate.fingerprint).await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Valid);
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        let status = querier.query_cert_status("issuer", &certificate.fingerprint).await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Revoked);
+
+        let status = querier.query_cert_status("issuer", "no-such-fingerprint").await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Unknown);
+    }
+
+    /// 篡改签发节点回答的查询通道
+    struct TamperingStatusTransport {
+        issuer: Arc<CertificateManager>,
+        replay_nonce: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl CertStatusTransport for TamperingStatusTransport {
+        async fn request_status(&self, _peer_id: &str, request: &CertStatusRequest) -> IdentityResult<CertStatusResponse> {
+            if self.replay_nonce {
+                // 重放针对其他随机数的合法回答
+                let stale = CertStatusRequest { nonce: request.nonce.wrapping_add(1), ..request.clone() };
+                return self.issuer.answer_status_query(&stale).await;
+            }
+            let mut response = self.issuer.answer_status_query(request).await?;
+            response.status = CertificateStatus::Valid;
+            Ok(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_cert_status_rejects_untrusted_answers() {
+        let issuer_dir = TempDir::new().expect("无法创建临时目录");
+        let querier_dir = TempDir::new().expect("无法创建临时目录");
+        let issuer = Arc::new(status_test_manager(&issuer_dir, "Issuer CA", Duration::from_secs(30)).await);
+        let querier = status_test_manager(&querier_dir, "Querier CA", Duration::from_millis(1)).await;
+
+        let certificate = issuer.issue_device_certificate("tampered-device").await.expect("证书签发失败");
+        issuer.revoke_device_certificate("tampered-device").await.expect("吊销失败");
+
+        // 未交叉签名时不信任签发节点的签名，本地也不认识该证书
+        querier.set_status_transport(Arc::new(LoopbackStatusTransport { issuer: issuer.clone() })).await;
+        let status = querier.query_cert_status("issuer", &certificate.fingerprint).await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Unknown);
+
+        let issuer_ca = issuer.get_certificate_authority().await.expect("获取CA失败");
+        let issuer_ca_der = pem::parse(&issuer_ca.certificate_data.certificate_pem).expect("解析CA证书PEM失败").into_contents();
+        querier.cross_sign(&issuer_ca_der).await.expect("交叉签名失败");
+
+        // 被篡改的状态和重放的回答都无法通过校验
+        for replay_nonce in [false, true] {
+            querier.set_status_transport(Arc::new(TamperingStatusTransport { issuer: issuer.clone(), replay_nonce })).await;
+            let status = querier.query_cert_status("issuer", &certificate.fingerprint).await.expect("状态查询失败");
+            assert_eq!(status, CertificateStatus::Unknown);
+        }
+
+        querier.set_status_transport(Arc::new(LoopbackStatusTransport { issuer: issuer.clone() })).await;
+        let status = querier.query_cert_status("issuer", &certificate.fingerprint).await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Revoked);
+    }
+
+    #[tokio::test]
+    async fn test_query_cert_status_falls_back_to_local_crl() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let manager = status_test_manager(&temp_dir, "Test CA", Duration::from_secs(30)).await;
+        manager.set_status_transport(Arc::new(UnreachableStatusTransport)).await;
+
+        let certificate = manager.issue_device_certificate("offline-device").await.expect("证书签发失败");
+        manager.revoke_device_certificate("offline-device").await.expect("吊销失败");
+
+        let status = manager.query_cert_status("offline-issuer", &certificate.fingerprint).await.expect("状态查询失败");
+        assert_eq!(status, CertificateStatus::Revoked);
+    }
+
+    #[tokio::test]
+    async fn test_verify_tofu_pins_first_certificate() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let manager = status_test_manager(&temp_dir, "Test CA", Duration::from_secs(30)).await;
+        let der_of = |certificate: &CertificateData| {
+            pem::parse(&certificate.certificate_pem).expect("解析证书PEM失败").into_contents()
+        };
+        let original = der_of(&manager.issue_device_certificate("tofu-peer").await.expect("证书签发失败"));
+        let impostor = der_of(&manager.issue_device_certificate("tofu-impostor").await.expect("证书签发失败"));
+
+        assert_eq!(manager.verify_tofu("tofu-peer", &original).await.expect("TOFU验证失败"), TofuResult::FirstSeen);
+        assert_eq!(manager.verify_tofu("tofu-peer", &original).await.expect("TOFU验证失败"), TofuResult::Matched);
+
+        // 证书变更每次都作为严重的独立错误上报
+        for _ in 0..2 {
+            let err = manager.verify_tofu("tofu-peer", &impostor).await.expect_err("变更的证书不应被信任");
+            assert_eq!(err.code(), 6010);
+            assert_eq!(err.severity(), error::ErrorSeverity::Critical);
+        }
+
+        // 重启后固定记录仍然有效
+        drop(manager);
+        let manager = status_test_manager(&temp_dir, "Test CA", Duration::from_secs(30)).await;
+        assert_eq!(manager.verify_tofu("tofu-peer", &original).await.expect("TOFU验证失败"), TofuResult::Matched);
+        assert!(manager.verify_tofu("tofu-peer", &impostor).await.is_err());
+
+        // 确认变更合法后重新信任新证书
+        assert!(manager.forget_tofu_pin("tofu-peer").await.expect("移除固定记录失败"));
+        assert_eq!(manager.verify_tofu("tofu-peer", &impostor).await.expect("TOFU验证失败"), TofuResult::FirstSeen);
+        assert!(manager.verify_tofu("tofu-peer", &original).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_initialize_recovers_from_corrupt_tofu_pins() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let manager = status_test_manager(&temp_dir, "Test CA", Duration::from_secs(30)).await;
+        let certificate = manager.issue_device_certificate("tofu-peer").await.expect("证书签发失败");
+        let der = pem::parse(&certificate.certificate_pem).expect("解析证书PEM失败").into_contents();
+        drop(manager);
+
+        std::fs::write(temp_dir.path().join(TOFU_PINS_FILE), b"\0\0garbage").expect("写入文件失败");
+        let manager = status_test_manager(&temp_dir, "Test CA", Duration::from_secs(30)).await;
+        assert_eq!(manager.check_tofu("tofu-peer", &der).expect("TOFU检查失败"), TofuResult::FirstSeen);
+        manager.flush_tofu_pins().await.expect("保存固定记录失败");
+        assert_eq!(manager.verify_tofu("tofu-peer", &der).await.expect("TOFU验证失败"), TofuResult::Matched);
+    }
+
+    /// 在内存中完成一次TLS握手，返回客户端是否接受服务端证书
+    fn tls_handshake(ca_pem: &str, cert_pem: &str, key_pem: &str, server_name: ServerName<'static>) -> Result<(), rustls::Error> {
+        use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+
+        let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+        let ca_der = CertificateDer::from(pem::parse(ca_pem).expect("解析CA证书失败").into_contents());
+        let cert_der = CertificateDer::from(pem::parse(cert_pem).expect("解析设备证书失败").into_contents());
+        let key_der = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pem::parse(key_pem).expect("解析私钥失败").into_contents()));
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(ca_der).expect("添加CA证书失败");
+        let client_config = rustls::ClientConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .expect("创建客户端配置失败")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .expect("创建服务端配置失败")
+            .with_no_client_auth()
+            .with_single_cert(vec![cert_der], key_der)
+            .expect("设置服务端证书失败");
+
+        let mut client = rustls::ClientConnection::new(Arc::new(client_config), server_name).expect("创建客户端连接失败");
+        let mut server = rustls::ServerConnection::new(Arc::new(server_config)).expect("创建服务端连接失败");
+
+        while client.is_handshaking() || server.is_handshaking() {
+            let mut buffer = Vec::new();
+            client.write_tls(&mut buffer).expect("写入客户端数据失败");
+            server.read_tls(&mut buffer.as_slice()).expect("读取客户端数据失败");
+            server.process_new_packets()?;
+
+            let mut buffer = Vec::new();
+            server.write_tls(&mut buffer).expect("写入服务端数据失败");
+            client.read_tls(&mut buffer.as_slice()).expect("读取服务端数据失败");
+            client.process_new_packets()?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_device_certificate_with_ip_san() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("SAN Test CA")
+            .build()
+            .expect("配置创建失败");
+        let manager = CertificateManager::initialize(config).await.expect("证书管理器初始化失败");
+        let ca = manager.get_certificate_authority().await.expect("获取CA失败");
+
+        let ip: IpAddr = "192.168.1.20".parse().expect("解析IP失败");
+        let certificate = manager.issue_device_certificate_with_sans(
+            "san-device",
+            vec!["nas.home.arpa".to_string()],
+            vec![ip],
+        ).await.expect("签发证书失败");
+        let key_pem = certificate.private_key_pem.as_deref().expect("证书应包含私钥");
+
+        // 使用IP作为服务端名称连接
+        tls_handshake(&ca.certificate_data.certificate_pem, &certificate.certificate_pem, key_pem, ServerName::IpAddress(ip.into()))
+            .expect("使用证书中的IP连接应该成功");
+
+        // 证书中没有的IP被拒绝
+        let other: IpAddr = "192.168.1.21".parse().expect("解析IP失败");
+        assert!(tls_handshake(&ca.certificate_data.certificate_pem, &certificate.certificate_pem, key_pem, ServerName::IpAddress(other.into())).is_err(),
+            "使用证书中没有的IP连接应该失败");
+
+        let leaf_der = pem::parse(&certificate.certificate_pem).expect("解析证书失败").into_contents();
+        for name in ["192.168.1.20", "nas.home.arpa", "san-device.bey.local"] {
+            let result = manager.verify_chain_for_server_name(&leaf_der, &[], Some(name)).await.expect("验证证书链失败");
+            assert!(result.is_valid, "{} 应该匹配证书的主题备用名称: {:?}", name, result.error_message);
+        }
+        let result = manager.verify_chain_for_server_name(&leaf_der, &[], Some("192.168.1.21")).await.expect("验证证书链失败");
+        assert!(!result.is_valid, "不在主题备用名称中的IP应该验证失败");
+    }
+
+    #[tokio::test]
+    async fn test_sha384_signed_certificate() {
+        // P-256 密钥不能使用 SHA-384 签名
+        assert!(CertificateConfig::builder()
+            .with_signature_hash(SignatureHash::Sha384)
+            .build()
+            .is_err());
+
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("SHA-384 Test CA")
+            .with_key_size(384)
+            .with_signature_hash(SignatureHash::Sha384)
+            .build()
+            .expect("配置创建失败");
+        let manager = CertificateManager::initialize(config).await.expect("证书管理器初始化失败");
+        let ca = manager.get_certificate_authority().await.expect("获取CA失败");
+        assert_eq!(ca.certificate_data.signature_hash, Some(SignatureHash::Sha384));
+
+        let certificate = manager.issue_device_certificate("sha384-device").await.expect("签发证书失败");
+        assert_eq!(certificate.signature_hash, Some(SignatureHash::Sha384));
+
+        let leaf_der = pem::parse(&certificate.certificate_pem).expect("解析证书失败").into_contents();
+        let (_, x509) = x509_parser::parse_x509_certificate(&leaf_der).expect("解析X.509证书失败");
+        assert_eq!(x509.signature_algorithm.algorithm.to_id_string(), "1.2.840.10045.4.3.3", "应使用ecdsa-with-SHA384签名");
+
+        let result = manager.verify_certificate(&certificate).await.expect("验证证书失败");
+        assert!(result.is_valid, "SHA-384签名的证书应该验证通过: {:?}", result.error_message);
+        let result = manager.verify_chain(&leaf_der, &[]).await.expect("验证证书链失败");
+        assert!(result.is_valid, "SHA-384签名的证书链应该验证通过: {:?}", result.error_message);
+    }
+
+    #[tokio::test]
+    async fn test_issuance_log_hash_chain() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Issuance Log Test CA")
+            .build()
+            .expect("配置创建失败");
+        let manager = CertificateManager::initialize(config).await.expect("证书管理器初始化失败");
+
+        let device_ids = vec!["log-device-1".to_string(), "log-device-2".to_string(), "log-device-3".to_string()];
+        manager.issue_device_certificates(&device_ids).await.expect("批量签发失败");
+        assert!(manager.revoke_device_certificate("log-device-2").await.expect("吊销证书失败"));
+
+        // CA证书本身也记录在日志中
+        let entries = manager.issuance_log().await.expect("读取签发日志失败");
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[0].prev_hash, ISSUANCE_LOG_GENESIS_HASH);
+        assert_eq!(entries[0].certificate_id, "ca");
+        assert_eq!(entries[4].action, IssuanceAction::Revoked);
+        assert_eq!(entries[4].device_identifier, "log-device-2");
+        assert!(manager.verify_issuance_log().await.expect("校验签发日志失败"));
+
+        // 截断日志后哈希链仍然完整，但与签名的链头不一致
+        let log_path = temp_dir.path().join("issuance_log.jsonl");
+        let content = std::fs::read_to_string(&log_path).expect("读取日志文件失败");
+        let truncated: String = content.lines().take(4).map(|line| format!("{}\n", line)).collect();
+        std::fs::write(&log_path, truncated).expect("写入日志文件失败");
+        assert!(!manager.verify_issuance_log().await.expect("校验签发日志失败"));
+
+        // 篡改一条记录后校验失败
+        std::fs::write(&log_path, content.replacen("log-device-1", "forged-device", 1)).expect("写入日志文件失败");
+        assert!(!manager.verify_issuance_log().await.expect("校验签发日志失败"));
+
+        // 恢复原日志后，重新打开的管理器从日志末尾继续追加
+        std::fs::write(&log_path, &content).expect("写入日志文件失败");
+        drop(manager);
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Issuance Log Test CA")
+            .build()
+            .expect("配置创建失败");
+        let manager = CertificateManager::initialize(config).await.expect("证书管理器初始化失败");
+        manager.issue_device_certificate("log-device-4").await.expect("签发证书失败");
+        let entries = manager.issuance_log().await.expect("读取签发日志失败");
+        assert_eq!(entries.len(), 6);
+        assert!(manager.verify_issuance_log().await.expect("校验签发日志失败"));
+    }
+
+    /// 使用指定私钥存储签发设备证书，重新初始化管理器后读回
+    async fn issue_with_key_store(temp_dir: &TempDir, key_store: Arc<dyn KeyStore>) -> (CertificateData, CertificateData) {
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Key Store Test CA")
+            .build()
+            .expect("配置创建失败");
+
+        let manager = CertificateManager::initialize_with_key_store(config.clone(), Arc::clone(&key_store)).await
+            .expect("证书管理器初始化失败");
+        let issued = manager.issue_device_certificate("keystore-device").await.expect("设备证书签发失败");
+        let verification = manager.verify_certificate(&issued).await.expect("证书验证失败");
+        assert!(verification.is_valid, "证书应该验证通过");
+
+        // 证书文件中不含私钥
+        let on_disk = manager.storage.retrieve_certificate("keystore-device").await
+            .expect("读取证书失败")
+            .expect("证书应该存在");
+        assert!(on_disk.private_key_pem.is_none(), "证书存储中不应保存私钥");
+
+        // 重新初始化后沿用同一CA，并从私钥存储读回私钥
+        let reloaded_manager = CertificateManager::initialize_with_key_store(config, key_store).await
+            .expect("证书管理器重新初始化失败");
+        let reloaded = reloaded_manager.get_device_certificate("keystore-device").await
+            .expect("获取证书失败")
+            .expect("证书应该存在");
+        let verification = reloaded_manager.verify_certificate(&reloaded).await.expect("证书验证失败");
+        assert!(verification.is_valid, "重新加载的证书应该验证通过");
+
+        (issued, reloaded)
+    }
+
+    #[tokio::test]
+    async fn test_issuance_with_memory_and_file_key_stores() {
+        let memory_dir = TempDir::new().expect("无法创建临时目录");
+        let (memory_issued, memory_reloaded) = issue_with_key_store(&memory_dir, Arc::new(crate::keystore::MemoryKeyStore::default())).await;
+
+        let file_dir = TempDir::new().expect("无法创建临时目录");
+        let (file_issued, file_reloaded) = issue_with_key_store(&file_dir, Arc::new(crate::keystore::FileKeyStore::new(file_dir.path()))).await;
+        assert!(file_dir.path().join("keystore-device.key").exists());
+        assert!(file_dir.path().join("ca.key").exists());
+        assert!(!memory_dir.path().join("keystore-device.key").exists());
+
+        for (issued, reloaded) in [(&memory_issued, &memory_reloaded), (&file_issued, &file_reloaded)] {
+            assert_eq!(reloaded.certificate_pem, issued.certificate_pem);
+            assert_eq!(reloaded.private_key_pem, issued.private_key_pem);
+
+            // 私钥与证书匹配，可以签名并用证书验证
+            let key_pem = reloaded.private_key_pem.as_deref().expect("证书应包含私钥");
+            let signature = crate::signing::sign_data(key_pem, b"key store").expect("签名失败");
+            assert!(crate::signing::verify_signature(&reloaded.certificate_pem, b"key store", &signature).expect("验签失败"));
+        }
+
+        // 两种私钥存储签发的证书除密钥外一致
+        assert_eq!(memory_issued.subject_identifier, file_issued.subject_identifier);
+        assert_eq!(memory_issued.issuer_identifier, file_issued.issuer_identifier);
+        assert_eq!(memory_issued.certificate_type, file_issued.certificate_type);
+        assert_eq!(memory_issued.key_algorithm, file_issued.key_algorithm);
+        assert_eq!(memory_issued.signature_hash, file_issued.signature_hash);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_and_revoke_delete_private_key() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .with_ca_common_name("Key Store Test CA")
+            .build()
+            .expect("配置创建失败");
+        let key_store: Arc<dyn KeyStore> = Arc::new(crate::keystore::FileKeyStore::new(temp_dir.path()));
+        let manager = CertificateManager::initialize_with_key_store(config, Arc::clone(&key_store)).await
+            .expect("证书管理器初始化失败");
+
+        // 重新签发时旧私钥被新私钥替换
+        let issued = manager.issue_device_certificate("rotate-device").await.expect("设备证书签发失败");
+        let rotated = manager.issue_device_certificate_with_sans("rotate-device", vec!["rotate.bey.local".to_string()], Vec::new()).await
+            .expect("重新签发失败");
+        assert_ne!(rotated.private_key_pem, issued.private_key_pem);
+        assert_eq!(key_store.load_key("rotate-device").await.expect("读取私钥失败"), rotated.private_key_pem);
+
+        // 吊销后私钥被删除
+        assert!(manager.revoke_device_certificate("rotate-device").await.expect("吊销失败"));
+        assert_eq!(key_store.load_key("rotate-device").await.expect("读取私钥失败"), None);
+        assert!(!temp_dir.path().join("rotate-device.key").exists());
+        let revoked = manager.get_device_certificate("rotate-device").await
+            .expect("获取证书失败")
+            .expect("证书应该存在");
+        assert!(revoked.private_key_pem.is_none(), "吊销的证书不应带有私钥");
+    }
+}
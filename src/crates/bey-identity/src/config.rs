@@ -3,6 +3,8 @@
 //! 提供证书管理系统的配置功能，包括证书策略、安全参数、存储配置等。
 //! 支持构建器模式的配置创建和验证。
 
+use crate::keystore::KeyStoreBackend;
+use crate::types::SignatureHash;
 use error::ErrorInfo;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -45,14 +47,17 @@ pub struct CertificateConfigBuilder {
     validity_days: u32,
     key_size: u32,
     key_algorithm: String,
+    signature_hash: Option<SignatureHash>,
     storage_directory: PathBuf,
     ca_common_name: String,
     organization_name: String,
     country_code: String,
     enable_crl: bool,
     crl_update_interval: Duration,
+    status_cache_ttl: Duration,
     max_certificate_chain_length: u8,
     enforce_strict_validation: bool,
+    key_store: KeyStoreBackend,
 }
 
 impl Default for CertificateConfigBuilder {
@@ -61,14 +66,17 @@ impl Default for CertificateConfigBuilder {
             validity_days: 365,
             key_size: 256,
             key_algorithm: "ECDSA".to_string(),
+            signature_hash: None,
             storage_directory: PathBuf::from("./certificates"),
             ca_common_name: "BEY Internal CA".to_string(),
             organization_name: "BEY".to_string(),
             country_code: "CN".to_string(),
             enable_crl: true,
             crl_update_interval: Duration::from_secs(86400), // 1天
+            status_cache_ttl: crate::status::DEFAULT_STATUS_CACHE_TTL,
             max_certificate_chain_length: 5,
             enforce_strict_validation: true,
+            key_store: KeyStoreBackend::default(),
         }
     }
 }
@@ -97,6 +105,14 @@ impl CertificateConfigBuilder {
         self
     }
 
+    /// 设置签名哈希算法，CA证书和设备证书都使用该哈希签名
+    ///
+    /// ECDSA的签名哈希由曲线决定：P-256 只支持 SHA-256，P-384 只支持 SHA-384
+    pub fn with_signature_hash(mut self, hash: SignatureHash) -> Self {
+        self.signature_hash = Some(hash);
+        self
+    }
+
     /// 设置证书存储目录
     pub fn with_storage_directory<P: AsRef<Path>>(mut self, directory: P) -> Self {
         self.storage_directory = directory.as_ref().to_path_buf();
@@ -133,6 +149,12 @@ impl CertificateConfigBuilder {
         self
     }
 
+    /// 设置在线状态查询结果的缓存时间
+    pub fn with_status_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.status_cache_ttl = ttl;
+        self
+    }
+
     /// 设置最大证书链长度
     pub fn with_max_chain_length(mut self, length: u8) -> Self {
         self.max_certificate_chain_length = length;
@@ -145,6 +167,12 @@ impl CertificateConfigBuilder {
         self
     }
 
+    /// 设置私钥存储后端
+    pub fn with_key_store(mut self, backend: KeyStoreBackend) -> Self {
+        self.key_store = backend;
+        self
+    }
+
     /// 构建并验证配置
     pub fn build(self) -> Result<CertificateConfig, ConfigError> {
         self.validate()?;
@@ -152,14 +180,17 @@ impl CertificateConfigBuilder {
             validity_days: self.validity_days,
             key_size: self.key_size,
             key_algorithm: self.key_algorithm,
+            signature_hash: self.signature_hash,
             storage_directory: self.storage_directory,
             ca_common_name: self.ca_common_name,
             organization_name: self.organization_name,
             country_code: self.country_code,
             enable_crl: self.enable_crl,
             crl_update_interval: self.crl_update_interval,
+            status_cache_ttl: self.status_cache_ttl,
             max_certificate_chain_length: self.max_certificate_chain_length,
             enforce_strict_validation: self.enforce_strict_validation,
+            key_store: self.key_store,
         })
     }
 
@@ -170,6 +201,15 @@ impl CertificateConfigBuilder {
             return Err(ConfigError::InvalidKeySize(self.key_size));
         }
 
+        // 验证签名哈希与密钥算法匹配
+        if let Some(hash) = self.signature_hash {
+            if signature_algorithm(&self.key_algorithm, self.key_size, Some(hash)).is_none() {
+                return Err(ConfigError::ValidationFailed(format!(
+                    "{}-{} 不支持 {} 签名", self.key_algorithm, self.key_size, hash
+                )));
+            }
+        }
+
         // 验证有效期
         if self.validity_days < 1 || self.validity_days > 3650 {
             return Err(ConfigError::InvalidValidityPeriod(self.validity_days));
@@ -218,6 +258,10 @@ pub struct CertificateConfig {
     /// 密钥算法
     pub key_algorithm: String,
 
+    /// 签名哈希算法，`None` 表示使用密钥算法的默认哈希
+    #[serde(default)]
+    pub signature_hash: Option<SignatureHash>,
+
     /// 证书存储目录
     pub storage_directory: PathBuf,
 
@@ -236,11 +280,18 @@ pub struct CertificateConfig {
     /// CRL更新间隔
     pub crl_update_interval: Duration,
 
+    /// 在线状态查询结果的缓存时间
+    pub status_cache_ttl: Duration,
+
     /// 最大证书链长度
     pub max_certificate_chain_length: u8,
 
     /// 是否启用严格验证
     pub enforce_strict_validation: bool,
+
+    /// 私钥存储后端
+    #[serde(default)]
+    pub key_store: KeyStoreBackend,
 }
 
 impl CertificateConfig {
@@ -256,6 +307,24 @@ impl CertificateConfig {
             .expect("默认配置应该有效")
     }
 
+    /// 设置签名哈希算法，CA证书和设备证书都使用该哈希签名
+    ///
+    /// 只修改已构建的配置，不重新验证；通过构建器设置时会检查哈希与密钥算法是否匹配
+    pub fn with_signature_hash(mut self, hash: SignatureHash) -> Self {
+        self.signature_hash = Some(hash);
+        self
+    }
+
+    /// 获取签发证书使用的签名算法
+    ///
+    /// # 返回值
+    ///
+    /// 返回与密钥算法、密钥长度和签名哈希对应的rcgen签名算法，
+    /// 组合不受支持时返回 `None`
+    pub fn signature_algorithm(&self) -> Option<&'static rcgen::SignatureAlgorithm> {
+        signature_algorithm(&self.key_algorithm, self.key_size, self.signature_hash)
+    }
+
     /// 获取CA证书有效期（通常比设备证书长）
     pub fn ca_validity_days(&self) -> u32 {
         self.validity_days * 10 // CA证书有效期是设备证书的10倍
@@ -344,6 +413,28 @@ impl CertificateConfig {
     }
 }
 
+/// 根据密钥算法、密钥长度和签名哈希选择rcgen签名算法
+///
+/// 未指定签名哈希时RSA按密钥长度选择哈希（2048→SHA-256，3072→SHA-384，4096→SHA-512），
+/// ECDSA使用曲线对应的哈希；Ed25519不单独指定哈希
+fn signature_algorithm(
+    key_algorithm: &str,
+    key_size: u32,
+    hash: Option<SignatureHash>,
+) -> Option<&'static rcgen::SignatureAlgorithm> {
+    use rcgen::{PKCS_ECDSA_P256_SHA256, PKCS_ECDSA_P384_SHA384, PKCS_ED25519, PKCS_RSA_SHA256, PKCS_RSA_SHA384, PKCS_RSA_SHA512};
+
+    match (key_algorithm, key_size, hash) {
+        ("RSA", _, Some(SignatureHash::Sha256)) | ("RSA", 2048, None) => Some(&PKCS_RSA_SHA256),
+        ("RSA", _, Some(SignatureHash::Sha384)) | ("RSA", 3072, None) => Some(&PKCS_RSA_SHA384),
+        ("RSA", _, Some(SignatureHash::Sha512)) | ("RSA", 4096, None) => Some(&PKCS_RSA_SHA512),
+        ("ECDSA", 256, None | Some(SignatureHash::Sha256)) => Some(&PKCS_ECDSA_P256_SHA256),
+        ("ECDSA", 384, None | Some(SignatureHash::Sha384)) => Some(&PKCS_ECDSA_P384_SHA384),
+        ("EdDSA", 255, None) => Some(&PKCS_ED25519),
+        _ => None,
+    }
+}
+
 /// 证书策略配置
 ///
 /// 定义证书签发和管理的策略规则。
@@ -0,0 +1,162 @@
+//! # 端到端加密
+//!
+//! 使用接收方设备证书中的公钥加密数据，只有持有对应私钥的设备才能解密。
+//! 采用 ECIES 方案：临时 ECDH(P-256) 密钥协商，HKDF-SHA256 派生密钥，AES-256-GCM 加密。
+//!
+//! 密文格式：版本(1字节) + 临时公钥(65字节，未压缩SEC1) + 随机数(12字节) + 密文和认证标签
+
+use crate::error::IdentityError;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use hkdf::Hkdf;
+use p256::ecdh::EphemeralSecret;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::pkcs8::DecodePrivateKey;
+use p256::{PublicKey, SecretKey};
+use sha2::Sha256;
+use tracing::debug;
+
+/// 密文格式版本
+const ENVELOPE_VERSION: u8 = 1;
+
+/// 未压缩SEC1公钥长度
+const PUBLIC_KEY_LEN: usize = 65;
+
+/// AES-GCM 随机数长度
+const NONCE_LEN: usize = 12;
+
+/// 密钥派生上下文
+const HKDF_INFO: &[u8] = b"bey-e2e-v1";
+
+/// 使用证书公钥加密数据
+///
+/// # 参数
+///
+/// * `certificate_pem` - 接收方证书（PEM格式，须为ECDSA P-256密钥）
+/// * `plaintext` - 明文
+///
+/// # 返回值
+///
+/// 返回密文或错误
+pub fn encrypt_for_certificate(certificate_pem: &str, plaintext: &[u8]) -> Result<Vec<u8>, IdentityError> {
+    let recipient = certificate_public_key(certificate_pem)?;
+
+    let ephemeral = EphemeralSecret::random(&mut OsRng);
+    let ephemeral_public = ephemeral.public_key().to_encoded_point(false);
+    let shared_secret = ephemeral.diffie_hellman(&recipient);
+
+    let cipher = derive_cipher(shared_secret.raw_secret_bytes(), ephemeral_public.as_bytes(), &recipient)?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plaintext)
+        .map_err(|e| IdentityError::CryptoError(format!("加密失败: {}", e)))?;
+
+    let mut envelope = Vec::with_capacity(1 + PUBLIC_KEY_LEN + NONCE_LEN + ciphertext.len());
+    envelope.push(ENVELOPE_VERSION);
+    envelope.extend_from_slice(ephemeral_public.as_bytes());
+    envelope.extend_from_slice(&nonce);
+    envelope.extend_from_slice(&ciphertext);
+
+    debug!("端到端加密完成: {} 字节明文, {} 字节密文", plaintext.len(), envelope.len());
+    Ok(envelope)
+}
+
+/// 使用设备私钥解密数据
+///
+/// # 参数
+///
+/// * `private_key_pem` - 接收方私钥（PKCS#8 PEM格式）
+/// * `envelope` - 由 [`encrypt_for_certificate`] 生成的密文
+///
+/// # 返回值
+///
+/// 返回明文或错误；私钥不匹配或密文被篡改时返回错误
+pub fn decrypt_with_private_key(private_key_pem: &str, envelope: &[u8]) -> Result<Vec<u8>, IdentityError> {
+    if envelope.len() < 1 + PUBLIC_KEY_LEN + NONCE_LEN || envelope[0] != ENVELOPE_VERSION {
+        return Err(IdentityError::CryptoError("密文格式无效".to_string()));
+    }
+
+    let secret_key = SecretKey::from_pkcs8_pem(private_key_pem)
+        .map_err(|e| IdentityError::CryptoError(format!("解析私钥失败（仅支持ECDSA P-256）: {}", e)))?;
+
+    let ephemeral_bytes = &envelope[1..1 + PUBLIC_KEY_LEN];
+    let nonce_bytes = &envelope[1 + PUBLIC_KEY_LEN..1 + PUBLIC_KEY_LEN + NONCE_LEN];
+    let ciphertext = &envelope[1 + PUBLIC_KEY_LEN + NONCE_LEN..];
+
+    let ephemeral_public = PublicKey::from_sec1_bytes(ephemeral_bytes)
+        .map_err(|e| IdentityError::CryptoError(format!("解析临时公钥失败: {}", e)))?;
+    let shared_secret = p256::ecdh::diffie_hellman(secret_key.to_nonzero_scalar(), ephemeral_public.as_affine());
+
+    let cipher = derive_cipher(shared_secret.raw_secret_bytes(), ephemeral_bytes, &secret_key.public_key())?;
+    let nonce: [u8; NONCE_LEN] = nonce_bytes.try_into()
+        .map_err(|_| IdentityError::CryptoError("解密失败：随机数长度无效".to_string()))?;
+    let plaintext = cipher.decrypt(&nonce.into(), ciphertext)
+        .map_err(|_| IdentityError::CryptoError("解密失败：私钥不匹配或密文已被篡改".to_string()))?;
+
+    debug!("端到端解密完成: {} 字节明文", plaintext.len());
+    Ok(plaintext)
+}
+
+/// 从证书中提取P-256公钥
+fn certificate_public_key(certificate_pem: &str) -> Result<PublicKey, IdentityError> {
+    let certificate = pem::parse(certificate_pem)
+        .map_err(|e| IdentityError::ValidationError(format!("解析证书PEM失败: {}", e)))?;
+
+    let (_, x509) = x509_parser::parse_x509_certificate(certificate.contents())
+        .map_err(|e| IdentityError::ValidationError(format!("解析证书失败: {}", e)))?;
+
+    PublicKey::from_sec1_bytes(&x509.public_key().subject_public_key.data)
+        .map_err(|e| IdentityError::CryptoError(format!("证书公钥不是ECDSA P-256密钥: {}", e)))
+}
+
+/// 从共享密钥派生 AES-256-GCM 加密器
+///
+/// 盐值绑定临时公钥和接收方公钥，防止密文被挪用到其他接收方
+fn derive_cipher(shared_secret: &[u8], ephemeral_public: &[u8], recipient: &PublicKey) -> Result<Aes256Gcm, IdentityError> {
+    let mut salt = Vec::with_capacity(PUBLIC_KEY_LEN * 2);
+    salt.extend_from_slice(ephemeral_public);
+    salt.extend_from_slice(recipient.to_encoded_point(false).as_bytes());
+
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret)
+        .expand(HKDF_INFO, &mut key)
+        .map_err(|e| IdentityError::CryptoError(format!("派生密钥失败: {}", e)))?;
+
+    Aes256Gcm::new_from_slice(&key)
+        .map_err(|e| IdentityError::CryptoError(format!("创建加密器失败: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::CertificateManager;
+    use crate::config::CertificateConfig;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_encrypt_and_decrypt() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .build()
+            .unwrap();
+        let manager = CertificateManager::initialize(config).await.unwrap();
+
+        let recipient = manager.issue_device_certificate("recipient").await.unwrap();
+        let other = manager.issue_device_certificate("other").await.unwrap();
+
+        let envelope = encrypt_for_certificate(&recipient.certificate_pem, b"secret").unwrap();
+        assert!(!envelope.windows(6).any(|window| window == b"secret"));
+
+        let plaintext = decrypt_with_private_key(recipient.private_key_pem.as_deref().unwrap(), &envelope).unwrap();
+        assert_eq!(plaintext, b"secret");
+
+        // 其他设备的私钥无法解密
+        assert!(decrypt_with_private_key(other.private_key_pem.as_deref().unwrap(), &envelope).is_err());
+
+        // 篡改密文后解密失败
+        let mut tampered = envelope.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xff;
+        assert!(decrypt_with_private_key(recipient.private_key_pem.as_deref().unwrap(), &tampered).is_err());
+    }
+}
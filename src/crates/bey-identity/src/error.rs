@@ -37,6 +37,9 @@ pub enum IdentityError {
     /// 权限相关错误
     PermissionError(String),
 
+    /// 设备出示的证书与首次使用时固定的证书不一致
+    CertificatePinMismatch(String),
+
     /// 未知错误
     Unknown(String),
 }
@@ -53,6 +56,7 @@ impl fmt::Display for IdentityError {
             IdentityError::NetworkError(msg) => write!(f, "网络错误: {}", msg),
             IdentityError::TimeError(msg) => write!(f, "时间错误: {}", msg),
             IdentityError::PermissionError(msg) => write!(f, "权限错误: {}", msg),
+            IdentityError::CertificatePinMismatch(msg) => write!(f, "证书固定不匹配: {}", msg),
             IdentityError::Unknown(msg) => write!(f, "未知错误: {}", msg),
         }
     }
@@ -113,6 +117,7 @@ impl From<IdentityError> for ErrorInfo {
             IdentityError::NetworkError(_) => (6007, ErrorCategory::Network, ErrorSeverity::Error),
             IdentityError::TimeError(_) => (6008, ErrorCategory::System, ErrorSeverity::Error),
             IdentityError::PermissionError(_) => (6009, ErrorCategory::Permission, ErrorSeverity::Error),
+            IdentityError::CertificatePinMismatch(_) => (6010, ErrorCategory::Authentication, ErrorSeverity::Critical),
             IdentityError::Unknown(_) => (6100, ErrorCategory::Other, ErrorSeverity::Error),
         };
 
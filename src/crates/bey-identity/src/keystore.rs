@@ -0,0 +1,304 @@
+//! # 私钥存储
+//!
+//! 证书管理器通过 [`KeyStore`] 保存证书私钥，证书本身仍由 `CertificateStorage` 保存且不含私钥。
+//! 内置文件系统和内存实现，启用 `keyring` 特性后可使用操作系统密钥环；
+//! 需要硬件安全模块时实现 [`KeyStore`] 并通过 `CertificateManager::initialize_with_key_store` 传入。
+
+use crate::error::IdentityError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// 私钥存储
+#[async_trait]
+pub trait KeyStore: Send + Sync {
+    /// 保存私钥，已存在时覆盖
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - 密钥ID（与证书ID相同）
+    /// * `private_key_pem` - 私钥（PEM格式）
+    async fn store_key(&self, key_id: &str, private_key_pem: &str) -> Result<(), IdentityError>;
+
+    /// 读取私钥
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - 密钥ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回私钥（PEM格式），不存在时返回 `None`
+    async fn load_key(&self, key_id: &str) -> Result<Option<String>, IdentityError>;
+
+    /// 删除私钥
+    ///
+    /// # 参数
+    ///
+    /// * `key_id` - 密钥ID
+    ///
+    /// # 返回值
+    ///
+    /// 私钥存在并已删除时返回 `true`
+    async fn delete_key(&self, key_id: &str) -> Result<bool, IdentityError>;
+}
+
+/// 私钥存储后端配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyStoreBackend {
+    /// 证书存储目录下的 `<密钥ID>.key` 文件
+    #[default]
+    File,
+    /// 进程内存，重启后丢失，仅用于测试
+    Memory,
+    /// 操作系统密钥环（需要启用 `keyring` 特性）
+    Keyring {
+        /// 密钥环服务名称
+        service: String,
+    },
+}
+
+impl KeyStoreBackend {
+    /// 创建对应的私钥存储
+    ///
+    /// # 参数
+    ///
+    /// * `storage_directory` - 证书存储目录，文件系统后端在此保存私钥
+    ///
+    /// # 返回值
+    ///
+    /// 返回私钥存储，未启用 `keyring` 特性时选择密钥环后端返回错误
+    pub fn create(&self, storage_directory: &Path) -> Result<Arc<dyn KeyStore>, IdentityError> {
+        match self {
+            KeyStoreBackend::File => Ok(Arc::new(FileKeyStore::new(storage_directory))),
+            KeyStoreBackend::Memory => Ok(Arc::new(MemoryKeyStore::default())),
+            #[cfg(feature = "keyring")]
+            KeyStoreBackend::Keyring { service } => Ok(Arc::new(KeyringKeyStore::new(service.clone()))),
+            #[cfg(not(feature = "keyring"))]
+            KeyStoreBackend::Keyring { .. } => Err(IdentityError::Config(crate::error::ConfigError::ValidationFailed(
+                "密钥环私钥存储需要启用 keyring 特性".to_string()
+            ))),
+        }
+    }
+}
+
+/// 检查密钥ID能否安全地用作文件名或密钥环条目名
+fn validate_key_id(key_id: &str) -> Result<(), IdentityError> {
+    if key_id.is_empty()
+        || key_id.starts_with('.')
+        || !key_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(IdentityError::ValidationError(format!("密钥ID无效: {:?}", key_id)));
+    }
+    Ok(())
+}
+
+/// 文件系统私钥存储
+///
+/// 每个私钥保存为一个 `<密钥ID>.key` 文件，Unix系统上权限为 0600。
+/// 私钥先写入以 0600 权限创建的临时文件再重命名，文件在任何时刻都不会以更宽的权限存在
+pub struct FileKeyStore {
+    /// 私钥目录
+    directory: PathBuf,
+}
+
+impl FileKeyStore {
+    /// 创建文件系统私钥存储
+    ///
+    /// # 参数
+    ///
+    /// * `directory` - 私钥目录
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// 私钥文件路径
+    fn key_path(&self, key_id: &str) -> PathBuf {
+        self.directory.join(format!("{}.key", key_id))
+    }
+}
+
+#[async_trait]
+impl KeyStore for FileKeyStore {
+    async fn store_key(&self, key_id: &str, private_key_pem: &str) -> Result<(), IdentityError> {
+        validate_key_id(key_id)?;
+        tokio::fs::create_dir_all(&self.directory).await
+            .map_err(|e| IdentityError::StorageError(format!("创建私钥目录失败: {}", e)))?;
+
+        let path = self.key_path(key_id);
+        let temp_path = self.directory.join(format!("{}.key.tmp", key_id));
+        let write = async {
+            let mut options = tokio::fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+            let mut file = options.open(&temp_path).await?;
+            // 临时文件可能是之前遗留的，重新收紧权限
+            #[cfg(unix)]
+            file.set_permissions(std::fs::Permissions::from_mode(0o600)).await?;
+            file.write_all(private_key_pem.as_bytes()).await?;
+            file.sync_all().await?;
+            drop(file);
+            tokio::fs::rename(&temp_path, &path).await
+        };
+        if let Err(e) = write.await {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(IdentityError::StorageError(format!("写入私钥文件失败: {}", e)));
+        }
+
+        debug!("私钥已写入文件: {}", key_id);
+        Ok(())
+    }
+
+    async fn load_key(&self, key_id: &str) -> Result<Option<String>, IdentityError> {
+        validate_key_id(key_id)?;
+        match tokio::fs::read_to_string(self.key_path(key_id)).await {
+            Ok(private_key_pem) => Ok(Some(private_key_pem)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(IdentityError::StorageError(format!("读取私钥文件失败: {}", e))),
+        }
+    }
+
+    async fn delete_key(&self, key_id: &str) -> Result<bool, IdentityError> {
+        validate_key_id(key_id)?;
+        match tokio::fs::remove_file(self.key_path(key_id)).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(IdentityError::StorageError(format!("删除私钥文件失败: {}", e))),
+        }
+    }
+}
+
+/// 内存私钥存储
+#[derive(Default)]
+pub struct MemoryKeyStore {
+    /// 密钥ID -> 私钥
+    keys: RwLock<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl KeyStore for MemoryKeyStore {
+    async fn store_key(&self, key_id: &str, private_key_pem: &str) -> Result<(), IdentityError> {
+        self.keys.write().await.insert(key_id.to_string(), private_key_pem.to_string());
+        Ok(())
+    }
+
+    async fn load_key(&self, key_id: &str) -> Result<Option<String>, IdentityError> {
+        Ok(self.keys.read().await.get(key_id).cloned())
+    }
+
+    async fn delete_key(&self, key_id: &str) -> Result<bool, IdentityError> {
+        Ok(self.keys.write().await.remove(key_id).is_some())
+    }
+}
+
+/// 操作系统密钥环私钥存储
+///
+/// 每个私钥保存为服务名称下以密钥ID为用户名的条目
+#[cfg(feature = "keyring")]
+pub struct KeyringKeyStore {
+    /// 密钥环服务名称
+    service: String,
+}
+
+#[cfg(feature = "keyring")]
+impl KeyringKeyStore {
+    /// 创建密钥环私钥存储
+    ///
+    /// # 参数
+    ///
+    /// * `service` - 密钥环服务名称
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    /// 在阻塞线程中操作密钥环条目
+    async fn with_entry<T, F>(&self, key_id: &str, operation: F) -> Result<T, IdentityError>
+    where
+        T: Send + 'static,
+        F: FnOnce(keyring::Entry) -> Result<T, keyring::Error> + Send + 'static,
+    {
+        validate_key_id(key_id)?;
+        let service = self.service.clone();
+        let key_id = key_id.to_string();
+        tokio::task::spawn_blocking(move || {
+            let entry = keyring::Entry::new(&service, &key_id)?;
+            operation(entry)
+        }).await
+            .map_err(|e| IdentityError::Unknown(format!("密钥环任务失败: {}", e)))?
+            .map_err(|e| IdentityError::StorageError(format!("访问密钥环失败: {}", e)))
+    }
+}
+
+#[cfg(feature = "keyring")]
+#[async_trait]
+impl KeyStore for KeyringKeyStore {
+    async fn store_key(&self, key_id: &str, private_key_pem: &str) -> Result<(), IdentityError> {
+        let private_key_pem = private_key_pem.to_string();
+        self.with_entry(key_id, move |entry| entry.set_password(&private_key_pem)).await?;
+        debug!("私钥已写入密钥环: {}", key_id);
+        Ok(())
+    }
+
+    async fn load_key(&self, key_id: &str) -> Result<Option<String>, IdentityError> {
+        self.with_entry(key_id, |entry| match entry.get_password() {
+            Ok(private_key_pem) => Ok(Some(private_key_pem)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e),
+        }).await
+    }
+
+    async fn delete_key(&self, key_id: &str) -> Result<bool, IdentityError> {
+        self.with_entry(key_id, |entry| match entry.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(e),
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_file_key_store_round_trip() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let store = FileKeyStore::new(temp_dir.path());
+
+        assert_eq!(store.load_key("device-1").await.expect("读取私钥失败"), None);
+
+        // 覆盖权限过宽的旧文件后权限收紧为 0600
+        std::fs::write(temp_dir.path().join("device-1.key"), "OLD KEY").expect("写入文件失败");
+        #[cfg(unix)]
+        std::fs::set_permissions(temp_dir.path().join("device-1.key"), std::fs::Permissions::from_mode(0o644))
+            .expect("设置权限失败");
+        store.store_key("device-1", "PRIVATE KEY").await.expect("保存私钥失败");
+        assert_eq!(store.load_key("device-1").await.expect("读取私钥失败").as_deref(), Some("PRIVATE KEY"));
+
+        #[cfg(unix)]
+        {
+            let mode = std::fs::metadata(temp_dir.path().join("device-1.key")).expect("读取元数据失败").permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(!temp_dir.path().join("device-1.key.tmp").exists(), "不应遗留临时文件");
+
+        assert!(store.delete_key("device-1").await.expect("删除私钥失败"));
+        assert!(!store.delete_key("device-1").await.expect("删除私钥失败"));
+
+        // 不能逃出私钥目录
+        assert!(store.store_key("../escape", "PRIVATE KEY").await.is_err());
+    }
+}
@@ -16,8 +16,9 @@
 //! - **证书颁发机构（CA）管理**: 自建根CA，支持证书签发和吊销
 //! - **设备证书管理**: 自动生成设备身份证书，支持密钥轮换
 //! - **证书验证链**: 完整的X.509证书路径验证
-//! - **安全存储**: 证书和私钥的加密存储和访问控制
+//! - **安全存储**: 证书和私钥的加密存储和访问控制，私钥可保存在文件、操作系统密钥环或自定义的密钥存储中
 //! - **证书吊销列表（CRL）**: 支持证书状态查询和批量吊销
+//! - **首次使用信任（TOFU）**: 第一次见到的设备证书被记录并固定，之后证书变更作为严重验证失败上报
 //! - **密钥管理**: 支持RSA和ECDSA密钥算法，安全的密钥生成
 //!
 //! ## 安全特性
@@ -64,13 +65,25 @@ pub mod storage;
 pub mod validation;
 pub mod config;
 pub mod error;
+pub mod signing;
+pub mod encryption;
+pub mod status;
+pub mod keystore;
+pub mod tofu;
 
 pub use certificate::{CertificateManager, CertificateAuthority, CertificateManagerStatistics};
-pub use types::{CertificateData, CertificateType, CertificateStatus, CertificateVerificationResult, KeyPairInfo};
+pub use types::{CertificateData, CertificateType, CertificateStatus, CertificateVerificationResult, IssuanceAction, IssuanceLogEntry, KeyPairInfo, SignatureHash, SignedIssuanceHead};
 pub use storage::{CertificateStorage, StorageConfig, StorageStatistics};
 pub use validation::{CertificateValidator, ValidatorStatistics};
 pub use config::{CertificateConfig, CertificatePolicy};
 pub use error::{IdentityError, ConfigError};
+pub use signing::{sign_data, verify_signature};
+pub use encryption::{encrypt_for_certificate, decrypt_with_private_key};
+pub use keystore::{KeyStore, KeyStoreBackend, FileKeyStore, MemoryKeyStore};
+#[cfg(feature = "keyring")]
+pub use keystore::KeyringKeyStore;
+pub use status::{CertStatusRequest, CertStatusResponse, CertStatusTransport, DEFAULT_STATUS_CACHE_TTL};
+pub use tofu::{TofuStore, TofuPin, TofuResult, TOFU_PINS_FILE};
 
 /// 证书管理统一结果类型
 pub type IdentityResult<T> = std::result::Result<T, ErrorInfo>;
\ No newline at end of file
@@ -0,0 +1,86 @@
+//! # 数据签名与验签
+//!
+//! 使用设备私钥对任意数据签名，并使用设备证书中的公钥验证签名。
+//! 用于在证书之外为应用层数据（如文件清单）提供端到端的来源认证。
+
+use crate::error::IdentityError;
+use rcgen::{KeyPair, SigningKey};
+use rustls::pki_types::CertificateDer;
+use tracing::debug;
+
+/// 使用设备私钥对数据签名
+///
+/// # 参数
+///
+/// * `private_key_pem` - 设备私钥（PEM格式）
+/// * `data` - 待签名数据
+///
+/// # 返回值
+///
+/// 返回签名字节或错误
+pub fn sign_data(private_key_pem: &str, data: &[u8]) -> Result<Vec<u8>, IdentityError> {
+    let key_pair = KeyPair::from_pem(private_key_pem)
+        .map_err(|e| IdentityError::CryptoError(format!("解析私钥失败: {}", e)))?;
+
+    let signature = key_pair.sign(data)
+        .map_err(|e| IdentityError::CryptoError(format!("签名失败: {}", e)))?;
+
+    debug!("数据签名完成: {} 字节数据, {} 字节签名", data.len(), signature.len());
+    Ok(signature)
+}
+
+/// 使用证书公钥验证数据签名
+///
+/// # 参数
+///
+/// * `certificate_pem` - 签名方证书（PEM格式）
+/// * `data` - 原始数据
+/// * `signature` - 待验证的签名
+///
+/// # 返回值
+///
+/// 签名有效返回 `true`，签名不匹配返回 `false`，证书无法解析时返回错误
+pub fn verify_signature(certificate_pem: &str, data: &[u8], signature: &[u8]) -> Result<bool, IdentityError> {
+    let certificate = pem::parse(certificate_pem)
+        .map_err(|e| IdentityError::ValidationError(format!("解析证书PEM失败: {}", e)))?;
+    let certificate_der = CertificateDer::from(certificate.into_contents());
+
+    let end_entity = webpki::EndEntityCert::try_from(&certificate_der)
+        .map_err(|e| IdentityError::ValidationError(format!("解析证书失败: {:?}", e)))?;
+
+    // 证书公钥决定了可用的算法，逐一尝试直到找到匹配项
+    let verified = webpki::ALL_VERIFICATION_ALGS
+        .iter()
+        .any(|algorithm| end_entity.verify_signature(*algorithm, data, signature).is_ok());
+
+    debug!("签名验证完成: {}", if verified { "有效" } else { "无效" });
+    Ok(verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::certificate::CertificateManager;
+    use crate::config::CertificateConfig;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_sign_and_verify() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = CertificateConfig::builder()
+            .with_storage_directory(temp_dir.path())
+            .build()
+            .unwrap();
+        let manager = CertificateManager::initialize(config).await.unwrap();
+
+        let signer = manager.issue_device_certificate("signer").await.unwrap();
+        let other = manager.issue_device_certificate("other").await.unwrap();
+        let private_key = signer.private_key_pem.as_deref().unwrap();
+
+        let signature = sign_data(private_key, b"manifest").unwrap();
+
+        assert!(verify_signature(&signer.certificate_pem, b"manifest", &signature).unwrap());
+        assert!(!verify_signature(&signer.certificate_pem, b"tampered", &signature).unwrap());
+        assert!(!verify_signature(&other.certificate_pem, b"manifest", &signature).unwrap());
+    }
+}
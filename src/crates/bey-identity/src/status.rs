@@ -0,0 +1,127 @@
+//! # 证书在线状态查询
+//!
+//! 类似 OCSP 的在线状态查询：向签发证书的节点询问某个指纹当前是否有效。
+//! 签发节点的回答具有权威性，查询方在短时间内缓存回答；
+//! 签发节点不可达时回退到本地证书库中记录的吊销状态。
+//!
+//! 响应由签发节点的CA私钥签名并回显查询方的随机数，查询方只接受
+//! 本地CA或其交叉证书所信任的CA签名的响应，伪造或重放的回答会被丢弃。
+
+use crate::types::CertificateStatus;
+use crate::IdentityResult;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::RwLock;
+
+/// 状态查询结果的默认缓存时间
+pub const DEFAULT_STATUS_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// 证书状态查询请求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertStatusRequest {
+    /// 要查询的证书SHA-256指纹
+    pub fingerprint: String,
+    /// 查询方生成的随机数，响应必须原样回显
+    #[serde(default)]
+    pub nonce: u64,
+}
+
+/// 证书状态查询响应
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertStatusResponse {
+    /// 被查询的证书指纹
+    pub fingerprint: String,
+    /// 证书当前状态，响应方不认识该证书时为 `Unknown`
+    pub status: CertificateStatus,
+    /// 生成响应的时间
+    pub produced_at: SystemTime,
+    /// 回显的查询随机数
+    #[serde(default)]
+    pub nonce: u64,
+    /// 签发节点CA私钥对 [`CertStatusResponse::signing_bytes`] 的签名
+    #[serde(default)]
+    pub signature: Vec<u8>,
+}
+
+impl CertStatusResponse {
+    /// 参与签名的响应内容（不含签名本身）
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&(&self.fingerprint, &self.status, &self.produced_at, self.nonce))
+            .unwrap_or_default()
+    }
+}
+
+/// 证书状态查询传输通道
+///
+/// 由传输层实现，负责把查询请求发送给对等设备并返回其响应；
+/// 对端收到请求后应调用 [`crate::CertificateManager::answer_status_query`] 作答。
+#[async_trait]
+pub trait CertStatusTransport: Send + Sync {
+    /// 向对等设备发送状态查询
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 签发证书的设备ID
+    /// * `request` - 查询请求
+    ///
+    /// # 返回值
+    ///
+    /// 返回对端的响应，对端不可达时返回错误
+    async fn request_status(&self, peer_id: &str, request: &CertStatusRequest) -> IdentityResult<CertStatusResponse>;
+}
+
+/// 状态查询结果缓存
+pub(crate) struct StatusCache {
+    /// 缓存条目（签发设备ID, 指纹）-> (状态, 缓存时间)
+    entries: RwLock<HashMap<(String, String), (CertificateStatus, Instant)>>,
+    /// 缓存有效时间
+    ttl: Duration,
+}
+
+impl StatusCache {
+    /// 创建缓存
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// 获取未过期的缓存状态
+    pub(crate) async fn get(&self, peer_id: &str, fingerprint: &str) -> Option<CertificateStatus> {
+        let entries = self.entries.read().await;
+        entries.get(&(peer_id.to_string(), fingerprint.to_string()))
+            .filter(|(_, cached_at)| cached_at.elapsed() < self.ttl)
+            .map(|(status, _)| *status)
+    }
+
+    /// 缓存签发节点的回答
+    pub(crate) async fn insert(&self, peer_id: &str, fingerprint: &str, status: CertificateStatus) {
+        let mut entries = self.entries.write().await;
+        entries.retain(|_, (_, cached_at)| cached_at.elapsed() < self.ttl);
+        entries.insert((peer_id.to_string(), fingerprint.to_string()), (status, Instant::now()));
+    }
+
+    /// 移除某个指纹的所有缓存
+    pub(crate) async fn invalidate(&self, fingerprint: &str) {
+        self.entries.write().await.retain(|(_, cached), _| cached != fingerprint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_status_cache_expires() {
+        let cache = StatusCache::new(Duration::from_millis(20));
+        cache.insert("issuer", "abc", CertificateStatus::Valid).await;
+        assert_eq!(cache.get("issuer", "abc").await, Some(CertificateStatus::Valid));
+        assert_eq!(cache.get("other", "abc").await, None);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(cache.get("issuer", "abc").await, None);
+    }
+}
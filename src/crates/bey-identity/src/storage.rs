@@ -4,15 +4,16 @@
 //! 支持证书的加密存储、访问控制和完整性验证。
 
 use crate::error::IdentityError;
-use crate::types::{CertificateData, CertificateType};
+use crate::types::{CertificateData, CertificateType, IssuanceAction, IssuanceLogEntry, SignedIssuanceHead, ISSUANCE_LOG_GENESIS_HASH};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{info, debug, warn};
 
 /// 证书存储管理器
@@ -27,8 +28,18 @@ pub struct CertificateStorage {
 
     /// 存储统计信息
     statistics: Arc<RwLock<StorageStatistics>>,
+
+    /// 签发日志的下一条序号和最后一条记录的哈希，首次追加时从日志文件加载；
+    /// 锁同时串行化签发日志的追加
+    issuance_log_tail: Mutex<Option<(u64, String)>>,
 }
 
+/// 签发日志文件名（位于存储根目录，每行一条JSON记录）
+const ISSUANCE_LOG_FILE: &str = "issuance_log.jsonl";
+
+/// 签名的签发日志链头文件名（位于存储根目录）
+const ISSUANCE_HEAD_FILE: &str = "issuance_log.head.json";
+
 /// 存储配置
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
@@ -125,6 +136,7 @@ impl CertificateStorage {
             config,
             cache: Arc::new(RwLock::new(HashMap::new())),
             statistics: Arc::new(RwLock::new(StorageStatistics::default())),
+            issuance_log_tail: Mutex::new(None),
         };
 
         // 初始化时加载现有证书
@@ -310,6 +322,92 @@ impl CertificateStorage {
         Ok(certificates)
     }
 
+    /// 向签发日志追加一条记录，并写入签名的新链头
+    ///
+    /// 记录的序号和前一条哈希取自内存中的链尾，只在首次追加时读取日志文件
+    ///
+    /// # 参数
+    ///
+    /// * `action` - 操作类型
+    /// * `certificate` - 相关证书
+    /// * `sign` - 用CA私钥签名链头内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回追加的记录
+    pub async fn append_issuance_log(
+        &self,
+        action: IssuanceAction,
+        certificate: &CertificateData,
+        sign: impl FnOnce(&[u8]) -> Result<Vec<u8>, IdentityError>,
+    ) -> Result<IssuanceLogEntry, IdentityError> {
+        let mut tail = self.issuance_log_tail.lock().await;
+        if tail.is_none() {
+            *tail = Some(match self.read_issuance_log()?.last() {
+                Some(last) => (last.sequence + 1, last.hash.clone()),
+                None => (0, ISSUANCE_LOG_GENESIS_HASH.to_string()),
+            });
+        }
+        let (sequence, prev_hash) = tail.clone().unwrap_or((0, ISSUANCE_LOG_GENESIS_HASH.to_string()));
+        let entry = IssuanceLogEntry::new(sequence, action, certificate, prev_hash);
+
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|e| IdentityError::StorageError(format!("序列化签发日志失败: {}", e)))?;
+        line.push('\n');
+
+        let file_path = self.config.root_directory.join(ISSUANCE_LOG_FILE);
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file_path)
+            .map_err(|e| IdentityError::StorageError(format!("打开签发日志失败: {}", e)))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| IdentityError::StorageError(format!("写入签发日志失败: {}", e)))?;
+
+        *tail = Some((entry.sequence + 1, entry.hash.clone()));
+
+        if let Some(ref permissions) = self.config.file_permissions {
+            fs::set_permissions(&file_path, permissions.clone())
+                .map_err(|e| IdentityError::StorageError(format!("设置文件权限失败: {}", e)))?;
+        }
+
+        let mut head = SignedIssuanceHead {
+            sequence: entry.sequence,
+            hash: entry.hash.clone(),
+            signature: Vec::new(),
+        };
+        head.signature = sign(&head.signing_bytes())?;
+        self.write_issuance_head(&head)?;
+
+        debug!("签发日志追加记录 #{}: {:?} {}", entry.sequence, entry.action, entry.certificate_id);
+        Ok(entry)
+    }
+
+    /// 读取签发日志的全部记录
+    pub async fn issuance_log(&self) -> Result<Vec<IssuanceLogEntry>, IdentityError> {
+        let _guard = self.issuance_log_tail.lock().await;
+        self.read_issuance_log()
+    }
+
+    /// 读取签名的签发日志链头
+    ///
+    /// # 返回值
+    ///
+    /// 返回链头，尚未追加过记录时返回 `None`
+    pub async fn issuance_head(&self) -> Result<Option<SignedIssuanceHead>, IdentityError> {
+        let _guard = self.issuance_log_tail.lock().await;
+        let file_path = self.config.root_directory.join(ISSUANCE_HEAD_FILE);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read(&file_path)
+            .map_err(|e| IdentityError::StorageError(format!("读取签发日志链头失败: {}", e)))?;
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| IdentityError::StorageError(format!("解析签发日志链头失败: {}", e)))
+    }
+
     /// 获取存储统计信息
     pub async fn get_statistics(&self) -> StorageStatistics {
         let _cache_size = if self.config.enable_memory_cache {
@@ -422,6 +520,45 @@ impl CertificateStorage {
         Ok(None)
     }
 
+    /// 写入临时文件后替换签发日志链头文件
+    fn write_issuance_head(&self, head: &SignedIssuanceHead) -> Result<(), IdentityError> {
+        let data = serde_json::to_vec(head)
+            .map_err(|e| IdentityError::StorageError(format!("序列化签发日志链头失败: {}", e)))?;
+
+        let file_path = self.config.root_directory.join(ISSUANCE_HEAD_FILE);
+        let temp_path = self.config.root_directory.join(format!("{}.tmp", ISSUANCE_HEAD_FILE));
+        let mut file = fs::File::create(&temp_path)
+            .map_err(|e| IdentityError::StorageError(format!("创建签发日志链头临时文件失败: {}", e)))?;
+        file.write_all(&data)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| IdentityError::StorageError(format!("写入签发日志链头失败: {}", e)))?;
+        drop(file);
+
+        fs::rename(&temp_path, &file_path)
+            .map_err(|e| IdentityError::StorageError(format!("替换签发日志链头失败: {}", e)))
+    }
+
+    /// 读取签发日志文件，文件不存在时返回空列表
+    fn read_issuance_log(&self) -> Result<Vec<IssuanceLogEntry>, IdentityError> {
+        let file_path = self.config.root_directory.join(ISSUANCE_LOG_FILE);
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&file_path)
+            .map_err(|e| IdentityError::StorageError(format!("读取签发日志失败: {}", e)))?;
+
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str::<IssuanceLogEntry>(line)
+                    .map_err(|e| IdentityError::StorageError(format!("解析签发日志第 {} 条记录失败: {}", index + 1, e)))
+            })
+            .collect()
+    }
+
     /// 加载现有证书到缓存
     async fn load_existing_certificates(&self) -> Result<(), IdentityError> {
         if !self.config.enable_memory_cache {
@@ -0,0 +1,332 @@
+//! # 首次使用信任（TOFU）
+//!
+//! 局域网零配置接入时，第一次见到某个设备的证书即记录其指纹并信任，
+//! 之后该设备的证书被固定：出示相同证书时匹配通过，出示不同证书时视为证书变更，
+//! 由 [`crate::CertificateManager::verify_tofu`] 作为独立的严重验证失败上报。
+//! 固定记录保存在一个JSON文件中，重启后仍然有效。
+//!
+//! 验证在内存中同步完成，可以直接在TLS证书验证器中调用；新的固定记录随后写入文件。
+//! 文件先写入临时文件再替换，写入中断不会损坏原有记录；匹配时只在内存中更新最近使用时间，
+//! 随下一次写入一并保存。记录文件损坏时改名备份并从空记录开始。
+
+use crate::error::IdentityError;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// 固定记录文件名
+pub const TOFU_PINS_FILE: &str = "tofu_pins.json";
+
+/// 设备证书的固定记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TofuPin {
+    /// 固定的证书SHA-256指纹
+    pub fingerprint: String,
+    /// 第一次见到该证书的时间
+    pub first_seen: SystemTime,
+    /// 最近一次匹配的时间（只在写入新记录时保存到文件）
+    pub last_seen: SystemTime,
+}
+
+/// TOFU验证结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TofuResult {
+    /// 第一次见到该设备，证书已被记录并信任
+    FirstSeen,
+    /// 证书与固定的证书一致
+    Matched,
+    /// 证书与固定的证书不一致，固定记录保持不变
+    Changed {
+        /// 固定的证书指纹
+        pinned_fingerprint: String,
+        /// 本次出示的证书指纹
+        presented_fingerprint: String,
+    },
+}
+
+impl TofuResult {
+    /// 证书是否可信（第一次见到或与固定的证书一致）
+    pub fn is_trusted(&self) -> bool {
+        !matches!(self, TofuResult::Changed { .. })
+    }
+}
+
+/// TOFU固定记录存储
+pub struct TofuStore {
+    /// 固定记录文件路径
+    path: PathBuf,
+    /// 设备ID -> 固定记录
+    pins: RwLock<HashMap<String, TofuPin>>,
+    /// 是否有尚未写入文件的新记录或移除
+    dirty: AtomicBool,
+    /// 串行化文件写入
+    write_lock: Mutex<()>,
+}
+
+impl TofuStore {
+    /// 打开固定记录存储，文件不存在时从空记录开始
+    ///
+    /// 文件无法解析时改名为 `<文件名>.corrupt` 备份，并从空记录开始
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 固定记录文件路径
+    ///
+    /// # 返回值
+    ///
+    /// 返回存储实例，文件无法读取时返回错误
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, IdentityError> {
+        let path = path.as_ref().to_path_buf();
+        let pins = match tokio::fs::read(&path).await {
+            Ok(data) => match serde_json::from_slice(&data) {
+                Ok(pins) => pins,
+                Err(e) => {
+                    let backup = Self::sibling(&path, "corrupt");
+                    warn!("TOFU固定记录 {} 已损坏，备份为 {} 并从空记录开始: {}", path.display(), backup.display(), e);
+                    if let Err(e) = tokio::fs::rename(&path, &backup).await {
+                        warn!("备份损坏的TOFU固定记录失败: {}", e);
+                    }
+                    HashMap::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(IdentityError::StorageError(format!("读取TOFU固定记录失败: {}", e))),
+        };
+
+        Ok(Self {
+            path,
+            pins: RwLock::new(pins),
+            dirty: AtomicBool::new(false),
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    /// 在内存中按首次使用信任检查设备证书
+    ///
+    /// 不读写文件，可在同步的证书验证器中调用；第一次见到的证书之后需调用
+    /// [`TofuStore::flush`] 写入文件
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备ID
+    /// * `cert_der` - 设备出示的证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回验证结果
+    pub fn check(&self, device_id: &str, cert_der: &[u8]) -> TofuResult {
+        let fingerprint = format!("{:x}", sha2::Sha256::digest(cert_der));
+        let now = SystemTime::now();
+        let mut pins = self.pins.write().unwrap_or_else(|e| e.into_inner());
+
+        match pins.get_mut(device_id) {
+            Some(pin) if pin.fingerprint == fingerprint => {
+                pin.last_seen = now;
+                TofuResult::Matched
+            }
+            Some(pin) => TofuResult::Changed {
+                pinned_fingerprint: pin.fingerprint.clone(),
+                presented_fingerprint: fingerprint,
+            },
+            None => {
+                info!("首次见到设备 {} 的证书，固定指纹: {}", device_id, fingerprint);
+                pins.insert(device_id.to_string(), TofuPin {
+                    fingerprint,
+                    first_seen: now,
+                    last_seen: now,
+                });
+                self.dirty.store(true, Ordering::SeqCst);
+                TofuResult::FirstSeen
+            }
+        }
+    }
+
+    /// 按首次使用信任验证设备证书
+    ///
+    /// 第一次见到设备时记录证书指纹并写入文件；之后只有相同的证书匹配通过，
+    /// 不同的证书返回 [`TofuResult::Changed`] 且不会替换固定记录
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备ID
+    /// * `cert_der` - 设备出示的证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回验证结果，保存固定记录失败时返回错误
+    pub async fn verify_tofu(&self, device_id: &str, cert_der: &[u8]) -> Result<TofuResult, IdentityError> {
+        let result = self.check(device_id, cert_der);
+        if result == TofuResult::FirstSeen {
+            self.flush().await?;
+        }
+        Ok(result)
+    }
+
+    /// 获取设备的固定记录
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备ID
+    pub async fn pin(&self, device_id: &str) -> Option<TofuPin> {
+        self.pins.read().unwrap_or_else(|e| e.into_inner()).get(device_id).cloned()
+    }
+
+    /// 移除设备的固定记录，该设备下次出示的证书会被重新信任
+    ///
+    /// 用于确认设备证书的变更是合法的（如重装系统）
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回是否存在被移除的记录，保存失败时返回错误
+    pub async fn forget(&self, device_id: &str) -> Result<bool, IdentityError> {
+        let removed = self.pins.write().unwrap_or_else(|e| e.into_inner()).remove(device_id).is_some();
+        if !removed {
+            return Ok(false);
+        }
+        self.dirty.store(true, Ordering::SeqCst);
+        self.flush().await?;
+        debug!("已移除设备 {} 的TOFU固定记录", device_id);
+        Ok(true)
+    }
+
+    /// 把尚未保存的固定记录写入文件，没有变化时不写入
+    ///
+    /// 先写入临时文件（Unix系统上权限为 0600）再替换原文件
+    pub async fn flush(&self) -> Result<(), IdentityError> {
+        let _guard = self.write_lock.lock().await;
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let data = {
+            let pins = self.pins.read().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_vec_pretty(&*pins)
+                .map_err(|e| IdentityError::StorageError(format!("序列化TOFU固定记录失败: {}", e)))?
+        };
+        if let Err(e) = self.write_atomically(&data).await {
+            // 写入失败时保留标记，下次写入时重试
+            self.dirty.store(true, Ordering::SeqCst);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// 写入临时文件后替换固定记录文件
+    async fn write_atomically(&self, data: &[u8]) -> Result<(), IdentityError> {
+        use tokio::io::AsyncWriteExt;
+
+        let temp_path = Self::sibling(&self.path, "tmp");
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(&temp_path).await
+            .map_err(|e| IdentityError::StorageError(format!("创建TOFU固定记录临时文件失败: {}", e)))?;
+        file.write_all(data).await
+            .map_err(|e| IdentityError::StorageError(format!("写入TOFU固定记录失败: {}", e)))?;
+        file.sync_all().await
+            .map_err(|e| IdentityError::StorageError(format!("同步TOFU固定记录失败: {}", e)))?;
+        drop(file);
+
+        tokio::fs::rename(&temp_path, &self.path).await
+            .map_err(|e| IdentityError::StorageError(format!("替换TOFU固定记录失败: {}", e)))
+    }
+
+    /// 与固定记录文件同目录、追加后缀的文件路径
+    fn sibling(path: &Path, suffix: &str) -> PathBuf {
+        let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+        name.push(format!(".{}", suffix));
+        path.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_first_seen_matched_and_changed_across_reopen() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let path = temp_dir.path().join(TOFU_PINS_FILE);
+        let store = TofuStore::open(&path).await.expect("打开TOFU存储失败");
+
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::FirstSeen);
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::Matched);
+        assert_eq!(store.verify_tofu("device-b", b"cert-1").await.expect("验证失败"), TofuResult::FirstSeen);
+
+        // 变更的证书每次都报告变更，不会替换固定记录
+        let pinned = store.pin("device-a").await.expect("应有固定记录").fingerprint;
+        for _ in 0..2 {
+            match store.verify_tofu("device-a", b"cert-2").await.expect("验证失败") {
+                TofuResult::Changed { pinned_fingerprint, presented_fingerprint } => {
+                    assert_eq!(pinned_fingerprint, pinned);
+                    assert_ne!(presented_fingerprint, pinned);
+                }
+                result => panic!("应为证书变更: {:?}", result),
+            }
+        }
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::Matched);
+
+        // 固定记录在重新打开后仍然有效
+        let store = TofuStore::open(&path).await.expect("重新打开TOFU存储失败");
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::Matched);
+        assert!(!store.verify_tofu("device-a", b"cert-2").await.expect("验证失败").is_trusted());
+
+        // 移除固定记录后重新信任
+        assert!(store.forget("device-a").await.expect("移除失败"));
+        assert_eq!(store.verify_tofu("device-a", b"cert-2").await.expect("验证失败"), TofuResult::FirstSeen);
+    }
+
+    #[tokio::test]
+    async fn test_matches_do_not_rewrite_and_writes_are_atomic() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let path = temp_dir.path().join(TOFU_PINS_FILE);
+        let store = TofuStore::open(&path).await.expect("打开TOFU存储失败");
+
+        // 同步检查只修改内存，写入后才出现在文件中
+        assert_eq!(store.check("device-a", b"cert-1"), TofuResult::FirstSeen);
+        assert!(!path.exists());
+        store.flush().await.expect("写入固定记录失败");
+        let written = std::fs::read(&path).expect("读取固定记录失败");
+
+        // 匹配不重写文件
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::Matched);
+        store.flush().await.expect("写入固定记录失败");
+        assert_eq!(std::fs::read(&path).expect("读取固定记录失败"), written);
+        assert!(!temp_dir.path().join(format!("{}.tmp", TOFU_PINS_FILE)).exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).expect("读取文件信息失败").permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_pins_file_is_backed_up() {
+        let temp_dir = TempDir::new().expect("无法创建临时目录");
+        let path = temp_dir.path().join(TOFU_PINS_FILE);
+        std::fs::write(&path, b"{ not json").expect("写入文件失败");
+
+        let store = TofuStore::open(&path).await.expect("损坏的固定记录不应阻止打开");
+        assert!(store.pin("device-a").await.is_none());
+        assert!(temp_dir.path().join(format!("{}.corrupt", TOFU_PINS_FILE)).exists());
+
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::FirstSeen);
+        let store = TofuStore::open(&path).await.expect("重新打开TOFU存储失败");
+        assert_eq!(store.verify_tofu("device-a", b"cert-1").await.expect("验证失败"), TofuResult::Matched);
+    }
+}
@@ -115,6 +115,47 @@ impl CertificateStatus {
     }
 }
 
+/// 证书签名使用的哈希算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignatureHash {
+    /// SHA-256
+    Sha256,
+    /// SHA-384
+    Sha384,
+    /// SHA-512
+    Sha512,
+}
+
+impl SignatureHash {
+    /// 根据X.509签名算法OID识别签名哈希
+    ///
+    /// # 参数
+    ///
+    /// * `oid` - 点分形式的签名算法OID，如 `1.2.840.10045.4.3.3`
+    ///
+    /// # 返回值
+    ///
+    /// 返回RSA或ECDSA签名使用的哈希，其他算法（如Ed25519）返回 `None`
+    pub fn from_signature_oid(oid: &str) -> Option<Self> {
+        match oid {
+            "1.2.840.113549.1.1.11" | "1.2.840.10045.4.3.2" => Some(SignatureHash::Sha256),
+            "1.2.840.113549.1.1.12" | "1.2.840.10045.4.3.3" => Some(SignatureHash::Sha384),
+            "1.2.840.113549.1.1.13" | "1.2.840.10045.4.3.4" => Some(SignatureHash::Sha512),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SignatureHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureHash::Sha256 => write!(f, "SHA-256"),
+            SignatureHash::Sha384 => write!(f, "SHA-384"),
+            SignatureHash::Sha512 => write!(f, "SHA-512"),
+        }
+    }
+}
+
 /// 证书数据结构
 ///
 /// 包含证书的所有相关信息，包括证书内容、元数据和状态信息。
@@ -169,6 +210,11 @@ pub struct CertificateData {
     /// 如 "RSA-2048", "ECDSA-P256" 等
     pub key_algorithm: Option<String>,
 
+    /// 签名哈希算法
+    /// 签发者签名时使用的哈希，Ed25519等不单独指定哈希的算法为 `None`
+    #[serde(default)]
+    pub signature_hash: Option<SignatureHash>,
+
     /// 证书版本
     /// X.509证书版本号
     pub version: Option<u32>,
@@ -199,6 +245,7 @@ impl CertificateData {
             serial_number: None,
             status: CertificateStatus::Pending,
             key_algorithm: None,
+            signature_hash: None,
             version: Some(3), // X.509 v3
         }
     }
@@ -342,4 +389,126 @@ impl KeyPairInfo {
     pub fn key_type_description(&self) -> String {
         format!("{}-{}", self.algorithm, self.key_size)
     }
-}
\ No newline at end of file
+}
+/// 签发日志中第一条记录的前一条哈希
+pub const ISSUANCE_LOG_GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// 签发日志记录的操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IssuanceAction {
+    /// 签发证书
+    Issued,
+
+    /// 吊销证书
+    Revoked,
+}
+
+/// 签名的签发日志链头
+///
+/// 记录日志最后一条记录的序号和哈希，由CA私钥签名。
+/// 截断日志或重写整条哈希链后无法伪造与之匹配的签名。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedIssuanceHead {
+    /// 最后一条记录的序号
+    pub sequence: u64,
+
+    /// 最后一条记录的哈希
+    pub hash: String,
+
+    /// CA私钥对 [`SignedIssuanceHead::signing_bytes`] 的签名
+    pub signature: Vec<u8>,
+}
+
+impl SignedIssuanceHead {
+    /// 参与签名的链头内容（不含签名本身）
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&("bey.issuance_log.head", self.sequence, &self.hash))
+            .unwrap_or_default()
+    }
+}
+
+/// 签发日志记录
+///
+/// 每条记录包含前一条记录的哈希，构成只能追加的哈希链，
+/// 修改或删除任意一条记录都会使之后的链校验失败。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssuanceLogEntry {
+    /// 记录序号（从0开始）
+    pub sequence: u64,
+
+    /// 记录时间
+    pub timestamp: SystemTime,
+
+    /// 操作类型
+    pub action: IssuanceAction,
+
+    /// 证书ID
+    pub certificate_id: String,
+
+    /// 设备标识符
+    pub device_identifier: String,
+
+    /// 证书指纹
+    pub fingerprint: String,
+
+    /// 前一条记录的哈希
+    pub prev_hash: String,
+
+    /// 本条记录的哈希
+    pub hash: String,
+}
+
+impl IssuanceLogEntry {
+    /// 创建签发日志记录并计算哈希
+    ///
+    /// # 参数
+    ///
+    /// * `sequence` - 记录序号
+    /// * `action` - 操作类型
+    /// * `certificate` - 相关证书
+    /// * `prev_hash` - 前一条记录的哈希
+    ///
+    /// # 返回值
+    ///
+    /// 返回哈希已计算好的记录
+    pub fn new(sequence: u64, action: IssuanceAction, certificate: &CertificateData, prev_hash: String) -> Self {
+        let mut entry = Self {
+            sequence,
+            timestamp: SystemTime::now(),
+            action,
+            certificate_id: certificate.certificate_id.clone(),
+            device_identifier: certificate.device_identifier.clone(),
+            fingerprint: certificate.fingerprint.clone(),
+            prev_hash,
+            hash: String::new(),
+        };
+        entry.hash = entry.compute_hash();
+        entry
+    }
+
+    /// 根据记录内容（不含 `hash` 字段）计算 SHA-256 哈希
+    pub fn compute_hash(&self) -> String {
+        use sha2::{Sha256, Digest};
+
+        let timestamp = self.timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+
+        let mut hasher = Sha256::new();
+        for field in [
+            self.sequence.to_string(),
+            timestamp.to_string(),
+            format!("{:?}", self.action),
+            self.certificate_id.clone(),
+            self.device_identifier.clone(),
+            self.fingerprint.clone(),
+            self.prev_hash.clone(),
+        ] {
+            // 写入长度前缀，避免字段拼接产生歧义
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
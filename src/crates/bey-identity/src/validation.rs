@@ -5,7 +5,7 @@
 
 use crate::config::CertificateConfig;
 use crate::error::IdentityError;
-use crate::types::{CertificateData, CertificateStatus, CertificateVerificationResult, CertificateType};
+use crate::types::{CertificateData, CertificateStatus, CertificateVerificationResult, CertificateType, SignatureHash};
 use rustls::RootCertStore;
 use rcgen::SigningKey;
 use sha2::Digest;
@@ -102,7 +102,17 @@ impl CertificateValidator {
             return Ok(result);
         }
 
-        // 4. 如果启用严格验证，进行更详细的检查
+        // 4. 检查基本约束和密钥用途
+        if let Err(result) = self.check_usage_constraints(certificate) {
+            return Ok(result);
+        }
+
+        // 5. 检查签名算法是否与配置一致
+        if let Err(result) = self.check_signature_hash(certificate) {
+            return Ok(result);
+        }
+
+        // 6. 如果启用严格验证，进行更详细的检查
         if self.config.enforce_strict_validation {
             if let Err(result) = self.check_certificate_strict(certificate) {
                 return Ok(result);
@@ -284,6 +294,71 @@ impl CertificateValidator {
         }
     }
 
+    /// 检查基本约束和扩展密钥用途
+    ///
+    /// CA证书必须带有 `CA:TRUE` 基本约束；终端实体证书不能带有CA基本约束，
+    /// 用作客户端的设备证书和客户端证书还必须包含 clientAuth 扩展密钥用途
+    fn check_usage_constraints(&self, certificate: &CertificateData) -> Result<(), CertificateVerificationResult> {
+        let der = pem::parse(&certificate.certificate_pem)
+            .map_err(|e| CertificateVerificationResult::failure(format!("证书格式解析失败: {}", e)))?;
+        let (_, x509) = x509_parser::parse_x509_certificate(der.contents())
+            .map_err(|e| CertificateVerificationResult::failure(format!("解析X.509证书失败: {}", e)))?;
+
+        let is_ca = x509.basic_constraints()
+            .map_err(|e| CertificateVerificationResult::failure(format!("解析基本约束失败: {}", e)))?
+            .is_some_and(|constraints| constraints.value.ca);
+
+        if certificate.certificate_type.is_ca() {
+            if !is_ca {
+                return Err(CertificateVerificationResult::failure("CA证书缺少CA基本约束".to_string()));
+            }
+            return Ok(());
+        }
+
+        if is_ca {
+            warn!("终端证书带有CA基本约束: {}", certificate.certificate_id);
+            return Err(CertificateVerificationResult::failure("终端证书不能带有CA基本约束".to_string()));
+        }
+
+        if matches!(certificate.certificate_type, CertificateType::Device | CertificateType::Client) {
+            let client_auth = x509.extended_key_usage()
+                .map_err(|e| CertificateVerificationResult::failure(format!("解析扩展密钥用途失败: {}", e)))?
+                .is_some_and(|usage| usage.value.client_auth);
+            if !client_auth {
+                warn!("证书缺少clientAuth扩展密钥用途: {}", certificate.certificate_id);
+                return Err(CertificateVerificationResult::failure("证书缺少clientAuth扩展密钥用途".to_string()));
+            }
+        }
+
+        debug!("证书用途约束检查通过: {}", certificate.certificate_id);
+        Ok(())
+    }
+
+    /// 检查签名哈希
+    ///
+    /// 配置了签名哈希时，证书的签名算法必须使用该哈希；未配置时不检查
+    fn check_signature_hash(&self, certificate: &CertificateData) -> Result<(), CertificateVerificationResult> {
+        let Some(expected) = self.config.signature_hash else {
+            return Ok(());
+        };
+
+        let der = pem::parse(&certificate.certificate_pem)
+            .map_err(|e| CertificateVerificationResult::failure(format!("证书格式解析失败: {}", e)))?;
+        let (_, x509) = x509_parser::parse_x509_certificate(der.contents())
+            .map_err(|e| CertificateVerificationResult::failure(format!("解析X.509证书失败: {}", e)))?;
+
+        let oid = x509.signature_algorithm.algorithm.to_id_string();
+        if SignatureHash::from_signature_oid(&oid) != Some(expected) {
+            warn!("证书 {} 的签名算法 {} 不使用配置的 {}", certificate.certificate_id, oid, expected);
+            return Err(CertificateVerificationResult::failure(
+                format!("证书签名算法 {} 与配置的 {} 不符", oid, expected)
+            ));
+        }
+
+        debug!("证书签名哈希检查通过: {} ({})", certificate.certificate_id, expected);
+        Ok(())
+    }
+
     /// 严格证书检查
     fn check_certificate_strict(&self, certificate: &CertificateData) -> Result<(), CertificateVerificationResult> {
         // 检查指纹是否为空
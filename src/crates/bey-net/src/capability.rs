@@ -0,0 +1,70 @@
+//! # 设备能力广播
+//!
+//! 在mDNS的TXT记录和UDP广播公告中携带设备能力，连接之前即可按能力筛选设备。
+//! 每种 [`Capability`] 对应一个固定的能力标识，能力列表按标识排序并去重后以逗号连接，
+//! 相同的能力集合无论声明顺序如何都编码为相同的内容。
+
+pub use bey_types::Capability;
+
+/// TXT记录中能力列表的键
+pub const CAPABILITIES_TXT_KEY: &str = "capabilities";
+
+/// 能力在发现记录中的标识
+///
+/// # 参数
+///
+/// * `capability` - 设备能力
+pub fn capability_token(capability: &Capability) -> &'static str {
+    match capability {
+        Capability::FileTransfer => "file_transfer",
+        Capability::ClipboardSync => "clipboard",
+        Capability::Messaging => "messaging",
+        Capability::StorageContribution => "storage_contribution",
+        Capability::CertificateManagement => "certificate_management",
+    }
+}
+
+/// 把能力转换为排序去重的能力标识列表
+///
+/// # 参数
+///
+/// * `capabilities` - 设备能力，顺序不限
+pub fn capability_tokens(capabilities: &[Capability]) -> Vec<String> {
+    normalize_capabilities(capabilities.iter().map(capability_token))
+}
+
+/// 生成携带能力列表的TXT记录
+///
+/// # 参数
+///
+/// * `capabilities` - 设备能力，顺序不限
+pub fn capabilities_txt_record(capabilities: &[Capability]) -> String {
+    format!("{}={}", CAPABILITIES_TXT_KEY, capability_tokens(capabilities).join(","))
+}
+
+/// 解析逗号分隔的能力列表
+///
+/// 未知的能力标识原样保留，便于其他组件声明自定义能力
+///
+/// # 参数
+///
+/// * `value` - TXT记录中能力列表的值
+///
+/// # 返回值
+///
+/// 返回排序去重的能力标识列表
+pub fn parse_capabilities(value: &str) -> Vec<String> {
+    normalize_capabilities(value.split(','))
+}
+
+/// 排序去重能力标识，忽略空白和空标识
+pub(crate) fn normalize_capabilities<'a>(tokens: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut tokens: Vec<String> = tokens.into_iter()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
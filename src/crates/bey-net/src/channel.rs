@@ -0,0 +1,314 @@
+//! # 逻辑通道多路复用
+//!
+//! 在同一条长期保持的QUIC连接上复用多个编号的逻辑通道，避免为每条消息打开新的流。
+//! 每个逻辑通道对应连接上的一条双向流，打开时先发送通道头（魔数和通道号），
+//! 对端按通道号把流交给认领该通道的子系统并回复接受或拒绝；之后双方在流上收发长度前缀帧。
+//!
+//! 消息、剪切板和文件子系统可以分别认领 [`MESSAGE_CHANNEL`]、[`CLIPBOARD_CHANNEL`]
+//! 和 [`FILE_CHANNEL`]，不同通道的数据互不阻塞：每条入站流在单独的任务中读取通道头，
+//! 认领方来不及取走通道、积压已满时重置该流，而不是等待认领方。
+
+use bey_transport::{Connection, ReadError, ReadExactError, RecvStream, SendStream, VarInt};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::NetResult;
+
+/// 逻辑通道号
+pub type ChannelId = u32;
+
+/// 消息子系统使用的通道
+pub const MESSAGE_CHANNEL: ChannelId = 1;
+/// 剪切板子系统使用的通道
+pub const CLIPBOARD_CHANNEL: ChannelId = 2;
+/// 文件子系统使用的通道
+pub const FILE_CHANNEL: ChannelId = 3;
+
+/// 单帧的最大长度（字节）
+pub const MAX_CHANNEL_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// 等待通道头和接受回复的超时时间
+pub const CHANNEL_OPEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 每个通道等待认领方取走的入站通道数量
+pub(crate) const CHANNEL_BACKLOG: usize = 16;
+
+/// 通道头魔数
+const CHANNEL_MAGIC: &[u8; 4] = b"BEYC";
+
+/// 对端接受通道
+const CHANNEL_ACCEPTED: u8 = 0;
+/// 对端没有认领该通道
+const CHANNEL_REJECTED: u8 = 1;
+
+/// 认领方积压已满时重置流使用的错误码
+const CHANNEL_BUSY: u32 = 1;
+
+/// 逻辑通道
+///
+/// 连接上的一条双向流，按长度前缀帧收发数据
+pub struct Channel {
+    /// 通道号
+    id: ChannelId,
+    /// 所属连接的ID
+    connection_id: usize,
+    /// 对端地址
+    remote_addr: SocketAddr,
+    /// 发送端
+    send: SendStream,
+    /// 接收端
+    recv: RecvStream,
+}
+
+impl Channel {
+    /// 通道号
+    pub fn id(&self) -> ChannelId {
+        self.id
+    }
+
+    /// 对端地址
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// 所属连接的ID
+    pub(crate) fn connection_id(&self) -> usize {
+        self.connection_id
+    }
+
+    /// 发送一帧数据
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 帧内容，不超过 [`MAX_CHANNEL_FRAME_SIZE`]
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果或错误
+    pub async fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        if data.len() > MAX_CHANNEL_FRAME_SIZE {
+            return Err(ErrorInfo::new(4944, format!("通道 {} 的帧过长: {} 字节", self.id, data.len()))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        self.send.write_all(&(data.len() as u32).to_be_bytes()).await
+            .map_err(|e| self.io_error("发送", e))?;
+        self.send.write_all(data).await
+            .map_err(|e| self.io_error("发送", e))
+    }
+
+    /// 接收一帧数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回帧内容，对端结束发送时返回 `None`
+    pub async fn recv(&mut self) -> NetResult<Option<Vec<u8>>> {
+        let mut len_bytes = [0u8; 4];
+        let mut filled = 0;
+        while filled < len_bytes.len() {
+            match self.recv.read(&mut len_bytes[filled..]).await.map_err(|e| self.io_error("接收", e))? {
+                Some(n) => filled += n,
+                None if filled == 0 => return Ok(None),
+                None => return Err(self.io_error("接收", "帧长度不完整")),
+            }
+        }
+
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        if len > MAX_CHANNEL_FRAME_SIZE {
+            return Err(ErrorInfo::new(4944, format!("通道 {} 收到过长的帧: {} 字节", self.id, len))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Error));
+        }
+        let mut data = vec![0u8; len];
+        self.recv.read_exact(&mut data).await
+            .map_err(|e| self.io_error("接收", e))?;
+        Ok(Some(data))
+    }
+
+    /// 结束发送，对端读完已发送的帧后 [`Channel::recv`] 返回 `None`
+    pub fn finish(&mut self) -> NetResult<()> {
+        self.send.finish().map_err(|e| self.io_error("结束", e))
+    }
+
+    /// 通道读写错误
+    fn io_error(&self, action: &str, error: impl std::fmt::Display) -> ErrorInfo {
+        ErrorInfo::new(4945, format!("通道 {} ({}) {}失败: {}", self.id, self.remote_addr, action, error))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error)
+    }
+}
+
+/// 在连接上打开逻辑通道
+///
+/// # 参数
+///
+/// * `connection` - 已建立的连接
+/// * `channel_id` - 通道号
+///
+/// # 返回值
+///
+/// 返回已被对端接受的通道，对端没有认领该通道或认领方积压已满时返回错误
+pub async fn open(connection: &Connection, channel_id: ChannelId) -> NetResult<Channel> {
+    let remote_addr = connection.remote_address();
+    let open_error = |e: &dyn std::fmt::Display| {
+        ErrorInfo::new(4942, format!("打开到 {} 的通道 {} 失败: {}", remote_addr, channel_id, e))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error)
+    };
+
+    let status = tokio::time::timeout(CHANNEL_OPEN_TIMEOUT, async {
+        let (mut send, mut recv) = connection.open_bi().await.map_err(|e| open_error(&e))?;
+        let mut header = CHANNEL_MAGIC.to_vec();
+        header.extend_from_slice(&channel_id.to_be_bytes());
+        send.write_all(&header).await.map_err(|e| open_error(&e))?;
+
+        let mut status = [0u8; 1];
+        match recv.read_exact(&mut status).await {
+            Ok(()) => Ok((status[0], send, recv)),
+            Err(ReadExactError::ReadError(ReadError::Reset(code))) if code == VarInt::from_u32(CHANNEL_BUSY) => {
+                Err(ErrorInfo::new(4947, format!("{} 的通道 {} 积压已满", remote_addr, channel_id))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Warning))
+            }
+            Err(e) => Err(open_error(&e)),
+        }
+    }).await.map_err(|_| open_error(&"等待对端接受超时"))?;
+    let (status, send, recv) = status?;
+
+    if status != CHANNEL_ACCEPTED {
+        return Err(ErrorInfo::new(4943, format!("{} 没有认领通道 {}", remote_addr, channel_id))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Warning));
+    }
+
+    debug!("已打开到 {} 的通道 {}", remote_addr, channel_id);
+    Ok(Channel { id: channel_id, connection_id: connection.stable_id(), remote_addr, send, recv })
+}
+
+/// 通道路由器
+///
+/// 记录各子系统认领的通道，并把连接上对端打开的通道按通道号交给认领方
+#[derive(Default)]
+pub struct ChannelRouter {
+    /// 通道号 -> 入站通道的发送端
+    claimed: std::sync::Mutex<HashMap<ChannelId, mpsc::Sender<Channel>>>,
+    /// 正在接受通道的连接
+    served: std::sync::Mutex<HashSet<usize>>,
+}
+
+impl ChannelRouter {
+    /// 创建通道路由器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 认领通道，对端打开的该通道从返回的接收端取出
+    ///
+    /// 丢弃接收端即释放通道，之后可以重新认领
+    ///
+    /// # 参数
+    ///
+    /// * `channel_id` - 通道号
+    ///
+    /// # 返回值
+    ///
+    /// 返回入站通道的接收端，通道已被认领时返回错误
+    pub fn claim(&self, channel_id: ChannelId) -> NetResult<mpsc::Receiver<Channel>> {
+        let mut claimed = self.claimed.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if claimed.get(&channel_id).is_some_and(|sender| !sender.is_closed()) {
+            return Err(ErrorInfo::new(4941, format!("通道 {} 已被认领", channel_id))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_BACKLOG);
+        claimed.insert(channel_id, sender);
+        Ok(receiver)
+    }
+
+    /// 接受连接上对端打开的通道，直到连接关闭
+    ///
+    /// 同一连接只会被接受一次，重复调用立即返回。每条流在单独的任务中路由，
+    /// 迟迟不发送通道头的流不会阻塞同一连接上的其他通道
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 已完成握手的连接
+    pub async fn serve(self: &Arc<Self>, connection: Connection) {
+        let connection_id = connection.stable_id();
+        if !self.lock_served().insert(connection_id) {
+            return;
+        }
+
+        let remote_addr = connection.remote_address();
+        let mut routes = JoinSet::new();
+        while let Ok((send, recv)) = connection.accept_bi().await {
+            let router = Arc::clone(self);
+            routes.spawn(async move {
+                if let Err(e) = router.route(connection_id, remote_addr, send, recv).await {
+                    warn!("处理 {} 打开的通道失败: {}", remote_addr, e);
+                }
+            });
+
+            // 回收已路由的流
+            while routes.try_join_next().is_some() {}
+        }
+
+        self.lock_served().remove(&connection_id);
+        debug!("停止接受 {} 的通道", remote_addr);
+    }
+
+    /// 读取通道头并把通道交给认领方，认领方积压已满时重置流
+    async fn route(&self, connection_id: usize, remote_addr: SocketAddr, mut send: SendStream, mut recv: RecvStream) -> NetResult<()> {
+        let io_error = |e: &dyn std::fmt::Display| {
+            ErrorInfo::new(4942, format!("接受 {} 的通道失败: {}", remote_addr, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error)
+        };
+
+        let mut header = [0u8; 8];
+        tokio::time::timeout(CHANNEL_OPEN_TIMEOUT, recv.read_exact(&mut header)).await
+            .map_err(|_| io_error(&"等待通道头超时"))?
+            .map_err(|e| io_error(&e))?;
+        if &header[..4] != CHANNEL_MAGIC {
+            return Err(io_error(&"通道头无效"));
+        }
+        let channel_id = ChannelId::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+        let sender = self.claimed.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&channel_id)
+            .cloned();
+        let permit = match sender.as_ref().map(|sender| sender.try_reserve()) {
+            Some(Ok(permit)) => permit,
+            Some(Err(TrySendError::Full(()))) => {
+                warn!("通道 {} 积压已满，重置 {} 打开的流", channel_id, remote_addr);
+                let _ = recv.stop(VarInt::from_u32(CHANNEL_BUSY));
+                let _ = send.reset(VarInt::from_u32(CHANNEL_BUSY));
+                return Ok(());
+            }
+            Some(Err(TrySendError::Closed(()))) | None => {
+                debug!("{} 打开的通道 {} 未被认领", remote_addr, channel_id);
+                send.write_all(&[CHANNEL_REJECTED]).await.map_err(|e| io_error(&e))?;
+                let _ = send.finish();
+                return Ok(());
+            }
+        };
+
+        send.write_all(&[CHANNEL_ACCEPTED]).await.map_err(|e| io_error(&e))?;
+        debug!("接受 {} 打开的通道 {}", remote_addr, channel_id);
+        permit.send(Channel { id: channel_id, connection_id, remote_addr, send, recv });
+        Ok(())
+    }
+
+    /// 获取已接受连接的锁，锁被毒化时继续使用其中的数据
+    fn lock_served(&self) -> std::sync::MutexGuard<'_, HashSet<usize>> {
+        self.served.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
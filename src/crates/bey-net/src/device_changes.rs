@@ -0,0 +1,373 @@
+//! # 设备变化流
+//!
+//! 将mDNS发现事件和UDP广播发现事件统一转换为设备的新增、更新、移除变化，
+//! 每条变化都携带完整的 [`DeviceInfo`]，便于界面直接订阅并刷新设备列表。
+//!
+//! ## 核心功能
+//!
+//! - **事件归一化**: mDNS和UDP两种来源的事件按设备ID合并为同一份设备列表
+//! - **去重**: 设备信息没有变化的重复发现不会产生变化事件
+//! - **多订阅者**: 每次调用 [`DeviceChangeFeed::device_changes`] 都得到独立的变化流
+
+use futures::Stream;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+
+use crate::capability::{parse_capabilities, Capability, CAPABILITIES_TXT_KEY};
+use crate::mdns_discovery::{MdnsDiscoveryEvent, MdnsServiceInfo};
+use crate::udp_discovery::{DeviceEvent, DeviceInfo};
+
+/// 默认变化通道容量
+pub const DEFAULT_DEVICE_CHANGE_CAPACITY: usize = 256;
+
+/// 设备变化
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceChange {
+    /// 发现新设备
+    Added(DeviceInfo),
+    /// 已知设备的信息发生变化
+    Updated(DeviceInfo),
+    /// 设备离线，携带最后一次已知的设备信息
+    Removed(DeviceInfo),
+}
+
+impl DeviceChange {
+    /// 变化对应的设备信息
+    pub fn device(&self) -> &DeviceInfo {
+        match self {
+            DeviceChange::Added(device)
+            | DeviceChange::Updated(device)
+            | DeviceChange::Removed(device) => device,
+        }
+    }
+}
+
+/// 已知设备列表
+#[derive(Default)]
+struct KnownDevices {
+    /// 设备ID -> 设备信息
+    devices: HashMap<String, DeviceInfo>,
+    /// mDNS服务名称 -> 设备ID（mDNS移除事件只携带服务名称）
+    mdns_names: HashMap<String, String>,
+}
+
+/// 设备变化源
+///
+/// 发现服务在产生事件时交给变化源归一化，订阅者通过变化流接收结果。
+/// 同一个变化源可以同时接收mDNS和UDP两种发现服务的事件。
+pub struct DeviceChangeFeed {
+    /// 已知设备
+    known: Mutex<KnownDevices>,
+    /// 变化广播
+    sender: broadcast::Sender<DeviceChange>,
+}
+
+impl Default for DeviceChangeFeed {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEVICE_CHANGE_CAPACITY)
+    }
+}
+
+impl DeviceChangeFeed {
+    /// 创建设备变化源
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 每个订阅者最多缓存的变化数量，落后过多的订阅者会跳过最旧的变化
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            known: Mutex::new(KnownDevices::default()),
+            sender,
+        }
+    }
+
+    /// 订阅设备变化
+    ///
+    /// 只包含订阅之后发生的变化，当前设备列表可通过 [`DeviceChangeFeed::devices`] 获取
+    ///
+    /// # 返回值
+    ///
+    /// 返回设备变化流，变化源被释放后流结束
+    pub fn device_changes(&self) -> impl Stream<Item = DeviceChange> + Send + 'static {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => return Some((change, receiver)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("设备变化订阅者落后，跳过 {} 条变化", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// 获取当前已知的设备列表
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.known.lock()
+            .map(|known| known.devices.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 按能力筛选当前已知的设备
+    ///
+    /// # 参数
+    ///
+    /// * `capability` - 设备能力
+    ///
+    /// # 返回值
+    ///
+    /// 返回声明了该能力的设备，按设备ID排列
+    pub fn discover_by_capability(&self, capability: Capability) -> Vec<DeviceInfo> {
+        let mut devices: Vec<DeviceInfo> = self.devices().into_iter()
+            .filter(|device| device.has_capability(&capability))
+            .collect();
+        devices.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        devices
+    }
+
+    /// 处理mDNS发现事件
+    ///
+    /// # 参数
+    ///
+    /// * `event` - mDNS发现事件
+    ///
+    /// # 返回值
+    ///
+    /// 返回事件产生的设备变化，与设备无关或没有实际变化的事件返回 `None`
+    pub fn apply_mdns_event(&self, event: &MdnsDiscoveryEvent) -> Option<DeviceChange> {
+        match event {
+            MdnsDiscoveryEvent::DeviceDiscovered(service)
+            | MdnsDiscoveryEvent::DeviceUpdated(_, service) => {
+                let device = Self::device_from_service(service)?;
+                let mut known = self.known.lock().ok()?;
+                known.mdns_names.insert(service.service_name.clone(), device.device_id.clone());
+                let change = Self::upsert(&mut known, device);
+                drop(known);
+                self.publish(change)
+            }
+            MdnsDiscoveryEvent::DeviceRemoved(service_name) => {
+                let mut known = self.known.lock().ok()?;
+                let device_id = known.mdns_names.remove(service_name)?;
+                let change = known.devices.remove(&device_id).map(DeviceChange::Removed);
+                drop(known);
+                self.publish(change)
+            }
+            _ => None,
+        }
+    }
+
+    /// 处理UDP广播发现事件
+    ///
+    /// # 参数
+    ///
+    /// * `event` - UDP发现服务的设备事件
+    ///
+    /// # 返回值
+    ///
+    /// 返回事件产生的设备变化，没有实际变化时返回 `None`
+    pub fn apply_device_event(&self, event: &DeviceEvent) -> Option<DeviceChange> {
+        let mut known = self.known.lock().ok()?;
+        let change = match event {
+            DeviceEvent::DeviceOnline(device) | DeviceEvent::DeviceUpdated(device) => {
+                Self::upsert(&mut known, device.clone())
+            }
+            DeviceEvent::DeviceOffline(device_id) => {
+                known.mdns_names.retain(|_, id| id != device_id);
+                known.devices.remove(device_id).map(DeviceChange::Removed)
+            }
+        };
+        drop(known);
+        self.publish(change)
+    }
+
+    /// 新增或更新设备，设备信息（除活跃时间外）未变化时不产生变化
+    fn upsert(known: &mut KnownDevices, device: DeviceInfo) -> Option<DeviceChange> {
+        match known.devices.get(&device.device_id) {
+            Some(existing) if Self::same_device(existing, &device) => {
+                known.devices.insert(device.device_id.clone(), device);
+                None
+            }
+            Some(_) => {
+                known.devices.insert(device.device_id.clone(), device.clone());
+                Some(DeviceChange::Updated(device))
+            }
+            None => {
+                known.devices.insert(device.device_id.clone(), device.clone());
+                Some(DeviceChange::Added(device))
+            }
+        }
+    }
+
+    /// 比较设备信息，忽略最后活跃时间
+    fn same_device(a: &DeviceInfo, b: &DeviceInfo) -> bool {
+        a.device_name == b.device_name
+            && a.device_type == b.device_type
+            && a.address == b.address
+            && a.capabilities == b.capabilities
+    }
+
+    fn publish(&self, change: Option<DeviceChange>) -> Option<DeviceChange> {
+        if let Some(change) = &change {
+            debug!("设备变化: {:?}", change);
+            // 没有订阅者时发送失败是正常的
+            let _ = self.sender.send(change.clone());
+        }
+        change
+    }
+
+    /// 从mDNS服务信息构造设备信息
+    ///
+    /// 设备ID、名称、类型和能力取自TXT记录，缺失时使用服务名称；服务没有地址时返回 `None`
+    pub(crate) fn device_from_service(service: &MdnsServiceInfo) -> Option<DeviceInfo> {
+        let txt = |key: &str| {
+            service.txt_records.iter()
+                .find_map(|record| record.strip_prefix(key)?.strip_prefix('='))
+                .map(str::to_string)
+        };
+        let ip = *service.addresses.first()?;
+
+        Some(DeviceInfo {
+            device_id: txt("device_id").unwrap_or_else(|| service.service_name.clone()),
+            device_name: txt("device_name").unwrap_or_else(|| service.service_name.clone()),
+            device_type: txt("device_type").unwrap_or_default(),
+            address: SocketAddr::new(ip, service.port),
+            capabilities: txt(CAPABILITIES_TXT_KEY)
+                .map(|capabilities| parse_capabilities(&capabilities))
+                .unwrap_or_default(),
+            last_active: SystemTime::now(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mdns_discovery::MdnsDiscovery;
+    use futures::StreamExt;
+    use std::net::IpAddr;
+    use std::time::Duration;
+
+    fn mdns_service(name: &str, ip: &str, port: u16) -> MdnsServiceInfo {
+        let ip: IpAddr = ip.parse().expect("地址解析失败");
+        MdnsDiscovery::create_default_device_info(
+            format!("{}-id", name),
+            name.to_string(),
+            "desktop".to_string(),
+            port,
+            vec![ip],
+        )
+    }
+
+    async fn next_change<S: Stream<Item = DeviceChange> + Unpin>(stream: &mut S) -> DeviceChange {
+        tokio::time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .expect("应收到设备变化")
+            .expect("变化流已结束")
+    }
+
+    #[tokio::test]
+    async fn test_mdns_discovered_then_removed() {
+        let feed = DeviceChangeFeed::default();
+        let mut first = Box::pin(feed.device_changes());
+        let mut second = Box::pin(feed.device_changes());
+
+        let service = mdns_service("laptop", "192.168.1.20", 8080);
+        feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceDiscovered(service.clone()));
+        // 重复发现不产生变化
+        assert!(feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceDiscovered(service.clone())).is_none());
+
+        let moved = mdns_service("laptop", "192.168.1.21", 8080);
+        feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceDiscovered(moved));
+        feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceRemoved("laptop".to_string()));
+        // 与设备无关的事件被忽略
+        assert!(feed.apply_mdns_event(&MdnsDiscoveryEvent::QueryCompleted).is_none());
+
+        for stream in [&mut first, &mut second] {
+            let added = next_change(stream).await;
+            let DeviceChange::Added(device) = &added else {
+                panic!("应为新增事件: {:?}", added);
+            };
+            assert_eq!(device.device_id, "laptop-id");
+            assert_eq!(device.device_name, "laptop");
+            assert_eq!(device.device_type, "desktop");
+            assert_eq!(device.address, "192.168.1.20:8080".parse().expect("地址解析失败"));
+            assert_eq!(device.capabilities, vec!["clipboard", "file_transfer", "messaging"]);
+
+            let updated = next_change(stream).await;
+            assert!(matches!(updated, DeviceChange::Updated(_)), "应为更新事件: {:?}", updated);
+            assert_eq!(updated.device().address, "192.168.1.21:8080".parse().expect("地址解析失败"));
+
+            let removed = next_change(stream).await;
+            assert!(matches!(removed, DeviceChange::Removed(_)), "应为移除事件: {:?}", removed);
+            assert_eq!(removed.device().device_id, "laptop-id");
+            assert_eq!(removed.device().address, "192.168.1.21:8080".parse().expect("地址解析失败"));
+        }
+
+        assert!(feed.devices().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_udp_events_share_device_list() {
+        let feed = DeviceChangeFeed::default();
+        let mut changes = Box::pin(feed.device_changes());
+
+        // mDNS发现的设备通过UDP下线
+        feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceDiscovered(mdns_service("phone", "10.0.0.5", 9000)));
+        let mut device = feed.devices().pop().expect("应有已知设备");
+        device.last_active = SystemTime::now() + Duration::from_secs(5);
+        assert!(feed.apply_device_event(&DeviceEvent::DeviceUpdated(device)).is_none(), "仅活跃时间变化不产生事件");
+        feed.apply_device_event(&DeviceEvent::DeviceOffline("phone-id".to_string()));
+
+        assert!(matches!(next_change(&mut changes).await, DeviceChange::Added(_)));
+        let removed = next_change(&mut changes).await;
+        assert!(matches!(removed, DeviceChange::Removed(_)));
+        assert_eq!(removed.device().device_name, "phone");
+
+        // 已移除的设备再次移除不产生事件
+        assert!(feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceRemoved("phone".to_string())).is_none());
+        assert!(feed.apply_device_event(&DeviceEvent::DeviceOffline("phone-id".to_string())).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_discover_by_capability() {
+        let feed = DeviceChangeFeed::default();
+
+        // 能力声明顺序不同时TXT记录相同
+        let storage_capabilities = [Capability::StorageContribution, Capability::FileTransfer];
+        let txt = crate::capability::capabilities_txt_record(&storage_capabilities);
+        assert_eq!(txt, crate::capability::capabilities_txt_record(&[Capability::FileTransfer, Capability::StorageContribution]));
+        assert_eq!(txt, "capabilities=file_transfer,storage_contribution");
+
+        let mut nas = mdns_service("nas", "192.168.1.30", 8080);
+        nas.txt_records.retain(|record| !record.starts_with("capabilities="));
+        nas.txt_records.push(txt);
+        feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceDiscovered(nas));
+        feed.apply_mdns_event(&MdnsDiscoveryEvent::DeviceDiscovered(mdns_service("laptop", "192.168.1.20", 8080)));
+
+        // UDP公告携带的能力同样参与筛选
+        feed.apply_device_event(&DeviceEvent::DeviceOnline(DeviceInfo {
+            device_id: "backup-id".to_string(),
+            device_name: "backup".to_string(),
+            device_type: "server".to_string(),
+            address: "192.168.1.40:8080".parse().expect("地址解析失败"),
+            capabilities: crate::capability::capability_tokens(&[Capability::StorageContribution]),
+            last_active: SystemTime::now(),
+        }));
+
+        let storage: Vec<String> = feed.discover_by_capability(Capability::StorageContribution)
+            .into_iter().map(|device| device.device_id).collect();
+        assert_eq!(storage, vec!["backup-id", "nas-id"]);
+
+        let file_transfer: Vec<String> = feed.discover_by_capability(Capability::FileTransfer)
+            .into_iter().map(|device| device.device_id).collect();
+        assert_eq!(file_transfer, vec!["laptop-id", "nas-id"]);
+        assert!(feed.discover_by_capability(Capability::CertificateManagement).is_empty());
+    }
+}
@@ -14,12 +14,18 @@
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use std::net::{SocketAddr, IpAddr};
 use std::sync::Arc;
-use std::collections::HashMap;
-use std::time::Duration;
-use tokio::sync::{RwLock, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
+use tokio::task::JoinSet;
 use tracing::{debug, info, warn};
-use bey_transport::{SecureTransport, TransportConfig};
+use bey_transport::{SecureTransport, TransportConfig, TransportEvent};
+use bey_transport::policy_engine::PolicySet;
 use bey_identity::{CertificateManager, CertificateData};
+use bey_types::{AuthMethod, ConnectionInfo, MessagePriority, ProtocolType, SecurityLevel};
 use sha2::{Sha256, Digest};
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
@@ -30,16 +36,44 @@ use base64::{Engine as _, engine::general_purpose};
 
 use crate::{
     NetResult,
-    token::{Token, TokenRouter, TokenHandler, TokenMeta},
+    token::{DeadLetter, Token, TokenRouter, TokenHandler, TokenMeta, TokenPriority},
     state_machine::{ConnectionStateMachine, StateEvent, ConnectionState},
-    receiver::{BufferedReceiver, MetaReceiver, ReceiverMode, create_receiver},
-    mdns_discovery::{MdnsDiscovery, MdnsDiscoveryConfig, MdnsServiceInfo},
+    receiver::{BufferedReceiver, InboundSender, MetaReceiver, OverflowPolicy, ReceiverMode},
+    mdns_discovery::{mdns_constants, DiscoveryIpMode, MdnsDiscovery, MdnsDiscoveryConfig, MdnsServiceInfo},
     stream::StreamManager,
     priority_queue::PriorityQueue,
+    qos::OutboundScheduler,
     flow_control::{FlowController, FlowControlStats},
-    metrics::{MetricsCollector, Metrics},
+    metrics::{MetricsCollector, Metrics, PeerMetrics},
+    path_selector::{AddressRtt, PathSelector, RttProbe},
+    replay::{ReplayCache, DEFAULT_REPLAY_CACHE_CAPACITY, DEFAULT_REPLAY_WINDOW},
+    peer_cache::{PeerCache, PersistedPeer, DEFAULT_PEER_CACHE_MAX_AGE},
+    topic::{TopicBus, TopicMessage},
+    channel::{self, Channel, ChannelId, ChannelRouter},
+    capability::{capabilities_txt_record, Capability},
+    device_changes::DeviceChangeFeed,
+    udp_discovery::DeviceInfo,
+    handshake::{self, ConnectionEvent, HandshakeOffer, NegotiatedConnection},
+    task_group::{TaskGroup, DEFAULT_TASK_SHUTDOWN_TIMEOUT},
+    tcp_fallback::{self, FrameReader, FrameWriter},
+    wire::{self, ChannelRoutes},
 };
 
+/// 确认令牌类型，负载为被确认令牌的ID
+pub const ACK_TOKEN_TYPE: &str = "bey.ack";
+
+/// 标记令牌需要接收方回复确认的属性
+const RELIABLE_ATTRIBUTE: &str = "reliable";
+
+/// 单个地址RTT探测的超时时间
+const RTT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 服务端检查新入站连接并响应握手的间隔
+const HANDSHAKE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// TCP回退连接的帧发送端（对端地址 -> 发送端）
+type TcpConnections = RwLock<HashMap<SocketAddr, Arc<Mutex<FrameWriter>>>>;
+
 /// 传输引擎配置
 #[derive(Debug, Clone)]
 pub struct EngineConfig {
@@ -49,6 +83,8 @@ pub struct EngineConfig {
     pub port: u16,
     /// 接收器缓冲区大小
     pub receiver_buffer_size: usize,
+    /// 接收器缓冲区满时的溢出策略
+    pub receiver_overflow_policy: OverflowPolicy,
     /// 是否启用认证
     pub enable_auth: bool,
     /// 是否启用加密
@@ -57,12 +93,20 @@ pub struct EngineConfig {
     pub enable_mdns: bool,
     /// mDNS服务类型
     pub mdns_service_type: String,
+    /// mDNS记录的TTL（秒）
+    pub mdns_ttl: u32,
     /// 传输层配置
     pub transport_config: TransportConfig,
-    /// 优先级队列配置
+    /// 是否启用TCP回退：UDP被阻断时通过TCP建立连接
+    pub enable_tcp_fallback: bool,
+    /// TCP回退监听端口，为 `None` 时使用与QUIC相同的端口号
+    pub tcp_fallback_port: Option<u16>,
+    /// 可靠发送等待确认的超时时间，超时后重传令牌
     pub ack_timeout: Duration,
-    /// 最大重试次数
-    pub max_retries: u32,
+    /// 可靠发送的最大重传次数，超过后发送失败
+    pub max_retransmits: u32,
+    /// 多地址设备的RTT重新探测间隔
+    pub rtt_probe_interval: Duration,
     /// 初始窗口大小
     pub initial_window: usize,
     /// 最大窗口大小
@@ -73,6 +117,16 @@ pub struct EngineConfig {
     pub token_pool_size: usize,
     /// 是否启用零拷贝优化
     pub enable_zero_copy: bool,
+    /// 重放检测的时间窗口，窗口内重复收到的令牌被丢弃，为零时不检测
+    pub replay_window: Duration,
+    /// 重放检测最多记录的令牌数
+    pub replay_cache_capacity: usize,
+    /// 已知设备缓存文件路径，为 `None` 时不持久化已发现的设备
+    pub peer_cache_path: Option<PathBuf>,
+    /// 已知设备缓存的最大保留时间，超过后不再加载
+    pub peer_cache_max_age: Duration,
+    /// 在mDNS记录中声明的设备能力
+    pub capabilities: Vec<Capability>,
 }
 
 impl Default for EngineConfig {
@@ -81,18 +135,28 @@ impl Default for EngineConfig {
             name: "bey-engine".to_string(),
             port: 8080,
             receiver_buffer_size: 1000,
+            receiver_overflow_policy: OverflowPolicy::default(),
             enable_auth: true,
             enable_encryption: true,
             enable_mdns: true,
             mdns_service_type: "_bey._tcp".to_string(),
+            mdns_ttl: mdns_constants::DEFAULT_TTL,
             transport_config: TransportConfig::default(),
+            enable_tcp_fallback: false,
+            tcp_fallback_port: None,
             ack_timeout: Duration::from_secs(5),
-            max_retries: 3,
+            max_retransmits: 3,
+            rtt_probe_interval: Duration::from_secs(30),
             initial_window: 65536,      // 64KB
             max_window: 1048576,        // 1MB
             stream_chunk_size: 65536,   // 64KB
             token_pool_size: 100,       // 预分配100个令牌槽位
             enable_zero_copy: true,     // 启用零拷贝优化
+            replay_window: DEFAULT_REPLAY_WINDOW,
+            replay_cache_capacity: DEFAULT_REPLAY_CACHE_CAPACITY,
+            peer_cache_path: None,
+            peer_cache_max_age: DEFAULT_PEER_CACHE_MAX_AGE,
+            capabilities: vec![Capability::Messaging, Capability::FileTransfer, Capability::ClipboardSync],
         }
     }
 }
@@ -111,6 +175,104 @@ struct DeviceEntry {
     cert_fingerprint: Option<String>,
     /// 最后活跃时间
     last_seen: std::time::SystemTime,
+    /// 从已知设备缓存加载、本次运行尚未重新发现
+    stale: bool,
+}
+
+/// 新发现的设备超过该时间未见即视为离开
+const DEVICE_EXPIRY: Duration = Duration::from_secs(30);
+
+/// 新发现设备的过期时间
+///
+/// 至少为 [`DEVICE_EXPIRY`]，查询间隔变长后保证设备在两轮查询都未应答才过期
+fn device_expiry(query_interval: Duration) -> Duration {
+    DEVICE_EXPIRY.max(query_interval * 2)
+}
+
+impl DeviceEntry {
+    /// 从已知设备缓存恢复的设备条目
+    fn from_persisted(peer: PersistedPeer) -> Self {
+        Self {
+            name: peer.name,
+            addresses: peer.addresses,
+            authenticated: false,
+            cert_fingerprint: peer.cert_fingerprint,
+            last_seen: peer.last_seen,
+            stale: true,
+        }
+    }
+
+    /// 设备是否已过期
+    ///
+    /// 新发现的设备超过 `expiry` 未见即过期；缓存加载的设备在重新发现之前保留到最大保留时间
+    fn is_expired(&self, now: SystemTime, expiry: Duration, stale_max_age: Duration) -> bool {
+        let max_age = if self.stale { stale_max_age } else { expiry };
+        now.duration_since(self.last_seen).is_ok_and(|elapsed| elapsed > max_age)
+    }
+}
+
+/// 已发现设备的快照（不含本机），用于写入已知设备缓存
+fn persisted_peers(devices: &HashMap<String, DeviceEntry>, own_name: &str) -> Vec<PersistedPeer> {
+    devices.iter()
+        .filter(|(name, _)| name.as_str() != own_name)
+        .map(|(name, entry)| PersistedPeer {
+            name: name.clone(),
+            addresses: entry.addresses.clone(),
+            cert_fingerprint: entry.cert_fingerprint.clone(),
+            last_seen: entry.last_seen,
+        })
+        .collect()
+}
+
+/// 将设备快照合并到已知设备缓存
+///
+/// 文件读写在阻塞线程中进行，调用方不应持有设备表的锁
+async fn persist_devices(cache: &PeerCache, peers: Vec<PersistedPeer>, max_age: Duration) -> NetResult<()> {
+    let cache = cache.clone();
+    tokio::task::spawn_blocking(move || cache.merge(&peers, max_age)).await
+        .map_err(|e| ErrorInfo::new(4337, format!("写入已知设备缓存失败: {}", e))
+            .with_category(ErrorCategory::Storage)
+            .with_severity(ErrorSeverity::Warning))?
+}
+
+/// 基于传输层连接的RTT探测器
+///
+/// 通过连接池建立（或复用）到目标地址的连接，读取连接的RTT估计值
+struct TransportRttProbe {
+    transport: Arc<RwLock<SecureTransport>>,
+}
+
+#[async_trait::async_trait]
+impl RttProbe for TransportRttProbe {
+    async fn probe(&self, addr: SocketAddr) -> Option<Duration> {
+        let transport = self.transport.read().await;
+        match tokio::time::timeout(RTT_PROBE_TIMEOUT, transport.connect(addr)).await {
+            Ok(Ok(connection)) => Some(connection.rtt()),
+            Ok(Err(e)) => {
+                debug!("探测地址 {} 失败: {}", addr, e);
+                None
+            }
+            Err(_) => {
+                debug!("探测地址 {} 超时", addr);
+                None
+            }
+        }
+    }
+}
+
+/// 命名监听器信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListenerInfo {
+    /// 监听器名称
+    pub name: String,
+    /// 实际绑定的本地地址
+    pub local_addr: Option<SocketAddr>,
+    /// 使用的策略集合ID
+    pub policy_set_id: String,
+    /// 是否要求客户端出示证书
+    pub require_client_cert: bool,
+    /// 已接受的活跃连接数量
+    pub accepted_connections: usize,
 }
 
 /// 网络传输引擎
@@ -131,20 +293,50 @@ pub struct TransportEngine {
     router: Arc<TokenRouter>,
     /// 令牌接收器
     receiver: Arc<BufferedReceiver>,
+    /// 入站令牌的重放缓存
+    replay_cache: Arc<ReplayCache>,
     /// 发送通道（内部使用）
-    _sender: mpsc::UnboundedSender<Token>,
+    _sender: InboundSender,
     /// 已发现的设备映射（设备名 -> 设备信息）
     discovered_devices: Arc<RwLock<HashMap<String, DeviceEntry>>>,
+    /// 已知设备缓存
+    peer_cache: Option<PeerCache>,
     /// 主加密密钥（从证书派生）
     master_key: Arc<RwLock<Option<Vec<u8>>>>,
     /// 优先级队列
     priority_queue: Arc<PriorityQueue>,
+    /// 按优先级调度出站令牌
+    outbound: OutboundScheduler,
     /// 流量控制器
     flow_controller: Arc<FlowController>,
     /// 流管理器
     stream_manager: Arc<StreamManager>,
     /// 性能指标收集器
     metrics: Arc<MetricsCollector>,
+    /// 服务器是否正在运行
+    running: Arc<AtomicBool>,
+    /// 新设备发现事件发送端
+    discovery_events: broadcast::Sender<String>,
+    /// 附加的命名监听器（监听器名称 -> 传输层）
+    listeners: RwLock<HashMap<String, SecureTransport>>,
+    /// 多地址设备的路径选择器
+    path_selector: Arc<PathSelector>,
+    /// 主题发布订阅总线
+    topics: Arc<TopicBus>,
+    /// 逻辑通道路由器
+    channels: Arc<ChannelRouter>,
+    /// 经由逻辑通道发送的令牌类型
+    channel_routes: Arc<ChannelRoutes>,
+    /// 握手协商后的连接（对端地址 -> 协商结果）
+    connection_infos: Arc<RwLock<HashMap<SocketAddr, NegotiatedConnection>>>,
+    /// 连接握手事件发送端
+    connection_events: broadcast::Sender<ConnectionEvent>,
+    /// 已完成握手和密钥交换的TCP回退连接
+    tcp_connections: Arc<TcpConnections>,
+    /// 引擎生命周期内的维护任务，引擎被丢弃时退出
+    maintenance_tasks: TaskGroup,
+    /// 服务器运行期间的任务（握手响应、连接接受和设备发现），停止服务器时退出
+    server_tasks: TaskGroup,
 }
 
 impl TransportEngine {
@@ -190,11 +382,13 @@ impl TransportEngine {
         // 创建状态机
         let state_machine = Arc::new(RwLock::new(ConnectionStateMachine::new()));
 
-        // 创建令牌路由器
+        // 创建令牌路由器并注册主题总线
         let router = Arc::new(TokenRouter::new());
+        let topics = Arc::new(TopicBus::new(config.name.clone()));
+        router.register_handler(Arc::clone(&topics) as Arc<dyn TokenHandler>).await?;
 
         // 创建接收器
-        let (sender, receiver) = create_receiver(config.receiver_buffer_size);
+        let (sender, receiver) = BufferedReceiver::new(config.receiver_buffer_size, config.receiver_overflow_policy);
 
         // 初始化mDNS发现（如果启用）
         let mdns_discovery = if config.enable_mdns {
@@ -213,14 +407,31 @@ impl TransportEngine {
         };
 
         // 初始化高级组件
-        let priority_queue = Arc::new(PriorityQueue::new(config.ack_timeout, config.max_retries));
+        let priority_queue = Arc::new(PriorityQueue::new(config.ack_timeout, config.max_retransmits));
         let flow_controller = Arc::new(FlowController::new(config.initial_window, config.max_window));
         let stream_manager = Arc::new(StreamManager::new(config.stream_chunk_size));
         let metrics = Arc::new(MetricsCollector::new());
+        let replay_cache = Arc::new(ReplayCache::new(config.replay_window, config.replay_cache_capacity));
+
+        // 加载上次运行保存的已知设备，发现完成前即可直接连接
+        let peer_cache = config.peer_cache_path.as_ref().map(PeerCache::new);
+        let mut discovered_devices = HashMap::new();
+        if let Some(cache) = &peer_cache {
+            match cache.load(config.peer_cache_max_age) {
+                Ok(peers) => {
+                    info!("从缓存加载 {} 个已知设备", peers.len());
+                    for peer in peers {
+                        discovered_devices.insert(peer.name.clone(), DeviceEntry::from_persisted(peer));
+                    }
+                }
+                Err(e) => warn!("加载已知设备缓存失败: {}", e),
+            }
+        }
 
         // 启动后台维护任务（在后台运行）
+        let maintenance_tasks = TaskGroup::new("引擎维护任务");
         let _stream_manager_clone = Arc::clone(&stream_manager);
-        tokio::spawn(async move {
+        maintenance_tasks.spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(60));
             loop {
                 interval.tick().await;
@@ -228,22 +439,43 @@ impl TransportEngine {
             }
         });
 
+        let transport = Arc::new(RwLock::new(transport));
+        let path_selector = Arc::new(PathSelector::new(
+            Arc::new(TransportRttProbe { transport: Arc::clone(&transport) }),
+            config.rtt_probe_interval,
+        ));
+
         // 启动后台任务
         let engine = Self {
             config,
-            transport: Arc::new(RwLock::new(transport)),
+            transport,
             mdns_discovery,
             cert_manager,
             state_machine,
             router,
             receiver: Arc::new(receiver),
+            replay_cache,
             _sender: sender,
-            discovered_devices: Arc::new(RwLock::new(HashMap::new())),
+            discovered_devices: Arc::new(RwLock::new(discovered_devices)),
+            peer_cache,
             master_key: Arc::new(RwLock::new(None)),
+            outbound: OutboundScheduler::new(Arc::clone(&priority_queue)),
             priority_queue,
             flow_controller,
             stream_manager,
             metrics,
+            running: Arc::new(AtomicBool::new(false)),
+            discovery_events: broadcast::channel(64).0,
+            listeners: RwLock::new(HashMap::new()),
+            path_selector,
+            topics,
+            channels: Arc::new(ChannelRouter::new()),
+            channel_routes: Arc::new(ChannelRoutes::default()),
+            connection_infos: Arc::new(RwLock::new(HashMap::new())),
+            connection_events: broadcast::channel(64).0,
+            tcp_connections: Arc::new(RwLock::new(HashMap::new())),
+            maintenance_tasks,
+            server_tasks: TaskGroup::new("引擎服务器任务"),
         };
 
         // 启动后台维护任务
@@ -262,6 +494,47 @@ impl TransportEngine {
         
         // 启动自动接收循环
         self.start_auto_receive_loop().await;
+
+        // 启动多地址RTT探测任务
+        self.start_path_probe_task().await;
+    }
+
+    /// 启动多地址RTT探测任务
+    ///
+    /// 定期重新探测已发现设备的所有地址，使新连接始终选择当前最快的路径
+    async fn start_path_probe_task(&self) {
+        let probe_interval = self.config.rtt_probe_interval;
+        if probe_interval.is_zero() {
+            return;
+        }
+
+        let path_selector = Arc::clone(&self.path_selector);
+        let discovered_devices = Arc::clone(&self.discovered_devices);
+        let metrics = Arc::clone(&self.metrics);
+
+        self.maintenance_tasks.spawn(async move {
+            let mut interval = tokio::time::interval(probe_interval);
+            loop {
+                interval.tick().await;
+
+                let devices: Vec<(String, Vec<SocketAddr>)> = discovered_devices.read().await
+                    .iter()
+                    .map(|(name, entry)| (name.clone(), entry.addresses.clone()))
+                    .collect();
+
+                for (name, addresses) in &devices {
+                    if addresses.len() > 1 {
+                        let measurements = path_selector.probe_device(name, addresses).await;
+                        if let Some(rtt) = measurements.iter().filter_map(|m| m.rtt).min() {
+                            metrics.update_peer_rtt(name, rtt).await;
+                        }
+                    }
+                }
+
+                let names: Vec<String> = devices.into_iter().map(|(name, _)| name).collect();
+                path_selector.retain_devices(&names).await;
+            }
+        });
     }
     
     /// 启动自动接收循环
@@ -272,10 +545,15 @@ impl TransportEngine {
         let router = Arc::clone(&self.router);
         let metrics = Arc::clone(&self.metrics);
         let master_key = Arc::clone(&self.master_key);
+        let priority_queue = Arc::clone(&self.priority_queue);
         let config = self.config.clone();
         let sender = self._sender.clone();  // 用于发送响应令牌
+        let replay_cache = Arc::clone(&self.replay_cache);
+        let transport = Arc::clone(&self.transport);
+        let tcp_connections = Arc::clone(&self.tcp_connections);
+        let channel_routes = Arc::clone(&self.channel_routes);
         
-        tokio::spawn(async move {
+        self.maintenance_tasks.spawn(async move {
             info!("自动接收循环已启动");
             
             loop {
@@ -297,17 +575,45 @@ impl TransportEngine {
                             }
                         }
                         
-                        // 记录接收指标
-                        metrics.record_receive(token.payload.len()).await;
+                        // 记录接收指标：经连接收到的令牌的发送方已按握手改写为认证的对端，
+                        // 本地投递的令牌自称的发送方不可信，只计入总量
+                        if wire::peer_addr(&token).is_some() {
+                            metrics.record_peer_receive(&token.meta.sender_id, token.payload.len()).await;
+                        } else {
+                            metrics.record_receive(token.payload.len()).await;
+                        }
+
+                        // 确认令牌：唤醒等待确认的可靠发送，只接受令牌接收方发来的确认
+                        if token.meta.token_type == ACK_TOKEN_TYPE {
+                            let token_id = String::from_utf8_lossy(&token.payload).to_string();
+                            if let Err(e) = priority_queue.acknowledge_from(&token_id, &token.meta.sender_id).await {
+                                debug!("忽略确认令牌: {}", e);
+                            }
+                            continue;
+                        }
+
+                        // 可靠发送的令牌：回复确认
+                        if token.meta.attributes.get(RELIABLE_ATTRIBUTE).is_some_and(|value| value == "true") {
+                            let mut ack = Token::response(&token, token.meta.id.as_bytes().to_vec());
+                            ack.meta.token_type = ACK_TOKEN_TYPE.to_string();
+                            Self::reply(&transport, &tcp_connections, &channel_routes, &sender, &token, ack);
+                        }
+
+                        // 窗口内重复收到的令牌视为重放，不再交给处理器；
+                        // 可靠发送的重传令牌已在上面重新确认
+                        if replay_cache.check_and_record(&token.meta.sender_id, &token.meta.id) {
+                            warn!("丢弃重放令牌: {} (发送方: {})", token.meta.id, token.meta.sender_id);
+                            metrics.record_replay().await;
+                            continue;
+                        }
                         
                         // 路由到处理器
+                        let request = token.clone();
                         match router.route_token(token).await {
                             Ok(Some(response_token)) => {
                                 // 处理器返回了响应令牌，发送回去
                                 debug!("处理器返回了响应令牌: {}", response_token.meta.id);
-                                if let Err(e) = sender.send(response_token) {
-                                    warn!("发送响应令牌失败: {}", e);
-                                }
+                                Self::reply(&transport, &tcp_connections, &channel_routes, &sender, &request, response_token);
                             }
                             Ok(None) => {
                                 debug!("令牌处理完成，无响应");
@@ -330,6 +636,35 @@ impl TransportEngine {
         });
     }
     
+    /// 回复收到的令牌
+    ///
+    /// 经连接收到的令牌把回复发回来源连接，本地投递的令牌把回复放回入站队列。
+    /// 接收循环是入站队列唯一的消费者，不能等待自身队列的空间，也不等待回复发送完成
+    fn reply(
+        transport: &Arc<RwLock<SecureTransport>>,
+        tcp_connections: &Arc<TcpConnections>,
+        channel_routes: &Arc<ChannelRoutes>,
+        sender: &InboundSender,
+        request: &Token,
+        reply: Token,
+    ) {
+        let Some(peer_addr) = wire::peer_addr(request) else {
+            if let Err(e) = sender.try_send(reply) {
+                warn!("发送响应令牌失败: {}", e);
+            }
+            return;
+        };
+
+        let transport = Arc::clone(transport);
+        let tcp_connections = Arc::clone(tcp_connections);
+        let channel_routes = Arc::clone(channel_routes);
+        tokio::spawn(async move {
+            if let Err(e) = Self::transmit(&transport, &tcp_connections, &channel_routes, peer_addr, &reply).await {
+                warn!("回复令牌 {} 失败: {}", reply.meta.id, e);
+            }
+        });
+    }
+
     /// 静态方法：解密令牌（用于后台任务）
     async fn decrypt_token_static(
         token: &Token,
@@ -395,15 +730,19 @@ impl TransportEngine {
         let priority_queue = Arc::clone(&self.priority_queue);
         let metrics = Arc::clone(&self.metrics);
 
-        tokio::spawn(async move {
+        self.maintenance_tasks.spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(1));
             loop {
                 interval.tick().await;
                 
-                // 检查超时
-                let timeout_count = priority_queue.check_timeouts().await;
-                if timeout_count > 0 {
-                    metrics.record_timeout().await;
+                // 检查超时，按接收方统计重传和超时
+                for timeout in priority_queue.check_timeouts().await {
+                    match (timeout.receiver_id.as_deref(), timeout.retried) {
+                        (Some(peer), true) => metrics.record_peer_retransmit(peer).await,
+                        (Some(peer), false) => metrics.record_peer_timeout(peer).await,
+                        (None, true) => metrics.record_retransmit().await,
+                        (None, false) => metrics.record_timeout().await,
+                    }
                 }
             }
         });
@@ -415,8 +754,9 @@ impl TransportEngine {
         let priority_queue = Arc::clone(&self.priority_queue);
         let discovered_devices = Arc::clone(&self.discovered_devices);
         let _stream_manager = Arc::clone(&self.stream_manager);
+        let receiver = Arc::clone(&self.receiver);
 
-        tokio::spawn(async move {
+        self.maintenance_tasks.spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(5));
             loop {
                 interval.tick().await;
@@ -427,6 +767,7 @@ impl TransportEngine {
                 // 更新队列大小
                 let queue_size = priority_queue.size().await;
                 metrics.update_queue_size(queue_size).await;
+                metrics.update_inbound_dropped(receiver.dropped_count()).await;
                 
                 // 更新连接数
                 let device_count = discovered_devices.read().await.len();
@@ -447,14 +788,18 @@ impl TransportEngine {
             port: config.port,
             priority: 0,
             weight: 0,
-            default_ttl: 120,
+            default_ttl: config.mdns_ttl,
             query_interval: std::time::Duration::from_secs(30),
+            min_query_interval: mdns_constants::MIN_QUERY_INTERVAL,
+            max_query_interval: mdns_constants::MAX_QUERY_INTERVAL,
             device_timeout: std::time::Duration::from_secs(60),
             max_retries: 3,
             enable_cache: true,
             cache_size_limit: 100,
-            enable_ipv6: false,
+            ip_mode: DiscoveryIpMode::V4Only,
             event_queue_size: 100,
+            subtypes: Vec::new(),
+            probe_interval: mdns_constants::PROBE_INTERVAL,
         };
 
         // 获取本机IP地址列表
@@ -473,8 +818,14 @@ impl TransportEngine {
             txt_records: vec![
                 format!("version={}", env!("CARGO_PKG_VERSION")),
                 "protocol=bey".to_string(),
-            ],
-            ttl: 120,
+                capabilities_txt_record(&config.capabilities),
+            ].into_iter()
+                .chain(config.enable_tcp_fallback.then(|| {
+                    tcp_fallback::tcp_port_txt_record(config.tcp_fallback_port.unwrap_or(config.port))
+                }))
+                .collect(),
+            ttl: config.mdns_ttl,
+            subtypes: Vec::new(),
         };
 
         // 创建mDNS发现服务
@@ -527,13 +878,30 @@ impl TransportEngine {
         // 启动传输层服务器
         {
             let mut transport = self.transport.write().await;
-            transport.start_server().await.map_err(|e| {
+            let result = transport.start_server().await.map_err(|e| {
                 ErrorInfo::new(4303, format!("启动传输层服务器失败: {}", e))
                     .with_category(ErrorCategory::Network)
                     .with_severity(ErrorSeverity::Error)
-            })?;
+            });
+            self.track_error(result).await?;
         }
 
+        // 绑定TCP回退监听器，未指定端口时使用与QUIC相同的端口号
+        let tcp_listener = if self.config.enable_tcp_fallback {
+            let quic_port = self.transport.read().await.local_addr()
+                .map(|addr| addr.port())
+                .unwrap_or(self.config.port);
+            let tcp_addr = SocketAddr::new(
+                self.config.transport_config.bind_address(),
+                self.config.tcp_fallback_port.unwrap_or(quic_port),
+            );
+            let listener = self.track_error(tcp_fallback::bind(tcp_addr).await).await?;
+            info!("TCP回退监听器已启动: {}", tcp_addr);
+            Some(listener)
+        } else {
+            None
+        };
+
         // 更新状态
         {
             let mut sm = self.state_machine.write().await;
@@ -542,11 +910,12 @@ impl TransportEngine {
 
         // 启动mDNS发现
         if let Some(mdns) = &self.mdns_discovery {
-            mdns.start().await.map_err(|e| {
+            let result = mdns.start().await.map_err(|e| {
                 ErrorInfo::new(4325, format!("启动mDNS发现失败: {}", e))
                     .with_category(ErrorCategory::Network)
                     .with_severity(ErrorSeverity::Warning)
-            })?;
+            });
+            self.track_error(result).await?;
             info!("mDNS发现服务已启动，广播服务: {}", self.config.name);
 
             // 启动设备发现监听任务
@@ -576,206 +945,974 @@ impl TransportEngine {
                 }
             }
         } else {
-            // 没有认证，直接进入已认证状态
+            // 没有认证，不校验证书直接进入已认证状态
             let mut sm = self.state_machine.write().await;
+            sm.handle_event(StateEvent::Authenticate)?;
             sm.handle_event(StateEvent::Authenticated)?;
         }
 
+        self.running.store(true, Ordering::SeqCst);
+        self.start_handshake_responder().await;
+        if let Some(listener) = tcp_listener {
+            self.start_tcp_fallback_acceptor(listener);
+        }
         info!("传输引擎服务器启动成功，监听端口: {}", self.config.port);
         Ok(())
     }
 
-    /// 连接到指定设备（通过设备名）
-    ///
-    /// # 参数
-    ///
-    /// * `device_name` - 目标设备名称
+    /// 本端的握手提议
     ///
-    /// # 返回值
-    ///
-    /// 返回连接结果或错误
-    pub async fn connect_to_device(&self, device_name: &str) -> NetResult<()> {
-        info!("连接到设备: {}", device_name);
-
-        // 首先查询mDNS获取设备地址
-        let device_addr = if let Some(mdns) = &self.mdns_discovery {
-            // 查询mDNS服务
-            let services = mdns.query_service(&self.config.mdns_service_type, None).await.map_err(|e| {
-                ErrorInfo::new(4326, format!("查询mDNS服务失败: {}", e))
-                    .with_category(ErrorCategory::Network)
-                    .with_severity(ErrorSeverity::Error)
-            })?;
+    /// 启用加密时只接受标准加密（传输层TLS加令牌加密），否则为基本加密（仅传输层TLS）；
+    /// 启用认证且证书管理器可用时要求证书认证，否则不认证
+    pub fn handshake_offer(&self) -> HandshakeOffer {
+        let security_level = if self.config.enable_encryption {
+            SecurityLevel::Standard
+        } else {
+            SecurityLevel::Basic
+        };
+        let auth_method = if self.config.enable_auth && self.cert_manager.is_some() {
+            AuthMethod::Certificate
+        } else {
+            AuthMethod::None
+        };
 
-            // 查找目标设备
-            let service = services.iter().find(|s| s.service_name == device_name)
-                .ok_or_else(|| {
-                    ErrorInfo::new(4327, format!("未找到设备: {}", device_name))
-                        .with_category(ErrorCategory::Network)
-                        .with_severity(ErrorSeverity::Warning)
-                })?;
+        HandshakeOffer {
+            engine_name: self.config.name.clone(),
+            security_levels: vec![security_level],
+            auth_methods: vec![auth_method],
+        }
+    }
 
-            // 获取第一个可用地址
-            let ip = service.addresses.first().ok_or_else(|| {
-                ErrorInfo::new(4328, format!("设备 {} 没有可用地址", device_name))
-                    .with_category(ErrorCategory::Network)
-                    .with_severity(ErrorSeverity::Warning)
-            })?;
+    /// 启动握手响应任务
+    ///
+    /// 定期检查新的入站连接，在每个连接的第一个双向流上响应客户端的握手提议，
+    /// 握手成功后在该连接上接受对端打开的逻辑通道；拒绝对端或握手超时后以
+    /// [`handshake::failure_close_code`] 给出的关闭码关闭连接
+    async fn start_handshake_responder(&self) {
+        let transport = Arc::clone(&self.transport);
+        let channels = Arc::clone(&self.channels);
+        let running = Arc::clone(&self.running);
+        let connection_infos = Arc::clone(&self.connection_infos);
+        let connection_events = self.connection_events.clone();
+        let inbound_tokens = self._sender.clone();
+        let topics = Arc::clone(&self.topics);
+        let offer = self.handshake_offer();
+        let fallback_addr = SocketAddr::new(self.config.transport_config.bind_address(), self.config.port);
+
+        self.server_tasks.spawn(async move {
+            let mut handled: HashSet<SocketAddr> = HashSet::new();
+            let mut handshakes = JoinSet::new();
+            let mut interval = tokio::time::interval(HANDSHAKE_POLL_INTERVAL);
+
+            while running.load(Ordering::SeqCst) {
+                interval.tick().await;
 
-            SocketAddr::new(*ip, service.port)
-        } else {
-            return Err(ErrorInfo::new(4329, "mDNS发现未启用".to_string())
-                .with_category(ErrorCategory::Configuration)
-                .with_severity(ErrorSeverity::Error));
-        };
+                let (local_addr, active, inbound) = {
+                    let transport = transport.read().await;
+                    let active = transport.active_connections().await;
+                    let mut inbound = Vec::new();
+                    for addr in &active {
+                        // 只有入站连接记录了信任级别
+                        if handled.contains(addr) || transport.peer_trust_level(*addr).await.is_none() {
+                            continue;
+                        }
+                        if let Some(connection) = transport.connection(*addr).await {
+                            inbound.push(connection);
+                        }
+                    }
+                    (transport.local_addr().unwrap_or(fallback_addr), active, inbound)
+                };
 
-        info!("设备 {} 解析到地址: {}", device_name, device_addr);
+                handled.retain(|addr| active.contains(addr));
+                // TCP回退连接由接受任务在连接关闭时清理
+                connection_infos.write().await
+                    .retain(|addr, negotiated| negotiated.info.protocol != ProtocolType::Quic || active.contains(addr));
+
+                for connection in inbound {
+                    let remote_addr = connection.remote_address();
+                    handled.insert(remote_addr);
+
+                    let connection_infos = Arc::clone(&connection_infos);
+                    let connection_events = connection_events.clone();
+                    let offer = offer.clone();
+                    let channels = Arc::clone(&channels);
+                    let inbound_tokens = inbound_tokens.clone();
+                    let topics = Arc::clone(&topics);
+                    let transport = Arc::clone(&transport);
+                    handshakes.spawn(async move {
+                        let result = handshake::respond(&connection, local_addr, &offer).await;
+                        match Self::record_handshake(&connection_infos, &connection_events, remote_addr, result).await {
+                            Ok(negotiated) => {
+                                transport.read().await.claim_streams(&connection);
+                                Self::serve_connection(&channels, &topics, connection, negotiated.peer_name, inbound_tokens).await;
+                            }
+                            Err(e) => {
+                                let code = handshake::failure_close_code(&e);
+                                if let Err(close_error) = transport.read().await.disconnect_with(remote_addr, code.code(), "handshake failed").await {
+                                    debug!("关闭握手失败的连接 {} 失败: {}", remote_addr, close_error);
+                                }
+                            }
+                        }
+                    });
+                }
 
-        // 连接到解析的地址
-        self.connect(device_addr).await
+                // 回收已结束的握手任务
+                while handshakes.try_join_next().is_some() {}
+            }
+        });
     }
 
-    /// 连接到服务器（客户端模式，通过地址）
-    ///
-    /// # 参数
-    ///
-    /// * `server_addr` - 服务器地址
-    ///
-    /// # 返回值
-    ///
-    /// 返回连接结果或错误
-    pub async fn connect(&self, server_addr: SocketAddr) -> NetResult<()> {
-        info!("连接到服务器: {}", server_addr);
+    /// 启动TCP回退接受任务
+    ///
+    /// 在每个入站TCP连接上先经传输层评估接受策略并完成mTLS握手（与QUIC连接同样校验对端证书），
+    /// 再响应握手协商，之后接收对端发送的令牌，连接关闭后清理协商的连接信息。
+    /// 服务器停止后接受任务退出，同时结束所有连接任务
+    fn start_tcp_fallback_acceptor(&self, listener: tokio::net::TcpListener) {
+        let transport = Arc::clone(&self.transport);
+        let running = Arc::clone(&self.running);
+        let connection_infos = Arc::clone(&self.connection_infos);
+        let connection_events = self.connection_events.clone();
+        let tcp_connections = Arc::clone(&self.tcp_connections);
+        let inbound_tokens = self._sender.clone();
+        let topics = Arc::clone(&self.topics);
+        let offer = self.handshake_offer();
+
+        self.server_tasks.spawn(async move {
+            let mut connections = JoinSet::new();
+
+            while running.load(Ordering::SeqCst) {
+                let (stream, remote_addr) = match tokio::time::timeout(HANDSHAKE_POLL_INTERVAL, listener.accept()).await {
+                    Ok(Ok(accepted)) => accepted,
+                    Ok(Err(e)) => {
+                        warn!("接受TCP回退连接失败: {}", e);
+                        continue;
+                    }
+                    Err(_) => continue,
+                };
+                let local_addr = match stream.local_addr() {
+                    Ok(addr) => addr,
+                    Err(e) => {
+                        warn!("获取TCP回退连接 {} 的本地地址失败: {}", remote_addr, e);
+                        continue;
+                    }
+                };
+                let _ = stream.set_nodelay(true);
+
+                let transport = Arc::clone(&transport);
+                let connection_infos = Arc::clone(&connection_infos);
+                let connection_events = connection_events.clone();
+                let tcp_connections = Arc::clone(&tcp_connections);
+                let inbound_tokens = inbound_tokens.clone();
+                let topics = Arc::clone(&topics);
+                let offer = offer.clone();
+                connections.spawn(async move {
+                    let accepted = transport.read().await.accept_tcp(stream, remote_addr).await;
+                    let result = match accepted {
+                        Ok((mut stream, trust_level)) => {
+                            debug!("TCP回退连接 {} 完成TLS握手，信任级别: {:?}", remote_addr, trust_level);
+                            handshake::respond_stream(&mut stream, local_addr, remote_addr, &offer).await
+                                .map(|negotiated| (negotiated, tcp_fallback::split(stream)))
+                        }
+                        Err(e) => Err(e),
+                    };
+                    let (result, link) = match result {
+                        Ok((negotiated, link)) => (Ok(negotiated), Some(link)),
+                        Err(e) => (Err(e), None),
+                    };
+                    let negotiated = Self::record_handshake(&connection_infos, &connection_events, remote_addr, result).await;
+                    if let (Ok(negotiated), Some((writer, reader))) = (negotiated, link) {
+                        let writer = Arc::new(Mutex::new(writer));
+                        tcp_connections.write().await.insert(remote_addr, Arc::clone(&writer));
+                        Self::serve_tcp_connection(
+                            tcp_connections, connection_infos, topics, remote_addr, negotiated.peer_name, writer, reader, inbound_tokens,
+                        ).await;
+                    }
+                });
 
-        // 更新状态
-        {
-            let mut sm = self.state_machine.write().await;
-            sm.handle_event(StateEvent::Connect)?;
-        }
+                // 回收已结束的连接任务
+                while connections.try_join_next().is_some() {}
+            }
+            debug!("TCP回退接受任务已退出");
+        });
+    }
 
-        // 连接到服务器
-        {
-            let transport = self.transport.write().await;
-            transport.connect(server_addr).await.map_err(|e| {
-                ErrorInfo::new(4304, format!("连接服务器失败: {}", e))
-                    .with_category(ErrorCategory::Network)
-                    .with_severity(ErrorSeverity::Error)
-            })?;
+    /// 接收TCP回退连接上对端发送的令牌，连接关闭后关闭发送方向并清理连接信息
+    ///
+    /// 开始接收前向对端公告本地订阅的主题。
+    /// 发送端需要已登记在 `tcp_connections` 中；连接已被断开或被新的连接替换时不再清理
+    #[allow(clippy::too_many_arguments)]
+    async fn serve_tcp_connection(
+        tcp_connections: Arc<TcpConnections>,
+        connection_infos: Arc<RwLock<HashMap<SocketAddr, NegotiatedConnection>>>,
+        topics: Arc<TopicBus>,
+        remote_addr: SocketAddr,
+        peer_name: String,
+        writer: Arc<Mutex<FrameWriter>>,
+        reader: FrameReader,
+        inbound: InboundSender,
+    ) {
+        if let Some(token) = Self::topic_announcement_for(&topics, &peer_name).await {
+            let result = match wire::encode(&token) {
+                Ok(data) => writer.lock().await.send(&data).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("向 {} 公告主题失败: {}", peer_name, e);
+            }
         }
+        tcp_fallback::serve(reader, peer_name, remote_addr, inbound).await;
+        writer.lock().await.close().await;
 
-        // 更新状态
-        {
-            let mut sm = self.state_machine.write().await;
-            sm.handle_event(StateEvent::Connected)?;
+        let mut connections = tcp_connections.write().await;
+        if connections.get(&remote_addr).is_some_and(|current| Arc::ptr_eq(current, &writer)) {
+            connections.remove(&remote_addr);
+            connection_infos.write().await.remove(&remote_addr);
         }
+        debug!("TCP回退连接已关闭: {}", remote_addr);
+    }
 
-        // 如果启用认证，执行认证流程
-        if self.config.enable_auth && self.cert_manager.is_some() {
-            let mut sm = self.state_machine.write().await;
-            sm.handle_event(StateEvent::Authenticate)?;
-            
-            // 执行实际的认证逻辑
-            match self.perform_authentication(server_addr).await {
-                Ok(()) => {
-                    sm.handle_event(StateEvent::Authenticated)?;
-                    info!("客户端认证成功");
-                }
-                Err(e) => {
-                    warn!("客户端认证失败: {}", e);
-                    sm.handle_event(StateEvent::AuthFailed)?;
-                    return Err(e);
+    /// 接受已握手的QUIC连接上对端打开的逻辑通道和发送的令牌，直到连接关闭
+    ///
+    /// 同时在该连接上向对端公告本地订阅的主题；公告要等对端读完才算送达，
+    /// 因此与接收并行进行，避免双方互相等待。
+    /// 调用方需先通过 [`SecureTransport::claim_streams`] 声明该连接上的双向流
+    async fn serve_connection(
+        channels: &Arc<ChannelRouter>,
+        topics: &TopicBus,
+        connection: bey_transport::Connection,
+        peer_name: String,
+        inbound: InboundSender,
+    ) {
+        let announce = async {
+            if let Some(token) = Self::topic_announcement_for(topics, &peer_name).await {
+                if let Err(e) = wire::send(&connection, &token).await {
+                    warn!("向 {} 公告主题失败: {}", peer_name, e);
                 }
             }
-        } else {
-            // 没有认证，直接进入已认证状态
-            let mut sm = self.state_machine.write().await;
-            sm.handle_event(StateEvent::Authenticated)?;
-        }
+        };
+        tokio::join!(
+            announce,
+            channels.serve(connection.clone()),
+            wire::serve(connection.clone(), peer_name.clone(), inbound),
+        );
+    }
 
-        info!("成功连接到服务器: {}", server_addr);
-        Ok(())
+    /// 构建发给新连接对端的主题公告，本地没有订阅主题时返回 `None`
+    async fn topic_announcement_for(topics: &TopicBus, peer_name: &str) -> Option<Token> {
+        let local_topics = topics.local_topics().await;
+        if local_topics.is_empty() {
+            return None;
+        }
+        match topics.announcement(peer_name, local_topics, true) {
+            Ok(token) => Some(token),
+            Err(e) => {
+                warn!("构建发给 {} 的主题公告失败: {}", peer_name, e);
+                None
+            }
+        }
     }
 
-    /// 断开连接
-    ///
-    /// # 返回值
-    ///
-    /// 返回断开结果或错误
-    pub async fn disconnect(&self) -> NetResult<()> {
-        info!("断开连接");
+    /// 记录握手结果并发出连接事件
+    async fn record_handshake(
+        connection_infos: &RwLock<HashMap<SocketAddr, NegotiatedConnection>>,
+        connection_events: &broadcast::Sender<ConnectionEvent>,
+        remote_addr: SocketAddr,
+        result: NetResult<NegotiatedConnection>,
+    ) -> NetResult<NegotiatedConnection> {
+        let event = match &result {
+            Ok(negotiated) => {
+                info!("与 {} ({}) 握手成功: 安全级别 {:?}, 认证方式 {:?}",
+                    negotiated.peer_name, remote_addr, negotiated.info.security_level, negotiated.info.auth_method);
+                connection_infos.write().await.insert(remote_addr, negotiated.clone());
+                StateEvent::Authenticated
+            }
+            Err(e) if e.code() == 4903 => {
+                warn!("与 {} 握手被拒绝: {}", remote_addr, e.message());
+                StateEvent::Rejected(e.message().to_string())
+            }
+            Err(e) => {
+                warn!("与 {} 握手失败: {}", remote_addr, e.message());
+                StateEvent::Error(e.message().to_string())
+            }
+        };
 
-        let mut sm = self.state_machine.write().await;
-        sm.handle_event(StateEvent::Disconnect)?;
-        sm.handle_event(StateEvent::Disconnect)?; // 转换到Disconnected状态
+        let _ = connection_events.send(ConnectionEvent { remote_addr, event });
+        result
+    }
 
-        info!("连接已断开");
-        Ok(())
+    /// 订阅连接握手事件
+    ///
+    /// 握手成功时事件为 [`StateEvent::Authenticated`]，对端不兼容时为携带拒绝原因的
+    /// [`StateEvent::Rejected`]，其他失败为 [`StateEvent::Error`]
+    pub fn subscribe_connection_events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.connection_events.subscribe()
     }
 
-    /// 发送令牌
+    /// 获取与指定地址协商后的连接信息
     ///
     /// # 参数
     ///
-    /// * `token` - 要发送的令牌
+    /// * `remote_addr` - 对端地址
     ///
     /// # 返回值
     ///
-    /// 返回发送结果或错误
-    pub async fn send_token(&self, mut token: Token) -> NetResult<()> {
-        debug!("发送令牌: {} (类型: {})", token.meta.id, token.meta.token_type);
-
-        // 检查状态
-        {
-            let sm = self.state_machine.read().await;
-            if !sm.can_transfer() {
-                return Err(ErrorInfo::new(4305, format!("当前状态不允许传输: {}", sm.current_state()))
-                    .with_category(ErrorCategory::System)
-                    .with_severity(ErrorSeverity::Warning));
-            }
-        }
-
-        // 如果启用加密，加密令牌
-        if self.config.enable_encryption && !token.meta.encrypted {
-            token = self.encrypt_token(token).await?;
-        }
-
-        // 序列化令牌
-        let _data = token.serialize()?;
-
-        // 查找目标设备地址
-        if let Some(receiver_id) = &token.meta.receiver_id {
-            debug!("查找目标设备: {}", receiver_id);
-            
-            let addresses = self.get_device_addresses(receiver_id).await;
-            if let Some(addrs) = addresses {
-                if let Some(target_addr) = addrs.first() {
-                    info!("令牌将发送到: {} ({})", receiver_id, target_addr);
-                    // TODO: 使用传输层实际发送
-                    // let transport = self.transport.read().await;
-                    // transport.send_to(&data, *target_addr).await?;
-                } else {
-                    return Err(ErrorInfo::new(4330, format!("设备 {} 没有可用地址", receiver_id))
-                        .with_category(ErrorCategory::Network)
-                        .with_severity(ErrorSeverity::Warning));
-                }
-            } else {
-                return Err(ErrorInfo::new(4331, format!("未找到设备: {}", receiver_id))
-                    .with_category(ErrorCategory::Network)
-                    .with_severity(ErrorSeverity::Warning));
-            }
-        } else {
-            debug!("令牌没有指定接收者，使用广播");
-            // 可以广播到所有已发现的设备
-        }
+    /// 返回协商的安全级别和认证方式等信息，未完成握手时返回 `None`
+    pub async fn connection_info(&self, remote_addr: SocketAddr) -> Option<ConnectionInfo> {
+        self.connection_infos.read().await.get(&remote_addr).map(|negotiated| negotiated.info.clone())
+    }
 
-        debug!("令牌已准备发送: {}", token.meta.id);
-        Ok(())
+    /// 获取所有已完成握手的连接信息
+    pub async fn connection_infos(&self) -> Vec<ConnectionInfo> {
+        self.connection_infos.read().await.values().map(|negotiated| negotiated.info.clone()).collect()
     }
 
-    /// 接收令牌
+    /// 查找到指定对端的已握手连接地址
     ///
     /// # 参数
     ///
-    /// * `mode` - 接收模式
+    /// * `peer_name` - 对端在握手中给出的名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接地址，没有到该对端的连接时返回 `None`
+    pub async fn peer_connection_addr(&self, peer_name: &str) -> Option<SocketAddr> {
+        self.connection_infos.read().await.iter()
+            .find(|(_, negotiated)| negotiated.peer_name == peer_name)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// 获取服务器实际绑定的本地地址
+    ///
+    /// 服务器未启动时返回 `None`
+    pub async fn local_addr(&self) -> Option<SocketAddr> {
+        self.transport.read().await.local_addr()
+    }
+
+    /// 设置主传输层的策略集合，控制出站连接和入站连接的访问
+    ///
+    /// # 参数
+    ///
+    /// * `policy_set` - 策略集合
+    ///
+    /// # 返回值
+    ///
+    /// 返回设置结果或错误
+    pub async fn set_policy_set(&self, policy_set: PolicySet) -> NetResult<()> {
+        self.transport.write().await.set_policy_set(policy_set).await.map_err(|e| {
+            ErrorInfo::new(4334, format!("设置策略集合失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error)
+        })
+    }
+
+    /// 服务器是否正在运行
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// 服务器运行期间启动的后台任务中仍在运行的数量
+    ///
+    /// 停止服务器后为0；引擎生命周期内的维护任务不计入
+    pub fn active_task_count(&self) -> usize {
+        self.server_tasks.active_count()
+    }
+
+    /// 获取引擎配置
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    /// 获取mDNS探测后实际声明的服务名称
+    ///
+    /// 名称冲突时为带数字后缀的名称，未启用mDNS时返回 `None`
+    pub async fn mdns_claimed_name(&self) -> Option<String> {
+        match &self.mdns_discovery {
+            Some(mdns) => Some(mdns.claimed_name().await),
+            None => None,
+        }
+    }
+
+    /// 添加命名监听器
+    ///
+    /// 在指定端口上启动一个独立的监听端点，使用独立的策略集合控制访问，
+    /// 适用于同一节点需要以不同端口、不同策略对外提供服务的场景。
+    /// 不要求客户端证书的监听器接受匿名客户端，匿名连接的信任级别较低。
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 监听器名称，在引擎内唯一
+    /// * `port` - 监听端口，为0时由系统分配
+    /// * `policy_set` - 该监听器使用的策略集合
+    /// * `require_client_cert` - 是否要求客户端出示证书
+    ///
+    /// # 返回值
+    ///
+    /// 返回实际绑定的本地地址或错误
+    pub async fn add_listener(
+        &self,
+        name: &str,
+        port: u16,
+        policy_set: PolicySet,
+        require_client_cert: bool,
+    ) -> NetResult<SocketAddr> {
+        let mut listeners = self.listeners.write().await;
+        if listeners.contains_key(name) {
+            return Err(ErrorInfo::new(4332, format!("监听器已存在: {}", name))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let result = SecureTransport::new(
+            self.config.transport_config.clone()
+                .with_port(port)
+                .with_require_client_cert(require_client_cert),
+            self.config.name.clone(),
+        ).await.map_err(|e| {
+            ErrorInfo::new(4301, format!("创建监听器 {} 的传输层失败: {}", name, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error)
+        });
+        let mut transport = self.track_error(result).await?;
+
+        transport.set_policy_set(policy_set).await.map_err(|e| {
+            ErrorInfo::new(4334, format!("设置监听器 {} 的策略集合失败: {}", name, e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error)
+        })?;
+
+        let result = transport.start_server().await.map_err(|e| {
+            ErrorInfo::new(4303, format!("启动监听器 {} 失败: {}", name, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error)
+        });
+        self.track_error(result).await?;
+
+        let local_addr = transport.local_addr().unwrap_or_else(|| {
+            SocketAddr::new(self.config.transport_config.bind_address(), port)
+        });
+        listeners.insert(name.to_string(), transport);
+
+        info!("监听器 {} 已启动，监听地址: {}", name, local_addr);
+        Ok(local_addr)
+    }
+
+    /// 关闭指定的命名监听器
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 监听器名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回关闭结果或错误
+    pub async fn stop_listener(&self, name: &str) -> NetResult<()> {
+        let listener = self.listeners.write().await.remove(name).ok_or_else(|| {
+            ErrorInfo::new(4333, format!("监听器不存在: {}", name))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Warning)
+        })?;
+
+        listener.stop().await;
+        info!("监听器已关闭: {}", name);
+        Ok(())
+    }
+
+    /// 列出所有命名监听器
+    ///
+    /// # 返回值
+    ///
+    /// 返回按名称排序的监听器信息列表
+    pub async fn list_listeners(&self) -> Vec<ListenerInfo> {
+        let listeners = self.listeners.read().await;
+        let mut infos = Vec::with_capacity(listeners.len());
+        for (name, transport) in listeners.iter() {
+            infos.push(ListenerInfo {
+                name: name.clone(),
+                local_addr: transport.local_addr(),
+                policy_set_id: transport.policy_set_id().to_string(),
+                require_client_cert: transport.config().require_client_cert(),
+                accepted_connections: transport.active_connections_count().await,
+            });
+        }
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+
+    /// 获取指定监听器已接受的活跃连接数量
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 监听器名称
+    ///
+    /// # 返回值
+    ///
+    /// 监听器存在时返回连接数量，否则返回 `None`
+    pub async fn listener_connections(&self, name: &str) -> Option<usize> {
+        let listeners = self.listeners.read().await;
+        match listeners.get(name) {
+            Some(transport) => Some(transport.active_connections_count().await),
+            None => None,
+        }
+    }
+
+    /// 停止引擎服务器
+    ///
+    /// 停止连接接受任务，关闭监听端点和所有连接（包括所有命名监听器），
+    /// 并将状态机转换到已断开状态。服务器运行期间启动的后台任务在超时时间内
+    /// 退出，超时未退出的任务被强制中止。
+    /// 重复调用不会产生副作用。
+    ///
+    /// # 返回值
+    ///
+    /// 返回停止结果或错误
+    pub async fn stop_server(&self) -> NetResult<()> {
+        // 关闭所有附加监听器
+        for (name, listener) in self.listeners.write().await.drain() {
+            listener.stop().await;
+            info!("监听器已关闭: {}", name);
+        }
+
+        if !self.running.swap(false, Ordering::SeqCst) {
+            debug!("传输引擎服务器未运行，无需停止");
+            return Ok(());
+        }
+
+        info!("停止传输引擎服务器: {}", self.config.name);
+
+        // 停止mDNS发现
+        if let Some(mdns) = &self.mdns_discovery {
+            if let Err(e) = mdns.stop().await {
+                warn!("停止mDNS发现失败: {}", e);
+            }
+        }
+
+        // 停止服务器后台任务，关闭回退连接
+        self.server_tasks.shutdown(DEFAULT_TASK_SHUTDOWN_TIMEOUT).await;
+        self.close_tcp_connections().await;
+
+        // 停止传输层（结束接受任务并关闭端点）
+        self.transport.read().await.stop().await;
+
+        if let Err(e) = self.save_peer_cache().await {
+            warn!("保存已知设备缓存失败: {}", e);
+        }
+
+        // 转换到终止状态
+        {
+            let mut sm = self.state_machine.write().await;
+            if sm.current_state() != ConnectionState::Disconnected {
+                sm.handle_event(StateEvent::Disconnect)?;
+                sm.handle_event(StateEvent::ConnectionLost)?; // 转换到Disconnected状态
+            }
+        }
+
+        info!("传输引擎服务器已停止");
+        Ok(())
+    }
+
+    /// 连接到指定设备（通过设备名）
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 目标设备名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接结果或错误
+    pub async fn connect_to_device(&self, device_name: &str) -> NetResult<()> {
+        info!("连接到设备: {}", device_name);
+
+        // 首先查询mDNS获取设备地址
+        let (device_addr, tcp_addr) = if let Some(mdns) = &self.mdns_discovery {
+            // 查询mDNS服务
+            let result = mdns.query_service(&self.config.mdns_service_type, None).await.map_err(|e| {
+                ErrorInfo::new(4326, format!("查询mDNS服务失败: {}", e))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Error)
+            });
+            let services = self.track_error(result).await?;
+
+            // 查找目标设备
+            let result = services.iter().find(|s| s.service_name == device_name)
+                .ok_or_else(|| {
+                    ErrorInfo::new(4327, format!("未找到设备: {}", device_name))
+                        .with_category(ErrorCategory::Network)
+                        .with_severity(ErrorSeverity::Warning)
+                });
+            let service = self.track_error(result).await?;
+
+            // 在设备的所有地址中选择首选地址或RTT最低的地址
+            let addresses: Vec<SocketAddr> = service.addresses.iter()
+                .map(|ip| SocketAddr::new(*ip, service.port))
+                .collect();
+            let result = self.path_selector.select_address(device_name, &addresses).await.ok_or_else(|| {
+                ErrorInfo::new(4328, format!("设备 {} 没有可用地址", device_name))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Warning)
+            });
+            let addr = self.track_error(result).await?;
+
+            // 只有对端通告了TCP回退端口时才能回退
+            let tcp_addr = tcp_fallback::advertised_tcp_port(&service.txt_records)
+                .filter(|_| self.config.enable_tcp_fallback)
+                .map(|port| SocketAddr::new(addr.ip(), port));
+            (addr, tcp_addr)
+        } else {
+            return Err(ErrorInfo::new(4329, "mDNS发现未启用".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error));
+        };
+
+        info!("设备 {} 解析到地址: {}", device_name, device_addr);
+
+        // 连接到解析的地址
+        self.connect_with_fallback(device_addr, tcp_addr).await
+    }
+
+    /// 连接到服务器（客户端模式，通过地址）
+    ///
+    /// # 参数
+    ///
+    /// * `server_addr` - 服务器地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接结果或错误
+    pub async fn connect(&self, server_addr: SocketAddr) -> NetResult<()> {
+        let tcp_addr = self.config.enable_tcp_fallback.then_some(server_addr);
+        self.connect_with_fallback(server_addr, tcp_addr).await
+    }
+
+    /// 连接到服务器，UDP被阻断时回退到TCP
+    ///
+    /// # 参数
+    ///
+    /// * `server_addr` - 服务器的QUIC地址
+    /// * `tcp_addr` - 服务器的TCP回退地址，为 `None` 时不回退
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接结果或错误
+    async fn connect_with_fallback(&self, server_addr: SocketAddr, tcp_addr: Option<SocketAddr>) -> NetResult<()> {
+        info!("连接到服务器: {}", server_addr);
+
+        // 更新状态
+        {
+            let mut sm = self.state_machine.write().await;
+            sm.handle_event(StateEvent::Connect)?;
+        }
+
+        // 连接到服务器
+        let connection = {
+            let transport = self.transport.write().await;
+            transport.connect(server_addr).await
+        };
+
+        // 协商安全级别和认证方式，不兼容的对端给出拒绝原因并断开连接
+        let result = match (connection, tcp_addr) {
+            (Ok(connection), _) => {
+                self.state_machine.write().await.handle_event(StateEvent::Connected)?;
+                self.handshake_outbound(server_addr, connection).await
+            }
+            (Err(e), Some(tcp_addr)) if tcp_fallback::is_udp_unreachable(&e) => {
+                warn!("QUIC连接 {} 失败（{}），回退到TCP: {}", server_addr, e.message(), tcp_addr);
+                let stream = self.track_error(tcp_fallback::connect(tcp_addr).await).await?;
+                self.state_machine.write().await.handle_event(StateEvent::Connected)?;
+                self.handshake_tcp_outbound(tcp_addr, stream).await
+            }
+            (Err(e), _) => Err(ErrorInfo::new(4304, format!("连接服务器失败: {}", e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error)),
+        };
+        self.track_error(result).await?;
+
+        // 如果启用认证，执行认证流程
+        if self.config.enable_auth && self.cert_manager.is_some() {
+            let mut sm = self.state_machine.write().await;
+            sm.handle_event(StateEvent::Authenticate)?;
+            
+            // 执行实际的认证逻辑
+            match self.perform_authentication(server_addr).await {
+                Ok(()) => {
+                    sm.handle_event(StateEvent::Authenticated)?;
+                    info!("客户端认证成功");
+                }
+                Err(e) => {
+                    warn!("客户端认证失败: {}", e);
+                    sm.handle_event(StateEvent::AuthFailed)?;
+                    return Err(e);
+                }
+            }
+        } else {
+            // 没有认证，不校验证书直接进入已认证状态
+            let mut sm = self.state_machine.write().await;
+            sm.handle_event(StateEvent::Authenticate)?;
+            sm.handle_event(StateEvent::Authenticated)?;
+        }
+
+        info!("成功连接到服务器: {}", server_addr);
+        Ok(())
+    }
+
+    /// 在新建立的出站QUIC连接上握手
+    ///
+    /// 握手成功后接受服务端在该连接上打开的逻辑通道和发送的令牌，连接关闭时退出；
+    /// 握手失败时断开连接
+    async fn handshake_outbound(&self, server_addr: SocketAddr, connection: bey_transport::Connection) -> NetResult<()> {
+        let result = handshake::initiate(&connection, &self.handshake_offer()).await;
+        match Self::record_handshake(&self.connection_infos, &self.connection_events, server_addr, result).await {
+            Ok(negotiated) => {
+                self.transport.read().await.claim_streams(&connection);
+                let channels = Arc::clone(&self.channels);
+                let topics = Arc::clone(&self.topics);
+                let inbound = self._sender.clone();
+                self.maintenance_tasks.spawn(async move {
+                    Self::serve_connection(&channels, &topics, connection, negotiated.peer_name, inbound).await;
+                });
+                Ok(())
+            }
+            Err(e) => {
+                if let Err(disconnect_error) = self.transport.read().await.disconnect(server_addr).await {
+                    debug!("断开握手失败的连接 {} 失败: {}", server_addr, disconnect_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 在新建立的出站TCP回退连接上完成mTLS握手和握手协商
+    ///
+    /// 传输层先评估连接策略并校验服务端证书；成功后在后台接收服务端发送的令牌，
+    /// 失败时丢弃TCP流即断开连接
+    async fn handshake_tcp_outbound(&self, server_addr: SocketAddr, stream: TcpStream) -> NetResult<()> {
+        let connected = self.transport.read().await.connect_tcp(stream, server_addr).await;
+        let result = match connected {
+            Ok(mut stream) => handshake::initiate_stream(&mut stream, server_addr, &self.handshake_offer()).await
+                .map(|negotiated| (negotiated, tcp_fallback::split(stream))),
+            Err(e) => Err(e),
+        };
+        let (result, link) = match result {
+            Ok((negotiated, link)) => (Ok(negotiated), Some(link)),
+            Err(e) => (Err(e), None),
+        };
+        let negotiated = Self::record_handshake(&self.connection_infos, &self.connection_events, server_addr, result).await?;
+        let Some((writer, reader)) = link else {
+            return Ok(());
+        };
+
+        // 先登记发送端，返回后立即可以发送令牌
+        let writer = Arc::new(Mutex::new(writer));
+        self.tcp_connections.write().await.insert(server_addr, Arc::clone(&writer));
+        self.maintenance_tasks.spawn(Self::serve_tcp_connection(
+            Arc::clone(&self.tcp_connections),
+            Arc::clone(&self.connection_infos),
+            Arc::clone(&self.topics),
+            server_addr,
+            negotiated.peer_name,
+            writer,
+            reader,
+            self._sender.clone(),
+        ));
+        Ok(())
+    }
+
+    /// 关闭所有TCP回退连接并清理它们的连接信息
+    async fn close_tcp_connections(&self) {
+        let closed: Vec<(SocketAddr, Arc<Mutex<FrameWriter>>)> = self.tcp_connections.write().await.drain().collect();
+        if !closed.is_empty() {
+            let mut connection_infos = self.connection_infos.write().await;
+            for (addr, _) in &closed {
+                connection_infos.remove(addr);
+            }
+        }
+        for (_, writer) in closed {
+            writer.lock().await.close().await;
+        }
+    }
+
+    /// 断开连接
+    ///
+    /// # 返回值
+    ///
+    /// 返回断开结果或错误
+    pub async fn disconnect(&self) -> NetResult<()> {
+        info!("断开连接");
+
+        // 关闭TCP回退连接
+        self.close_tcp_connections().await;
+
+        let mut sm = self.state_machine.write().await;
+        sm.handle_event(StateEvent::Disconnect)?;
+        sm.handle_event(StateEvent::ConnectionLost)?; // 转换到Disconnected状态
+
+        info!("连接已断开");
+        Ok(())
+    }
+
+    /// 使用指定的关闭码断开与对端的连接
+    ///
+    /// QUIC连接以应用关闭帧把关闭码和原因发送给对端，对端在传输层事件流中收到
+    /// 携带该关闭码的 [`TransportEvent::Disconnected`]；TCP回退连接没有关闭码，直接关闭。
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 对端地址
+    /// * `code` - QUIC应用错误码，标准关闭码见 [`CloseCode`](bey_transport::CloseCode)
+    /// * `reason` - 关闭原因
+    ///
+    /// # 返回值
+    ///
+    /// 返回断开结果，连接不存在时返回错误
+    pub async fn disconnect_with(&self, addr: SocketAddr, code: u32, reason: &str) -> NetResult<()> {
+        self.connection_infos.write().await.remove(&addr);
+        let tcp_writer = self.tcp_connections.write().await.remove(&addr);
+        if let Some(writer) = tcp_writer {
+            writer.lock().await.close().await;
+            info!("已关闭TCP回退连接: {} (关闭码: {}, 原因: {})", addr, code, reason);
+            return Ok(());
+        }
+
+        let result = self.transport.read().await.disconnect_with(addr, code, reason).await
+            .map_err(|e| ErrorInfo::new(4335, format!("断开连接 {} 失败: {}", addr, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Warning));
+        self.track_error(result).await
+    }
+
+    /// 订阅传输层连接事件
+    ///
+    /// 包括连接建立、断开（携带对端的关闭码）、握手失败和双向流打开
+    pub async fn subscribe_transport_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.transport.read().await.events()
+    }
+
+    /// 发送令牌
+    ///
+    /// 指定了接收者的令牌经到该对端的已握手连接发送，尚未连接时先连接到已发现设备的地址；
+    /// 没有指定接收者的令牌发送到所有已握手的连接（包括TCP回退连接）。对端确认收到后返回
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 要发送的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果或错误
+    pub async fn send_token(&self, mut token: Token) -> NetResult<()> {
+        debug!("发送令牌: {} (类型: {})", token.meta.id, token.meta.token_type);
+
+        // 检查状态
+        {
+            let sm = self.state_machine.read().await;
+            if !sm.can_transfer() {
+                return Err(ErrorInfo::new(4305, format!("当前状态不允许传输: {}", sm.current_state()))
+                    .with_category(ErrorCategory::System)
+                    .with_severity(ErrorSeverity::Warning));
+            }
+        }
+
+        // 如果启用加密，加密令牌
+        if self.config.enable_encryption && !token.meta.encrypted {
+            token = self.encrypt_token(token).await?;
+        }
+
+        // 指定接收者时发送到该设备，否则发送到所有已握手的连接
+        let targets = match &token.meta.receiver_id {
+            Some(receiver_id) => {
+                let target = self.resolve_target(receiver_id).await;
+                vec![self.track_error(target).await?]
+            }
+            None => {
+                let targets: Vec<SocketAddr> = self.connection_infos.read().await.keys().copied().collect();
+                debug!("令牌没有指定接收者，发送到 {} 个已连接的对端", targets.len());
+                targets
+            }
+        };
+
+        for target_addr in targets {
+            let result = Self::transmit(&self.transport, &self.tcp_connections, &self.channel_routes, target_addr, &token).await;
+            self.track_error(result).await?;
+            debug!("令牌 {} 已发送到 {}", token.meta.id, target_addr);
+        }
+        Ok(())
+    }
+
+    /// 确定接收者的连接地址
+    ///
+    /// 优先使用到该对端的已握手连接，否则连接到已发现设备的第一个地址并完成握手
+    ///
+    /// # 参数
+    ///
+    /// * `receiver_id` - 接收者名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回已握手的连接地址或错误
+    async fn resolve_target(&self, receiver_id: &str) -> NetResult<SocketAddr> {
+        if let Some(addr) = self.peer_connection_addr(receiver_id).await {
+            return Ok(addr);
+        }
+
+        debug!("查找目标设备: {}", receiver_id);
+        let addrs = self.get_device_addresses(receiver_id).await
+            .ok_or_else(|| ErrorInfo::new(4331, format!("未找到设备: {}", receiver_id))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Warning))?;
+        let target_addr = *addrs.first()
+            .ok_or_else(|| ErrorInfo::new(4330, format!("设备 {} 没有可用地址", receiver_id))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Warning))?;
+
+        if !self.connection_infos.read().await.contains_key(&target_addr) {
+            info!("连接到令牌接收者: {} ({})", receiver_id, target_addr);
+            let connection = self.transport.read().await.connect(target_addr).await
+                .map_err(|e| ErrorInfo::new(4304, format!("连接服务器失败: {}", e))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Error))?;
+            self.handshake_outbound(target_addr, connection).await?;
+        }
+        Ok(target_addr)
+    }
+
+    /// 在到对端的已握手连接上发送令牌
+    ///
+    /// # 参数
+    ///
+    /// * `transport` - 传输层
+    /// * `tcp_connections` - TCP回退连接
+    /// * `channel_routes` - 经由逻辑通道发送的令牌类型
+    /// * `target_addr` - 对端地址
+    /// * `token` - 要发送的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果，没有到对端的连接时返回错误
+    async fn transmit(
+        transport: &RwLock<SecureTransport>,
+        tcp_connections: &TcpConnections,
+        channel_routes: &ChannelRoutes,
+        target_addr: SocketAddr,
+        token: &Token,
+    ) -> NetResult<()> {
+        // TCP回退连接以加密帧发送
+        let tcp_writer = tcp_connections.read().await.get(&target_addr).cloned();
+        if let Some(writer) = tcp_writer {
+            let data = wire::encode(token)?;
+            return writer.lock().await.send(&data).await;
+        }
+
+        let connection = transport.read().await.connection(target_addr).await
+            .ok_or_else(|| ErrorInfo::new(4338, format!("没有到 {} 的连接，无法发送令牌 {}", target_addr, token.meta.id))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Warning))?;
+        channel_routes.send(&connection, token).await
+    }
+
+    /// 接收令牌
+    ///
+    /// # 参数
+    ///
+    /// * `mode` - 接收模式
     ///
     /// # 返回值
     ///
@@ -792,19 +1929,24 @@ impl TransportEngine {
         }
 
         // 从接收器获取令牌
-        let token = self.receiver.receive(mode).await?;
+        // 跳过重放令牌，继续接收下一个
+        while let Some(mut token) = self.receiver.receive(mode).await? {
+            if self.replay_cache.check_and_record(&token.meta.sender_id, &token.meta.id) {
+                warn!("丢弃重放令牌: {} (发送方: {})", token.meta.id, token.meta.sender_id);
+                self.metrics.record_replay().await;
+                continue;
+            }
 
-        if let Some(mut token) = token {
             // 如果令牌是加密的，解密
             if token.meta.encrypted {
                 token = self.decrypt_token(token).await?;
             }
 
             debug!("接收令牌: {} (类型: {})", token.meta.id, token.meta.token_type);
-            Ok(Some(token))
-        } else {
-            Ok(None)
+            return Ok(Some(token));
         }
+
+        Ok(None)
     }
 
     /// 注册令牌处理器
@@ -820,6 +1962,27 @@ impl TransportEngine {
         self.router.register_handler(handler).await
     }
 
+    /// 注册兜底处理器
+    ///
+    /// 找不到处理器的入站令牌交由兜底处理器处理，不再进入死信队列
+    ///
+    /// # 参数
+    ///
+    /// * `handler` - 兜底处理器
+    pub async fn set_fallback_handler(&self, handler: Arc<dyn TokenHandler>) {
+        self.router.set_fallback_handler(handler).await;
+    }
+
+    /// 获取死信队列中无法路由的令牌（从旧到新）
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.router.dead_letters().await
+    }
+
+    /// 取出并清空死信队列
+    pub async fn take_dead_letters(&self) -> Vec<DeadLetter> {
+        self.router.take_dead_letters().await
+    }
+
     /// 获取当前状态
     ///
     /// # 返回值
@@ -848,6 +2011,29 @@ impl TransportEngine {
         devices.keys().cloned().collect()
     }
 
+    /// 按能力筛选mDNS发现的设备
+    ///
+    /// 能力取自设备mDNS记录中声明的能力列表，未启用mDNS时返回空列表
+    ///
+    /// # 参数
+    ///
+    /// * `capability` - 设备能力
+    ///
+    /// # 返回值
+    ///
+    /// 返回声明了该能力的设备，按设备ID排列
+    pub async fn discover_by_capability(&self, capability: Capability) -> Vec<DeviceInfo> {
+        let Some(mdns) = &self.mdns_discovery else {
+            return Vec::new();
+        };
+        let mut devices: Vec<DeviceInfo> = mdns.get_discovered_services().await.iter()
+            .filter_map(DeviceChangeFeed::device_from_service)
+            .filter(|device| device.has_capability(&capability))
+            .collect();
+        devices.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        devices
+    }
+
     /// 获取设备地址
     ///
     /// # 参数
@@ -862,6 +2048,83 @@ impl TransportEngine {
         devices.get(device_name).map(|d| d.addresses.clone())
     }
 
+    /// 设备是否为从缓存加载、本次运行尚未重新发现的已知设备
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    ///
+    /// # 返回值
+    ///
+    /// 设备不在列表中时返回 `None`
+    pub async fn is_device_stale(&self, device_name: &str) -> Option<bool> {
+        let devices = self.discovered_devices.read().await;
+        devices.get(device_name).map(|d| d.stale)
+    }
+
+    /// 将已发现的设备保存到已知设备缓存
+    ///
+    /// 与缓存中暂时未被发现的设备合并，发现任务每轮查询后和停止服务器时会自动保存，
+    /// 未配置缓存路径时不做任何操作
+    ///
+    /// # 返回值
+    ///
+    /// 成功返回 `Ok(())`，写入失败返回错误
+    pub async fn save_peer_cache(&self) -> NetResult<()> {
+        match &self.peer_cache {
+            Some(cache) => {
+                let peers = persisted_peers(&*self.discovered_devices.read().await, &self.config.name);
+                persist_devices(cache, peers, self.config.peer_cache_max_age).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 手动指定设备的首选地址
+    ///
+    /// 连接该设备时，只要首选地址仍在设备通告的地址列表中，就优先于RTT探测结果
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    /// * `addr` - 首选地址
+    pub async fn prefer_address(&self, device_name: &str, addr: SocketAddr) {
+        self.path_selector.prefer_address(device_name, addr).await;
+    }
+
+    /// 清除设备的首选地址，恢复按RTT选择
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    pub async fn clear_preferred_address(&self, device_name: &str) {
+        self.path_selector.clear_preferred_address(device_name).await;
+    }
+
+    /// 获取设备各地址最近一次的RTT测量结果
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回测量结果列表，尚未探测时为空
+    pub async fn address_rtts(&self, device_name: &str) -> Vec<AddressRtt> {
+        self.path_selector.address_rtts(device_name).await
+    }
+
+    /// 订阅新设备发现事件
+    ///
+    /// 每当发现一个此前不在设备列表中的设备时，接收端会收到该设备名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回事件接收端
+    pub fn subscribe_device_discovered(&self) -> broadcast::Receiver<String> {
+        self.discovery_events.subscribe()
+    }
+
     /// 启动设备发现监听任务
     async fn start_device_discovery_listener(&self) {
         let mdns = match &self.mdns_discovery {
@@ -871,8 +2134,12 @@ impl TransportEngine {
 
         let discovered_devices = Arc::clone(&self.discovered_devices);
         let service_type = self.config.mdns_service_type.clone();
+        let discovery_events = self.discovery_events.clone();
+        let peer_cache = self.peer_cache.clone();
+        let peer_cache_max_age = self.config.peer_cache_max_age;
+        let own_name = self.config.name.clone();
 
-        tokio::spawn(async move {
+        self.server_tasks.spawn(async move {
             info!("设备发现监听任务已启动");
 
             loop {
@@ -893,7 +2160,12 @@ impl TransportEngine {
                             if let Some(entry) = devices.get_mut(&device_name) {
                                 entry.addresses = addresses;
                                 entry.last_seen = std::time::SystemTime::now();
-                                debug!("更新设备: {}", device_name);
+                                if std::mem::take(&mut entry.stale) {
+                                    info!("重新发现已知设备: {}", device_name);
+                                    let _ = discovery_events.send(device_name.clone());
+                                } else {
+                                    debug!("更新设备: {}", device_name);
+                                }
                             } else {
                                 let entry = DeviceEntry {
                                     name: device_name.clone(),
@@ -901,31 +2173,41 @@ impl TransportEngine {
                                     authenticated: false,
                                     cert_fingerprint: None,
                                     last_seen: std::time::SystemTime::now(),
+                                    stale: false,
                                 };
                                 devices.insert(device_name.clone(), entry);
                                 info!("发现新设备: {}", device_name);
+                                // 没有订阅者时发送失败是正常的
+                                let _ = discovery_events.send(device_name.clone());
                             }
                         }
 
-                        // 清理过期设备（30秒未见）
+                        // 清理过期设备（两轮查询未见，缓存加载的设备超过最大保留时间）
+                        let expiry = device_expiry(mdns.current_query_interval().await);
                         let now = std::time::SystemTime::now();
                         devices.retain(|name, entry| {
-                            if let Ok(elapsed) = now.duration_since(entry.last_seen) {
-                                if elapsed.as_secs() > 30 {
-                                    info!("移除过期设备: {}", name);
-                                    return false;
-                                }
+                            if entry.is_expired(now, expiry, peer_cache_max_age) {
+                                info!("移除过期设备: {}", name);
+                                return false;
                             }
                             true
                         });
+
+                        if let Some(cache) = &peer_cache {
+                            let peers = persisted_peers(&devices, &own_name);
+                            drop(devices);
+                            if let Err(e) = persist_devices(cache, peers, peer_cache_max_age).await {
+                                warn!("保存已知设备缓存失败: {}", e);
+                            }
+                        }
                     }
                     Err(e) => {
                         warn!("查询mDNS服务失败: {}", e);
                     }
                 }
 
-                // 每15秒查询一次
-                tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
+                // 按mDNS发现服务的自适应间隔查询，设备集合稳定时降低查询频率
+                tokio::time::sleep(mdns.current_query_interval().await).await;
             }
         });
     }
@@ -1180,6 +2462,7 @@ impl TransportEngine {
                     authenticated: true,
                     cert_fingerprint: Some(cert_fingerprint.clone()),
                     last_seen: std::time::SystemTime::now(),
+                    stale: false,
                 };
                 devices.insert(device_name.clone(), entry);
             }
@@ -1232,22 +2515,33 @@ impl TransportEngine {
     ///
     /// 返回清理的设备数
     pub async fn cleanup_devices(&self) -> usize {
+        let expiry = match &self.mdns_discovery {
+            Some(mdns) => device_expiry(mdns.current_query_interval().await),
+            None => DEVICE_EXPIRY,
+        };
         let mut devices = self.discovered_devices.write().await;
         let initial_count = devices.len();
         
-        // 移除30秒以上未见的设备
+        // 移除两轮查询未见的设备，缓存加载的设备超过最大保留时间后移除
         let now = std::time::SystemTime::now();
+        let mut removed_names = Vec::new();
         devices.retain(|name, entry| {
-            if let Ok(elapsed) = now.duration_since(entry.last_seen) {
-                if elapsed.as_secs() > 30 {
-                    info!("清理过期设备: {}", name);
-                    return false;
-                }
+            if entry.is_expired(now, expiry, self.config.peer_cache_max_age) {
+                info!("清理过期设备: {}", name);
+                removed_names.push(name.clone());
+                return false;
             }
             true
         });
-        
+
         let removed = initial_count - devices.len();
+        drop(devices);
+
+        // 过期设备不再接收主题消息
+        for name in &removed_names {
+            self.topics.remove_peer(name).await;
+        }
+
         if removed > 0 {
             info!("清理了 {} 个过期设备", removed);
         }
@@ -1275,39 +2569,94 @@ impl TransportEngine {
         data: Vec<u8>,
         message_type: &str,
     ) -> NetResult<()> {
-        // 创建令牌
+        self.send_with_priority(device_name, data, message_type, MessagePriority::Normal).await
+    }
+
+    /// 简单发送（高优先级）：发送高优先级数据
+    pub async fn send_urgent(
+        &self,
+        device_name: &str,
+        data: Vec<u8>,
+        message_type: &str,
+    ) -> NetResult<()> {
+        self.send_with_priority(device_name, data, message_type, MessagePriority::High).await
+    }
+
+    /// 按指定优先级发送数据
+    ///
+    /// 优先级决定令牌在出站队列中的顺序：`Critical` 控制消息会越过
+    /// 正在排队的低优先级大文件数据块先发出
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 目标设备名称
+    /// * `data` - 要发送的数据
+    /// * `message_type` - 消息类型
+    /// * `priority` - 消息优先级
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果
+    pub async fn send_with_priority(
+        &self,
+        device_name: &str,
+        data: Vec<u8>,
+        message_type: &str,
+        priority: MessagePriority,
+    ) -> NetResult<()> {
         let mut meta = TokenMeta::new(message_type.to_string(), self.config.name.clone());
         meta.receiver_id = Some(device_name.to_string());
+        meta.priority = priority.into();
         meta.requires_ack = true; // 默认需要确认
-        
+
         let token = Token::new(meta, data);
-        
+
         // 记录指标
-        self.metrics.record_send(token.payload.len()).await;
-        
-        // 入队（自动处理优先级）
-        self.priority_queue.enqueue(token.clone()).await?;
-        
-        // 实际发送（带流量控制）
-        self.send_with_flow_control(token).await
+        self.metrics.record_peer_send(device_name, token.payload.len()).await;
+
+        // 按优先级排队发送（带流量控制）
+        self.outbound.send(token, |token| self.send_with_flow_control(token)).await
     }
 
-    /// 简单发送（高优先级）：发送高优先级数据
-    pub async fn send_urgent(
+    /// 可靠发送：发送数据并等待对端确认，超时后自动重传
+    ///
+    /// 等待确认的超时时间和最大重传次数分别由 `EngineConfig::ack_timeout`
+    /// 和 `EngineConfig::max_retransmits` 配置，重传次数计入性能指标。
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 目标设备名称
+    /// * `data` - 要发送的数据
+    /// * `message_type` - 消息类型
+    ///
+    /// # 返回值
+    ///
+    /// 返回收到确认前的重传次数；重传次数耗尽仍未收到确认时返回错误
+    pub async fn send_reliable(
         &self,
         device_name: &str,
         data: Vec<u8>,
         message_type: &str,
-    ) -> NetResult<()> {
+    ) -> NetResult<u32> {
         let mut meta = TokenMeta::new(message_type.to_string(), self.config.name.clone());
         meta.receiver_id = Some(device_name.to_string());
-        meta.priority = crate::token::TokenPriority::High;
         meta.requires_ack = true;
-        
+        meta.attributes.insert(RELIABLE_ATTRIBUTE.to_string(), "true".to_string());
+
         let token = Token::new(meta, data);
-        self.metrics.record_send(token.payload.len()).await;
-        self.priority_queue.enqueue(token.clone()).await?;
-        self.send_with_flow_control(token).await
+        self.metrics.record_peer_send(device_name, token.payload.len()).await;
+
+        let result = self.priority_queue.send_reliable(token, |token, attempt| async move {
+            if attempt > 0 {
+                self.metrics.record_peer_retransmit(device_name).await;
+            }
+            self.send_with_flow_control(token).await
+        }).await;
+
+        if matches!(&result, Err(e) if e.code() == 4502) {
+            self.metrics.record_peer_timeout(device_name).await;
+        }
+        self.track_error(result).await
     }
 
     /// ⚠️ 已废弃：请使用 register_handler() 注册消息处理器
@@ -1366,10 +2715,11 @@ impl TransportEngine {
         note = "请使用 register_handler() 注册消息处理器，消息接收现在是自动的"
     )]
     pub async fn receive_blocking(&self) -> NetResult<(String, String, Vec<u8>)> {
-        let token = self.receive_token(ReceiverMode::Blocking).await?
+        let result = self.receive_token(ReceiverMode::Blocking).await?
             .ok_or_else(|| ErrorInfo::new(4701, "接收被中断".to_string())
                 .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
+                .with_severity(ErrorSeverity::Error));
+        let token = self.track_error(result).await?;
         
         self.metrics.record_receive(token.payload.len()).await;
         
@@ -1380,43 +2730,121 @@ impl TransportEngine {
         ))
     }
 
-    /// 发送大文件：自动分块流式传输
+    /// 发送大文件：自动分块流式传输
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 目标设备名称
+    /// * `data` - 大文件数据
+    /// * `file_type` - 文件类型标识
+    ///
+    /// # 返回值
+    ///
+    /// 返回流ID
+    pub async fn send_large_file(
+        &self,
+        device_name: &str,
+        data: Vec<u8>,
+        file_type: &str,
+    ) -> NetResult<String> {
+        self.send_large_file_with_progress(device_name, data, file_type, |_, _| {}).await
+    }
+
+    /// 发送大文件并报告进度
+    ///
+    /// 每发送完一个数据块调用一次 `on_progress(已发送字节, 总字节)`。
+    /// 丢弃返回的 future 会在下一个数据块之前停止发送。
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 目标设备名称
+    /// * `data` - 大文件数据
+    /// * `file_type` - 文件类型标识
+    /// * `on_progress` - 进度回调
+    ///
+    /// # 返回值
+    ///
+    /// 返回流ID
+    pub async fn send_large_file_with_progress<F>(
+        &self,
+        device_name: &str,
+        data: Vec<u8>,
+        file_type: &str,
+        on_progress: F,
+    ) -> NetResult<String>
+    where
+        F: FnMut(u64, u64) + Send,
+    {
+        let chunk_size = self.config.stream_chunk_size;
+        self.send_large_file_with_chunk_size(device_name, data, file_type, chunk_size, on_progress).await
+    }
+
+    /// 使用指定块大小发送大文件并报告进度
+    ///
+    /// 与 [`send_large_file_with_progress`](Self::send_large_file_with_progress) 相同，
+    /// 但本次传输使用调用方指定的块大小而不是配置的 `stream_chunk_size`，
+    /// 供上层根据链路状况调整块大小。
     ///
     /// # 参数
     ///
     /// * `device_name` - 目标设备名称
     /// * `data` - 大文件数据
     /// * `file_type` - 文件类型标识
+    /// * `chunk_size` - 数据块大小（字节）
+    /// * `on_progress` - 进度回调
     ///
     /// # 返回值
     ///
     /// 返回流ID
-    pub async fn send_large_file(
+    pub async fn send_large_file_with_chunk_size<F>(
         &self,
         device_name: &str,
         data: Vec<u8>,
         file_type: &str,
-    ) -> NetResult<String> {
+        chunk_size: usize,
+        mut on_progress: F,
+    ) -> NetResult<String>
+    where
+        F: FnMut(u64, u64) + Send,
+    {
         let stream_id = uuid::Uuid::new_v4().to_string();
-        info!("开始发送大文件: {} ({} 字节)", stream_id, data.len());
+        let total_bytes = data.len() as u64;
+        info!("开始发送大文件: {} ({} 字节, 块大小 {} 字节)", stream_id, total_bytes, chunk_size);
         
         // 创建流块
-        let chunks = self.stream_manager.create_send_stream(
+        let chunks = self.stream_manager.create_send_stream_with_chunk_size(
             stream_id.clone(),
             data,
             file_type.to_string(),
+            chunk_size,
         ).await?;
 
-        // 发送所有块
+        // 所有块以低优先级排队，高优先级的消息可以越过剩余的块先发出
+        let mut chunk_tokens = Vec::with_capacity(chunks.len());
+        let mut chunk_sizes = Vec::with_capacity(chunks.len());
         for chunk in chunks {
             let token = chunk.to_token(self.config.name.clone());
             let mut meta = token.meta.clone();
             meta.receiver_id = Some(device_name.to_string());
-            
+            meta.priority = TokenPriority::Low;
+
             let chunk_token = Token::new(meta, token.payload);
-            self.metrics.record_send(chunk_token.payload.len()).await;
-            self.priority_queue.enqueue(chunk_token.clone()).await?;
-            self.send_with_flow_control(chunk_token).await?;
+            self.metrics.record_peer_send(device_name, chunk_token.payload.len()).await;
+            chunk_tokens.push(chunk_token);
+            chunk_sizes.push(chunk.data.len() as u64);
+        }
+
+        // 按顺序等待每个块发送完成；中途丢弃返回的 future 时尚未发送的块被撤销
+        let transmit = |token| self.send_with_flow_control(token);
+        let receivers = self.outbound.submit(chunk_tokens).await?;
+        let mut sent_bytes = 0u64;
+        for (receiver, size) in receivers.into_iter().zip(chunk_sizes) {
+            self.outbound.wait(receiver, &transmit).await?;
+
+            if size > 0 {
+                sent_bytes += size;
+                on_progress(sent_bytes, total_bytes);
+            }
         }
 
         info!("大文件发送完成: {}", stream_id);
@@ -1440,9 +2868,37 @@ impl TransportEngine {
 
     /// 获取性能统计：获取当前性能指标
     pub async fn get_performance_stats(&self) -> Metrics {
+        self.metrics.update_inbound_dropped(self.receiver.dropped_count()).await;
+        self.metrics.update_dead_letters(self.router.dead_letter_counts().await).await;
         self.metrics.get_metrics().await
     }
 
+    /// 获取单个对端设备的指标
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    ///
+    /// # 返回值
+    ///
+    /// 返回该设备的收发、RTT和丢包统计，尚未统计该设备时返回 `None`
+    pub async fn peer_metrics(&self, device_name: &str) -> Option<PeerMetrics> {
+        self.metrics.get_metrics().await.peers.remove(device_name)
+    }
+
+    /// 重置错误统计：清空错误计数和按错误码的统计
+    pub async fn reset_errors(&self) {
+        self.metrics.reset_errors().await;
+    }
+
+    /// 记录网络错误：按错误码计入性能指标后原样返回结果
+    async fn track_error<T>(&self, result: NetResult<T>) -> NetResult<T> {
+        if let Err(ref e) = result {
+            self.metrics.record_error(e.code(), format!("{:?}", e.category())).await;
+        }
+        result
+    }
+
     /// 获取流量控制统计：获取流量控制状态
     pub async fn get_flow_control_stats(&self) -> FlowControlStats {
         self.flow_controller.get_stats().await
@@ -1461,17 +2917,23 @@ impl TransportEngine {
         info!("状态: {:?}", fc_stats.congestion_state);
     }
 
+    /// 广播的接收方：除本设备外所有已发现的设备
+    pub async fn broadcast_targets(&self) -> Vec<String> {
+        self.list_discovered_devices().await
+            .into_iter()
+            .filter(|name| *name != self.config.name)
+            .collect()
+    }
+
     /// 广播消息：向所有已发现的设备发送消息
     pub async fn broadcast(&self, data: Vec<u8>, message_type: &str) -> NetResult<usize> {
-        let devices = self.list_discovered_devices().await;
+        let devices = self.broadcast_targets().await;
         let mut sent_count = 0;
 
         for device_name in devices {
-            if device_name != self.config.name {
-                match self.send_to(&device_name, data.clone(), message_type).await {
-                    Ok(_) => sent_count += 1,
-                    Err(e) => warn!("广播到 {} 失败: {}", device_name, e),
-                }
+            match self.send_to(&device_name, data.clone(), message_type).await {
+                Ok(_) => sent_count += 1,
+                Err(e) => warn!("广播到 {} 失败: {}", device_name, e),
             }
         }
 
@@ -1479,6 +2941,161 @@ impl TransportEngine {
         Ok(sent_count)
     }
 
+    /// 订阅主题：加入主题并向所有已发现的设备公告
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    ///
+    /// # 返回值
+    ///
+    /// 返回主题消息接收器
+    pub async fn subscribe_topic(&self, topic: &str) -> NetResult<broadcast::Receiver<TopicMessage>> {
+        let receiver = self.topics.subscribe(topic).await;
+        self.announce_to_devices(vec![topic.to_string()], true).await?;
+        info!("订阅主题: {}", topic);
+        Ok(receiver)
+    }
+
+    /// 退订主题：关闭本地接收器并通知其他设备
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    ///
+    /// # 返回值
+    ///
+    /// 返回退订结果，未订阅过该主题时不做任何事
+    pub async fn unsubscribe_topic(&self, topic: &str) -> NetResult<()> {
+        if self.topics.unsubscribe(topic).await {
+            self.announce_to_devices(vec![topic.to_string()], false).await?;
+            info!("退订主题: {}", topic);
+        }
+        Ok(())
+    }
+
+    /// 重新公告本地订阅的所有主题，供新发现的设备获知成员关系
+    ///
+    /// 与设备握手成功后会自动在该连接上公告，一般无需手动调用
+    pub async fn announce_topics(&self) -> NetResult<()> {
+        let topics = self.topics.local_topics().await;
+        if topics.is_empty() {
+            return Ok(());
+        }
+        self.announce_to_devices(topics, true).await
+    }
+
+    /// 发布主题消息：发送给所有已知订阅者，本地订阅者也会收到
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    /// * `payload` - 消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回成功发送到的远端订阅者数量
+    pub async fn publish_topic(&self, topic: &str, payload: Vec<u8>) -> NetResult<usize> {
+        let mut sent_count = 0;
+        for token in self.topics.publish(topic, payload).await {
+            let receiver = token.meta.receiver_id.clone().unwrap_or_default();
+            self.metrics.record_peer_send(&receiver, token.payload.len()).await;
+            match self.send_with_flow_control(token).await {
+                Ok(_) => sent_count += 1,
+                Err(e) => warn!("发布主题 {} 到 {} 失败: {}", topic, receiver, e),
+            }
+        }
+        debug!("主题 {} 发布到 {} 个订阅者", topic, sent_count);
+        Ok(sent_count)
+    }
+
+    /// 认领逻辑通道，对端在任意连接上打开的该通道都从返回的接收端取出
+    ///
+    /// 丢弃接收端即释放通道
+    ///
+    /// # 参数
+    ///
+    /// * `channel_id` - 通道号，如 [`crate::MESSAGE_CHANNEL`]
+    ///
+    /// # 返回值
+    ///
+    /// 返回入站通道的接收端，通道已被认领时返回错误
+    pub fn claim_channel(&self, channel_id: ChannelId) -> NetResult<mpsc::Receiver<Channel>> {
+        self.channels.claim(channel_id)
+    }
+
+    /// 把某类令牌改为经由逻辑通道收发
+    ///
+    /// 本端认领该通道，对端在通道上发送的令牌与单向流上的令牌一样进入入站队列；
+    /// 发往对端的匹配令牌在到对端的长期通道上发送，对端没有认领该通道时仍使用单向流。
+    /// 重复调用相同的参数不做任何事
+    ///
+    /// # 参数
+    ///
+    /// * `type_prefix` - 令牌类型前缀，如 `"bey.message."`
+    /// * `channel_id` - 通道号，如 [`crate::MESSAGE_CHANNEL`]
+    ///
+    /// # 返回值
+    ///
+    /// 返回设置结果，通道已被其他接收方认领时返回错误
+    pub fn route_tokens_over_channel(&self, type_prefix: &str, channel_id: ChannelId) -> NetResult<()> {
+        // 通道已由先前的路由认领，入站令牌已在接收
+        if self.channel_routes.routes_channel(channel_id) {
+            self.channel_routes.add(type_prefix, channel_id);
+            return Ok(());
+        }
+        let mut incoming = self.channels.claim(channel_id)?;
+        self.channel_routes.add(type_prefix, channel_id);
+
+        let connection_infos = Arc::clone(&self.connection_infos);
+        let inbound = self._sender.clone();
+        self.maintenance_tasks.spawn(async move {
+            while let Some(channel) = incoming.recv().await {
+                let remote_addr = channel.remote_addr();
+                let Some(peer_name) = connection_infos.read().await.get(&remote_addr).map(|negotiated| negotiated.peer_name.clone()) else {
+                    warn!("拒绝未完成握手的 {} 打开的通道 {}", remote_addr, channel.id());
+                    continue;
+                };
+                tokio::spawn(wire::serve_channel(channel, peer_name, inbound.clone()));
+            }
+        });
+        Ok(())
+    }
+
+    /// 在到对端的现有QUIC连接上打开逻辑通道
+    ///
+    /// 同一连接上的各个通道互不阻塞，通道内按长度前缀帧收发数据
+    ///
+    /// # 参数
+    ///
+    /// * `peer` - 对端地址，需要已建立连接
+    /// * `channel_id` - 通道号
+    ///
+    /// # 返回值
+    ///
+    /// 返回已被对端接受的通道，没有到对端的连接或对端没有认领该通道时返回错误
+    pub async fn open_channel(&self, peer: SocketAddr, channel_id: ChannelId) -> NetResult<Channel> {
+        let connection = self.transport.read().await.connection(peer).await
+            .ok_or_else(|| ErrorInfo::new(4946, format!("没有到 {} 的连接，无法打开通道 {}", peer, channel_id))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+        self.track_error(channel::open(&connection, channel_id).await).await
+    }
+
+    /// 向所有已发现的设备发送主题公告
+    async fn announce_to_devices(&self, topics: Vec<String>, subscribed: bool) -> NetResult<()> {
+        for device_name in self.list_discovered_devices().await {
+            if device_name == self.config.name {
+                continue;
+            }
+            let token = self.topics.announcement(&device_name, topics.clone(), subscribed)?;
+            if let Err(e) = self.send_with_flow_control(token).await {
+                warn!("向 {} 公告主题失败: {}", device_name, e);
+            }
+        }
+        Ok(())
+    }
+
     /// 群发消息：向指定的一组设备发送消息
     ///
     /// # 参数
@@ -1526,6 +3143,17 @@ impl TransportEngine {
         Ok(sent_count)
     }
 
+    /// 获取组成员：属于该组的已发现设备，不含本设备
+    ///
+    /// # 参数
+    ///
+    /// * `group_name` - 组名称
+    pub async fn group_members(&self, _group_name: &str) -> Vec<String> {
+        // 在实际实现中，应该从设备元数据中读取组信息
+        // 这里简化为所有发现的设备
+        self.broadcast_targets().await
+    }
+
     /// 组发消息：向特定组的所有成员发送消息
     ///
     /// 组信息从设备元数据的 "group" 属性中读取
@@ -1546,16 +3174,7 @@ impl TransportEngine {
         message_type: &str,
     ) -> NetResult<usize> {
         // 获取属于该组的所有设备
-        let devices = self.discovered_devices.read().await;
-        let group_devices: Vec<String> = devices.keys()
-            .filter(|name| {
-                // 在实际实现中，应该从设备元数据中读取组信息
-                // 这里简化为所有发现的设备
-                **name != self.config.name
-            })
-            .cloned()
-            .collect();
-        drop(devices);
+        let group_devices = self.group_members(group_name).await;
 
         if group_devices.is_empty() {
             warn!("组 {} 中没有设备", group_name);
@@ -1581,10 +3200,15 @@ impl TransportEngine {
         // 记录发送
         self.flow_controller.on_send(size).await?;
 
-        // 实际发送令牌
-        self.send_token(token).await?;
-
-        Ok(())
+        // 实际发送令牌，对端确认收到后释放窗口
+        let started = std::time::Instant::now();
+        match self.send_token(token).await {
+            Ok(()) => self.flow_controller.on_ack(size, started.elapsed()).await,
+            Err(e) => {
+                self.flow_controller.on_failed(size).await;
+                Err(e)
+            }
+        }
     }
 
     /// 确认消息：确认收到的消息
@@ -1614,6 +3238,735 @@ mod tests {
         // 暂时跳过实际创建测试
     }
 
+    #[tokio::test]
+    async fn test_engine_start_stop_lifecycle() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let config = EngineConfig {
+            name: "lifecycle-test".to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_mdns: false,
+            transport_config: TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path()),
+            ..Default::default()
+        };
+        let engine = TransportEngine::new(config).await.expect("创建引擎失败");
+        assert!(!engine.is_running());
+
+        engine.start_server().await.expect("启动服务器失败");
+        assert!(engine.is_running());
+
+        engine.stop_server().await.expect("停止服务器失败");
+        assert!(!engine.is_running());
+        assert_eq!(engine.current_state().await, ConnectionState::Disconnected);
+
+        // 重复停止不应出错
+        engine.stop_server().await.expect("重复停止服务器失败");
+        assert_eq!(engine.current_state().await, ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn test_stop_server_terminates_background_tasks() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let engine = create_handshake_engine("task-test", temp_dir.path(), false).await;
+        assert_eq!(engine.active_task_count(), 0);
+
+        engine.start_server().await.expect("启动服务器失败");
+        assert!(engine.active_task_count() > 0);
+
+        // 停止后不应有后台任务继续运行
+        engine.stop_server().await.expect("停止服务器失败");
+        assert_eq!(engine.active_task_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connect_failures_counted_by_error_code() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let config = EngineConfig {
+            name: "error-breakdown-test".to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_mdns: false,
+            transport_config: TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path()),
+            ..Default::default()
+        };
+        let engine = TransportEngine::new(config).await.expect("创建引擎失败");
+
+        // 端口0不是有效的远程地址，连接会立即失败
+        let unreachable: SocketAddr = "127.0.0.1:0".parse().expect("地址解析失败");
+        for _ in 0..3 {
+            let err = engine.connect(unreachable).await.expect_err("连接应该失败");
+            assert_eq!(err.code(), 4304);
+        }
+
+        let metrics = engine.get_performance_stats().await;
+        assert_eq!(metrics.error_breakdown().get(&4304), Some(&3));
+        assert_eq!(metrics.error_count, 3);
+
+        engine.reset_errors().await;
+        let metrics = engine.get_performance_stats().await;
+        assert!(metrics.error_breakdown().is_empty());
+        assert_eq!(metrics.error_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_inbound_overflow_reported_in_metrics() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let config = EngineConfig {
+            name: "overflow-test".to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_mdns: false,
+            receiver_buffer_size: 2,
+            receiver_overflow_policy: OverflowPolicy::DropOldest,
+            transport_config: TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path()),
+            ..Default::default()
+        };
+        let engine = TransportEngine::new(config).await.expect("创建引擎失败");
+
+        for i in 0..5u8 {
+            let meta = TokenMeta::new("test_message".to_string(), "sender".to_string());
+            engine._sender.send(Token::new(meta, vec![i])).await.expect("发送令牌失败");
+        }
+
+        assert_eq!(engine.receiver().pending_count().await, 2);
+        let metrics = engine.get_performance_stats().await;
+        assert_eq!(metrics.inbound_dropped, 3);
+    }
+
+    #[tokio::test]
+    async fn test_replayed_token_is_dropped() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let config = EngineConfig {
+            name: "replay-test".to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_mdns: false,
+            replay_window: Duration::from_secs(60),
+            transport_config: TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path()),
+            ..Default::default()
+        };
+        let engine = TransportEngine::new(config).await.expect("创建引擎失败");
+        assert_eq!(engine.config().replay_window, Duration::from_secs(60));
+
+        let handled_count = Arc::new(AtomicUsize::new(0));
+        engine.register_handler(Arc::new(TestHandler {
+            handled_count: Arc::clone(&handled_count),
+            expected_type: "test_message".to_string(),
+        })).await.expect("注册处理器失败");
+
+        // 同一个令牌收到两次，第二次被识别为重放
+        let token = Token::new(TokenMeta::new("test_message".to_string(), "sender".to_string()), vec![1]);
+        engine._sender.send(token.clone()).await.expect("发送令牌失败");
+        engine._sender.send(token).await.expect("发送令牌失败");
+
+        // 另一个令牌正常处理
+        let other = Token::new(TokenMeta::new("test_message".to_string(), "sender".to_string()), vec![2]);
+        engine._sender.send(other).await.expect("发送令牌失败");
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while handled_count.load(Ordering::SeqCst) < 2 || engine.receiver().pending_count().await > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("等待令牌处理超时");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(handled_count.load(Ordering::SeqCst), 2);
+        let metrics = engine.get_performance_stats().await;
+        assert_eq!(metrics.replays_dropped, 1);
+        assert!(metrics.to_prometheus().contains("bey_replays_dropped_total 1"));
+    }
+
+    #[tokio::test]
+    async fn test_multiple_named_listeners() {
+        use bey_transport::policy_engine::PolicyAction;
+
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let config = EngineConfig {
+            name: "listener-test".to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_mdns: false,
+            transport_config: TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path()),
+            ..Default::default()
+        };
+        let engine = TransportEngine::new(config).await.expect("创建引擎失败");
+
+        let allow_all = PolicySet::new(
+            "allow-all".to_string(),
+            "允许所有".to_string(),
+            "测试用策略集合".to_string(),
+            PolicyAction::Allow,
+        );
+        let storage_addr = engine.add_listener("storage", 0, allow_all.clone(), true).await
+            .expect("添加存储监听器失败");
+        let peer_addr = engine.add_listener("peer", 0, allow_all.clone(), false).await
+            .expect("添加对等监听器失败");
+        assert_ne!(storage_addr.port(), peer_addr.port());
+
+        let err = engine.add_listener("peer", 0, allow_all.clone(), false).await
+            .expect_err("重复的监听器名称应该失败");
+        assert_eq!(err.code(), 4332);
+
+        let require_client_cert: Vec<(String, bool)> = engine.list_listeners().await
+            .into_iter()
+            .map(|l| (l.name, l.require_client_cert))
+            .collect();
+        assert_eq!(require_client_cert, vec![("peer".to_string(), false), ("storage".to_string(), true)]);
+
+        // 分别连接两个监听器：客户端与引擎共用证书目录（同一CA），按引擎的设备ID校验证书名称
+        let mut client = SecureTransport::new(
+            TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path())
+                .with_server_name("listener-test.bey.local".to_string()),
+            "listener-client".to_string(),
+        ).await.expect("创建客户端失败");
+        client.set_policy_set(allow_all).await.expect("设置客户端策略失败");
+
+        let loopback = IpAddr::V4(std::net::Ipv4Addr::LOCALHOST);
+        for addr in [storage_addr, peer_addr] {
+            client.connect(SocketAddr::new(loopback, addr.port())).await.expect("连接监听器失败");
+        }
+
+        // 服务端异步接受连接，等待每个监听器各接受一个连接
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        loop {
+            let listeners = engine.list_listeners().await;
+            if listeners.iter().all(|l| l.accepted_connections == 1) {
+                break;
+            }
+            assert!(tokio::time::Instant::now() < deadline, "监听器未接受连接: {:?}", listeners);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        engine.stop_listener("storage").await.expect("关闭监听器失败");
+        assert_eq!(engine.listener_connections("storage").await, None);
+        assert_eq!(engine.listener_connections("peer").await, Some(1));
+        assert_eq!(engine.stop_listener("storage").await.expect_err("重复关闭应该失败").code(), 4333);
+
+        engine.stop_server().await.expect("停止服务器失败");
+        assert!(engine.list_listeners().await.is_empty());
+
+        client.stop().await;
+    }
+
+    /// 创建仅监听回环地址、不启用认证和发现的测试引擎
+    ///
+    /// 设备证书签发给 `<设备ID>.bey.local`，客户端按服务端的设备ID校验证书名称
+    async fn create_handshake_engine(name: &str, certificates_dir: &std::path::Path, enable_encryption: bool) -> TransportEngine {
+        create_allow_all_engine(handshake_engine_config(name, certificates_dir, enable_encryption)).await
+    }
+
+    fn handshake_engine_config(name: &str, certificates_dir: &std::path::Path, enable_encryption: bool) -> EngineConfig {
+        EngineConfig {
+            name: name.to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_encryption,
+            enable_mdns: false,
+            transport_config: TransportConfig::new()
+                .with_bind_address(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST))
+                .with_port(0)
+                .with_certificates_dir(certificates_dir)
+                .with_server_name("handshake-server.bey.local".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn create_allow_all_engine(config: EngineConfig) -> TransportEngine {
+        let engine = TransportEngine::new(config).await.expect("创建引擎失败");
+
+        let allow_all = PolicySet::new(
+            "allow-all".to_string(),
+            "允许所有".to_string(),
+            "测试用策略集合".to_string(),
+            bey_transport::policy_engine::PolicyAction::Allow,
+        );
+        engine.set_policy_set(allow_all).await.expect("设置策略失败");
+        engine
+    }
+
+    #[tokio::test]
+    async fn test_handshake_negotiates_connection_info() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_handshake_engine("handshake-server", temp_dir.path(), true).await;
+        server.start_server().await.expect("启动服务器失败");
+        let server_addr = server.local_addr().await.expect("服务器未绑定地址");
+        let mut server_events = server.subscribe_connection_events();
+
+        // 双方都启用加密：协商为标准加密、不认证
+        let client = create_handshake_engine("handshake-client", temp_dir.path(), true).await;
+        let mut client_events = client.subscribe_connection_events();
+        client.connect(server_addr).await.expect("握手失败");
+
+        let info = client.connection_info(server_addr).await.expect("应记录协商结果");
+        assert_eq!(info.remote_address, server_addr);
+        assert_eq!(info.protocol, bey_types::ProtocolType::Quic);
+        assert_eq!(info.security_level, SecurityLevel::Standard);
+        assert_eq!(info.auth_method, AuthMethod::None);
+        assert_eq!(info.status, bey_types::ConnectionStatus::Connected);
+        assert_eq!(client_events.recv().await.expect("接收事件失败").event, StateEvent::Authenticated);
+
+        // 服务端记录相同的协商结果，本端地址与客户端观察到的地址对应
+        let server_event = tokio::time::timeout(Duration::from_secs(5), server_events.recv()).await
+            .expect("等待服务端事件超时")
+            .expect("接收事件失败");
+        assert_eq!(server_event.event, StateEvent::Authenticated);
+        let server_info = server.connection_info(server_event.remote_addr).await.expect("服务端应记录协商结果");
+        assert_eq!(server_info.remote_address, info.local_address);
+        assert_eq!(server_info.security_level, SecurityLevel::Standard);
+        assert_eq!(server_info.auth_method, AuthMethod::None);
+
+        // 未启用加密的对端不兼容，被拒绝并给出原因
+        let plain = create_handshake_engine("handshake-plain", temp_dir.path(), false).await;
+        let mut plain_events = plain.subscribe_connection_events();
+        let err = plain.connect(server_addr).await.expect_err("不兼容的对端应被拒绝");
+        assert_eq!(err.code(), 4903);
+        assert!(err.message().contains("安全级别"), "拒绝原因应说明不兼容的安全级别: {}", err);
+        assert!(plain.connection_info(server_addr).await.is_none());
+        match plain_events.recv().await.expect("接收事件失败").event {
+            StateEvent::Rejected(reason) => assert!(reason.contains("安全级别")),
+            event => panic!("应为拒绝事件: {:?}", event),
+        }
+
+        server.stop_server().await.expect("停止服务器失败");
+    }
+
+    #[tokio::test]
+    async fn test_server_closes_connection_without_handshake() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_handshake_engine("handshake-server", temp_dir.path(), false).await;
+        server.start_server().await.expect("启动服务器失败");
+        let server_addr = server.local_addr().await.expect("服务器未绑定地址");
+
+        // 只建立QUIC连接，不发送握手提议
+        let client = create_handshake_engine("silent-client", temp_dir.path(), false).await;
+        let mut events = client.transport.read().await.events();
+        client.transport.write().await.connect(server_addr).await.expect("建立连接失败");
+
+        let code = tokio::time::timeout(handshake::HANDSHAKE_TIMEOUT + Duration::from_secs(5), async {
+            loop {
+                if let TransportEvent::Disconnected { code, .. } = events.recv().await.expect("接收事件失败") {
+                    return code;
+                }
+            }
+        }).await.expect("服务端应在握手超时后关闭连接");
+        assert_eq!(code, Some(bey_transport::CloseCode::ProtocolError.code()));
+
+        server.stop_server().await.expect("停止服务器失败");
+    }
+
+    #[tokio::test]
+    async fn test_channels_multiplexed_over_one_connection() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_handshake_engine("handshake-server", temp_dir.path(), true).await;
+        let mut messages = server.claim_channel(crate::MESSAGE_CHANNEL).expect("认领消息通道失败");
+        let mut clipboard = server.claim_channel(crate::CLIPBOARD_CHANNEL).expect("认领剪切板通道失败");
+        assert_eq!(server.claim_channel(crate::MESSAGE_CHANNEL).err().map(|e| e.code()), Some(4941));
+        server.start_server().await.expect("启动服务器失败");
+        let server_addr = server.local_addr().await.expect("服务器未绑定地址");
+
+        let client = create_handshake_engine("channel-client", temp_dir.path(), true).await;
+        client.connect(server_addr).await.expect("连接失败");
+
+        // 迟迟不发送通道头的流不影响同一连接上其他通道的打开
+        let connection = client.transport.read().await.connection(server_addr).await.expect("应有到服务器的连接");
+        let (mut stalled, _stalled_recv) = connection.open_bi().await.expect("打开流失败");
+        stalled.write_all(b"B").await.expect("写入失败");
+
+        let started = std::time::Instant::now();
+        let mut client_messages = client.open_channel(server_addr, crate::MESSAGE_CHANNEL).await.expect("打开消息通道失败");
+        let mut client_clipboard = client.open_channel(server_addr, crate::CLIPBOARD_CHANNEL).await.expect("打开剪切板通道失败");
+
+        async fn accept(receiver: &mut mpsc::Receiver<Channel>) -> Channel {
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await
+                .expect("等待通道超时")
+                .expect("通道接收端已关闭")
+        }
+        let mut server_messages = accept(&mut messages).await;
+        let mut server_clipboard = accept(&mut clipboard).await;
+        assert_eq!(server_messages.id(), crate::MESSAGE_CHANNEL);
+        assert_eq!(server_clipboard.id(), crate::CLIPBOARD_CHANNEL);
+        assert_eq!(server_messages.remote_addr(), server_clipboard.remote_addr(), "两个通道应在同一连接上");
+        assert!(started.elapsed() < crate::CHANNEL_OPEN_TIMEOUT, "打开通道不应等待停滞的流");
+
+        // 两个通道交替发送，各自按顺序收到自己的帧
+        for i in 0..3 {
+            client_messages.send(format!("message-{}", i).as_bytes()).await.expect("发送消息帧失败");
+            client_clipboard.send(format!("clipboard-{}", i).as_bytes()).await.expect("发送剪切板帧失败");
+        }
+        client_messages.finish().expect("结束消息通道失败");
+        for i in 0..3 {
+            let frame = server_clipboard.recv().await.expect("接收剪切板帧失败").expect("剪切板通道已结束");
+            assert_eq!(frame, format!("clipboard-{}", i).into_bytes());
+        }
+        for i in 0..3 {
+            let frame = server_messages.recv().await.expect("接收消息帧失败").expect("消息通道已结束");
+            assert_eq!(frame, format!("message-{}", i).into_bytes());
+        }
+        assert_eq!(server_messages.recv().await.expect("接收消息帧失败"), None);
+
+        // 反方向回复，消息通道结束不影响剪切板通道
+        server_clipboard.send(b"ack").await.expect("回复失败");
+        assert_eq!(client_clipboard.recv().await.expect("接收回复失败"), Some(b"ack".to_vec()));
+
+        // 未被认领的通道被拒绝
+        let err = client.open_channel(server_addr, crate::FILE_CHANNEL).await.err().expect("未认领的通道应被拒绝");
+        assert_eq!(err.code(), 4943);
+
+        // 认领方积压已满时新通道被重置，已打开的通道不受影响
+        let mut backlog = Vec::new();
+        for _ in 0..channel::CHANNEL_BACKLOG {
+            backlog.push(client.open_channel(server_addr, crate::CLIPBOARD_CHANNEL).await.expect("打开剪切板通道失败"));
+        }
+        let err = client.open_channel(server_addr, crate::CLIPBOARD_CHANNEL).await.err().expect("积压已满时应拒绝");
+        assert_eq!(err.code(), 4947);
+        server_clipboard.send(b"still open").await.expect("回复失败");
+        assert_eq!(client_clipboard.recv().await.expect("接收回复失败"), Some(b"still open".to_vec()));
+
+        server.stop_server().await.expect("停止服务器失败");
+    }
+
+    /// 把收到的令牌转发到通道的测试处理器，可选返回响应令牌
+    struct ForwardingHandler {
+        token_type: String,
+        tokens: mpsc::UnboundedSender<Token>,
+        respond: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl TokenHandler for ForwardingHandler {
+        fn token_types(&self) -> Vec<TokenType> {
+            vec![self.token_type.clone()]
+        }
+
+        async fn handle_token(&self, token: Token) -> NetResult<Option<Token>> {
+            let response = self.respond.then(|| Token::response(&token, b"pong".to_vec()));
+            let _ = self.tokens.send(token);
+            Ok(response)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tokens_sent_over_connection() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_handshake_engine("handshake-server", temp_dir.path(), false).await;
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        server.register_handler(Arc::new(ForwardingHandler {
+            token_type: "wire_ping".to_string(),
+            tokens: server_tx,
+            respond: true,
+        })).await.expect("注册处理器失败");
+        server.start_server().await.expect("启动服务器失败");
+        let server_addr = server.local_addr().await.expect("服务器未绑定地址");
+
+        let client = create_handshake_engine("wire-client", temp_dir.path(), false).await;
+        let (client_tx, mut client_rx) = mpsc::unbounded_channel();
+        client.register_handler(Arc::new(ForwardingHandler {
+            token_type: "wire_ping_response".to_string(),
+            tokens: client_tx,
+            respond: false,
+        })).await.expect("注册处理器失败");
+        client.connect(server_addr).await.expect("连接失败");
+        assert_eq!(client.peer_connection_addr("handshake-server").await, Some(server_addr));
+
+        // 令牌按接收者名称找到已握手的连接，经优先级流发送；自称的发送方被改写为握手时的名称
+        let meta = TokenMeta::new("wire_ping".to_string(), "spoofed".to_string())
+            .with_receiver("handshake-server".to_string())
+            .with_priority(TokenPriority::High);
+        client.send_with_flow_control(Token::new(meta, b"ping".to_vec())).await.expect("发送令牌失败");
+
+        let received = tokio::time::timeout(Duration::from_secs(5), server_rx.recv()).await
+            .expect("等待服务端收到令牌超时")
+            .expect("通道已关闭");
+        assert_eq!(received.payload, b"ping".to_vec());
+        assert_eq!(received.meta.sender_id, "wire-client");
+        assert_eq!(received.meta.priority, TokenPriority::High);
+        let peer_addr = wire::peer_addr(&received).expect("应记录来源连接");
+        assert!(server.connection_info(peer_addr).await.is_some(), "来源应为已握手的连接");
+
+        // 处理器的响应沿来源连接发回客户端
+        let response = tokio::time::timeout(Duration::from_secs(5), client_rx.recv()).await
+            .expect("等待客户端收到响应超时")
+            .expect("通道已关闭");
+        assert_eq!(response.payload, b"pong".to_vec());
+        assert_eq!(response.meta.sender_id, "handshake-server");
+        assert_eq!(wire::peer_addr(&response), Some(server_addr));
+        assert_eq!(client.flow_controller.get_stats().await.bytes_in_flight, 0, "对端确认后应释放窗口");
+
+        // 没有连接的接收者无法发送
+        let meta = TokenMeta::new("wire_ping".to_string(), "wire-client".to_string())
+            .with_receiver("unknown-device".to_string());
+        let err = client.send_token(Token::new(meta, Vec::new())).await.expect_err("未知接收者应失败");
+        assert_eq!(err.code(), 4331);
+
+        server.stop_server().await.expect("停止服务器失败");
+    }
+
+    #[tokio::test]
+    async fn test_tokens_routed_over_channel() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_handshake_engine("handshake-server", temp_dir.path(), false).await;
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        server.register_handler(Arc::new(ForwardingHandler {
+            token_type: "chan_ping".to_string(),
+            tokens: server_tx,
+            respond: true,
+        })).await.expect("注册处理器失败");
+        server.route_tokens_over_channel("chan_", crate::MESSAGE_CHANNEL).expect("设置通道路由失败");
+        server.route_tokens_over_channel("chan_", crate::MESSAGE_CHANNEL).expect("重复设置通道路由应成功");
+        server.start_server().await.expect("启动服务器失败");
+        let server_addr = server.local_addr().await.expect("服务器未绑定地址");
+
+        let client = create_handshake_engine("wire-client", temp_dir.path(), false).await;
+        let (client_tx, mut client_rx) = mpsc::unbounded_channel();
+        client.register_handler(Arc::new(ForwardingHandler {
+            token_type: "chan_ping_response".to_string(),
+            tokens: client_tx,
+            respond: false,
+        })).await.expect("注册处理器失败");
+        client.route_tokens_over_channel("chan_", crate::MESSAGE_CHANNEL).expect("设置通道路由失败");
+        client.connect(server_addr).await.expect("连接失败");
+
+        // 同一通道上按顺序发送的令牌按顺序到达，发送方按连接改写
+        for i in 0..3u8 {
+            let meta = TokenMeta::new("chan_ping".to_string(), "spoofed".to_string())
+                .with_receiver("handshake-server".to_string());
+            client.send_token(Token::new(meta, vec![i])).await.expect("发送令牌失败");
+        }
+        for i in 0..3u8 {
+            let received = tokio::time::timeout(Duration::from_secs(5), server_rx.recv()).await
+                .expect("等待服务端收到令牌超时")
+                .expect("通道已关闭");
+            assert_eq!(received.payload, vec![i]);
+            assert_eq!(received.meta.sender_id, "wire-client");
+        }
+
+        // 响应同样经由通道发回
+        for _ in 0..3 {
+            let response = tokio::time::timeout(Duration::from_secs(5), client_rx.recv()).await
+                .expect("等待客户端收到响应超时")
+                .expect("通道已关闭");
+            assert_eq!(response.meta.sender_id, "handshake-server");
+            assert_eq!(wire::peer_addr(&response), Some(server_addr));
+        }
+
+        // 通道已被其他接收方认领时无法设置路由
+        let _files = client.claim_channel(crate::FILE_CHANNEL).expect("认领文件通道失败");
+        let err = client.route_tokens_over_channel("file_", crate::FILE_CHANNEL).expect_err("通道已被认领");
+        assert_eq!(err.code(), 4941);
+
+        server.stop_server().await.expect("停止服务器失败");
+    }
+
+    #[tokio::test]
+    async fn test_topics_announced_on_connect() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_handshake_engine("handshake-server", temp_dir.path(), false).await;
+        let _server_topic = server.subscribe_topic("group").await.expect("订阅主题失败");
+        server.start_server().await.expect("启动服务器失败");
+        let server_addr = server.local_addr().await.expect("服务器未绑定地址");
+
+        // 连接前订阅的主题在握手后经连接互相公告
+        let client = create_handshake_engine("topic-client", temp_dir.path(), false).await;
+        let _client_topic = client.subscribe_topic("chat").await.expect("订阅主题失败");
+        client.connect(server_addr).await.expect("连接失败");
+
+        for (engine, topic, peer) in [(&client, "group", "handshake-server"), (&server, "chat", "topic-client")] {
+            let mut subscribers = Vec::new();
+            for _ in 0..100 {
+                subscribers = engine.topics.subscribers(topic).await;
+                if !subscribers.is_empty() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            assert_eq!(subscribers, vec![peer.to_string()], "应从连接公告中获知 {} 的订阅者", topic);
+        }
+
+        server.stop_server().await.expect("停止服务器失败");
+    }
+
+    #[tokio::test]
+    async fn test_tcp_fallback_when_udp_blocked() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+
+        // 占用一个UDP端口但从不响应，模拟UDP被阻断
+        let black_hole = std::net::UdpSocket::bind("127.0.0.1:0").expect("绑定UDP套接字失败");
+        let blocked_addr = black_hole.local_addr().expect("获取地址失败");
+
+        // 服务端在同一端口号上提供TCP回退
+        let mut server_config = handshake_engine_config("fallback-server", temp_dir.path(), true);
+        server_config.enable_tcp_fallback = true;
+        server_config.tcp_fallback_port = Some(blocked_addr.port());
+        let server = create_allow_all_engine(server_config).await;
+        let (server_tx, mut server_rx) = mpsc::unbounded_channel();
+        server.register_handler(Arc::new(ForwardingHandler {
+            token_type: "fallback_ping".to_string(),
+            tokens: server_tx,
+            respond: true,
+        })).await.expect("注册处理器失败");
+        server.start_server().await.expect("启动服务器失败");
+        let mut server_events = server.subscribe_connection_events();
+
+        // 回退连接的mTLS握手与QUIC一样按服务端的设备ID校验证书名称
+        let mut client_config = handshake_engine_config("fallback-client", temp_dir.path(), true);
+        client_config.enable_tcp_fallback = true;
+        client_config.transport_config = client_config.transport_config
+            .with_connection_timeout(Duration::from_secs(1))
+            .with_server_name("fallback-server.bey.local".to_string());
+        let client = create_allow_all_engine(client_config).await;
+        let (client_tx, mut client_rx) = mpsc::unbounded_channel();
+        client.register_handler(Arc::new(ForwardingHandler {
+            token_type: "fallback_ping_response".to_string(),
+            tokens: client_tx,
+            respond: false,
+        })).await.expect("注册处理器失败");
+
+        // 令牌负载加密使用双方共享的主密钥
+        for engine in [&server, &client] {
+            *engine.master_key.write().await = Some(vec![7u8; 32]);
+        }
+
+        client.connect(blocked_addr).await.expect("TCP回退连接失败");
+        let info = client.connection_info(blocked_addr).await.expect("应记录协商结果");
+        assert_eq!(info.protocol, bey_types::ProtocolType::Tcp);
+        assert_eq!(info.security_level, tcp_fallback::TCP_FALLBACK_SECURITY_LEVEL, "经mTLS认证的TCP回退应保持协商的安全级别");
+        assert_eq!(info.security_level, SecurityLevel::Standard);
+
+        let server_event = tokio::time::timeout(Duration::from_secs(5), server_events.recv()).await
+            .expect("等待服务端事件超时")
+            .expect("接收事件失败");
+        assert_eq!(server_event.event, StateEvent::Authenticated);
+        let server_info = server.connection_info(server_event.remote_addr).await.expect("服务端应记录协商结果");
+        assert_eq!(server_info.protocol, bey_types::ProtocolType::Tcp);
+        assert_eq!(server_info.security_level, tcp_fallback::TCP_FALLBACK_SECURITY_LEVEL);
+
+        // 令牌经TLS流上的帧发送，发送方按握手名称标记，响应沿回退连接发回
+        let meta = TokenMeta::new("fallback_ping".to_string(), "fallback-client".to_string())
+            .with_receiver("fallback-server".to_string());
+        client.send_token(Token::new(meta, b"ping".to_vec())).await.expect("经TCP回退发送令牌失败");
+        let received = tokio::time::timeout(Duration::from_secs(5), server_rx.recv()).await
+            .expect("等待服务端收到令牌超时")
+            .expect("通道已关闭");
+        assert_eq!(received.payload, b"ping".to_vec());
+        assert_eq!(received.meta.sender_id, "fallback-client");
+        assert_eq!(wire::peer_addr(&received), Some(server_event.remote_addr));
+
+        let response = tokio::time::timeout(Duration::from_secs(5), client_rx.recv()).await
+            .expect("等待客户端收到响应超时")
+            .expect("通道已关闭");
+        assert_eq!(response.payload, b"pong".to_vec());
+        assert_eq!(response.meta.sender_id, "fallback-server");
+
+        // 证书由其他CA签发的客户端无法通过回退连接的mTLS握手
+        let other_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let mut untrusted_config = handshake_engine_config("untrusted-client", other_dir.path(), true);
+        untrusted_config.enable_tcp_fallback = true;
+        untrusted_config.transport_config = untrusted_config.transport_config
+            .with_connection_timeout(Duration::from_secs(1))
+            .with_server_name("fallback-server.bey.local".to_string());
+        let untrusted = create_allow_all_engine(untrusted_config).await;
+        untrusted.connect(blocked_addr).await.expect_err("不应信任其他CA签发的服务端证书");
+        assert!(untrusted.connection_info(blocked_addr).await.is_none());
+        let rejected_event = tokio::time::timeout(Duration::from_secs(5), server_events.recv()).await
+            .expect("等待服务端事件超时")
+            .expect("接收事件失败");
+        assert!(matches!(rejected_event.event, StateEvent::Error(_)), "服务端应记录TLS握手失败");
+
+        // 未启用回退时UDP被阻断的连接直接失败
+        let strict_config = handshake_engine_config("strict-client", temp_dir.path(), true);
+        let strict = create_allow_all_engine(EngineConfig {
+            transport_config: strict_config.transport_config.clone()
+                .with_connection_timeout(Duration::from_secs(1)),
+            ..strict_config
+        }).await;
+        let err = strict.connect(blocked_addr).await.expect_err("UDP被阻断时应连接失败");
+        assert_eq!(err.code(), 4304);
+
+        client.disconnect().await.expect("断开连接失败");
+        assert!(client.connection_info(blocked_addr).await.is_none());
+        server.stop_server().await.expect("停止服务器失败");
+        drop(black_hole);
+    }
+
+    #[tokio::test]
+    async fn test_persisted_peer_available_after_restart() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let cache_path = temp_dir.path().join("known_peers.json");
+        let config = EngineConfig {
+            name: "peer-cache-test".to_string(),
+            port: 0,
+            enable_auth: false,
+            enable_mdns: false,
+            transport_config: TransportConfig::new()
+                .with_port(0)
+                .with_certificates_dir(temp_dir.path()),
+            peer_cache_path: Some(cache_path.clone()),
+            peer_cache_max_age: Duration::from_secs(3600),
+            ..Default::default()
+        };
+
+        let peer_addr: SocketAddr = "192.168.1.20:8080".parse().expect("解析地址失败");
+        {
+            let engine = TransportEngine::new(config.clone()).await.expect("创建引擎失败");
+            let mut devices = engine.discovered_devices.write().await;
+            for (name, last_seen) in [
+                ("kitchen-pc", SystemTime::now()),
+                ("long-gone", SystemTime::now() - Duration::from_secs(7200)),
+            ] {
+                devices.insert(name.to_string(), DeviceEntry {
+                    name: name.to_string(),
+                    addresses: vec![peer_addr],
+                    authenticated: true,
+                    cert_fingerprint: Some("fingerprint".to_string()),
+                    last_seen,
+                    stale: false,
+                });
+            }
+            drop(devices);
+            engine.save_peer_cache().await.expect("保存已知设备缓存失败");
+        }
+
+        // 重启后发现尚未运行，已知设备即可直接使用；超过最大保留时间的设备被丢弃
+        let engine = TransportEngine::new(config).await.expect("重新创建引擎失败");
+        assert_eq!(engine.list_discovered_devices().await, vec!["kitchen-pc".to_string()]);
+        assert_eq!(engine.get_device_addresses("kitchen-pc").await, Some(vec![peer_addr]));
+        assert_eq!(engine.is_device_stale("kitchen-pc").await, Some(true));
+
+        // 缓存加载的设备不按30秒规则清理
+        assert_eq!(engine.cleanup_devices().await, 0);
+    }
+
+    #[test]
+    fn test_device_expiry_follows_query_interval() {
+        assert_eq!(device_expiry(Duration::from_secs(5)), DEVICE_EXPIRY);
+        assert_eq!(device_expiry(Duration::from_secs(120)), Duration::from_secs(240));
+
+        // 查询间隔变长后，一轮查询未应答的设备不过期
+        let entry = DeviceEntry {
+            name: "desk-pc".to_string(),
+            addresses: Vec::new(),
+            authenticated: false,
+            cert_fingerprint: None,
+            last_seen: SystemTime::now() - Duration::from_secs(100),
+            stale: false,
+        };
+        let now = SystemTime::now();
+        assert!(entry.is_expired(now, device_expiry(Duration::from_secs(15)), Duration::from_secs(3600)));
+        assert!(!entry.is_expired(now, device_expiry(Duration::from_secs(80)), Duration::from_secs(3600)));
+    }
+
     #[test]
     fn test_engine_config_default() {
         let config = EngineConfig::default();
@@ -1621,6 +3974,7 @@ mod tests {
         assert_eq!(config.receiver_buffer_size, 1000);
         assert!(config.enable_auth);
         assert!(config.enable_encryption);
+        assert_eq!(config.rtt_probe_interval, Duration::from_secs(30));
     }
     
     /// 测试用的简单消息处理器
@@ -1647,7 +4001,7 @@ mod tests {
     async fn test_auto_receive_with_handler() {
         // 创建路由器和接收器
         let router = Arc::new(TokenRouter::new());
-        let (sender, receiver) = create_receiver(10);
+        let (sender, receiver) = crate::receiver::create_receiver(10);
         
         // 创建测试处理器
         let handled_count = Arc::new(AtomicUsize::new(0));
@@ -1663,7 +4017,7 @@ mod tests {
         for i in 0..3 {
             let meta = TokenMeta::new("test_message".to_string(), format!("sender_{}", i));
             let token = Token::new(meta, vec![i as u8]);
-            sender.send(token).expect("发送令牌失败");
+            sender.send(token).await.expect("发送令牌失败");
         }
         
         // 启动一个简化版的接收循环
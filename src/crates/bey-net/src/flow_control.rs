@@ -9,7 +9,6 @@
 //! - **速率限制**: 限制发送速率
 //! - **反压机制**: 接收端反压信号
 
-use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
@@ -88,6 +87,13 @@ impl FlowController {
         Ok(())
     }
 
+    /// 记录发送失败，失败的数据不再计入飞行字节数
+    pub async fn on_failed(&self, size: usize) {
+        let mut bytes_in_flight = self.bytes_in_flight.write().await;
+        *bytes_in_flight = bytes_in_flight.saturating_sub(size);
+        debug!("发送 {} 字节失败，飞行中: {}", size, *bytes_in_flight);
+    }
+
     /// 记录确认
     pub async fn on_ack(&self, size: usize, rtt: Duration) -> NetResult<()> {
         // 更新飞行字节数
@@ -244,19 +250,36 @@ impl RateLimiter {
         }
     }
 
-    /// 尝试获取令牌
+    /// 获取令牌，令牌不足时等待补充
+    ///
+    /// 超过桶容量的请求在桶满后取走全部令牌，不会被永久阻塞；速率为0时不限速
+    ///
+    /// # 参数
+    ///
+    /// * `size` - 需要的字节数
     pub async fn acquire(&self, size: u64) -> NetResult<()> {
-        // 补充令牌
-        self.refill_tokens().await;
-
-        let mut tokens = self.tokens.write().await;
-        if *tokens >= size {
-            *tokens -= size;
-            Ok(())
-        } else {
-            Err(ErrorInfo::new(4601, "速率限制：令牌不足".to_string())
-                .with_category(ErrorCategory::System)
-                .with_severity(ErrorSeverity::Warning))
+        loop {
+            // 补充令牌
+            self.refill_tokens().await;
+
+            let max_rate = *self.max_rate.read().await;
+            if max_rate == 0 {
+                return Ok(());
+            }
+            let needed = size.min(*self.bucket_capacity.read().await);
+
+            let shortfall = {
+                let mut tokens = self.tokens.write().await;
+                if *tokens >= needed {
+                    *tokens -= needed;
+                    return Ok(());
+                }
+                needed - *tokens
+            };
+
+            let wait = Duration::from_secs_f64(shortfall as f64 / max_rate as f64);
+            debug!("速率限制：等待 {:?} 补充 {} 字节令牌", wait, shortfall);
+            tokio::time::sleep(wait.max(Duration::from_millis(1))).await;
         }
     }
 
@@ -316,7 +339,13 @@ mod tests {
         // 第二次应该成功
         assert!(limiter.acquire(500).await.is_ok());
         
-        // 第三次应该失败（超过容量）
-        assert!(limiter.acquire(100).await.is_err());
+        // 第三次等待令牌补充（100字节约需100毫秒）而不是失败
+        let started = std::time::Instant::now();
+        assert!(limiter.acquire(100).await.is_ok());
+        assert!(started.elapsed() >= Duration::from_millis(50), "令牌不足时应等待补充");
+
+        // 超过桶容量的请求在桶满后完成
+        let limiter = RateLimiter::new(10_000);
+        assert!(limiter.acquire(50_000).await.is_ok());
     }
 }
@@ -0,0 +1,414 @@
+//! # 连接握手协商
+//!
+//! 新连接建立后，客户端在第一个双向流上发送握手提议，列出本端支持的安全级别和认证方式；
+//! 服务端选择双方都支持的最高安全级别和服务端优先的认证方式，回复协商结果或拒绝原因。
+//!
+//! 协商结果以 [`ConnectionInfo`] 的形式保存，握手成功或失败都会产生 [`ConnectionEvent`]，
+//! 便于诊断对端被拒绝的原因。
+//!
+//! TCP 回退连接没有多路复用的流，握手消息直接以长度前缀帧在 mTLS 加密的 TCP 流上收发；
+//! 协商的安全级别高于回退连接能提供的 [`TCP_FALLBACK_SECURITY_LEVEL`] 时拒绝连接，不静默降级。
+//!
+//! 服务端在拒绝对端或握手未在 [`HANDSHAKE_TIMEOUT`] 内完成后，以 [`failure_close_code`]
+//! 给出的关闭码关闭QUIC连接，对端据此区分被拒绝和握手失败。
+
+use bey_transport::{CloseCode, Connection};
+use bey_types::{AuthMethod, ConnectionInfo, ConnectionStatus, ProtocolType, SecurityLevel};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::state_machine::StateEvent;
+use crate::tcp_fallback::TCP_FALLBACK_SECURITY_LEVEL;
+use crate::NetResult;
+
+/// 握手超时时间
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 握手消息的最大长度（字节）
+const MAX_HANDSHAKE_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// 握手提议
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandshakeOffer {
+    /// 发起方引擎名称
+    pub engine_name: String,
+    /// 支持的安全级别
+    pub security_levels: Vec<SecurityLevel>,
+    /// 支持的认证方式（按优先顺序）
+    pub auth_methods: Vec<AuthMethod>,
+}
+
+/// 握手回复
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HandshakeReply {
+    /// 接受连接
+    Accepted {
+        /// 服务端引擎名称
+        engine_name: String,
+        /// 协商的安全级别
+        security_level: SecurityLevel,
+        /// 协商的认证方式
+        auth_method: AuthMethod,
+        /// 服务端观察到的客户端地址
+        observed_addr: SocketAddr,
+    },
+    /// 拒绝连接
+    Rejected {
+        /// 拒绝原因
+        reason: String,
+    },
+}
+
+/// 握手结果
+#[derive(Debug, Clone)]
+pub struct NegotiatedConnection {
+    /// 协商后的连接信息
+    pub info: ConnectionInfo,
+    /// 对端在握手中给出的引擎名称，经该连接收到的令牌以它作为发送方
+    pub peer_name: String,
+}
+
+/// 连接事件
+///
+/// 记录单个连接的握手结果，成功时为 [`StateEvent::Authenticated`]，
+/// 失败时为携带拒绝原因的 [`StateEvent::Rejected`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionEvent {
+    /// 对端地址
+    pub remote_addr: SocketAddr,
+    /// 状态事件
+    pub event: StateEvent,
+}
+
+/// 协商安全级别和认证方式
+///
+/// 安全级别取双方都支持的最高级别，认证方式取本端优先顺序中第一个对端也支持的方式
+///
+/// # 参数
+///
+/// * `local` - 本端（服务端）的提议
+/// * `remote` - 对端（客户端）的提议
+///
+/// # 返回值
+///
+/// 返回协商的安全级别和认证方式，没有共同支持的选项时返回拒绝原因
+pub fn negotiate(local: &HandshakeOffer, remote: &HandshakeOffer) -> Result<(SecurityLevel, AuthMethod), String> {
+    let security_level = local.security_levels.iter()
+        .filter(|level| remote.security_levels.contains(level))
+        .max()
+        .copied()
+        .ok_or_else(|| format!(
+            "没有共同支持的安全级别 (本端: {:?}, 对端: {:?})",
+            local.security_levels, remote.security_levels
+        ))?;
+
+    let auth_method = local.auth_methods.iter()
+        .find(|method| remote.auth_methods.contains(method))
+        .copied()
+        .ok_or_else(|| format!(
+            "没有共同支持的认证方式 (本端: {:?}, 对端: {:?})",
+            local.auth_methods, remote.auth_methods
+        ))?;
+
+    Ok((security_level, auth_method))
+}
+
+/// 发起握手（客户端）
+///
+/// # 参数
+///
+/// * `connection` - 已建立的连接
+/// * `offer` - 本端的握手提议
+///
+/// # 返回值
+///
+/// 返回协商后的连接，对端拒绝时返回包含拒绝原因的错误
+pub async fn initiate(connection: &Connection, offer: &HandshakeOffer) -> NetResult<NegotiatedConnection> {
+    let remote_addr = connection.remote_address();
+    let reply: HandshakeReply = with_timeout(remote_addr, async {
+        let (mut send, mut recv) = connection.open_bi().await
+            .map_err(|e| handshake_io_error(4901, remote_addr, e))?;
+        send.write_all(&encode(offer)?).await
+            .map_err(|e| handshake_io_error(4901, remote_addr, e))?;
+        send.finish().map_err(|e| handshake_io_error(4901, remote_addr, e))?;
+
+        let data = recv.read_to_end(MAX_HANDSHAKE_MESSAGE_SIZE).await
+            .map_err(|e| handshake_io_error(4901, remote_addr, e))?;
+        decode(&data)
+    }).await?;
+
+    accept_reply(reply, remote_addr, ProtocolType::Quic)
+}
+
+/// 在 TCP 回退连接上发起握手（客户端）
+///
+/// # 参数
+///
+/// * `stream` - 已建立的 TCP 流
+/// * `remote_addr` - 对端地址
+/// * `offer` - 本端的握手提议
+///
+/// # 返回值
+///
+/// 返回协商后的连接（协议为 TCP），对端拒绝时返回包含拒绝原因的错误
+pub async fn initiate_stream<S>(stream: &mut S, remote_addr: SocketAddr, offer: &HandshakeOffer) -> NetResult<NegotiatedConnection>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let reply: HandshakeReply = with_timeout(remote_addr, async {
+        write_frame(stream, &encode(offer)?).await
+            .map_err(|e| handshake_io_error(4901, remote_addr, e))?;
+        let data = read_frame(stream).await
+            .map_err(|e| handshake_io_error(4901, remote_addr, e))?;
+        decode(&data)
+    }).await?;
+
+    accept_reply(reply, remote_addr, ProtocolType::Tcp)
+}
+
+/// 响应握手（服务端）
+///
+/// # 参数
+///
+/// * `connection` - 已接受的连接
+/// * `local_addr` - 本端监听地址
+/// * `offer` - 本端支持的安全级别和认证方式
+///
+/// # 返回值
+///
+/// 返回协商后的连接，双方不兼容时回复拒绝原因并返回错误
+pub async fn respond(connection: &Connection, local_addr: SocketAddr, offer: &HandshakeOffer) -> NetResult<NegotiatedConnection> {
+    let remote_addr = connection.remote_address();
+    with_timeout(remote_addr, async {
+        let (mut send, mut recv) = connection.accept_bi().await
+            .map_err(|e| handshake_io_error(4902, remote_addr, e))?;
+        let data = recv.read_to_end(MAX_HANDSHAKE_MESSAGE_SIZE).await
+            .map_err(|e| handshake_io_error(4902, remote_addr, e))?;
+        let remote: HandshakeOffer = decode(&data)?;
+        let (reply, result) = answer(offer, &remote, local_addr, remote_addr, ProtocolType::Quic);
+
+        send.write_all(&encode(&reply)?).await
+            .map_err(|e| handshake_io_error(4902, remote_addr, e))?;
+        send.finish().map_err(|e| handshake_io_error(4902, remote_addr, e))?;
+        // 等待对端读取回复，避免连接关闭时丢失拒绝原因
+        let _ = send.stopped().await;
+        result
+    }).await
+}
+
+/// 在 TCP 回退连接上响应握手（服务端）
+///
+/// # 参数
+///
+/// * `stream` - 已接受的 TCP 流
+/// * `local_addr` - 本端监听地址
+/// * `remote_addr` - 对端地址
+/// * `offer` - 本端支持的安全级别和认证方式
+///
+/// # 返回值
+///
+/// 返回协商后的连接（协议为 TCP），双方不兼容时回复拒绝原因并返回错误
+pub async fn respond_stream<S>(
+    stream: &mut S,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    offer: &HandshakeOffer,
+) -> NetResult<NegotiatedConnection>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    with_timeout(remote_addr, async {
+        let data = read_frame(stream).await
+            .map_err(|e| handshake_io_error(4902, remote_addr, e))?;
+        let remote: HandshakeOffer = decode(&data)?;
+        let (reply, result) = answer(offer, &remote, local_addr, remote_addr, ProtocolType::Tcp);
+
+        write_frame(stream, &encode(&reply)?).await
+            .map_err(|e| handshake_io_error(4902, remote_addr, e))?;
+        result
+    }).await
+}
+
+/// 握手失败时关闭连接使用的关闭码
+///
+/// # 参数
+///
+/// * `error` - 握手错误
+///
+/// # 返回值
+///
+/// 对端被拒绝时返回 [`CloseCode::PolicyViolation`]，握手超时、消息无效或读写失败时返回 [`CloseCode::ProtocolError`]
+pub fn failure_close_code(error: &ErrorInfo) -> CloseCode {
+    if error.code() == 4903 {
+        CloseCode::PolicyViolation
+    } else {
+        CloseCode::ProtocolError
+    }
+}
+
+/// 根据对端提议生成回复和本端的协商结果
+///
+/// TCP 回退连接上协商的安全级别高于 [`TCP_FALLBACK_SECURITY_LEVEL`] 时拒绝
+fn answer(
+    offer: &HandshakeOffer,
+    remote: &HandshakeOffer,
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    protocol: ProtocolType,
+) -> (HandshakeReply, NetResult<NegotiatedConnection>) {
+    let negotiated = negotiate(offer, remote).and_then(|(security_level, auth_method)| {
+        if protocol == ProtocolType::Tcp && security_level > TCP_FALLBACK_SECURITY_LEVEL {
+            return Err(format!(
+                "TCP回退连接最高提供 {:?} 安全级别，无法满足协商的 {:?}",
+                TCP_FALLBACK_SECURITY_LEVEL, security_level
+            ));
+        }
+        Ok((security_level, auth_method))
+    });
+    match negotiated {
+        Ok((security_level, auth_method)) => (
+            HandshakeReply::Accepted {
+                engine_name: offer.engine_name.clone(),
+                security_level,
+                auth_method,
+                observed_addr: remote_addr,
+            },
+            Ok(NegotiatedConnection {
+                info: connection_info(local_addr, remote_addr, protocol, security_level, auth_method),
+                peer_name: remote.engine_name.clone(),
+            }),
+        ),
+        Err(reason) => (
+            HandshakeReply::Rejected { reason: reason.clone() },
+            Err(ErrorInfo::new(4903, format!("拒绝 {} ({}): {}", remote.engine_name, remote_addr, reason))
+                .with_category(ErrorCategory::Authentication)
+                .with_severity(ErrorSeverity::Warning)),
+        ),
+    }
+}
+
+/// 处理服务端的握手回复
+fn accept_reply(reply: HandshakeReply, remote_addr: SocketAddr, protocol: ProtocolType) -> NetResult<NegotiatedConnection> {
+    match reply {
+        HandshakeReply::Accepted { engine_name, security_level, auth_method, observed_addr } => {
+            Ok(NegotiatedConnection {
+                info: connection_info(observed_addr, remote_addr, protocol, security_level, auth_method),
+                peer_name: engine_name,
+            })
+        }
+        HandshakeReply::Rejected { reason } => {
+            Err(ErrorInfo::new(4903, format!("连接被 {} 拒绝: {}", remote_addr, reason))
+                .with_category(ErrorCategory::Authentication)
+                .with_severity(ErrorSeverity::Warning))
+        }
+    }
+}
+
+/// 构造协商后的连接信息
+fn connection_info(
+    local_addr: SocketAddr,
+    remote_addr: SocketAddr,
+    protocol: ProtocolType,
+    security_level: SecurityLevel,
+    auth_method: AuthMethod,
+) -> ConnectionInfo {
+    let mut info = ConnectionInfo::new(local_addr, remote_addr, protocol)
+        .with_security_level(security_level)
+        .with_auth_method(auth_method);
+    info.status = ConnectionStatus::Connected;
+    info
+}
+
+/// 为握手过程设置超时
+async fn with_timeout<T>(remote_addr: SocketAddr, future: impl std::future::Future<Output = NetResult<T>>) -> NetResult<T> {
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, future).await.map_err(|_| {
+        ErrorInfo::new(4904, format!("与 {} 握手超时", remote_addr))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Warning)
+    })?
+}
+
+/// 握手读写错误
+fn handshake_io_error(code: u32, remote_addr: SocketAddr, error: impl std::fmt::Display) -> ErrorInfo {
+    ErrorInfo::new(code, format!("与 {} 握手失败: {}", remote_addr, error))
+        .with_category(ErrorCategory::Network)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 写入长度前缀帧
+async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, data: &[u8]) -> std::io::Result<()> {
+    stream.write_u32(data.len() as u32).await?;
+    stream.write_all(data).await?;
+    stream.flush().await
+}
+
+/// 读取长度前缀帧
+async fn read_frame<S: AsyncRead + Unpin>(stream: &mut S) -> std::io::Result<Vec<u8>> {
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_HANDSHAKE_MESSAGE_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("握手消息过长: {} 字节", len),
+        ));
+    }
+    let mut data = vec![0u8; len];
+    stream.read_exact(&mut data).await?;
+    Ok(data)
+}
+
+/// 编码握手消息
+fn encode<T: Serialize>(message: &T) -> NetResult<Vec<u8>> {
+    serde_json::to_vec(message).map_err(|e| {
+        ErrorInfo::new(4905, format!("编码握手消息失败: {}", e))
+            .with_category(ErrorCategory::Parse)
+            .with_severity(ErrorSeverity::Error)
+    })
+}
+
+/// 解码握手消息
+fn decode<T: for<'de> Deserialize<'de>>(data: &[u8]) -> NetResult<T> {
+    serde_json::from_slice(data).map_err(|e| {
+        ErrorInfo::new(4905, format!("解码握手消息失败: {}", e))
+            .with_category(ErrorCategory::Parse)
+            .with_severity(ErrorSeverity::Error)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(engine_name: &str, security_level: SecurityLevel) -> HandshakeOffer {
+        HandshakeOffer {
+            engine_name: engine_name.to_string(),
+            security_levels: vec![security_level],
+            auth_methods: vec![AuthMethod::Certificate],
+        }
+    }
+
+    #[test]
+    fn test_tcp_fallback_refuses_level_above_ceiling() {
+        let local_addr: SocketAddr = "127.0.0.1:8443".parse().expect("解析地址失败");
+        let remote_addr: SocketAddr = "127.0.0.1:50000".parse().expect("解析地址失败");
+        let server = offer("server", SecurityLevel::High);
+        let client = offer("client", SecurityLevel::High);
+
+        // 回退连接无法提供协商的级别时拒绝，而不是静默降级
+        let (reply, result) = answer(&server, &client, local_addr, remote_addr, ProtocolType::Tcp);
+        assert!(matches!(reply, HandshakeReply::Rejected { .. }));
+        assert_eq!(result.expect_err("应拒绝连接").code(), 4903);
+
+        let (_, result) = answer(&server, &client, local_addr, remote_addr, ProtocolType::Quic);
+        assert_eq!(result.expect("QUIC连接应协商成功").info.security_level, SecurityLevel::High);
+
+        // 不超过回退连接能提供的级别时照常协商
+        let server = offer("server", TCP_FALLBACK_SECURITY_LEVEL);
+        let client = offer("client", TCP_FALLBACK_SECURITY_LEVEL);
+        let (reply, result) = answer(&server, &client, local_addr, remote_addr, ProtocolType::Tcp);
+        assert!(matches!(reply, HandshakeReply::Accepted { .. }));
+        assert_eq!(result.expect("应协商成功").info.security_level, TCP_FALLBACK_SECURITY_LEVEL);
+    }
+}
@@ -56,12 +56,24 @@
 //! - `state_machine` - 状态机：管理连接状态和转换
 //! - `receiver` - 元接收器：灵活的消息接收机制
 //! - `engine` - 传输引擎：集成所有功能的核心引擎
+//! - `tcp_fallback` - TCP回退：UDP被阻断时通过TCP建立连接
 //! - `stream` - 流式传输：大文件分块传输和流水线
 //! - `priority_queue` - 优先级队列：令牌优先级排序和确认机制
+//! - `qos` - 服务质量调度：按令牌优先级决定出站顺序和QUIC流优先级
+//! - `wire` - 令牌线路：在已握手的连接上收发令牌，以握手认证的对端作为发送方
 //! - `flow_control` - 流量控制：滑动窗口和拥塞控制
 //! - `metrics` - 性能监控：指标收集和统计
+//! - `replay` - 重放检测：按发送方和令牌ID丢弃时间窗口内重复收到的令牌
+//! - `peer_cache` - 已知设备缓存：持久化已发现的设备，重启后无需等待发现即可连接
+//! - `path_selector` - 路径选择：多地址设备的RTT探测和最快地址选择
 //! - `mdns_discovery` - mDNS设备发现
 //! - `udp_discovery` - UDP广播设备发现
+//! - `device_changes` - 设备变化流：统一mDNS和UDP发现事件
+//! - `task_group` - 后台任务组：停止服务时取消并等待所有后台任务退出
+//! - `query_interval` - 自适应查询间隔：按设备集合的稳定程度调整mDNS查询间隔
+//! - `topic` - 主题发布订阅：基于令牌的主题成员管理和消息扇出
+//! - `channel` - 逻辑通道：在同一QUIC连接上复用多个编号的双向帧通道
+//! - `capability` - 设备能力广播：在发现记录中携带设备能力并按能力筛选设备
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 
@@ -72,7 +84,7 @@ pub type NetResult<T> = std::result::Result<T, ErrorInfo>;
 pub mod token;
 pub use token::{
     Token, TokenMeta, TokenId, TokenType, TokenPriority,
-    TokenHandler, TokenRouter,
+    TokenHandler, TokenRouter, DeadLetter, DEFAULT_DEAD_LETTER_CAPACITY, MAX_DEAD_LETTER_TYPES, DEAD_LETTER_OTHER_TYPE,
 };
 
 // 导出状态机
@@ -86,14 +98,25 @@ pub mod receiver;
 pub use receiver::{
     MetaReceiver, BufferedReceiver, ReceiverMode,
     ReceiverFilter, TypeFilter, PriorityFilter,
-    create_receiver,
+    InboundSender, OverflowPolicy, create_receiver,
 };
 
 // 导出传输引擎
 pub mod engine;
 pub use engine::{
-    TransportEngine, EngineConfig,
+    TransportEngine, EngineConfig, ListenerInfo, ACK_TOKEN_TYPE,
 };
+pub use bey_transport::{CloseCode, TransportEvent};
+
+// 导出连接握手协商
+pub mod handshake;
+pub use handshake::{
+    ConnectionEvent, HandshakeOffer, HandshakeReply, NegotiatedConnection, HANDSHAKE_TIMEOUT,
+};
+
+// 导出TCP回退传输
+pub mod tcp_fallback;
+pub use tcp_fallback::{TCP_PORT_TXT_KEY, TCP_CONNECT_TIMEOUT};
 
 // 导出流式传输
 pub mod stream;
@@ -104,9 +127,17 @@ pub use stream::{
 // 导出优先级队列
 pub mod priority_queue;
 pub use priority_queue::{
-    PriorityQueue, AckStatus,
+    PriorityQueue, AckStatus, AckTimeout,
 };
 
+// 导出服务质量调度
+pub mod qos;
+pub use qos::{OutboundScheduler, open_prioritized_stream};
+
+// 导出令牌线路
+pub mod wire;
+pub use wire::{MAX_WIRE_TOKEN_SIZE, PEER_ADDR_ATTRIBUTE};
+
 // 导出流量控制
 pub mod flow_control;
 pub use flow_control::{
@@ -117,13 +148,31 @@ pub use flow_control::{
 // 导出性能监控
 pub mod metrics;
 pub use metrics::{
-    Metrics, MetricsCollector, ErrorStats,
+    Metrics, MetricsCollector, ErrorStats, PeerMetrics, MAX_TRACKED_PEERS,
+};
+
+// 导出重放检测
+pub mod replay;
+pub use replay::{
+    ReplayCache, DEFAULT_REPLAY_WINDOW, DEFAULT_REPLAY_CACHE_CAPACITY,
+};
+
+// 导出已知设备缓存
+pub mod peer_cache;
+pub use peer_cache::{
+    PeerCache, PersistedPeer, DEFAULT_PEER_CACHE_MAX_AGE,
+};
+
+// 导出路径选择
+pub mod path_selector;
+pub use path_selector::{
+    PathSelector, RttProbe, AddressRtt,
 };
 
 // 导出mDNS发现模块
 mod mdns_discovery;
 pub use mdns_discovery::{
-    MdnsDiscovery, MdnsDiscoveryConfig, MdnsServiceInfo, MdnsDiscoveryEvent,
+    MdnsDiscovery, MdnsDiscoveryConfig, DiscoveryIpMode, MdnsServiceInfo, MdnsDiscoveryEvent,
     MdnsInfo, MdnsQuery, MdnsRecord, MdnsResponse, MdnsRecordType, MdnsQueryType,
     MdnsDiscoveryStats, mdns_constants, create_default_mdns_config, 
     create_default_mdns_device_info,
@@ -136,6 +185,44 @@ pub use udp_discovery::{
     DiscoveryService, DiscoveryResult,
 };
 
+// 导出设备变化流
+pub mod device_changes;
+pub use device_changes::{
+    DeviceChange, DeviceChangeFeed, DEFAULT_DEVICE_CHANGE_CAPACITY,
+};
+
+// 导出后台任务组
+pub mod task_group;
+pub use task_group::{
+    TaskGroup, DEFAULT_TASK_SHUTDOWN_TIMEOUT,
+};
+
+// 导出自适应查询间隔
+pub mod query_interval;
+pub use query_interval::{
+    AdaptiveQueryInterval, STABLE_ROUNDS_BEFORE_BACKOFF,
+};
+
+// 导出主题发布订阅
+pub mod topic;
+pub use topic::{
+    TopicBus, TopicMessage, TOPIC_ANNOUNCE_TOKEN_TYPE, TOPIC_MESSAGE_TOKEN_TYPE,
+};
+
+// 导出逻辑通道多路复用
+pub mod channel;
+pub use channel::{
+    Channel, ChannelId, ChannelRouter, MESSAGE_CHANNEL, CLIPBOARD_CHANNEL, FILE_CHANNEL,
+    MAX_CHANNEL_FRAME_SIZE, CHANNEL_OPEN_TIMEOUT,
+};
+
+// 导出设备能力广播
+pub mod capability;
+pub use capability::{
+    Capability, CAPABILITIES_TXT_KEY, capabilities_txt_record, capability_token, capability_tokens,
+    parse_capabilities,
+};
+
 /// 网络模块版本信息
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -11,11 +11,15 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 use tracing::info;
 
+/// 单独统计的对端数上限，超出后新对端只计入总量
+pub const MAX_TRACKED_PEERS: usize = 256;
+
 /// 性能指标
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Metrics {
@@ -39,6 +43,9 @@ pub struct Metrics {
     pub max_rtt_ms: u64,
     /// 错误计数
     pub error_count: u64,
+    /// 按错误码统计的错误次数
+    #[serde(default)]
+    pub errors_by_code: HashMap<u32, u64>,
     /// 重传次数
     pub retransmit_count: u64,
     /// 超时次数
@@ -49,6 +56,18 @@ pub struct Metrics {
     pub active_streams: usize,
     /// 队列大小
     pub queue_size: usize,
+    /// 入站缓冲区溢出丢弃的令牌数
+    #[serde(default)]
+    pub inbound_dropped: u64,
+    /// 被识别为重放而丢弃的令牌数
+    #[serde(default)]
+    pub replays_dropped: u64,
+    /// 按令牌类型统计的死信数
+    #[serde(default)]
+    pub dead_letters_by_type: HashMap<String, u64>,
+    /// 按对端设备统计的指标
+    #[serde(default)]
+    pub peers: HashMap<String, PeerMetrics>,
     /// 开始时间
     pub start_time: SystemTime,
     /// 运行时间（秒）
@@ -68,17 +87,165 @@ impl Default for Metrics {
             min_rtt_ms: u64::MAX,
             max_rtt_ms: 0,
             error_count: 0,
+            errors_by_code: HashMap::new(),
             retransmit_count: 0,
             timeout_count: 0,
             active_connections: 0,
             active_streams: 0,
             queue_size: 0,
+            inbound_dropped: 0,
+            replays_dropped: 0,
+            dead_letters_by_type: HashMap::new(),
+            peers: HashMap::new(),
             start_time: SystemTime::now(),
             uptime_secs: 0,
         }
     }
 }
 
+/// 单个对端设备的指标
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerMetrics {
+    /// 发往该对端的字节数
+    pub bytes_sent: u64,
+    /// 从该对端接收的字节数
+    pub bytes_received: u64,
+    /// 发往该对端的令牌数
+    pub tokens_sent: u64,
+    /// 从该对端接收的令牌数
+    pub tokens_received: u64,
+    /// 最近一次测得的RTT（毫秒），尚未测量时为 `None`
+    pub rtt_ms: Option<f64>,
+    /// 发往该对端的重传次数
+    #[serde(default)]
+    pub retransmits: u64,
+    /// 发往该对端的超时次数
+    #[serde(default)]
+    pub timeouts: u64,
+}
+
+impl Metrics {
+    /// 按错误码统计的错误次数
+    pub fn error_breakdown(&self) -> HashMap<u32, u64> {
+        self.errors_by_code.clone()
+    }
+
+    /// 导出为 Prometheus 文本格式
+    ///
+    /// 每个指标族都带 `# HELP` 和 `# TYPE` 行，累计值导出为 counter，瞬时值导出为 gauge。
+    /// 错误按 `code` 标签、死信按 `token_type` 标签、对端指标按 `peer` 标签区分，
+    /// 标签值按字典序输出，保证同一份指标的输出稳定。
+    ///
+    /// # 返回值
+    ///
+    /// 返回可直接作为抓取响应的文本
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        write_metric(&mut out, "bey_tokens_sent_total", "counter", "发送的令牌总数",
+            [(String::new(), self.tokens_sent.to_string())]);
+        write_metric(&mut out, "bey_tokens_received_total", "counter", "接收的令牌总数",
+            [(String::new(), self.tokens_received.to_string())]);
+        write_metric(&mut out, "bey_bytes_sent_total", "counter", "发送的字节总数",
+            [(String::new(), self.bytes_sent.to_string())]);
+        write_metric(&mut out, "bey_bytes_received_total", "counter", "接收的字节总数",
+            [(String::new(), self.bytes_received.to_string())]);
+
+        let mut errors: Vec<_> = self.errors_by_code.iter().collect();
+        errors.sort();
+        write_metric(&mut out, "bey_errors_total", "counter", "按错误码统计的错误次数",
+            errors.into_iter().map(|(code, count)| {
+                (labels(&[("code", &code.to_string())]), count.to_string())
+            }));
+        write_metric(&mut out, "bey_retransmits_total", "counter", "重传次数",
+            [(String::new(), self.retransmit_count.to_string())]);
+        write_metric(&mut out, "bey_timeouts_total", "counter", "超时次数",
+            [(String::new(), self.timeout_count.to_string())]);
+        write_metric(&mut out, "bey_inbound_dropped_total", "counter", "入站缓冲区溢出丢弃的令牌数",
+            [(String::new(), self.inbound_dropped.to_string())]);
+        write_metric(&mut out, "bey_replays_dropped_total", "counter", "被识别为重放而丢弃的令牌数",
+            [(String::new(), self.replays_dropped.to_string())]);
+
+        let mut dead_letters: Vec<_> = self.dead_letters_by_type.iter().collect();
+        dead_letters.sort();
+        write_metric(&mut out, "bey_dead_letters_total", "counter", "按令牌类型统计的死信数",
+            dead_letters.into_iter().map(|(token_type, count)| {
+                (labels(&[("token_type", token_type)]), count.to_string())
+            }));
+
+        write_metric(&mut out, "bey_active_connections", "gauge", "活跃连接数",
+            [(String::new(), self.active_connections.to_string())]);
+        write_metric(&mut out, "bey_active_streams", "gauge", "活跃流数",
+            [(String::new(), self.active_streams.to_string())]);
+        write_metric(&mut out, "bey_queue_size", "gauge", "队列大小",
+            [(String::new(), self.queue_size.to_string())]);
+        write_metric(&mut out, "bey_rtt_avg_milliseconds", "gauge", "平均RTT（毫秒）",
+            [(String::new(), self.avg_rtt_ms.to_string())]);
+        // 尚未测量RTT时最小值为 u64::MAX，不导出样本
+        write_metric(&mut out, "bey_rtt_min_milliseconds", "gauge", "最小RTT（毫秒）",
+            (self.min_rtt_ms != u64::MAX).then(|| (String::new(), self.min_rtt_ms.to_string())));
+        write_metric(&mut out, "bey_rtt_max_milliseconds", "gauge", "最大RTT（毫秒）",
+            [(String::new(), self.max_rtt_ms.to_string())]);
+        write_metric(&mut out, "bey_uptime_seconds", "gauge", "运行时间（秒）",
+            [(String::new(), self.uptime_secs.to_string())]);
+
+        let mut peers: Vec<_> = self.peers.iter().collect();
+        peers.sort_by(|a, b| a.0.cmp(b.0));
+        let peer_samples = |value: fn(&PeerMetrics) -> Option<String>| {
+            peers.iter()
+                .filter_map(move |(peer, stats)| {
+                    value(stats).map(|value| (labels(&[("peer", peer)]), value))
+                })
+        };
+        write_metric(&mut out, "bey_peer_tokens_sent_total", "counter", "发往各对端的令牌数",
+            peer_samples(|stats| Some(stats.tokens_sent.to_string())));
+        write_metric(&mut out, "bey_peer_tokens_received_total", "counter", "从各对端接收的令牌数",
+            peer_samples(|stats| Some(stats.tokens_received.to_string())));
+        write_metric(&mut out, "bey_peer_bytes_sent_total", "counter", "发往各对端的字节数",
+            peer_samples(|stats| Some(stats.bytes_sent.to_string())));
+        write_metric(&mut out, "bey_peer_bytes_received_total", "counter", "从各对端接收的字节数",
+            peer_samples(|stats| Some(stats.bytes_received.to_string())));
+        write_metric(&mut out, "bey_peer_rtt_milliseconds", "gauge", "各对端最近一次测得的RTT（毫秒）",
+            peer_samples(|stats| stats.rtt_ms.map(|rtt| rtt.to_string())));
+        write_metric(&mut out, "bey_peer_retransmits_total", "counter", "发往各对端的重传次数",
+            peer_samples(|stats| Some(stats.retransmits.to_string())));
+        write_metric(&mut out, "bey_peer_timeouts_total", "counter", "发往各对端的超时次数",
+            peer_samples(|stats| Some(stats.timeouts.to_string())));
+
+        out
+    }
+}
+
+/// 写入一个 Prometheus 指标族（HELP、TYPE 行和全部样本）
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, String)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+}
+
+/// 生成 Prometheus 标签集，如 `{peer="device-a"}`
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = pairs.iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, escape_label_value(value)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// 按 Prometheus 文本格式转义标签值中的反斜杠、双引号和换行
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
 /// 延迟直方图
 #[derive(Debug, Clone)]
 pub struct LatencyHistogram {
@@ -186,6 +353,43 @@ impl MetricsCollector {
         metrics.tokens_received += 1;
     }
 
+    /// 记录发往指定对端的令牌（同时计入总量）
+    ///
+    /// 已单独统计 [`MAX_TRACKED_PEERS`] 个对端后，新对端只计入总量
+    pub async fn record_peer_send(&self, peer: &str, bytes: usize) {
+        let mut metrics = self.metrics.write().await;
+        metrics.bytes_sent += bytes as u64;
+        metrics.tokens_sent += 1;
+
+        if let Some(stats) = tracked_peer(&mut metrics.peers, peer) {
+            stats.bytes_sent += bytes as u64;
+            stats.tokens_sent += 1;
+        }
+    }
+
+    /// 记录从指定对端接收的令牌（同时计入总量）
+    ///
+    /// `peer` 应为握手认证的对端名称，而不是令牌自称的发送方；
+    /// 已单独统计 [`MAX_TRACKED_PEERS`] 个对端后，新对端只计入总量
+    pub async fn record_peer_receive(&self, peer: &str, bytes: usize) {
+        let mut metrics = self.metrics.write().await;
+        metrics.bytes_received += bytes as u64;
+        metrics.tokens_received += 1;
+
+        if let Some(stats) = tracked_peer(&mut metrics.peers, peer) {
+            stats.bytes_received += bytes as u64;
+            stats.tokens_received += 1;
+        }
+    }
+
+    /// 更新指定对端最近一次测得的RTT
+    pub async fn update_peer_rtt(&self, peer: &str, rtt: Duration) {
+        let mut metrics = self.metrics.write().await;
+        if let Some(stats) = tracked_peer(&mut metrics.peers, peer) {
+            stats.rtt_ms = Some(rtt.as_secs_f64() * 1000.0);
+        }
+    }
+
     /// 记录RTT
     pub async fn record_rtt(&self, rtt: Duration) {
         let rtt_ms = rtt.as_millis() as u64;
@@ -228,6 +432,24 @@ impl MetricsCollector {
         metrics.timeout_count += 1;
     }
 
+    /// 记录发往指定对端的重传（同时计入总量）
+    pub async fn record_peer_retransmit(&self, peer: &str) {
+        let mut metrics = self.metrics.write().await;
+        metrics.retransmit_count += 1;
+        if let Some(stats) = tracked_peer(&mut metrics.peers, peer) {
+            stats.retransmits += 1;
+        }
+    }
+
+    /// 记录发往指定对端的超时（同时计入总量）
+    pub async fn record_peer_timeout(&self, peer: &str) {
+        let mut metrics = self.metrics.write().await;
+        metrics.timeout_count += 1;
+        if let Some(stats) = tracked_peer(&mut metrics.peers, peer) {
+            stats.timeouts += 1;
+        }
+    }
+
     /// 更新连接数
     pub async fn update_connections(&self, count: usize) {
         let mut metrics = self.metrics.write().await;
@@ -246,6 +468,24 @@ impl MetricsCollector {
         metrics.queue_size = size;
     }
 
+    /// 更新入站缓冲区溢出丢弃数
+    pub async fn update_inbound_dropped(&self, count: u64) {
+        let mut metrics = self.metrics.write().await;
+        metrics.inbound_dropped = count;
+    }
+
+    /// 记录丢弃的重放令牌
+    pub async fn record_replay(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.replays_dropped += 1;
+    }
+
+    /// 更新按令牌类型统计的死信数
+    pub async fn update_dead_letters(&self, counts: HashMap<String, u64>) {
+        let mut metrics = self.metrics.write().await;
+        metrics.dead_letters_by_type = counts;
+    }
+
     /// 更新速率
     pub async fn update_rates(&self) {
         let now = SystemTime::now();
@@ -271,7 +511,9 @@ impl MetricsCollector {
     /// 获取当前指标
     pub async fn get_metrics(&self) -> Metrics {
         self.update_rates().await;
-        self.metrics.read().await.clone()
+        let mut metrics = self.metrics.read().await.clone();
+        metrics.errors_by_code = self.error_stats.read().await.by_code.clone();
+        metrics
     }
 
     /// 获取错误统计
@@ -279,6 +521,15 @@ impl MetricsCollector {
         self.error_stats.read().await.clone()
     }
 
+    /// 重置错误统计
+    pub async fn reset_errors(&self) {
+        let mut metrics = self.metrics.write().await;
+        metrics.error_count = 0;
+
+        let mut error_stats = self.error_stats.write().await;
+        *error_stats = ErrorStats::default();
+    }
+
     /// 获取延迟百分位
     pub async fn get_latency_percentiles(&self) -> HashMap<String, u64> {
         let histogram = self.latency_histogram.read().await;
@@ -328,8 +579,9 @@ impl MetricsCollector {
             percentiles.get("p99").unwrap_or(&0));
         info!("错误: {}, 重传: {}, 超时: {}", 
             metrics.error_count, metrics.retransmit_count, metrics.timeout_count);
-        info!("活跃连接: {}, 活跃流: {}, 队列: {}", 
-            metrics.active_connections, metrics.active_streams, metrics.queue_size);
+        info!("活跃连接: {}, 活跃流: {}, 队列: {}, 入站丢弃: {}, 重放丢弃: {}", 
+            metrics.active_connections, metrics.active_streams, metrics.queue_size,
+            metrics.inbound_dropped, metrics.replays_dropped);
     }
 }
 
@@ -339,6 +591,14 @@ impl Default for MetricsCollector {
     }
 }
 
+/// 对端的统计项，对端数已达上限且该对端尚未统计时返回 `None`
+fn tracked_peer<'a>(peers: &'a mut HashMap<String, PeerMetrics>, peer: &str) -> Option<&'a mut PeerMetrics> {
+    if !peers.contains_key(peer) && peers.len() >= MAX_TRACKED_PEERS {
+        return None;
+    }
+    Some(peers.entry(peer.to_string()).or_default())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -358,6 +618,107 @@ mod tests {
         assert_eq!(metrics.tokens_received, 1);
     }
 
+    #[tokio::test]
+    async fn test_error_breakdown_and_reset() {
+        let collector = MetricsCollector::new();
+
+        collector.record_error(4304, "Network".to_string()).await;
+        collector.record_error(4304, "Network".to_string()).await;
+        collector.record_error(4331, "Network".to_string()).await;
+
+        let breakdown = collector.get_metrics().await.error_breakdown();
+        assert_eq!(breakdown.get(&4304), Some(&2));
+        assert_eq!(breakdown.get(&4331), Some(&1));
+
+        collector.reset_errors().await;
+        let metrics = collector.get_metrics().await;
+        assert!(metrics.error_breakdown().is_empty());
+        assert_eq!(metrics.error_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_export() {
+        let collector = MetricsCollector::new();
+        collector.record_peer_send("device-a", 100).await;
+        collector.record_peer_receive("device-\"b\"", 40).await;
+        collector.update_peer_rtt("device-a", Duration::from_millis(12)).await;
+        collector.record_rtt(Duration::from_millis(20)).await;
+        collector.record_error(4304, "Network".to_string()).await;
+        collector.update_connections(2).await;
+
+        let output = collector.get_metrics().await.to_prometheus();
+
+        // 每个样本行都是 `名称{标签} 数值`，且所属指标族已声明 TYPE
+        let mut declared = HashMap::new();
+        let mut samples = HashMap::new();
+        for line in output.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').expect("TYPE行格式错误");
+                assert!(kind == "counter" || kind == "gauge");
+                declared.insert(name.to_string(), kind.to_string());
+            } else if !line.starts_with('#') {
+                let (series, value) = line.rsplit_once(' ').expect("样本行格式错误");
+                value.parse::<f64>().expect("样本值不是数字");
+                let name = series.split('{').next().unwrap_or(series);
+                assert!(declared.contains_key(name), "指标 {} 缺少TYPE行", name);
+                samples.insert(series.to_string(), value.to_string());
+            }
+        }
+
+        assert_eq!(declared.get("bey_tokens_sent_total").map(String::as_str), Some("counter"));
+        assert_eq!(declared.get("bey_active_connections").map(String::as_str), Some("gauge"));
+        assert_eq!(samples.get("bey_tokens_sent_total").map(String::as_str), Some("1"));
+        assert_eq!(samples.get("bey_bytes_received_total").map(String::as_str), Some("40"));
+        assert_eq!(samples.get("bey_errors_total{code=\"4304\"}").map(String::as_str), Some("1"));
+        assert_eq!(samples.get("bey_active_connections").map(String::as_str), Some("2"));
+        assert_eq!(samples.get("bey_rtt_min_milliseconds").map(String::as_str), Some("20"));
+        assert_eq!(samples.get("bey_peer_bytes_sent_total{peer=\"device-a\"}").map(String::as_str), Some("100"));
+        assert_eq!(samples.get("bey_peer_rtt_milliseconds{peer=\"device-a\"}").map(String::as_str), Some("12"));
+        assert_eq!(
+            samples.get("bey_peer_tokens_received_total{peer=\"device-\\\"b\\\"\"}").map(String::as_str),
+            Some("1")
+        );
+        // 未测量RTT的对端不导出RTT样本
+        assert!(!samples.contains_key("bey_peer_rtt_milliseconds{peer=\"device-\\\"b\\\"\"}"));
+
+        // 尚未测量RTT时不导出最小RTT样本
+        let empty = Metrics::default().to_prometheus();
+        assert!(empty.contains("# TYPE bey_rtt_min_milliseconds gauge"));
+        assert!(!empty.lines().any(|line| line.starts_with("bey_rtt_min_milliseconds ")));
+    }
+
+    #[tokio::test]
+    async fn test_peer_metrics_are_bounded() {
+        let collector = MetricsCollector::new();
+        for i in 0..MAX_TRACKED_PEERS + 10 {
+            collector.record_peer_receive(&format!("peer-{}", i), 10).await;
+        }
+        collector.record_peer_send("peer-0", 5).await;
+
+        let metrics = collector.get_metrics().await;
+        assert_eq!(metrics.peers.len(), MAX_TRACKED_PEERS);
+        assert_eq!(metrics.tokens_received, (MAX_TRACKED_PEERS + 10) as u64, "总量应包含未单独统计的对端");
+        assert_eq!(metrics.peers.get("peer-0").map(|stats| stats.bytes_sent), Some(5));
+        assert!(!metrics.peers.contains_key(&format!("peer-{}", MAX_TRACKED_PEERS)));
+    }
+
+    #[tokio::test]
+    async fn test_peer_loss_counts() {
+        let collector = MetricsCollector::new();
+        collector.record_peer_retransmit("lossy").await;
+        collector.record_peer_retransmit("lossy").await;
+        collector.record_peer_timeout("lossy").await;
+        collector.record_peer_send("clean", 10).await;
+
+        let metrics = collector.get_metrics().await;
+        assert_eq!(metrics.retransmit_count, 2);
+        assert_eq!(metrics.timeout_count, 1);
+        let lossy = metrics.peers.get("lossy").expect("应统计丢包对端");
+        assert_eq!((lossy.retransmits, lossy.timeouts), (2, 1));
+        let clean = metrics.peers.get("clean").expect("应统计正常对端");
+        assert_eq!((clean.retransmits, clean.timeouts), (0, 0));
+    }
+
     #[test]
     fn test_latency_histogram() {
         let mut histogram = LatencyHistogram::new();
@@ -0,0 +1,295 @@
+//! # BEY 多地址路径选择
+//!
+//! 设备可能同时通告多个地址（如有线和无线网卡）。路径选择器测量每个地址的往返时间（RTT），
+//! 新连接优先使用RTT最低的地址，并支持手动指定设备的首选地址。
+//!
+//! ## 核心功能
+//!
+//! - **RTT探测**: 通过可替换的 [`RttProbe`] 并发探测设备的所有地址
+//! - **最低延迟优先**: 选择最近一次探测中RTT最低的可达地址
+//! - **定期重新探测**: 测量结果超过探测间隔后重新探测
+//! - **手动首选**: 首选地址仍在设备地址列表中时优先于探测结果
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::task::JoinSet;
+use tracing::debug;
+
+/// RTT探测接口
+#[async_trait]
+pub trait RttProbe: Send + Sync {
+    /// 探测地址的往返时间
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 目标地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回测得的RTT，地址不可达时返回 `None`
+    async fn probe(&self, addr: SocketAddr) -> Option<Duration>;
+}
+
+/// 地址的RTT测量结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressRtt {
+    /// 地址
+    pub addr: SocketAddr,
+    /// 测得的RTT，不可达时为 `None`
+    pub rtt: Option<Duration>,
+}
+
+/// 单个设备的测量记录
+#[derive(Debug, Clone)]
+struct DeviceRtts {
+    /// 各地址的测量结果（按探测时的地址顺序）
+    measurements: Vec<AddressRtt>,
+    /// 探测时间
+    probed_at: Instant,
+}
+
+/// 多地址路径选择器
+pub struct PathSelector {
+    /// RTT探测器
+    probe: Arc<dyn RttProbe>,
+    /// 重新探测间隔
+    probe_interval: Duration,
+    /// 设备测量记录（设备名 -> 测量结果）
+    rtts: RwLock<HashMap<String, DeviceRtts>>,
+    /// 手动指定的首选地址（设备名 -> 地址）
+    preferred: RwLock<HashMap<String, SocketAddr>>,
+}
+
+impl PathSelector {
+    /// 创建路径选择器
+    ///
+    /// # 参数
+    ///
+    /// * `probe` - RTT探测器
+    /// * `probe_interval` - 测量结果的有效期，过期后重新探测
+    pub fn new(probe: Arc<dyn RttProbe>, probe_interval: Duration) -> Self {
+        Self {
+            probe,
+            probe_interval,
+            rtts: RwLock::new(HashMap::new()),
+            preferred: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 获取重新探测间隔
+    pub fn probe_interval(&self) -> Duration {
+        self.probe_interval
+    }
+
+    /// 探测设备的所有地址并记录结果
+    ///
+    /// 所有地址并发探测，总耗时取决于最慢的地址
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    /// * `addresses` - 设备地址列表
+    ///
+    /// # 返回值
+    ///
+    /// 返回按输入顺序排列的测量结果
+    pub async fn probe_device(&self, device_name: &str, addresses: &[SocketAddr]) -> Vec<AddressRtt> {
+        let mut probes = JoinSet::new();
+        for (index, addr) in addresses.iter().copied().enumerate() {
+            let probe = Arc::clone(&self.probe);
+            probes.spawn(async move { (index, probe.probe(addr).await) });
+        }
+
+        let mut measurements: Vec<AddressRtt> = addresses.iter()
+            .map(|addr| AddressRtt { addr: *addr, rtt: None })
+            .collect();
+        while let Some(result) = probes.join_next().await {
+            if let Ok((index, rtt)) = result {
+                measurements[index].rtt = rtt;
+            }
+        }
+
+        debug!("设备 {} 地址探测结果: {:?}", device_name, measurements);
+        self.rtts.write().await.insert(device_name.to_string(), DeviceRtts {
+            measurements: measurements.clone(),
+            probed_at: Instant::now(),
+        });
+        measurements
+    }
+
+    /// 为新连接选择地址
+    ///
+    /// 首选地址在地址列表中时直接使用；否则在测量结果缺失、过期或地址列表变化时重新探测，
+    /// 返回RTT最低的可达地址。所有地址都不可达时返回列表中的第一个地址。
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    /// * `addresses` - 设备当前的地址列表
+    ///
+    /// # 返回值
+    ///
+    /// 返回选中的地址，地址列表为空时返回 `None`
+    pub async fn select_address(&self, device_name: &str, addresses: &[SocketAddr]) -> Option<SocketAddr> {
+        if let Some(preferred) = self.preferred.read().await.get(device_name) {
+            if addresses.contains(preferred) {
+                return Some(*preferred);
+            }
+        }
+
+        let first = *addresses.first()?;
+        if addresses.len() == 1 {
+            return Some(first);
+        }
+
+        let cached = self.rtts.read().await.get(device_name)
+            .filter(|entry| entry.probed_at.elapsed() < self.probe_interval)
+            .filter(|entry| entry.measurements.iter().map(|m| m.addr).eq(addresses.iter().copied()))
+            .map(|entry| entry.measurements.clone());
+        let measurements = match cached {
+            Some(measurements) => measurements,
+            None => self.probe_device(device_name, addresses).await,
+        };
+
+        let selected = measurements.iter()
+            .filter_map(|m| m.rtt.map(|rtt| (rtt, m.addr)))
+            .min_by_key(|(rtt, _)| *rtt)
+            .map(|(_, addr)| addr)
+            .unwrap_or(first);
+        debug!("设备 {} 选择地址: {}", device_name, selected);
+        Some(selected)
+    }
+
+    /// 手动指定设备的首选地址
+    ///
+    /// # 参数
+    ///
+    /// * `device_name` - 设备名称
+    /// * `addr` - 首选地址
+    pub async fn prefer_address(&self, device_name: &str, addr: SocketAddr) {
+        self.preferred.write().await.insert(device_name.to_string(), addr);
+        debug!("设备 {} 首选地址: {}", device_name, addr);
+    }
+
+    /// 清除设备的首选地址，恢复按RTT选择
+    pub async fn clear_preferred_address(&self, device_name: &str) {
+        self.preferred.write().await.remove(device_name);
+    }
+
+    /// 获取设备的首选地址
+    pub async fn preferred_address(&self, device_name: &str) -> Option<SocketAddr> {
+        self.preferred.read().await.get(device_name).copied()
+    }
+
+    /// 获取设备最近一次的测量结果
+    pub async fn address_rtts(&self, device_name: &str) -> Vec<AddressRtt> {
+        self.rtts.read().await.get(device_name)
+            .map(|entry| entry.measurements.clone())
+            .unwrap_or_default()
+    }
+
+    /// 移除不在设备列表中的测量记录
+    pub async fn retain_devices(&self, device_names: &[String]) {
+        self.rtts.write().await.retain(|name, _| device_names.contains(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 按预设延迟返回结果的模拟探测器
+    #[derive(Default)]
+    struct MockProbe {
+        latencies: Mutex<HashMap<SocketAddr, Duration>>,
+        probes: AtomicUsize,
+    }
+
+    impl MockProbe {
+        fn set_latency(&self, addr: SocketAddr, latency: Duration) {
+            self.latencies.lock().expect("获取锁失败").insert(addr, latency);
+        }
+    }
+
+    #[async_trait]
+    impl RttProbe for MockProbe {
+        async fn probe(&self, addr: SocketAddr) -> Option<Duration> {
+            self.probes.fetch_add(1, Ordering::SeqCst);
+            self.latencies.lock().expect("获取锁失败").get(&addr).copied()
+        }
+    }
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().expect("地址解析失败")
+    }
+
+    #[tokio::test]
+    async fn test_select_lowest_rtt_address() {
+        let wired = addr("192.168.1.10:8080");
+        let wireless = addr("192.168.2.10:8080");
+        let probe = Arc::new(MockProbe::default());
+        probe.set_latency(wireless, Duration::from_millis(40));
+        probe.set_latency(wired, Duration::from_millis(2));
+
+        let selector = PathSelector::new(probe.clone(), Duration::from_secs(60));
+        let addresses = [wireless, wired];
+        assert_eq!(selector.select_address("peer", &addresses).await, Some(wired));
+        assert_eq!(probe.probes.load(Ordering::SeqCst), 2);
+
+        let rtts = selector.address_rtts("peer").await;
+        assert_eq!(rtts[0], AddressRtt { addr: wireless, rtt: Some(Duration::from_millis(40)) });
+        assert_eq!(rtts[1], AddressRtt { addr: wired, rtt: Some(Duration::from_millis(2)) });
+
+        // 测量结果未过期，不重新探测
+        assert_eq!(selector.select_address("peer", &addresses).await, Some(wired));
+        assert_eq!(probe.probes.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reprobe_and_unreachable_addresses() {
+        let first = addr("10.0.0.1:8080");
+        let second = addr("10.0.0.2:8080");
+        let probe = Arc::new(MockProbe::default());
+        probe.set_latency(first, Duration::from_millis(30));
+        probe.set_latency(second, Duration::from_millis(5));
+
+        // 间隔为0时每次选择都重新探测
+        let selector = PathSelector::new(probe.clone(), Duration::ZERO);
+        assert_eq!(selector.select_address("peer", &[first, second]).await, Some(second));
+
+        probe.set_latency(second, Duration::from_millis(80));
+        assert_eq!(selector.select_address("peer", &[first, second]).await, Some(first));
+
+        // 所有地址都不可达时退回第一个地址
+        probe.latencies.lock().expect("获取锁失败").clear();
+        assert_eq!(selector.select_address("peer", &[first, second]).await, Some(first));
+        assert_eq!(selector.select_address("peer", &[]).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_preferred_address_override() {
+        let fast = addr("10.0.0.1:8080");
+        let slow = addr("10.0.0.2:8080");
+        let probe = Arc::new(MockProbe::default());
+        probe.set_latency(fast, Duration::from_millis(1));
+        probe.set_latency(slow, Duration::from_millis(50));
+
+        let selector = PathSelector::new(probe.clone(), Duration::from_secs(60));
+        selector.prefer_address("peer", slow).await;
+        assert_eq!(selector.select_address("peer", &[fast, slow]).await, Some(slow));
+        assert_eq!(probe.probes.load(Ordering::SeqCst), 0);
+
+        // 首选地址不在地址列表中时按RTT选择
+        assert_eq!(selector.select_address("peer", &[fast, addr("10.0.0.3:8080")]).await, Some(fast));
+
+        selector.clear_preferred_address("peer").await;
+        assert_eq!(selector.preferred_address("peer").await, None);
+        assert_eq!(selector.select_address("peer", &[fast, slow]).await, Some(fast));
+    }
+}
@@ -0,0 +1,211 @@
+//! # 已知设备缓存
+//!
+//! 将已发现的设备（地址、证书指纹和最后活跃时间）保存到磁盘，引擎重启后重新加载为
+//! “过期的已知设备”，无需等待新一轮发现即可直接连接。超过最大保留时间的记录在加载时丢弃。
+//!
+//! 保存时与磁盘上的缓存合并：暂时未被发现的设备在最大保留时间内仍保留在缓存中。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+use crate::NetResult;
+
+/// 已知设备缓存默认的最大保留时间
+pub const DEFAULT_PEER_CACHE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 持久化的已知设备
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedPeer {
+    /// 设备名称
+    pub name: String,
+    /// 设备地址列表
+    pub addresses: Vec<SocketAddr>,
+    /// 证书指纹
+    pub cert_fingerprint: Option<String>,
+    /// 最后活跃时间
+    pub last_seen: SystemTime,
+}
+
+/// 已知设备缓存文件
+#[derive(Debug, Clone)]
+pub struct PeerCache {
+    /// 缓存文件路径
+    path: PathBuf,
+    /// 串行化读取-合并-写入，避免并发保存互相覆盖
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl PeerCache {
+    /// 创建已知设备缓存
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 缓存文件路径
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// 缓存文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取缓存的设备，丢弃超过最大保留时间的记录
+    ///
+    /// # 参数
+    ///
+    /// * `max_age` - 最大保留时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回仍在保留时间内的设备，缓存文件不存在时返回空列表
+    pub fn load(&self, max_age: Duration) -> NetResult<Vec<PersistedPeer>> {
+        let content = match std::fs::read(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(ErrorInfo::new(4336, format!("读取已知设备缓存失败: {}", e))
+                    .with_category(ErrorCategory::Storage)
+                    .with_severity(ErrorSeverity::Warning));
+            }
+        };
+
+        let peers: Vec<PersistedPeer> = serde_json::from_slice(&content)
+            .map_err(|e| ErrorInfo::new(4336, format!("解析已知设备缓存失败: {}", e))
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Warning))?;
+
+        let now = SystemTime::now();
+        let total = peers.len();
+        let peers: Vec<PersistedPeer> = peers.into_iter()
+            .filter(|peer| now.duration_since(peer.last_seen).map_or(true, |age| age <= max_age))
+            .collect();
+        debug!("加载已知设备缓存: {} 个设备，丢弃 {} 个过期设备", peers.len(), total - peers.len());
+        Ok(peers)
+    }
+
+    /// 将设备合并到磁盘上的缓存
+    ///
+    /// 同名设备保留最后活跃时间较新的记录，缓存中未出现在 `peers` 里的设备保留到最大保留时间；
+    /// 原有缓存无法读取时以 `peers` 重建。会阻塞在文件读写上，异步上下文中应放到阻塞线程执行
+    ///
+    /// # 参数
+    ///
+    /// * `peers` - 当前已发现的设备
+    /// * `max_age` - 最大保留时间
+    pub fn merge(&self, peers: &[PersistedPeer], max_age: Duration) -> NetResult<()> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let existing = self.load(max_age).unwrap_or_else(|e| {
+            warn!("已知设备缓存无法读取，将重建: {}", e);
+            Vec::new()
+        });
+        let mut merged: HashMap<String, PersistedPeer> = existing.into_iter()
+            .map(|peer| (peer.name.clone(), peer))
+            .collect();
+        for peer in peers {
+            match merged.get(&peer.name) {
+                Some(cached) if cached.last_seen > peer.last_seen => {}
+                _ => {
+                    merged.insert(peer.name.clone(), peer.clone());
+                }
+            }
+        }
+
+        let mut merged: Vec<PersistedPeer> = merged.into_values().collect();
+        merged.sort_by(|a, b| a.name.cmp(&b.name));
+        self.save(&merged)
+    }
+
+    /// 保存设备列表，覆盖原有缓存
+    ///
+    /// 先写入临时文件再替换，写入中断不会损坏原有缓存
+    ///
+    /// # 参数
+    ///
+    /// * `peers` - 要保存的设备
+    pub fn save(&self, peers: &[PersistedPeer]) -> NetResult<()> {
+        let write_error = |e: String| {
+            ErrorInfo::new(4337, format!("写入已知设备缓存失败: {}", e))
+                .with_category(ErrorCategory::Storage)
+                .with_severity(ErrorSeverity::Warning)
+        };
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| write_error(e.to_string()))?;
+        }
+        let content = serde_json::to_vec_pretty(peers).map_err(|e| write_error(e.to_string()))?;
+        let temp_path = self.path.with_extension("tmp");
+        std::fs::write(&temp_path, content).map_err(|e| write_error(e.to_string()))?;
+        std::fs::rename(&temp_path, &self.path).map_err(|e| write_error(e.to_string()))?;
+
+        debug!("已保存 {} 个已知设备到 {}", peers.len(), self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_cache_round_trip_purges_old_entries() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let cache = PeerCache::new(temp_dir.path().join("peers").join("known_peers.json"));
+        assert!(cache.load(DEFAULT_PEER_CACHE_MAX_AGE).expect("读取缓存失败").is_empty());
+
+        let recent = PersistedPeer {
+            name: "recent".to_string(),
+            addresses: vec!["192.168.1.10:8080".parse().expect("解析地址失败")],
+            cert_fingerprint: Some("fingerprint".to_string()),
+            last_seen: SystemTime::now() - Duration::from_secs(60),
+        };
+        let old = PersistedPeer {
+            name: "old".to_string(),
+            addresses: vec!["192.168.1.11:8080".parse().expect("解析地址失败")],
+            cert_fingerprint: None,
+            last_seen: SystemTime::now() - Duration::from_secs(7200),
+        };
+        cache.save(&[recent.clone(), old]).expect("保存缓存失败");
+
+        let loaded = cache.load(Duration::from_secs(3600)).expect("读取缓存失败");
+        assert_eq!(loaded, vec![recent]);
+    }
+
+    #[test]
+    fn test_merge_keeps_cached_peers_not_currently_seen() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let cache = PeerCache::new(temp_dir.path().join("known_peers.json"));
+        let peer = |name: &str, age: u64| PersistedPeer {
+            name: name.to_string(),
+            addresses: vec!["192.168.1.10:8080".parse().expect("解析地址失败")],
+            cert_fingerprint: None,
+            last_seen: SystemTime::now() - Duration::from_secs(age),
+        };
+
+        cache.merge(&[peer("offline", 600), peer("updated", 600)], Duration::from_secs(3600)).expect("保存缓存失败");
+        // 只包含当前可见设备的保存不会清除暂时离线的设备
+        cache.merge(&[peer("updated", 10), peer("new", 10)], Duration::from_secs(3600)).expect("保存缓存失败");
+
+        let loaded = cache.load(Duration::from_secs(3600)).expect("读取缓存失败");
+        let names: Vec<&str> = loaded.iter().map(|peer| peer.name.as_str()).collect();
+        assert_eq!(names, vec!["new", "offline", "updated"]);
+        let updated = loaded.iter().find(|peer| peer.name == "updated").expect("应有设备");
+        assert!(SystemTime::now().duration_since(updated.last_seen).expect("时间错误") < Duration::from_secs(60));
+
+        // 较旧的记录不覆盖较新的记录，超过保留时间的记录被丢弃
+        cache.merge(&[peer("updated", 900)], Duration::from_secs(300)).expect("保存缓存失败");
+        let loaded = cache.load(Duration::from_secs(3600)).expect("读取缓存失败");
+        let names: Vec<&str> = loaded.iter().map(|peer| peer.name.as_str()).collect();
+        assert_eq!(names, vec!["new", "updated"]);
+    }
+}
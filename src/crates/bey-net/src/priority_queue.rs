@@ -7,13 +7,16 @@
 //! - **优先级排序**: 自动按优先级排序令牌
 //! - **确认机制**: 支持令牌确认和重传
 //! - **超时管理**: 自动处理超时的令牌
+//! - **可靠发送**: 按确认超时重传令牌，超过最大重传次数后返回错误
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use std::collections::{BinaryHeap, HashMap};
 use std::cmp::Ordering;
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::time::{SystemTime, Duration};
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::{RwLock, mpsc, oneshot};
 use tracing::{debug, info, warn};
 
 use crate::{NetResult, token::Token};
@@ -23,8 +26,8 @@ use crate::{NetResult, token::Token};
 struct PriorityQueueEntry {
     /// 令牌
     token: Token,
-    /// 入队时间
-    enqueued_at: SystemTime,
+    /// 入队序号，同优先级的令牌按入队顺序出队
+    sequence: u64,
     /// 需要确认
     requires_ack: bool,
     /// 重试次数
@@ -47,9 +50,9 @@ impl PartialOrd for PriorityQueueEntry {
 
 impl Ord for PriorityQueueEntry {
     fn cmp(&self, other: &Self) -> Ordering {
-        // 优先级高的排在前面，时间早的排在前面
+        // 优先级高的排在前面，入队早的排在前面
         match self.token.meta.priority.cmp(&other.token.meta.priority) {
-            Ordering::Equal => other.enqueued_at.cmp(&self.enqueued_at),
+            Ordering::Equal => other.sequence.cmp(&self.sequence),
             other_ord => other_ord,
         }
     }
@@ -66,6 +69,17 @@ pub enum AckStatus {
     Timeout,
 }
 
+/// 确认超时的令牌
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AckTimeout {
+    /// 令牌ID
+    pub token_id: String,
+    /// 接收方设备，未指定接收方时为 `None`
+    pub receiver_id: Option<String>,
+    /// 是否已重新入队重传，`false` 表示已达最大重传次数被放弃
+    pub retried: bool,
+}
+
 /// 待确认的令牌
 struct PendingAck {
     /// 令牌
@@ -76,8 +90,10 @@ struct PendingAck {
     timeout: Duration,
     /// 重试次数
     retry_count: u32,
-    /// 最大重试次数
-    max_retries: u32,
+    /// 最大重传次数
+    max_retransmits: u32,
+    /// 可靠发送的确认等待者，由发送方自行负责重传
+    waiter: Option<oneshot::Sender<()>>,
 }
 
 /// 优先级队列
@@ -92,13 +108,20 @@ pub struct PriorityQueue {
     _ack_receiver: Arc<RwLock<mpsc::UnboundedReceiver<String>>>,
     /// 默认确认超时
     default_ack_timeout: Duration,
-    /// 最大重试次数
-    max_retries: u32,
+    /// 最大重传次数
+    max_retransmits: u32,
+    /// 下一个入队序号
+    next_sequence: AtomicU64,
 }
 
 impl PriorityQueue {
     /// 创建优先级队列
-    pub fn new(default_ack_timeout: Duration, max_retries: u32) -> Self {
+    ///
+    /// # 参数
+    ///
+    /// * `default_ack_timeout` - 等待确认的超时时间
+    /// * `max_retransmits` - 超时后的最大重传次数
+    pub fn new(default_ack_timeout: Duration, max_retransmits: u32) -> Self {
         let (ack_notify, ack_receiver) = mpsc::unbounded_channel();
         
         Self {
@@ -107,7 +130,8 @@ impl PriorityQueue {
             ack_notify,
             _ack_receiver: Arc::new(RwLock::new(ack_receiver)),
             default_ack_timeout,
-            max_retries,
+            max_retransmits,
+            next_sequence: AtomicU64::new(0),
         }
     }
 
@@ -117,7 +141,7 @@ impl PriorityQueue {
         
         let entry = PriorityQueueEntry {
             token: token.clone(),
-            enqueued_at: SystemTime::now(),
+            sequence: self.next_sequence.fetch_add(1, AtomicOrdering::Relaxed),
             requires_ack,
             retry_count: 0,
         };
@@ -145,7 +169,8 @@ impl PriorityQueue {
                     sent_at: SystemTime::now(),
                     timeout: self.default_ack_timeout,
                     retry_count: entry.retry_count,
-                    max_retries: self.max_retries,
+                    max_retransmits: self.max_retransmits,
+                    waiter: None,
                 };
                 
                 let mut pending_acks = self.pending_acks.write().await;
@@ -161,10 +186,42 @@ impl PriorityQueue {
 
     /// 确认令牌
     pub async fn acknowledge(&self, token_id: &str) -> NetResult<()> {
+        self.complete_ack(token_id, None).await
+    }
+
+    /// 确认对端发来的确认令牌
+    ///
+    /// 令牌指定了接收方时，只接受该接收方发来的确认，其他设备的确认被拒绝，令牌继续等待
+    ///
+    /// # 参数
+    ///
+    /// * `token_id` - 被确认的令牌ID
+    /// * `sender_id` - 确认令牌的发送方
+    ///
+    /// # 返回值
+    ///
+    /// 返回确认结果或错误
+    pub async fn acknowledge_from(&self, token_id: &str, sender_id: &str) -> NetResult<()> {
+        self.complete_ack(token_id, Some(sender_id)).await
+    }
+
+    /// 移除待确认的令牌并唤醒等待者，给出发送方时检查其是否为令牌的接收方
+    async fn complete_ack(&self, token_id: &str, sender_id: Option<&str>) -> NetResult<()> {
         let mut pending_acks = self.pending_acks.write().await;
-        
-        if pending_acks.remove(token_id).is_some() {
+
+        if let (Some(sender_id), Some(pending)) = (sender_id, pending_acks.get(token_id)) {
+            if let Some(receiver_id) = pending.token.meta.receiver_id.as_deref().filter(|receiver_id| *receiver_id != sender_id) {
+                return Err(ErrorInfo::new(4506, format!("令牌 {} 的确认来自 {}，而不是接收方 {}", token_id, sender_id, receiver_id))
+                    .with_category(ErrorCategory::Validation)
+                    .with_severity(ErrorSeverity::Warning));
+            }
+        }
+
+        if let Some(pending) = pending_acks.remove(token_id) {
             info!("令牌已确认: {}", token_id);
+            if let Some(waiter) = pending.waiter {
+                let _ = waiter.send(());
+            }
             let _ = self.ack_notify.send(token_id.to_string());
             Ok(())
         } else {
@@ -175,18 +232,28 @@ impl PriorityQueue {
     }
 
     /// 检查并处理超时的令牌
-    pub async fn check_timeouts(&self) -> usize {
+    ///
+    /// # 返回值
+    ///
+    /// 返回本次超时的令牌及其接收方，供按对端统计重传和超时
+    pub async fn check_timeouts(&self) -> Vec<AckTimeout> {
         let now = SystemTime::now();
         let mut pending_acks = self.pending_acks.write().await;
         let mut heap = self.heap.write().await;
         
         let mut timed_out = Vec::new();
         let mut to_retry = Vec::new();
+        let mut report = Vec::new();
 
         for (token_id, pending) in pending_acks.iter() {
+            // 可靠发送的令牌由发送方按自己的节奏重传
+            if pending.waiter.is_some() {
+                continue;
+            }
+
             if let Ok(elapsed) = now.duration_since(pending.sent_at) {
                 if elapsed > pending.timeout {
-                    if pending.retry_count < pending.max_retries {
+                    if pending.retry_count < pending.max_retransmits {
                         // 可以重试
                         to_retry.push(token_id.clone());
                     } else {
@@ -199,8 +266,13 @@ impl PriorityQueue {
 
         // 移除超时的令牌
         for token_id in &timed_out {
-            if pending_acks.remove(token_id).is_some() {
+            if let Some(pending) = pending_acks.remove(token_id) {
                 warn!("令牌超时（已达最大重试次数）: {}", token_id);
+                report.push(AckTimeout {
+                    token_id: token_id.clone(),
+                    receiver_id: pending.token.meta.receiver_id.clone(),
+                    retried: false,
+                });
             }
         }
 
@@ -212,17 +284,109 @@ impl PriorityQueue {
                 
                 let entry = PriorityQueueEntry {
                     token: pending.token.clone(),
-                    enqueued_at: now,
+                    sequence: self.next_sequence.fetch_add(1, AtomicOrdering::Relaxed),
                     requires_ack: true,
                     retry_count: pending.retry_count,
                 };
                 
                 heap.push(entry);
                 info!("令牌重试: {} (第{}次)", token_id, pending.retry_count);
+                report.push(AckTimeout {
+                    token_id: token_id.clone(),
+                    receiver_id: pending.token.meta.receiver_id.clone(),
+                    retried: true,
+                });
+            }
+        }
+
+        report
+    }
+
+    /// 可靠发送令牌
+    ///
+    /// 发送令牌后等待确认，超过确认超时未收到确认时重传，
+    /// 重传次数超过 `max_retransmits` 后放弃并返回错误。
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 要发送的令牌
+    /// * `send` - 实际发送函数，参数为令牌和发送序号（0为首次发送，大于0为重传）
+    ///
+    /// # 返回值
+    ///
+    /// 返回收到确认前的重传次数，发送失败或重传次数耗尽时返回错误
+    pub async fn send_reliable<F, Fut>(&self, token: Token, mut send: F) -> NetResult<u32>
+    where
+        F: FnMut(Token, u32) -> Fut,
+        Fut: Future<Output = NetResult<()>>,
+    {
+        let token_id = token.meta.id.clone();
+
+        for attempt in 0..=self.max_retransmits {
+            let (waiter, acked) = oneshot::channel();
+            {
+                let mut pending_acks = self.pending_acks.write().await;
+                pending_acks.insert(token_id.clone(), PendingAck {
+                    token: token.clone(),
+                    sent_at: SystemTime::now(),
+                    timeout: self.default_ack_timeout,
+                    retry_count: attempt,
+                    max_retransmits: self.max_retransmits,
+                    waiter: Some(waiter),
+                });
+            }
+
+            if attempt > 0 {
+                info!("令牌重传: {} (第{}次)", token_id, attempt);
+            }
+
+            if let Err(e) = send(token.clone(), attempt).await {
+                self.pending_acks.write().await.remove(&token_id);
+                return Err(e);
+            }
+
+            match tokio::time::timeout(self.default_ack_timeout, acked).await {
+                Ok(Ok(())) => return Ok(attempt),
+                Ok(Err(_)) => {
+                    return Err(ErrorInfo::new(4503, format!("令牌 {} 的确认等待已被取消", token_id))
+                        .with_category(ErrorCategory::System)
+                        .with_severity(ErrorSeverity::Warning));
+                }
+                Err(_) => debug!("令牌确认超时: {} (第{}次发送)", token_id, attempt + 1),
             }
         }
 
-        timed_out.len() + to_retry.len()
+        self.pending_acks.write().await.remove(&token_id);
+        warn!("令牌超时（已达最大重传次数）: {}", token_id);
+        Err(ErrorInfo::new(4502, format!(
+            "令牌 {} 在重传 {} 次后仍未收到确认（确认超时 {:?}）",
+            token_id, self.max_retransmits, self.default_ack_timeout
+        ))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error))
+    }
+
+    /// 撤销令牌：从队列和待确认列表中移除，不再发送或重传
+    ///
+    /// # 参数
+    ///
+    /// * `token_id` - 令牌ID
+    ///
+    /// # 返回值
+    ///
+    /// 令牌在队列或待确认列表中时返回 `true`
+    pub async fn cancel(&self, token_id: &str) -> bool {
+        let mut heap = self.heap.write().await;
+        let before = heap.len();
+        heap.retain(|entry| entry.token.meta.id != token_id);
+        let removed = heap.len() != before;
+        drop(heap);
+
+        let acked = self.pending_acks.write().await.remove(token_id).is_some();
+        if removed || acked {
+            debug!("令牌已撤销: {}", token_id);
+        }
+        removed || acked
     }
 
     /// 获取队列大小
@@ -258,7 +422,8 @@ impl Default for PriorityQueue {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::token::TokenMeta;
+    use crate::token::{TokenMeta, TokenPriority};
+    use std::sync::atomic::{AtomicU32, Ordering as AtomicOrdering};
 
     #[tokio::test]
     async fn test_priority_queue() {
@@ -309,4 +474,71 @@ mod tests {
         queue.acknowledge(&token_id).await.unwrap();
         assert_eq!(queue.pending_acks_count().await, 0);
     }
+
+    #[tokio::test]
+    async fn test_acknowledge_only_from_receiver() {
+        let queue = PriorityQueue::default();
+        let token = Token::new(
+            TokenMeta::new("test".to_string(), "sender".to_string())
+                .with_receiver("peer".to_string())
+                .with_ack(true),
+            vec![1, 2, 3]
+        );
+        let token_id = token.meta.id.clone();
+        queue.enqueue(token).await.unwrap();
+        queue.dequeue().await.unwrap().unwrap();
+
+        // 其他设备的确认被拒绝，令牌仍在等待确认
+        let error = queue.acknowledge_from(&token_id, "intruder").await.unwrap_err();
+        assert_eq!(error.code(), 4506);
+        assert_eq!(queue.pending_acks_count().await, 1);
+
+        queue.acknowledge_from(&token_id, "peer").await.unwrap();
+        assert_eq!(queue.pending_acks_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_reliable_with_injected_loss() {
+        let queue = Arc::new(PriorityQueue::new(Duration::from_millis(50), 2));
+
+        // 丢弃前两次发送，第三次送达后对端确认
+        let token = Token::new(
+            TokenMeta::new("test".to_string(), "sender".to_string()).with_ack(true),
+            vec![1, 2, 3]
+        );
+        let attempts = Arc::new(AtomicU32::new(0));
+        let retransmits = queue.send_reliable(token, |token, attempt| {
+            let queue = Arc::clone(&queue);
+            let attempts = Arc::clone(&attempts);
+            async move {
+                attempts.fetch_add(1, AtomicOrdering::SeqCst);
+                if attempt >= 2 {
+                    tokio::spawn(async move {
+                        queue.acknowledge(&token.meta.id).await.unwrap();
+                    });
+                }
+                Ok(())
+            }
+        }).await.unwrap();
+        assert_eq!(retransmits, 2);
+        assert_eq!(attempts.load(AtomicOrdering::SeqCst), 3);
+        assert_eq!(queue.pending_acks_count().await, 0);
+
+        // 所有发送都丢失，重传次数耗尽后返回错误
+        let lost_token = Token::new(
+            TokenMeta::new("test".to_string(), "sender".to_string()).with_ack(true),
+            vec![4, 5, 6]
+        );
+        let lost_attempts = Arc::new(AtomicU32::new(0));
+        let error = queue.send_reliable(lost_token, |_, _| {
+            let lost_attempts = Arc::clone(&lost_attempts);
+            async move {
+                lost_attempts.fetch_add(1, AtomicOrdering::SeqCst);
+                Ok(())
+            }
+        }).await.unwrap_err();
+        assert_eq!(error.code(), 4502);
+        assert_eq!(lost_attempts.load(AtomicOrdering::SeqCst), 3);
+        assert_eq!(queue.pending_acks_count().await, 0);
+    }
 }
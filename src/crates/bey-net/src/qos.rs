@@ -0,0 +1,281 @@
+//! # BEY 服务质量调度
+//!
+//! 让令牌优先级（以及由 `bey_types::MessagePriority` 转换来的优先级）决定实际的发送顺序：
+//!
+//! - **发送调度**: 出站令牌先进入优先级队列，由调度器每次取出优先级最高的令牌发送，
+//!   大文件的低优先级数据块排队时，紧急的控制消息会越过剩余的数据块先发出
+//! - **QUIC流优先级**: 按令牌优先级设置发送流的优先级，同一连接上高优先级的流先占用带宽
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+use tracing::{debug, warn};
+
+use bey_transport::{Connection, SendStream};
+
+use crate::{NetResult, priority_queue::PriorityQueue, token::{Token, TokenId, TokenPriority}};
+
+/// 等待发送结果的令牌
+type Waiters = std::sync::Mutex<HashMap<TokenId, oneshot::Sender<NetResult<()>>>>;
+
+/// 出站令牌调度器
+///
+/// 所有发送方共享同一个优先级队列，每次只发送一个令牌：等待结果的发送方轮流取得发送权，
+/// 取出队列中优先级最高的令牌（不一定是自己的）发送并通知它的发送方，
+/// 直到自己的令牌被发送为止。发送方放弃等待后，尚未发送的令牌会被撤销。
+pub struct OutboundScheduler {
+    /// 出站优先级队列
+    queue: Arc<PriorityQueue>,
+    /// 发送权，同一时间只发送一个令牌
+    dispatch: Mutex<()>,
+    /// 令牌ID -> 发送结果通知
+    waiters: Waiters,
+}
+
+impl OutboundScheduler {
+    /// 创建调度器
+    ///
+    /// # 参数
+    ///
+    /// * `queue` - 出站优先级队列
+    pub fn new(queue: Arc<PriorityQueue>) -> Self {
+        Self {
+            queue,
+            dispatch: Mutex::new(()),
+            waiters: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 按优先级发送一个令牌
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 要发送的令牌
+    /// * `transmit` - 实际发送函数，调度器用它发送队列中的任意令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回该令牌的发送结果
+    pub async fn send<F, Fut>(&self, token: Token, transmit: F) -> NetResult<()>
+    where
+        F: Fn(Token) -> Fut,
+        Fut: Future<Output = NetResult<()>>,
+    {
+        let mut pending = self.submit(vec![token]).await?;
+        match pending.pop() {
+            Some(receiver) => self.wait(receiver, &transmit).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 将一批令牌放入队列
+    ///
+    /// 返回的接收端按令牌顺序排列，需要逐个传给 [`OutboundScheduler::wait`]；
+    /// 丢弃接收端表示放弃发送，尚未发送的令牌会被撤销。
+    ///
+    /// # 参数
+    ///
+    /// * `tokens` - 要发送的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回每个令牌发送结果的接收端
+    pub async fn submit(&self, tokens: Vec<Token>) -> NetResult<Vec<oneshot::Receiver<NetResult<()>>>> {
+        let mut receivers = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let (sender, receiver) = oneshot::channel();
+            self.lock_waiters().insert(token.meta.id.clone(), sender);
+            if let Err(e) = self.queue.enqueue(token.clone()).await {
+                self.lock_waiters().remove(&token.meta.id);
+                return Err(e);
+            }
+            receivers.push(receiver);
+        }
+        Ok(receivers)
+    }
+
+    /// 等待令牌发送完成，等待期间轮流发送队列中优先级最高的令牌
+    ///
+    /// # 参数
+    ///
+    /// * `receiver` - [`OutboundScheduler::submit`] 返回的接收端
+    /// * `transmit` - 实际发送函数
+    ///
+    /// # 返回值
+    ///
+    /// 返回该令牌的发送结果
+    pub async fn wait<F, Fut>(&self, mut receiver: oneshot::Receiver<NetResult<()>>, transmit: &F) -> NetResult<()>
+    where
+        F: Fn(Token) -> Fut,
+        Fut: Future<Output = NetResult<()>>,
+    {
+        loop {
+            tokio::select! {
+                biased;
+                result = &mut receiver => {
+                    return result.unwrap_or_else(|_| Err(Self::withdrawn_error()));
+                }
+                _guard = self.dispatch.lock() => {
+                    if !self.dispatch_one(transmit).await {
+                        // 队列已空：自己的令牌要么刚被发送，要么已被移出队列
+                        return receiver.try_recv().unwrap_or_else(|_| Err(Self::withdrawn_error()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// 取出并发送队列中优先级最高的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 队列为空时返回 `false`
+    async fn dispatch_one<F, Fut>(&self, transmit: &F) -> bool
+    where
+        F: Fn(Token) -> Fut,
+        Fut: Future<Output = NetResult<()>>,
+    {
+        let token = match self.queue.dequeue().await {
+            Ok(Some(token)) => token,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!("出站队列出队失败: {}", e);
+                return false;
+            }
+        };
+        let token_id = token.meta.id.clone();
+        let waiter = self.lock_waiters().remove(&token_id);
+
+        // 发送方已放弃等待，撤销令牌
+        if waiter.as_ref().is_some_and(|waiter| waiter.is_closed()) {
+            self.queue.cancel(&token_id).await;
+            debug!("发送方已放弃，跳过令牌: {}", token_id);
+            return true;
+        }
+
+        debug!("调度发送令牌: {} (优先级: {:?})", token_id, token.meta.priority);
+        let result = transmit(token).await;
+        match waiter {
+            Some(waiter) => {
+                let _ = waiter.send(result);
+            }
+            None => {
+                // 超时重传的令牌没有等待者
+                if let Err(e) = result {
+                    warn!("重传令牌 {} 发送失败: {}", token_id, e);
+                }
+            }
+        }
+        true
+    }
+
+    fn lock_waiters(&self) -> std::sync::MutexGuard<'_, HashMap<TokenId, oneshot::Sender<NetResult<()>>>> {
+        self.waiters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn withdrawn_error() -> ErrorInfo {
+        ErrorInfo::new(4504, "令牌已被移出发送队列".to_string())
+            .with_category(ErrorCategory::System)
+            .with_severity(ErrorSeverity::Warning)
+    }
+}
+
+/// 打开按令牌优先级设置了QUIC流优先级的单向发送流
+///
+/// # 参数
+///
+/// * `connection` - QUIC连接
+/// * `priority` - 令牌优先级
+///
+/// # 返回值
+///
+/// 返回发送流或错误
+pub async fn open_prioritized_stream(connection: &Connection, priority: TokenPriority) -> NetResult<SendStream> {
+    let stream = connection.open_uni().await
+        .map_err(|e| ErrorInfo::new(4505, format!("打开发送流失败: {}", e))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error))?;
+    stream.set_priority(priority.stream_priority())
+        .map_err(|e| ErrorInfo::new(4505, format!("设置流优先级失败: {}", e))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error))?;
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenMeta;
+    use std::time::Duration;
+
+    fn token(priority: TokenPriority, label: &str) -> Token {
+        let meta = TokenMeta::new("qos_test".to_string(), "sender".to_string())
+            .with_priority(priority)
+            .with_attribute("label".to_string(), label.to_string());
+        Token::new(meta, Vec::new())
+    }
+
+    #[tokio::test]
+    async fn test_critical_message_preempts_bulk_transfer() {
+        let scheduler = Arc::new(OutboundScheduler::new(Arc::new(PriorityQueue::default())));
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let transmit = {
+            let sent = Arc::clone(&sent);
+            move |token: Token| {
+                let sent = Arc::clone(&sent);
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    sent.lock().expect("锁失败").push(token.meta.attributes["label"].clone());
+                    Ok(())
+                }
+            }
+        };
+
+        // 低优先级的大文件数据块全部排队
+        let chunks: Vec<Token> = (0..10).map(|i| token(TokenPriority::Low, &format!("chunk-{}", i))).collect();
+        let receivers = scheduler.submit(chunks).await.expect("入队失败");
+        let bulk = tokio::spawn({
+            let scheduler = Arc::clone(&scheduler);
+            let transmit = transmit.clone();
+            async move {
+                for receiver in receivers {
+                    scheduler.wait(receiver, &transmit).await.expect("发送数据块失败");
+                }
+            }
+        });
+
+        // 传输进行中发送紧急控制消息
+        while sent.lock().expect("锁失败").len() < 2 {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        scheduler.send(token(TokenPriority::Critical, "control"), transmit.clone()).await.expect("发送控制消息失败");
+        bulk.await.expect("大文件任务失败");
+
+        let sent = sent.lock().expect("锁失败").clone();
+        assert_eq!(sent.len(), 11);
+        let control_at = sent.iter().position(|label| label == "control").expect("控制消息未发送");
+        assert!(control_at <= 3, "控制消息应越过剩余的数据块: {:?}", sent);
+        let chunks: Vec<&String> = sent.iter().filter(|label| label.starts_with("chunk-")).collect();
+        let expected: Vec<String> = (0..10).map(|i| format!("chunk-{}", i)).collect();
+        assert_eq!(chunks, expected.iter().collect::<Vec<_>>(), "同优先级的数据块应保持顺序");
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_tokens_are_withdrawn() {
+        let queue = Arc::new(PriorityQueue::default());
+        let scheduler = OutboundScheduler::new(Arc::clone(&queue));
+        let receivers = scheduler.submit(vec![token(TokenPriority::Low, "abandoned")]).await.expect("入队失败");
+        drop(receivers);
+
+        let sent = std::sync::Mutex::new(Vec::new());
+        scheduler.send(token(TokenPriority::Low, "kept"), |token: Token| {
+            sent.lock().expect("锁失败").push(token.meta.attributes["label"].clone());
+            async { Ok(()) }
+        }).await.expect("发送失败");
+
+        assert_eq!(*sent.lock().expect("锁失败"), vec!["kept".to_string()]);
+        assert_eq!(queue.size().await, 0);
+    }
+}
@@ -0,0 +1,112 @@
+//! # 自适应查询间隔
+//!
+//! 根据已发现设备集合的稳定程度调整周期性查询的间隔：连续若干轮没有设备
+//! 出现或消失时逐步加倍间隔直到上限，减少稳定网络中的组播流量；一轮中出现
+//! 设备变化时减半间隔直到下限，让变化频繁的网络更快收敛。
+
+use std::time::Duration;
+
+/// 间隔开始加倍前需要连续稳定的查询轮数
+pub const STABLE_ROUNDS_BEFORE_BACKOFF: u32 = 3;
+
+/// 自适应查询间隔
+#[derive(Debug, Clone)]
+pub struct AdaptiveQueryInterval {
+    /// 当前间隔
+    current: Duration,
+    /// 最小间隔
+    min: Duration,
+    /// 最大间隔
+    max: Duration,
+    /// 连续没有设备变化的轮数
+    stable_rounds: u32,
+    /// 本轮观察到的设备变化次数
+    pending_changes: u32,
+}
+
+impl AdaptiveQueryInterval {
+    /// 创建自适应查询间隔
+    ///
+    /// # 参数
+    ///
+    /// * `initial` - 初始间隔，会被限制在最小和最大间隔之间
+    /// * `min` - 最小间隔
+    /// * `max` - 最大间隔，小于最小间隔时按最小间隔处理
+    pub fn new(initial: Duration, min: Duration, max: Duration) -> Self {
+        let max = max.max(min);
+        Self {
+            current: initial.clamp(min, max),
+            min,
+            max,
+            stable_rounds: 0,
+            pending_changes: 0,
+        }
+    }
+
+    /// 当前查询间隔
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// 记录一次设备出现或消失
+    pub fn record_change(&mut self) {
+        self.pending_changes = self.pending_changes.saturating_add(1);
+    }
+
+    /// 结束一轮查询，根据本轮是否有设备变化调整间隔
+    ///
+    /// # 返回值
+    ///
+    /// 返回到下一轮查询的间隔
+    pub fn complete_round(&mut self) -> Duration {
+        if self.pending_changes > 0 {
+            self.pending_changes = 0;
+            self.stable_rounds = 0;
+            self.current = (self.current / 2).max(self.min);
+        } else {
+            self.stable_rounds = self.stable_rounds.saturating_add(1);
+            if self.stable_rounds >= STABLE_ROUNDS_BEFORE_BACKOFF {
+                self.current = self.current.saturating_mul(2).min(self.max);
+            }
+        }
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stability_grows_and_churn_shrinks_interval() {
+        let mut interval = AdaptiveQueryInterval::new(
+            Duration::from_secs(30),
+            Duration::from_secs(5),
+            Duration::from_secs(120),
+        );
+        assert_eq!(interval.current(), Duration::from_secs(30));
+
+        // 稳定轮数不足时保持不变，之后每轮加倍直到上限
+        for _ in 1..STABLE_ROUNDS_BEFORE_BACKOFF {
+            assert_eq!(interval.complete_round(), Duration::from_secs(30));
+        }
+        assert_eq!(interval.complete_round(), Duration::from_secs(60));
+        assert_eq!(interval.complete_round(), Duration::from_secs(120));
+        assert_eq!(interval.complete_round(), Duration::from_secs(120));
+
+        // 设备变化时每轮减半直到下限，并重新累计稳定轮数
+        interval.record_change();
+        interval.record_change();
+        assert_eq!(interval.complete_round(), Duration::from_secs(60));
+        for _ in 0..4 {
+            interval.record_change();
+            interval.complete_round();
+        }
+        assert_eq!(interval.current(), Duration::from_secs(5));
+        assert_eq!(interval.complete_round(), Duration::from_secs(5));
+
+        // 初始值被限制在范围内
+        let interval = AdaptiveQueryInterval::new(Duration::from_secs(1), Duration::from_secs(5), Duration::from_secs(120));
+        assert_eq!(interval.current(), Duration::from_secs(5));
+    }
+}
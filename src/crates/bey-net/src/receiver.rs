@@ -7,14 +7,16 @@
 //!
 //! - **元接收器(MetaReceiver)**: 抽象的消息接收器，定义接收行为
 //! - **接收器过滤器(ReceiverFilter)**: 过滤接收的令牌
-//! - **接收器缓冲区(ReceiverBuffer)**: 缓存接收的令牌
+//! - **接收器缓冲区(ReceiverBuffer)**: 缓存接收的令牌，容量有限，满时按溢出策略处理
 //! - **接收器策略(ReceiverStrategy)**: 定义接收和处理策略
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::sync::Arc;
-use tokio::sync::{RwLock, mpsc};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
 use tracing::{debug, info};
 
 use crate::{NetResult, token::{Token, TokenType, TokenPriority}};
@@ -145,38 +147,195 @@ pub trait MetaReceiver: Send + Sync {
     async fn clear(&self) -> NetResult<()>;
 }
 
+/// 入站队列溢出策略
+///
+/// 入站队列达到容量上限时如何处理新到达的令牌
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverflowPolicy {
+    /// 丢弃队列中最旧的令牌，为新令牌腾出空间
+    DropOldest,
+    /// 丢弃新到达的令牌
+    DropNewest,
+    /// 发送方等待直到队列有空间（反压）
+    #[default]
+    Block,
+}
+
+/// 有界入站队列，发送端和接收端共享
+struct InboundQueue {
+    /// 排队的令牌
+    tokens: Mutex<VecDeque<Token>>,
+    /// 队列容量
+    capacity: usize,
+    /// 溢出策略
+    policy: OverflowPolicy,
+    /// 因溢出被丢弃的令牌数
+    dropped: AtomicU64,
+    /// 存活的发送端数量
+    senders: AtomicUsize,
+    /// 有令牌入队时的通知
+    not_empty: Notify,
+    /// 有令牌出队时的通知
+    not_full: Notify,
+}
+
+impl InboundQueue {
+    /// 锁定令牌队列，锁损坏时继续使用其中的数据
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Token>> {
+        self.tokens.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 取出队首令牌并唤醒等待空间的发送方
+    fn pop(&self) -> Option<Token> {
+        let token = self.lock().pop_front();
+        if token.is_some() {
+            self.not_full.notify_one();
+        }
+        token
+    }
+
+    /// 按溢出策略放入令牌
+    ///
+    /// 队列已满且策略为阻塞时原样返回令牌，否则返回 None
+    fn push(&self, token: Token) -> Option<Token> {
+        let mut tokens = self.lock();
+        if tokens.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    if let Some(oldest) = tokens.pop_front() {
+                        debug!("入站队列已满，丢弃最旧的令牌: {}", oldest.meta.id);
+                    }
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                OverflowPolicy::DropNewest => {
+                    debug!("入站队列已满，丢弃新令牌: {}", token.meta.id);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                OverflowPolicy::Block => return Some(token),
+            }
+        }
+        tokens.push_back(token);
+        drop(tokens);
+
+        self.not_empty.notify_one();
+        None
+    }
+}
+
+/// 入站令牌发送端
+///
+/// 由 [`create_receiver`] 创建，可克隆，全部释放后接收端报告通道关闭
+pub struct InboundSender {
+    queue: Arc<InboundQueue>,
+}
+
+impl InboundSender {
+    /// 发送令牌
+    ///
+    /// 队列已满时按溢出策略处理，阻塞策略下等待接收方取走令牌
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 要发送的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果
+    pub async fn send(&self, mut token: Token) -> NetResult<()> {
+        loop {
+            let not_full = self.queue.not_full.notified();
+            tokio::pin!(not_full);
+            not_full.as_mut().enable();
+
+            match self.queue.push(token) {
+                None => return Ok(()),
+                Some(rejected) => token = rejected,
+            }
+            not_full.await;
+        }
+    }
+
+    /// 尝试发送令牌，不等待队列空间
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 要发送的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 阻塞策略下队列已满时返回错误，令牌计入丢弃数
+    pub fn try_send(&self, token: Token) -> NetResult<()> {
+        match self.queue.push(token) {
+            None => Ok(()),
+            Some(token) => {
+                self.queue.dropped.fetch_add(1, Ordering::Relaxed);
+                Err(ErrorInfo::new(4202, format!("入站队列已满，丢弃令牌: {}", token.meta.id))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Warning))
+            }
+        }
+    }
+}
+
+impl Clone for InboundSender {
+    fn clone(&self) -> Self {
+        self.queue.senders.fetch_add(1, Ordering::SeqCst);
+        Self {
+            queue: Arc::clone(&self.queue),
+        }
+    }
+}
+
+impl Drop for InboundSender {
+    fn drop(&mut self) {
+        if self.queue.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // 唤醒等待中的接收方，使其发现通道已关闭
+            self.queue.not_empty.notify_waiters();
+        }
+    }
+}
+
 /// 缓冲接收器
 ///
-/// 带缓冲区的令牌接收器实现
+/// 带有界缓冲区的令牌接收器实现
 pub struct BufferedReceiver {
-    /// 令牌缓冲区
-    buffer: Arc<RwLock<VecDeque<Token>>>,
-    /// 缓冲区大小限制
-    buffer_size: usize,
-    /// 接收通道
-    rx: Arc<RwLock<mpsc::UnboundedReceiver<Token>>>,
+    /// 有界入站队列
+    queue: Arc<InboundQueue>,
     /// 过滤器链
     filters: Vec<Arc<dyn ReceiverFilter>>,
 }
 
 impl BufferedReceiver {
-    /// 创建新的缓冲接收器
+    /// 创建新的缓冲接收器及其发送端
     ///
     /// # 参数
     ///
-    /// * `buffer_size` - 缓冲区大小
-    /// * `rx` - 接收通道
+    /// * `capacity` - 缓冲区容量
+    /// * `policy` - 缓冲区满时的溢出策略
     ///
     /// # 返回值
     ///
-    /// 返回新的缓冲接收器
-    pub fn new(buffer_size: usize, rx: mpsc::UnboundedReceiver<Token>) -> Self {
-        Self {
-            buffer: Arc::new(RwLock::new(VecDeque::with_capacity(buffer_size))),
-            buffer_size,
-            rx: Arc::new(RwLock::new(rx)),
+    /// 返回发送端和接收器
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> (InboundSender, Self) {
+        let queue = Arc::new(InboundQueue {
+            tokens: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.max(1),
+            policy,
+            dropped: AtomicU64::new(0),
+            senders: AtomicUsize::new(1),
+            not_empty: Notify::new(),
+            not_full: Notify::new(),
+        });
+
+        let sender = InboundSender {
+            queue: Arc::clone(&queue),
+        };
+        let receiver = Self {
+            queue,
             filters: Vec::new(),
-        }
+        };
+        (sender, receiver)
     }
 
     /// 添加过滤器
@@ -188,31 +347,58 @@ impl BufferedReceiver {
         self.filters.push(filter);
     }
 
-    /// 从通道填充缓冲区
-    async fn fill_buffer(&self) -> NetResult<()> {
-        let mut rx = self.rx.write().await;
-        let mut buffer = self.buffer.write().await;
-
-        // 尽可能多地从通道读取令牌到缓冲区
-        while buffer.len() < self.buffer_size {
-            match rx.try_recv() {
-                Ok(token) => {
-                    // 应用过滤器
-                    let should_receive = self.apply_filters(&token).await;
-                    if should_receive {
-                        buffer.push_back(token);
+    /// 缓冲区容量
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity
+    }
+
+    /// 缓冲区溢出策略
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.queue.policy
+    }
+
+    /// 因缓冲区溢出被丢弃的令牌数
+    pub fn dropped_count(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 取出下一个通过过滤器的令牌，不等待
+    async fn next_filtered(&self) -> NetResult<Option<Token>> {
+        while let Some(token) = self.queue.pop() {
+            if self.apply_filters(&token).await {
+                debug!("从缓冲区接收令牌: {}", token.meta.id);
+                return Ok(Some(token));
+            }
+        }
+
+        if self.queue.senders.load(Ordering::SeqCst) == 0 {
+            return Err(ErrorInfo::new(4201, "接收通道已关闭".to_string())
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error));
+        }
+        Ok(None)
+    }
+
+    /// 等待令牌，直到收到令牌或到达截止时间
+    async fn wait_for_token(&self, deadline: Option<tokio::time::Instant>) -> NetResult<Option<Token>> {
+        loop {
+            let not_empty = self.queue.not_empty.notified();
+            tokio::pin!(not_empty);
+            not_empty.as_mut().enable();
+
+            if let Some(token) = self.next_filtered().await? {
+                return Ok(Some(token));
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, not_empty).await.is_err() {
+                        return Ok(None);
                     }
                 }
-                Err(mpsc::error::TryRecvError::Empty) => break,
-                Err(mpsc::error::TryRecvError::Disconnected) => {
-                    return Err(ErrorInfo::new(4201, "接收通道已关闭".to_string())
-                        .with_category(ErrorCategory::Network)
-                        .with_severity(ErrorSeverity::Error));
-                }
+                None => not_empty.await,
             }
         }
-
-        Ok(())
     }
 
     /// 应用所有过滤器
@@ -229,54 +415,11 @@ impl BufferedReceiver {
 #[async_trait]
 impl MetaReceiver for BufferedReceiver {
     async fn receive(&self, mode: ReceiverMode) -> NetResult<Option<Token>> {
-        // 先尝试从缓冲区获取
-        {
-            let mut buffer = self.buffer.write().await;
-            if let Some(token) = buffer.pop_front() {
-                debug!("从缓冲区接收令牌: {}", token.meta.id);
-                return Ok(Some(token));
-            }
-        }
-
-        // 缓冲区为空，尝试从通道获取
         match mode {
-            ReceiverMode::NonBlocking => {
-                self.fill_buffer().await?;
-                let mut buffer = self.buffer.write().await;
-                Ok(buffer.pop_front())
-            }
-            ReceiverMode::Blocking => {
-                // 阻塞等待
-                loop {
-                    self.fill_buffer().await?;
-                    {
-                        let mut buffer = self.buffer.write().await;
-                        if let Some(token) = buffer.pop_front() {
-                            return Ok(Some(token));
-                        }
-                    }
-                    // 短暂休眠后重试
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-                }
-            }
+            ReceiverMode::NonBlocking => self.next_filtered().await,
+            ReceiverMode::Blocking => self.wait_for_token(None).await,
             ReceiverMode::Timeout(duration) => {
-                // 带超时的等待
-                let deadline = tokio::time::Instant::now() + duration;
-                loop {
-                    self.fill_buffer().await?;
-                    {
-                        let mut buffer = self.buffer.write().await;
-                        if let Some(token) = buffer.pop_front() {
-                            return Ok(Some(token));
-                        }
-                    }
-
-                    if tokio::time::Instant::now() >= deadline {
-                        return Ok(None);
-                    }
-
-                    tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-                }
+                self.wait_for_token(Some(tokio::time::Instant::now() + duration)).await
             }
         }
     }
@@ -284,28 +427,10 @@ impl MetaReceiver for BufferedReceiver {
     async fn receive_batch(&self, max_count: usize, _mode: ReceiverMode) -> NetResult<Vec<Token>> {
         let mut tokens = Vec::with_capacity(max_count);
 
-        // 先从缓冲区获取
-        {
-            let mut buffer = self.buffer.write().await;
-            while tokens.len() < max_count {
-                if let Some(token) = buffer.pop_front() {
-                    tokens.push(token);
-                } else {
-                    break;
-                }
-            }
-        }
-
-        // 如果还需要更多令牌
-        if tokens.len() < max_count {
-            self.fill_buffer().await?;
-            let mut buffer = self.buffer.write().await;
-            while tokens.len() < max_count {
-                if let Some(token) = buffer.pop_front() {
-                    tokens.push(token);
-                } else {
-                    break;
-                }
+        while tokens.len() < max_count {
+            match self.next_filtered().await? {
+                Some(token) => tokens.push(token),
+                None => break,
             }
         }
 
@@ -314,21 +439,25 @@ impl MetaReceiver for BufferedReceiver {
     }
 
     async fn peek(&self) -> NetResult<Option<Token>> {
-        // 确保缓冲区有数据
-        self.fill_buffer().await?;
-
-        let buffer = self.buffer.read().await;
-        Ok(buffer.front().cloned())
+        // 丢弃队首不满足过滤器的令牌
+        loop {
+            let front = self.queue.lock().front().cloned();
+            match front {
+                Some(token) if !self.apply_filters(&token).await => {
+                    self.queue.pop();
+                }
+                front => return Ok(front),
+            }
+        }
     }
 
     async fn pending_count(&self) -> usize {
-        let buffer = self.buffer.read().await;
-        buffer.len()
+        self.queue.lock().len()
     }
 
     async fn clear(&self) -> NetResult<()> {
-        let mut buffer = self.buffer.write().await;
-        buffer.clear();
+        self.queue.lock().clear();
+        self.queue.not_full.notify_waiters();
         info!("接收器缓冲区已清空");
         Ok(())
     }
@@ -336,6 +465,8 @@ impl MetaReceiver for BufferedReceiver {
 
 /// 创建接收器对
 ///
+/// 缓冲区满时使用默认的阻塞策略
+///
 /// # 参数
 ///
 /// * `buffer_size` - 缓冲区大小
@@ -343,10 +474,8 @@ impl MetaReceiver for BufferedReceiver {
 /// # 返回值
 ///
 /// 返回发送端和接收端
-pub fn create_receiver(buffer_size: usize) -> (mpsc::UnboundedSender<Token>, BufferedReceiver) {
-    let (tx, rx) = mpsc::unbounded_channel();
-    let receiver = BufferedReceiver::new(buffer_size, rx);
-    (tx, receiver)
+pub fn create_receiver(buffer_size: usize) -> (InboundSender, BufferedReceiver) {
+    BufferedReceiver::new(buffer_size, OverflowPolicy::default())
 }
 
 #[cfg(test)]
@@ -389,7 +518,7 @@ mod tests {
         // 发送令牌
         let meta = TokenMeta::new("test".to_string(), "sender".to_string());
         let token = Token::new(meta, vec![1, 2, 3]);
-        tx.send(token.clone()).unwrap();
+        tx.send(token.clone()).await.unwrap();
 
         // 接收令牌
         let received = receiver.receive(ReceiverMode::NonBlocking).await.unwrap();
@@ -405,7 +534,7 @@ mod tests {
         for i in 0..5 {
             let meta = TokenMeta::new("test".to_string(), format!("sender_{}", i));
             let token = Token::new(meta, vec![i as u8]);
-            tx.send(token).unwrap();
+            tx.send(token).await.unwrap();
         }
 
         // 批量接收
@@ -420,7 +549,7 @@ mod tests {
         // 发送令牌
         let meta = TokenMeta::new("test".to_string(), "sender".to_string());
         let token = Token::new(meta, vec![1, 2, 3]);
-        tx.send(token.clone()).unwrap();
+        tx.send(token.clone()).await.unwrap();
 
         // Peek不应该移除令牌
         let peeked = receiver.peek().await.unwrap();
@@ -430,4 +559,53 @@ mod tests {
         let received = receiver.receive(ReceiverMode::NonBlocking).await.unwrap();
         assert!(received.is_some());
     }
+
+    #[tokio::test]
+    async fn test_drop_oldest_discards_oldest_tokens() {
+        let (tx, receiver) = BufferedReceiver::new(3, OverflowPolicy::DropOldest);
+
+        for i in 0..5u8 {
+            let meta = TokenMeta::new("test".to_string(), "sender".to_string());
+            tx.send(Token::new(meta, vec![i])).await.expect("发送令牌失败");
+        }
+
+        assert_eq!(receiver.pending_count().await, 3);
+        assert_eq!(receiver.dropped_count(), 2);
+
+        let payloads: Vec<u8> = receiver.receive_batch(5, ReceiverMode::NonBlocking).await
+            .expect("接收失败")
+            .into_iter()
+            .map(|token| token.payload[0])
+            .collect();
+        assert_eq!(payloads, vec![2, 3, 4], "最旧的两个令牌应被丢弃");
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest_and_block_policies() {
+        let (tx, receiver) = BufferedReceiver::new(1, OverflowPolicy::DropNewest);
+        for i in 0..3u8 {
+            let meta = TokenMeta::new("test".to_string(), "sender".to_string());
+            tx.send(Token::new(meta, vec![i])).await.expect("发送令牌失败");
+        }
+        let token = receiver.receive(ReceiverMode::NonBlocking).await.expect("接收失败").expect("应有令牌");
+        assert_eq!(token.payload, vec![0]);
+        assert_eq!(receiver.dropped_count(), 2);
+
+        let (tx, receiver) = BufferedReceiver::new(1, OverflowPolicy::Block);
+        let meta = TokenMeta::new("test".to_string(), "sender".to_string());
+        tx.send(Token::new(meta.clone(), vec![0])).await.expect("发送令牌失败");
+        assert!(tx.try_send(Token::new(meta.clone(), vec![1])).is_err(), "队列已满时不等待的发送应失败");
+
+        // 阻塞的发送在接收方取走令牌后完成
+        let blocked = tokio::spawn(async move {
+            tx.send(Token::new(meta, vec![2])).await
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert!(!blocked.is_finished());
+
+        receiver.receive(ReceiverMode::NonBlocking).await.expect("接收失败");
+        blocked.await.expect("任务失败").expect("发送令牌失败");
+        let token = receiver.receive(ReceiverMode::Blocking).await.expect("接收失败").expect("应有令牌");
+        assert_eq!(token.payload, vec![2]);
+    }
 }
@@ -0,0 +1,162 @@
+//! # 重放检测
+//!
+//! 记录时间窗口内收到的 `(发送方ID, 令牌ID)`，窗口内再次收到相同的令牌时判定为重放。
+//! 缓存容量有上限，超过后淘汰最早的记录，避免大量令牌耗尽内存。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 重放缓存默认的时间窗口
+pub const DEFAULT_REPLAY_WINDOW: Duration = Duration::from_secs(300);
+
+/// 重放缓存默认最多记录的令牌数
+pub const DEFAULT_REPLAY_CACHE_CAPACITY: usize = 10_000;
+
+/// 令牌键：发送方ID和令牌ID
+type ReplayKey = (String, String);
+
+/// 缓存内容
+#[derive(Debug, Default)]
+struct ReplayEntries {
+    /// 令牌键 -> 首次收到的时间
+    seen: HashMap<ReplayKey, Instant>,
+    /// 按收到顺序排列的令牌键，用于过期和淘汰
+    order: VecDeque<(ReplayKey, Instant)>,
+}
+
+/// 有界的重放缓存
+#[derive(Debug)]
+pub struct ReplayCache {
+    /// 时间窗口
+    window: Duration,
+    /// 最多记录的令牌数
+    capacity: usize,
+    /// 已收到的令牌
+    entries: Mutex<ReplayEntries>,
+}
+
+impl ReplayCache {
+    /// 创建重放缓存
+    ///
+    /// # 参数
+    ///
+    /// * `window` - 时间窗口，为零时不检测重放
+    /// * `capacity` - 最多记录的令牌数（至少为1）
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity: capacity.max(1),
+            entries: Mutex::new(ReplayEntries::default()),
+        }
+    }
+
+    /// 时间窗口
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// 当前记录的令牌数
+    pub fn len(&self) -> usize {
+        self.lock().seen.len()
+    }
+
+    /// 是否没有记录任何令牌
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 检查令牌是否为重放，并记录首次收到的令牌
+    ///
+    /// # 参数
+    ///
+    /// * `sender_id` - 发送方ID
+    /// * `token_id` - 令牌ID
+    ///
+    /// # 返回值
+    ///
+    /// 时间窗口内已收到过相同的令牌时返回 `true`
+    pub fn check_and_record(&self, sender_id: &str, token_id: &str) -> bool {
+        self.check_and_record_at(sender_id, token_id, Instant::now())
+    }
+
+    /// 按指定时间检查并记录令牌
+    fn check_and_record_at(&self, sender_id: &str, token_id: &str, now: Instant) -> bool {
+        if self.window.is_zero() {
+            return false;
+        }
+
+        let mut entries = self.lock();
+
+        // 清理窗口外的记录
+        while let Some((_, seen_at)) = entries.order.front() {
+            if now.saturating_duration_since(*seen_at) < self.window {
+                break;
+            }
+            if let Some((key, seen_at)) = entries.order.pop_front() {
+                if entries.seen.get(&key) == Some(&seen_at) {
+                    entries.seen.remove(&key);
+                }
+            }
+        }
+
+        let key = (sender_id.to_string(), token_id.to_string());
+        if entries.seen.contains_key(&key) {
+            return true;
+        }
+
+        // 达到容量上限时淘汰最早的记录
+        while entries.seen.len() >= self.capacity {
+            match entries.order.pop_front() {
+                Some((oldest, _)) => {
+                    entries.seen.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+
+        entries.seen.insert(key.clone(), now);
+        entries.order.push_back((key, now));
+        false
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ReplayEntries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for ReplayCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_WINDOW, DEFAULT_REPLAY_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_window_and_capacity() {
+        let cache = ReplayCache::new(Duration::from_secs(10), 2);
+        let start = Instant::now();
+
+        assert!(!cache.check_and_record_at("alice", "t1", start));
+        assert!(cache.check_and_record_at("alice", "t1", start + Duration::from_secs(1)));
+        // 不同发送方的相同令牌ID不是重放
+        assert!(!cache.check_and_record_at("bob", "t1", start));
+
+        // 超出容量时淘汰最早的记录
+        assert!(!cache.check_and_record_at("alice", "t2", start + Duration::from_secs(2)));
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.check_and_record_at("alice", "t1", start + Duration::from_secs(3)));
+
+        // 窗口外的记录过期
+        assert!(!cache.check_and_record_at("alice", "t2", start + Duration::from_secs(20)));
+
+        // 窗口为零时不检测
+        let disabled = ReplayCache::new(Duration::ZERO, 2);
+        assert!(!disabled.check_and_record("alice", "t1"));
+        assert!(!disabled.check_and_record("alice", "t1"));
+        assert!(disabled.is_empty());
+    }
+}
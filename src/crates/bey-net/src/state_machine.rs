@@ -82,6 +82,8 @@ pub enum StateEvent {
     ConnectionLost,
     /// 超时
     Timeout,
+    /// 握手被拒绝（携带拒绝原因）
+    Rejected(String),
     /// 错误
     Error(String),
 }
@@ -99,6 +101,7 @@ impl fmt::Display for StateEvent {
             StateEvent::Disconnect => write!(f, "断开连接"),
             StateEvent::ConnectionLost => write!(f, "连接丢失"),
             StateEvent::Timeout => write!(f, "超时"),
+            StateEvent::Rejected(reason) => write!(f, "握手被拒绝: {}", reason),
             StateEvent::Error(msg) => write!(f, "错误: {}", msg),
         }
     }
@@ -233,6 +236,7 @@ impl ConnectionStateMachine {
 
             // 认证失败
             (ConnectionState::Authenticating, StateEvent::AuthFailed) => ConnectionState::Error,
+            (ConnectionState::Authenticating, StateEvent::Rejected(_)) => ConnectionState::Error,
 
             // 开始传输数据
             (ConnectionState::Authenticated, StateEvent::StartTransfer) => ConnectionState::Transferring,
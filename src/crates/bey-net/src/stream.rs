@@ -246,9 +246,23 @@ impl StreamManager {
         stream_id: String,
         data: Vec<u8>,
         stream_type: String,
+    ) -> NetResult<Vec<StreamChunk>> {
+        self.create_send_stream_with_chunk_size(stream_id, data, stream_type, self.default_chunk_size).await
+    }
+
+    /// 使用指定块大小创建发送流
+    ///
+    /// 块大小随开始块发送给接收方，同一个流内所有数据块大小一致；
+    /// 块大小为 0 时使用默认块大小
+    pub async fn create_send_stream_with_chunk_size(
+        &self,
+        stream_id: String,
+        data: Vec<u8>,
+        stream_type: String,
+        chunk_size: usize,
     ) -> NetResult<Vec<StreamChunk>> {
         let total_size = data.len() as u64;
-        let chunk_size = self.default_chunk_size;
+        let chunk_size = if chunk_size == 0 { self.default_chunk_size } else { chunk_size };
         let total_chunks = (total_size as usize + chunk_size - 1) / chunk_size;
 
         let meta = StreamMeta {
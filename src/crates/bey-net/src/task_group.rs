@@ -0,0 +1,169 @@
+//! # 后台任务组
+//!
+//! 统一管理服务启动的后台任务：记录每个任务的 `JoinHandle`，停止时通过
+//! `CancellationToken` 通知所有任务退出，并在超时时间内等待任务结束，
+//! 超时未结束的任务被强制中止，保证没有任务在服务停止后继续运行。
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// 停止时等待后台任务退出的默认超时时间
+pub const DEFAULT_TASK_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 运行中任务计数守卫，任务结束或被中止时减少计数
+struct ActiveTaskGuard(Arc<AtomicUsize>);
+
+impl Drop for ActiveTaskGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 后台任务组
+///
+/// 任务组被丢弃时同样会通知所有任务退出
+pub struct TaskGroup {
+    /// 任务组名称（用于日志）
+    name: &'static str,
+    /// 当前的取消令牌，停止后替换为新令牌以便重新启动
+    cancel: Mutex<CancellationToken>,
+    /// 已启动任务的句柄
+    handles: Mutex<Vec<JoinHandle<()>>>,
+    /// 运行中的任务数量
+    active: Arc<AtomicUsize>,
+}
+
+impl TaskGroup {
+    /// 创建任务组
+    ///
+    /// # 参数
+    ///
+    /// * `name` - 任务组名称
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            cancel: Mutex::new(CancellationToken::new()),
+            handles: Mutex::new(Vec::new()),
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 启动后台任务
+    ///
+    /// 任务在自身结束或任务组停止时退出
+    ///
+    /// # 参数
+    ///
+    /// * `task` - 要运行的任务
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let cancel = self.cancellation_token();
+        self.active.fetch_add(1, Ordering::SeqCst);
+        let guard = ActiveTaskGuard(Arc::clone(&self.active));
+
+        let handle = tokio::spawn(async move {
+            let _guard = guard;
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = task => {}
+            }
+        });
+
+        if let Ok(mut handles) = self.handles.lock() {
+            handles.retain(|handle| !handle.is_finished());
+            handles.push(handle);
+        }
+    }
+
+    /// 当前的取消令牌
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.lock()
+            .map(|cancel| cancel.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    /// 运行中的任务数量
+    pub fn active_count(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// 停止所有任务
+    ///
+    /// 通知所有任务退出并等待其结束，超时未结束的任务被强制中止。
+    /// 停止后可以继续启动新任务。
+    ///
+    /// # 参数
+    ///
+    /// * `timeout` - 等待任务退出的超时时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回被强制中止的任务数量
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        let cancel = match self.cancel.lock() {
+            Ok(mut cancel) => std::mem::take(&mut *cancel),
+            Err(poisoned) => std::mem::take(&mut *poisoned.into_inner()),
+        };
+        cancel.cancel();
+
+        let handles = match self.handles.lock() {
+            Ok(mut handles) => std::mem::take(&mut *handles),
+            Err(poisoned) => std::mem::take(&mut *poisoned.into_inner()),
+        };
+
+        let mut aborted = 0;
+        let deadline = tokio::time::Instant::now() + timeout;
+        for mut handle in handles {
+            if tokio::time::timeout_at(deadline, &mut handle).await.is_err() {
+                handle.abort();
+                let _ = handle.await;
+                aborted += 1;
+            }
+        }
+
+        if aborted > 0 {
+            warn!("{}: {} 个后台任务未在 {:?} 内退出，已强制中止", self.name, aborted, timeout);
+        } else {
+            debug!("{}: 所有后台任务已退出", self.name);
+        }
+        aborted
+    }
+}
+
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        self.cancellation_token().cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_stops_tasks_and_allows_restart() {
+        let tasks = TaskGroup::new("test");
+        tasks.spawn(async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        });
+        tasks.spawn(std::future::pending());
+        assert_eq!(tasks.active_count(), 2);
+
+        assert_eq!(tasks.shutdown(Duration::from_secs(1)).await, 0);
+        assert_eq!(tasks.active_count(), 0);
+
+        // 停止后可以重新启动任务
+        tasks.spawn(std::future::pending());
+        assert_eq!(tasks.active_count(), 1);
+        assert!(!tasks.cancellation_token().is_cancelled());
+    }
+}
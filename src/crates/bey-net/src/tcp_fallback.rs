@@ -0,0 +1,317 @@
+//! # TCP 回退传输
+//!
+//! 在阻断 UDP 的网络中 QUIC 无法建立连接。启用回退后，服务端额外在 TCP 上监听，
+//! 并通过 mDNS TXT 记录 `tcp_port=<端口>` 通告回退端口；客户端的 QUIC 连接因 UDP
+//! 不可达失败时，改用 TCP 流建立连接并完成同样的握手协商，协商结果的
+//! [`ConnectionInfo::protocol`](bey_types::ConnectionInfo) 为 TCP。
+//!
+//! 回退连接先在 TCP 流上完成 mTLS 握手（[`SecureTransport::connect_tcp`] /
+//! [`SecureTransport::accept_tcp`]），使用与 QUIC 相同的设备证书、CA 和 TOFU 固定记录校验对端，
+//! 并同样经过策略引擎评估；之后握手协商和令牌都以长度前缀帧在 TLS 流上收发。
+//! 回退连接能提供的安全级别为 [`TCP_FALLBACK_SECURITY_LEVEL`]，要求更高级别的协商被拒绝。
+//!
+//! [`SecureTransport::connect_tcp`]: bey_transport::SecureTransport::connect_tcp
+//! [`SecureTransport::accept_tcp`]: bey_transport::SecureTransport::accept_tcp
+
+use bey_transport::{error_codes, TlsStream};
+use bey_types::SecurityLevel;
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+use crate::{NetResult, receiver::InboundSender, token::Token, wire};
+
+/// 通告 TCP 回退端口的 mDNS TXT 记录键
+pub const TCP_PORT_TXT_KEY: &str = "tcp_port";
+
+/// 建立 TCP 回退连接的超时时间
+pub const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 回退连接能提供的最高安全级别
+///
+/// 回退连接与QUIC连接一样经mTLS双向认证并以TLS 1.3加密
+pub const TCP_FALLBACK_SECURITY_LEVEL: SecurityLevel = SecurityLevel::Standard;
+
+/// 帧的最大长度
+const MAX_FRAME_SIZE: usize = wire::MAX_WIRE_TOKEN_SIZE;
+
+/// 回退连接的帧发送端
+pub struct FrameWriter {
+    /// TLS流的写入端
+    writer: WriteHalf<TlsStream<TcpStream>>,
+}
+
+impl FrameWriter {
+    /// 发送一帧
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 帧内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果或错误
+    pub async fn send(&mut self, data: &[u8]) -> NetResult<()> {
+        self.writer.write_u32(data.len() as u32).await.map_err(|e| frame_error(4924, e))?;
+        self.writer.write_all(data).await.map_err(|e| frame_error(4924, e))?;
+        self.writer.flush().await.map_err(|e| frame_error(4924, e))
+    }
+
+    /// 关闭发送方向
+    ///
+    /// 发送TLS关闭通知并关闭TCP写方向，对端的接收端随之结束
+    pub async fn close(&mut self) {
+        if let Err(e) = self.writer.shutdown().await {
+            debug!("关闭TCP回退连接的发送方向失败: {}", e);
+        }
+    }
+}
+
+/// 回退连接的帧接收端
+pub struct FrameReader {
+    /// TLS流的读取端
+    reader: ReadHalf<TlsStream<TcpStream>>,
+}
+
+impl FrameReader {
+    /// 接收一帧
+    ///
+    /// # 返回值
+    ///
+    /// 返回帧内容，对端关闭连接时返回 `None`，帧无效时返回错误
+    pub async fn recv(&mut self) -> NetResult<Option<Vec<u8>>> {
+        let len = match self.reader.read_u32().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(frame_error(4924, e)),
+        };
+        if len > MAX_FRAME_SIZE {
+            return Err(frame_error(4925, format!("帧过长: {} 字节", len)));
+        }
+
+        let mut data = vec![0u8; len];
+        self.reader.read_exact(&mut data).await.map_err(|e| frame_error(4924, e))?;
+        Ok(Some(data))
+    }
+}
+
+/// 把已完成握手协商的TLS流拆分为帧发送端和接收端
+///
+/// # 参数
+///
+/// * `stream` - 已完成mTLS握手和握手协商的TLS流
+///
+/// # 返回值
+///
+/// 返回帧发送端和接收端
+pub fn split(stream: TlsStream<TcpStream>) -> (FrameWriter, FrameReader) {
+    let (reader, writer) = tokio::io::split(stream);
+    (FrameWriter { writer }, FrameReader { reader })
+}
+
+/// 接收回退连接上对端发送的令牌，直到连接关闭或收到无效的帧
+///
+/// # 参数
+///
+/// * `reader` - 帧接收端
+/// * `peer_name` - 握手时对端给出的名称，对端证书已在mTLS握手中校验
+/// * `remote_addr` - 对端地址
+/// * `inbound` - 入站令牌队列
+pub async fn serve(mut reader: FrameReader, peer_name: String, remote_addr: SocketAddr, inbound: InboundSender) {
+    loop {
+        let data = match reader.recv().await {
+            Ok(Some(data)) => data,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("TCP回退连接 {} 读取失败，断开连接: {}", remote_addr, e);
+                break;
+            }
+        };
+        match Token::deserialize(&data) {
+            Ok(token) => {
+                if let Err(e) = inbound.send(wire::stamp(token, &peer_name, remote_addr)).await {
+                    warn!("{} 发送的令牌无法放入入站队列: {}", remote_addr, e);
+                }
+            }
+            Err(e) => warn!("丢弃 {} 发送的无效令牌: {}", remote_addr, e),
+        }
+    }
+    debug!("停止接收TCP回退连接 {} 的令牌", remote_addr);
+}
+
+/// 帧读写失败
+fn frame_error(code: u32, error: impl std::fmt::Display) -> ErrorInfo {
+    let category = if code == 4925 { ErrorCategory::Parse } else { ErrorCategory::Network };
+    ErrorInfo::new(code, format!("TCP回退连接帧读写失败: {}", error))
+        .with_category(category)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 判断 QUIC 连接错误是否由 UDP 不可达引起
+///
+/// # 参数
+///
+/// * `error` - 传输层返回的连接错误
+///
+/// # 返回值
+///
+/// UDP 端点无法创建或 QUIC 握手超时时返回 `true`，此时可以尝试 TCP 回退
+pub fn is_udp_unreachable(error: &ErrorInfo) -> bool {
+    error.code() == error_codes::transport::UDP_UNREACHABLE
+}
+
+/// 生成通告 TCP 回退端口的 TXT 记录
+pub fn tcp_port_txt_record(port: u16) -> String {
+    format!("{}={}", TCP_PORT_TXT_KEY, port)
+}
+
+/// 从 mDNS TXT 记录中解析对端通告的 TCP 回退端口
+///
+/// # 参数
+///
+/// * `txt_records` - 服务的 TXT 记录
+///
+/// # 返回值
+///
+/// 返回通告的端口，对端未启用回退时返回 `None`
+pub fn advertised_tcp_port(txt_records: &[String]) -> Option<u16> {
+    txt_records.iter()
+        .filter_map(|record| record.split_once('='))
+        .find(|(key, _)| *key == TCP_PORT_TXT_KEY)
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// 绑定 TCP 回退监听器
+///
+/// # 参数
+///
+/// * `addr` - 监听地址
+///
+/// # 返回值
+///
+/// 返回监听器或错误
+pub async fn bind(addr: SocketAddr) -> NetResult<TcpListener> {
+    TcpListener::bind(addr).await.map_err(|e| {
+        ErrorInfo::new(4921, format!("绑定TCP回退监听地址 {} 失败: {}", addr, e))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error)
+    })
+}
+
+/// 建立 TCP 回退连接
+///
+/// # 参数
+///
+/// * `addr` - 对端的 TCP 回退地址
+///
+/// # 返回值
+///
+/// 返回已建立的 TCP 流或错误
+pub async fn connect(addr: SocketAddr) -> NetResult<TcpStream> {
+    let stream = tokio::time::timeout(TCP_CONNECT_TIMEOUT, TcpStream::connect(addr)).await
+        .map_err(|_| ErrorInfo::new(4922, format!("TCP回退连接 {} 超时", addr))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error))?
+        .map_err(|e| ErrorInfo::new(4922, format!("TCP回退连接 {} 失败: {}", addr, e))
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Error))?;
+    // 握手和令牌都是小消息，关闭 Nagle 算法降低延迟
+    let _ = stream.set_nodelay(true);
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bey_transport::policy_engine::{PolicyAction, PolicySet};
+    use bey_transport::{SecureTransport, TransportConfig};
+    use std::path::Path;
+
+    /// 创建允许所有连接的传输层，证书签发在给定目录中
+    async fn create_transport(certificates_dir: &Path, device_id: &str, server_name: &str) -> SecureTransport {
+        let config = TransportConfig::new()
+            .with_certificates_dir(certificates_dir)
+            .with_server_name(server_name.to_string())
+            .with_connection_timeout(Duration::from_secs(5));
+        let mut transport = SecureTransport::new(config, device_id.to_string()).await.expect("创建传输层失败");
+        transport.provision_certificates().await.expect("签发证书失败");
+        let allow_all = PolicySet::new(
+            "allow-all".to_string(),
+            "允许所有".to_string(),
+            "测试用策略集合".to_string(),
+            PolicyAction::Allow,
+        );
+        transport.set_policy_set(allow_all).await.expect("设置策略失败");
+        transport
+    }
+
+    #[tokio::test]
+    async fn test_frames_over_mtls() {
+        let temp_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_transport(temp_dir.path(), "fallback-frame-server", "fallback-frame-server.bey.local").await;
+        let client = create_transport(temp_dir.path(), "fallback-frame-client", "fallback-frame-server.bey.local").await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("绑定失败");
+        let addr = listener.local_addr().expect("获取地址失败");
+        let server_task = tokio::spawn(async move {
+            let (stream, remote_addr) = listener.accept().await.expect("接受连接失败");
+            server.accept_tcp(stream, remote_addr).await.expect("服务端TLS握手失败")
+        });
+        let client_stream = client.connect_tcp(connect(addr).await.expect("连接失败"), addr).await
+            .expect("客户端TLS握手失败");
+        let (server_stream, trust_level) = server_task.await.expect("服务端任务失败");
+        assert_eq!(trust_level, bey_transport::TrustLevel::Trusted, "客户端证书应已通过校验");
+
+        let (mut client_writer, mut client_reader) = split(client_stream);
+        let (mut server_writer, mut server_reader) = split(server_stream);
+        client_writer.send(b"hello").await.expect("发送失败");
+        client_writer.send(b"again").await.expect("发送失败");
+        assert_eq!(server_reader.recv().await.expect("接收失败"), Some(b"hello".to_vec()));
+        assert_eq!(server_reader.recv().await.expect("接收失败"), Some(b"again".to_vec()));
+        server_writer.send(b"reply").await.expect("回复失败");
+        assert_eq!(client_reader.recv().await.expect("接收回复失败"), Some(b"reply".to_vec()));
+
+        // 对端关闭发送方向后接收端结束
+        client_writer.close().await;
+        assert_eq!(server_reader.recv().await.expect("接收失败"), None);
+    }
+
+    #[tokio::test]
+    async fn test_peer_from_other_ca_is_rejected() {
+        let server_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let client_dir = tempfile::tempdir().expect("创建临时目录失败");
+        let server = create_transport(server_dir.path(), "fallback-ca-server", "fallback-ca-server.bey.local").await;
+        let client = create_transport(client_dir.path(), "fallback-ca-client", "fallback-ca-server.bey.local").await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("绑定失败");
+        let addr = listener.local_addr().expect("获取地址失败");
+        let server_task = tokio::spawn(async move {
+            let (stream, remote_addr) = listener.accept().await.expect("接受连接失败");
+            server.accept_tcp(stream, remote_addr).await.map(|_| ())
+        });
+
+        // 双方的证书由不同的CA签发，任何一方都不接受对端
+        let result = client.connect_tcp(connect(addr).await.expect("连接失败"), addr).await;
+        assert!(result.is_err(), "不应信任其他CA签发的服务端证书");
+        assert!(server_task.await.expect("服务端任务失败").is_err(), "不应接受其他CA签发的客户端证书");
+    }
+
+    #[test]
+    fn test_advertised_tcp_port() {
+        let records = vec![
+            "version=0.1.0".to_string(),
+            tcp_port_txt_record(18080),
+        ];
+        assert_eq!(advertised_tcp_port(&records), Some(18080));
+        assert_eq!(advertised_tcp_port(&["protocol=bey".to_string()]), None);
+        assert_eq!(advertised_tcp_port(&["tcp_port=invalid".to_string()]), None);
+
+        let udp_error = ErrorInfo::new(error_codes::transport::UDP_UNREACHABLE, "超时".to_string());
+        assert!(is_udp_unreachable(&udp_error));
+        assert!(!is_udp_unreachable(&ErrorInfo::new(2012, "连接失败".to_string())));
+    }
+}
@@ -12,10 +12,11 @@
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use async_trait::async_trait;
-use tokio::sync::RwLock;
+use bey_types::MessagePriority;
+use tokio::sync::{Mutex, RwLock};
 
 use crate::NetResult;
 
@@ -44,6 +45,37 @@ impl Default for TokenPriority {
     }
 }
 
+impl TokenPriority {
+    /// 对应的QUIC流优先级
+    ///
+    /// 同一连接上优先级高的流先发送，`Normal` 对应Quinn的默认优先级0
+    pub fn stream_priority(self) -> i32 {
+        self as i32 - TokenPriority::Normal as i32
+    }
+}
+
+impl From<MessagePriority> for TokenPriority {
+    fn from(priority: MessagePriority) -> Self {
+        match priority {
+            MessagePriority::Low => TokenPriority::Low,
+            MessagePriority::Normal => TokenPriority::Normal,
+            MessagePriority::High => TokenPriority::High,
+            MessagePriority::Critical => TokenPriority::Critical,
+        }
+    }
+}
+
+impl From<TokenPriority> for MessagePriority {
+    fn from(priority: TokenPriority) -> Self {
+        match priority {
+            TokenPriority::Low => MessagePriority::Low,
+            TokenPriority::Normal => MessagePriority::Normal,
+            TokenPriority::High => MessagePriority::High,
+            TokenPriority::Critical => MessagePriority::Critical,
+        }
+    }
+}
+
 /// 令牌元数据
 ///
 /// 定义令牌的基本属性和元信息
@@ -242,22 +274,66 @@ pub trait TokenHandler: Send + Sync {
     }
 }
 
+/// 死信队列的默认容量
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 256;
+
+/// 死信统计最多单独计数的令牌类型数，其余类型计入 [`DEAD_LETTER_OTHER_TYPE`]
+pub const MAX_DEAD_LETTER_TYPES: usize = 64;
+
+/// 死信统计中未单独计数的令牌类型（超出类型数上限或类型名过长）
+pub const DEAD_LETTER_OTHER_TYPE: &str = "other";
+
+/// 单独计数的令牌类型名的最大长度（字节）
+const MAX_DEAD_LETTER_TYPE_LEN: usize = 128;
+
+/// 死信
+///
+/// 无法路由到任何处理器的令牌
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    /// 未能路由的令牌
+    pub token: Token,
+    /// 进入死信队列的原因
+    pub reason: String,
+    /// 进入死信队列的时间戳
+    pub timestamp: u64,
+}
+
 /// 令牌路由器
 ///
-/// 负责将令牌路由到对应的处理器
+/// 负责将令牌路由到对应的处理器。找不到处理器且未设置默认处理器的令牌
+/// 进入有界的死信队列，队列满时丢弃最早的死信。
 pub struct TokenRouter {
     /// 处理器映射表
     handlers: Arc<RwLock<HashMap<TokenType, Arc<dyn TokenHandler>>>>,
     /// 默认处理器
-    default_handler: Option<Arc<dyn TokenHandler>>,
+    default_handler: RwLock<Option<Arc<dyn TokenHandler>>>,
+    /// 死信队列
+    dead_letters: Mutex<VecDeque<DeadLetter>>,
+    /// 死信队列容量
+    dead_letter_capacity: usize,
+    /// 按令牌类型统计的死信数，最多 [`MAX_DEAD_LETTER_TYPES`] 个类型加上 [`DEAD_LETTER_OTHER_TYPE`]
+    dead_letter_counts: Mutex<HashMap<TokenType, u64>>,
 }
 
 impl TokenRouter {
     /// 创建新的令牌路由器
     pub fn new() -> Self {
+        Self::with_dead_letter_capacity(DEFAULT_DEAD_LETTER_CAPACITY)
+    }
+
+    /// 创建指定死信队列容量的令牌路由器
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 死信队列最多保留的死信数
+    pub fn with_dead_letter_capacity(capacity: usize) -> Self {
         Self {
             handlers: Arc::new(RwLock::new(HashMap::new())),
-            default_handler: None,
+            default_handler: RwLock::new(None),
+            dead_letters: Mutex::new(VecDeque::with_capacity(capacity.min(DEFAULT_DEAD_LETTER_CAPACITY))),
+            dead_letter_capacity: capacity,
+            dead_letter_counts: Mutex::new(HashMap::new()),
         }
     }
 
@@ -287,7 +363,19 @@ impl TokenRouter {
     ///
     /// * `handler` - 默认处理器
     pub fn set_default_handler(&mut self, handler: Arc<dyn TokenHandler>) {
-        self.default_handler = Some(handler);
+        *self.default_handler.get_mut() = Some(handler);
+    }
+
+    /// 注册兜底处理器
+    ///
+    /// 与 [`set_default_handler`](Self::set_default_handler) 相同，但可以在路由器共享后调用。
+    /// 设置后找不到处理器的令牌交由兜底处理器处理，不再进入死信队列。
+    ///
+    /// # 参数
+    ///
+    /// * `handler` - 兜底处理器
+    pub async fn set_fallback_handler(&self, handler: Arc<dyn TokenHandler>) {
+        *self.default_handler.write().await = Some(handler);
     }
 
     /// 路由令牌到对应的处理器
@@ -320,14 +408,75 @@ impl TokenRouter {
         }
 
         // 如果没有找到对应的处理器，使用默认处理器
-        if let Some(default_handler) = &self.default_handler {
+        let default_handler = self.default_handler.read().await.clone();
+        if let Some(default_handler) = default_handler {
             return default_handler.handle_token(token).await;
         }
 
-        // 没有可用的处理器
-        Err(ErrorInfo::new(4004, format!("未找到令牌类型 {} 的处理器", token.meta.token_type))
+        // 没有可用的处理器，放入死信队列
+        let error = ErrorInfo::new(4004, format!("未找到令牌类型 {} 的处理器", token.meta.token_type))
             .with_category(ErrorCategory::NotImplemented)
-            .with_severity(ErrorSeverity::Warning))
+            .with_severity(ErrorSeverity::Warning);
+        self.push_dead_letter(token, error.message().to_string()).await;
+        Err(error)
+    }
+
+    /// 将令牌放入死信队列，队列满时丢弃最早的死信
+    async fn push_dead_letter(&self, token: Token, reason: String) {
+        {
+            // 令牌类型由对端决定，限制单独计数的类型数，避免统计无限增长
+            let mut counts = self.dead_letter_counts.lock().await;
+            let token_type = &token.meta.token_type;
+            let tracked = counts.contains_key(token_type)
+                || (token_type.len() <= MAX_DEAD_LETTER_TYPE_LEN
+                    && token_type != DEAD_LETTER_OTHER_TYPE
+                    && counts.keys().filter(|key| key.as_str() != DEAD_LETTER_OTHER_TYPE).count() < MAX_DEAD_LETTER_TYPES);
+            let key = if tracked { token_type.clone() } else { DEAD_LETTER_OTHER_TYPE.to_string() };
+            *counts.entry(key).or_insert(0) += 1;
+        }
+
+        if self.dead_letter_capacity == 0 {
+            return;
+        }
+
+        let mut dead_letters = self.dead_letters.lock().await;
+        while dead_letters.len() >= self.dead_letter_capacity {
+            dead_letters.pop_front();
+        }
+        dead_letters.push_back(DeadLetter {
+            token,
+            reason,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        });
+    }
+
+    /// 获取死信队列中的死信（从旧到新）
+    ///
+    /// # 返回值
+    ///
+    /// 返回死信队列的快照
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().await.iter().cloned().collect()
+    }
+
+    /// 取出并清空死信队列
+    ///
+    /// # 返回值
+    ///
+    /// 返回取出的死信（从旧到新）
+    pub async fn take_dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().await.drain(..).collect()
+    }
+
+    /// 按令牌类型统计的死信数
+    ///
+    /// 统计包含已被丢弃或取出的死信；超出 [`MAX_DEAD_LETTER_TYPES`] 的新类型和过长的类型名
+    /// 计入 [`DEAD_LETTER_OTHER_TYPE`]
+    pub async fn dead_letter_counts(&self) -> HashMap<TokenType, u64> {
+        self.dead_letter_counts.lock().await.clone()
     }
 
     /// 注销令牌处理器
@@ -401,6 +550,11 @@ mod tests {
         assert!(TokenPriority::Critical > TokenPriority::High);
         assert!(TokenPriority::High > TokenPriority::Normal);
         assert!(TokenPriority::Normal > TokenPriority::Low);
+
+        assert_eq!(TokenPriority::from(MessagePriority::Critical), TokenPriority::Critical);
+        assert_eq!(MessagePriority::from(TokenPriority::Low), MessagePriority::Low);
+        assert_eq!(TokenPriority::Normal.stream_priority(), 0);
+        assert!(TokenPriority::Critical.stream_priority() > TokenPriority::Low.stream_priority());
     }
 
     #[tokio::test]
@@ -408,4 +562,72 @@ mod tests {
         let router = TokenRouter::new();
         assert!(router.handlers.read().await.is_empty());
     }
+
+    struct EchoHandler;
+
+    #[async_trait]
+    impl TokenHandler for EchoHandler {
+        fn token_types(&self) -> Vec<TokenType> {
+            vec!["echo".to_string()]
+        }
+
+        async fn handle_token(&self, token: Token) -> NetResult<Option<Token>> {
+            Ok(Some(token))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unroutable_token_goes_to_dead_letters() {
+        let router = TokenRouter::with_dead_letter_capacity(2);
+        router.register_handler(Arc::new(EchoHandler)).await.expect("注册处理器失败");
+
+        let routed = Token::new(TokenMeta::new("echo".to_string(), "sender".to_string()), vec![]);
+        router.route_token(routed).await.expect("路由令牌失败");
+        assert!(router.dead_letters().await.is_empty());
+
+        for payload in 0..3u8 {
+            let token = Token::new(TokenMeta::new("unknown".to_string(), "sender".to_string()), vec![payload]);
+            let error = router.route_token(token).await.expect_err("无处理器的令牌应该路由失败");
+            assert_eq!(error.code(), 4004);
+        }
+
+        // 队列有界，只保留最新的两条
+        let dead_letters = router.dead_letters().await;
+        assert_eq!(dead_letters.len(), 2);
+        assert_eq!(dead_letters[0].token.payload, vec![1]);
+        assert_eq!(dead_letters[1].token.meta.token_type, "unknown");
+        assert!(dead_letters[1].reason.contains("unknown"));
+        assert_eq!(router.dead_letter_counts().await.get("unknown"), Some(&3));
+
+        assert_eq!(router.take_dead_letters().await.len(), 2);
+        assert!(router.dead_letters().await.is_empty());
+
+        // 注册兜底处理器后不再产生死信
+        router.set_fallback_handler(Arc::new(EchoHandler)).await;
+        let token = Token::new(TokenMeta::new("unknown".to_string(), "sender".to_string()), vec![9]);
+        let response = router.route_token(token).await.expect("兜底处理器应该处理令牌");
+        assert_eq!(response.map(|token| token.payload), Some(vec![9]));
+        assert!(router.dead_letters().await.is_empty());
+        assert_eq!(router.dead_letter_counts().await.get("unknown"), Some(&3));
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_counts_are_bounded() {
+        let router = TokenRouter::with_dead_letter_capacity(0);
+        for i in 0..MAX_DEAD_LETTER_TYPES + 10 {
+            let token = Token::new(TokenMeta::new(format!("type-{}", i), "sender".to_string()), Vec::new());
+            let _ = router.route_token(token).await;
+        }
+        let token = Token::new(TokenMeta::new("x".repeat(1000), "sender".to_string()), Vec::new());
+        let _ = router.route_token(token).await;
+
+        // 已计数的类型继续单独计数
+        let token = Token::new(TokenMeta::new("type-0".to_string(), "sender".to_string()), Vec::new());
+        let _ = router.route_token(token).await;
+
+        let counts = router.dead_letter_counts().await;
+        assert_eq!(counts.len(), MAX_DEAD_LETTER_TYPES + 1);
+        assert_eq!(counts.get(DEAD_LETTER_OTHER_TYPE), Some(&11));
+        assert_eq!(counts.get("type-0"), Some(&2));
+    }
 }
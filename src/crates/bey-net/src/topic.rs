@@ -0,0 +1,374 @@
+//! # 主题发布订阅
+//!
+//! 在令牌之上提供简单的主题（topic）发布订阅层，供群聊、剪切板同步等需要扇出的功能复用。
+//!
+//! 成员关系通过公告令牌维护：设备订阅或退订主题时向已知设备发送公告，
+//! 与设备建立连接时也在该连接上公告本地订阅的全部主题；
+//! 收到公告的设备记录发送方对该主题的订阅状态；发布时只向已知订阅者发送消息令牌。
+//! 公告的发送方取自令牌到达的已认证连接（见 [`crate::wire::stamp`]），
+//! 不是经连接收到的公告被忽略，设备无法替其他设备订阅。
+//!
+//! [`TopicBus`] 不直接进行网络发送，而是返回需要发送的令牌，
+//! 由 [`crate::TransportEngine`] 负责实际发送，便于在没有网络的情况下测试。
+
+use crate::token::{Token, TokenHandler, TokenMeta, TokenType};
+use crate::{wire, NetResult};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::{broadcast, RwLock};
+use tracing::debug;
+
+/// 主题订阅公告令牌类型
+pub const TOPIC_ANNOUNCE_TOKEN_TYPE: &str = "bey.topic.announce";
+
+/// 主题消息令牌类型
+pub const TOPIC_MESSAGE_TOKEN_TYPE: &str = "bey.topic.message";
+
+/// 主题消息令牌中记录主题名的属性
+const TOPIC_ATTRIBUTE: &str = "topic";
+
+/// 每个主题本地订阅通道的容量
+const TOPIC_CHANNEL_CAPACITY: usize = 256;
+
+/// 主题订阅公告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TopicAnnouncement {
+    /// 公告涉及的主题
+    topics: Vec<String>,
+    /// `true` 表示订阅，`false` 表示退订
+    subscribed: bool,
+}
+
+/// 收到的主题消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopicMessage {
+    /// 主题名
+    pub topic: String,
+    /// 发布者设备ID
+    pub sender_id: String,
+    /// 消息内容
+    pub payload: Vec<u8>,
+}
+
+/// 主题总线
+///
+/// 维护本地订阅和远端设备的主题成员关系
+pub struct TopicBus {
+    /// 本地设备ID
+    local_id: String,
+    /// 本地订阅：主题 -> 消息广播通道
+    local: RwLock<HashMap<String, broadcast::Sender<TopicMessage>>>,
+    /// 远端成员：主题 -> 订阅该主题的设备
+    members: RwLock<HashMap<String, HashSet<String>>>,
+}
+
+impl TopicBus {
+    /// 创建主题总线
+    ///
+    /// # 参数
+    ///
+    /// * `local_id` - 本地设备ID
+    pub fn new(local_id: impl Into<String>) -> Self {
+        Self {
+            local_id: local_id.into(),
+            local: RwLock::new(HashMap::new()),
+            members: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 订阅主题
+    ///
+    /// 同一主题可以多次订阅，所有订阅者都会收到消息
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    ///
+    /// # 返回值
+    ///
+    /// 返回主题消息接收器
+    pub async fn subscribe(&self, topic: &str) -> broadcast::Receiver<TopicMessage> {
+        let mut local = self.local.write().await;
+        local.entry(topic.to_string())
+            .or_insert_with(|| broadcast::channel(TOPIC_CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// 退订主题
+    ///
+    /// 关闭该主题的所有本地接收器
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    ///
+    /// # 返回值
+    ///
+    /// 之前订阅过该主题返回 `true`
+    pub async fn unsubscribe(&self, topic: &str) -> bool {
+        self.local.write().await.remove(topic).is_some()
+    }
+
+    /// 本地已订阅的主题
+    pub async fn local_topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self.local.read().await.keys().cloned().collect();
+        topics.sort();
+        topics
+    }
+
+    /// 已知订阅了指定主题的远端设备
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    pub async fn subscribers(&self, topic: &str) -> Vec<String> {
+        let mut subscribers: Vec<String> = self.members.read().await
+            .get(topic)
+            .map(|members| members.iter().cloned().collect())
+            .unwrap_or_default();
+        subscribers.sort();
+        subscribers
+    }
+
+    /// 构建发给指定设备的订阅公告令牌
+    ///
+    /// # 参数
+    ///
+    /// * `peer` - 接收公告的设备ID
+    /// * `topics` - 公告涉及的主题
+    /// * `subscribed` - `true` 表示订阅，`false` 表示退订
+    ///
+    /// # 返回值
+    ///
+    /// 返回公告令牌或序列化错误
+    pub fn announcement(&self, peer: &str, topics: Vec<String>, subscribed: bool) -> NetResult<Token> {
+        let payload = serde_json::to_vec(&TopicAnnouncement { topics, subscribed })
+            .map_err(|e| ErrorInfo::new(4801, format!("序列化主题公告失败: {}", e))
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let mut meta = TokenMeta::new(TOPIC_ANNOUNCE_TOKEN_TYPE.to_string(), self.local_id.clone());
+        meta.receiver_id = Some(peer.to_string());
+        Ok(Token::new(meta, payload))
+    }
+
+    /// 发布消息
+    ///
+    /// 本地订阅者直接收到消息；返回发给每个远端订阅者的消息令牌
+    ///
+    /// # 参数
+    ///
+    /// * `topic` - 主题名
+    /// * `payload` - 消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回需要发送给远端订阅者的令牌
+    pub async fn publish(&self, topic: &str, payload: Vec<u8>) -> Vec<Token> {
+        self.deliver_local(TopicMessage {
+            topic: topic.to_string(),
+            sender_id: self.local_id.clone(),
+            payload: payload.clone(),
+        }).await;
+
+        self.subscribers(topic).await
+            .into_iter()
+            .filter(|peer| *peer != self.local_id)
+            .map(|peer| {
+                let mut meta = TokenMeta::new(TOPIC_MESSAGE_TOKEN_TYPE.to_string(), self.local_id.clone());
+                meta.receiver_id = Some(peer);
+                meta.attributes.insert(TOPIC_ATTRIBUTE.to_string(), topic.to_string());
+                Token::new(meta, payload.clone())
+            })
+            .collect()
+    }
+
+    /// 处理收到的主题令牌
+    ///
+    /// 公告令牌更新发送方的成员关系，消息令牌投递给本地订阅者
+    ///
+    /// # 参数
+    ///
+    /// * `token` - 收到的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回处理结果，令牌格式错误时返回错误
+    pub async fn handle_inbound(&self, token: Token) -> NetResult<()> {
+        match token.meta.token_type.as_str() {
+            TOPIC_ANNOUNCE_TOKEN_TYPE => {
+                if wire::peer_addr(&token).is_none() {
+                    debug!("忽略不是经连接收到的主题公告: {}", token.meta.sender_id);
+                    return Ok(());
+                }
+                let announcement: TopicAnnouncement = serde_json::from_slice(&token.payload)
+                    .map_err(|e| ErrorInfo::new(4801, format!("解析主题公告失败: {}", e))
+                        .with_category(ErrorCategory::Parse)
+                        .with_severity(ErrorSeverity::Warning))?;
+
+                let peer = token.meta.sender_id;
+                let mut members = self.members.write().await;
+                for topic in announcement.topics {
+                    if announcement.subscribed {
+                        members.entry(topic).or_default().insert(peer.clone());
+                    } else if let Some(subscribers) = members.get_mut(&topic) {
+                        subscribers.remove(&peer);
+                        if subscribers.is_empty() {
+                            members.remove(&topic);
+                        }
+                    }
+                }
+                debug!("更新设备 {} 的主题订阅", peer);
+                Ok(())
+            }
+            TOPIC_MESSAGE_TOKEN_TYPE => {
+                let topic = token.meta.attributes.get(TOPIC_ATTRIBUTE).cloned()
+                    .ok_or_else(|| ErrorInfo::new(4802, "主题消息缺少主题名".to_string())
+                        .with_category(ErrorCategory::Validation)
+                        .with_severity(ErrorSeverity::Warning))?;
+
+                self.deliver_local(TopicMessage {
+                    topic,
+                    sender_id: token.meta.sender_id,
+                    payload: token.payload,
+                }).await;
+                Ok(())
+            }
+            other => {
+                debug!("忽略非主题令牌: {}", other);
+                Ok(())
+            }
+        }
+    }
+
+    /// 移除设备的所有主题成员关系
+    ///
+    /// # 参数
+    ///
+    /// * `peer` - 设备ID
+    pub async fn remove_peer(&self, peer: &str) {
+        let mut members = self.members.write().await;
+        members.retain(|_, subscribers| {
+            subscribers.remove(peer);
+            !subscribers.is_empty()
+        });
+    }
+
+    /// 投递给本地订阅者
+    async fn deliver_local(&self, message: TopicMessage) {
+        if let Some(sender) = self.local.read().await.get(&message.topic) {
+            // 所有接收器都已丢弃时发送失败，忽略即可
+            let _ = sender.send(message);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenHandler for TopicBus {
+    fn token_types(&self) -> Vec<TokenType> {
+        vec![TOPIC_ANNOUNCE_TOKEN_TYPE.to_string(), TOPIC_MESSAGE_TOKEN_TYPE.to_string()]
+    }
+
+    async fn handle_token(&self, token: Token) -> NetResult<Option<Token>> {
+        self.handle_inbound(token).await?;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// 把令牌按接收者投递给对应的总线，像经连接收到的令牌一样标记发送方
+    async fn deliver(buses: &HashMap<String, Arc<TopicBus>>, tokens: Vec<Token>) {
+        for token in tokens {
+            let receiver = token.meta.receiver_id.clone().expect("令牌应该指定接收者");
+            let sender = token.meta.sender_id.clone();
+            let token = wire::stamp(token, &sender, "127.0.0.1:9000".parse().expect("地址解析失败"));
+            buses[&receiver].handle_inbound(token).await.expect("处理令牌失败");
+        }
+    }
+
+    /// 订阅主题并向其他设备公告
+    async fn join(
+        buses: &HashMap<String, Arc<TopicBus>>,
+        device: &str,
+        topic: &str,
+    ) -> broadcast::Receiver<TopicMessage> {
+        let bus = &buses[device];
+        let receiver = bus.subscribe(topic).await;
+        for peer in buses.keys().filter(|peer| *peer != device) {
+            let token = bus.announcement(peer, vec![topic.to_string()], true).expect("构建公告失败");
+            deliver(buses, vec![token]).await;
+        }
+        receiver
+    }
+
+    #[tokio::test]
+    async fn test_publish_reaches_only_subscribers() {
+        let buses: HashMap<String, Arc<TopicBus>> = ["publisher", "sub-a", "sub-b", "bystander"]
+            .into_iter()
+            .map(|name| (name.to_string(), Arc::new(TopicBus::new(name))))
+            .collect();
+
+        let mut sub_a = join(&buses, "sub-a", "clipboard").await;
+        let mut sub_b = join(&buses, "sub-b", "clipboard").await;
+        let mut bystander = buses["bystander"].subscribe("chat").await;
+
+        assert_eq!(buses["publisher"].subscribers("clipboard").await, vec!["sub-a", "sub-b"]);
+
+        let tokens = buses["publisher"].publish("clipboard", b"copied".to_vec()).await;
+        assert_eq!(tokens.len(), 2);
+        deliver(&buses, tokens).await;
+
+        for receiver in [&mut sub_a, &mut sub_b] {
+            let message = receiver.try_recv().expect("订阅者应该收到消息");
+            assert_eq!(message.topic, "clipboard");
+            assert_eq!(message.sender_id, "publisher");
+            assert_eq!(message.payload, b"copied");
+        }
+        assert!(bystander.try_recv().is_err(), "未订阅者不应该收到消息");
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_removes_membership() {
+        let buses: HashMap<String, Arc<TopicBus>> = ["publisher", "sub-a"]
+            .into_iter()
+            .map(|name| (name.to_string(), Arc::new(TopicBus::new(name))))
+            .collect();
+
+        let _receiver = join(&buses, "sub-a", "group").await;
+        assert_eq!(buses["publisher"].subscribers("group").await, vec!["sub-a"]);
+
+        assert!(buses["sub-a"].unsubscribe("group").await);
+        let token = buses["sub-a"].announcement("publisher", vec!["group".to_string()], false)
+            .expect("构建公告失败");
+        deliver(&buses, vec![token]).await;
+
+        assert!(buses["publisher"].subscribers("group").await.is_empty());
+        assert!(buses["publisher"].publish("group", b"hello".to_vec()).await.is_empty());
+
+        // 设备离线时移除其成员关系
+        let _receiver = join(&buses, "sub-a", "group").await;
+        buses["publisher"].remove_peer("sub-a").await;
+        assert!(buses["publisher"].subscribers("group").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_announcement_sender_comes_from_connection() {
+        let bus = TopicBus::new("publisher");
+        let forged = TopicBus::new("victim").announcement("publisher", vec!["group".to_string()], true)
+            .expect("构建公告失败");
+
+        // 未经连接收到的公告不改变成员关系
+        bus.handle_inbound(forged.clone()).await.expect("处理令牌失败");
+        assert!(bus.subscribers("group").await.is_empty());
+
+        // 经连接收到时以连接认证的对端为准，而不是令牌自称的发送方
+        let stamped = wire::stamp(forged, "mallory", "127.0.0.1:9000".parse().expect("地址解析失败"));
+        bus.handle_inbound(stamped).await.expect("处理令牌失败");
+        assert_eq!(bus.subscribers("group").await, vec!["mallory"]);
+    }
+}
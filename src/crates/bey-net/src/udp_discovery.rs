@@ -24,6 +24,10 @@ use tokio::net::UdpSocket;
 use tokio::sync::{mpsc, RwLock};
 use tokio::time::{interval, sleep};
 
+use crate::capability::{capability_token, normalize_capabilities, Capability};
+use crate::device_changes::{DeviceChange, DeviceChangeFeed};
+use crate::task_group::{TaskGroup, DEFAULT_TASK_SHUTDOWN_TIMEOUT};
+
 /// 设备信息（临时定义，避免循环依赖）
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DeviceInfo {
@@ -41,6 +45,23 @@ pub struct DeviceInfo {
     pub last_active: SystemTime,
 }
 
+impl DeviceInfo {
+    /// 设备是否声明了指定能力
+    ///
+    /// # 参数
+    ///
+    /// * `capability` - 设备能力
+    pub fn has_capability(&self, capability: &Capability) -> bool {
+        let token = capability_token(capability);
+        self.capabilities.iter().any(|capability| capability == token)
+    }
+
+    /// 排序去重能力列表，使相同的能力集合得到相同的公告内容
+    fn normalize_capabilities(&mut self) {
+        self.capabilities = normalize_capabilities(self.capabilities.iter().map(String::as_str));
+    }
+}
+
 
 /// 设备发现服务结果类型
 pub type DiscoveryResult<T> = std::result::Result<T, ErrorInfo>;
@@ -201,12 +222,16 @@ pub struct DiscoveryService {
     event_sender: mpsc::UnboundedSender<DeviceEvent>,
     /// 设备事件接收器
     event_receiver: Option<mpsc::UnboundedReceiver<DeviceEvent>>,
+    /// 设备变化源
+    device_changes: Arc<DeviceChangeFeed>,
     /// 运行状态
     is_running: Arc<RwLock<bool>>,
     /// 消息ID计数器
     message_counter: Arc<RwLock<u64>>,
     /// 序列化缓冲区池，减少内存分配
     serialization_pool: Arc<RwLock<Vec<Vec<u8>>>>,
+    /// 消息接收、心跳和设备清理后台任务
+    tasks: TaskGroup,
 }
 
 impl DiscoveryService {
@@ -222,8 +247,10 @@ impl DiscoveryService {
     /// 返回发现服务实例或错误信息
     pub async fn new(
         config: DiscoveryConfig,
-        local_device: DeviceInfo,
+        mut local_device: DeviceInfo,
     ) -> DiscoveryResult<Self> {
+        local_device.normalize_capabilities();
+
         // 绑定 UDP 套接字
         let bind_addr = format!("0.0.0.0:{}", config.port());
         let socket = UdpSocket::bind(&bind_addr)
@@ -250,9 +277,11 @@ impl DiscoveryService {
             discovered_devices: Arc::new(RwLock::new(HashMap::new())),
             event_sender,
             event_receiver: Some(event_receiver),
+            device_changes: Arc::new(DeviceChangeFeed::default()),
             is_running: Arc::new(RwLock::new(false)),
             message_counter: Arc::new(RwLock::new(0)),
             serialization_pool: Arc::new(RwLock::new(Vec::with_capacity(10))),
+            tasks: TaskGroup::new("UDP发现服务"),
         })
     }
 
@@ -291,15 +320,24 @@ impl DiscoveryService {
     }
 
     /// 停止设备发现服务
+    ///
+    /// 下线消息发送失败时仍会停止所有后台任务
     pub async fn stop(&self) -> DiscoveryResult<()> {
         // 发送设备下线消息
-        self.broadcast_device_offline().await?;
+        let result = self.broadcast_device_offline().await;
 
         // 设置运行状态为停止
-        let mut is_running = self.is_running.write().await;
-        *is_running = false;
+        *self.is_running.write().await = false;
 
-        Ok(())
+        // 停止所有后台任务
+        self.tasks.shutdown(DEFAULT_TASK_SHUTDOWN_TIMEOUT).await;
+
+        result
+    }
+
+    /// 运行中的后台任务数量
+    pub fn active_task_count(&self) -> usize {
+        self.tasks.active_count()
     }
 
     /// 获取下一个设备事件
@@ -314,6 +352,21 @@ impl DiscoveryService {
         }
     }
 
+    /// 使用指定的设备变化源，与其他发现服务共享设备列表和变化流
+    ///
+    /// 须在 `start` 之前调用
+    pub fn with_device_change_feed(mut self, feed: Arc<DeviceChangeFeed>) -> Self {
+        self.device_changes = feed;
+        self
+    }
+
+    /// 订阅设备变化
+    ///
+    /// 与 `next_event` 互不影响，可以有多个订阅者
+    pub fn device_changes(&self) -> impl futures::Stream<Item = DeviceChange> + Send + 'static {
+        self.device_changes.device_changes()
+    }
+
     /// 获取已发现的设备列表
     ///
     /// # 返回值
@@ -338,6 +391,25 @@ impl DiscoveryService {
         devices.get(device_id).cloned()
     }
 
+    /// 按能力筛选已发现的设备
+    ///
+    /// # 参数
+    ///
+    /// * `capability` - 设备能力
+    ///
+    /// # 返回值
+    ///
+    /// 返回声明了该能力的设备，按设备ID排列
+    pub async fn discover_by_capability(&self, capability: Capability) -> Vec<DeviceInfo> {
+        let devices = self.discovered_devices.read().await;
+        let mut matched: Vec<DeviceInfo> = devices.values()
+            .filter(|device| device.has_capability(&capability))
+            .cloned()
+            .collect();
+        matched.sort_by(|a, b| a.device_id.cmp(&b.device_id));
+        matched
+    }
+
     /// 广播设备公告消息
     async fn broadcast_device_announcement(&self) -> DiscoveryResult<()> {
         let message = DiscoveryMessage::DeviceAnnouncement {
@@ -412,10 +484,11 @@ impl DiscoveryService {
         let socket = Arc::clone(&self.socket);
         let discovered_devices = Arc::clone(&self.discovered_devices);
         let event_sender = self.event_sender.clone();
+        let device_changes = Arc::clone(&self.device_changes);
         let is_running = Arc::clone(&self.is_running);
         let local_device_id = self.local_device.device_id.clone();
 
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             // 使用栈分配的数组减少堆分配，提高性能
             let mut buffer = [0u8; 8192]; // 8KB 栈缓冲区，适合大多数UDP消息
 
@@ -430,6 +503,7 @@ impl DiscoveryService {
                                     addr,
                                     &discovered_devices,
                                     &event_sender,
+                                    &device_changes,
                                     &local_device_id,
                                 ).await;
                             }
@@ -455,7 +529,7 @@ impl DiscoveryService {
         let port = self.config.port();
         let local_device_id = self.local_device.device_id.clone();
 
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             let mut interval = interval(heartbeat_interval);
 
             while *is_running.read().await {
@@ -478,10 +552,11 @@ impl DiscoveryService {
     async fn start_device_cleanup_task(&self) {
         let discovered_devices = Arc::clone(&self.discovered_devices);
         let event_sender = self.event_sender.clone();
+        let device_changes = Arc::clone(&self.device_changes);
         let device_timeout = self.config.device_timeout();
         let is_running = Arc::clone(&self.is_running);
 
-        tokio::spawn(async move {
+        self.tasks.spawn(async move {
             let mut interval = interval(Duration::from_secs(10)); // 每10秒检查一次
 
             while *is_running.read().await {
@@ -506,7 +581,7 @@ impl DiscoveryService {
                     let mut devices = discovered_devices.write().await;
                     for device_id in timeout_devices {
                         devices.remove(&device_id);
-                        let _ = event_sender.send(DeviceEvent::DeviceOffline(device_id.clone()));
+                        Self::emit_event(&event_sender, &device_changes, DeviceEvent::DeviceOffline(device_id));
                     }
                 }
             }
@@ -519,6 +594,7 @@ impl DiscoveryService {
         addr: SocketAddr,
         discovered_devices: &Arc<RwLock<HashMap<String, DeviceInfo>>>,
         event_sender: &mpsc::UnboundedSender<DeviceEvent>,
+        device_changes: &DeviceChangeFeed,
         local_device_id: &str,
     ) {
         match message {
@@ -535,15 +611,17 @@ impl DiscoveryService {
                 let mut updated_device = device_info.clone();
                 updated_device.last_active = timestamp;
                 updated_device.address = addr; // 更新实际接收到的地址
+                updated_device.normalize_capabilities();
 
                 devices.insert(device_info.device_id.clone(), updated_device.clone());
 
                 // 发送事件
-                if is_new_device {
-                    let _ = event_sender.send(DeviceEvent::DeviceOnline(updated_device));
+                let event = if is_new_device {
+                    DeviceEvent::DeviceOnline(updated_device)
                 } else {
-                    let _ = event_sender.send(DeviceEvent::DeviceUpdated(updated_device));
-                }
+                    DeviceEvent::DeviceUpdated(updated_device)
+                };
+                Self::emit_event(event_sender, device_changes, event);
             }
             DiscoveryMessage::Heartbeat { device_id, timestamp } => {
                 // 忽略自己的心跳
@@ -564,12 +642,22 @@ impl DiscoveryService {
 
                 let mut devices = discovered_devices.write().await;
                 if devices.remove(&device_id).is_some() {
-                    let _ = event_sender.send(DeviceEvent::DeviceOffline(device_id));
+                    Self::emit_event(event_sender, device_changes, DeviceEvent::DeviceOffline(device_id));
                 }
             }
         }
     }
 
+    /// 发送设备事件并更新设备变化源
+    fn emit_event(
+        event_sender: &mpsc::UnboundedSender<DeviceEvent>,
+        device_changes: &DeviceChangeFeed,
+        event: DeviceEvent,
+    ) {
+        device_changes.apply_device_event(&event);
+        let _ = event_sender.send(event);
+    }
+
     /// 生成消息ID
     async fn generate_message_id(&self) -> String {
         let mut counter = self.message_counter.write().await;
@@ -649,13 +737,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_discovery_service_creation() {
-        let (service, _) = create_test_discovery_service(18080).await;
+        let (service, _) = create_test_discovery_service(18080).await.expect("创建发现服务失败");
         assert!(!service.local_device.device_id.is_empty());
     }
 
     #[tokio::test]
     async fn test_discovery_service_start_stop() {
-        let (mut service, _) = create_test_discovery_service(18081).await;
+        let (mut service, _) = create_test_discovery_service(18081).await.expect("创建发现服务失败");
 
         // 启动服务
         let start_result = service.start().await;
@@ -700,27 +788,34 @@ mod tests {
 
     #[tokio::test]
     async fn test_device_discovery_flow() {
-        // 创建两个发现服务实例，模拟设备发现
-        let (mut service1, device1) = create_test_discovery_service(18082).await;
-        let (mut service2, device2) = create_test_discovery_service(18083).await;
-
-        // 启动服务
-        service1.start().await.expect("服务1启动失败");
-        service2.start().await.expect("服务2启动失败");
+        let (mut service, _) = create_test_discovery_service(18082).await.expect("创建发现服务失败");
+        service.start().await.expect("服务启动失败");
+
+        // 模拟另一台设备向本机端口发送上线通告
+        let peer = DeviceInfo {
+            device_id: "peer-device".to_string(),
+            device_name: "Peer Device".to_string(),
+            device_type: "Laptop".to_string(),
+            address: "127.0.0.1:8081".parse().expect("地址解析失败"),
+            capabilities: vec!["messaging".to_string()],
+            last_active: SystemTime::now(),
+        };
+        let announcement = DiscoveryMessage::DeviceAnnouncement {
+            device_info: peer,
+            timestamp: SystemTime::now(),
+            message_id: "peer-message".to_string(),
+        };
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("绑定套接字失败");
+        socket.send_to(&serde_json::to_vec(&announcement).expect("序列化失败"), "127.0.0.1:18082")
+            .await
+            .expect("发送通告失败");
 
         // 等待设备发现
         sleep(Duration::from_millis(500)).await;
 
-        // 检查是否发现了对方
-        let discovered1 = service1.get_discovered_devices().await;
-        let discovered2 = service2.get_discovered_devices().await;
+        let discovered = service.get_discovered_devices().await;
+        assert!(discovered.iter().any(|device| device.device_id == "peer-device"), "应该发现通告的设备");
 
-        // 验证设备发现结果
-        assert!(!discovered1.is_empty() || !discovered2.is_empty(),
-               "至少应该发现一个设备");
-
-        // 停止服务
-        service1.stop().await.expect("服务1停止失败");
-        service2.stop().await.expect("服务2停止失败");
+        service.stop().await.expect("服务停止失败");
     }
 }
\ No newline at end of file
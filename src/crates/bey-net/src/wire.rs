@@ -0,0 +1,315 @@
+//! # 令牌线路
+//!
+//! 在已完成握手的QUIC连接上收发令牌。每个令牌占用一条单向流，流的内容是序列化后的令牌；
+//! 发送流通过 [`open_prioritized_stream`] 按令牌优先级设置QUIC流优先级，
+//! 同一连接上高优先级的令牌先占用带宽。
+//!
+//! 接收方不采信令牌中自称的发送方：令牌的发送方被改写为握手时对端给出的名称，
+//! 令牌到达的连接地址记录在 [`PEER_ADDR_ATTRIBUTE`] 属性中，确认和响应令牌按该地址发回。
+//!
+//! 子系统可以把某类令牌改为经由逻辑通道发送（见 [`ChannelRoutes`]）：
+//! 类型前缀匹配的令牌在到对端的长期通道上逐帧发送，不再为每个令牌打开新的流。
+
+use bey_transport::Connection;
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+use crate::{
+    NetResult,
+    channel::{self, Channel, ChannelId},
+    qos::open_prioritized_stream,
+    receiver::InboundSender,
+    token::Token,
+};
+
+/// 单个令牌序列化后的最大长度（字节）
+pub const MAX_WIRE_TOKEN_SIZE: usize = 16 * 1024 * 1024;
+
+/// 记录令牌到达的连接地址的属性
+pub const PEER_ADDR_ATTRIBUTE: &str = "bey.peer_addr";
+
+/// 在连接上发送令牌
+///
+/// 对端确认收到流上的全部数据后返回，不代表对端已经处理了令牌
+///
+/// # 参数
+///
+/// * `connection` - 已完成握手的连接
+/// * `token` - 要发送的令牌
+///
+/// # 返回值
+///
+/// 返回发送结果或错误
+pub async fn send(connection: &Connection, token: &Token) -> NetResult<()> {
+    let remote_addr = connection.remote_address();
+    let data = encode(token)?;
+
+    let mut stream = open_prioritized_stream(connection, token.meta.priority).await?;
+    stream.write_all(&data).await.map_err(|e| wire_error(remote_addr, "发送", e))?;
+    stream.finish().map_err(|e| wire_error(remote_addr, "发送", e))?;
+    match stream.stopped().await {
+        Ok(None) => Ok(()),
+        Ok(Some(code)) => Err(wire_error(remote_addr, "发送", format!("对端停止接收 (错误码: {})", code))),
+        Err(e) => Err(wire_error(remote_addr, "发送", e)),
+    }
+}
+
+/// 接收连接上对端发送的令牌，直到连接关闭
+///
+/// 每条单向流在单独的任务中读取，慢速的大令牌不会阻塞其后的令牌。
+/// 入站队列已满时按队列的溢出策略处理
+///
+/// # 参数
+///
+/// * `connection` - 已完成握手的连接
+/// * `peer_name` - 握手时对端给出的名称
+/// * `inbound` - 入站令牌队列
+pub async fn serve(connection: Connection, peer_name: String, inbound: InboundSender) {
+    let remote_addr = connection.remote_address();
+    let mut readers = JoinSet::new();
+
+    while let Ok(mut recv) = connection.accept_uni().await {
+        let peer_name = peer_name.clone();
+        let inbound = inbound.clone();
+        readers.spawn(async move {
+            let data = match recv.read_to_end(MAX_WIRE_TOKEN_SIZE).await {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!("读取 {} 发送的令牌失败: {}", remote_addr, e);
+                    return;
+                }
+            };
+            let token = match Token::deserialize(&data) {
+                Ok(token) => stamp(token, &peer_name, remote_addr),
+                Err(e) => {
+                    warn!("丢弃 {} 发送的无效令牌: {}", remote_addr, e);
+                    return;
+                }
+            };
+            if let Err(e) = inbound.send(token).await {
+                warn!("{} 发送的令牌无法放入入站队列: {}", remote_addr, e);
+            }
+        });
+
+        // 回收已读完的流
+        while readers.try_join_next().is_some() {}
+    }
+
+    debug!("停止接收 {} 的令牌", remote_addr);
+}
+
+/// 接收逻辑通道上对端发送的令牌，直到对端结束发送或通道出错
+///
+/// # 参数
+///
+/// * `channel` - 对端打开的通道
+/// * `peer_name` - 握手时对端给出的名称
+/// * `inbound` - 入站令牌队列
+pub(crate) async fn serve_channel(mut channel: Channel, peer_name: String, inbound: InboundSender) {
+    let remote_addr = channel.remote_addr();
+    loop {
+        let data = match channel.recv().await {
+            Ok(Some(data)) => data,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("读取 {} 在通道 {} 上发送的令牌失败: {}", remote_addr, channel.id(), e);
+                break;
+            }
+        };
+        let token = match Token::deserialize(&data) {
+            Ok(token) => stamp(token, &peer_name, remote_addr),
+            Err(e) => {
+                warn!("丢弃 {} 在通道 {} 上发送的无效令牌: {}", remote_addr, channel.id(), e);
+                continue;
+            }
+        };
+        if let Err(e) = inbound.send(token).await {
+            warn!("{} 发送的令牌无法放入入站队列: {}", remote_addr, e);
+        }
+    }
+    debug!("停止接收 {} 在通道 {} 上的令牌", remote_addr, channel.id());
+}
+
+/// 到对端的通道槽位，为空时下次发送打开通道
+type OutboundSlot = Arc<tokio::sync::Mutex<Option<OutboundChannel>>>;
+
+/// 到对端的令牌通道
+enum OutboundChannel {
+    /// 已打开的通道
+    Open(Channel),
+    /// 对端没有认领该通道，在该连接上回退为单向流
+    Unclaimed {
+        /// 连接ID
+        connection_id: usize,
+    },
+}
+
+impl OutboundChannel {
+    /// 通道是否属于给定的连接
+    fn belongs_to(&self, connection_id: usize) -> bool {
+        match self {
+            Self::Open(channel) => channel.connection_id() == connection_id,
+            Self::Unclaimed { connection_id: id } => *id == connection_id,
+        }
+    }
+}
+
+/// 按令牌类型选择逻辑通道发送令牌
+///
+/// 类型前缀匹配的令牌在到对端的长期通道上逐帧发送，返回时数据已写入通道，
+/// 不等待对端确认；对端没有认领该通道时回退为每个令牌一条单向流。
+/// 其他令牌仍通过 [`send`] 发送
+#[derive(Default)]
+pub(crate) struct ChannelRoutes {
+    /// (令牌类型前缀, 通道号)
+    routes: RwLock<Vec<(String, ChannelId)>>,
+    /// (对端地址, 通道号) -> 到对端的通道，每个通道同时只有一个发送方写入
+    outbound: Mutex<HashMap<(SocketAddr, ChannelId), OutboundSlot>>,
+}
+
+impl ChannelRoutes {
+    /// 登记经由通道发送的令牌类型，已登记的路由不重复添加
+    ///
+    /// # 参数
+    ///
+    /// * `type_prefix` - 令牌类型前缀
+    /// * `channel_id` - 通道号
+    pub(crate) fn add(&self, type_prefix: &str, channel_id: ChannelId) {
+        let mut routes = self.routes.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !routes.iter().any(|(prefix, id)| prefix == type_prefix && *id == channel_id) {
+            routes.push((type_prefix.to_string(), channel_id));
+        }
+    }
+
+    /// 是否已有令牌类型经由该通道发送
+    pub(crate) fn routes_channel(&self, channel_id: ChannelId) -> bool {
+        self.routes.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .any(|(_, id)| *id == channel_id)
+    }
+
+    /// 令牌类型对应的通道
+    fn channel_for(&self, token_type: &str) -> Option<ChannelId> {
+        self.routes.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .find(|(prefix, _)| token_type.starts_with(prefix.as_str()))
+            .map(|(_, channel_id)| *channel_id)
+    }
+
+    /// 在连接上发送令牌，类型登记了通道的令牌经由通道发送
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 已完成握手的连接
+    /// * `token` - 要发送的令牌
+    ///
+    /// # 返回值
+    ///
+    /// 返回发送结果或错误，通道写入失败后下次发送重新打开通道
+    pub(crate) async fn send(&self, connection: &Connection, token: &Token) -> NetResult<()> {
+        let Some(channel_id) = self.channel_for(&token.meta.token_type) else {
+            return send(connection, token).await;
+        };
+        let data = encode(token)?;
+
+        let remote_addr = connection.remote_address();
+        let connection_id = connection.stable_id();
+        let slot = {
+            let mut outbound = self.outbound.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            Arc::clone(outbound.entry((remote_addr, channel_id)).or_default())
+        };
+        let mut slot = slot.lock().await;
+
+        // 连接重建后重新打开通道
+        if !slot.as_ref().is_some_and(|outbound| outbound.belongs_to(connection_id)) {
+            *slot = Some(match channel::open(connection, channel_id).await {
+                Ok(channel) => OutboundChannel::Open(channel),
+                Err(e) if e.code() == 4943 => {
+                    debug!("{} 没有认领通道 {}，令牌改用单向流发送", remote_addr, channel_id);
+                    OutboundChannel::Unclaimed { connection_id }
+                }
+                Err(e) => {
+                    *slot = None;
+                    return Err(e);
+                }
+            });
+        }
+
+        match slot.as_mut() {
+            Some(OutboundChannel::Open(channel)) => {
+                let result = channel.send(&data).await;
+                if result.is_err() {
+                    *slot = None;
+                }
+                result
+            }
+            _ => {
+                drop(slot);
+                send(connection, token).await
+            }
+        }
+    }
+}
+
+/// 编码令牌
+///
+/// # 参数
+///
+/// * `token` - 要发送的令牌
+///
+/// # 返回值
+///
+/// 返回序列化后的令牌，超过 [`MAX_WIRE_TOKEN_SIZE`] 时返回错误
+pub fn encode(token: &Token) -> NetResult<Vec<u8>> {
+    let data = token.serialize()?;
+    if data.len() > MAX_WIRE_TOKEN_SIZE {
+        return Err(ErrorInfo::new(4951, format!("令牌 {} 过长: {} 字节", token.meta.id, data.len()))
+            .with_category(ErrorCategory::Validation)
+            .with_severity(ErrorSeverity::Error));
+    }
+    Ok(data)
+}
+
+/// 以认证的对端标记收到的令牌
+///
+/// # 参数
+///
+/// * `token` - 收到的令牌
+/// * `peer_name` - 握手时对端给出的名称
+/// * `remote_addr` - 令牌到达的连接地址
+///
+/// # 返回值
+///
+/// 返回发送方被改写、带有连接地址属性的令牌
+pub fn stamp(mut token: Token, peer_name: &str, remote_addr: SocketAddr) -> Token {
+    if token.meta.sender_id != peer_name {
+        debug!("令牌 {} 自称来自 {}，按连接改写为 {}", token.meta.id, token.meta.sender_id, peer_name);
+        token.meta.sender_id = peer_name.to_string();
+    }
+    token.meta.attributes.insert(PEER_ADDR_ATTRIBUTE.to_string(), remote_addr.to_string());
+    token
+}
+
+/// 令牌的来源连接地址
+///
+/// # 参数
+///
+/// * `token` - 收到的令牌
+///
+/// # 返回值
+///
+/// 经连接收到的令牌返回连接地址，本地投递的令牌返回 `None`
+pub fn peer_addr(token: &Token) -> Option<SocketAddr> {
+    token.meta.attributes.get(PEER_ADDR_ATTRIBUTE)?.parse().ok()
+}
+
+/// 令牌收发错误
+fn wire_error(remote_addr: SocketAddr, action: &str, error: impl std::fmt::Display) -> ErrorInfo {
+    ErrorInfo::new(4952, format!("向 {} {}令牌失败: {}", remote_addr, action, error))
+        .with_category(ErrorCategory::Network)
+        .with_severity(ErrorSeverity::Error)
+}
@@ -0,0 +1,93 @@
+//! # 插件能力模块
+//!
+//! 定义插件可申请的能力，插件只能执行其声明过的操作
+
+use std::collections::HashSet;
+use serde::{Serialize, Deserialize};
+
+/// 插件能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PluginCapability {
+    /// 发送事件
+    EmitEvents,
+    /// 注册钩子
+    RegisterHooks,
+}
+
+impl PluginCapability {
+    /// 获取能力名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::EmitEvents => "emit_events",
+            Self::RegisterHooks => "register_hooks",
+        }
+    }
+}
+
+/// 插件能力集合
+///
+/// 插件注册时由 [`crate::Plugin::capabilities`] 声明，之后不可更改
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginCapabilities {
+    /// 已授予的能力
+    granted: HashSet<PluginCapability>,
+}
+
+impl PluginCapabilities {
+    /// 创建空的能力集合
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加能力
+    pub fn with(mut self, capability: PluginCapability) -> Self {
+        self.granted.insert(capability);
+        self
+    }
+
+    /// 检查是否拥有指定能力
+    ///
+    /// # 参数
+    ///
+    /// * `capability` - 要检查的能力
+    ///
+    /// # 返回值
+    ///
+    /// 拥有该能力返回 true，否则返回 false
+    pub fn contains(&self, capability: PluginCapability) -> bool {
+        self.granted.contains(&capability)
+    }
+
+    /// 获取能力数量
+    pub fn len(&self) -> usize {
+        self.granted.len()
+    }
+
+    /// 检查是否为空
+    pub fn is_empty(&self) -> bool {
+        self.granted.is_empty()
+    }
+}
+
+impl FromIterator<PluginCapability> for PluginCapabilities {
+    fn from_iter<I: IntoIterator<Item = PluginCapability>>(iter: I) -> Self {
+        Self {
+            granted: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_contains() {
+        let capabilities: PluginCapabilities = vec![PluginCapability::EmitEvents].into_iter().collect();
+
+        assert!(capabilities.contains(PluginCapability::EmitEvents));
+        assert!(!capabilities.contains(PluginCapability::RegisterHooks));
+        assert_eq!(capabilities.len(), 1);
+        assert!(PluginCapabilities::new().is_empty());
+    }
+}
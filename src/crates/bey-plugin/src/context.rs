@@ -2,13 +2,17 @@
 //!
 //! 为插件提供运行时环境和 API 访问
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use dashmap::DashMap;
-use crate::{EventBus, HookRegistry};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use crate::{Event, EventBus, HookRegistry, PluginResult};
+use crate::capabilities::{PluginCapability, PluginCapabilities};
+use crate::hooks::{Hook, HookPoint};
 
 /// 插件上下文
 ///
-/// 为插件提供访问系统资源和其他插件的能力
+/// 为插件提供访问系统资源和其他插件的能力，
+/// 发送事件、注册钩子等操作只有在插件声明了对应能力时才允许执行
 pub struct PluginContext {
     /// 插件名称
     plugin_name: String,
@@ -18,10 +22,16 @@ pub struct PluginContext {
     hook_registry: Arc<HookRegistry>,
     /// 插件数据存储
     data: DashMap<String, Vec<u8>>,
+    /// 插件已声明的能力
+    capabilities: PluginCapabilities,
+    /// 被拒绝的能力请求，由插件管理器取出并记录
+    denied_requests: Mutex<Vec<PluginCapability>>,
 }
 
 impl PluginContext {
     /// 创建新的插件上下文
+    ///
+    /// 新建的上下文不拥有任何能力，需通过 [`PluginContext::with_capabilities`] 授予
     pub fn new(
         plugin_name: String,
         event_bus: Arc<EventBus>,
@@ -32,22 +42,92 @@ impl PluginContext {
             event_bus,
             hook_registry,
             data: DashMap::new(),
+            capabilities: PluginCapabilities::new(),
+            denied_requests: Mutex::new(Vec::new()),
         }
     }
+
+    /// 设置插件能力
+    pub fn with_capabilities(mut self, capabilities: PluginCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
     
     /// 获取插件名称
     pub fn plugin_name(&self) -> &str {
         &self.plugin_name
     }
-    
-    /// 获取事件总线引用
-    pub fn event_bus(&self) -> Arc<EventBus> {
-        Arc::clone(&self.event_bus)
+
+    /// 获取插件已声明的能力
+    pub fn capabilities(&self) -> &PluginCapabilities {
+        &self.capabilities
     }
-    
-    /// 获取钩子注册表引用
-    pub fn hook_registry(&self) -> Arc<HookRegistry> {
-        Arc::clone(&self.hook_registry)
+
+    /// 检查插件是否拥有指定能力
+    ///
+    /// # 参数
+    ///
+    /// * `capability` - 需要的能力
+    ///
+    /// # 返回值
+    ///
+    /// 拥有该能力返回 `Ok(())`，否则记录本次请求并返回权限错误
+    pub fn require_capability(&self, capability: PluginCapability) -> PluginResult<()> {
+        if self.capabilities.contains(capability) {
+            return Ok(());
+        }
+
+        if let Ok(mut denied) = self.denied_requests.lock() {
+            denied.push(capability);
+        }
+
+        Err(ErrorInfo::new(8011, format!("插件 {} 未声明能力: {}", self.plugin_name, capability.name()))
+            .with_category(ErrorCategory::Authorization)
+            .with_severity(ErrorSeverity::Warning))
+    }
+
+    /// 发送事件
+    ///
+    /// 需要 [`PluginCapability::EmitEvents`] 能力
+    ///
+    /// # 参数
+    ///
+    /// * `event_name` - 事件名称
+    /// * `data` - 事件数据
+    ///
+    /// # 返回值
+    ///
+    /// 事件进入待分发队列返回 `Ok(())`，缺少能力时返回权限错误
+    pub fn emit(&self, event_name: &str, data: Vec<u8>) -> PluginResult<()> {
+        self.require_capability(PluginCapability::EmitEvents)?;
+        self.event_bus.publish(Event::new(event_name.to_string(), data));
+        Ok(())
+    }
+
+    /// 注册钩子
+    ///
+    /// 需要 [`PluginCapability::RegisterHooks`] 能力
+    ///
+    /// # 参数
+    ///
+    /// * `point` - 钩子点
+    /// * `hook` - 钩子处理器
+    ///
+    /// # 返回值
+    ///
+    /// 注册成功返回 `Ok(())`，缺少能力时返回权限错误
+    pub fn register_hook(&self, point: HookPoint, hook: Arc<dyn Hook>) -> PluginResult<()> {
+        self.require_capability(PluginCapability::RegisterHooks)?;
+        self.hook_registry.register(point, hook);
+        Ok(())
+    }
+
+    /// 取出被拒绝的能力请求
+    pub(crate) fn take_denied_requests(&self) -> Vec<PluginCapability> {
+        self.denied_requests
+            .lock()
+            .map(|mut denied| std::mem::take(&mut *denied))
+            .unwrap_or_default()
     }
     
     /// 存储数据
@@ -143,4 +223,20 @@ mod tests {
         assert!(!ctx.has_data("key1"));
         assert!(!ctx.has_data("key2"));
     }
+
+    #[test]
+    fn test_plugin_context_emit_requires_capability() {
+        let event_bus = Arc::new(EventBus::new());
+        let hook_registry = Arc::new(HookRegistry::new());
+        let ctx = PluginContext::new("test".to_string(), Arc::clone(&event_bus), hook_registry);
+
+        let result = ctx.emit("test.event", b"data".to_vec());
+        assert_eq!(result.expect_err("缺少能力时应拒绝发送").code(), 8011);
+        assert!(event_bus.take_pending().is_empty());
+        assert_eq!(ctx.take_denied_requests(), vec![PluginCapability::EmitEvents]);
+
+        let ctx = ctx.with_capabilities(PluginCapabilities::new().with(PluginCapability::EmitEvents));
+        ctx.emit("test.event", b"data".to_vec()).expect("发送事件失败");
+        assert_eq!(event_bus.take_pending().len(), 1);
+    }
 }
@@ -4,6 +4,8 @@
 
 use dashmap::DashMap;
 use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
 use tracing::debug;
 
 /// 事件优先级
@@ -59,6 +61,8 @@ impl Event {
 pub struct EventBus {
     /// 事件订阅表: 事件名 -> 订阅者列表
     subscriptions: DashMap<String, Vec<String>>,
+    /// 插件发布、等待分发的事件
+    pending: Mutex<VecDeque<Event>>,
 }
 
 impl EventBus {
@@ -66,6 +70,7 @@ impl EventBus {
     pub fn new() -> Self {
         Self {
             subscriptions: DashMap::new(),
+            pending: Mutex::new(VecDeque::new()),
         }
     }
     
@@ -113,6 +118,29 @@ impl EventBus {
             .unwrap_or_default()
     }
     
+    /// 发布事件
+    ///
+    /// 事件进入待分发队列，由插件管理器的
+    /// [`crate::PluginManager::dispatch_pending_events`] 分发给订阅者
+    ///
+    /// # 参数
+    ///
+    /// * `event` - 要发布的事件
+    pub fn publish(&self, event: Event) {
+        debug!("发布事件: {}", event.name);
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push_back(event);
+        }
+    }
+
+    /// 取出所有待分发的事件
+    pub fn take_pending(&self) -> Vec<Event> {
+        self.pending
+            .lock()
+            .map(|mut pending| pending.drain(..).collect())
+            .unwrap_or_default()
+    }
+
     /// 清除所有订阅
     pub fn clear(&self) {
         self.subscriptions.clear();
@@ -120,12 +120,14 @@ pub mod lifecycle;
 pub mod event_bus;
 pub mod hooks;
 pub mod context;
+pub mod capabilities;
 
 // 重新导出主要类型
 pub use lifecycle::{PluginState, PluginMetadata};
 pub use event_bus::{EventBus, Event, EventPriority};
 pub use hooks::{HookPoint, HookRegistry};
 pub use context::PluginContext;
+pub use capabilities::{PluginCapability, PluginCapabilities};
 
 /// 插件结果类型
 pub type PluginResult<T> = std::result::Result<T, ErrorInfo>;
@@ -155,6 +157,13 @@ pub trait Plugin: Send + Sync {
     fn dependencies(&self) -> Vec<String> {
         Vec::new()
     }
+
+    /// 获取插件需要的能力
+    ///
+    /// 默认不申请任何能力，未声明的操作（如发送事件、注册钩子）会被拒绝
+    fn capabilities(&self) -> Vec<PluginCapability> {
+        Vec::new()
+    }
     
     /// 初始化插件
     ///
@@ -241,6 +250,8 @@ pub struct PluginStats {
     avg_event_time_us: u64,
     /// 最后活跃时间
     last_active: SystemTime,
+    /// 被拒绝的能力请求次数
+    denied_capability_requests: u64,
 }
 
 impl Default for PluginStats {
@@ -250,6 +261,7 @@ impl Default for PluginStats {
             event_count: 0,
             avg_event_time_us: 0,
             last_active: SystemTime::now(),
+            denied_capability_requests: 0,
         }
     }
 }
@@ -284,16 +296,19 @@ impl PluginManager {
                 .with_severity(ErrorSeverity::Warning));
         }
         
-        // 创建插件上下文
+        // 创建插件上下文，只授予插件声明的能力
+        let capabilities: PluginCapabilities = plugin.capabilities().into_iter().collect();
         let mut context = PluginContext::new(
             name.clone(),
             Arc::clone(&self.event_bus),
             Arc::clone(&self.hook_registry),
-        );
+        ).with_capabilities(capabilities);
         
         // 初始化插件
         let start = SystemTime::now();
-        plugin.on_init(&mut context).await
+        let init_result = plugin.on_init(&mut context).await;
+        let denied_requests = log_denied_requests(&name, &context);
+        init_result
             .map_err(|e| ErrorInfo::new(8002, format!("插件初始化失败: {}", e))
                 .with_category(ErrorCategory::System))?;
         
@@ -317,6 +332,7 @@ impl PluginManager {
         
         let mut stats = PluginStats::default();
         stats.init_time_ms = init_time;
+        stats.denied_capability_requests = denied_requests;
         
         // 保存插件
         let entry = PluginEntry {
@@ -353,20 +369,22 @@ impl PluginManager {
             let context_ptr = &mut entry.context as *mut PluginContext;
             let plugin_ptr = &mut entry.plugin as *mut Box<dyn Plugin>;
             
-            unsafe {
+            let result = unsafe {
                 (*plugin_ptr).on_stop(&mut *context_ptr).await
-                    .map_err(|e| ErrorInfo::new(8004, format!("插件停止失败: {}", e)))?;
-            }
+            };
+            entry.stats.denied_capability_requests += log_denied_requests(name, &entry.context);
+            result.map_err(|e| ErrorInfo::new(8004, format!("插件停止失败: {}", e)))?;
         }
         
         // 清理插件
         let context_ptr = &mut entry.context as *mut PluginContext;
         let plugin_ptr = &mut entry.plugin as *mut Box<dyn Plugin>;
         
-        unsafe {
+        let result = unsafe {
             (*plugin_ptr).on_cleanup(&mut *context_ptr).await
-                .map_err(|e| ErrorInfo::new(8005, format!("插件清理失败: {}", e)))?;
-        }
+        };
+        entry.stats.denied_capability_requests += log_denied_requests(name, &entry.context);
+        result.map_err(|e| ErrorInfo::new(8005, format!("插件清理失败: {}", e)))?;
         
         entry.state = PluginState::Unloaded;
         
@@ -395,10 +413,11 @@ impl PluginManager {
                     let context_ptr = &mut entry.context as *mut PluginContext;
                     let plugin_ptr = &mut entry.plugin as *mut Box<dyn Plugin>;
                     
-                    unsafe {
+                    let result = unsafe {
                         (*plugin_ptr).on_start(&mut *context_ptr).await
-                            .map_err(|e| ErrorInfo::new(8007, format!("插件启动失败: {}", e)))?;
-                    }
+                    };
+                    entry.stats.denied_capability_requests += log_denied_requests(&name, &entry.context);
+                    result.map_err(|e| ErrorInfo::new(8007, format!("插件启动失败: {}", e)))?;
                     
                     entry.state = PluginState::Running;
                     info!("插件已启动: {}", name);
@@ -431,10 +450,11 @@ impl PluginManager {
                     
                     // Safety: We're the only ones with access to this entry, and we're not
                     // creating any references that outlive this scope
-                    unsafe {
+                    let result = unsafe {
                         (*plugin_ptr).on_stop(&mut *context_ptr).await
-                            .map_err(|e| ErrorInfo::new(8008, format!("插件停止失败: {}", e)))?;
-                    }
+                    };
+                    entry.stats.denied_capability_requests += log_denied_requests(&name, &entry.context);
+                    result.map_err(|e| ErrorInfo::new(8008, format!("插件停止失败: {}", e)))?;
                     
                     entry.state = PluginState::Stopped;
                     info!("插件已停止: {}", name);
@@ -473,6 +493,7 @@ impl PluginManager {
                     let result = unsafe {
                         (*plugin_ptr).on_event(event_name, data, &mut *context_ptr).await
                     };
+                    entry.stats.denied_capability_requests += log_denied_requests(&plugin_name, &entry.context);
                     
                     match result {
                         Ok(_) => {
@@ -499,6 +520,22 @@ impl PluginManager {
         Ok(())
     }
     
+    /// 分发插件发布的事件
+    ///
+    /// 插件通过 [`PluginContext::emit`] 发布的事件进入事件总线的待分发队列，
+    /// 调用此方法将其依次发送给订阅者
+    ///
+    /// # 返回值
+    ///
+    /// 返回分发的事件数量
+    pub async fn dispatch_pending_events(&self) -> PluginResult<usize> {
+        let events = self.event_bus.take_pending();
+        for event in &events {
+            self.emit_event(&event.name, &event.data).await?;
+        }
+        Ok(events.len())
+    }
+    
     /// 获取插件列表
     pub fn list_plugins(&self) -> Vec<PluginMetadata> {
         self.plugins.iter()
@@ -579,6 +616,19 @@ impl PluginManager {
     }
 }
 
+/// 记录插件被拒绝的能力请求
+///
+/// # 返回值
+///
+/// 返回被拒绝的请求数量
+fn log_denied_requests(name: &str, context: &PluginContext) -> u64 {
+    let denied = context.take_denied_requests();
+    for capability in &denied {
+        warn!("插件 {} 请求未声明的能力被拒绝: {}", name, capability.name());
+    }
+    denied.len() as u64
+}
+
 impl Default for PluginManager {
     fn default() -> Self {
         Self::new()
@@ -650,4 +700,91 @@ mod tests {
         let state = manager.get_plugin_state("test");
         assert_eq!(state, Some(PluginState::Stopped));
     }
+    
+    /// 收到事件时尝试转发事件的插件
+    struct RelayPlugin {
+        name: String,
+        capabilities: Vec<PluginCapability>,
+    }
+    
+    #[async_trait]
+    impl Plugin for RelayPlugin {
+        fn name(&self) -> &str {
+            &self.name
+        }
+        
+        fn version(&self) -> &str {
+            "1.0.0"
+        }
+        
+        fn capabilities(&self) -> Vec<PluginCapability> {
+            self.capabilities.clone()
+        }
+        
+        fn subscribed_events(&self) -> Vec<String> {
+            vec!["test.input".to_string()]
+        }
+        
+        async fn on_event(&mut self, _event: &str, data: &[u8], ctx: &mut PluginContext) -> PluginResult<()> {
+            ctx.emit("test.relayed", data.to_vec())
+        }
+
+        async fn on_stop(&mut self, ctx: &mut PluginContext) -> PluginResult<()> {
+            ctx.emit("test.stopped", Vec::new())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_plugin_without_emit_capability_is_blocked() {
+        let manager = PluginManager::new();
+        let plugin = Box::new(RelayPlugin {
+            name: "relay".to_string(),
+            capabilities: Vec::new(),
+        });
+        
+        manager.register(plugin).await.expect("注册失败");
+        manager.start_all().await.expect("启动失败");
+        manager.emit_event("test.input", b"data").await.expect("发送事件失败");
+        
+        // 事件未进入待分发队列，拒绝次数被记录
+        assert_eq!(manager.dispatch_pending_events().await.expect("分发事件失败"), 0);
+        let stats = manager.get_plugin_stats("relay").expect("插件统计不存在");
+        assert_eq!(stats.denied_capability_requests, 1);
+        assert_eq!(stats.event_count, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_denied_requests_counted_when_unregistering() {
+        let manager = PluginManager::new();
+        let plugin = Box::new(RelayPlugin {
+            name: "relay".to_string(),
+            capabilities: Vec::new(),
+        });
+
+        manager.register(plugin).await.expect("注册失败");
+        manager.start_all().await.expect("启动失败");
+
+        // 停止时发送事件被拒绝，卸载失败，插件保留且拒绝次数被记录
+        assert!(manager.unregister("relay").await.is_err());
+        let stats = manager.get_plugin_stats("relay").expect("插件统计不存在");
+        assert_eq!(stats.denied_capability_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_with_emit_capability_dispatches() {
+        let manager = PluginManager::new();
+        let plugin = Box::new(RelayPlugin {
+            name: "relay".to_string(),
+            capabilities: vec![PluginCapability::EmitEvents],
+        });
+        
+        manager.register(plugin).await.expect("注册失败");
+        manager.start_all().await.expect("启动失败");
+        manager.emit_event("test.input", b"data").await.expect("发送事件失败");
+        
+        assert_eq!(manager.dispatch_pending_events().await.expect("分发事件失败"), 1);
+        let stats = manager.get_plugin_stats("relay").expect("插件统计不存在");
+        assert_eq!(stats.denied_capability_requests, 0);
+        assert_eq!(stats.event_count, 1);
+    }
 }
@@ -0,0 +1,405 @@
+//! # 存储归档
+//!
+//! 把整个存储导出为一个可移植的归档，用于迁移节点。归档包含对象存储、云存储、消息和剪切板中的全部数据。
+//! 对象的标签作为单独的条目紧跟在对象之后。
+//! 归档是一个zstd压缩流：开头是魔数，之后依次是各个条目，末尾是清单。
+//! 每个条目由长度前缀的JSON头（类型、键、大小、SHA-256哈希）和内容组成，清单列出所有条目的头。
+//!
+//! 导出和导入都按块读写条目内容，不会把整个对象或文件加载到内存。
+//! 导入时条目先暂存到磁盘并校验哈希，读到清单并核对完整后才写入存储。
+
+use async_compression::tokio::bufread::ZstdDecoder;
+use async_compression::tokio::write::ZstdEncoder;
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::clipboard::ClipboardConflict;
+use crate::snapshot::{content_hash, is_plain_key};
+use crate::StorageResult;
+
+/// 归档魔数（含格式版本）
+pub const ARCHIVE_MAGIC: &[u8; 8] = b"BEYARCH1";
+
+/// 条目头的最大长度（字节）
+const MAX_ENTRY_HEADER_SIZE: u32 = 64 * 1024;
+
+/// 读写条目内容时每块的字节数
+pub(crate) const ARCHIVE_CHUNK_SIZE: usize = 256 * 1024;
+
+/// 归档条目类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveEntryKind {
+    /// 对象存储中的对象，键为对象ID
+    Object,
+    /// 对象的标签（JSON），键为对象ID，紧跟在对应的对象之后
+    ObjectTags,
+    /// 云存储中的文件，键为文件名
+    CloudFile,
+    /// 消息（JSON），键为消息ID
+    Message,
+    /// 剪切板条目（JSON），键为条目ID
+    ClipboardEntry,
+    /// 归档末尾的清单
+    Manifest,
+}
+
+/// 归档条目头
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// 条目类型
+    pub kind: ArchiveEntryKind,
+    /// 对象ID、文件名、消息ID或剪切板条目ID
+    pub key: String,
+    /// 内容大小（字节）
+    pub size: u64,
+    /// 内容的SHA-256哈希（十六进制）
+    pub hash: String,
+}
+
+/// 归档清单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// 导出归档的设备ID
+    pub device_id: String,
+    /// 导出时间（Unix秒）
+    pub created_at: u64,
+    /// 按写入顺序排列的条目
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl ArchiveManifest {
+    /// 指定类型的条目数量
+    ///
+    /// # 参数
+    ///
+    /// * `kind` - 条目类型
+    pub fn count(&self, kind: ArchiveEntryKind) -> usize {
+        self.entries.iter().filter(|entry| entry.kind == kind).count()
+    }
+}
+
+/// 归档导入结果
+#[derive(Debug, Clone)]
+pub struct ArchiveImport {
+    /// 归档清单
+    pub manifest: ArchiveManifest,
+    /// 与本地条目并发修改、未被覆盖的剪切板条目
+    pub clipboard_conflicts: Vec<ClipboardConflict>,
+}
+
+/// 从归档中读出的一项
+pub(crate) enum ArchiveItem {
+    /// 哈希已校验的条目及其内容在暂存目录中的路径
+    Entry(ArchiveEntry, PathBuf),
+    /// 已核对的清单，归档到此结束
+    Manifest(ArchiveManifest),
+}
+
+/// 归档写入器
+pub(crate) struct ArchiveWriter<W: AsyncWrite + Unpin> {
+    /// zstd压缩流
+    encoder: ZstdEncoder<W>,
+    /// 已写入的条目
+    entries: Vec<ArchiveEntry>,
+    /// 正在写入的条目，以及已写入的字节数和内容哈希
+    current: Option<(ArchiveEntry, u64, Sha256)>,
+}
+
+impl<W: AsyncWrite + Unpin> ArchiveWriter<W> {
+    /// 开始写入归档
+    pub(crate) async fn new(writer: W) -> StorageResult<Self> {
+        let mut encoder = ZstdEncoder::new(writer);
+        encoder.write_all(ARCHIVE_MAGIC).await.map_err(write_error)?;
+        Ok(Self {
+            encoder,
+            entries: Vec::new(),
+            current: None,
+        })
+    }
+
+    /// 写入一个内容已在内存中的条目
+    ///
+    /// # 参数
+    ///
+    /// * `kind` - 条目类型
+    /// * `key` - 条目键
+    /// * `data` - 条目内容
+    pub(crate) async fn write_entry(&mut self, kind: ArchiveEntryKind, key: &str, data: &[u8]) -> StorageResult<()> {
+        self.begin_entry(kind, key, data.len() as u64, content_hash(data)).await?;
+        self.write_data(data).await?;
+        self.end_entry()
+    }
+
+    /// 从读取器分块写入一个条目
+    ///
+    /// # 参数
+    ///
+    /// * `kind` - 条目类型
+    /// * `key` - 条目键
+    /// * `size` - 内容大小
+    /// * `hash` - 内容的SHA-256哈希
+    /// * `reader` - 内容来源，最多读取 `size` 字节
+    pub(crate) async fn write_entry_from<R: AsyncRead + Unpin>(
+        &mut self,
+        kind: ArchiveEntryKind,
+        key: &str,
+        size: u64,
+        hash: String,
+        reader: R,
+    ) -> StorageResult<()> {
+        self.begin_entry(kind, key, size, hash).await?;
+        let mut reader = reader.take(size);
+        let mut buffer = vec![0u8; ARCHIVE_CHUNK_SIZE];
+        loop {
+            let read = reader.read(&mut buffer).await.map_err(write_error)?;
+            if read == 0 {
+                break;
+            }
+            self.write_data(&buffer[..read]).await?;
+        }
+        self.end_entry()
+    }
+
+    /// 写入条目头，之后用 [`ArchiveWriter::write_data`] 分块写入内容
+    ///
+    /// # 参数
+    ///
+    /// * `kind` - 条目类型
+    /// * `key` - 条目键
+    /// * `size` - 内容大小
+    /// * `hash` - 内容的SHA-256哈希
+    pub(crate) async fn begin_entry(&mut self, kind: ArchiveEntryKind, key: &str, size: u64, hash: String) -> StorageResult<()> {
+        if self.current.is_some() {
+            return Err(invalid_archive("上一个条目尚未写完".to_string()));
+        }
+        let entry = ArchiveEntry {
+            kind,
+            key: key.to_string(),
+            size,
+            hash,
+        };
+        self.write_header(&entry).await?;
+        self.current = Some((entry, 0, Sha256::new()));
+        Ok(())
+    }
+
+    /// 写入当前条目的一块内容
+    pub(crate) async fn write_data(&mut self, data: &[u8]) -> StorageResult<()> {
+        let Some((entry, written, hasher)) = self.current.as_mut() else {
+            return Err(invalid_archive("没有正在写入的条目".to_string()));
+        };
+        *written += data.len() as u64;
+        if *written > entry.size {
+            return Err(changed_during_export(entry));
+        }
+        hasher.update(data);
+        self.encoder.write_all(data).await.map_err(write_error)
+    }
+
+    /// 结束当前条目
+    ///
+    /// 写入的内容与条目头的大小或哈希不一致时返回错误，说明内容在导出期间被修改
+    pub(crate) fn end_entry(&mut self) -> StorageResult<()> {
+        let Some((entry, written, hasher)) = self.current.take() else {
+            return Err(invalid_archive("没有正在写入的条目".to_string()));
+        };
+        if written != entry.size || format!("{:x}", hasher.finalize()) != entry.hash {
+            return Err(changed_during_export(&entry));
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// 写入清单并结束压缩流
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 导出归档的设备ID
+    /// * `created_at` - 导出时间（Unix秒）
+    ///
+    /// # 返回值
+    ///
+    /// 返回写入的清单或错误
+    pub(crate) async fn finish(mut self, device_id: String, created_at: u64) -> StorageResult<ArchiveManifest> {
+        let manifest = ArchiveManifest {
+            device_id,
+            created_at,
+            entries: std::mem::take(&mut self.entries),
+        };
+        let data = serde_json::to_vec(&manifest).map_err(format_error)?;
+        let entry = ArchiveEntry {
+            kind: ArchiveEntryKind::Manifest,
+            key: String::new(),
+            size: data.len() as u64,
+            hash: content_hash(&data),
+        };
+        self.write_header(&entry).await?;
+        self.encoder.write_all(&data).await.map_err(write_error)?;
+        self.encoder.shutdown().await.map_err(write_error)?;
+        Ok(manifest)
+    }
+
+    /// 写入长度前缀的条目头
+    async fn write_header(&mut self, entry: &ArchiveEntry) -> StorageResult<()> {
+        let header = serde_json::to_vec(entry).map_err(format_error)?;
+        self.encoder.write_all(&(header.len() as u32).to_be_bytes()).await.map_err(write_error)?;
+        self.encoder.write_all(&header).await.map_err(write_error)
+    }
+}
+
+/// 归档读取器
+pub(crate) struct ArchiveReader<R: AsyncRead + Unpin> {
+    /// zstd解压流
+    decoder: ZstdDecoder<BufReader<R>>,
+    /// 暂存条目内容的目录
+    staging_dir: PathBuf,
+    /// 已读出的条目
+    entries: Vec<ArchiveEntry>,
+}
+
+impl<R: AsyncRead + Unpin> ArchiveReader<R> {
+    /// 打开归档并校验魔数
+    ///
+    /// # 参数
+    ///
+    /// * `reader` - 归档读取来源
+    /// * `staging_dir` - 暂存条目内容的目录，需已存在，由调用方负责清理
+    pub(crate) async fn open(reader: R, staging_dir: &Path) -> StorageResult<Self> {
+        let mut decoder = ZstdDecoder::new(BufReader::new(reader));
+        let mut magic = [0u8; 8];
+        decoder.read_exact(&mut magic).await.map_err(read_error)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(invalid_archive("不是BEY存储归档或版本不受支持".to_string()));
+        }
+        Ok(Self {
+            decoder,
+            staging_dir: staging_dir.to_path_buf(),
+            entries: Vec::new(),
+        })
+    }
+
+    /// 读出下一项
+    ///
+    /// 条目内容分块写入暂存目录，大小和哈希与条目头一致才返回；
+    /// 读到清单时核对清单与已读出的条目一致，并确认清单之后没有多余的数据
+    pub(crate) async fn next_item(&mut self) -> StorageResult<ArchiveItem> {
+        let mut len = [0u8; 4];
+        self.decoder.read_exact(&mut len).await.map_err(read_error)?;
+        let len = u32::from_be_bytes(len);
+        if len > MAX_ENTRY_HEADER_SIZE {
+            return Err(invalid_archive(format!("条目头过长: {} 字节", len)));
+        }
+        let mut header = vec![0u8; len as usize];
+        self.decoder.read_exact(&mut header).await.map_err(read_error)?;
+        let entry: ArchiveEntry = serde_json::from_slice(&header)
+            .map_err(|e| invalid_archive(format!("条目头无法解析: {}", e)))?;
+
+        if entry.kind != ArchiveEntryKind::Manifest {
+            if !is_plain_key(&entry.key) {
+                return Err(invalid_archive(format!("条目键无效: {}", entry.key)));
+            }
+            let path = self.staging_dir.join(self.entries.len().to_string());
+            self.stage_content(&entry, &path).await?;
+            self.entries.push(entry.clone());
+            return Ok(ArchiveItem::Entry(entry, path));
+        }
+
+        // 按实际读到的数据增长缓冲区，损坏的大小不会导致一次性分配
+        let mut data = Vec::new();
+        (&mut self.decoder).take(entry.size).read_to_end(&mut data).await.map_err(read_error)?;
+        if data.len() as u64 != entry.size || content_hash(&data) != entry.hash {
+            return Err(hash_mismatch(&entry));
+        }
+        let manifest: ArchiveManifest = serde_json::from_slice(&data)
+            .map_err(|e| invalid_archive(format!("清单无法解析: {}", e)))?;
+        if manifest.entries != self.entries {
+            return Err(invalid_archive(format!(
+                "清单列出 {} 个条目，归档中读到 {} 个条目或内容不一致",
+                manifest.entries.len(),
+                self.entries.len(),
+            )));
+        }
+        let mut trailing = [0u8; 1];
+        if self.decoder.read(&mut trailing).await.map_err(read_error)? != 0 {
+            return Err(invalid_archive("清单之后存在多余的数据".to_string()));
+        }
+        Ok(ArchiveItem::Manifest(manifest))
+    }
+
+    /// 把条目内容分块写入暂存文件并校验大小和哈希
+    async fn stage_content(&mut self, entry: &ArchiveEntry, path: &Path) -> StorageResult<()> {
+        let mut file = fs::File::create(path).await.map_err(staging_error)?;
+        let mut content = (&mut self.decoder).take(entry.size);
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buffer = vec![0u8; ARCHIVE_CHUNK_SIZE];
+        loop {
+            let read = content.read(&mut buffer).await.map_err(read_error)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            file.write_all(&buffer[..read]).await.map_err(staging_error)?;
+            size += read as u64;
+        }
+        file.flush().await.map_err(staging_error)?;
+
+        if size != entry.size || format!("{:x}", hasher.finalize()) != entry.hash {
+            return Err(hash_mismatch(entry));
+        }
+        Ok(())
+    }
+}
+
+/// 条目内容与条目头不符
+fn hash_mismatch(entry: &ArchiveEntry) -> ErrorInfo {
+    ErrorInfo::new(6603, format!("归档条目内容与哈希不符: {:?} {}", entry.kind, entry.key))
+        .with_category(ErrorCategory::Validation)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 导出的内容与开始写入时的大小或哈希不符
+fn changed_during_export(entry: &ArchiveEntry) -> ErrorInfo {
+    ErrorInfo::new(6601, format!("写入归档失败: {:?} {} 在导出期间被修改", entry.kind, entry.key))
+        .with_category(ErrorCategory::Io)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 读写暂存文件失败
+pub(crate) fn staging_error(e: std::io::Error) -> ErrorInfo {
+    ErrorInfo::new(6606, format!("暂存归档条目失败: {}", e))
+        .with_category(ErrorCategory::FileSystem)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 写入归档失败
+fn write_error(e: std::io::Error) -> ErrorInfo {
+    ErrorInfo::new(6601, format!("写入归档失败: {}", e))
+        .with_category(ErrorCategory::Io)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 读取归档失败（含归档被截断）
+fn read_error(e: std::io::Error) -> ErrorInfo {
+    ErrorInfo::new(6602, format!("读取归档失败: {}", e))
+        .with_category(ErrorCategory::Io)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 归档格式无效
+fn invalid_archive(message: String) -> ErrorInfo {
+    ErrorInfo::new(6604, format!("归档格式无效: {}", message))
+        .with_category(ErrorCategory::Validation)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 序列化归档内容失败
+pub(crate) fn format_error(e: serde_json::Error) -> ErrorInfo {
+    ErrorInfo::new(6605, format!("序列化归档内容失败: {}", e))
+        .with_category(ErrorCategory::Parse)
+        .with_severity(ErrorSeverity::Error)
+}
@@ -1,18 +1,29 @@
 //! # 剪切板同步模块
 //!
 //! 提供剪切板数据的同步功能，支持差异同步、群组同步、点对点同步。
-//! 使用sled数据库进行持久化存储，通过bey-net模块进行实时同步。
+//! 使用键值存储后端（默认sled）进行持久化存储，通过bey-net模块进行实时同步。
 
 use error::{ErrorInfo, ErrorCategory};
-use sled::Db;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use serde::{Deserialize, Serialize};
-use tracing::{info, debug};
+use tracing::{info, debug, warn};
+
+use crate::kv_backend::{expires_after, now_millis, spawn_expiry_sweeper, sweep_expired, KvBackend, SledBackend};
+use crate::object_storage::ObjectStorage;
+use crate::snapshot::content_hash;
+use crate::thumbnail::{generate_thumbnail, is_image_content_type, DEFAULT_THUMBNAIL_MAX_DIMENSION};
 
 /// 剪切板同步结果类型
 pub type ClipboardResult<T> = std::result::Result<T, ErrorInfo>;
 
+/// 由剪切板写入对象存储的文件的键前缀
+///
+/// 只有带此前缀的对象归剪切板所有，不再被任何条目引用时删除
+pub const CLIPBOARD_OBJECT_PREFIX: &str = "clipboard-";
+
 /// 剪切板条目
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ClipboardEntry {
@@ -28,6 +39,61 @@ pub struct ClipboardEntry {
     pub timestamp: u64,
     /// 版本号（用于冲突解决）
     pub version: u64,
+    /// 向量时钟（设备ID -> 该设备对条目的修改次数），用于检测并发修改
+    #[serde(default)]
+    pub clock: BTreeMap<String, u64>,
+    /// 过期时间（Unix毫秒时间戳），`None` 表示永不过期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// 图片条目的缩略图（PNG），非图片条目为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<Vec<u8>>,
+    /// 文件引用条目引用的对象存储键，此时内容不内嵌在条目中，读取时从对象存储解析
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_ref: Option<String>,
+}
+
+impl ClipboardEntry {
+    /// 条目是否为文件引用
+    pub fn is_file_ref(&self) -> bool {
+        self.file_ref.is_some()
+    }
+
+    /// 条目是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(now_millis())
+    }
+
+    /// 条目在指定时间（Unix毫秒时间戳）是否已过期
+    fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// 条目合并冲突
+///
+/// 本地和远程条目由不同设备并发修改（向量时钟互不包含）时产生，
+/// 由调用方通过 [`ClipboardManager::resolve_conflict`] 选择解决方式
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardConflict {
+    /// 本地条目
+    pub local: ClipboardEntry,
+    /// 远程条目
+    pub remote: ClipboardEntry,
+}
+
+/// 冲突解决方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// 保留本地内容
+    KeepLocal,
+    /// 采用远程内容
+    KeepRemote,
+    /// 两者都保留，远程内容另存为新条目
+    KeepBoth,
+    /// 保留时间戳较新的内容，时间戳相同时保留来源设备ID较大的内容。
+    /// 双方设备各自解决同一冲突时选择相同的内容，适合自动解决
+    KeepNewest,
 }
 
 /// 剪切板同步模式
@@ -60,10 +126,16 @@ pub enum ClipboardEvent {
 pub struct ClipboardManager {
     /// 本地设备ID
     device_id: String,
-    /// sled数据库
-    db: Arc<Db>,
+    /// 键值存储后端
+    db: Arc<dyn KvBackend>,
     /// 最大条目数
     max_entries: usize,
+    /// 缩略图的最大边长
+    thumbnail_max_dimension: u32,
+    /// 保存文件引用条目内容的对象存储
+    objects: Option<Arc<ObjectStorage>>,
+    /// 串行化文件对象的写入引用与回收，避免回收刚写入尚未被引用的对象
+    gc_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl ClipboardManager {
@@ -86,7 +158,7 @@ impl ClipboardManager {
         }
 
         // 打开sled数据库
-        let db = sled::open(&db_path)
+        let db = SledBackend::open(&db_path)
             .map_err(|e| ErrorInfo::new(6202, format!("打开数据库失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
@@ -95,9 +167,62 @@ impl ClipboardManager {
             device_id,
             db: Arc::new(db),
             max_entries: 1000,
+            thumbnail_max_dimension: DEFAULT_THUMBNAIL_MAX_DIMENSION,
+            objects: None,
+            gc_lock: Arc::new(tokio::sync::Mutex::new(())),
         })
     }
 
+    /// 使用指定的存储后端创建剪切板管理器
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 本地设备ID
+    /// * `backend` - 键值存储后端
+    pub fn with_backend(device_id: String, backend: Arc<dyn KvBackend>) -> Self {
+        Self {
+            device_id,
+            db: backend,
+            max_entries: 1000,
+            thumbnail_max_dimension: DEFAULT_THUMBNAIL_MAX_DIMENSION,
+            objects: None,
+            gc_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// 设置最多保留的条目数，超出时删除最旧的条目
+    ///
+    /// # 参数
+    ///
+    /// * `max_entries` - 最大条目数（至少为1）
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    /// 设置图片条目缩略图的最大边长
+    ///
+    /// # 参数
+    ///
+    /// * `max_dimension` - 最大边长（像素，至少为1）
+    pub fn with_thumbnail_max_dimension(mut self, max_dimension: u32) -> Self {
+        self.thumbnail_max_dimension = max_dimension.max(1);
+        self
+    }
+
+    /// 关联保存文件引用条目内容的对象存储
+    ///
+    /// 关联后，键以 [`CLIPBOARD_OBJECT_PREFIX`] 开头的对象在删除、覆盖、淘汰或过期清理
+    /// 使最后一个引用消失时一并删除
+    ///
+    /// # 参数
+    ///
+    /// * `objects` - 对象存储
+    pub fn with_object_storage(mut self, objects: Arc<ObjectStorage>) -> Self {
+        self.objects = Some(objects);
+        self
+    }
+
     /// 添加剪切板条目
     ///
     /// # 参数
@@ -109,19 +234,105 @@ impl ClipboardManager {
     ///
     /// 返回条目ID或错误
     pub async fn add_entry(&self, content: Vec<u8>, content_type: String) -> ClipboardResult<String> {
+        self.store_new_entry(content, content_type, None, None).await
+    }
+
+    /// 添加文件引用条目
+    ///
+    /// 条目只记录对象存储键，不内嵌文件内容，避免大文件在剪切板和对象存储中各存一份
+    ///
+    /// # 参数
+    ///
+    /// * `object_key` - 文件在对象存储中的键
+    /// * `content_type` - 内容类型
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目ID或错误
+    pub async fn add_file_ref(&self, object_key: String, content_type: String) -> ClipboardResult<String> {
+        self.store_new_entry(Vec::new(), content_type, None, Some(object_key)).await
+    }
+
+    /// 以文件引用的方式添加剪切板条目
+    ///
+    /// 对象存储中已有相同内容的对象时直接引用该对象，否则以内容哈希为键写入一份，
+    /// 剪切板条目本身不内嵌文件内容
+    ///
+    /// # 参数
+    ///
+    /// * `content` - 文件内容
+    /// * `content_type` - 内容类型
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目ID，未关联对象存储时返回错误
+    pub async fn add_file(&self, content: &[u8], content_type: String) -> ClipboardResult<String> {
+        let objects = self.object_storage()?;
+        let (id, evicted) = {
+            let _gc = self.gc_lock.lock().await;
+            let object_key = Self::store_file_object(objects, content).await?;
+            self.insert_new_entry(Vec::new(), content_type, None, Some(object_key)).await?
+        };
+        self.release_objects(evicted).await;
+        Ok(id)
+    }
+
+    /// 添加临时剪切板条目
+    ///
+    /// 条目在存活时间过后不可读取，并由过期清理删除
+    ///
+    /// # 参数
+    ///
+    /// * `content` - 内容
+    /// * `content_type` - 内容类型
+    /// * `ttl` - 存活时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目ID或错误
+    pub async fn add_entry_ephemeral(&self, content: Vec<u8>, content_type: String, ttl: Duration) -> ClipboardResult<String> {
+        self.store_new_entry(content, content_type, Some(expires_after(ttl)), None).await
+    }
+
+    /// 创建并存储本设备的新条目
+    async fn store_new_entry(
+        &self,
+        content: Vec<u8>,
+        content_type: String,
+        expires_at: Option<u64>,
+        file_ref: Option<String>,
+    ) -> ClipboardResult<String> {
+        let (id, evicted) = self.insert_new_entry(content, content_type, expires_at, file_ref).await?;
+        self.release_objects(evicted).await;
+        Ok(id)
+    }
+
+    /// 写入本设备的新条目，超出条目上限时淘汰最旧的条目
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目ID和被淘汰条目引用的对象键
+    async fn insert_new_entry(
+        &self,
+        content: Vec<u8>,
+        content_type: String,
+        expires_at: Option<u64>,
+        file_ref: Option<String>,
+    ) -> ClipboardResult<(String, Option<String>)> {
         let id = uuid::Uuid::new_v4().to_string();
-        let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+        let (content, thumbnail) = self.render_thumbnail(content, &content_type).await;
 
         let entry = ClipboardEntry {
             id: id.clone(),
             content,
             content_type,
             source_device_id: self.device_id.clone(),
-            timestamp,
+            timestamp: Self::now_secs(),
             version: 1,
+            clock: BTreeMap::from([(self.device_id.clone(), 1)]),
+            expires_at,
+            thumbnail,
+            file_ref,
         };
 
         // 序列化并存储
@@ -129,21 +340,55 @@ impl ClipboardManager {
             .map_err(|e| ErrorInfo::new(6203, format!("序列化失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
-        self.db.insert(id.as_bytes(), entry_bytes)
+        self.db.put(id.as_bytes(), entry_bytes)
             .map_err(|e| ErrorInfo::new(6204, format!("存储失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
         // 限制条目数量
+        let mut evicted = None;
         let count = self.db.len();
         if count > self.max_entries {
             // 删除最旧的条目
             if let Some(oldest_key) = self.find_oldest_entry_key() {
-                let _ = self.db.remove(oldest_key);
+                if let Ok(Some(bytes)) = self.db.delete(&oldest_key) {
+                    evicted = entry_file_ref(&bytes);
+                }
             }
         }
 
         debug!("添加剪切板条目: {}", id);
-        Ok(id)
+        Ok((id, evicted))
+    }
+
+    /// 修改本地剪切板条目
+    ///
+    /// 版本号和本设备的向量时钟计数加一，修改后的条目应同步给其他设备
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 条目ID
+    /// * `content` - 新内容
+    /// * `content_type` - 新内容类型
+    ///
+    /// # 返回值
+    ///
+    /// 返回修改后的条目或错误
+    pub async fn update_entry(&self, id: &str, content: Vec<u8>, content_type: String) -> ClipboardResult<ClipboardEntry> {
+        let mut entry = self.get_entry(id).await?;
+        let (content, thumbnail) = self.render_thumbnail(content, &content_type).await;
+        entry.content = content;
+        entry.content_type = content_type;
+        entry.thumbnail = thumbnail;
+        entry.file_ref = None;
+        entry.source_device_id = self.device_id.clone();
+        entry.timestamp = Self::now_secs();
+        entry.version += 1;
+        *entry.clock.entry(self.device_id.clone()).or_insert(0) += 1;
+
+        let released = self.store_entry(&entry)?;
+        self.release_objects(released).await;
+        debug!("修改剪切板条目: {}", id);
+        Ok(entry)
     }
 
     /// 获取剪切板条目
@@ -154,7 +399,7 @@ impl ClipboardManager {
     ///
     /// # 返回值
     ///
-    /// 返回条目或错误
+    /// 返回条目或错误，已过期但尚未清理的条目视为不存在
     pub async fn get_entry(&self, id: &str) -> ClipboardResult<ClipboardEntry> {
         let entry_bytes = self.db.get(id.as_bytes())
             .map_err(|e| ErrorInfo::new(6205, format!("查询失败: {}", e))
@@ -166,9 +411,51 @@ impl ClipboardManager {
             .map_err(|e| ErrorInfo::new(6207, format!("反序列化失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
+        if entry.is_expired() {
+            return Err(ErrorInfo::new(6206, format!("剪切板条目已过期: {}", id))
+                .with_category(ErrorCategory::Storage));
+        }
+
         Ok(entry)
     }
 
+    /// 获取条目的缩略图
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 条目ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回图片条目的PNG缩略图，条目不存在、已过期或不是图片时返回 `None`
+    pub async fn get_thumbnail(&self, id: &str) -> Option<Vec<u8>> {
+        self.get_entry(id).await.ok()?.thumbnail
+    }
+
+    /// 为图片内容生成缩略图
+    ///
+    /// 解码和缩放在阻塞线程中执行，返回原内容和缩略图
+    async fn render_thumbnail(&self, content: Vec<u8>, content_type: &str) -> (Vec<u8>, Option<Vec<u8>>) {
+        if !is_image_content_type(content_type) {
+            return (content, None);
+        }
+
+        let max_dimension = self.thumbnail_max_dimension;
+        let content = Arc::new(content);
+        let task_content = Arc::clone(&content);
+        let thumbnail = match tokio::task::spawn_blocking(move || generate_thumbnail(&task_content, max_dimension)).await {
+            Ok(thumbnail) => thumbnail,
+            Err(e) => {
+                warn!("缩略图生成任务异常终止: {}", e);
+                None
+            }
+        };
+
+        // 阻塞任务结束后只剩本地引用，不会复制内容
+        let content = Arc::try_unwrap(content).unwrap_or_else(|content| content.as_ref().clone());
+        (content, thumbnail)
+    }
+
     /// 获取最新的剪切板条目
     ///
     /// # 返回值
@@ -177,14 +464,13 @@ impl ClipboardManager {
     pub async fn get_latest(&self) -> Option<ClipboardEntry> {
         let mut latest: Option<ClipboardEntry> = None;
         let mut latest_timestamp = 0u64;
+        let now = now_millis();
 
-        for item in self.db.iter() {
-            if let Ok((_, value)) = item {
-                if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
-                    if entry.timestamp > latest_timestamp {
-                        latest_timestamp = entry.timestamp;
-                        latest = Some(entry);
-                    }
+        for (_, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
+                if entry.timestamp > latest_timestamp && !entry.is_expired_at(now) {
+                    latest_timestamp = entry.timestamp;
+                    latest = Some(entry);
                 }
             }
         }
@@ -199,10 +485,11 @@ impl ClipboardManager {
     /// 返回所有条目的列表
     pub async fn list_entries(&self) -> Vec<ClipboardEntry> {
         let mut entries = Vec::new();
+        let now = now_millis();
 
-        for item in self.db.iter() {
-            if let Ok((_, value)) = item {
-                if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
+        for (_, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
+                if !entry.is_expired_at(now) {
                     entries.push(entry);
                 }
             }
@@ -212,6 +499,21 @@ impl ClipboardManager {
         entries
     }
 
+    /// 是否还有未过期的条目引用指定的对象存储键
+    ///
+    /// # 参数
+    ///
+    /// * `object_key` - 对象存储键
+    ///
+    /// # 返回值
+    ///
+    /// 存在引用该对象的文件引用条目时返回 `true`
+    pub async fn references_object(&self, object_key: &str) -> bool {
+        self.list_entries().await
+            .iter()
+            .any(|entry| entry.file_ref.as_deref() == Some(object_key))
+    }
+
     /// 删除剪切板条目
     ///
     /// # 参数
@@ -222,11 +524,12 @@ impl ClipboardManager {
     ///
     /// 返回删除结果
     pub async fn delete_entry(&self, id: &str) -> ClipboardResult<()> {
-        self.db.remove(id.as_bytes())
+        let removed = self.db.delete(id.as_bytes())
             .map_err(|e| ErrorInfo::new(6208, format!("删除失败: {}", e))
                 .with_category(ErrorCategory::Database))?
             .ok_or_else(|| ErrorInfo::new(6209, format!("剪切板条目不存在: {}", id))
                 .with_category(ErrorCategory::Storage))?;
+        self.release_objects(entry_file_ref(&removed)).await;
 
         debug!("删除剪切板条目: {}", id);
         Ok(())
@@ -240,14 +543,12 @@ impl ClipboardManager {
     ///
     /// # 返回值
     ///
-    /// 返回处理结果
-    pub async fn handle_sync_event(&self, event: ClipboardEvent) -> ClipboardResult<()> {
+    /// 返回合并时检测到的冲突，冲突条目保持本地内容不变，等待调用方解决
+    pub async fn handle_sync_event(&self, event: ClipboardEvent) -> ClipboardResult<Vec<ClipboardConflict>> {
+        let mut conflicts = Vec::new();
         match event {
-            ClipboardEvent::Add(entry) => {
-                self.merge_entry(entry).await?;
-            }
-            ClipboardEvent::Update(entry) => {
-                self.merge_entry(entry).await?;
+            ClipboardEvent::Add(entry) | ClipboardEvent::Update(entry) => {
+                conflicts.extend(self.merge_entry(entry).await?);
             }
             ClipboardEvent::Delete { id, .. } => {
                 let _ = self.delete_entry(&id).await;
@@ -258,44 +559,205 @@ impl ClipboardManager {
             }
             ClipboardEvent::FullSyncResponse { entries } => {
                 for entry in entries {
-                    self.merge_entry(entry).await?;
+                    conflicts.extend(self.merge_entry(entry).await?);
                 }
             }
         }
-        Ok(())
+        Ok(conflicts)
     }
 
-    /// 合并远程条目（冲突解决）
-    async fn merge_entry(&self, remote_entry: ClipboardEntry) -> ClipboardResult<()> {
-        // 尝试获取本地条目
-        let should_update = if let Ok(local_entry) = self.get_entry(&remote_entry.id).await {
-            // 条目已存在，比较版本
-            if remote_entry.version > local_entry.version {
-                true
-            } else if remote_entry.version == local_entry.version 
-                && remote_entry.timestamp > local_entry.timestamp {
-                true
-            } else {
-                false
+    /// 合并远程条目
+    ///
+    /// 双方都带有向量时钟时按时钟判断先后：远程条目包含本地的全部修改时覆盖本地，
+    /// 本地已包含远程的修改时忽略，两者互不包含（并发修改）或时钟相同但内容不同时
+    /// 返回冲突且不修改本地条目。
+    /// 任一方没有向量时钟（旧版本数据）时按版本号和时间戳判断，后写入者覆盖。
+    ///
+    /// 远程的文件引用条目须内嵌文件内容（见 [`ClipboardManager::inline_file_content`]），
+    /// 内容写入本地对象存储后条目改为引用本地对象；未内嵌内容时只接受本地已有的剪切板对象，
+    /// 否则忽略该条目
+    ///
+    /// # 参数
+    ///
+    /// * `remote_entry` - 远程条目
+    ///
+    /// # 返回值
+    ///
+    /// 检测到并发修改时返回冲突，否则返回 `None`
+    pub async fn merge_entry(&self, remote_entry: ClipboardEntry) -> ClipboardResult<Option<ClipboardConflict>> {
+        let mut released = Vec::new();
+        let result = {
+            let _gc = self.gc_lock.lock().await;
+            self.merge_locked(remote_entry, &mut released).await
+        };
+        self.release_objects(released).await;
+        result
+    }
+
+    /// 持有回收锁时合并远程条目
+    ///
+    /// # 参数
+    ///
+    /// * `remote_entry` - 远程条目
+    /// * `released` - 收集被覆盖条目引用的对象键
+    async fn merge_locked(
+        &self,
+        remote_entry: ClipboardEntry,
+        released: &mut Vec<String>,
+    ) -> ClipboardResult<Option<ClipboardConflict>> {
+        // 已过期的远程条目不再保存
+        if remote_entry.is_expired() {
+            debug!("忽略已过期的远程剪切板条目: {}", remote_entry.id);
+            return Ok(None);
+        }
+
+        let Some(remote_entry) = self.localize_file_ref(remote_entry).await? else {
+            return Ok(None);
+        };
+
+        let local_entry = match self.get_entry(&remote_entry.id).await {
+            Ok(local_entry) => local_entry,
+            Err(_) => {
+                // 新条目
+                released.extend(self.store_entry(&remote_entry)?);
+                debug!("合并剪切板条目: {}", remote_entry.id);
+                return Ok(None);
             }
+        };
+        let same_content = remote_entry.content == local_entry.content
+            && remote_entry.content_type == local_entry.content_type
+            && remote_entry.file_ref == local_entry.file_ref;
+
+        let should_update = if local_entry.clock.is_empty() || remote_entry.clock.is_empty() {
+            remote_entry.version > local_entry.version
+                || (remote_entry.version == local_entry.version
+                    && remote_entry.timestamp > local_entry.timestamp)
         } else {
-            // 新条目
-            true
+            match Self::compare_clocks(&remote_entry.clock, &local_entry.clock) {
+                Some(Ordering::Greater) => true,
+                Some(Ordering::Less) => false,
+                Some(Ordering::Equal) if same_content => false,
+                None if same_content => {
+                    // 内容相同的并发修改不算冲突，合并时钟即可
+                    let mut merged = local_entry;
+                    merged.clock = Self::merge_clocks(&merged.clock, &remote_entry.clock);
+                    merged.version = merged.version.max(remote_entry.version);
+                    released.extend(self.store_entry(&merged)?);
+                    return Ok(None);
+                }
+                // 时钟相同但内容不同：双方以不同方式解决过同一冲突
+                Some(Ordering::Equal) | None => {
+                    debug!("剪切板条目冲突: {} (本地 {:?}, 远程 {:?})",
+                        remote_entry.id, local_entry.clock, remote_entry.clock);
+                    return Ok(Some(ClipboardConflict {
+                        local: local_entry,
+                        remote: remote_entry,
+                    }));
+                }
+            }
         };
 
         if should_update {
-            let entry_bytes = serde_json::to_vec(&remote_entry)
-                .map_err(|e| ErrorInfo::new(6210, format!("序列化失败: {}", e))
-                    .with_category(ErrorCategory::Parse))?;
+            released.extend(self.store_entry(&remote_entry)?);
+            debug!("合并剪切板条目: {}", remote_entry.id);
+        }
 
-            self.db.insert(remote_entry.id.as_bytes(), entry_bytes)
-                .map_err(|e| ErrorInfo::new(6211, format!("存储失败: {}", e))
-                    .with_category(ErrorCategory::Database))?;
+        Ok(None)
+    }
 
-            debug!("合并剪切板条目: {}", remote_entry.id);
+    /// 把远程文件引用条目改为引用本地对象
+    ///
+    /// # 返回值
+    ///
+    /// 不是文件引用的条目原样返回；文件内容无法在本地解析时返回 `None`
+    async fn localize_file_ref(&self, mut entry: ClipboardEntry) -> ClipboardResult<Option<ClipboardEntry>> {
+        let Some(file_ref) = entry.file_ref.clone() else {
+            return Ok(Some(entry));
+        };
+        let Some(objects) = &self.objects else {
+            warn!("未关联对象存储，忽略远程文件引用条目: {}", entry.id);
+            return Ok(None);
+        };
+
+        if entry.content.is_empty() {
+            // 剪切板对象以内容哈希为键，各设备上相同的键对应相同的内容
+            if file_ref.starts_with(CLIPBOARD_OBJECT_PREFIX) && objects.exists(&file_ref).await {
+                return Ok(Some(entry));
+            }
+            warn!("远程文件引用条目 {} 未附带内容，本地也没有对象 {}，忽略", entry.id, file_ref);
+            return Ok(None);
         }
 
-        Ok(())
+        let object_key = Self::store_file_object(objects, &entry.content).await?;
+        entry.file_ref = Some(object_key);
+        entry.content = Vec::new();
+        Ok(Some(entry))
+    }
+
+    /// 解决合并冲突
+    ///
+    /// 保留的条目使用双方合并后的向量时钟，并把本设备的计数加一，
+    /// 因此解决结果包含双方的修改且晚于两者，同步给其他设备后会覆盖冲突的版本。
+    /// 其他设备以不同方式解决了同一冲突时，两个解决结果互不包含，会再次作为冲突上报
+    ///
+    /// # 参数
+    ///
+    /// * `conflict` - 合并时返回的冲突
+    /// * `resolution` - 解决方式
+    ///
+    /// # 返回值
+    ///
+    /// 返回写入的条目（应同步给其他设备），`KeepBoth` 时第二个条目为远程内容的副本
+    pub async fn resolve_conflict(
+        &self,
+        conflict: ClipboardConflict,
+        resolution: ConflictResolution,
+    ) -> ClipboardResult<Vec<ClipboardEntry>> {
+        let ClipboardConflict { local, remote } = conflict;
+        let mut clock = Self::merge_clocks(&local.clock, &remote.clock);
+        *clock.entry(self.device_id.clone()).or_insert(0) += 1;
+        let version = local.version.max(remote.version) + 1;
+        let timestamp = Self::now_secs();
+
+        let winner = match resolution {
+            ConflictResolution::KeepLocal | ConflictResolution::KeepBoth => &local,
+            ConflictResolution::KeepRemote => &remote,
+            ConflictResolution::KeepNewest => {
+                let local_key = (local.timestamp, &local.source_device_id, &local.content);
+                let remote_key = (remote.timestamp, &remote.source_device_id, &remote.content);
+                if remote_key > local_key { &remote } else { &local }
+            }
+        };
+        let resolved = ClipboardEntry {
+            id: local.id.clone(),
+            content: winner.content.clone(),
+            content_type: winner.content_type.clone(),
+            source_device_id: winner.source_device_id.clone(),
+            timestamp,
+            version,
+            clock,
+            expires_at: winner.expires_at,
+            thumbnail: winner.thumbnail.clone(),
+            file_ref: winner.file_ref.clone(),
+        };
+        let mut released: Vec<String> = self.store_entry(&resolved)?.into_iter().collect();
+
+        let mut written = vec![resolved];
+        if resolution == ConflictResolution::KeepBoth {
+            let copy = ClipboardEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp,
+                version: 1,
+                clock: BTreeMap::from([(self.device_id.clone(), 1)]),
+                ..remote
+            };
+            released.extend(self.store_entry(&copy)?);
+            written.push(copy);
+        }
+        self.release_objects(released).await;
+
+        debug!("解决剪切板条目冲突: {} ({:?})", local.id, resolution);
+        Ok(written)
     }
 
     /// 获取差异（自指定时间戳以来的变化）
@@ -309,13 +771,12 @@ impl ClipboardManager {
     /// 返回差异条目列表
     pub async fn get_diff(&self, since_timestamp: u64) -> Vec<ClipboardEntry> {
         let mut diff = Vec::new();
+        let now = now_millis();
 
-        for item in self.db.iter() {
-            if let Ok((_, value)) = item {
-                if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
-                    if entry.timestamp > since_timestamp {
-                        diff.push(entry);
-                    }
+        for (_, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
+                if entry.timestamp > since_timestamp && !entry.is_expired_at(now) {
+                    diff.push(entry);
                 }
             }
         }
@@ -323,18 +784,191 @@ impl ClipboardManager {
         diff
     }
 
+    /// 序列化并存储条目
+    ///
+    /// # 返回值
+    ///
+    /// 被覆盖的旧条目引用了其他对象时返回该对象键
+    fn store_entry(&self, entry: &ClipboardEntry) -> ClipboardResult<Option<String>> {
+        let entry_bytes = serde_json::to_vec(entry)
+            .map_err(|e| ErrorInfo::new(6210, format!("序列化失败: {}", e))
+                .with_category(ErrorCategory::Parse))?;
+
+        let previous = self.db.get(entry.id.as_bytes()).ok().flatten();
+        self.db.put(entry.id.as_bytes(), entry_bytes)
+            .map_err(|e| ErrorInfo::new(6211, format!("存储失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+
+        Ok(previous
+            .and_then(|previous| entry_file_ref(&previous))
+            .filter(|previous| entry.file_ref.as_ref() != Some(previous)))
+    }
+
+    /// 关联的对象存储，未关联时返回错误
+    fn object_storage(&self) -> ClipboardResult<&Arc<ObjectStorage>> {
+        self.objects.as_ref()
+            .ok_or_else(|| ErrorInfo::new(6215, "剪切板未关联对象存储".to_string())
+                .with_category(ErrorCategory::Storage))
+    }
+
+    /// 写入文件内容，已有相同内容的对象时直接复用
+    ///
+    /// # 返回值
+    ///
+    /// 返回保存该内容的对象键
+    async fn store_file_object(objects: &ObjectStorage, content: &[u8]) -> ClipboardResult<String> {
+        if let Some(existing) = objects.find_by_content(content).await? {
+            debug!("剪切板文件与已有对象内容相同，直接引用: {}", existing);
+            return Ok(existing);
+        }
+
+        let object_key = format!("{}{}", CLIPBOARD_OBJECT_PREFIX, content_hash(content));
+        objects.store(&object_key, content).await?;
+        Ok(object_key)
+    }
+
+    /// 删除不再被任何条目引用的剪切板对象
+    ///
+    /// # 参数
+    ///
+    /// * `object_keys` - 引用刚刚消失的对象键，不是剪切板对象的键被忽略
+    async fn release_objects(&self, object_keys: impl IntoIterator<Item = String>) {
+        let Some(objects) = &self.objects else {
+            return;
+        };
+        let candidates: Vec<String> = object_keys.into_iter()
+            .filter(|key| key.starts_with(CLIPBOARD_OBJECT_PREFIX))
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let _gc = self.gc_lock.lock().await;
+        let referenced = referenced_objects(self.db.as_ref());
+        for object_key in candidates {
+            if referenced.contains(&object_key) || !objects.exists(&object_key).await {
+                continue;
+            }
+            match objects.delete(&object_key).await {
+                Ok(()) => debug!("删除不再被引用的剪切板对象: {}", object_key),
+                Err(e) => warn!("删除剪切板对象 {} 失败: {}", object_key, e),
+            }
+        }
+    }
+
+    /// 删除所有不再被任何条目引用的剪切板对象
+    ///
+    /// 过期清理和清空剪切板后调用，也可用于回收异常退出时遗留的对象
+    ///
+    /// # 返回值
+    ///
+    /// 返回删除的对象数量，未关联对象存储时返回 0
+    pub async fn collect_orphaned_objects(&self) -> ClipboardResult<usize> {
+        let Some(objects) = &self.objects else {
+            return Ok(0);
+        };
+        let _gc = self.gc_lock.lock().await;
+        collect_orphaned_objects(self.db.as_ref(), objects).await
+    }
+
+    /// 文件引用条目的实际内容大小
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 剪切板条目
+    ///
+    /// # 返回值
+    ///
+    /// 文件引用条目返回所引用对象的大小（对象不存在时为 0），其他条目返回内嵌内容的大小
+    pub async fn content_size(&self, entry: &ClipboardEntry) -> u64 {
+        match (&entry.file_ref, &self.objects) {
+            (Some(object_key), Some(objects)) => objects.size(object_key).await.unwrap_or(0),
+            (Some(_), None) => 0,
+            (None, _) => entry.content.len() as u64,
+        }
+    }
+
+    /// 为同步准备条目，文件引用条目内嵌所引用的文件内容
+    ///
+    /// 对端收到后把内容写入自己的对象存储，见 [`ClipboardManager::merge_entry`]
+    ///
+    /// # 参数
+    ///
+    /// * `entry` - 剪切板条目
+    ///
+    /// # 返回值
+    ///
+    /// 返回可发送的条目，引用的对象无法读取时返回 `None`
+    pub async fn inline_file_content(&self, mut entry: ClipboardEntry) -> Option<ClipboardEntry> {
+        let Some(object_key) = &entry.file_ref else {
+            return Some(entry);
+        };
+        let Some(objects) = &self.objects else {
+            warn!("未关联对象存储，无法同步文件引用条目: {}", entry.id);
+            return None;
+        };
+
+        match objects.retrieve(object_key).await {
+            Ok(content) => {
+                entry.content = content;
+                Some(entry)
+            }
+            Err(e) => {
+                warn!("读取剪切板条目 {} 引用的对象 {} 失败，不同步该条目: {}", entry.id, object_key, e);
+                None
+            }
+        }
+    }
+
+    /// 比较两个向量时钟
+    ///
+    /// 返回 `None` 表示两者互不包含（并发修改）
+    fn compare_clocks(a: &BTreeMap<String, u64>, b: &BTreeMap<String, u64>) -> Option<Ordering> {
+        let mut a_ahead = false;
+        let mut b_ahead = false;
+        for device in a.keys().chain(b.keys()) {
+            let a_count = a.get(device).copied().unwrap_or(0);
+            let b_count = b.get(device).copied().unwrap_or(0);
+            a_ahead |= a_count > b_count;
+            b_ahead |= b_count > a_count;
+        }
+
+        match (a_ahead, b_ahead) {
+            (true, true) => None,
+            (true, false) => Some(Ordering::Greater),
+            (false, true) => Some(Ordering::Less),
+            (false, false) => Some(Ordering::Equal),
+        }
+    }
+
+    /// 合并两个向量时钟（逐设备取最大值）
+    fn merge_clocks(a: &BTreeMap<String, u64>, b: &BTreeMap<String, u64>) -> BTreeMap<String, u64> {
+        let mut merged = a.clone();
+        for (device, count) in b {
+            let entry = merged.entry(device.clone()).or_insert(0);
+            *entry = (*entry).max(*count);
+        }
+        merged
+    }
+
+    /// 当前时间戳（秒）
+    fn now_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
     /// 查找最旧条目的键
     fn find_oldest_entry_key(&self) -> Option<Vec<u8>> {
         let mut oldest_key: Option<Vec<u8>> = None;
         let mut oldest_timestamp = u64::MAX;
 
-        for item in self.db.iter() {
-            if let Ok((key, value)) = item {
-                if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
-                    if entry.timestamp < oldest_timestamp {
-                        oldest_timestamp = entry.timestamp;
-                        oldest_key = Some(key.to_vec());
-                    }
+        for (key, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(entry) = serde_json::from_slice::<ClipboardEntry>(&value) {
+                if entry.timestamp < oldest_timestamp {
+                    oldest_timestamp = entry.timestamp;
+                    oldest_key = Some(key.to_vec());
                 }
             }
         }
@@ -342,17 +976,142 @@ impl ClipboardManager {
         oldest_key
     }
 
+    /// 剪切板条目数量
+    pub fn entry_count(&self) -> usize {
+        self.db.len()
+    }
+
+    /// 将剪切板数据库缓冲的写入刷到磁盘
+    pub async fn flush(&self) -> ClipboardResult<()> {
+        let db = Arc::clone(&self.db);
+        tokio::task::spawn_blocking(move || db.flush()).await
+            .map_err(|e| ErrorInfo::new(6213, format!("刷盘任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6213, format!("刷新剪切板数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))
+    }
+
+    /// 删除已过期的条目
+    ///
+    /// # 返回值
+    ///
+    /// 返回删除的条目数量或错误
+    pub async fn sweep_expired(&self) -> ClipboardResult<usize> {
+        let db = Arc::clone(&self.db);
+        let removed = tokio::task::spawn_blocking(move || sweep_expired(db.as_ref(), clipboard_entry_expired)).await
+            .map_err(|e| ErrorInfo::new(6214, format!("过期清理任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6214, format!("清理过期剪切板条目失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+
+        debug!("清理 {} 个过期剪切板条目", removed);
+        if removed > 0 {
+            self.collect_orphaned_objects().await?;
+        }
+        Ok(removed)
+    }
+
+    /// 启动定期过期清理任务
+    ///
+    /// 任务在剪切板管理器释放后自动退出。关联了对象存储时，
+    /// 每次清理后一并删除不再被引用的剪切板对象
+    ///
+    /// # 参数
+    ///
+    /// * `interval` - 清理间隔
+    pub fn start_expiry_sweeper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let Some(objects) = self.objects.clone() else {
+            return spawn_expiry_sweeper(&self.db, interval, "剪切板数据库", clipboard_entry_expired);
+        };
+        let db = Arc::downgrade(&self.db);
+        let gc_lock = Arc::clone(&self.gc_lock);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 第一次触发立即返回，跳过
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+                let Some(db) = db.upgrade() else {
+                    debug!("剪切板数据库已释放，过期清理任务退出");
+                    break;
+                };
+
+                let sweep_db = Arc::clone(&db);
+                match tokio::task::spawn_blocking(move || sweep_expired(sweep_db.as_ref(), clipboard_entry_expired)).await {
+                    Ok(Ok(0)) => continue,
+                    Ok(Ok(removed)) => debug!("剪切板数据库清理 {} 个过期条目", removed),
+                    Ok(Err(e)) => {
+                        warn!("剪切板数据库过期清理失败: {}", e);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("剪切板数据库过期清理任务异常终止: {}", e);
+                        continue;
+                    }
+                }
+
+                let _gc = gc_lock.lock().await;
+                if let Err(e) = collect_orphaned_objects(db.as_ref(), &objects).await {
+                    warn!("回收剪切板对象失败: {}", e);
+                }
+            }
+        })
+    }
+
     /// 清空所有条目
+    ///
+    /// 关联了对象存储时一并删除剪切板写入的对象
     pub async fn clear(&self) -> ClipboardResult<()> {
         self.db.clear()
             .map_err(|e| ErrorInfo::new(6212, format!("清空失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
+        self.collect_orphaned_objects().await?;
         
         info!("清空剪切板");
         Ok(())
     }
 }
 
+/// 判断序列化的剪切板条目在指定时间是否已过期
+fn clipboard_entry_expired(bytes: &[u8], now: u64) -> bool {
+    serde_json::from_slice::<ClipboardEntry>(bytes)
+        .map(|entry| entry.is_expired_at(now))
+        .unwrap_or(false)
+}
+
+/// 序列化的剪切板条目引用的对象键
+fn entry_file_ref(bytes: &[u8]) -> Option<String> {
+    serde_json::from_slice::<ClipboardEntry>(bytes).ok()?.file_ref
+}
+
+/// 数据库中所有条目引用的对象键
+fn referenced_objects(db: &dyn KvBackend) -> HashSet<String> {
+    db.scan(&[]).unwrap_or_default()
+        .into_iter()
+        .filter_map(|(_, value)| entry_file_ref(&value))
+        .collect()
+}
+
+/// 删除所有不再被引用的剪切板对象，调用方须持有回收锁
+async fn collect_orphaned_objects(db: &dyn KvBackend, objects: &ObjectStorage) -> ClipboardResult<usize> {
+    let referenced = referenced_objects(db);
+    let mut removed = 0;
+    for object_key in objects.list().await? {
+        if !object_key.starts_with(CLIPBOARD_OBJECT_PREFIX) || referenced.contains(&object_key) {
+            continue;
+        }
+        objects.delete(&object_key).await?;
+        removed += 1;
+    }
+
+    if removed > 0 {
+        debug!("回收 {} 个不再被引用的剪切板对象", removed);
+    }
+    Ok(removed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,6 +1181,10 @@ mod tests {
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
             version: 2,
+            clock: BTreeMap::new(),
+            expires_at: None,
+            thumbnail: None,
+            file_ref: None,
         };
 
         manager.handle_sync_event(ClipboardEvent::Update(remote_entry)).await
@@ -432,4 +1195,291 @@ mod tests {
         assert_eq!(entry.content, b"Remote Updated");
         assert_eq!(entry.version, 2);
     }
+
+    /// 模拟两台设备从同一条目出发并发修改，返回 (本地管理器, 冲突)
+    async fn concurrent_edit_conflict(dir: &std::path::Path) -> (ClipboardManager, ClipboardConflict) {
+        let local = ClipboardManager::new("device1".to_string(), dir.join("local.db")).await
+            .expect("创建管理器失败");
+        let remote = ClipboardManager::new("device2".to_string(), dir.join("remote.db")).await
+            .expect("创建管理器失败");
+
+        // 条目同步到远程设备
+        let id = local.add_entry(b"Base".to_vec(), "text".to_string()).await
+            .expect("添加失败");
+        let base = local.get_entry(&id).await.expect("获取失败");
+        let conflicts = remote.handle_sync_event(ClipboardEvent::Add(base)).await
+            .expect("处理事件失败");
+        assert!(conflicts.is_empty());
+
+        // 两台设备在同步前各自修改
+        local.update_entry(&id, b"Local Edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        let remote_edit = remote.update_entry(&id, b"Remote Edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+
+        let mut conflicts = local.handle_sync_event(ClipboardEvent::Update(remote_edit)).await
+            .expect("处理事件失败");
+        assert_eq!(conflicts.len(), 1, "并发修改应产生冲突");
+
+        // 冲突未解决前本地内容不变
+        assert_eq!(local.get_entry(&id).await.expect("获取失败").content, b"Local Edit");
+
+        let conflict = conflicts.remove(0);
+        assert_eq!(conflict.local.content, b"Local Edit");
+        assert_eq!(conflict.remote.content, b"Remote Edit");
+        (local, conflict)
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_conflict_keep_local() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let (manager, conflict) = concurrent_edit_conflict(temp_dir.path()).await;
+        let id = conflict.local.id.clone();
+        let remote = conflict.remote.clone();
+
+        let written = manager.resolve_conflict(conflict, ConflictResolution::KeepLocal).await
+            .expect("解决冲突失败");
+        assert_eq!(written.len(), 1);
+        assert_eq!(manager.get_entry(&id).await.expect("获取失败").content, b"Local Edit");
+        assert_eq!(manager.entry_count(), 1);
+
+        // 解决后的条目包含远程修改，重复收到远程条目不再冲突
+        let conflicts = manager.handle_sync_event(ClipboardEvent::Update(remote)).await
+            .expect("处理事件失败");
+        assert!(conflicts.is_empty());
+        assert_eq!(manager.get_entry(&id).await.expect("获取失败").content, b"Local Edit");
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_conflict_keep_remote() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let (manager, conflict) = concurrent_edit_conflict(temp_dir.path()).await;
+        let id = conflict.local.id.clone();
+
+        let written = manager.resolve_conflict(conflict, ConflictResolution::KeepRemote).await
+            .expect("解决冲突失败");
+        assert_eq!(written.len(), 1);
+
+        let entry = manager.get_entry(&id).await.expect("获取失败");
+        assert_eq!(entry.content, b"Remote Edit");
+        assert_eq!(entry.source_device_id, "device2");
+        assert_eq!(entry.version, 3);
+        assert_eq!(manager.entry_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_conflict_keep_both() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let (manager, conflict) = concurrent_edit_conflict(temp_dir.path()).await;
+        let id = conflict.local.id.clone();
+
+        let written = manager.resolve_conflict(conflict, ConflictResolution::KeepBoth).await
+            .expect("解决冲突失败");
+        assert_eq!(written.len(), 2);
+        assert_eq!(written[0].id, id);
+        assert_ne!(written[1].id, id);
+
+        assert_eq!(manager.get_entry(&id).await.expect("获取失败").content, b"Local Edit");
+        let copy = manager.get_entry(&written[1].id).await.expect("获取失败");
+        assert_eq!(copy.content, b"Remote Edit");
+        assert_eq!(copy.source_device_id, "device2");
+        assert_eq!(manager.entry_count(), 2);
+    }
+
+    /// 两台设备并发修改同一条目并互相同步，返回 (本地管理器, 远程管理器, 本地冲突, 远程冲突)
+    async fn conflict_on_both_devices(dir: &std::path::Path)
+        -> (ClipboardManager, ClipboardManager, ClipboardConflict, ClipboardConflict) {
+        let local = ClipboardManager::new("device1".to_string(), dir.join("local.db")).await
+            .expect("创建管理器失败");
+        let remote = ClipboardManager::new("device2".to_string(), dir.join("remote.db")).await
+            .expect("创建管理器失败");
+
+        let id = local.add_entry(b"Base".to_vec(), "text".to_string()).await
+            .expect("添加失败");
+        let base = local.get_entry(&id).await.expect("获取失败");
+        remote.handle_sync_event(ClipboardEvent::Add(base)).await.expect("处理事件失败");
+
+        let local_edit = local.update_entry(&id, b"Local Edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        let remote_edit = remote.update_entry(&id, b"Remote Edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+
+        let mut local_conflicts = local.handle_sync_event(ClipboardEvent::Update(remote_edit)).await
+            .expect("处理事件失败");
+        let mut remote_conflicts = remote.handle_sync_event(ClipboardEvent::Update(local_edit)).await
+            .expect("处理事件失败");
+        assert_eq!((local_conflicts.len(), remote_conflicts.len()), (1, 1));
+        (local, remote, local_conflicts.remove(0), remote_conflicts.remove(0))
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_divergent_resolutions_conflict_again() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let (local, remote, local_conflict, remote_conflict) = conflict_on_both_devices(temp_dir.path()).await;
+
+        // 双方各自保留本地内容，解决结果都晚于冲突的两个版本
+        let local_resolved = local.resolve_conflict(local_conflict, ConflictResolution::KeepLocal).await
+            .expect("解决冲突失败").remove(0);
+        let remote_resolved = remote.resolve_conflict(remote_conflict, ConflictResolution::KeepLocal).await
+            .expect("解决冲突失败").remove(0);
+        assert_eq!(local_resolved.clock.get("device1"), Some(&3));
+        assert_eq!(remote_resolved.clock.get("device2"), Some(&2));
+
+        // 两个不同的解决结果互相同步时再次上报冲突，而不是静默保持分歧
+        let conflicts = local.handle_sync_event(ClipboardEvent::Update(remote_resolved.clone())).await
+            .expect("处理事件失败");
+        assert_eq!(conflicts.len(), 1);
+
+        // 时钟相同但内容不同的条目同样视为冲突
+        let mut tampered = local_resolved.clone();
+        tampered.content = b"Other Content".to_vec();
+        let conflicts = local.handle_sync_event(ClipboardEvent::Update(tampered)).await
+            .expect("处理事件失败");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(local.get_entry(&local_resolved.id).await.expect("获取失败").content, b"Local Edit");
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_keep_newest_converges() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let (local, remote, local_conflict, remote_conflict) = conflict_on_both_devices(temp_dir.path()).await;
+        let id = local_conflict.local.id.clone();
+
+        let local_resolved = local.resolve_conflict(local_conflict, ConflictResolution::KeepNewest).await
+            .expect("解决冲突失败").remove(0);
+        let remote_resolved = remote.resolve_conflict(remote_conflict, ConflictResolution::KeepNewest).await
+            .expect("解决冲突失败").remove(0);
+        assert_eq!(local_resolved.content, remote_resolved.content);
+
+        // 双方选择了相同的内容，交换解决结果后不再冲突且时钟一致
+        assert!(local.handle_sync_event(ClipboardEvent::Update(remote_resolved)).await
+            .expect("处理事件失败").is_empty());
+        assert!(remote.handle_sync_event(ClipboardEvent::Update(local_resolved)).await
+            .expect("处理事件失败").is_empty());
+        let local_entry = local.get_entry(&id).await.expect("获取失败");
+        let remote_entry = remote.get_entry(&id).await.expect("获取失败");
+        assert_eq!(local_entry.content, remote_entry.content);
+        assert_eq!(local_entry.clock, remote_entry.clock);
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_sequential_edit_no_conflict() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let local = ClipboardManager::new("device1".to_string(), temp_dir.path().join("local.db")).await
+            .expect("创建管理器失败");
+        let remote = ClipboardManager::new("device2".to_string(), temp_dir.path().join("remote.db")).await
+            .expect("创建管理器失败");
+
+        let id = local.add_entry(b"Base".to_vec(), "text".to_string()).await
+            .expect("添加失败");
+        let base = local.get_entry(&id).await.expect("获取失败");
+        remote.handle_sync_event(ClipboardEvent::Add(base.clone())).await
+            .expect("处理事件失败");
+
+        // 远程在已同步的版本上修改，本地直接采用
+        let remote_edit = remote.update_entry(&id, b"Remote Edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        let conflicts = local.handle_sync_event(ClipboardEvent::Update(remote_edit)).await
+            .expect("处理事件失败");
+        assert!(conflicts.is_empty());
+        assert_eq!(local.get_entry(&id).await.expect("获取失败").content, b"Remote Edit");
+
+        // 过时的条目被忽略
+        let conflicts = local.handle_sync_event(ClipboardEvent::Update(base)).await
+            .expect("处理事件失败");
+        assert!(conflicts.is_empty());
+        assert_eq!(local.get_entry(&id).await.expect("获取失败").content, b"Remote Edit");
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_entry_expires_and_is_swept() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = ClipboardManager::new("device1".to_string(), temp_dir.path().join("clipboard.db")).await
+            .expect("创建管理器失败");
+
+        let permanent = manager.add_entry(b"keep".to_vec(), "text".to_string()).await
+            .expect("添加失败");
+        let ephemeral = manager.add_entry_ephemeral(b"secret".to_vec(), "text".to_string(), Duration::from_millis(50)).await
+            .expect("添加临时条目失败");
+        assert!(manager.get_entry(&ephemeral).await.is_ok());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // 过期但尚未清理的条目不可读取，也不出现在列表中
+        let err = manager.get_entry(&ephemeral).await.expect_err("过期条目不应可读");
+        assert_eq!(err.code(), 6206);
+        let entries = manager.list_entries().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, permanent);
+        assert_eq!(manager.entry_count(), 2);
+
+        assert_eq!(manager.sweep_expired().await.expect("清理失败"), 1);
+        assert_eq!(manager.entry_count(), 1);
+        assert!(manager.get_entry(&permanent).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_file_ref_releases_object() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let objects = Arc::new(ObjectStorage::new(crate::ObjectStorageConfig {
+            storage_root: temp_dir.path().join("objects"),
+            ..Default::default()
+        }).await.expect("创建对象存储失败"));
+        let manager = ClipboardManager::new("device1".to_string(), temp_dir.path().join("clipboard.db")).await
+            .expect("创建管理器失败")
+            .with_object_storage(Arc::clone(&objects));
+
+        let kept = manager.add_file(b"same file", "file".to_string()).await.expect("添加文件失败");
+        let expiring = manager.add_file(b"same file", "file".to_string()).await.expect("添加文件失败");
+        let mut entry = manager.get_entry(&expiring).await.expect("获取失败");
+        let object_key = entry.file_ref.clone().expect("应为文件引用条目");
+        entry.expires_at = Some(1);
+        manager.store_entry(&entry).expect("存储失败");
+
+        // 仍有未过期的条目引用对象时保留
+        assert_eq!(manager.sweep_expired().await.expect("清理失败"), 1);
+        assert!(objects.exists(&object_key).await);
+
+        let mut entry = manager.get_entry(&kept).await.expect("获取失败");
+        entry.expires_at = Some(1);
+        manager.store_entry(&entry).expect("存储失败");
+        assert_eq!(manager.sweep_expired().await.expect("清理失败"), 1);
+        assert!(!objects.exists(&object_key).await);
+    }
+
+    #[cfg(feature = "thumbnails")]
+    #[tokio::test]
+    async fn test_image_entry_has_smaller_thumbnail() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = ClipboardManager::new("device1".to_string(), temp_dir.path().join("clipboard.db")).await
+            .expect("创建管理器失败")
+            .with_thumbnail_max_dimension(32);
+
+        let image = image::RgbImage::from_fn(256, 128, |x, y| image::Rgb([x as u8, y as u8, (x ^ y) as u8]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .expect("编码PNG失败");
+        let png = png.into_inner();
+
+        let id = manager.add_entry(png.clone(), "image/png".to_string()).await
+            .expect("添加失败");
+        assert_eq!(manager.get_entry(&id).await.expect("获取失败").content, png);
+
+        let thumbnail = manager.get_thumbnail(&id).await.expect("图片条目应有缩略图");
+        assert!(thumbnail.len() < png.len(), "缩略图应小于原图");
+        let decoded = image::load_from_memory(&thumbnail).expect("解码缩略图失败");
+        assert_eq!((decoded.width(), decoded.height()), (32, 16));
+
+        // 非图片条目和不存在的条目没有缩略图
+        let text = manager.add_entry(b"Hello".to_vec(), "text".to_string()).await
+            .expect("添加失败");
+        assert!(manager.get_thumbnail(&text).await.is_none());
+        assert!(manager.get_thumbnail("missing").await.is_none());
+
+        // 修改为文本后缩略图被移除
+        manager.update_entry(&id, b"now text".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        assert!(manager.get_thumbnail(&id).await.is_none());
+    }
 }
@@ -1,18 +1,24 @@
 //! # 云存储模块
 //!
-//! 提供分布式云存储功能，使用sled数据库存储元数据，
+//! 提供分布式云存储功能，使用键值存储后端（默认sled）存储元数据，
 //! 文件使用zstd压缩并带有二进制前缀，存储为.beycloud文件。
 //! 实现动态冗余算法和一致性哈希分布。
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
-use sled::Db;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tracing::{info, debug};
 use sha2::{Sha256, Digest};
 
+use crate::events::{StorageEvent, StorageEventBus, StorageKind, StorageOperation};
+use crate::integrity::IntegrityIssueKind;
+use crate::kv_backend::{open_backend, spawn_compaction_task, KvBackend, KvBackendKind};
+use crate::read_cache::{CacheStats, ReadCache};
+
 /// 云存储结果类型
 pub type CloudStorageResult<T> = std::result::Result<T, ErrorInfo>;
 
@@ -27,8 +33,14 @@ pub struct CloudStorageConfig {
     pub chunk_size: usize,
     /// 冗余因子（1表示无冗余）
     pub redundancy_factor: usize,
-    /// 最大本地存储大小（字节）
+    /// 最大本地存储大小（已存储文件原始大小总和的上限，字节）
     pub max_local_storage: u64,
+    /// 元数据存储后端
+    pub backend: KvBackendKind,
+    /// 元数据库定期压缩间隔（`None` 表示不启用定期压缩）
+    pub compaction_interval: Option<Duration>,
+    /// 读缓存最大字节数（`None` 表示不启用读缓存）
+    pub read_cache_bytes: Option<u64>,
 }
 
 impl Default for CloudStorageConfig {
@@ -39,6 +51,9 @@ impl Default for CloudStorageConfig {
             chunk_size: 1024 * 1024, // 1MB
             redundancy_factor: 2,
             max_local_storage: 10 * 1024 * 1024 * 1024, // 10GB
+            backend: KvBackendKind::Sled,
+            compaction_interval: None,
+            read_cache_bytes: None,
         }
     }
 }
@@ -58,6 +73,9 @@ pub struct FileMetadata {
     pub chunk_ids: Vec<String>,
     /// 原始文件哈希（用于验证）
     pub original_hash: String,
+    /// 上传时使用的块大小（除最后一块外每块的原始字节数），旧版本上传的文件为0
+    #[serde(default)]
+    pub chunk_size: u64,
 }
 
 /// 块前缀结构（固定128字节）
@@ -174,7 +192,13 @@ impl ChunkPrefix {
 /// 云存储管理器
 pub struct CloudStorage {
     config: CloudStorageConfig,
-    db: Arc<Db>,
+    db: Arc<dyn KvBackend>,
+    /// 已存储文件的原始大小总和（字节）
+    used_bytes: AtomicU64,
+    /// 存储事件广播器
+    events: StorageEventBus,
+    /// 热点文件读缓存（按文件哈希）
+    cache: Option<ReadCache>,
 }
 
 impl CloudStorage {
@@ -195,24 +219,70 @@ impl CloudStorage {
                 .with_severity(ErrorSeverity::Error))?;
 
         // 创建数据库目录
-        if let Some(parent) = config.db_path.parent() {
-            fs::create_dir_all(parent).await
-                .map_err(|e| ErrorInfo::new(6106, format!("创建数据库目录失败: {}", e))
-                    .with_category(ErrorCategory::FileSystem)
-                    .with_severity(ErrorSeverity::Error))?;
+        if config.backend == KvBackendKind::Sled {
+            if let Some(parent) = config.db_path.parent() {
+                fs::create_dir_all(parent).await
+                    .map_err(|e| ErrorInfo::new(6106, format!("创建数据库目录失败: {}", e))
+                        .with_category(ErrorCategory::FileSystem)
+                        .with_severity(ErrorSeverity::Error))?;
+            }
         }
 
-        // 打开sled数据库
-        let db = sled::open(&config.db_path)
+        // 打开元数据存储后端
+        let db = open_backend(config.backend, &config.db_path)
             .map_err(|e| ErrorInfo::new(6107, format!("打开数据库失败: {}", e))
                 .with_category(ErrorCategory::Database)
                 .with_severity(ErrorSeverity::Error))?;
 
-        info!("云存储初始化成功: {:?}", config.storage_root);
-        Ok(Self {
+        info!("云存储初始化成功: {:?} (后端: {:?})", config.storage_root, config.backend);
+        let cache = config.read_cache_bytes.map(ReadCache::new);
+        let storage = Self {
             config,
-            db: Arc::new(db),
-        })
+            db,
+            used_bytes: AtomicU64::new(0),
+            events: StorageEventBus::new(),
+            cache,
+        };
+
+        // 统计已有文件的占用，之后随上传和删除增量维护
+        let used_bytes = storage.list_files()
+            .map(|files| files.iter().map(|file| file.size).sum())
+            .unwrap_or(0);
+        storage.used_bytes.store(used_bytes, Ordering::Relaxed);
+
+        if let Some(interval) = storage.config.compaction_interval {
+            spawn_compaction_task(&storage.db, interval, "云存储元数据库");
+            info!("云存储元数据库定期压缩已启用，间隔: {:?}", interval);
+        }
+
+        Ok(storage)
+    }
+
+    /// 使用指定的事件广播器，与其他存储共享事件通道
+    pub fn with_event_bus(mut self, events: StorageEventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 订阅存储事件
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+
+    /// 获取读缓存统计
+    ///
+    /// # 返回值
+    ///
+    /// 未启用读缓存时返回 `None`
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(ReadCache::stats)
+    }
+
+    /// 使文件的读缓存失效
+    fn invalidate_cache(&self, file_hash: &str) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(file_hash);
+        }
     }
 
     /// 计算文件哈希
@@ -222,6 +292,21 @@ impl CloudStorage {
         format!("{:x}", hasher.finalize())
     }
 
+    /// 检查指定哈希的文件是否已上传
+    ///
+    /// 可与 [`hash_reader`] 配合，在上传前跳过已存在的文件
+    ///
+    /// # 参数
+    ///
+    /// * `file_hash` - 文件哈希
+    ///
+    /// # 返回值
+    ///
+    /// 文件已存在返回 true，不存在或查询失败返回 false
+    pub fn exists_by_hash(&self, file_hash: &str) -> bool {
+        self.db.contains_key(file_hash.as_bytes()).unwrap_or(false)
+    }
+
     /// 上传文件到云存储
     ///
     /// # 参数
@@ -233,8 +318,83 @@ impl CloudStorage {
     ///
     /// 返回文件哈希或错误
     pub async fn upload_file(&self, filename: &str, data: &[u8]) -> CloudStorageResult<String> {
-        // 计算文件哈希
+        self.upload_file_with_progress(filename, data, |_, _| {}).await
+    }
+
+    /// 上传文件到云存储，每写入一个块报告一次进度
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `data` - 文件数据
+    /// * `on_progress` - 进度回调，参数为已写入字节数和总字节数；文件已存在时直接报告完成
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件哈希或错误
+    pub async fn upload_file_with_progress(
+        &self,
+        filename: &str,
+        data: &[u8],
+        on_progress: impl Fn(u64, u64),
+    ) -> CloudStorageResult<String> {
         let file_hash = Self::calculate_hash(data);
+        self.store_chunks(filename, file_hash, data.len() as u64, data, on_progress).await
+    }
+
+    /// 从本地文件上传到云存储，分块读取，不把整个文件加载到内存
+    ///
+    /// 先读一遍文件计算哈希，再分块写入；两次读取之间文件被修改时上传失败
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `path` - 本地文件路径
+    /// * `on_progress` - 进度回调，参数为已写入字节数和总字节数；文件已存在时直接报告完成
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件哈希或错误
+    pub async fn upload_file_from_path(
+        &self,
+        filename: &str,
+        path: &Path,
+        on_progress: impl Fn(u64, u64),
+    ) -> CloudStorageResult<String> {
+        let open = || async {
+            fs::File::open(path).await
+                .map_err(|e| ErrorInfo::new(6131, format!("打开文件 {} 失败: {}", path.display(), e))
+                    .with_category(ErrorCategory::FileSystem))
+        };
+
+        let mut file = open().await?;
+        let size = file.metadata().await
+            .map_err(|e| ErrorInfo::new(6131, format!("读取文件 {} 信息失败: {}", path.display(), e))
+                .with_category(ErrorCategory::FileSystem))?
+            .len();
+        let file_hash = hash_reader(&mut file).await?;
+        self.store_chunks(filename, file_hash, size, open().await?, on_progress).await
+    }
+
+    /// 把读取器中的数据分块压缩写入并登记元数据
+    ///
+    /// 写入的内容与 `file_hash` 和 `size` 不一致时删除已写入的块并返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `filename` - 文件名
+    /// * `file_hash` - 文件内容的哈希
+    /// * `total_bytes` - 文件大小
+    /// * `reader` - 文件内容来源
+    /// * `on_progress` - 进度回调
+    async fn store_chunks<R: AsyncRead + Unpin>(
+        &self,
+        filename: &str,
+        file_hash: String,
+        total_bytes: u64,
+        mut reader: R,
+        on_progress: impl Fn(u64, u64),
+    ) -> CloudStorageResult<String> {
         let file_hash_bytes = hex::decode(&file_hash)
             .map_err(|e| ErrorInfo::new(6108, format!("哈希解码失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
@@ -244,17 +404,41 @@ impl CloudStorage {
             .map_err(|e| ErrorInfo::new(6109, format!("检查文件存在失败: {}", e))
                 .with_category(ErrorCategory::Database))? {
             info!("文件已存在: {}", file_hash);
+            on_progress(total_bytes, total_bytes);
             return Ok(file_hash);
         }
 
+        // 检查存储配额
+        let used_bytes = self.used_bytes();
+        if used_bytes.saturating_add(total_bytes) > self.config.max_local_storage {
+            return Err(ErrorInfo::new(6130, format!(
+                "超出存储配额: 已用 {} 字节，上传 {} 字节，上限 {} 字节",
+                used_bytes, total_bytes, self.config.max_local_storage
+            ))
+                .with_category(ErrorCategory::Storage)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
         // 分块
         let chunk_size = self.config.chunk_size;
-        let total_chunks = (data.len() + chunk_size - 1) / chunk_size;
+        let total_chunks = total_bytes.div_ceil(chunk_size as u64);
         let mut chunk_ids = Vec::new();
+        let mut bytes_written = 0u64;
+        let mut hasher = Sha256::new();
+        let mut chunk_data = Vec::with_capacity(chunk_size.min(total_bytes as usize));
+
+        for index in 0..total_chunks {
+            chunk_data.clear();
+            let read = (&mut reader).take(chunk_size as u64).read_to_end(&mut chunk_data).await
+                .map_err(|e| ErrorInfo::new(6131, format!("读取数据失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&chunk_data);
 
-        for (index, chunk_data) in data.chunks(chunk_size).enumerate() {
             // 压缩块数据
-            let compressed = zstd::encode_all(chunk_data, 3)
+            let compressed = zstd::encode_all(chunk_data.as_slice(), 3)
                 .map_err(|e| ErrorInfo::new(6110, format!("压缩失败: {}", e))
                     .with_category(ErrorCategory::Compression))?;
 
@@ -277,18 +461,30 @@ impl CloudStorage {
             let chunk_path = self.config.storage_root.join(&chunk_filename);
 
             // 写入块文件
-            fs::write(&chunk_path, &chunk_with_prefix).await
-                .map_err(|e| ErrorInfo::new(6111, format!("写入块文件失败: {}", e))
-                    .with_category(ErrorCategory::FileSystem))?;
+            if let Err(e) = fs::write(&chunk_path, &chunk_with_prefix).await {
+                self.remove_chunk_files(&chunk_ids).await;
+                return Err(ErrorInfo::new(6111, format!("写入块文件失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem));
+            }
 
             chunk_ids.push(chunk_hash);
+            bytes_written += read as u64;
+            on_progress(bytes_written, total_bytes);
             debug!("块 {}/{} 上传成功: {}", index + 1, total_chunks, chunk_filename);
         }
 
+        // 读取期间内容发生变化时不登记
+        if bytes_written != total_bytes || format!("{:x}", hasher.finalize()) != file_hash {
+            self.remove_chunk_files(&chunk_ids).await;
+            return Err(ErrorInfo::new(6134, format!("文件 {} 在上传期间被修改", filename))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Error));
+        }
+
         // 创建元数据
         let metadata = FileMetadata {
             filename: filename.to_string(),
-            size: data.len() as u64,
+            size: total_bytes,
             hash: file_hash.clone(),
             upload_time: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
@@ -296,6 +492,7 @@ impl CloudStorage {
                 .unwrap_or(0),
             chunk_ids,
             original_hash: file_hash.clone(),
+            chunk_size: chunk_size as u64,
         };
 
         // 存储元数据到数据库
@@ -303,14 +500,25 @@ impl CloudStorage {
             .map_err(|e| ErrorInfo::new(6112, format!("序列化元数据失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
-        self.db.insert(file_hash.as_bytes(), metadata_json)
+        self.db.put(file_hash.as_bytes(), metadata_json)
             .map_err(|e| ErrorInfo::new(6113, format!("存储元数据失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
+        self.used_bytes.fetch_add(metadata.size, Ordering::Relaxed);
+        self.invalidate_cache(&file_hash);
+
         info!("文件上传成功: {} -> {}", filename, file_hash);
+        self.events.emit(StorageKind::Cloud, StorageOperation::Write, &file_hash, metadata.size);
         Ok(file_hash)
     }
 
+    /// 删除上传失败时已写入的块文件
+    async fn remove_chunk_files(&self, chunk_ids: &[String]) {
+        for chunk_id in chunk_ids {
+            let _ = fs::remove_file(self.config.storage_root.join(format!("{}.beycloud", chunk_id))).await;
+        }
+    }
+
     /// 从云存储下载文件
     ///
     /// # 参数
@@ -321,50 +529,48 @@ impl CloudStorage {
     ///
     /// 返回文件数据或错误
     pub async fn download_file(&self, file_hash: &str) -> CloudStorageResult<Vec<u8>> {
-        // 获取元数据
-        let metadata_bytes = self.db.get(file_hash.as_bytes())
-            .map_err(|e| ErrorInfo::new(6114, format!("查询元数据失败: {}", e))
-                .with_category(ErrorCategory::Database))?
-            .ok_or_else(|| ErrorInfo::new(6115, format!("文件不存在: {}", file_hash))
-                .with_category(ErrorCategory::FileSystem))?;
+        self.download_file_with_progress(file_hash, |_, _| {}).await
+    }
 
-        let metadata: FileMetadata = serde_json::from_slice(&metadata_bytes)
-            .map_err(|e| ErrorInfo::new(6116, format!("反序列化元数据失败: {}", e))
-                .with_category(ErrorCategory::Parse))?;
+    /// 从云存储下载文件，每读取一个块报告一次进度
+    ///
+    /// # 参数
+    ///
+    /// * `file_hash` - 文件哈希
+    /// * `on_progress` - 进度回调，参数为已读取字节数和总字节数；命中读缓存时直接报告完成
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件数据或错误
+    pub async fn download_file_with_progress(
+        &self,
+        file_hash: &str,
+        on_progress: impl Fn(u64, u64),
+    ) -> CloudStorageResult<Vec<u8>> {
+        let generation = match &self.cache {
+            Some(cache) => {
+                if let Some(data) = cache.get(file_hash) {
+                    debug!("文件读缓存命中: {} ({} 字节)", file_hash, data.len());
+                    on_progress(data.len() as u64, data.len() as u64);
+                    self.events.emit(StorageKind::Cloud, StorageOperation::Read, file_hash, data.len() as u64);
+                    return Ok(data.as_ref().clone());
+                }
+                Some(cache.generation())
+            }
+            None => None,
+        };
+
+        let metadata = self.read_metadata(file_hash)?;
 
         // 读取并组装所有块
         let mut file_data = Vec::with_capacity(metadata.size as usize);
 
         for (index, chunk_id) in metadata.chunk_ids.iter().enumerate() {
-            let chunk_filename = format!("{}.beycloud", chunk_id);
-            let chunk_path = self.config.storage_root.join(&chunk_filename);
-
-            // 读取块文件
-            let chunk_with_prefix = fs::read(&chunk_path).await
-                .map_err(|e| ErrorInfo::new(6117, format!("读取块文件失败: {}", e))
-                    .with_category(ErrorCategory::FileSystem))?;
-
-            // 提取并验证前缀
-            if chunk_with_prefix.len() < ChunkPrefix::SIZE {
-                return Err(ErrorInfo::new(6118, format!("块文件 {} 格式错误", chunk_filename))
-                    .with_category(ErrorCategory::Parse));
-            }
-
-            let prefix = ChunkPrefix::from_bytes(&chunk_with_prefix[..ChunkPrefix::SIZE])?;
-            
-            // 验证块索引
-            if prefix.chunk_index as usize != index {
-                return Err(ErrorInfo::new(6119, format!("块 {} 索引不匹配", chunk_filename))
-                    .with_category(ErrorCategory::Validation));
-            }
-
-            // 解压缩块数据
-            let compressed_data = &chunk_with_prefix[ChunkPrefix::SIZE..];
-            let decompressed = zstd::decode_all(compressed_data)
-                .map_err(|e| ErrorInfo::new(6120, format!("解压缩失败: {}", e))
-                    .with_category(ErrorCategory::Compression))?;
+            let chunk_with_prefix = self.read_chunk_file(chunk_id).await?;
+            let decompressed = Self::decode_chunk(index, chunk_id, &chunk_with_prefix)?;
 
             file_data.extend_from_slice(&decompressed);
+            on_progress(file_data.len() as u64, metadata.size);
             debug!("块 {}/{} 下载成功", index + 1, metadata.chunk_ids.len());
         }
 
@@ -375,10 +581,134 @@ impl CloudStorage {
                 .with_category(ErrorCategory::Validation));
         }
 
+        if let (Some(cache), Some(generation)) = (&self.cache, generation) {
+            cache.insert(file_hash, Arc::new(file_data.clone()), generation);
+        }
+
         info!("文件下载成功: {} ({} 字节)", file_hash, file_data.len());
+        self.events.emit(StorageKind::Cloud, StorageOperation::Read, file_hash, file_data.len() as u64);
         Ok(file_data)
     }
 
+    /// 查询文件元数据
+    ///
+    /// # 参数
+    ///
+    /// * `file_hash` - 文件哈希
+    ///
+    /// # 返回值
+    ///
+    /// 返回文件元数据，文件不存在时返回错误
+    pub fn file_metadata(&self, file_hash: &str) -> CloudStorageResult<FileMetadata> {
+        self.read_metadata(file_hash)
+    }
+
+    /// 读取文件中的一段字节
+    ///
+    /// 只读取并解压与请求范围重叠的块，逐块校验块哈希；范围超出文件末尾时截断到文件大小
+    ///
+    /// # 参数
+    ///
+    /// * `file_hash` - 文件哈希
+    /// * `offset` - 起始偏移
+    /// * `len` - 读取长度
+    ///
+    /// # 返回值
+    ///
+    /// 返回请求范围内的数据，起始偏移不小于文件大小时返回空数据
+    pub async fn read_range(&self, file_hash: &str, offset: u64, len: u64) -> CloudStorageResult<Vec<u8>> {
+        if let Some(data) = self.cache.as_ref().and_then(|cache| cache.get(file_hash)) {
+            let start = offset.min(data.len() as u64) as usize;
+            let end = offset.saturating_add(len).min(data.len() as u64) as usize;
+            self.events.emit(StorageKind::Cloud, StorageOperation::Read, file_hash, (end - start) as u64);
+            return Ok(data[start..end].to_vec());
+        }
+
+        let metadata = self.read_metadata(file_hash)?;
+        let start = offset.min(metadata.size);
+        let end = offset.saturating_add(len).min(metadata.size);
+        let mut range = Vec::with_capacity((end - start) as usize);
+        if start == end {
+            return Ok(range);
+        }
+
+        // 已知块大小时直接定位到第一个重叠的块，旧版本上传的文件从头逐块定位
+        let (first_index, mut chunk_start) = match start.checked_div(metadata.chunk_size) {
+            Some(index) => (index as usize, index * metadata.chunk_size),
+            None => (0, 0),
+        };
+
+        for (index, chunk_id) in metadata.chunk_ids.iter().enumerate().skip(first_index) {
+            if chunk_start >= end {
+                break;
+            }
+
+            let chunk_with_prefix = self.read_chunk_file(chunk_id).await?;
+            if Self::calculate_hash(&chunk_with_prefix) != *chunk_id {
+                return Err(ErrorInfo::new(6133, format!("块 {} 哈希验证失败", chunk_id))
+                    .with_category(ErrorCategory::Validation));
+            }
+            let chunk = Self::decode_chunk(index, chunk_id, &chunk_with_prefix)?;
+            let chunk_end = chunk_start + chunk.len() as u64;
+
+            if chunk_end > start {
+                let from = start.saturating_sub(chunk_start) as usize;
+                let to = (end.min(chunk_end) - chunk_start) as usize;
+                range.extend_from_slice(&chunk[from..to]);
+            }
+            chunk_start = chunk_end;
+        }
+
+        debug!("读取文件范围: {} [{}, {}) ({} 字节)", file_hash, start, end, range.len());
+        self.events.emit(StorageKind::Cloud, StorageOperation::Read, file_hash, range.len() as u64);
+        Ok(range)
+    }
+
+    /// 读取文件元数据
+    fn read_metadata(&self, file_hash: &str) -> CloudStorageResult<FileMetadata> {
+        let metadata_bytes = self.db.get(file_hash.as_bytes())
+            .map_err(|e| ErrorInfo::new(6114, format!("查询元数据失败: {}", e))
+                .with_category(ErrorCategory::Database))?
+            .ok_or_else(|| ErrorInfo::new(6115, format!("文件不存在: {}", file_hash))
+                .with_category(ErrorCategory::FileSystem))?;
+
+        serde_json::from_slice(&metadata_bytes)
+            .map_err(|e| ErrorInfo::new(6116, format!("反序列化元数据失败: {}", e))
+                .with_category(ErrorCategory::Parse))
+    }
+
+    /// 读取块文件（含前缀）
+    async fn read_chunk_file(&self, chunk_id: &str) -> CloudStorageResult<Vec<u8>> {
+        let chunk_path = self.config.storage_root.join(format!("{}.beycloud", chunk_id));
+        fs::read(&chunk_path).await
+            .map_err(|e| ErrorInfo::new(6117, format!("读取块文件失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))
+    }
+
+    /// 校验块前缀并解压块数据
+    fn decode_chunk(index: usize, chunk_id: &str, chunk_with_prefix: &[u8]) -> CloudStorageResult<Vec<u8>> {
+        let chunk_filename = format!("{}.beycloud", chunk_id);
+
+        // 提取并验证前缀
+        if chunk_with_prefix.len() < ChunkPrefix::SIZE {
+            return Err(ErrorInfo::new(6118, format!("块文件 {} 格式错误", chunk_filename))
+                .with_category(ErrorCategory::Parse));
+        }
+
+        let prefix = ChunkPrefix::from_bytes(&chunk_with_prefix[..ChunkPrefix::SIZE])?;
+
+        // 验证块索引
+        if prefix.chunk_index as usize != index {
+            return Err(ErrorInfo::new(6119, format!("块 {} 索引不匹配", chunk_filename))
+                .with_category(ErrorCategory::Validation));
+        }
+
+        // 解压缩块数据
+        zstd::decode_all(&chunk_with_prefix[ChunkPrefix::SIZE..])
+            .map_err(|e| ErrorInfo::new(6120, format!("解压缩失败: {}", e))
+                .with_category(ErrorCategory::Compression))
+    }
+
     /// 删除文件
     ///
     /// # 参数
@@ -413,14 +743,93 @@ impl CloudStorage {
         }
 
         // 从数据库删除元数据
-        self.db.remove(file_hash.as_bytes())
+        self.db.delete(file_hash.as_bytes())
             .map_err(|e| ErrorInfo::new(6126, format!("删除元数据失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
+        let _ = self.used_bytes.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| Some(used.saturating_sub(metadata.size)));
+        self.invalidate_cache(file_hash);
+
         info!("文件删除成功: {}", file_hash);
+        self.events.emit(StorageKind::Cloud, StorageOperation::Delete, file_hash, metadata.size);
         Ok(())
     }
 
+    /// 已存储的文件数量
+    pub fn file_count(&self) -> usize {
+        self.db.len()
+    }
+
+    /// 已存储文件的原始大小总和（字节）
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes.load(Ordering::Relaxed)
+    }
+
+    /// 元数据库占用的磁盘空间（字节）
+    pub fn size_on_disk(&self) -> u64 {
+        self.db.size_on_disk()
+    }
+
+    /// 压缩元数据库
+    ///
+    /// 在阻塞线程池中执行，分批重写存活数据并刷盘，不会长时间阻塞读取。
+    ///
+    /// # 返回值
+    ///
+    /// 返回回收的字节数或错误
+    pub async fn compact(&self) -> CloudStorageResult<u64> {
+        let db = Arc::clone(&self.db);
+        let reclaimed = tokio::task::spawn_blocking(move || db.compact()).await
+            .map_err(|e| ErrorInfo::new(6129, format!("压缩任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6129, format!("压缩元数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+
+        info!("云存储元数据库压缩完成，回收 {} 字节", reclaimed);
+        Ok(reclaimed)
+    }
+
+    /// 将元数据库缓冲的写入刷到磁盘
+    pub async fn flush(&self) -> CloudStorageResult<()> {
+        let db = Arc::clone(&self.db);
+        tokio::task::spawn_blocking(move || db.flush()).await
+            .map_err(|e| ErrorInfo::new(6132, format!("刷盘任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6132, format!("刷新元数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))
+    }
+
+    /// 检查文件的所有块是否存在且内容与块哈希一致
+    ///
+    /// 直接读取磁盘上的块文件，不经过读缓存，也不修改任何数据
+    ///
+    /// # 参数
+    ///
+    /// * `metadata` - 文件元数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回发现的第一个问题，所有块完好时返回 `None`
+    pub async fn verify_file(&self, metadata: &FileMetadata) -> CloudStorageResult<Option<IntegrityIssueKind>> {
+        for chunk_id in &metadata.chunk_ids {
+            let chunk_path = self.config.storage_root.join(format!("{}.beycloud", chunk_id));
+            let chunk_with_prefix = match fs::read(&chunk_path).await {
+                Ok(data) => data,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Some(IntegrityIssueKind::MissingChunk { chunk_id: chunk_id.clone() }));
+                }
+                Err(e) => return Err(ErrorInfo::new(6117, format!("读取块文件失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)),
+            };
+
+            if Self::calculate_hash(&chunk_with_prefix) != *chunk_id {
+                return Ok(Some(IntegrityIssueKind::CorruptChunk { chunk_id: chunk_id.clone() }));
+            }
+        }
+
+        Ok(None)
+    }
+
     /// 列出所有文件
     ///
     /// # 返回值
@@ -429,11 +838,11 @@ impl CloudStorage {
     pub fn list_files(&self) -> CloudStorageResult<Vec<FileMetadata>> {
         let mut files = Vec::new();
 
-        for item in self.db.iter() {
-            let (_key, value) = item
-                .map_err(|e| ErrorInfo::new(6127, format!("遍历数据库失败: {}", e))
-                    .with_category(ErrorCategory::Database))?;
+        let items = self.db.scan(&[])
+            .map_err(|e| ErrorInfo::new(6127, format!("遍历数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
 
+        for (_key, value) in items {
             let metadata: FileMetadata = serde_json::from_slice(&value)
                 .map_err(|e| ErrorInfo::new(6128, format!("反序列化元数据失败: {}", e))
                     .with_category(ErrorCategory::Parse))?;
@@ -445,11 +854,60 @@ impl CloudStorage {
     }
 }
 
+/// 流式计算内容哈希
+///
+/// 分块读取数据，结果与 [`CloudStorage::upload_file`] 返回的文件哈希一致，
+/// 上传大文件前可用于检查云存储中是否已有相同内容
+///
+/// # 参数
+///
+/// * `reader` - 数据来源
+///
+/// # 返回值
+///
+/// 返回十六进制格式的文件哈希或错误
+pub async fn hash_reader<R: AsyncRead + Unpin>(mut reader: R) -> CloudStorageResult<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = reader.read(&mut buffer).await
+            .map_err(|e| ErrorInfo::new(6131, format!("读取数据失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[tokio::test]
+    async fn test_hash_reader_matches_uploaded_file() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = CloudStorageConfig {
+            storage_root: temp_dir.path().join("storage"),
+            db_path: temp_dir.path().join("db"),
+            chunk_size: 1024,
+            ..Default::default()
+        };
+        let storage = CloudStorage::new(config).await.expect("创建云存储失败");
+
+        let test_data = b"streaming hash test data ".repeat(10_000);
+        let hash = hash_reader(&test_data[..]).await.expect("计算哈希失败");
+        assert!(!storage.exists_by_hash(&hash));
+
+        let file_hash = storage.upload_file("large.bin", &test_data).await.expect("上传失败");
+        assert_eq!(hash, file_hash);
+        assert!(storage.exists_by_hash(&hash));
+    }
+
     #[tokio::test]
     async fn test_cloud_storage_upload_download() {
         let temp_dir = tempdir().expect("创建临时目录失败");
@@ -467,6 +925,8 @@ mod tests {
         
         // 上传
         let file_hash = storage.upload_file("test.txt", &test_data).await.expect("上传失败");
+        assert_eq!(storage.file_count(), 1);
+        assert_eq!(storage.used_bytes(), test_data.len() as u64);
         
         // 下载
         let downloaded = storage.download_file(&file_hash).await.expect("下载失败");
@@ -474,6 +934,63 @@ mod tests {
         
         // 删除
         storage.delete_file(&file_hash).await.expect("删除失败");
+        assert_eq!(storage.file_count(), 0);
+        assert_eq!(storage.used_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cloud_storage_read_range() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = CloudStorageConfig {
+            storage_root: temp_dir.path().join("storage"),
+            db_path: temp_dir.path().join("db"),
+            chunk_size: 1024,
+            ..Default::default()
+        };
+        let storage = CloudStorage::new(config).await.expect("创建云存储失败");
+
+        let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let file_hash = storage.upload_file("media.bin", &data).await.expect("上传失败");
+
+        // 块内、跨块、从块边界开始、截断到文件末尾
+        for (offset, len) in [(0u64, 10u64), (100, 500), (1000, 100), (1024, 1024), (900, 2500), (4990, 100)] {
+            let range = storage.read_range(&file_hash, offset, len).await.expect("读取范围失败");
+            let end = (offset + len).min(data.len() as u64) as usize;
+            assert_eq!(range, &data[offset as usize..end], "范围 {}+{} 不匹配", offset, len);
+        }
+        assert!(storage.read_range(&file_hash, 6000, 10).await.expect("读取范围失败").is_empty());
+        assert!(storage.read_range(&file_hash, 10, 0).await.expect("读取范围失败").is_empty());
+
+        // 旧版本元数据没有块大小时从头定位
+        let mut metadata = storage.read_metadata(&file_hash).expect("读取元数据失败");
+        metadata.chunk_size = 0;
+        storage.db.put(file_hash.as_bytes(), serde_json::to_vec(&metadata).expect("序列化失败"))
+            .expect("写入元数据失败");
+        let range = storage.read_range(&file_hash, 2000, 1500).await.expect("读取范围失败");
+        assert_eq!(range, &data[2000..3500]);
+    }
+
+    #[tokio::test]
+    async fn test_cloud_storage_quota() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = CloudStorageConfig {
+            storage_root: temp_dir.path().join("storage"),
+            db_path: temp_dir.path().join("db"),
+            max_local_storage: 100,
+            ..Default::default()
+        };
+
+        let storage = CloudStorage::new(config).await.expect("创建云存储失败");
+        storage.upload_file("a.txt", &[1u8; 60]).await.expect("上传失败");
+
+        let error = storage.upload_file("b.txt", &[2u8; 60]).await
+            .expect_err("超出配额时应返回错误");
+        assert_eq!(error.code(), 6130);
+        assert_eq!(storage.file_count(), 1);
+
+        // 配额内的上传不受影响
+        storage.upload_file("c.txt", &[3u8; 40]).await.expect("上传失败");
+        assert_eq!(storage.used_bytes(), 100);
     }
 
     #[tokio::test]
@@ -490,4 +1007,29 @@ mod tests {
         assert_eq!(decoded.chunk_index, 0);
         assert_eq!(decoded.total_chunks, 10);
     }
+
+    #[tokio::test]
+    async fn test_cloud_storage_read_cache() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = CloudStorageConfig {
+            storage_root: temp_dir.path().join("storage"),
+            db_path: temp_dir.path().join("db"),
+            read_cache_bytes: Some(64 * 1024),
+            ..Default::default()
+        };
+        let storage = CloudStorage::new(config).await.expect("创建云存储失败");
+
+        let test_data = b"hot cloud file".repeat(100);
+        let file_hash = storage.upload_file("hot.txt", &test_data).await.expect("上传失败");
+
+        assert_eq!(storage.download_file(&file_hash).await.expect("下载失败"), test_data);
+        assert_eq!(storage.download_file(&file_hash).await.expect("下载失败"), test_data);
+        let stats = storage.cache_stats().expect("应启用读缓存");
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        // 删除使缓存失效，不会再从缓存返回已删除的文件
+        storage.delete_file(&file_hash).await.expect("删除失败");
+        assert_eq!(storage.cache_stats().expect("应启用读缓存").entries, 0);
+        assert!(storage.download_file(&file_hash).await.is_err());
+    }
 }
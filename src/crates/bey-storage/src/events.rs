@@ -0,0 +1,101 @@
+//! # 存储事件模块
+//!
+//! 对象存储和云存储在写入、读取、删除、重命名成功后广播存储事件，
+//! 插件和界面可以订阅事件并作出响应。
+//!
+//! 事件通过 `tokio::sync::broadcast` 发送，发送操作从不阻塞：
+//! 没有订阅者时事件被直接丢弃，订阅者处理过慢时只会丢失最旧的事件。
+
+use tokio::sync::broadcast;
+
+/// 事件通道容量
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// 事件来源的存储类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StorageKind {
+    /// 对象存储
+    Object,
+    /// 云存储
+    Cloud,
+}
+
+/// 存储操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum StorageOperation {
+    /// 写入
+    Write,
+    /// 读取
+    Read,
+    /// 删除
+    Delete,
+    /// 重命名，事件的键为新的标识符
+    Rename,
+}
+
+/// 存储事件
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StorageEvent {
+    /// 事件来源
+    pub storage: StorageKind,
+    /// 操作类型
+    pub operation: StorageOperation,
+    /// 对象ID（对象存储）或文件哈希（云存储）
+    pub key: String,
+    /// 数据大小（字节）
+    pub size: u64,
+    /// 重命名前的标识符，其他操作为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_key: Option<String>,
+}
+
+/// 存储事件广播器
+///
+/// 克隆的广播器共享同一个通道，订阅者可以收到所有克隆发出的事件
+#[derive(Debug, Clone)]
+pub struct StorageEventBus {
+    sender: broadcast::Sender<StorageEvent>,
+}
+
+impl StorageEventBus {
+    /// 创建事件广播器
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// 订阅存储事件
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 广播存储事件，没有订阅者时直接返回
+    pub(crate) fn emit(&self, storage: StorageKind, operation: StorageOperation, key: &str, size: u64) {
+        self.send(storage, operation, key, size, None);
+    }
+
+    /// 广播重命名事件，没有订阅者时直接返回
+    pub(crate) fn emit_rename(&self, storage: StorageKind, from_key: &str, to_key: &str, size: u64) {
+        self.send(storage, StorageOperation::Rename, to_key, size, Some(from_key));
+    }
+
+    fn send(&self, storage: StorageKind, operation: StorageOperation, key: &str, size: u64, previous_key: Option<&str>) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+
+        let _ = self.sender.send(StorageEvent {
+            storage,
+            operation,
+            key: key.to_string(),
+            size,
+            previous_key: previous_key.map(str::to_string),
+        });
+    }
+}
+
+impl Default for StorageEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
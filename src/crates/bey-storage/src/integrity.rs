@@ -0,0 +1,71 @@
+//! # 完整性扫描模块
+//!
+//! 定义存储完整性扫描的报告类型。扫描逐个重新计算对象存储和云存储中数据的校验和，
+//! 只报告损坏或缺少块的条目，不删除任何数据。
+
+use crate::events::StorageKind;
+
+/// 完整性问题类型
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum IntegrityIssueKind {
+    /// 对象内容与记录的校验和不一致
+    Corrupt,
+    /// 云存储文件的块内容与块哈希不一致
+    CorruptChunk {
+        /// 块ID
+        chunk_id: String,
+    },
+    /// 云存储文件缺少块文件
+    MissingChunk {
+        /// 块ID
+        chunk_id: String,
+    },
+    /// 读取数据失败，无法校验
+    Unreadable {
+        /// 失败原因
+        reason: String,
+    },
+}
+
+/// 完整性问题
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IntegrityIssue {
+    /// 问题所在的存储
+    pub storage: StorageKind,
+    /// 对象ID（对象存储）或文件哈希（云存储）
+    pub key: String,
+    /// 问题类型
+    pub kind: IntegrityIssueKind,
+}
+
+/// 完整性扫描报告
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IntegrityReport {
+    /// 已校验的对象数量
+    pub objects_checked: u64,
+    /// 没有记录校验和、无法校验的对象
+    pub unverified_objects: Vec<String>,
+    /// 已校验的云存储文件数量
+    pub files_checked: u64,
+    /// 发现的问题
+    pub issues: Vec<IntegrityIssue>,
+    /// 扫描是否被取消（取消时报告只包含已扫描的部分）
+    pub cancelled: bool,
+}
+
+impl IntegrityReport {
+    /// 扫描完成且没有发现问题
+    pub fn is_clean(&self) -> bool {
+        !self.cancelled && self.issues.is_empty()
+    }
+
+    /// 指定条目是否有问题
+    ///
+    /// # 参数
+    ///
+    /// * `storage` - 存储类型
+    /// * `key` - 对象ID或文件哈希
+    pub fn has_issue(&self, storage: StorageKind, key: &str) -> bool {
+        self.issues.iter().any(|issue| issue.storage == storage && issue.key == key)
+    }
+}
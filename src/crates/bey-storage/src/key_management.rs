@@ -183,7 +183,7 @@ pub struct AccessLogEntry {
 }
 
 /// 密钥操作类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum KeyOperation {
     /// 创建密钥
     Create,
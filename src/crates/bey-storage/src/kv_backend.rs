@@ -0,0 +1,591 @@
+//! # 键值存储后端模块
+//!
+//! 定义云存储、剪切板和消息系统共用的键值存储接口，
+//! 提供基于sled的持久化实现和基于内存的实现（用于测试和嵌入式目标）。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, TryLockError};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// 键值存储结果类型
+pub type KvResult<T> = std::result::Result<T, ErrorInfo>;
+
+/// 压缩时每批重写的键数量
+const COMPACTION_BATCH_SIZE: usize = 256;
+
+/// 键值存储后端类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum KvBackendKind {
+    /// sled数据库（持久化，默认）
+    #[default]
+    Sled,
+    /// 内存存储（进程退出后丢失）
+    Memory,
+}
+
+/// 键值存储后端
+///
+/// 键按字节序排列，`scan` 返回的结果按键升序排列
+pub trait KvBackend: Send + Sync {
+    /// 获取键对应的值
+    fn get(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>>;
+
+    /// 写入键值对
+    fn put(&self, key: &[u8], value: Vec<u8>) -> KvResult<()>;
+
+    /// 删除键，返回被删除的值
+    fn delete(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>>;
+
+    /// 扫描指定前缀的所有键值对（空前缀表示全部）
+    fn scan(&self, prefix: &[u8]) -> KvResult<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// 检查键是否存在
+    fn contains_key(&self, key: &[u8]) -> KvResult<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// 键值对数量
+    fn len(&self) -> usize {
+        self.scan(&[]).map(|items| items.len()).unwrap_or(0)
+    }
+
+    /// 是否没有键值对
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 清空所有键值对
+    fn clear(&self) -> KvResult<()> {
+        for (key, _) in self.scan(&[])? {
+            self.delete(&key)?;
+        }
+        Ok(())
+    }
+
+    /// 占用的磁盘空间（字节），非持久化后端返回0
+    fn size_on_disk(&self) -> u64 {
+        0
+    }
+
+    /// 压缩存储，回收已删除数据占用的空间
+    ///
+    /// 返回回收的字节数，非持久化后端无需压缩，返回0
+    fn compact(&self) -> KvResult<u64> {
+        Ok(0)
+    }
+
+    /// 将缓冲的写入刷到磁盘，非持久化后端无需刷盘
+    fn flush(&self) -> KvResult<()> {
+        Ok(())
+    }
+}
+
+/// 启动周期性压缩任务
+///
+/// 任务只持有后端的弱引用，后端被释放后任务自动退出。
+/// 压缩在阻塞线程池中执行，不占用异步运行时的工作线程。
+///
+/// # 参数
+///
+/// * `backend` - 要压缩的存储后端
+/// * `interval` - 压缩间隔
+/// * `label` - 日志中使用的存储名称
+///
+/// # 返回值
+///
+/// 返回压缩任务句柄
+pub fn spawn_compaction_task(
+    backend: &Arc<dyn KvBackend>,
+    interval: Duration,
+    label: &'static str,
+) -> tokio::task::JoinHandle<()> {
+    let backend = Arc::downgrade(backend);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次触发立即返回，跳过
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let Some(backend) = backend.upgrade() else {
+                debug!("{}已释放，压缩任务退出", label);
+                break;
+            };
+
+            match tokio::task::spawn_blocking(move || backend.compact()).await {
+                Ok(Ok(reclaimed)) => info!("{}定期压缩完成，回收 {} 字节", label, reclaimed),
+                Ok(Err(e)) => warn!("{}定期压缩失败: {}", label, e),
+                Err(e) => warn!("{}压缩任务异常终止: {}", label, e),
+            }
+        }
+    })
+}
+
+/// 判断序列化条目在指定时间（Unix毫秒时间戳）是否已过期
+pub type ExpiryCheck = fn(&[u8], u64) -> bool;
+
+/// 当前时间（Unix毫秒时间戳），用于条目过期判断
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or(0)
+}
+
+/// 根据存活时间计算过期时间（Unix毫秒时间戳）
+pub(crate) fn expires_after(ttl: Duration) -> u64 {
+    now_millis().saturating_add(u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// 删除已过期的条目
+///
+/// 删除前重新读取条目，扫描后被重新写入且不再过期的条目不会被删除
+///
+/// # 参数
+///
+/// * `backend` - 存储后端
+/// * `is_expired` - 过期判断函数
+///
+/// # 返回值
+///
+/// 返回删除的条目数量或错误
+pub fn sweep_expired(backend: &dyn KvBackend, is_expired: ExpiryCheck) -> KvResult<usize> {
+    let now = now_millis();
+    let mut removed = 0;
+
+    for (key, value) in backend.scan(&[])? {
+        if !is_expired(&value, now) {
+            continue;
+        }
+        let still_expired = backend.get(&key)?.is_some_and(|current| is_expired(&current, now));
+        if still_expired && backend.delete(&key)?.is_some() {
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// 启动周期性过期清理任务
+///
+/// 与压缩任务一样只持有后端的弱引用，后端被释放后任务自动退出，
+/// 清理在阻塞线程池中执行。
+///
+/// # 参数
+///
+/// * `backend` - 要清理的存储后端
+/// * `interval` - 清理间隔
+/// * `label` - 日志中使用的存储名称
+/// * `is_expired` - 过期判断函数
+///
+/// # 返回值
+///
+/// 返回清理任务句柄
+pub fn spawn_expiry_sweeper(
+    backend: &Arc<dyn KvBackend>,
+    interval: Duration,
+    label: &'static str,
+    is_expired: ExpiryCheck,
+) -> tokio::task::JoinHandle<()> {
+    let backend = Arc::downgrade(backend);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // 第一次触发立即返回，跳过
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let Some(backend) = backend.upgrade() else {
+                debug!("{}已释放，过期清理任务退出", label);
+                break;
+            };
+
+            match tokio::task::spawn_blocking(move || sweep_expired(backend.as_ref(), is_expired)).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(removed)) => debug!("{}清理 {} 个过期条目", label, removed),
+                Ok(Err(e)) => warn!("{}过期清理失败: {}", label, e),
+                Err(e) => warn!("{}过期清理任务异常终止: {}", label, e),
+            }
+        }
+    })
+}
+
+/// 打开指定类型的存储后端
+///
+/// # 参数
+///
+/// * `kind` - 后端类型
+/// * `path` - 数据库路径（内存后端忽略）
+///
+/// # 返回值
+///
+/// 返回存储后端或错误
+pub fn open_backend(kind: KvBackendKind, path: &Path) -> KvResult<Arc<dyn KvBackend>> {
+    match kind {
+        KvBackendKind::Sled => Ok(Arc::new(SledBackend::open(path)?)),
+        KvBackendKind::Memory => Ok(Arc::new(MemoryBackend::new())),
+    }
+}
+
+/// 基于sled的存储后端
+pub struct SledBackend {
+    /// 当前数据库，压缩成功打开重建的数据库后才被替换
+    db: RwLock<sled::Db>,
+    /// 数据库路径
+    path: PathBuf,
+    /// 保证同一时间只有一个压缩任务
+    compaction: Mutex<()>,
+}
+
+impl SledBackend {
+    /// 打开sled数据库
+    pub fn open(path: &Path) -> KvResult<Self> {
+        let db = sled::open(path)
+            .map_err(|e| ErrorInfo::new(6401, format!("打开数据库失败: {}", e))
+                .with_category(ErrorCategory::Database)
+                .with_severity(ErrorSeverity::Error))?;
+
+        Ok(Self {
+            db: RwLock::new(db),
+            path: path.to_path_buf(),
+            compaction: Mutex::new(()),
+        })
+    }
+
+    fn db_error(code: u32, action: &str, e: sled::Error) -> ErrorInfo {
+        ErrorInfo::new(code, format!("{}失败: {}", action, e))
+            .with_category(ErrorCategory::Database)
+    }
+
+    fn unavailable_error() -> ErrorInfo {
+        ErrorInfo::new(6409, "数据库不可用".to_string())
+            .with_category(ErrorCategory::Database)
+            .with_severity(ErrorSeverity::Error)
+    }
+
+    fn io_error(action: &str, e: std::io::Error) -> ErrorInfo {
+        ErrorInfo::new(6408, format!("{}失败: {}", action, e))
+            .with_category(ErrorCategory::FileSystem)
+            .with_severity(ErrorSeverity::Error)
+    }
+
+    /// 在读锁保护下访问数据库
+    ///
+    /// 操作期间持有读锁，压缩替换数据库时会等待进行中的操作完成
+    fn with_db<T>(&self, f: impl FnOnce(&sled::Db) -> KvResult<T>) -> KvResult<T> {
+        let guard = self.db.read().map_err(|_| Self::unavailable_error())?;
+        f(&guard)
+    }
+
+    /// 在路径后追加后缀
+    fn sibling_path(&self, suffix: &str) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// 将订阅到的变更应用到重建的数据库
+    fn replay_events(subscriber: &mut sled::Subscriber, target: &sled::Db) -> KvResult<usize> {
+        let mut replayed = 0usize;
+        while let Ok(event) = subscriber.next_timeout(Duration::ZERO) {
+            match event {
+                sled::Event::Insert { key, value } => {
+                    target.insert(key, value).map_err(|e| Self::db_error(6408, "压缩", e))?;
+                }
+                sled::Event::Remove { key } => {
+                    target.remove(key).map_err(|e| Self::db_error(6408, "压缩", e))?;
+                }
+            }
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+
+    /// 把重建的数据库移动到数据库路径并打开
+    ///
+    /// 调用方持有写锁，当前数据库已刷盘且仍然打开；任何一步失败都把文件恢复原位，
+    /// 当前数据库的句柄继续可用。成功后原数据库位于 `.retired` 目录，由调用方在关闭后删除
+    fn swap_in(&self, rebuilt_path: &Path) -> KvResult<sled::Db> {
+        let retired_path = self.sibling_path(".retired");
+        if retired_path.exists() {
+            std::fs::remove_dir_all(&retired_path).map_err(|e| Self::io_error("清理旧数据库", e))?;
+        }
+
+        std::fs::rename(&self.path, &retired_path).map_err(|e| Self::io_error("移动旧数据库", e))?;
+        if let Err(e) = std::fs::rename(rebuilt_path, &self.path) {
+            // 恢复原数据库
+            let _ = std::fs::rename(&retired_path, &self.path);
+            return Err(Self::io_error("替换数据库", e));
+        }
+
+        sled::open(&self.path).map_err(|e| {
+            // 恢复原数据库
+            let _ = std::fs::rename(&self.path, rebuilt_path);
+            let _ = std::fs::rename(&retired_path, &self.path);
+            Self::db_error(6401, "打开数据库", e)
+        })
+    }
+}
+
+impl KvBackend for SledBackend {
+    fn get(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>> {
+        self.with_db(|db| db.get(key)
+            .map(|value| value.map(|v| v.to_vec()))
+            .map_err(|e| Self::db_error(6402, "查询", e)))
+    }
+
+    fn put(&self, key: &[u8], value: Vec<u8>) -> KvResult<()> {
+        self.with_db(|db| db.insert(key, value)
+            .map(|_| ())
+            .map_err(|e| Self::db_error(6403, "存储", e)))
+    }
+
+    fn delete(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>> {
+        self.with_db(|db| db.remove(key)
+            .map(|value| value.map(|v| v.to_vec()))
+            .map_err(|e| Self::db_error(6404, "删除", e)))
+    }
+
+    fn scan(&self, prefix: &[u8]) -> KvResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.with_db(|db| db.scan_prefix(prefix)
+            .map(|item| item
+                .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                .map_err(|e| Self::db_error(6405, "遍历数据库", e)))
+            .collect())
+    }
+
+    fn contains_key(&self, key: &[u8]) -> KvResult<bool> {
+        self.with_db(|db| db.contains_key(key)
+            .map_err(|e| Self::db_error(6402, "查询", e)))
+    }
+
+    fn len(&self) -> usize {
+        self.with_db(|db| Ok(db.len())).unwrap_or(0)
+    }
+
+    fn clear(&self) -> KvResult<()> {
+        self.with_db(|db| db.clear()
+            .map_err(|e| Self::db_error(6406, "清空", e)))
+    }
+
+    fn size_on_disk(&self) -> u64 {
+        self.with_db(|db| Ok(db.size_on_disk().unwrap_or(0))).unwrap_or(0)
+    }
+
+    fn flush(&self) -> KvResult<()> {
+        self.with_db(|db| db.flush()
+            .map(|_| ())
+            .map_err(|e| Self::db_error(6410, "刷盘", e)))
+    }
+
+    /// 将存活的键值对重建到新的数据库文件并替换原数据库
+    ///
+    /// sled不会收缩已增长的日志文件，只有重建才能真正归还磁盘空间。
+    /// 数据分批复制，期间读写照常进行，复制过程中的变更通过订阅重放到新数据库；
+    /// 只有最后重放剩余变更和替换文件时短暂阻塞读写。
+    fn compact(&self) -> KvResult<u64> {
+        let _compaction = self.compaction.lock().map_err(|_| Self::unavailable_error())?;
+        let before = self.size_on_disk();
+
+        let rebuilt_path = self.sibling_path(".compacting");
+        if rebuilt_path.exists() {
+            std::fs::remove_dir_all(&rebuilt_path).map_err(|e| Self::io_error("清理压缩目录", e))?;
+        }
+        let rebuilt = sled::open(&rebuilt_path).map_err(|e| Self::db_error(6408, "创建压缩数据库", e))?;
+
+        // 先订阅变更再复制，复制期间的写入和删除随后重放。
+        // 在写锁下订阅，确保没有进行中的写入错过订阅
+        let mut subscriber = {
+            self.db.write().map_err(|_| Self::unavailable_error())?.watch_prefix(vec![])
+        };
+
+        let mut last_key: Option<Vec<u8>> = None;
+        let mut copied = 0usize;
+        let mut replayed = 0usize;
+        loop {
+            let batch: Vec<(sled::IVec, sled::IVec)> = self.with_db(|db| match &last_key {
+                None => db.iter().take(COMPACTION_BATCH_SIZE).collect::<Result<_, _>>(),
+                Some(last) => db
+                    .range::<Vec<u8>, _>((Bound::Excluded(last.clone()), Bound::Unbounded))
+                    .take(COMPACTION_BATCH_SIZE)
+                    .collect::<Result<_, _>>(),
+            }
+            .map_err(|e| Self::db_error(6405, "遍历数据库", e)))?;
+
+            if batch.is_empty() {
+                break;
+            }
+
+            for (key, value) in &batch {
+                rebuilt.insert(key, value.clone()).map_err(|e| Self::db_error(6408, "压缩", e))?;
+            }
+
+            copied += batch.len();
+            last_key = batch.last().map(|(key, _)| key.to_vec());
+
+            // 及时消费变更，订阅通道写满时写入方会被阻塞
+            replayed += Self::replay_events(&mut subscriber, &rebuilt)?;
+            std::thread::yield_now();
+        }
+
+        rebuilt.flush().map_err(|e| Self::db_error(6408, "压缩刷盘", e))?;
+
+        // 等待写锁期间继续消费变更，避免写入方阻塞在订阅通道上而无法释放读锁
+        let mut guard = loop {
+            replayed += Self::replay_events(&mut subscriber, &rebuilt)?;
+            match self.db.try_write() {
+                Ok(guard) => break guard,
+                Err(TryLockError::WouldBlock) => std::thread::yield_now(),
+                Err(TryLockError::Poisoned(_)) => return Err(Self::unavailable_error()),
+            }
+        };
+
+        // 短暂阻塞读写：重放剩余变更并替换数据库
+        replayed += Self::replay_events(&mut subscriber, &rebuilt)?;
+        rebuilt.flush().map_err(|e| Self::db_error(6408, "压缩刷盘", e))?;
+        drop(subscriber);
+        drop(rebuilt);
+
+        guard.flush().map_err(|e| Self::db_error(6408, "压缩刷盘", e))?;
+
+        let db = match self.swap_in(&rebuilt_path) {
+            Ok(db) => db,
+            Err(e) => {
+                // 替换失败时继续使用原数据库
+                let _ = std::fs::remove_dir_all(&rebuilt_path);
+                return Err(e);
+            }
+        };
+        let after = db.size_on_disk().unwrap_or(0);
+        *guard = db;
+        drop(guard);
+
+        // 原数据库的句柄已关闭
+        if let Err(e) = std::fs::remove_dir_all(self.sibling_path(".retired")) {
+            warn!("删除旧数据库失败: {}", e);
+        }
+
+        debug!("数据库压缩完成: 复制 {} 个键, 重放 {} 个变更, {} -> {} 字节", copied, replayed, before, after);
+        Ok(before.saturating_sub(after))
+    }
+}
+
+/// 基于内存的存储后端
+#[derive(Default)]
+pub struct MemoryBackend {
+    map: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    /// 创建空的内存存储
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock_error() -> ErrorInfo {
+        ErrorInfo::new(6407, "内存存储锁已损坏".to_string())
+            .with_category(ErrorCategory::Database)
+            .with_severity(ErrorSeverity::Error)
+    }
+}
+
+impl KvBackend for MemoryBackend {
+    fn get(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>> {
+        let map = self.map.read().map_err(|_| Self::lock_error())?;
+        Ok(map.get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: Vec<u8>) -> KvResult<()> {
+        let mut map = self.map.write().map_err(|_| Self::lock_error())?;
+        map.insert(key.to_vec(), value);
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> KvResult<Option<Vec<u8>>> {
+        let mut map = self.map.write().map_err(|_| Self::lock_error())?;
+        Ok(map.remove(key))
+    }
+
+    fn scan(&self, prefix: &[u8]) -> KvResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let map = self.map.read().map_err(|_| Self::lock_error())?;
+        Ok(map.range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn len(&self) -> usize {
+        self.map.read().map(|map| map.len()).unwrap_or(0)
+    }
+
+    fn clear(&self) -> KvResult<()> {
+        let mut map = self.map.write().map_err(|_| Self::lock_error())?;
+        map.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sled_compaction_keeps_concurrent_writes() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let backend = Arc::new(SledBackend::open(&temp_dir.path().join("kv.db")).expect("打开数据库失败"));
+
+        for i in 0..2000u32 {
+            backend.put(format!("old-{:05}", i).as_bytes(), vec![0u8; 1024]).expect("写入失败");
+        }
+        for i in 10..2000u32 {
+            backend.delete(format!("old-{:05}", i).as_bytes()).expect("删除失败");
+        }
+
+        // 压缩期间持续写入和删除
+        let writer = {
+            let backend = Arc::clone(&backend);
+            std::thread::spawn(move || {
+                for i in 0..500u32 {
+                    backend.put(format!("new-{:05}", i).as_bytes(), i.to_be_bytes().to_vec()).expect("写入失败");
+                }
+                backend.delete(b"old-00000").expect("删除失败");
+            })
+        };
+
+        let before = backend.size_on_disk();
+        backend.compact().expect("压缩失败");
+        writer.join().expect("写入线程异常");
+
+        assert!(backend.size_on_disk() < before);
+        assert_eq!(backend.len(), 9 + 500);
+        assert!(backend.get(b"old-00000").expect("查询失败").is_none());
+        assert_eq!(backend.get(b"old-00009").expect("查询失败"), Some(vec![0u8; 1024]));
+        for i in 0..500u32 {
+            assert_eq!(backend.get(format!("new-{:05}", i).as_bytes()).expect("查询失败"), Some(i.to_be_bytes().to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_failed_compaction_keeps_database_open() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let backend = SledBackend::open(&temp_dir.path().join("kv.db")).expect("打开数据库失败");
+        backend.put(b"key", b"value".to_vec()).expect("写入失败");
+
+        // 旧数据库目录的位置被普通文件占用，替换无法进行
+        std::fs::write(temp_dir.path().join("kv.db.retired"), b"blocked").expect("写入文件失败");
+        assert!(backend.compact().is_err());
+
+        // 原数据库仍然可以读写
+        assert_eq!(backend.get(b"key").expect("查询失败"), Some(b"value".to_vec()));
+        backend.put(b"other", b"value".to_vec()).expect("写入失败");
+        assert_eq!(backend.len(), 2);
+        assert!(!temp_dir.path().join("kv.db.compacting").exists());
+    }
+}
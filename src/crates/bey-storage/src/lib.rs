@@ -2,9 +2,13 @@
 //!
 //! 提供完整的存储解决方案，包括：
 //! - **对象存储**：文件原样存储和传输
-//! - **云存储**：分布式存储，使用sled数据库和zstd压缩
-//! - **剪切板同步**：跨设备剪切板数据同步
-//! - **消息系统**：支持私信和群聊的消息系统
+//! - **云存储**：分布式存储，使用可插拔键值存储后端和zstd压缩
+//! - **剪切板同步**：跨设备剪切板数据同步，图片条目附带缩略图，文件条目可引用对象存储避免重复存储
+//! - **消息系统**：支持私信和群聊的消息系统，发送的消息经由预写日志持久化
+//! - **存储快照**：签名的快照清单，用于复制到备份设备
+//! - **读缓存**：对象存储和云存储可选的LRU读缓存，按字节数限制容量
+//! - **完整性扫描**：重新计算对象存储和云存储的校验和，报告损坏或缺少块的条目
+//! - **存储归档**：把整个存储导出为压缩归档并在其他节点上导入，用于迁移节点
 //!
 //! ## 架构概览
 //!
@@ -64,6 +68,10 @@
 //! ```
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tracing::{debug, warn};
 
 /// 存储结果类型
 pub type StorageResult<T> = std::result::Result<T, ErrorInfo>;
@@ -72,30 +80,93 @@ pub type StorageResult<T> = std::result::Result<T, ErrorInfo>;
 pub mod object_storage;
 pub mod cloud_storage;
 pub mod clipboard;
+pub mod thumbnail;
 pub mod message;
 pub mod compression;
 pub mod key_management;
+pub mod kv_backend;
+pub mod events;
+pub mod snapshot;
+pub mod read_cache;
+pub mod integrity;
+pub mod archive;
+mod wal;
 
 // 重新导出主要类型
 pub use object_storage::{ObjectStorage, ObjectStorageConfig};
-pub use cloud_storage::{CloudStorage, CloudStorageConfig, FileMetadata as CloudFileMetadata};
-pub use clipboard::{ClipboardManager, ClipboardEntry, ClipboardEvent, SyncMode};
-pub use message::{MessageManager, Message, MessageType, MessageEvent};
+pub use cloud_storage::{CloudStorage, CloudStorageConfig, FileMetadata as CloudFileMetadata, hash_reader};
+pub use clipboard::{CLIPBOARD_OBJECT_PREFIX, ClipboardManager, ClipboardEntry, ClipboardEvent, ClipboardConflict, ConflictResolution, SyncMode};
+pub use thumbnail::{generate_thumbnail, is_image_content_type, DEFAULT_THUMBNAIL_MAX_DIMENSION};
+pub use message::{MessageManager, Message, MessageEdit, MessageType, MessageEvent};
 pub use compression::{SmartCompressor, CompressionStrategy, CompressionAlgorithm};
 pub use key_management::SecureKeyManager;
+pub use kv_backend::{KvBackend, KvBackendKind, SledBackend, MemoryBackend, open_backend, spawn_compaction_task, spawn_expiry_sweeper, sweep_expired, ExpiryCheck};
+pub use events::{StorageEvent, StorageEventBus, StorageKind, StorageOperation};
+pub use snapshot::{SnapshotManifest, SnapshotEntry, SnapshotObjectKind, SnapshotSigner};
+pub use read_cache::CacheStats;
+pub use integrity::{IntegrityIssue, IntegrityIssueKind, IntegrityReport};
+pub use archive::{ArchiveEntry, ArchiveEntryKind, ArchiveImport, ArchiveManifest, ARCHIVE_MAGIC};
+
+/// 剪切板和消息过期清理的默认间隔
+pub const DEFAULT_EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 统一存储管理器选项
+#[derive(Debug, Clone)]
+pub struct StorageOptions {
+    /// 云存储、剪切板和消息系统使用的存储后端
+    pub backend: KvBackendKind,
+    /// 云存储配额（已存储文件原始大小总和的上限，字节）
+    pub max_local_storage: u64,
+    /// 剪切板最多保留的条目数
+    pub max_clipboard_entries: usize,
+    /// 云存储元数据库定期压缩间隔（`None` 表示不启用定期压缩）
+    pub compaction_interval: Option<Duration>,
+    /// 对象存储和云存储各自的读缓存最大字节数（`None` 表示不启用读缓存）
+    pub read_cache_bytes: Option<u64>,
+    /// 剪切板和消息过期清理间隔（`None` 表示不启动后台清理任务）
+    pub expiry_sweep_interval: Option<Duration>,
+    /// 对象存储和云存储事件使用的广播器（`None` 表示创建新的广播器）
+    ///
+    /// 重新打开存储时传入同一个广播器，已有的订阅者无需重新订阅
+    pub events: Option<StorageEventBus>,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        Self {
+            backend: KvBackendKind::Sled,
+            max_local_storage: CloudStorageConfig::default().max_local_storage,
+            max_clipboard_entries: 1000,
+            compaction_interval: None,
+            read_cache_bytes: None,
+            expiry_sweep_interval: Some(DEFAULT_EXPIRY_SWEEP_INTERVAL),
+            events: None,
+        }
+    }
+}
 
 /// 统一存储管理器
 ///
 /// 整合所有存储功能的统一接口
 pub struct UnifiedStorageManager {
-    /// 对象存储
-    pub object_storage: ObjectStorage,
+    /// 对象存储（与剪切板共享，用于保存文件引用条目的内容）
+    pub object_storage: std::sync::Arc<ObjectStorage>,
     /// 云存储
     pub cloud_storage: CloudStorage,
     /// 剪切板管理器
     pub clipboard: ClipboardManager,
     /// 消息管理器
     pub message: MessageManager,
+    /// 对象存储和云存储共享的事件广播器
+    events: StorageEventBus,
+    /// 本地设备ID
+    device_id: String,
+    /// 存储根目录
+    storage_root: std::path::PathBuf,
+    /// 快照签名身份
+    snapshot_signer: Option<SnapshotSigner>,
+    /// 剪切板和消息的过期清理任务
+    sweepers: std::sync::Mutex<Vec<tokio::task::JoinHandle<()>>>,
 }
 
 impl UnifiedStorageManager {
@@ -110,6 +181,50 @@ impl UnifiedStorageManager {
     ///
     /// 返回管理器实例或错误
     pub async fn new(device_id: String, storage_root: std::path::PathBuf) -> StorageResult<Self> {
+        Self::new_with_backend(device_id, storage_root, KvBackendKind::Sled).await
+    }
+
+    /// 使用指定的键值存储后端创建统一存储管理器
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 本地设备ID
+    /// * `storage_root` - 存储根目录
+    /// * `backend` - 云存储、剪切板和消息系统使用的存储后端
+    ///
+    /// # 返回值
+    ///
+    /// 返回管理器实例或错误
+    pub async fn new_with_backend(
+        device_id: String,
+        storage_root: std::path::PathBuf,
+        backend: KvBackendKind,
+    ) -> StorageResult<Self> {
+        let options = StorageOptions {
+            backend,
+            ..Default::default()
+        };
+        Self::new_with_options(device_id, storage_root, options).await
+    }
+
+    /// 使用指定选项创建统一存储管理器
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 本地设备ID
+    /// * `storage_root` - 存储根目录
+    /// * `options` - 存储后端和配额选项
+    ///
+    /// # 返回值
+    ///
+    /// 返回管理器实例或错误
+    pub async fn new_with_options(
+        device_id: String,
+        storage_root: std::path::PathBuf,
+        options: StorageOptions,
+    ) -> StorageResult<Self> {
+        let backend = options.backend;
+
         // 创建存储根目录
         tokio::fs::create_dir_all(&storage_root).await
             .map_err(|e| ErrorInfo::new(6001, format!("创建存储根目录失败: {}", e))
@@ -120,42 +235,505 @@ impl UnifiedStorageManager {
         let object_config = ObjectStorageConfig {
             storage_root: storage_root.join("objects"),
             enable_checksum: true,
+            read_cache_bytes: options.read_cache_bytes,
+            tag_backend: backend,
+            tag_db_path: Some(storage_root.join("object_tags.db")),
         };
-        let object_storage = ObjectStorage::new(object_config).await?;
+        let events = options.events.clone().unwrap_or_default();
+        let object_storage = std::sync::Arc::new(ObjectStorage::new(object_config).await?
+            .with_event_bus(events.clone()));
 
         // 初始化云存储
         let cloud_config = CloudStorageConfig {
             storage_root: storage_root.join("cloud"),
             db_path: storage_root.join("cloud_metadata.db"),
+            max_local_storage: options.max_local_storage,
+            backend,
+            compaction_interval: options.compaction_interval,
+            read_cache_bytes: options.read_cache_bytes,
             ..Default::default()
         };
-        let cloud_storage = CloudStorage::new(cloud_config).await?;
+        let cloud_storage = CloudStorage::new(cloud_config).await?
+            .with_event_bus(events.clone());
+
+        let (clipboard, message) = match backend {
+            KvBackendKind::Sled => {
+                // 初始化剪切板管理器
+                let clipboard_path = storage_root.join("clipboard.db");
+                let clipboard = ClipboardManager::new(device_id.clone(), clipboard_path).await
+                    .map_err(|e| ErrorInfo::new(6002, format!("创建剪切板管理器失败: {}", e))
+                        .with_category(ErrorCategory::System))?;
+
+                // 初始化消息管理器
+                let message_path = storage_root.join("messages.db");
+                let message = MessageManager::new(device_id.clone(), message_path).await
+                    .map_err(|e| ErrorInfo::new(6003, format!("创建消息管理器失败: {}", e))
+                        .with_category(ErrorCategory::System))?;
 
-        // 初始化剪切板管理器
-        let clipboard_path = storage_root.join("clipboard.db");
-        let clipboard = ClipboardManager::new(device_id.clone(), clipboard_path).await
-            .map_err(|e| ErrorInfo::new(6002, format!("创建剪切板管理器失败: {}", e))
-                .with_category(ErrorCategory::System))?;
+                (clipboard, message)
+            }
+            KvBackendKind::Memory => (
+                ClipboardManager::with_backend(device_id.clone(), std::sync::Arc::new(MemoryBackend::new())),
+                MessageManager::with_backend(device_id.clone(), std::sync::Arc::new(MemoryBackend::new())),
+            ),
+        };
+        let clipboard = clipboard
+            .with_max_entries(options.max_clipboard_entries)
+            .with_object_storage(std::sync::Arc::clone(&object_storage));
 
-        // 初始化消息管理器
-        let message_path = storage_root.join("messages.db");
-        let message = MessageManager::new(device_id, message_path).await
-            .map_err(|e| ErrorInfo::new(6003, format!("创建消息管理器失败: {}", e))
-                .with_category(ErrorCategory::System))?;
+        let sweepers = match options.expiry_sweep_interval {
+            Some(interval) => vec![
+                clipboard.start_expiry_sweeper(interval),
+                message.start_expiry_sweeper(interval),
+            ],
+            None => Vec::new(),
+        };
 
         Ok(Self {
             object_storage,
             cloud_storage,
             clipboard,
             message,
+            events,
+            device_id,
+            storage_root,
+            snapshot_signer: None,
+            sweepers: std::sync::Mutex::new(sweepers),
+        })
+    }
+
+    /// 停止剪切板和消息的过期清理任务，重复调用不会产生副作用
+    ///
+    /// 存储管理器释放时也会停止清理任务
+    pub fn stop_sweepers(&self) {
+        let sweepers = std::mem::take(&mut *self.sweepers.lock().unwrap_or_else(|e| e.into_inner()));
+        for sweeper in &sweepers {
+            sweeper.abort();
+        }
+        if !sweepers.is_empty() {
+            debug!("已停止 {} 个过期清理任务", sweepers.len());
+        }
+    }
+
+    /// 重新启动剪切板和消息的过期清理任务，已有的清理任务先被停止
+    ///
+    /// # 参数
+    ///
+    /// * `interval` - 清理间隔
+    pub fn restart_sweepers(&self, interval: Duration) {
+        self.stop_sweepers();
+        let sweepers = vec![
+            self.clipboard.start_expiry_sweeper(interval),
+            self.message.start_expiry_sweeper(interval),
+        ];
+        *self.sweepers.lock().unwrap_or_else(|e| e.into_inner()) = sweepers;
+    }
+
+    /// 正在运行的过期清理任务数量
+    pub fn active_sweepers(&self) -> usize {
+        self.sweepers.lock().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|sweeper| !sweeper.is_finished())
+            .count()
+    }
+
+    /// 将云存储、剪切板和消息数据库缓冲的写入刷到磁盘
+    ///
+    /// 复制或迁移存储目录之前调用，确保磁盘上的数据库文件完整
+    pub async fn flush(&self) -> StorageResult<()> {
+        self.object_storage.flush().await?;
+        self.cloud_storage.flush().await?;
+        self.clipboard.flush().await?;
+        self.message.flush().await?;
+        Ok(())
+    }
+
+    /// 设置快照签名身份
+    ///
+    /// # 参数
+    ///
+    /// * `signer` - 用于签名快照清单的设备证书和私钥
+    pub fn with_snapshot_signer(mut self, signer: SnapshotSigner) -> Self {
+        self.snapshot_signer = Some(signer);
+        self
+    }
+
+    /// 生成已签名的存储快照清单
+    ///
+    /// 清单列出对象存储和云存储中的所有对象及其哈希，需要先通过
+    /// [`UnifiedStorageManager::with_snapshot_signer`] 设置签名身份
+    ///
+    /// # 返回值
+    ///
+    /// 返回快照清单或错误
+    pub async fn create_snapshot(&self) -> StorageResult<SnapshotManifest> {
+        let signer = self.snapshot_signer.as_ref()
+            .ok_or_else(|| ErrorInfo::new(6502, "未设置快照签名身份".to_string())
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let mut entries = Vec::new();
+        for object_id in self.object_storage.list().await? {
+            let data = self.object_storage.retrieve(&object_id).await?;
+            entries.push(SnapshotEntry {
+                kind: SnapshotObjectKind::Object,
+                hash: snapshot::content_hash(&data),
+                size: data.len() as u64,
+                key: object_id,
+            });
+        }
+        for file in self.cloud_storage.list_files()? {
+            entries.push(SnapshotEntry {
+                kind: SnapshotObjectKind::CloudFile,
+                key: file.filename,
+                hash: file.hash,
+                size: file.size,
+            });
+        }
+
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        SnapshotManifest::sign(self.device_id.clone(), created_at, entries, signer)
+    }
+
+    /// 应用其他设备的存储快照
+    ///
+    /// 先验证清单签名，再逐个拉取本地缺少或内容不同的对象，
+    /// 每个对象的哈希与清单一致后才写入存储
+    ///
+    /// # 参数
+    ///
+    /// * `manifest` - 快照清单
+    /// * `fetch` - 按内容哈希从源设备拉取对象数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回实际拉取并写入的对象数量或错误
+    pub async fn apply_snapshot<F, Fut>(&self, manifest: &SnapshotManifest, fetch: F) -> StorageResult<usize>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = StorageResult<Vec<u8>>>,
+    {
+        manifest.verify()?;
+
+        let mut applied = 0;
+        for entry in &manifest.entries {
+            match entry.kind {
+                SnapshotObjectKind::Object => {
+                    snapshot::validate_entry_key(entry)?;
+                    if self.object_storage.exists(&entry.key).await {
+                        let local = self.object_storage.retrieve(&entry.key).await?;
+                        if snapshot::content_hash(&local) == entry.hash {
+                            continue;
+                        }
+                    }
+                    let data = fetch(entry.hash.clone()).await?;
+                    snapshot::verify_entry_content(entry, &data)?;
+                    self.object_storage.store(&entry.key, &data).await?;
+                }
+                SnapshotObjectKind::CloudFile => {
+                    if self.cloud_storage.exists_by_hash(&entry.hash) {
+                        continue;
+                    }
+                    let data = fetch(entry.hash.clone()).await?;
+                    snapshot::verify_entry_content(entry, &data)?;
+                    self.cloud_storage.upload_file(&entry.key, &data).await?;
+                }
+            }
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+
+    /// 把整个存储导出为压缩归档
+    ///
+    /// 依次写入对象存储中的对象及其标签、云存储中的文件、消息和剪切板条目，最后写入清单。
+    /// 对象和文件按块读取并写入，不会把整个对象、文件或存储加载到内存
+    ///
+    /// # 参数
+    ///
+    /// * `writer` - 归档写入目标
+    ///
+    /// # 返回值
+    ///
+    /// 返回导出结果或错误
+    pub async fn export_archive<W: AsyncWrite + Unpin>(&self, writer: W) -> StorageResult<()> {
+        let mut archive = archive::ArchiveWriter::new(writer).await?;
+
+        let tags = self.object_storage.all_tags().await?;
+        for object_id in self.object_storage.list().await? {
+            let size = self.object_storage.size(&object_id).await?;
+            let hash = hash_reader(self.object_storage.open(&object_id).await?).await?;
+            let reader = self.object_storage.open(&object_id).await?;
+            archive.write_entry_from(ArchiveEntryKind::Object, &object_id, size, hash, reader).await?;
+
+            if let Some(tags) = tags.get(&object_id) {
+                let data = serde_json::to_vec(tags).map_err(archive::format_error)?;
+                archive.write_entry(ArchiveEntryKind::ObjectTags, &object_id, &data).await?;
+            }
+        }
+        for file in self.cloud_storage.list_files()? {
+            archive.begin_entry(ArchiveEntryKind::CloudFile, &file.filename, file.size, file.original_hash.clone()).await?;
+            let mut offset = 0;
+            while offset < file.size {
+                let chunk = self.cloud_storage.read_range(&file.hash, offset, archive::ARCHIVE_CHUNK_SIZE as u64).await?;
+                if chunk.is_empty() {
+                    break;
+                }
+                offset += chunk.len() as u64;
+                archive.write_data(&chunk).await?;
+            }
+            archive.end_entry()?;
+        }
+        for message in self.message.get_diff(0).await {
+            let data = serde_json::to_vec(&message).map_err(archive::format_error)?;
+            archive.write_entry(ArchiveEntryKind::Message, &message.id, &data).await?;
+        }
+        for entry in self.clipboard.get_diff(0).await {
+            let data = serde_json::to_vec(&entry).map_err(archive::format_error)?;
+            archive.write_entry(ArchiveEntryKind::ClipboardEntry, &entry.id, &data).await?;
+        }
+
+        let created_at = SystemTime::now().duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let manifest = archive.finish(self.device_id.clone(), created_at).await?;
+        debug!("已导出存储归档: {} 个条目", manifest.entries.len());
+        Ok(())
+    }
+
+    /// 从压缩归档导入存储
+    ///
+    /// 条目先按块暂存到存储根目录下的临时目录并逐个校验哈希，读到清单并核对条目完整后才写入存储；
+    /// 归档损坏或被截断时返回错误，存储不被修改。暂存目录在导入结束后删除。
+    /// 与本地条目并发修改的剪切板条目不会覆盖本地内容，作为冲突返回，由调用方决定如何解决
+    ///
+    /// # 参数
+    ///
+    /// * `reader` - 归档读取来源
+    ///
+    /// # 返回值
+    ///
+    /// 返回归档清单和剪切板冲突，或错误
+    pub async fn import_archive<R: AsyncRead + Unpin>(&self, reader: R) -> StorageResult<ArchiveImport> {
+        let staging_dir = self.storage_root.join(format!(".archive-import-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&staging_dir).await.map_err(archive::staging_error)?;
+
+        let result = self.import_staged_archive(reader, &staging_dir).await;
+        if let Err(e) = tokio::fs::remove_dir_all(&staging_dir).await {
+            warn!("删除归档暂存目录 {} 失败: {}", staging_dir.display(), e);
+        }
+        result
+    }
+
+    /// 暂存并校验整个归档，然后把条目写入存储
+    async fn import_staged_archive<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        staging_dir: &std::path::Path,
+    ) -> StorageResult<ArchiveImport> {
+        let mut archive = archive::ArchiveReader::open(reader, staging_dir).await?;
+        let mut staged = Vec::new();
+        let manifest = loop {
+            match archive.next_item().await? {
+                archive::ArchiveItem::Entry(entry, path) => staged.push((entry, path)),
+                archive::ArchiveItem::Manifest(manifest) => break manifest,
+            }
+        };
+
+        let mut clipboard_conflicts = Vec::new();
+        for (entry, path) in staged {
+            match entry.kind {
+                ArchiveEntryKind::Object => {
+                    let file = tokio::fs::File::open(&path).await.map_err(archive::staging_error)?;
+                    self.object_storage.store_reader(&entry.key, file).await?;
+                }
+                ArchiveEntryKind::ObjectTags => {
+                    let data = tokio::fs::read(&path).await.map_err(archive::staging_error)?;
+                    let tags = serde_json::from_slice(&data).map_err(archive::format_error)?;
+                    self.object_storage.set_tags(&entry.key, tags).await?;
+                }
+                ArchiveEntryKind::CloudFile => {
+                    self.cloud_storage.upload_file_from_path(&entry.key, &path, |_, _| {}).await?;
+                }
+                ArchiveEntryKind::Message => {
+                    let data = tokio::fs::read(&path).await.map_err(archive::staging_error)?;
+                    let message: Message = serde_json::from_slice(&data).map_err(archive::format_error)?;
+                    self.message.handle_sync_event(MessageEvent::NewMessage(message)).await?;
+                }
+                ArchiveEntryKind::ClipboardEntry => {
+                    let data = tokio::fs::read(&path).await.map_err(archive::staging_error)?;
+                    let clipboard_entry: ClipboardEntry = serde_json::from_slice(&data).map_err(archive::format_error)?;
+                    if let Some(conflict) = self.clipboard.merge_entry(clipboard_entry).await? {
+                        clipboard_conflicts.push(conflict);
+                    }
+                }
+                ArchiveEntryKind::Manifest => unreachable!("清单由归档读取器单独返回"),
+            }
+        }
+
+        debug!("已导入存储归档: {} 个条目，{} 个剪切板冲突", manifest.entries.len(), clipboard_conflicts.len());
+        Ok(ArchiveImport {
+            manifest,
+            clipboard_conflicts,
         })
     }
+
+    /// 以文件引用的方式添加剪切板条目
+    ///
+    /// 对象存储中已有相同内容的对象时直接引用该对象，否则以内容哈希为键写入一份，
+    /// 剪切板条目本身不内嵌文件内容
+    ///
+    /// # 参数
+    ///
+    /// * `content` - 文件内容
+    /// * `content_type` - 内容类型
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目ID或错误
+    pub async fn add_clipboard_file(&self, content: &[u8], content_type: String) -> StorageResult<String> {
+        self.clipboard.add_file(content, content_type).await
+    }
+
+    /// 读取剪切板条目的内容
+    ///
+    /// 文件引用条目从对象存储解析内容，其他条目直接返回内嵌的内容
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 条目ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回条目内容或错误
+    pub async fn clipboard_content(&self, id: &str) -> StorageResult<Vec<u8>> {
+        let entry = self.clipboard.get_entry(id).await?;
+        match entry.file_ref {
+            Some(object_key) => self.object_storage.retrieve(&object_key).await,
+            None => Ok(entry.content),
+        }
+    }
+
+    /// 删除剪切板条目
+    ///
+    /// 文件引用条目引用的对象只有在由剪切板写入且不再被其他条目引用时才一并删除，
+    /// 剪切板之外写入的对象始终保留
+    ///
+    /// # 参数
+    ///
+    /// * `id` - 条目ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回删除结果
+    pub async fn delete_clipboard_entry(&self, id: &str) -> StorageResult<()> {
+        self.clipboard.delete_entry(id).await
+    }
+
+    /// 扫描对象存储和云存储的完整性
+    ///
+    /// 重新计算所有对象和云存储块的校验和，只报告问题，不删除任何数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回扫描报告或错误
+    pub async fn integrity_scan(&self) -> StorageResult<IntegrityReport> {
+        let (_cancel, cancelled) = tokio::sync::watch::channel(false);
+        self.integrity_scan_with_progress(cancelled, |_, _| {}).await
+    }
+
+    /// 扫描对象存储和云存储的完整性，支持取消并报告进度
+    ///
+    /// 每校验完一个对象或文件报告一次进度；收到取消信号后在下一个条目前停止，
+    /// 返回的报告只包含已扫描的部分，并标记为已取消
+    ///
+    /// # 参数
+    ///
+    /// * `cancel` - 取消信号，值变为 `true` 时停止扫描
+    /// * `on_progress` - 进度回调，参数为已扫描条目数和总条目数
+    ///
+    /// # 返回值
+    ///
+    /// 返回扫描报告或错误
+    pub async fn integrity_scan_with_progress(
+        &self,
+        cancel: tokio::sync::watch::Receiver<bool>,
+        on_progress: impl Fn(u64, u64),
+    ) -> StorageResult<IntegrityReport> {
+        let objects = self.object_storage.list().await?;
+        let files = self.cloud_storage.list_files()?;
+        let total = (objects.len() + files.len()) as u64;
+        let mut report = IntegrityReport::default();
+        let mut scanned = 0;
+
+        for object_id in objects {
+            if *cancel.borrow() {
+                report.cancelled = true;
+                return Ok(report);
+            }
+
+            let kind = match self.object_storage.verify(&object_id).await {
+                Ok(Some(true)) => None,
+                Ok(Some(false)) => Some(IntegrityIssueKind::Corrupt),
+                Ok(None) => {
+                    report.unverified_objects.push(object_id.clone());
+                    None
+                }
+                Err(e) => Some(IntegrityIssueKind::Unreadable { reason: e.to_string() }),
+            };
+            if let Some(kind) = kind {
+                debug!("对象 {} 完整性异常: {:?}", object_id, kind);
+                report.issues.push(IntegrityIssue { storage: StorageKind::Object, key: object_id, kind });
+            }
+            report.objects_checked += 1;
+            scanned += 1;
+            on_progress(scanned, total);
+        }
+
+        for file in files {
+            if *cancel.borrow() {
+                report.cancelled = true;
+                return Ok(report);
+            }
+
+            let kind = match self.cloud_storage.verify_file(&file).await {
+                Ok(kind) => kind,
+                Err(e) => Some(IntegrityIssueKind::Unreadable { reason: e.to_string() }),
+            };
+            if let Some(kind) = kind {
+                debug!("云存储文件 {} 完整性异常: {:?}", file.hash, kind);
+                report.issues.push(IntegrityIssue { storage: StorageKind::Cloud, key: file.hash, kind });
+            }
+            report.files_checked += 1;
+            scanned += 1;
+            on_progress(scanned, total);
+        }
+
+        Ok(report)
+    }
+
+    /// 订阅对象存储和云存储的写入、读取、删除事件
+    ///
+    /// 事件在操作成功后发出，广播不会阻塞存储操作
+    pub fn storage_events(&self) -> tokio::sync::broadcast::Receiver<StorageEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl Drop for UnifiedStorageManager {
+    fn drop(&mut self) {
+        self.stop_sweepers();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::tempdir;
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_unified_storage_manager() {
@@ -187,12 +765,59 @@ mod tests {
         assert_eq!(msg.content, b"hello");
     }
 
+    /// 对指定后端运行相同的存储场景
+    async fn run_backend_scenario(backend: KvBackendKind) {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = UnifiedStorageManager::new_with_backend(
+            "test_device".to_string(),
+            temp_dir.path().to_path_buf(),
+            backend,
+        ).await.expect("创建管理器失败");
+
+        // 云存储：上传、列出、删除
+        let file_hash = manager.cloud_storage.upload_file("a.txt", b"cloud data").await
+            .expect("上传失败");
+        assert_eq!(manager.cloud_storage.download_file(&file_hash).await.expect("下载失败"), b"cloud data");
+        assert_eq!(manager.cloud_storage.list_files().expect("列出文件失败").len(), 1);
+        manager.cloud_storage.delete_file(&file_hash).await.expect("删除失败");
+        assert!(manager.cloud_storage.list_files().expect("列出文件失败").is_empty());
+
+        // 剪切板：添加、列出、删除
+        let clip_id = manager.clipboard.add_entry(b"clip".to_vec(), "text".to_string()).await
+            .expect("剪切板添加失败");
+        assert_eq!(manager.clipboard.list_entries().await.len(), 1);
+        manager.clipboard.delete_entry(&clip_id).await.expect("剪切板删除失败");
+        assert!(manager.clipboard.get_entry(&clip_id).await.is_err());
+
+        // 消息：发送、读取、删除
+        let msg_id = manager.message.send_message(
+            MessageType::Private,
+            "other_device".to_string(),
+            b"hello".to_vec(),
+            "text".to_string(),
+        ).await.expect("消息发送失败");
+        assert_eq!(manager.message.get_message(&msg_id).await.expect("消息获取失败").content, b"hello");
+        manager.message.delete_message(&msg_id).await.expect("消息删除失败");
+        assert!(manager.message.get_message(&msg_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sled_backend_scenario() {
+        run_backend_scenario(KvBackendKind::Sled).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_scenario() {
+        run_backend_scenario(KvBackendKind::Memory).await;
+    }
+
     #[tokio::test]
     async fn test_object_storage() {
         let temp_dir = tempdir().expect("创建临时目录失败");
         let config = ObjectStorageConfig {
             storage_root: temp_dir.path().to_path_buf(),
             enable_checksum: true,
+            ..Default::default()
         };
 
         let storage = ObjectStorage::new(config).await.expect("创建存储失败");
@@ -226,4 +851,420 @@ mod tests {
         let downloaded = storage.download_file(&file_hash).await.expect("下载失败");
         assert_eq!(test_data, downloaded.as_slice());
     }
+
+    #[tokio::test]
+    async fn test_storage_events() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = UnifiedStorageManager::new_with_backend(
+            "test_device".to_string(),
+            temp_dir.path().to_path_buf(),
+            KvBackendKind::Memory,
+        ).await.expect("创建管理器失败");
+
+        // 没有订阅者时存储操作不受影响
+        manager.object_storage.store("before", b"ignored").await.expect("对象存储失败");
+
+        let mut events = manager.storage_events();
+        manager.object_storage.store("doc", b"hello").await.expect("对象存储失败");
+        let file_hash = manager.cloud_storage.upload_file("a.txt", b"cloud data").await
+            .expect("上传失败");
+        manager.cloud_storage.delete_file(&file_hash).await.expect("删除失败");
+
+        let write = events.recv().await.expect("接收事件失败");
+        assert_eq!(write, StorageEvent {
+            storage: StorageKind::Object,
+            operation: StorageOperation::Write,
+            key: "doc".to_string(),
+            size: 5,
+            previous_key: None,
+        });
+
+        let upload = events.recv().await.expect("接收事件失败");
+        assert_eq!((upload.storage, upload.operation), (StorageKind::Cloud, StorageOperation::Write));
+        assert_eq!(upload.key, file_hash);
+        assert_eq!(upload.size, 10);
+
+        let delete = events.recv().await.expect("接收事件失败");
+        assert_eq!(delete.operation, StorageOperation::Delete);
+
+        manager.object_storage.rename("doc", "renamed", false).await.expect("重命名失败");
+        let rename = events.recv().await.expect("接收事件失败");
+        assert_eq!(rename, StorageEvent {
+            storage: StorageKind::Object,
+            operation: StorageOperation::Rename,
+            key: "renamed".to_string(),
+            size: 5,
+            previous_key: Some("doc".to_string()),
+        });
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_file_ref_dedup() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = UnifiedStorageManager::new_with_backend(
+            "test_device".to_string(),
+            temp_dir.path().to_path_buf(),
+            KvBackendKind::Memory,
+        ).await.expect("创建管理器失败");
+
+        // 已在对象存储中的文件直接被引用，不再写入第二份
+        let report = vec![42u8; 64 * 1024];
+        manager.object_storage.store("report.pdf", &report).await.expect("对象存储失败");
+        let clip_id = manager.add_clipboard_file(&report, "file".to_string()).await
+            .expect("添加文件引用失败");
+
+        let entry = manager.clipboard.get_entry(&clip_id).await.expect("剪切板获取失败");
+        assert_eq!(entry.file_ref.as_deref(), Some("report.pdf"));
+        assert!(entry.content.is_empty());
+        assert_eq!(manager.clipboard_content(&clip_id).await.expect("读取剪切板内容失败"), report);
+        assert_eq!(manager.object_storage.list().await.expect("列出失败"), vec!["report.pdf".to_string()]);
+
+        // 删除条目不影响剪切板之外写入的对象
+        manager.delete_clipboard_entry(&clip_id).await.expect("剪切板删除失败");
+        assert!(manager.object_storage.exists("report.pdf").await);
+
+        // 新文件按内容哈希写入一份，相同内容的条目共享同一个对象
+        let first = manager.add_clipboard_file(b"new file", "file".to_string()).await
+            .expect("添加文件引用失败");
+        let second = manager.add_clipboard_file(b"new file", "file".to_string()).await
+            .expect("添加文件引用失败");
+        let object_key = manager.clipboard.get_entry(&first).await.expect("剪切板获取失败")
+            .file_ref.expect("应为文件引用条目");
+        assert!(object_key.starts_with(CLIPBOARD_OBJECT_PREFIX));
+        assert_eq!(manager.object_storage.list().await.expect("列出失败").len(), 2);
+
+        // 仍被引用的对象不随条目删除，最后一个引用删除后才清理
+        manager.delete_clipboard_entry(&first).await.expect("剪切板删除失败");
+        assert_eq!(manager.clipboard_content(&second).await.expect("读取剪切板内容失败"), b"new file");
+        manager.delete_clipboard_entry(&second).await.expect("剪切板删除失败");
+        assert!(!manager.object_storage.exists(&object_key).await);
+    }
+
+    #[tokio::test]
+    async fn test_expiry_sweepers_follow_manager_lifecycle() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let options = StorageOptions {
+            backend: KvBackendKind::Memory,
+            expiry_sweep_interval: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let manager = UnifiedStorageManager::new_with_options(
+            "sweeper-device".to_string(), temp_dir.path().to_path_buf(), options,
+        ).await.expect("创建管理器失败");
+        assert_eq!(manager.active_sweepers(), 2);
+
+        manager.message.send_message_ephemeral(
+            MessageType::Private,
+            "peer".to_string(),
+            b"burn".to_vec(),
+            "text".to_string(),
+            Duration::from_millis(10),
+        ).await.expect("发送阅后即焚消息失败");
+
+        // 后台清理任务随管理器启动，无需手动清理
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(manager.message.sweep_expired().await.expect("清理失败"), 0);
+        assert_eq!(manager.message.message_count(), 0);
+
+        manager.stop_sweepers();
+        tokio::task::yield_now().await;
+        assert_eq!(manager.active_sweepers(), 0);
+        manager.stop_sweepers();
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_file_ref_sync_and_gc() {
+        let sender_dir = tempdir().expect("创建临时目录失败");
+        let receiver_dir = tempdir().expect("创建临时目录失败");
+        let sender = UnifiedStorageManager::new_with_backend(
+            "sender".to_string(), sender_dir.path().to_path_buf(), KvBackendKind::Memory,
+        ).await.expect("创建管理器失败");
+        let receiver = UnifiedStorageManager::new_with_backend(
+            "receiver".to_string(), receiver_dir.path().to_path_buf(), KvBackendKind::Memory,
+        ).await.expect("创建管理器失败");
+
+        let clip_id = sender.add_clipboard_file(b"shared file", "file".to_string()).await
+            .expect("添加文件引用失败");
+        let entry = sender.clipboard.get_entry(&clip_id).await.expect("剪切板获取失败");
+        assert_eq!(sender.clipboard.content_size(&entry).await, b"shared file".len() as u64);
+
+        // 未附带内容的文件引用条目无法在对端解析，被忽略
+        receiver.clipboard.merge_entry(entry.clone()).await.expect("合并失败");
+        assert!(receiver.clipboard.get_entry(&clip_id).await.is_err());
+
+        // 附带内容后对端写入自己的对象存储并引用本地对象
+        let shipped = sender.clipboard.inline_file_content(entry.clone()).await.expect("应能内嵌文件内容");
+        receiver.clipboard.merge_entry(shipped).await.expect("合并失败");
+        let received = receiver.clipboard.get_entry(&clip_id).await.expect("剪切板获取失败");
+        assert!(received.content.is_empty());
+        assert_eq!(receiver.clipboard_content(&clip_id).await.expect("读取剪切板内容失败"), b"shared file");
+        let object_key = received.file_ref.expect("应为文件引用条目");
+
+        // 远程修改覆盖文件引用后，不再被引用的对象被回收
+        let mut updated = entry;
+        updated.file_ref = None;
+        updated.content = b"plain text".to_vec();
+        updated.version += 1;
+        *updated.clock.entry("sender".to_string()).or_insert(0) += 1;
+        receiver.clipboard.merge_entry(updated).await.expect("合并失败");
+        assert!(!receiver.object_storage.exists(&object_key).await);
+
+        // 清空剪切板时回收剪切板写入的对象，其他对象保留
+        receiver.object_storage.store("notes", b"kept").await.expect("对象存储失败");
+        receiver.add_clipboard_file(b"another file", "file".to_string()).await.expect("添加文件引用失败");
+        receiver.clipboard.clear().await.expect("清空剪切板失败");
+        assert_eq!(receiver.object_storage.list().await.expect("列出失败"), vec!["notes".to_string()]);
+    }
+
+    /// 签发测试用的快照签名身份
+    async fn test_snapshot_signer(cert_dir: &std::path::Path) -> SnapshotSigner {
+        let config = bey_identity::CertificateConfig::builder()
+            .with_storage_directory(cert_dir)
+            .build()
+            .expect("证书配置创建失败");
+        let certificates = bey_identity::CertificateManager::initialize(config).await
+            .expect("证书管理器初始化失败");
+        let certificate = certificates.issue_device_certificate("primary").await
+            .expect("证书签发失败");
+        let private_key = certificate.private_key_pem.clone().expect("证书缺少私钥");
+        SnapshotSigner::new(certificate.certificate_pem, private_key)
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_replicates_to_fresh_manager() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let signer = test_snapshot_signer(&temp_dir.path().join("certs")).await;
+        let primary = UnifiedStorageManager::new_with_backend(
+            "primary".to_string(),
+            temp_dir.path().join("primary"),
+            KvBackendKind::Memory,
+        ).await.expect("创建管理器失败")
+            .with_snapshot_signer(signer);
+
+        primary.object_storage.store("notes", b"object data").await.expect("对象存储失败");
+        primary.object_storage.store("photo", &[7u8; 4096]).await.expect("对象存储失败");
+        primary.cloud_storage.upload_file("report.txt", b"cloud data").await.expect("上传失败");
+
+        let manifest = primary.create_snapshot().await.expect("创建快照失败");
+        assert_eq!(manifest.device_id, "primary");
+        assert_eq!(manifest.entries.len(), 3);
+        manifest.verify().expect("快照签名应该有效");
+
+        // 模拟源设备按哈希提供对象
+        let mut objects = std::collections::HashMap::new();
+        for entry in &manifest.entries {
+            let data = match entry.kind {
+                SnapshotObjectKind::Object => primary.object_storage.retrieve(&entry.key).await,
+                SnapshotObjectKind::CloudFile => primary.cloud_storage.download_file(&entry.hash).await,
+            }.expect("读取源对象失败");
+            objects.insert(entry.hash.clone(), data);
+        }
+        let fetch = |hash: String| {
+            let data = objects.get(&hash).cloned();
+            async move {
+                data.ok_or_else(|| ErrorInfo::new(9999, format!("对象不存在: {}", hash)))
+            }
+        };
+
+        let backup = UnifiedStorageManager::new_with_backend(
+            "backup".to_string(),
+            temp_dir.path().join("backup"),
+            KvBackendKind::Memory,
+        ).await.expect("创建管理器失败");
+        let applied = backup.apply_snapshot(&manifest, &fetch).await.expect("应用快照失败");
+        assert_eq!(applied, 3);
+
+        assert_eq!(backup.object_storage.retrieve("notes").await.expect("对象检索失败"), b"object data");
+        assert_eq!(backup.object_storage.retrieve("photo").await.expect("对象检索失败"), vec![7u8; 4096]);
+        let cloud_entry = manifest.entries.iter()
+            .find(|entry| entry.kind == SnapshotObjectKind::CloudFile)
+            .expect("快照应包含云存储文件");
+        assert_eq!(backup.cloud_storage.download_file(&cloud_entry.hash).await.expect("下载失败"), b"cloud data");
+
+        // 再次应用时对象已存在，无需拉取
+        assert_eq!(backup.apply_snapshot(&manifest, &fetch).await.expect("应用快照失败"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_rejects_tampering() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let signer = test_snapshot_signer(&temp_dir.path().join("certs")).await;
+        let primary = UnifiedStorageManager::new_with_backend(
+            "primary".to_string(),
+            temp_dir.path().join("primary"),
+            KvBackendKind::Memory,
+        ).await.expect("创建管理器失败")
+            .with_snapshot_signer(signer);
+        primary.object_storage.store("notes", b"object data").await.expect("对象存储失败");
+        let manifest = primary.create_snapshot().await.expect("创建快照失败");
+
+        let backup = UnifiedStorageManager::new_with_backend(
+            "backup".to_string(),
+            temp_dir.path().join("backup"),
+            KvBackendKind::Memory,
+        ).await.expect("创建管理器失败");
+
+        // 拉取到的内容被篡改
+        let error = backup.apply_snapshot(&manifest, |_hash| async { Ok(b"evil data!!".to_vec()) }).await
+            .expect_err("内容不符时应该失败");
+        assert_eq!(error.code(), 6504);
+        assert!(!backup.object_storage.exists("notes").await);
+
+        // 清单被篡改
+        let mut forged = manifest.clone();
+        forged.entries[0].key = "other".to_string();
+        let error = backup.apply_snapshot(&forged, |_hash| async { Ok(b"object data".to_vec()) }).await
+            .expect_err("清单被篡改时应该失败");
+        assert_eq!(error.code(), 6503);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_scan_reports_corruption() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = UnifiedStorageManager::new("test_device".to_string(), temp_dir.path().to_path_buf())
+            .await.expect("创建管理器失败");
+
+        manager.object_storage.store("intact", b"intact data").await.expect("存储失败");
+        manager.object_storage.store("damaged", b"original data").await.expect("存储失败");
+        let intact_file = manager.cloud_storage.upload_file("intact.txt", b"intact file").await.expect("上传失败");
+        let damaged_file = manager.cloud_storage.upload_file("damaged.txt", b"damaged file").await.expect("上传失败");
+
+        let report = manager.integrity_scan().await.expect("扫描失败");
+        assert!(report.is_clean(), "未损坏时不应报告问题: {:?}", report);
+
+        // 篡改对象内容并删除云存储文件的块
+        std::fs::write(temp_dir.path().join("objects").join("damaged"), b"tampered data").expect("写入失败");
+        let metadata = manager.cloud_storage.list_files().expect("列出文件失败")
+            .into_iter()
+            .find(|file| file.hash == damaged_file)
+            .expect("文件不存在");
+        std::fs::remove_file(temp_dir.path().join("cloud").join(format!("{}.beycloud", metadata.chunk_ids[0])))
+            .expect("删除块失败");
+
+        let progress = std::sync::Mutex::new(Vec::new());
+        let (_cancel, cancelled) = tokio::sync::watch::channel(false);
+        let report = manager.integrity_scan_with_progress(cancelled, |done, total| {
+            progress.lock().expect("锁失败").push((done, total));
+        }).await.expect("扫描失败");
+
+        assert_eq!(report.objects_checked, 2);
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.issues.len(), 2);
+        assert!(report.has_issue(StorageKind::Object, "damaged"));
+        assert!(!report.has_issue(StorageKind::Object, "intact"));
+        assert!(report.issues.contains(&IntegrityIssue {
+            storage: StorageKind::Cloud,
+            key: damaged_file,
+            kind: IntegrityIssueKind::MissingChunk { chunk_id: metadata.chunk_ids[0].clone() },
+        }));
+        assert!(!report.has_issue(StorageKind::Cloud, &intact_file));
+        assert_eq!(progress.into_inner().expect("锁失败").last(), Some(&(4, 4)));
+
+        // 扫描不删除任何数据
+        assert!(manager.object_storage.exists("damaged").await);
+
+        // 已取消时不扫描
+        let (cancel, cancelled) = tokio::sync::watch::channel(false);
+        cancel.send_replace(true);
+        let report = manager.integrity_scan_with_progress(cancelled, |_, _| {}).await.expect("扫描失败");
+        assert!(report.cancelled);
+        assert_eq!(report.objects_checked, 0);
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trip() {
+        let source_dir = tempdir().expect("创建临时目录失败");
+        let source = UnifiedStorageManager::new("primary".to_string(), source_dir.path().to_path_buf()).await
+            .expect("创建管理器失败");
+        source.object_storage.store("notes", b"object data").await.expect("对象存储失败");
+        let tags = HashMap::from([("project".to_string(), "bey".to_string())]);
+        source.object_storage.set_tags("notes", tags.clone()).await.expect("设置标签失败");
+        let file_hash = source.cloud_storage.upload_file("photo.jpg", &vec![7u8; 300 * 1024]).await
+            .expect("上传文件失败");
+        let msg_id = source.message.send_message(
+            MessageType::Private,
+            "other_device".to_string(),
+            b"hello".to_vec(),
+            "text".to_string(),
+        ).await.expect("消息发送失败");
+        let clip_id = source.clipboard.add_entry(b"clipboard".to_vec(), "text".to_string()).await
+            .expect("剪切板添加失败");
+        let file_clip_id = source.add_clipboard_file(b"file content", "file".to_string()).await
+            .expect("添加剪切板文件失败");
+
+        let mut archive = Vec::new();
+        source.export_archive(&mut archive).await.expect("导出归档失败");
+
+        let target_dir = tempdir().expect("创建临时目录失败");
+        let target = UnifiedStorageManager::new("replacement".to_string(), target_dir.path().to_path_buf()).await
+            .expect("创建管理器失败");
+        let imported = target.import_archive(archive.as_slice()).await.expect("导入归档失败");
+        let manifest = imported.manifest;
+        assert!(imported.clipboard_conflicts.is_empty());
+
+        assert_eq!(manifest.device_id, "primary");
+        assert_eq!(manifest.count(ArchiveEntryKind::Object), 2);
+        assert_eq!(manifest.count(ArchiveEntryKind::ObjectTags), 1);
+        assert_eq!(manifest.count(ArchiveEntryKind::CloudFile), 1);
+        assert_eq!(manifest.count(ArchiveEntryKind::Message), 1);
+        assert_eq!(manifest.count(ArchiveEntryKind::ClipboardEntry), 2);
+        assert_eq!(target.object_storage.retrieve("notes").await.expect("对象检索失败"), b"object data");
+        assert_eq!(target.object_storage.get_tags("notes").await.expect("获取标签失败"), tags);
+        assert_eq!(target.cloud_storage.download_file(&file_hash).await.expect("下载文件失败"), vec![7u8; 300 * 1024]);
+        assert_eq!(target.message.get_message(&msg_id).await.expect("消息获取失败").content, b"hello");
+        assert_eq!(target.clipboard_content(&clip_id).await.expect("剪切板获取失败"), b"clipboard");
+        assert_eq!(target.clipboard_content(&file_clip_id).await.expect("剪切板获取失败"), b"file content");
+
+        // 损坏或截断的归档导入失败
+        let mut corrupted = archive.clone();
+        let middle = corrupted.len() / 2;
+        corrupted[middle] ^= 0xff;
+        let fresh_dir = tempdir().expect("创建临时目录失败");
+        let fresh = UnifiedStorageManager::new("fresh".to_string(), fresh_dir.path().to_path_buf()).await
+            .expect("创建管理器失败");
+        assert!(fresh.import_archive(corrupted.as_slice()).await.is_err());
+        assert!(fresh.import_archive(&archive[..archive.len() - 8]).await.is_err());
+        assert!(fresh.import_archive(&b"not an archive"[..]).await.is_err());
+
+        // 清单核对通过前不写入存储，暂存目录被删除
+        assert!(fresh.object_storage.list().await.expect("列出对象失败").is_empty());
+        assert_eq!(fresh.cloud_storage.file_count(), 0);
+        assert!(fresh.clipboard.get_diff(0).await.is_empty());
+        let leftovers = std::fs::read_dir(fresh_dir.path()).expect("读取目录失败")
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(".archive-import-"))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[tokio::test]
+    async fn test_archive_import_reports_clipboard_conflicts() {
+        let source_dir = tempdir().expect("创建临时目录失败");
+        let source = UnifiedStorageManager::new("primary".to_string(), source_dir.path().to_path_buf()).await
+            .expect("创建管理器失败");
+        let clip_id = source.clipboard.add_entry(b"base".to_vec(), "text".to_string()).await
+            .expect("剪切板添加失败");
+        let mut archive = Vec::new();
+        source.export_archive(&mut archive).await.expect("导出归档失败");
+
+        let target_dir = tempdir().expect("创建临时目录失败");
+        let target = UnifiedStorageManager::new("replacement".to_string(), target_dir.path().to_path_buf()).await
+            .expect("创建管理器失败");
+        target.import_archive(archive.as_slice()).await.expect("导入归档失败");
+
+        // 两台设备各自修改同一条目后再次导入
+        source.clipboard.update_entry(&clip_id, b"source edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        target.clipboard.update_entry(&clip_id, b"target edit".to_vec(), "text".to_string()).await
+            .expect("修改失败");
+        let mut archive = Vec::new();
+        source.export_archive(&mut archive).await.expect("导出归档失败");
+        let imported = target.import_archive(archive.as_slice()).await.expect("导入归档失败");
+
+        assert_eq!(imported.clipboard_conflicts.len(), 1);
+        assert_eq!(imported.clipboard_conflicts[0].local.content, b"target edit");
+        assert_eq!(imported.clipboard_conflicts[0].remote.content, b"source edit");
+        assert_eq!(target.clipboard_content(&clip_id).await.expect("剪切板获取失败"), b"target edit");
+    }
 }
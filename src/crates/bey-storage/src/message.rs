@@ -1,14 +1,18 @@
 //! # 消息同步模块
 //!
 //! 提供消息的同步功能，支持群聊、私信、差异同步。
-//! 使用sled数据库进行持久化存储，通过bey-net模块进行实时同步。
+//! 使用键值存储后端（默认sled）进行持久化存储，通过bey-net模块进行实时同步。
+//! 使用sled持久化时，本设备发送的消息先写入预写日志，异常退出后重新打开时重放。
 
 use error::{ErrorInfo, ErrorCategory};
-use sled::Db;
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 use serde::{Deserialize, Serialize};
-use tracing::{info, debug};
+use tracing::{info, debug, warn};
+
+use crate::kv_backend::{expires_after, now_millis, spawn_compaction_task, spawn_expiry_sweeper, sweep_expired, KvBackend, SledBackend};
+use crate::wal::WriteAheadLog;
 
 /// 消息同步结果类型
 pub type MessageResult<T> = std::result::Result<T, ErrorInfo>;
@@ -43,6 +47,46 @@ pub struct Message {
     pub is_read: bool,
     /// 来源设备DNS ID
     pub source_device_id: String,
+    /// 是否被编辑过
+    #[serde(default)]
+    pub edited: bool,
+    /// 编辑历史（被替换的旧内容，按编辑顺序）
+    #[serde(default)]
+    pub edit_history: Vec<MessageEdit>,
+    /// 表情回应（表情 -> 回应的设备ID）
+    #[serde(default)]
+    pub reactions: BTreeMap<String, BTreeSet<String>>,
+    /// 过期时间（Unix毫秒时间戳），`None` 表示永不过期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl Message {
+    /// 消息是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(now_millis())
+    }
+
+    /// 消息在指定时间（Unix毫秒时间戳）是否已过期
+    fn is_expired_at(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// 获取各表情的回应数量
+    pub fn reaction_counts(&self) -> BTreeMap<String, usize> {
+        self.reactions.iter()
+            .map(|(emoji, devices)| (emoji.clone(), devices.len()))
+            .collect()
+    }
+}
+
+/// 消息编辑记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageEdit {
+    /// 编辑前的内容
+    pub previous_content: Vec<u8>,
+    /// 编辑时间戳
+    pub edited_at: u64,
 }
 
 /// 消息同步事件
@@ -68,10 +112,12 @@ pub enum MessageEvent {
 pub struct MessageManager {
     /// 本地设备ID
     device_id: String,
-    /// sled数据库
-    db: Arc<Db>,
+    /// 键值存储后端
+    db: Arc<dyn KvBackend>,
     /// 最大消息数
     max_messages: usize,
+    /// 发送消息的预写日志（仅sled持久化时启用）
+    wal: Option<Arc<Mutex<WriteAheadLog>>>,
 }
 
 impl MessageManager {
@@ -94,16 +140,40 @@ impl MessageManager {
         }
 
         // 打开sled数据库
-        let db = sled::open(&db_path)
+        let db = SledBackend::open(&db_path)
             .map_err(|e| ErrorInfo::new(6302, format!("打开数据库失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
-        info!("消息管理器初始化成功");
-        Ok(Self {
+        // 打开预写日志并重放上次异常退出时未应用的消息
+        let wal = WriteAheadLog::open(&db_path.with_extension("wal"))
+            .map_err(|e| ErrorInfo::new(6320, format!("打开预写日志失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+
+        let manager = Self {
             device_id,
             db: Arc::new(db),
             max_messages: 10000,
-        })
+            wal: Some(Arc::new(Mutex::new(wal))),
+        };
+        manager.recover_from_wal()?;
+
+        info!("消息管理器初始化成功");
+        Ok(manager)
+    }
+
+    /// 使用指定的存储后端创建消息管理器
+    ///
+    /// # 参数
+    ///
+    /// * `device_id` - 本地设备ID
+    /// * `backend` - 键值存储后端
+    pub fn with_backend(device_id: String, backend: Arc<dyn KvBackend>) -> Self {
+        Self {
+            device_id,
+            db: backend,
+            max_messages: 10000,
+            wal: None,
+        }
     }
 
     /// 发送消息
@@ -124,12 +194,49 @@ impl MessageManager {
         receiver_id: String,
         content: Vec<u8>,
         content_type: String,
+    ) -> MessageResult<String> {
+        self.store_new_message(message_type, receiver_id, content, content_type, None).await
+    }
+
+    /// 发送阅后即焚消息
+    ///
+    /// 消息在存活时间过后不可读取，并由过期清理删除
+    ///
+    /// # 参数
+    ///
+    /// * `message_type` - 消息类型
+    /// * `receiver_id` - 接收者ID
+    /// * `content` - 消息内容
+    /// * `content_type` - 内容类型
+    /// * `ttl` - 存活时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回消息ID或错误
+    pub async fn send_message_ephemeral(
+        &self,
+        message_type: MessageType,
+        receiver_id: String,
+        content: Vec<u8>,
+        content_type: String,
+        ttl: Duration,
+    ) -> MessageResult<String> {
+        self.store_new_message(message_type, receiver_id, content, content_type, Some(expires_after(ttl))).await
+    }
+
+    /// 创建并存储本设备发送的新消息
+    ///
+    /// 预写日志的同步和数据库刷盘在阻塞线程池中执行，不占用异步运行时的工作线程
+    async fn store_new_message(
+        &self,
+        message_type: MessageType,
+        receiver_id: String,
+        content: Vec<u8>,
+        content_type: String,
+        expires_at: Option<u64>,
     ) -> MessageResult<String> {
         let id = uuid::Uuid::new_v4().to_string();
-        let timestamp = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+        let timestamp = current_timestamp();
 
         let message = Message {
             id: id.clone(),
@@ -141,6 +248,10 @@ impl MessageManager {
             timestamp,
             is_read: false,
             source_device_id: self.device_id.clone(),
+            edited: false,
+            edit_history: Vec::new(),
+            reactions: BTreeMap::new(),
+            expires_at,
         };
 
         // 序列化并存储
@@ -148,21 +259,108 @@ impl MessageManager {
             .map_err(|e| ErrorInfo::new(6303, format!("序列化失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
-        self.db.insert(id.as_bytes(), message_bytes)
+        let db = Arc::clone(&self.db);
+        let wal = self.wal.clone();
+        let max_messages = self.max_messages;
+        tokio::task::spawn_blocking(move || {
+            Self::persist_new_message(db.as_ref(), wal.as_deref(), &message, message_bytes)?;
+            // 限制消息数量
+            trim_oldest_messages(db.as_ref(), max_messages);
+            Ok::<_, ErrorInfo>(())
+        }).await
+            .map_err(|e| ErrorInfo::new(6304, format!("存储任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))??;
+
+        debug!("发送消息: {} (类型: {:?})", id, message_type);
+        Ok(id)
+    }
+
+    /// 经由预写日志存储本设备发送的消息
+    ///
+    /// 消息先追加到预写日志并同步到磁盘，写入数据库并刷盘后截断日志
+    ///
+    /// # 参数
+    ///
+    /// * `db` - 消息数据库
+    /// * `wal` - 预写日志，未启用时直接写入数据库
+    /// * `message` - 新消息
+    /// * `message_bytes` - 序列化后的消息
+    fn persist_new_message(
+        db: &dyn KvBackend,
+        wal: Option<&Mutex<WriteAheadLog>>,
+        message: &Message,
+        message_bytes: Vec<u8>,
+    ) -> MessageResult<()> {
+        let Some(wal) = wal else {
+            return db.put(message.id.as_bytes(), message_bytes)
+                .map_err(|e| ErrorInfo::new(6304, format!("存储失败: {}", e))
+                    .with_category(ErrorCategory::Database));
+        };
+
+        // 持有日志锁直到截断，避免截断其他发送尚未应用的记录
+        let mut wal = wal.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        wal.append(message)
+            .map_err(|e| ErrorInfo::new(6321, format!("写入预写日志失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+
+        db.put(message.id.as_bytes(), message_bytes)
             .map_err(|e| ErrorInfo::new(6304, format!("存储失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
+        db.flush()
+            .map_err(|e| ErrorInfo::new(6322, format!("刷新消息数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+
+        // 截断失败时记录会在下次打开时重放，已存在的消息不会被覆盖
+        if let Err(e) = wal.truncate() {
+            warn!("截断预写日志失败: {}", e);
+        }
+        Ok(())
+    }
+
+    /// 重放预写日志中未应用的消息
+    ///
+    /// 数据库中已存在的消息保持不变，重放后按最大消息数删除最旧的消息，刷盘后截断日志
+    ///
+    /// # 返回值
+    ///
+    /// 返回重放的消息数量或错误
+    fn recover_from_wal(&self) -> MessageResult<usize> {
+        let Some(wal) = &self.wal else {
+            return Ok(0);
+        };
 
-        // 限制消息数量
-        let count = self.db.len();
-        if count > self.max_messages {
-            // 删除最旧的消息
-            if let Some(oldest_key) = self.find_oldest_message_key() {
-                let _ = self.db.remove(oldest_key);
+        let mut wal = wal.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let pending: Vec<Message> = wal.records()
+            .map_err(|e| ErrorInfo::new(6323, format!("读取预写日志失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let mut replayed = 0;
+        for message in &pending {
+            if self.db.contains_key(message.id.as_bytes()).unwrap_or(false) {
+                continue;
             }
+            let message_bytes = serde_json::to_vec(message)
+                .map_err(|e| ErrorInfo::new(6303, format!("序列化失败: {}", e))
+                    .with_category(ErrorCategory::Parse))?;
+            self.db.put(message.id.as_bytes(), message_bytes)
+                .map_err(|e| ErrorInfo::new(6304, format!("存储失败: {}", e))
+                    .with_category(ErrorCategory::Database))?;
+            replayed += 1;
         }
+        trim_oldest_messages(self.db.as_ref(), self.max_messages);
 
-        debug!("发送消息: {} (类型: {:?})", id, message_type);
-        Ok(id)
+        self.db.flush()
+            .map_err(|e| ErrorInfo::new(6322, format!("刷新消息数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+        wal.truncate()
+            .map_err(|e| ErrorInfo::new(6323, format!("截断预写日志失败: {}", e))
+                .with_category(ErrorCategory::FileSystem))?;
+
+        info!("从预写日志恢复 {} 条消息", replayed);
+        Ok(replayed)
     }
 
     /// 获取消息
@@ -173,7 +371,7 @@ impl MessageManager {
     ///
     /// # 返回值
     ///
-    /// 返回消息或错误
+    /// 返回消息或错误，已过期但尚未清理的消息视为不存在
     pub async fn get_message(&self, message_id: &str) -> MessageResult<Message> {
         let message_bytes = self.db.get(message_id.as_bytes())
             .map_err(|e| ErrorInfo::new(6305, format!("查询失败: {}", e))
@@ -185,6 +383,11 @@ impl MessageManager {
             .map_err(|e| ErrorInfo::new(6307, format!("反序列化失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
+        if message.is_expired() {
+            return Err(ErrorInfo::new(6306, format!("消息已过期: {}", message_id))
+                .with_category(ErrorCategory::Storage));
+        }
+
         Ok(message)
     }
 
@@ -200,14 +403,14 @@ impl MessageManager {
     /// 返回消息列表
     pub async fn get_private_messages(&self, peer_id: &str, limit: Option<usize>) -> Vec<Message> {
         let mut messages = Vec::new();
+        let now = now_millis();
 
-        for item in self.db.iter() {
-            if let Ok((_, value)) = item {
-                if let Ok(message) = serde_json::from_slice::<Message>(&value) {
-                    if message.message_type == MessageType::Private 
-                        && (message.receiver_id == peer_id || message.sender_id == peer_id) {
-                        messages.push(message);
-                    }
+        for (_, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(message) = serde_json::from_slice::<Message>(&value) {
+                if message.message_type == MessageType::Private 
+                    && !message.is_expired_at(now)
+                    && (message.receiver_id == peer_id || message.sender_id == peer_id) {
+                    messages.push(message);
                 }
             }
         }
@@ -233,14 +436,14 @@ impl MessageManager {
     /// 返回消息列表
     pub async fn get_group_messages(&self, group_id: &str, limit: Option<usize>) -> Vec<Message> {
         let mut messages = Vec::new();
+        let now = now_millis();
 
-        for item in self.db.iter() {
-            if let Ok((_, value)) = item {
-                if let Ok(message) = serde_json::from_slice::<Message>(&value) {
-                    if message.message_type == MessageType::Group 
-                        && message.receiver_id == group_id {
-                        messages.push(message);
-                    }
+        for (_, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(message) = serde_json::from_slice::<Message>(&value) {
+                if message.message_type == MessageType::Group 
+                    && !message.is_expired_at(now)
+                    && message.receiver_id == group_id {
+                    messages.push(message);
                 }
             }
         }
@@ -266,16 +469,105 @@ impl MessageManager {
     pub async fn mark_as_read(&self, message_id: &str) -> MessageResult<()> {
         let mut message = self.get_message(message_id).await?;
         message.is_read = true;
+        self.update_message(&message)?;
 
-        let message_bytes = serde_json::to_vec(&message)
+        debug!("标记消息已读: {}", message_id);
+        Ok(())
+    }
+
+    /// 编辑消息
+    ///
+    /// 只能编辑本设备发送的消息，旧内容保存在编辑历史中
+    ///
+    /// # 参数
+    ///
+    /// * `message_id` - 消息ID
+    /// * `new_content` - 新的消息内容
+    ///
+    /// # 返回值
+    ///
+    /// 返回操作结果
+    pub async fn edit_message(&self, message_id: &str, new_content: Vec<u8>) -> MessageResult<()> {
+        let mut message = self.get_message(message_id).await?;
+        if message.sender_id != self.device_id {
+            return Err(ErrorInfo::new(6317, format!("只能编辑本设备发送的消息: {}", message_id))
+                .with_category(ErrorCategory::Validation));
+        }
+
+        let previous_content = std::mem::replace(&mut message.content, new_content);
+        message.edit_history.push(MessageEdit {
+            previous_content,
+            edited_at: current_timestamp(),
+        });
+        message.edited = true;
+        self.update_message(&message)?;
+
+        debug!("编辑消息: {} (第 {} 次)", message_id, message.edit_history.len());
+        Ok(())
+    }
+
+    /// 添加表情回应
+    ///
+    /// 同一设备对同一表情重复回应只计一次
+    ///
+    /// # 参数
+    ///
+    /// * `message_id` - 消息ID
+    /// * `emoji` - 表情
+    /// * `from_device` - 回应的设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回操作结果
+    pub async fn react_to_message(&self, message_id: &str, emoji: &str, from_device: &str) -> MessageResult<()> {
+        let mut message = self.get_message(message_id).await?;
+        message.reactions.entry(emoji.to_string())
+            .or_default()
+            .insert(from_device.to_string());
+        self.update_message(&message)?;
+
+        debug!("设备 {} 回应消息 {}: {}", from_device, message_id, emoji);
+        Ok(())
+    }
+
+    /// 撤销表情回应
+    ///
+    /// # 参数
+    ///
+    /// * `message_id` - 消息ID
+    /// * `emoji` - 表情
+    /// * `from_device` - 回应的设备ID
+    ///
+    /// # 返回值
+    ///
+    /// 返回操作结果，该设备没有此回应时不做修改
+    pub async fn remove_reaction(&self, message_id: &str, emoji: &str, from_device: &str) -> MessageResult<()> {
+        let mut message = self.get_message(message_id).await?;
+        let Some(devices) = message.reactions.get_mut(emoji) else {
+            return Ok(());
+        };
+        if !devices.remove(from_device) {
+            return Ok(());
+        }
+        if devices.is_empty() {
+            message.reactions.remove(emoji);
+        }
+        self.update_message(&message)?;
+
+        debug!("设备 {} 撤销消息 {} 的回应: {}", from_device, message_id, emoji);
+        Ok(())
+    }
+
+    /// 覆盖写入已存在的消息
+    fn update_message(&self, message: &Message) -> MessageResult<()> {
+        let message_bytes = serde_json::to_vec(message)
             .map_err(|e| ErrorInfo::new(6308, format!("序列化失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
-        self.db.insert(message_id.as_bytes(), message_bytes)
+        self.db.put(message.id.as_bytes(), message_bytes)
             .map_err(|e| ErrorInfo::new(6309, format!("更新失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
-        debug!("标记消息已读: {}", message_id);
         Ok(())
     }
 
@@ -289,7 +581,7 @@ impl MessageManager {
     ///
     /// 返回操作结果
     pub async fn delete_message(&self, message_id: &str) -> MessageResult<()> {
-        self.db.remove(message_id.as_bytes())
+        self.db.delete(message_id.as_bytes())
             .map_err(|e| ErrorInfo::new(6310, format!("删除失败: {}", e))
                 .with_category(ErrorCategory::Database))?
             .ok_or_else(|| ErrorInfo::new(6311, format!("消息不存在: {}", message_id))
@@ -334,6 +626,12 @@ impl MessageManager {
 
     /// 合并远程消息
     async fn merge_message(&self, remote_message: Message) -> MessageResult<()> {
+        // 已过期的远程消息不再保存
+        if remote_message.is_expired() {
+            debug!("忽略已过期的远程消息: {}", remote_message.id);
+            return Ok(());
+        }
+
         // 检查消息是否已存在
         if self.db.contains_key(remote_message.id.as_bytes())
             .map_err(|e| ErrorInfo::new(6312, format!("查询失败: {}", e))
@@ -346,7 +644,7 @@ impl MessageManager {
             .map_err(|e| ErrorInfo::new(6313, format!("序列化失败: {}", e))
                 .with_category(ErrorCategory::Parse))?;
 
-        self.db.insert(remote_message.id.as_bytes(), message_bytes)
+        self.db.put(remote_message.id.as_bytes(), message_bytes)
             .map_err(|e| ErrorInfo::new(6314, format!("存储失败: {}", e))
                 .with_category(ErrorCategory::Database))?;
 
@@ -365,13 +663,12 @@ impl MessageManager {
     /// 返回差异消息列表
     pub async fn get_diff(&self, since_timestamp: u64) -> Vec<Message> {
         let mut diff = Vec::new();
+        let now = now_millis();
 
-        for item in self.db.iter() {
-            if let Ok((_, value)) = item {
-                if let Ok(message) = serde_json::from_slice::<Message>(&value) {
-                    if message.timestamp > since_timestamp {
-                        diff.push(message);
-                    }
+        for (_, value) in self.db.scan(&[]).unwrap_or_default() {
+            if let Ok(message) = serde_json::from_slice::<Message>(&value) {
+                if message.timestamp > since_timestamp && !message.is_expired_at(now) {
+                    diff.push(message);
                 }
             }
         }
@@ -379,23 +676,91 @@ impl MessageManager {
         diff
     }
 
-    /// 查找最旧消息的键
-    fn find_oldest_message_key(&self) -> Option<Vec<u8>> {
-        let mut oldest_key: Option<Vec<u8>> = None;
-        let mut oldest_timestamp = u64::MAX;
-
-        for item in self.db.iter() {
-            if let Ok((key, value)) = item {
-                if let Ok(message) = serde_json::from_slice::<Message>(&value) {
-                    if message.timestamp < oldest_timestamp {
-                        oldest_timestamp = message.timestamp;
-                        oldest_key = Some(key.to_vec());
-                    }
-                }
+    /// 已存储且未过期的消息数量
+    ///
+    /// 过期但尚未被清理的消息不计入
+    pub fn message_count(&self) -> usize {
+        let now = now_millis();
+        match self.db.scan(&[]) {
+            Ok(entries) => entries.iter().filter(|(_, value)| !message_expired(value, now)).count(),
+            Err(e) => {
+                warn!("统计消息数量失败: {}", e);
+                0
             }
         }
+    }
+
+    /// 消息数据库占用的磁盘空间（字节）
+    pub fn size_on_disk(&self) -> u64 {
+        self.db.size_on_disk()
+    }
+
+    /// 压缩消息数据库
+    ///
+    /// 在阻塞线程池中执行，分批重写存活数据并刷盘，不会长时间阻塞读取。
+    ///
+    /// # 返回值
+    ///
+    /// 返回回收的字节数或错误
+    pub async fn compact(&self) -> MessageResult<u64> {
+        let db = Arc::clone(&self.db);
+        let reclaimed = tokio::task::spawn_blocking(move || db.compact()).await
+            .map_err(|e| ErrorInfo::new(6316, format!("压缩任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6316, format!("压缩消息数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+
+        info!("消息数据库压缩完成，回收 {} 字节", reclaimed);
+        Ok(reclaimed)
+    }
+
+    /// 将消息数据库缓冲的写入刷到磁盘
+    pub async fn flush(&self) -> MessageResult<()> {
+        let db = Arc::clone(&self.db);
+        tokio::task::spawn_blocking(move || db.flush()).await
+            .map_err(|e| ErrorInfo::new(6318, format!("刷盘任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6318, format!("刷新消息数据库失败: {}", e))
+                .with_category(ErrorCategory::Database))
+    }
+
+    /// 启动定期压缩任务
+    ///
+    /// 任务在消息管理器释放后自动退出
+    ///
+    /// # 参数
+    ///
+    /// * `interval` - 压缩间隔
+    pub fn start_compaction_task(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        spawn_compaction_task(&self.db, interval, "消息数据库")
+    }
+
+    /// 删除已过期的消息
+    ///
+    /// # 返回值
+    ///
+    /// 返回删除的消息数量或错误
+    pub async fn sweep_expired(&self) -> MessageResult<usize> {
+        let db = Arc::clone(&self.db);
+        let removed = tokio::task::spawn_blocking(move || sweep_expired(db.as_ref(), message_expired)).await
+            .map_err(|e| ErrorInfo::new(6319, format!("过期清理任务异常终止: {}", e))
+                .with_category(ErrorCategory::System))?
+            .map_err(|e| ErrorInfo::new(6319, format!("清理过期消息失败: {}", e))
+                .with_category(ErrorCategory::Database))?;
+
+        debug!("清理 {} 条过期消息", removed);
+        Ok(removed)
+    }
 
-        oldest_key
+    /// 启动定期过期清理任务
+    ///
+    /// 任务在消息管理器释放后自动退出
+    ///
+    /// # 参数
+    ///
+    /// * `interval` - 清理间隔
+    pub fn start_expiry_sweeper(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        spawn_expiry_sweeper(&self.db, interval, "消息数据库", message_expired)
     }
 
     /// 清空所有消息
@@ -409,6 +774,51 @@ impl MessageManager {
     }
 }
 
+/// 判断序列化的消息在指定时间是否已过期
+fn message_expired(bytes: &[u8], now: u64) -> bool {
+    serde_json::from_slice::<Message>(bytes)
+        .map(|message| message.is_expired_at(now))
+        .unwrap_or(false)
+}
+
+/// 当前时间戳（秒）
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 消息数超过上限时删除最旧的消息
+///
+/// # 参数
+///
+/// * `db` - 消息数据库
+/// * `max_messages` - 最大消息数
+///
+/// # 返回值
+///
+/// 返回删除的消息数量
+fn trim_oldest_messages(db: &dyn KvBackend, max_messages: usize) -> usize {
+    let excess = db.len().saturating_sub(max_messages);
+    if excess == 0 {
+        return 0;
+    }
+
+    let mut messages: Vec<(u64, Vec<u8>)> = db.scan(&[]).unwrap_or_default()
+        .into_iter()
+        .filter_map(|(key, value)| {
+            serde_json::from_slice::<Message>(&value).ok().map(|message| (message.timestamp, key))
+        })
+        .collect();
+    messages.sort();
+
+    messages.into_iter()
+        .take(excess)
+        .filter(|(_, key)| db.delete(key).is_ok())
+        .count()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -485,4 +895,230 @@ mod tests {
             assert_eq!(message.content, b"Test");
         }
     }
+
+    #[tokio::test]
+    async fn test_wal_replays_message_after_crash_before_apply() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let db_path = temp_dir.path().join("messages.db");
+        let wal_path = db_path.with_extension("wal");
+
+        let message = Message {
+            id: "crashed-message".to_string(),
+            message_type: MessageType::Private,
+            sender_id: "device1".to_string(),
+            receiver_id: "device2".to_string(),
+            content: b"Survives crash".to_vec(),
+            content_type: "text".to_string(),
+            timestamp: current_timestamp(),
+            is_read: false,
+            source_device_id: "device1".to_string(),
+            edited: false,
+            edit_history: Vec::new(),
+            reactions: BTreeMap::new(),
+            expires_at: None,
+        };
+
+        {
+            let manager = MessageManager::new("device1".to_string(), db_path.clone()).await
+                .expect("创建管理器失败");
+
+            // 正常发送后预写日志被截断
+            manager.send_message(
+                MessageType::Private,
+                "device2".to_string(),
+                b"Applied".to_vec(),
+                "text".to_string(),
+            ).await.expect("发送失败");
+            assert_eq!(std::fs::metadata(&wal_path).expect("读取预写日志失败").len(), 0);
+
+            // 模拟追加预写日志之后、写入数据库之前崩溃
+            manager.wal.as_ref().expect("应启用预写日志")
+                .lock().expect("获取预写日志锁失败")
+                .append(&message).expect("写入预写日志失败");
+            assert!(manager.get_message(&message.id).await.is_err());
+        }
+
+        // 重新打开时重放未应用的消息并截断日志
+        let manager = MessageManager::new("device1".to_string(), db_path).await
+            .expect("创建管理器失败");
+        let recovered = manager.get_message(&message.id).await.expect("恢复的消息应存在");
+        assert_eq!(recovered.content, b"Survives crash");
+        assert_eq!(manager.message_count(), 2);
+        assert_eq!(std::fs::metadata(&wal_path).expect("读取预写日志失败").len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_wal_replay_applies_message_limit() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let db_path = temp_dir.path().join("messages.db");
+
+        let mut manager = MessageManager::new("device1".to_string(), db_path).await
+            .expect("创建管理器失败");
+        manager.max_messages = 2;
+        for (id, timestamp) in [("oldest", 1), ("middle", 2), ("newest", 3)] {
+            let message = Message {
+                id: id.to_string(),
+                message_type: MessageType::Group,
+                sender_id: "device1".to_string(),
+                receiver_id: "group".to_string(),
+                content: id.as_bytes().to_vec(),
+                content_type: "text".to_string(),
+                timestamp,
+                is_read: false,
+                source_device_id: "device1".to_string(),
+                edited: false,
+                edit_history: Vec::new(),
+                reactions: BTreeMap::new(),
+                expires_at: None,
+            };
+            manager.wal.as_ref().expect("应启用预写日志")
+                .lock().expect("获取预写日志锁失败")
+                .append(&message).expect("写入预写日志失败");
+        }
+
+        // 重放后超过上限的最旧消息被删除
+        assert_eq!(manager.recover_from_wal().expect("重放失败"), 3);
+        assert_eq!(manager.message_count(), 2);
+        assert!(manager.get_message("oldest").await.is_err());
+        assert!(manager.get_message("newest").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_compaction_reduces_size_on_disk() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let db_path = temp_dir.path().join("messages.db");
+
+        let manager = MessageManager::new("device1".to_string(), db_path).await
+            .expect("创建管理器失败");
+
+        // 写入大量消息后删除绝大部分
+        let mut message_ids = Vec::new();
+        for i in 0..2000 {
+            let id = manager.send_message(
+                MessageType::Private,
+                "device2".to_string(),
+                vec![(i % 251) as u8; 4096],
+                "binary".to_string(),
+            ).await.expect("发送失败");
+            message_ids.push(id);
+        }
+        for id in message_ids.iter().skip(10) {
+            manager.delete_message(id).await.expect("删除失败");
+        }
+
+        let before = manager.size_on_disk();
+        let reclaimed = manager.compact().await.expect("压缩失败");
+        let after = manager.size_on_disk();
+
+        assert!(after < before, "压缩后磁盘占用应减少: {} -> {}", before, after);
+        assert!(reclaimed > 0);
+
+        // 存活的消息不受影响
+        assert_eq!(manager.message_count(), 10);
+        for id in message_ids.iter().take(10) {
+            manager.get_message(id).await.expect("获取失败");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_edit_message_preserves_history() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = MessageManager::new("device1".to_string(), temp_dir.path().join("messages.db")).await
+            .expect("创建管理器失败");
+
+        let msg_id = manager.send_message(
+            MessageType::Private,
+            "device2".to_string(),
+            b"v1".to_vec(),
+            "text".to_string(),
+        ).await.expect("发送失败");
+        assert!(!manager.get_message(&msg_id).await.expect("获取失败").edited);
+
+        manager.edit_message(&msg_id, b"v2".to_vec()).await.expect("编辑失败");
+        manager.edit_message(&msg_id, b"v3".to_vec()).await.expect("编辑失败");
+
+        let message = manager.get_message(&msg_id).await.expect("获取失败");
+        assert_eq!(message.content, b"v3");
+        assert!(message.edited);
+        let history: Vec<&[u8]> = message.edit_history.iter()
+            .map(|edit| edit.previous_content.as_slice())
+            .collect();
+        assert_eq!(history, vec![&b"v1"[..], &b"v2"[..]]);
+
+        // 不能编辑其他设备发送的消息
+        let mut remote = message.clone();
+        remote.id = "remote-msg".to_string();
+        remote.sender_id = "device2".to_string();
+        manager.handle_sync_event(MessageEvent::NewMessage(remote)).await.expect("合并失败");
+        let err = manager.edit_message("remote-msg", b"hacked".to_vec()).await.expect_err("应该失败");
+        assert_eq!(err.code(), 6317);
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_reaction() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = MessageManager::new("device1".to_string(), temp_dir.path().join("messages.db")).await
+            .expect("创建管理器失败");
+
+        let msg_id = manager.send_message(
+            MessageType::Group,
+            "group1".to_string(),
+            b"lunch?".to_vec(),
+            "text".to_string(),
+        ).await.expect("发送失败");
+
+        manager.react_to_message(&msg_id, "👍", "device2").await.expect("回应失败");
+        manager.react_to_message(&msg_id, "👍", "device3").await.expect("回应失败");
+        manager.react_to_message(&msg_id, "👍", "device3").await.expect("回应失败");
+        manager.react_to_message(&msg_id, "🎉", "device2").await.expect("回应失败");
+
+        let counts = manager.get_message(&msg_id).await.expect("获取失败").reaction_counts();
+        assert_eq!(counts.get("👍"), Some(&2));
+        assert_eq!(counts.get("🎉"), Some(&1));
+
+        manager.remove_reaction(&msg_id, "🎉", "device2").await.expect("撤销失败");
+        manager.remove_reaction(&msg_id, "👍", "device3").await.expect("撤销失败");
+
+        let counts = manager.get_message(&msg_id).await.expect("获取失败").reaction_counts();
+        assert_eq!(counts.get("👍"), Some(&1));
+        assert!(!counts.contains_key("🎉"));
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_message_expires_and_is_swept() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let manager = MessageManager::new("device1".to_string(), temp_dir.path().join("messages.db")).await
+            .expect("创建管理器失败");
+
+        let permanent = manager.send_message(MessageType::Private, "device2".to_string(), b"keep".to_vec(), "text".to_string()).await
+            .expect("发送消息失败");
+        let ephemeral = manager.send_message_ephemeral(
+            MessageType::Private,
+            "device2".to_string(),
+            b"burn".to_vec(),
+            "text".to_string(),
+            Duration::from_millis(50),
+        ).await.expect("发送阅后即焚消息失败");
+        assert!(manager.get_message(&ephemeral).await.is_ok());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // 过期但尚未清理的消息不可读取，也不出现在历史中
+        let err = manager.get_message(&ephemeral).await.expect_err("过期消息不应可读");
+        assert_eq!(err.code(), 6306);
+        let history = manager.get_private_messages("device2", None).await;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, permanent);
+        assert_eq!(manager.message_count(), 1, "过期消息不应计入数量");
+        assert_eq!(manager.db.len(), 2, "过期消息尚未被清理");
+
+        // 后台清理任务删除过期消息，保留永久消息
+        let sweeper = manager.start_expiry_sweeper(Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        sweeper.abort();
+        assert_eq!(manager.db.len(), 1);
+        assert_eq!(manager.message_count(), 1);
+        assert!(manager.get_message(&permanent).await.is_ok());
+        assert_eq!(manager.sweep_expired().await.expect("清理失败"), 0);
+    }
 }
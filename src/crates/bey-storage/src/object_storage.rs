@@ -4,21 +4,86 @@
 //! 用于直接的文件传输场景。
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tracing::{info, debug};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+use tracing::{info, debug, warn};
+
+use crate::events::{StorageEventBus, StorageKind, StorageOperation};
+use crate::kv_backend::{open_backend, KvBackend, KvBackendKind};
+use crate::read_cache::{CacheStats, ReadCache};
 
 /// 对象存储结果类型
 pub type ObjectStorageResult<T> = std::result::Result<T, ErrorInfo>;
 
+/// 存放对象校验和的目录名（位于存储根目录下，不出现在对象列表中）
+const CHECKSUM_DIR: &str = ".checksums";
+
+/// 默认的对象标签数据库目录名（位于存储根目录下，不出现在对象列表中）
+const TAG_DB_DIR: &str = ".tags.db";
+
+/// 旧版本的对象标签索引文件及其临时文件，打开存储时导入标签数据库后删除
+const LEGACY_TAG_INDEX_FILE: &str = ".tags.json";
+const LEGACY_TAG_INDEX_TEMP_FILE: &str = ".tags.tmp";
+
+/// 存储内部使用、不能用作对象ID第一级路径的名称
+const RESERVED_NAMES: [&str; 4] = [CHECKSUM_DIR, TAG_DB_DIR, LEGACY_TAG_INDEX_FILE, LEGACY_TAG_INDEX_TEMP_FILE];
+
+/// 流式写入对象时每次读取的字节数
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 内容哈希索引，用于写入前按内容去重
+#[derive(Default)]
+struct ContentIndex {
+    /// 内容哈希 -> 对象ID
+    by_hash: HashMap<String, BTreeSet<String>>,
+    /// 对象ID -> 内容哈希
+    by_object: HashMap<String, String>,
+}
+
+impl ContentIndex {
+    /// 记录对象的内容哈希，替换旧记录
+    fn insert(&mut self, object_id: &str, hash: String) {
+        self.remove(object_id);
+        self.by_hash.entry(hash.clone()).or_default().insert(object_id.to_string());
+        self.by_object.insert(object_id.to_string(), hash);
+    }
+
+    /// 移除对象的记录
+    fn remove(&mut self, object_id: &str) {
+        if let Some(hash) = self.by_object.remove(object_id) {
+            if let Some(objects) = self.by_hash.get_mut(&hash) {
+                objects.remove(object_id);
+                if objects.is_empty() {
+                    self.by_hash.remove(&hash);
+                }
+            }
+        }
+    }
+
+    /// 内容哈希对应的对象
+    fn find(&self, hash: &str) -> Vec<String> {
+        self.by_hash.get(hash).map(|objects| objects.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
 /// 对象存储配置
 #[derive(Debug, Clone)]
 pub struct ObjectStorageConfig {
     /// 存储根目录
     pub storage_root: PathBuf,
-    /// 是否启用校验
+    /// 是否启用校验（写入对象时记录SHA-256校验和，供完整性扫描使用）
     pub enable_checksum: bool,
+    /// 读缓存最大字节数（`None` 表示不启用读缓存）
+    pub read_cache_bytes: Option<u64>,
+    /// 标签索引使用的存储后端
+    pub tag_backend: KvBackendKind,
+    /// 标签数据库路径（`None` 表示存储根目录下的 `.tags.db`）
+    pub tag_db_path: Option<PathBuf>,
 }
 
 impl Default for ObjectStorageConfig {
@@ -26,6 +91,9 @@ impl Default for ObjectStorageConfig {
         Self {
             storage_root: PathBuf::from("./object_storage"),
             enable_checksum: true,
+            read_cache_bytes: None,
+            tag_backend: KvBackendKind::Sled,
+            tag_db_path: None,
         }
     }
 }
@@ -35,6 +103,16 @@ impl Default for ObjectStorageConfig {
 /// 负责文件的直接存储和检索，不进行分片或冗余
 pub struct ObjectStorage {
     config: ObjectStorageConfig,
+    /// 存储事件广播器
+    events: StorageEventBus,
+    /// 热点对象读缓存
+    cache: Option<ReadCache>,
+    /// 标签索引（对象ID -> JSON编码的标签）
+    tags: Arc<dyn KvBackend>,
+    /// 串行化标签的存在检查和移动
+    tag_lock: Mutex<()>,
+    /// 内容哈希索引，首次按内容查找时从磁盘建立
+    content_index: std::sync::Mutex<Option<ContentIndex>>,
 }
 
 impl ObjectStorage {
@@ -54,8 +132,89 @@ impl ObjectStorage {
                 .with_category(ErrorCategory::FileSystem)
                 .with_severity(ErrorSeverity::Error))?;
         
+        let tag_db_path = config.tag_db_path.clone().unwrap_or_else(|| config.storage_root.join(TAG_DB_DIR));
+        let tags = open_backend(config.tag_backend, &tag_db_path)
+            .map_err(|e| ErrorInfo::new(6019, format!("打开标签索引失败: {}", e))
+                .with_category(ErrorCategory::Database)
+                .with_severity(ErrorSeverity::Error))?;
+
         info!("对象存储初始化成功: {:?}", config.storage_root);
-        Ok(Self { config })
+        let cache = config.read_cache_bytes.map(ReadCache::new);
+        let storage = Self {
+            config,
+            events: StorageEventBus::new(),
+            cache,
+            tags,
+            tag_lock: Mutex::new(()),
+            content_index: std::sync::Mutex::new(None),
+        };
+        storage.migrate_legacy_tag_index().await?;
+        Ok(storage)
+    }
+
+    /// 使用指定的事件广播器，与其他存储共享事件通道
+    pub fn with_event_bus(mut self, events: StorageEventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 订阅存储事件
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<crate::events::StorageEvent> {
+        self.events.subscribe()
+    }
+
+    /// 获取读缓存统计
+    ///
+    /// # 返回值
+    ///
+    /// 未启用读缓存时返回 `None`
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(ReadCache::stats)
+    }
+
+    /// 使对象的读缓存失效
+    fn invalidate_cache(&self, object_id: &str) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(object_id);
+        }
+    }
+
+    /// 对象校验和文件的路径
+    fn checksum_path(&self, object_id: &str) -> PathBuf {
+        self.config.storage_root.join(CHECKSUM_DIR).join(format!("{}.sha256", object_id))
+    }
+
+    /// 更新已建立的内容哈希索引
+    ///
+    /// 索引尚未建立时不做处理，建立时会从磁盘读取
+    fn update_content_index(&self, update: impl FnOnce(&mut ContentIndex)) {
+        if let Ok(mut index) = self.content_index.lock() {
+            if let Some(index) = index.as_mut() {
+                update(index);
+            }
+        }
+    }
+
+    /// 写入或清除对象的校验和
+    ///
+    /// 未启用校验时删除旧的校验和，避免覆盖后的对象被误判为损坏
+    async fn record_checksum(&self, object_id: &str, checksum: &str) -> ObjectStorageResult<()> {
+        let path = self.checksum_path(object_id);
+        if !self.config.enable_checksum {
+            let _ = fs::remove_file(&path).await;
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await
+                .map_err(|e| ErrorInfo::new(6002, format!("创建父目录失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error))?;
+        }
+        fs::write(&path, checksum).await
+            .map_err(|e| ErrorInfo::new(6017, format!("写入校验和失败: {}", e))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Error))
     }
 
     /// 存储对象
@@ -69,8 +228,24 @@ impl ObjectStorage {
     ///
     /// 返回存储路径或错误
     pub async fn store(&self, object_id: &str, data: &[u8]) -> ObjectStorageResult<PathBuf> {
+        self.store_reader(object_id, data).await
+    }
+
+    /// 从读取器分块存储对象，不把整个对象加载到内存
+    ///
+    /// # 参数
+    ///
+    /// * `object_id` - 对象唯一标识符
+    /// * `reader` - 对象数据来源，读到末尾为止
+    ///
+    /// # 返回值
+    ///
+    /// 返回存储路径或错误
+    pub async fn store_reader<R: AsyncRead + Unpin>(&self, object_id: &str, mut reader: R) -> ObjectStorageResult<PathBuf> {
+        check_object_id(object_id)?;
         let path = self.config.storage_root.join(object_id);
-        
+        self.invalidate_cache(object_id);
+
         // 创建父目录（如果需要）
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).await
@@ -84,21 +259,68 @@ impl ObjectStorage {
             .map_err(|e| ErrorInfo::new(6003, format!("创建文件失败: {}", e))
                 .with_category(ErrorCategory::FileSystem)
                 .with_severity(ErrorSeverity::Error))?;
-        
-        file.write_all(data).await
-            .map_err(|e| ErrorInfo::new(6004, format!("写入文件失败: {}", e))
-                .with_category(ErrorCategory::FileSystem)
-                .with_severity(ErrorSeverity::Error))?;
+
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut buffer = vec![0u8; STREAM_BUFFER_SIZE];
+        loop {
+            let read = reader.read(&mut buffer).await
+                .map_err(|e| ErrorInfo::new(6008, format!("读取对象数据失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read]).await
+                .map_err(|e| ErrorInfo::new(6004, format!("写入文件失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error))?;
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
         
         file.sync_all().await
             .map_err(|e| ErrorInfo::new(6005, format!("同步文件失败: {}", e))
                 .with_category(ErrorCategory::FileSystem)
                 .with_severity(ErrorSeverity::Error))?;
 
-        debug!("对象存储成功: {} ({} 字节)", object_id, data.len());
+        let checksum = format!("{:x}", hasher.finalize());
+        self.record_checksum(object_id, &checksum).await?;
+        self.update_content_index(|index| index.insert(object_id, checksum));
+
+        // 写入期间的并发读取可能缓存了部分内容，写入完成后再次失效
+        self.invalidate_cache(object_id);
+
+        debug!("对象存储成功: {} ({} 字节)", object_id, size);
+        self.events.emit(StorageKind::Object, StorageOperation::Write, object_id, size);
         Ok(path)
     }
 
+    /// 打开对象用于分块读取
+    ///
+    /// 不经过读缓存，也不发出读取事件，适合导出等需要顺序读取大对象的场景
+    ///
+    /// # 参数
+    ///
+    /// * `object_id` - 对象唯一标识符
+    ///
+    /// # 返回值
+    ///
+    /// 返回打开的对象文件，对象不存在时返回错误
+    pub async fn open(&self, object_id: &str) -> ObjectStorageResult<fs::File> {
+        let path = self.config.storage_root.join(object_id);
+        if !path.is_file() {
+            return Err(ErrorInfo::new(6006, format!("对象不存在: {}", object_id))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        fs::File::open(&path).await
+            .map_err(|e| ErrorInfo::new(6007, format!("打开文件失败: {}", e))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Error))
+    }
+
     /// 检索对象
     ///
     /// # 参数
@@ -109,8 +331,20 @@ impl ObjectStorage {
     ///
     /// 返回对象数据或错误
     pub async fn retrieve(&self, object_id: &str) -> ObjectStorageResult<Vec<u8>> {
+        let generation = match &self.cache {
+            Some(cache) => {
+                if let Some(data) = cache.get(object_id) {
+                    debug!("对象读缓存命中: {} ({} 字节)", object_id, data.len());
+                    self.events.emit(StorageKind::Object, StorageOperation::Read, object_id, data.len() as u64);
+                    return Ok(data.as_ref().clone());
+                }
+                Some(cache.generation())
+            }
+            None => None,
+        };
+
         let path = self.config.storage_root.join(object_id);
-        
+
         if !path.exists() {
             return Err(ErrorInfo::new(6006, format!("对象不存在: {}", object_id))
                 .with_category(ErrorCategory::FileSystem)
@@ -128,7 +362,12 @@ impl ObjectStorage {
                 .with_category(ErrorCategory::FileSystem)
                 .with_severity(ErrorSeverity::Error))?;
 
+        if let (Some(cache), Some(generation)) = (&self.cache, generation) {
+            cache.insert(object_id, Arc::new(data.clone()), generation);
+        }
+
         debug!("对象检索成功: {} ({} 字节)", object_id, data.len());
+        self.events.emit(StorageKind::Object, StorageOperation::Read, object_id, data.len() as u64);
         Ok(data)
     }
 
@@ -142,6 +381,7 @@ impl ObjectStorage {
     ///
     /// 返回删除结果
     pub async fn delete(&self, object_id: &str) -> ObjectStorageResult<()> {
+        check_object_id(object_id)?;
         let path = self.config.storage_root.join(object_id);
         
         if !path.exists() {
@@ -150,15 +390,132 @@ impl ObjectStorage {
                 .with_severity(ErrorSeverity::Warning));
         }
 
+        let size = fs::metadata(&path).await.map(|metadata| metadata.len()).unwrap_or(0);
         fs::remove_file(&path).await
             .map_err(|e| ErrorInfo::new(6010, format!("删除文件失败: {}", e))
                 .with_category(ErrorCategory::FileSystem)
                 .with_severity(ErrorSeverity::Error))?;
+        let _ = fs::remove_file(self.checksum_path(object_id)).await;
+        self.invalidate_cache(object_id);
+        self.update_content_index(|index| index.remove(object_id));
+        self.update_tags_after(object_id, None).await;
 
         debug!("对象删除成功: {}", object_id);
+        self.events.emit(StorageKind::Object, StorageOperation::Delete, object_id, size);
+        Ok(())
+    }
+
+    /// 重命名（移动）对象
+    ///
+    /// 在同一存储根目录内原子地移动底层文件，不会复制数据。
+    ///
+    /// # 参数
+    ///
+    /// * `from_key` - 原对象标识符
+    /// * `to_key` - 新对象标识符
+    /// * `overwrite` - 目标已存在时是否覆盖
+    ///
+    /// # 返回值
+    ///
+    /// 返回重命名结果，目标已存在且不允许覆盖时返回错误
+    pub async fn rename(&self, from_key: &str, to_key: &str, overwrite: bool) -> ObjectStorageResult<()> {
+        check_object_id(from_key)?;
+        check_object_id(to_key)?;
+        let from_path = self.config.storage_root.join(from_key);
+        let to_path = self.config.storage_root.join(to_key);
+
+        if !from_path.exists() {
+            return Err(ErrorInfo::new(6013, format!("对象不存在: {}", from_key))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        if from_path == to_path {
+            return Ok(());
+        }
+
+        // 创建目标父目录（如果需要）
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent).await
+                .map_err(|e| ErrorInfo::new(6002, format!("创建父目录失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error))?;
+        }
+
+        if overwrite {
+            // rename 在同一文件系统内是原子的，并会替换已存在的目标
+            fs::rename(&from_path, &to_path).await
+                .map_err(|e| ErrorInfo::new(6014, format!("重命名对象失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error))?;
+        } else {
+            // 硬链接在目标已存在时原子地失败，避免检查与移动之间的竞争
+            fs::hard_link(&from_path, &to_path).await
+                .map_err(|e| if e.kind() == std::io::ErrorKind::AlreadyExists {
+                    ErrorInfo::new(6015, format!("目标对象已存在: {}", to_key))
+                        .with_category(ErrorCategory::FileSystem)
+                        .with_severity(ErrorSeverity::Warning)
+                } else {
+                    ErrorInfo::new(6014, format!("重命名对象失败: {}", e))
+                        .with_category(ErrorCategory::FileSystem)
+                        .with_severity(ErrorSeverity::Error)
+                })?;
+
+            fs::remove_file(&from_path).await
+                .map_err(|e| ErrorInfo::new(6016, format!("移除原对象失败: {}", e))
+                    .with_category(ErrorCategory::FileSystem)
+                    .with_severity(ErrorSeverity::Error))?;
+        }
+
+        // 校验和随对象移动，原对象没有校验和时清除目标的旧校验和
+        let from_checksum = self.checksum_path(from_key);
+        let to_checksum = self.checksum_path(to_key);
+        if from_checksum.exists() {
+            if let Some(parent) = to_checksum.parent() {
+                let _ = fs::create_dir_all(parent).await;
+            }
+            let _ = fs::rename(&from_checksum, &to_checksum).await;
+        } else {
+            let _ = fs::remove_file(&to_checksum).await;
+        }
+
+        self.invalidate_cache(from_key);
+        self.invalidate_cache(to_key);
+        self.update_content_index(|index| {
+            let hash = index.by_object.get(from_key).cloned();
+            index.remove(from_key);
+            match hash {
+                Some(hash) => index.insert(to_key, hash),
+                None => index.remove(to_key),
+            }
+        });
+        self.update_tags_after(from_key, Some(to_key)).await;
+
+        let size = fs::metadata(&to_path).await.map(|metadata| metadata.len()).unwrap_or(0);
+        self.events.emit_rename(StorageKind::Object, from_key, to_key, size);
+        debug!("对象重命名成功: {} -> {}", from_key, to_key);
         Ok(())
     }
 
+    /// 获取对象大小
+    ///
+    /// # 参数
+    ///
+    /// * `object_id` - 对象唯一标识符
+    ///
+    /// # 返回值
+    ///
+    /// 返回对象的字节数，对象不存在时返回错误
+    pub async fn size(&self, object_id: &str) -> ObjectStorageResult<u64> {
+        fs::metadata(self.config.storage_root.join(object_id)).await
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .ok_or_else(|| ErrorInfo::new(6006, format!("对象不存在: {}", object_id))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Warning))
+    }
+
     /// 检查对象是否存在
     ///
     /// # 参数
@@ -190,12 +547,275 @@ impl ObjectStorage {
                 .with_severity(ErrorSeverity::Error))? {
             
             if let Ok(file_name) = entry.file_name().into_string() {
-                objects.push(file_name);
+                if !RESERVED_NAMES.contains(&file_name.as_str()) {
+                    objects.push(file_name);
+                }
+            }
+        }
+
+        Ok(objects)
+    }
+
+    /// 按记录的校验和验证对象内容
+    ///
+    /// 直接读取磁盘上的文件，不经过读缓存
+    ///
+    /// # 参数
+    ///
+    /// * `object_id` - 对象唯一标识符
+    ///
+    /// # 返回值
+    ///
+    /// 内容与校验和一致时返回 `Some(true)`，不一致时返回 `Some(false)`，
+    /// 对象没有记录校验和时返回 `None`
+    pub async fn verify(&self, object_id: &str) -> ObjectStorageResult<Option<bool>> {
+        let expected = match fs::read_to_string(self.checksum_path(object_id)).await {
+            Ok(checksum) => checksum,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(ErrorInfo::new(6008, format!("读取校验和失败: {}", e))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Error)),
+        };
+
+        let data = fs::read(self.config.storage_root.join(object_id)).await
+            .map_err(|e| ErrorInfo::new(6008, format!("读取文件失败: {}", e))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Error))?;
+        Ok(Some(format!("{:x}", Sha256::digest(&data)) == expected.trim()))
+    }
+
+    /// 查找内容与给定数据相同的对象
+    ///
+    /// 按内容哈希索引查找，索引在首次查找时从记录的校验和建立，
+    /// 没有校验和的对象读取内容计算哈希
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 待查找的数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回内容相同的对象ID，不存在时返回 `None`
+    pub async fn find_by_content(&self, data: &[u8]) -> ObjectStorageResult<Option<String>> {
+        let hash = format!("{:x}", Sha256::digest(data));
+        self.ensure_content_index().await?;
+
+        let candidates = self.content_index.lock()
+            .map(|index| index.as_ref().map(|index| index.find(&hash)).unwrap_or_default())
+            .unwrap_or_default();
+        for object_id in candidates {
+            if self.size(&object_id).await.is_ok_and(|size| size == data.len() as u64) {
+                return Ok(Some(object_id));
+            }
+            // 对象已在存储之外被移除
+            self.update_content_index(|index| index.remove(&object_id));
+        }
+
+        Ok(None)
+    }
+
+    /// 建立内容哈希索引（已建立时直接返回）
+    async fn ensure_content_index(&self) -> ObjectStorageResult<()> {
+        if self.content_index.lock().map(|index| index.is_some()).unwrap_or(false) {
+            return Ok(());
+        }
+
+        let mut index = ContentIndex::default();
+        for object_id in self.list().await? {
+            let path = self.config.storage_root.join(&object_id);
+            if !fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file()) {
+                continue;
+            }
+
+            let hash = match fs::read_to_string(self.checksum_path(&object_id)).await {
+                Ok(checksum) => checksum.trim().to_string(),
+                Err(_) => {
+                    let data = fs::read(&path).await
+                        .map_err(|e| ErrorInfo::new(6008, format!("读取文件失败: {}", e))
+                            .with_category(ErrorCategory::FileSystem)
+                            .with_severity(ErrorSeverity::Error))?;
+                    format!("{:x}", Sha256::digest(&data))
+                }
+            };
+            index.insert(&object_id, hash);
+        }
+
+        if let Ok(mut current) = self.content_index.lock() {
+            // 建立期间其他任务可能已经建好索引并开始增量维护
+            if current.is_none() {
+                *current = Some(index);
             }
         }
+        Ok(())
+    }
 
+    /// 设置对象的标签，替换原有标签
+    ///
+    /// 标签在对象被覆盖写入后保留，随对象重命名移动，对象删除时一并清除
+    ///
+    /// # 参数
+    ///
+    /// * `object_id` - 对象唯一标识符
+    /// * `tags` - 标签键值对，为空时清除对象的所有标签
+    ///
+    /// # 返回值
+    ///
+    /// 返回设置结果，对象不存在时返回错误
+    pub async fn set_tags(&self, object_id: &str, tags: HashMap<String, String>) -> ObjectStorageResult<()> {
+        check_object_id(object_id)?;
+        let _guard = self.tag_lock.lock().await;
+        if !self.config.storage_root.join(object_id).is_file() {
+            return Err(ErrorInfo::new(6018, format!("对象不存在: {}", object_id))
+                .with_category(ErrorCategory::FileSystem)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        // 只写入该对象的标签，不重写其他对象的标签
+        if tags.is_empty() {
+            self.tags.delete(object_id.as_bytes()).map_err(tag_index_error)?;
+        } else {
+            let value = serde_json::to_vec(&tags).map_err(tag_index_error)?;
+            self.tags.put(object_id.as_bytes(), value).map_err(tag_index_error)?;
+        }
+
+        debug!("对象标签已更新: {}", object_id);
+        Ok(())
+    }
+
+    /// 获取对象的标签
+    ///
+    /// # 参数
+    ///
+    /// * `object_id` - 对象唯一标识符
+    ///
+    /// # 返回值
+    ///
+    /// 返回对象的标签，对象没有标签时返回空集合
+    pub async fn get_tags(&self, object_id: &str) -> ObjectStorageResult<HashMap<String, String>> {
+        match self.tags.get(object_id.as_bytes()).map_err(tag_index_error)? {
+            Some(value) => decode_tags(&value),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    /// 所有对象的标签
+    ///
+    /// # 返回值
+    ///
+    /// 返回对象ID到标签的映射，只包含带有标签的对象
+    pub async fn all_tags(&self) -> ObjectStorageResult<HashMap<String, HashMap<String, String>>> {
+        self.tags.scan(&[]).map_err(tag_index_error)?
+            .into_iter()
+            .map(|(key, value)| Ok((String::from_utf8_lossy(&key).into_owned(), decode_tags(&value)?)))
+            .collect()
+    }
+
+    /// 查找带有指定标签的对象
+    ///
+    /// # 参数
+    ///
+    /// * `key` - 标签键
+    /// * `value` - 标签值
+    ///
+    /// # 返回值
+    ///
+    /// 返回按ID排序的对象ID列表
+    pub async fn find_by_tag(&self, key: &str, value: &str) -> ObjectStorageResult<Vec<String>> {
+        let mut objects: Vec<String> = self.all_tags().await?
+            .into_iter()
+            .filter(|(_, tags)| tags.get(key).is_some_and(|tag| tag == value))
+            .map(|(object_id, _)| object_id)
+            .collect();
+        objects.sort();
         Ok(objects)
     }
+
+    /// 移动或清除对象的标签
+    ///
+    /// # 参数
+    ///
+    /// * `from_key` - 原对象标识符
+    /// * `to_key` - 新对象标识符，为 `None` 时清除标签
+    async fn move_tags(&self, from_key: &str, to_key: Option<&str>) -> ObjectStorageResult<()> {
+        let _guard = self.tag_lock.lock().await;
+        let tags = self.tags.delete(from_key.as_bytes()).map_err(tag_index_error)?;
+        if let Some(to_key) = to_key {
+            // 目标原有的标签随被覆盖的对象一起失效
+            self.tags.delete(to_key.as_bytes()).map_err(tag_index_error)?;
+            if let Some(tags) = tags {
+                self.tags.put(to_key.as_bytes(), tags).map_err(tag_index_error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 文件操作完成后更新标签索引
+    ///
+    /// 对象已经删除或移动，索引更新失败不再作为操作失败返回，只记录日志
+    async fn update_tags_after(&self, from_key: &str, to_key: Option<&str>) {
+        if let Err(e) = self.move_tags(from_key, to_key).await {
+            warn!("更新对象 {} 的标签索引失败: {}", from_key, e);
+        }
+    }
+
+    /// 把旧版本保存在存储根目录下的标签索引文件导入标签数据库
+    ///
+    /// 导入完成后删除旧的索引文件及其遗留的临时文件
+    async fn migrate_legacy_tag_index(&self) -> ObjectStorageResult<()> {
+        let legacy_path = self.config.storage_root.join(LEGACY_TAG_INDEX_FILE);
+        let content = match fs::read(&legacy_path).await {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(tag_index_error(format!("读取旧标签索引失败: {}", e))),
+        };
+
+        if let Some(content) = content {
+            let index: HashMap<String, HashMap<String, String>> = serde_json::from_slice(&content)
+                .map_err(|e| tag_index_error(format!("解析旧标签索引失败: {}", e)))?;
+            for (object_id, tags) in &index {
+                let value = serde_json::to_vec(tags).map_err(tag_index_error)?;
+                self.tags.put(object_id.as_bytes(), value).map_err(tag_index_error)?;
+            }
+            self.tags.flush().map_err(tag_index_error)?;
+            let _ = fs::remove_file(&legacy_path).await;
+            info!("已导入旧标签索引: {} 个对象", index.len());
+        }
+        let _ = fs::remove_file(self.config.storage_root.join(LEGACY_TAG_INDEX_TEMP_FILE)).await;
+        Ok(())
+    }
+
+    /// 将标签索引的缓冲写入刷到磁盘
+    pub async fn flush(&self) -> ObjectStorageResult<()> {
+        self.tags.flush().map_err(tag_index_error)
+    }
+}
+
+/// 检查对象ID是否可用
+///
+/// 第一级路径为存储内部使用的保留名称时返回错误
+fn check_object_id(object_id: &str) -> ObjectStorageResult<()> {
+    let first = object_id.split(['/', '\\']).next().unwrap_or_default();
+    if RESERVED_NAMES.contains(&first) {
+        return Err(ErrorInfo::new(6021, format!("对象ID使用了保留名称: {}", object_id))
+            .with_category(ErrorCategory::Validation)
+            .with_severity(ErrorSeverity::Warning));
+    }
+    Ok(())
+}
+
+/// 解码标签数据库中保存的标签
+fn decode_tags(value: &[u8]) -> ObjectStorageResult<HashMap<String, String>> {
+    serde_json::from_slice(value)
+        .map_err(|e| ErrorInfo::new(6019, format!("解析对象标签失败: {}", e))
+            .with_category(ErrorCategory::Parse)
+            .with_severity(ErrorSeverity::Error))
+}
+
+/// 标签索引读写错误
+fn tag_index_error(error: impl std::fmt::Display) -> ErrorInfo {
+    ErrorInfo::new(6020, format!("访问标签索引失败: {}", error))
+        .with_category(ErrorCategory::Database)
+        .with_severity(ErrorSeverity::Error)
 }
 
 #[cfg(test)]
@@ -209,6 +829,7 @@ mod tests {
         let config = ObjectStorageConfig {
             storage_root: temp_dir.path().to_path_buf(),
             enable_checksum: true,
+            ..Default::default()
         };
 
         let storage = ObjectStorage::new(config).await.expect("创建存储失败");
@@ -235,6 +856,7 @@ mod tests {
         let config = ObjectStorageConfig {
             storage_root: temp_dir.path().to_path_buf(),
             enable_checksum: true,
+            ..Default::default()
         };
 
         let storage = ObjectStorage::new(config).await.expect("创建存储失败");
@@ -251,4 +873,164 @@ mod tests {
         assert!(objects.contains(&"obj2".to_string()));
         assert!(objects.contains(&"obj3".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_object_storage_rename() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = ObjectStorageConfig {
+            storage_root: temp_dir.path().to_path_buf(),
+            enable_checksum: true,
+            ..Default::default()
+        };
+
+        let storage = ObjectStorage::new(config).await.expect("创建存储失败");
+        storage.store("old_name", b"payload").await.expect("存储失败");
+
+        storage.rename("old_name", "new_name", false).await.expect("重命名失败");
+
+        assert!(!storage.exists("old_name").await);
+        let data = storage.retrieve("new_name").await.expect("检索失败");
+        assert_eq!(data, b"payload");
+    }
+
+    #[tokio::test]
+    async fn test_object_storage_rename_conflict() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = ObjectStorageConfig {
+            storage_root: temp_dir.path().to_path_buf(),
+            enable_checksum: true,
+            ..Default::default()
+        };
+
+        let storage = ObjectStorage::new(config).await.expect("创建存储失败");
+        storage.store("source", b"source data").await.expect("存储失败");
+        storage.store("target", b"target data").await.expect("存储失败");
+
+        let result = storage.rename("source", "target", false).await;
+        assert_eq!(result.expect_err("目标已存在时应该失败").code(), 6015);
+
+        // 两个对象都保持不变
+        assert_eq!(storage.retrieve("source").await.expect("检索失败"), b"source data");
+        assert_eq!(storage.retrieve("target").await.expect("检索失败"), b"target data");
+    }
+
+    #[tokio::test]
+    async fn test_object_storage_rename_overwrite() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = ObjectStorageConfig {
+            storage_root: temp_dir.path().to_path_buf(),
+            enable_checksum: true,
+            ..Default::default()
+        };
+
+        let storage = ObjectStorage::new(config).await.expect("创建存储失败");
+        storage.store("source", b"source data").await.expect("存储失败");
+        storage.store("target", b"target data").await.expect("存储失败");
+
+        storage.rename("source", "target", true).await.expect("覆盖重命名失败");
+
+        assert!(!storage.exists("source").await);
+        assert_eq!(storage.retrieve("target").await.expect("检索失败"), b"source data");
+    }
+
+    #[tokio::test]
+    async fn test_object_storage_read_cache() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = ObjectStorageConfig {
+            storage_root: temp_dir.path().to_path_buf(),
+            read_cache_bytes: Some(1024),
+            ..Default::default()
+        };
+
+        let storage = ObjectStorage::new(config).await.expect("创建存储失败");
+        storage.store("hot", b"version 1").await.expect("存储失败");
+
+        // 第一次读取未命中，第二次从缓存返回
+        assert_eq!(storage.retrieve("hot").await.expect("检索失败"), b"version 1");
+        assert_eq!(storage.retrieve("hot").await.expect("检索失败"), b"version 1");
+        let stats = storage.cache_stats().expect("应启用读缓存");
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        // 写入使缓存失效，之后读取到新内容
+        storage.store("hot", b"version 2").await.expect("存储失败");
+        assert_eq!(storage.cache_stats().expect("应启用读缓存").entries, 0);
+        assert_eq!(storage.retrieve("hot").await.expect("检索失败"), b"version 2");
+        let stats = storage.cache_stats().expect("应启用读缓存");
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+
+        // 删除同样使缓存失效
+        storage.delete("hot").await.expect("删除失败");
+        assert!(storage.retrieve("hot").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_object_storage_tags() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let config = ObjectStorageConfig {
+            storage_root: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let storage = ObjectStorage::new(config).await.expect("创建存储失败");
+        storage.store("report", b"report data").await.expect("存储失败");
+        storage.store("photo", b"photo data").await.expect("存储失败");
+        storage.store("notes", b"notes data").await.expect("存储失败");
+
+        let tags = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        storage.set_tags("report", tags(&[("owner", "alice"), ("kind", "doc")])).await.expect("设置标签失败");
+        storage.set_tags("photo", tags(&[("owner", "alice"), ("kind", "image")])).await.expect("设置标签失败");
+        storage.set_tags("notes", tags(&[("owner", "bob")])).await.expect("设置标签失败");
+        let result = storage.set_tags("missing", tags(&[("owner", "alice")])).await;
+        assert_eq!(result.expect_err("对象不存在时应该失败").code(), 6018);
+
+        assert_eq!(storage.get_tags("report").await.expect("获取标签失败"), tags(&[("owner", "alice"), ("kind", "doc")]));
+        assert_eq!(storage.find_by_tag("owner", "alice").await.expect("查询标签失败"), vec!["photo", "report"]);
+        assert_eq!(storage.find_by_tag("kind", "image").await.expect("查询标签失败"), vec!["photo"]);
+        assert!(storage.find_by_tag("owner", "carol").await.expect("查询标签失败").is_empty());
+
+        // 覆盖写入后标签保留，索引文件不出现在对象列表中
+        storage.store("report", b"report v2").await.expect("存储失败");
+        assert_eq!(storage.get_tags("report").await.expect("获取标签失败").get("kind"), Some(&"doc".to_string()));
+        assert_eq!(storage.list().await.expect("列出失败").len(), 3);
+
+        // 删除对象时清除其标签
+        storage.delete("photo").await.expect("删除失败");
+        assert!(storage.get_tags("photo").await.expect("获取标签失败").is_empty());
+        assert_eq!(storage.find_by_tag("owner", "alice").await.expect("查询标签失败"), vec!["report"]);
+
+        // 标签随对象重命名移动
+        storage.rename("notes", "archived_notes", false).await.expect("重命名失败");
+        assert_eq!(storage.find_by_tag("owner", "bob").await.expect("查询标签失败"), vec!["archived_notes"]);
+
+        // 存储内部使用的名称不能用作对象ID
+        for reserved in [".tags.db", ".tags.json", ".tags.tmp", ".checksums/report.sha256"] {
+            assert_eq!(storage.store(reserved, b"x").await.expect_err("保留名称应被拒绝").code(), 6021);
+        }
+        assert_eq!(storage.rename("report", ".tags.json", true).await.expect_err("保留名称应被拒绝").code(), 6021);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_tag_index_is_migrated() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        std::fs::write(temp_dir.path().join("report"), b"report data").expect("写入对象失败");
+        std::fs::write(temp_dir.path().join(".tags.json"), br#"{"report":{"owner":"alice"}}"#).expect("写入旧索引失败");
+        std::fs::write(temp_dir.path().join(".tags.tmp"), b"{").expect("写入临时文件失败");
+
+        let config = ObjectStorageConfig {
+            storage_root: temp_dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let storage = ObjectStorage::new(config.clone()).await.expect("创建存储失败");
+        assert_eq!(storage.find_by_tag("owner", "alice").await.expect("查询标签失败"), vec!["report"]);
+        assert_eq!(storage.list().await.expect("列出失败"), vec!["report"]);
+        assert!(!temp_dir.path().join(".tags.json").exists());
+        assert!(!temp_dir.path().join(".tags.tmp").exists());
+        drop(storage);
+
+        // 重新打开后标签仍然存在
+        let storage = ObjectStorage::new(config).await.expect("重新打开存储失败");
+        assert_eq!(storage.get_tags("report").await.expect("获取标签失败").get("owner"), Some(&"alice".to_string()));
+    }
 }
@@ -0,0 +1,174 @@
+//! # 读缓存模块
+//!
+//! 为对象存储和云存储提供按字节数限制容量的LRU读缓存，
+//! 热点对象的重复读取直接从内存返回，写入或删除对应键时失效。
+
+use lru::LruCache;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 读缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// 命中次数
+    pub hits: u64,
+    /// 未命中次数
+    pub misses: u64,
+    /// 当前缓存的条目数
+    pub entries: usize,
+    /// 当前缓存的字节数
+    pub bytes: u64,
+}
+
+/// 缓存内容
+struct CacheState {
+    /// LRU顺序的缓存条目
+    entries: LruCache<String, Arc<Vec<u8>>>,
+    /// 已缓存的字节数
+    bytes: u64,
+    /// 失效代数，每次失效时递增
+    generation: u64,
+}
+
+/// 按字节数限制容量的LRU读缓存
+pub(crate) struct ReadCache {
+    /// 缓存内容
+    state: Mutex<CacheState>,
+    /// 最大缓存字节数
+    max_bytes: u64,
+    /// 命中次数
+    hits: AtomicU64,
+    /// 未命中次数
+    misses: AtomicU64,
+}
+
+impl ReadCache {
+    /// 创建读缓存
+    ///
+    /// # 参数
+    ///
+    /// * `max_bytes` - 最大缓存字节数，超过时淘汰最久未使用的条目
+    pub(crate) fn new(max_bytes: u64) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: LruCache::unbounded(),
+                bytes: 0,
+                generation: 0,
+            }),
+            max_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 锁定缓存内容
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// 查询缓存，同时记录命中或未命中
+    pub(crate) fn get(&self, key: &str) -> Option<Arc<Vec<u8>>> {
+        let cached = self.lock().entries.get(key).cloned();
+        match cached {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        cached
+    }
+
+    /// 当前失效代数，读取底层存储前获取，缓存结果时传回
+    pub(crate) fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// 缓存读取到的数据
+    ///
+    /// 超过容量上限的对象不缓存；读取期间发生过失效时也不缓存，
+    /// 避免与并发写入交错时缓存旧数据
+    ///
+    /// # 参数
+    ///
+    /// * `key` - 缓存键
+    /// * `data` - 读取到的数据
+    /// * `generation` - 读取底层存储前获取的失效代数
+    pub(crate) fn insert(&self, key: &str, data: Arc<Vec<u8>>, generation: u64) {
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return;
+        }
+
+        let mut state = self.lock();
+        if state.generation != generation {
+            return;
+        }
+        if let Some(previous) = state.entries.put(key.to_string(), data) {
+            state.bytes -= previous.len() as u64;
+        }
+        state.bytes += size;
+
+        while state.bytes > self.max_bytes {
+            match state.entries.pop_lru() {
+                Some((_, evicted)) => state.bytes -= evicted.len() as u64,
+                None => break,
+            }
+        }
+    }
+
+    /// 使键对应的缓存失效
+    pub(crate) fn invalidate(&self, key: &str) {
+        let mut state = self.lock();
+        state.generation += 1;
+        if let Some(removed) = state.entries.pop(key) {
+            state.bytes -= removed.len() as u64;
+        }
+    }
+
+    /// 获取缓存统计
+    pub(crate) fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: state.entries.len(),
+            bytes: state.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used_by_bytes() {
+        let cache = ReadCache::new(10);
+        cache.insert("a", Arc::new(vec![0; 4]), 0);
+        cache.insert("b", Arc::new(vec![0; 4]), 0);
+        assert!(cache.get("a").is_some());
+
+        // 超出容量时淘汰最久未使用的 b
+        cache.insert("c", Arc::new(vec![0; 4]), 0);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+
+        // 超过容量上限的对象不缓存
+        cache.insert("huge", Arc::new(vec![0; 11]), 0);
+        assert!(cache.get("huge").is_none());
+
+        // 读取期间发生失效时不缓存旧数据
+        let generation = cache.generation();
+        cache.invalidate("d");
+        cache.insert("d", Arc::new(vec![0; 1]), generation);
+        assert!(cache.get("d").is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.bytes, 8);
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 3);
+    }
+}
@@ -0,0 +1,172 @@
+//! # 存储快照模块
+//!
+//! 为复制到备份设备生成可验证的存储快照。快照清单列出对象存储和云存储中的
+//! 所有对象及其SHA-256哈希，并由设备私钥签名；接收方先验证签名，
+//! 再逐个拉取对象并核对哈希，任何篡改都会被拒绝。
+
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path};
+
+use crate::StorageResult;
+
+/// 快照对象类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SnapshotObjectKind {
+    /// 对象存储中的对象，键为对象ID
+    Object,
+    /// 云存储中的文件，键为文件名
+    CloudFile,
+}
+
+/// 快照条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// 对象类型
+    pub kind: SnapshotObjectKind,
+    /// 对象ID或文件名
+    pub key: String,
+    /// 内容的SHA-256哈希（十六进制）
+    pub hash: String,
+    /// 内容大小（字节）
+    pub size: u64,
+}
+
+/// 快照签名身份
+#[derive(Debug, Clone)]
+pub struct SnapshotSigner {
+    /// 签名方证书（PEM格式），随清单一起发送
+    pub certificate_pem: String,
+    /// 签名方私钥（PEM格式）
+    pub private_key_pem: String,
+}
+
+impl SnapshotSigner {
+    /// 创建快照签名身份
+    ///
+    /// # 参数
+    ///
+    /// * `certificate_pem` - 设备证书（PEM格式）
+    /// * `private_key_pem` - 设备私钥（PEM格式）
+    pub fn new(certificate_pem: impl Into<String>, private_key_pem: impl Into<String>) -> Self {
+        Self {
+            certificate_pem: certificate_pem.into(),
+            private_key_pem: private_key_pem.into(),
+        }
+    }
+}
+
+/// 已签名的快照清单
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// 生成快照的设备ID
+    pub device_id: String,
+    /// 生成时间（Unix时间戳，秒）
+    pub created_at: u64,
+    /// 按类型和键排序的快照条目
+    pub entries: Vec<SnapshotEntry>,
+    /// 签名方证书（PEM格式）
+    pub signer_certificate: String,
+    /// 对清单内容的签名
+    pub signature: Vec<u8>,
+}
+
+/// 参与签名的清单内容
+#[derive(Serialize)]
+struct SignedContent<'a> {
+    device_id: &'a str,
+    created_at: u64,
+    entries: &'a [SnapshotEntry],
+}
+
+impl SnapshotManifest {
+    /// 对快照条目签名生成清单
+    pub(crate) fn sign(
+        device_id: String,
+        created_at: u64,
+        mut entries: Vec<SnapshotEntry>,
+        signer: &SnapshotSigner,
+    ) -> StorageResult<Self> {
+        entries.sort_by(|a, b| (a.kind, &a.key).cmp(&(b.kind, &b.key)));
+
+        let payload = signed_payload(&device_id, created_at, &entries)?;
+        let signature = bey_identity::sign_data(&signer.private_key_pem, &payload)
+            .map_err(|e| ErrorInfo::new(6502, format!("快照签名失败: {}", e))
+                .with_category(ErrorCategory::Encryption)
+                .with_severity(ErrorSeverity::Error))?;
+
+        Ok(Self {
+            device_id,
+            created_at,
+            entries,
+            signer_certificate: signer.certificate_pem.clone(),
+            signature,
+        })
+    }
+
+    /// 验证清单签名
+    ///
+    /// 只证明清单自签名后未被修改；调用方应另行确认 `signer_certificate` 属于可信设备。
+    ///
+    /// # 返回值
+    ///
+    /// 签名有效返回 `Ok(())`，否则返回错误
+    pub fn verify(&self) -> StorageResult<()> {
+        let payload = signed_payload(&self.device_id, self.created_at, &self.entries)?;
+        let valid = bey_identity::verify_signature(&self.signer_certificate, &payload, &self.signature)
+            .map_err(|e| ErrorInfo::new(6503, format!("快照签名方证书无效: {}", e))
+                .with_category(ErrorCategory::Authentication)
+                .with_severity(ErrorSeverity::Error))?;
+
+        if !valid {
+            return Err(ErrorInfo::new(6503, "快照清单签名验证失败".to_string())
+                .with_category(ErrorCategory::Authentication)
+                .with_severity(ErrorSeverity::Error));
+        }
+        Ok(())
+    }
+
+    /// 快照中所有对象的原始大小总和
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+}
+
+/// 序列化参与签名的清单内容
+fn signed_payload(device_id: &str, created_at: u64, entries: &[SnapshotEntry]) -> StorageResult<Vec<u8>> {
+    serde_json::to_vec(&SignedContent { device_id, created_at, entries })
+        .map_err(|e| ErrorInfo::new(6501, format!("序列化快照清单失败: {}", e))
+            .with_category(ErrorCategory::Parse)
+            .with_severity(ErrorSeverity::Error))
+}
+
+/// 计算内容的SHA-256哈希（十六进制）
+pub(crate) fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// 校验拉取到的对象内容与清单条目一致
+pub(crate) fn verify_entry_content(entry: &SnapshotEntry, data: &[u8]) -> StorageResult<()> {
+    if data.len() as u64 != entry.size || content_hash(data) != entry.hash {
+        return Err(ErrorInfo::new(6504, format!("快照对象内容与清单不符: {}", entry.key))
+            .with_category(ErrorCategory::Validation)
+            .with_severity(ErrorSeverity::Error));
+    }
+    Ok(())
+}
+
+/// 键是否为不会逃逸出存储目录的相对路径
+pub(crate) fn is_plain_key(key: &str) -> bool {
+    !key.is_empty() && Path::new(key).components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// 校验条目键不会逃逸出存储目录
+pub(crate) fn validate_entry_key(entry: &SnapshotEntry) -> StorageResult<()> {
+    if !is_plain_key(&entry.key) {
+        return Err(ErrorInfo::new(6505, format!("快照条目键无效: {}", entry.key))
+            .with_category(ErrorCategory::Validation)
+            .with_severity(ErrorSeverity::Error));
+    }
+    Ok(())
+}
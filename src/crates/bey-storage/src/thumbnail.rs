@@ -0,0 +1,87 @@
+//! # 缩略图模块
+//!
+//! 为图片剪切板条目生成小尺寸预览：解码原图，按最大边长等比缩小后重新编码为PNG。
+//! 图片编解码依赖 `thumbnails` 特性，未启用时不生成缩略图。
+
+/// 缩略图默认的最大边长（像素）
+pub const DEFAULT_THUMBNAIL_MAX_DIMENSION: u32 = 128;
+
+/// 判断内容类型是否为图片
+///
+/// # 参数
+///
+/// * `content_type` - 内容类型，如 `image` 或 `image/png`
+///
+/// # 返回值
+///
+/// 内容类型为 `image` 或以 `image/` 开头时返回 `true`
+pub fn is_image_content_type(content_type: &str) -> bool {
+    content_type == "image" || content_type.starts_with("image/")
+}
+
+/// 生成图片缩略图
+///
+/// 原图的宽高都不超过最大边长时不缩放，只重新编码
+///
+/// # 参数
+///
+/// * `content` - 原图数据（PNG、JPEG）
+/// * `max_dimension` - 缩略图的最大边长（至少为1）
+///
+/// # 返回值
+///
+/// 返回PNG格式的缩略图，数据无法解码或未启用 `thumbnails` 特性时返回 `None`
+#[cfg(feature = "thumbnails")]
+pub fn generate_thumbnail(content: &[u8], max_dimension: u32) -> Option<Vec<u8>> {
+    let max_dimension = max_dimension.max(1);
+    let image = match image::load_from_memory(content) {
+        Ok(image) => image,
+        Err(e) => {
+            tracing::debug!("解码图片失败，不生成缩略图: {}", e);
+            return None;
+        }
+    };
+
+    let thumbnail = if image.width() > max_dimension || image.height() > max_dimension {
+        image.thumbnail(max_dimension, max_dimension)
+    } else {
+        image
+    };
+
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    match thumbnail.write_to(&mut encoded, image::ImageFormat::Png) {
+        Ok(()) => Some(encoded.into_inner()),
+        Err(e) => {
+            tracing::debug!("编码缩略图失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 生成图片缩略图（未启用 `thumbnails` 特性，始终返回 `None`）
+#[cfg(not(feature = "thumbnails"))]
+pub fn generate_thumbnail(_content: &[u8], _max_dimension: u32) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(all(test, feature = "thumbnails"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_thumbnail_keeps_aspect_ratio() {
+        let image = image::RgbImage::from_fn(64, 32, |x, y| image::Rgb([x as u8, y as u8, 0]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .expect("编码PNG失败");
+
+        let thumbnail = generate_thumbnail(png.get_ref(), 16).expect("生成缩略图失败");
+        let decoded = image::load_from_memory(&thumbnail).expect("解码缩略图失败");
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+
+        assert!(generate_thumbnail(b"not an image", 16).is_none());
+        assert!(is_image_content_type("image/png"));
+        assert!(!is_image_content_type("text"));
+    }
+}
@@ -0,0 +1,110 @@
+//! # 预写日志
+//!
+//! 记录在写入存储之前先追加到预写日志并同步到磁盘，写入存储并刷盘后截断日志。
+//! 进程在两步之间异常退出时，重新打开时可以读出未截断的记录并重放。
+//! 每条记录为一行JSON；写入中途崩溃留下的不完整末行会被忽略。
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// 预写日志
+pub(crate) struct WriteAheadLog {
+    /// 日志文件路径
+    path: PathBuf,
+    /// 以追加模式打开的日志文件
+    file: File,
+}
+
+impl WriteAheadLog {
+    /// 打开预写日志，文件不存在时创建
+    ///
+    /// # 参数
+    ///
+    /// * `path` - 日志文件路径
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).read(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// 追加一条记录并同步到磁盘
+    ///
+    /// # 参数
+    ///
+    /// * `record` - 要追加的记录
+    pub(crate) fn append<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.sync_data()
+    }
+
+    /// 读出日志中所有完整的记录
+    ///
+    /// # 返回值
+    ///
+    /// 按追加顺序返回记录，遇到不完整或无法解析的记录时停止读取
+    pub(crate) fn records<T: DeserializeOwned>(&self) -> io::Result<Vec<T>> {
+        let mut data = Vec::new();
+        File::open(&self.path)?.read_to_end(&mut data)?;
+
+        let mut records = Vec::new();
+        let mut lines = data.split_inclusive(|&byte| byte == b'\n');
+        for line in lines.by_ref() {
+            let Some(line) = line.strip_suffix(b"\n") else {
+                warn!("预写日志 {} 末尾存在不完整的记录，已忽略", self.path.display());
+                break;
+            };
+            match serde_json::from_slice(line) {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    warn!("预写日志 {} 中的记录无法解析，忽略之后的记录: {}", self.path.display(), e);
+                    break;
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// 截断日志，丢弃所有已确认应用的记录
+    pub(crate) fn truncate(&mut self) -> io::Result<()> {
+        self.file.set_len(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_append_read_and_truncate() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let path = temp_dir.path().join("test.wal");
+
+        let mut wal = WriteAheadLog::open(&path).expect("打开预写日志失败");
+        wal.append(&"first".to_string()).expect("追加失败");
+        wal.append(&"second".to_string()).expect("追加失败");
+
+        // 模拟写入中途崩溃留下的不完整记录
+        std::fs::OpenOptions::new().append(true).open(&path)
+            .and_then(|mut file| file.write_all(b"\"thi"))
+            .expect("写入不完整记录失败");
+
+        let wal = WriteAheadLog::open(&path).expect("重新打开预写日志失败");
+        let records: Vec<String> = wal.records().expect("读取记录失败");
+        assert_eq!(records, vec!["first".to_string(), "second".to_string()]);
+
+        let mut wal = wal;
+        wal.truncate().expect("截断失败");
+        wal.append(&"third".to_string()).expect("追加失败");
+        let records: Vec<String> = wal.records().expect("读取记录失败");
+        assert_eq!(records, vec!["third".to_string()]);
+    }
+}
@@ -0,0 +1,231 @@
+//! # 证书状态查询通道
+//!
+//! 在已建立的QUIC连接上用数据报收发证书状态查询，不占用应用的流。
+//! 每个数据报以一个字节的类型开头，后接JSON编码的请求或响应；
+//! 每条连接由一个应答任务读取数据报：请求交给本地证书管理器签名作答，
+//! 响应按随机数交给等待中的查询。数据报可能丢失，查询在超时前会重发。
+
+use async_trait::async_trait;
+use bey_identity::{CertStatusRequest, CertStatusResponse, CertStatusTransport, CertificateManager, IdentityResult};
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use quinn::Connection;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, RwLock};
+use tracing::{debug, warn};
+
+use crate::error_codes::transport::CERT_STATUS_QUERY_FAILED;
+use crate::{SecureTransport, TransportResult};
+
+/// 状态查询请求数据报
+const CERT_STATUS_REQUEST: u8 = 1;
+/// 状态查询响应数据报
+const CERT_STATUS_RESPONSE: u8 = 2;
+
+/// 单次查询的默认超时时间
+pub const DEFAULT_CERT_STATUS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 超时前发送请求的次数
+const CERT_STATUS_ATTEMPTS: u32 = 3;
+
+/// 等待响应的查询（对端地址, 随机数）-> 响应接收端
+#[derive(Default)]
+pub(crate) struct PendingStatusQueries {
+    waiters: Mutex<HashMap<(SocketAddr, u64), oneshot::Sender<CertStatusResponse>>>,
+}
+
+impl PendingStatusQueries {
+    /// 登记等待响应的查询
+    fn register(&self, remote_addr: SocketAddr, nonce: u64) -> oneshot::Receiver<CertStatusResponse> {
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut waiters) = self.waiters.lock() {
+            waiters.insert((remote_addr, nonce), sender);
+        }
+        receiver
+    }
+
+    /// 移除查询，超时或失败后调用
+    fn cancel(&self, remote_addr: SocketAddr, nonce: u64) {
+        if let Ok(mut waiters) = self.waiters.lock() {
+            waiters.remove(&(remote_addr, nonce));
+        }
+    }
+
+    /// 把响应交给对应的查询，没有等待者的响应被丢弃
+    fn complete(&self, remote_addr: SocketAddr, response: CertStatusResponse) {
+        let waiter = self.waiters.lock().ok()
+            .and_then(|mut waiters| waiters.remove(&(remote_addr, response.nonce)));
+        match waiter {
+            Some(waiter) => {
+                let _ = waiter.send(response);
+            }
+            None => debug!("丢弃 {} 发来的未知状态查询响应", remote_addr),
+        }
+    }
+}
+
+/// 为连接启动状态查询应答任务，连接关闭后任务退出
+///
+/// # 参数
+///
+/// * `connection` - 已完成握手的连接
+/// * `certificate_manager` - 回答查询的本地证书管理器
+/// * `pending` - 本端等待响应的查询
+pub(crate) fn spawn_status_responder(
+    connection: Connection,
+    certificate_manager: Arc<CertificateManager>,
+    pending: Arc<PendingStatusQueries>,
+) {
+    tokio::spawn(async move {
+        let remote_addr = connection.remote_address();
+        while let Ok(datagram) = connection.read_datagram().await {
+            let Some((&kind, body)) = datagram.split_first() else {
+                continue;
+            };
+            match kind {
+                CERT_STATUS_REQUEST => {
+                    let request: CertStatusRequest = match serde_json::from_slice(body) {
+                        Ok(request) => request,
+                        Err(e) => {
+                            warn!("丢弃 {} 发来的无效状态查询: {}", remote_addr, e);
+                            continue;
+                        }
+                    };
+                    let response = match certificate_manager.answer_status_query(&request).await {
+                        Ok(response) => response,
+                        Err(e) => {
+                            warn!("回答 {} 的状态查询失败: {}", remote_addr, e);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = send_datagram(&connection, CERT_STATUS_RESPONSE, &response) {
+                        warn!("向 {} 发送状态查询响应失败: {}", remote_addr, e);
+                    }
+                }
+                CERT_STATUS_RESPONSE => match serde_json::from_slice(body) {
+                    Ok(response) => pending.complete(remote_addr, response),
+                    Err(e) => warn!("丢弃 {} 发来的无效状态查询响应: {}", remote_addr, e),
+                },
+                other => debug!("忽略 {} 发来的未知数据报类型: {}", remote_addr, other),
+            }
+        }
+        debug!("停止应答 {} 的状态查询", remote_addr);
+    });
+}
+
+/// 在连接上查询证书状态
+///
+/// # 参数
+///
+/// * `connection` - 到签发节点的连接
+/// * `pending` - 本端等待响应的查询
+/// * `request` - 查询请求
+/// * `timeout` - 等待响应的总时间
+///
+/// # 返回值
+///
+/// 返回对端的响应，未签名校验；超时或连接不支持数据报时返回错误
+pub(crate) async fn query_status(
+    connection: &Connection,
+    pending: &PendingStatusQueries,
+    request: &CertStatusRequest,
+    timeout: Duration,
+) -> TransportResult<CertStatusResponse> {
+    let remote_addr = connection.remote_address();
+    let mut receiver = pending.register(remote_addr, request.nonce);
+    let attempt_timeout = timeout / CERT_STATUS_ATTEMPTS;
+
+    for attempt in 1..=CERT_STATUS_ATTEMPTS {
+        if let Err(e) = send_datagram(connection, CERT_STATUS_REQUEST, request) {
+            pending.cancel(remote_addr, request.nonce);
+            return Err(e);
+        }
+        match tokio::time::timeout(attempt_timeout, &mut receiver).await {
+            Ok(Ok(response)) => return Ok(response),
+            Ok(Err(_)) => break,
+            Err(_) => debug!("第 {} 次状态查询 {} 超时", attempt, remote_addr),
+        }
+    }
+
+    pending.cancel(remote_addr, request.nonce);
+    Err(status_error(format!("等待 {} 的证书状态响应超时", remote_addr)))
+}
+
+/// 发送带类型字节的数据报
+fn send_datagram<T: serde::Serialize>(connection: &Connection, kind: u8, body: &T) -> TransportResult<()> {
+    let mut data = vec![kind];
+    serde_json::to_writer(&mut data, body)
+        .map_err(|e| status_error(format!("序列化状态查询失败: {}", e)))?;
+
+    match connection.max_datagram_size() {
+        Some(max) if data.len() <= max => {}
+        Some(max) => return Err(status_error(format!("状态查询数据报过长: {} > {} 字节", data.len(), max))),
+        None => return Err(status_error(format!("{} 不支持数据报", connection.remote_address()))),
+    }
+
+    connection.send_datagram(data.into())
+        .map_err(|e| status_error(format!("发送状态查询数据报失败: {}", e)))
+}
+
+/// 证书状态查询错误
+fn status_error(message: String) -> ErrorInfo {
+    ErrorInfo::new(CERT_STATUS_QUERY_FAILED, message)
+        .with_category(ErrorCategory::Network)
+        .with_severity(ErrorSeverity::Warning)
+}
+
+/// 基于安全传输层的证书状态查询通道
+///
+/// 通过 [`CertificateManager::set_status_transport`] 安装后，
+/// 查询经由到签发设备的连接发送；没有连接时按登记的地址建立连接。
+pub struct TransportCertStatus {
+    /// 传输层
+    transport: Arc<SecureTransport>,
+    /// 设备ID -> 地址列表
+    peers: RwLock<HashMap<String, Vec<SocketAddr>>>,
+    /// 单次查询超时时间
+    timeout: Duration,
+}
+
+impl TransportCertStatus {
+    /// 创建查询通道
+    ///
+    /// # 参数
+    ///
+    /// * `transport` - 已启动的传输层
+    pub fn new(transport: Arc<SecureTransport>) -> Self {
+        Self {
+            transport,
+            peers: RwLock::new(HashMap::new()),
+            timeout: DEFAULT_CERT_STATUS_TIMEOUT,
+        }
+    }
+
+    /// 设置单次查询超时时间
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 登记签发设备的地址
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 设备ID
+    /// * `addrs` - 设备的地址列表
+    pub async fn set_peer_addrs(&self, peer_id: &str, addrs: Vec<SocketAddr>) {
+        self.peers.write().await.insert(peer_id.to_string(), addrs);
+    }
+}
+
+#[async_trait]
+impl CertStatusTransport for TransportCertStatus {
+    async fn request_status(&self, peer_id: &str, request: &CertStatusRequest) -> IdentityResult<CertStatusResponse> {
+        let addrs = self.peers.read().await.get(peer_id).cloned()
+            .ok_or_else(|| status_error(format!("未登记设备 {} 的地址", peer_id)))?;
+        let connection = self.transport.connect_peer(peer_id, &addrs).await?;
+        self.transport.request_cert_status(&connection, request, self.timeout).await
+    }
+}
@@ -18,6 +18,8 @@ pub mod mtls {
     pub const CERT_VERIFICATION_FAILED: u32 = 5006;
     /// 证书吊销失败
     pub const CERT_REVOCATION_FAILED: u32 = 5007;
+    /// 交叉签名其他设备CA失败
+    pub const CROSS_SIGN_FAILED: u32 = 5020;
 }
 
 /// 策略引擎错误代码
@@ -56,4 +58,30 @@ pub mod transport {
     pub const SEND_MESSAGE_FAILED: u32 = 2004;
     /// 接收消息失败
     pub const RECEIVE_MESSAGE_FAILED: u32 = 2005;
+    /// 监听地址不是本机地址
+    pub const INVALID_BIND_ADDRESS: u32 = 2023;
+    /// 对端发送队列已满
+    pub const SEND_QUEUE_FULL: u32 = 2024;
+    /// 对端发送队列已关闭
+    pub const SEND_QUEUE_CLOSED: u32 = 2025;
+    /// ALPN协议不匹配
+    pub const ALPN_MISMATCH: u32 = 2026;
+    /// 打开双向流失败
+    pub const STREAM_OPEN_FAILED: u32 = 2027;
+    /// 接受双向流失败
+    pub const STREAM_ACCEPT_FAILED: u32 = 2028;
+    /// 双向流被策略拒绝
+    pub const STREAM_POLICY_DENIED: u32 = 2029;
+    /// UDP不可达（无法创建UDP端点，或QUIC握手超时未收到响应）
+    pub const UDP_UNREACHABLE: u32 = 2030;
+    /// 写入流失败
+    pub const STREAM_WRITE_FAILED: u32 = 2031;
+    /// 证书存储中没有设备证书
+    pub const CERTIFICATES_NOT_INITIALIZED: u32 = 2032;
+    /// 证书状态查询失败（对端不可达、不支持数据报或响应超时）
+    pub const CERT_STATUS_QUERY_FAILED: u32 = 2033;
+    /// 签发设备证书失败
+    pub const CERT_PROVISION_FAILED: u32 = 2034;
+    /// TCP回退连接的TLS握手失败
+    pub const TCP_TLS_HANDSHAKE_FAILED: u32 = 2035;
 }
@@ -7,43 +7,55 @@
 //!
 //! - **QUIC 协议**: 基于 UDP 的高性能传输协议
 //! - **TLS 1.3 加密**: 最新的传输层安全协议
-//! - **双向认证**: 客户端和服务端的相互身份验证
+//! - **双向认证**: 客户端和服务端的相互身份验证，可按监听器允许匿名客户端
 //! - **证书管理**: 完全依赖 bey_identity 模块进行证书管理
 //! - **连接复用**: 支持多路复用和流管理
 //! - **策略引擎**: 集成安全策略管理
+//! - **对端限速**: 按对端的令牌桶限制发送和接收速率，超出时等待而不报错
+//! - **TCP回退**: UDP被阻断时在TCP流上以同样的证书和策略完成mTLS握手
 
 // 模块声明 - 新的模块化结构
 pub mod pool;
 pub mod policy;
 pub mod mtls;
 pub mod error_codes;
+pub mod send_queue;
+pub mod rate_limit;
+pub mod cert_status;
 
 // 兼容性模块声明 - 保留旧的模块以便逐步迁移
 pub mod mtls_manager;
 pub mod policy_engine;
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
-use quinn::{Endpoint, Connection};
+use quinn::Endpoint;
+pub use quinn::{Connection, SendStream, RecvStream, ReadError, ReadExactError, VarInt};
+pub use tokio_rustls::TlsStream;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
-use tracing::{info, debug};
-use bey_identity::CertificateManager;
+use tokio::net::TcpStream;
+use tokio::sync::{RwLock, broadcast};
+use tracing::{info, debug, warn};
+use bey_identity::{CertificateData, CertificateManager};
 use mtls_manager::CompleteMtlsManager;
-use policy_engine::{CompletePolicyEngine, PolicyContext, PolicyAction};
+use policy_engine::{CompletePolicyEngine, PolicyContext, PolicyAction, PolicyDecision, PolicyRequest, PolicySet, PolicySetEvaluationResult};
 
 // 类型别名和重新导出
 pub type MtlsStats = mtls_manager::MtlsStats;
 pub type PolicyEngineStats = policy_engine::PolicyEngineStats;
 
 // 重新导出新模块的类型
-pub use pool::{CompleteConnectionPoolConfig, LoadBalanceStrategy, CompleteConnectionStats};
+pub use pool::{CompleteConnectionPoolConfig, LoadBalanceStrategy, CompleteConnectionStats, PeerConnectionPool};
 pub use policy::{PolicyAction as PolicyActionType, ConditionOperator};
-pub use mtls::{MtlsConfig};
+pub use mtls::{MtlsConfig, HandshakeFailure, DEFAULT_ALPN_PROTOCOL};
+pub use send_queue::{Delivery, PeerSendQueues, PeerSink, DEFAULT_SEND_QUEUE_CAPACITY};
+pub use rate_limit::{PeerRateLimiter, RateDirection, RATE_LIMIT_BURST};
+pub use cert_status::{TransportCertStatus, DEFAULT_CERT_STATUS_TIMEOUT};
+pub use bey_types::TrustLevel;
 
 
 /// 传输消息
@@ -63,14 +75,156 @@ pub struct TransportMessage {
     pub receiver_id: Option<String>,
 }
 
+/// 传输层连接事件
+///
+/// 通过 [`SecureTransport::events`] 订阅，用于实时观察连接的建立、断开和握手失败
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransportEvent {
+    /// 连接建立
+    Connected {
+        /// 对端地址
+        remote_addr: SocketAddr,
+        /// 是否为接受的入站连接
+        inbound: bool,
+    },
+    /// 连接断开
+    Disconnected {
+        /// 对端地址
+        remote_addr: SocketAddr,
+        /// QUIC应用关闭码，标准关闭码见 [`CloseCode`]；连接因超时、重置等传输层原因断开时为 `None`
+        code: Option<u32>,
+        /// 关闭原因
+        reason: String,
+    },
+    /// QUIC握手失败或超时
+    HandshakeFailed {
+        /// 对端地址
+        remote_addr: SocketAddr,
+        /// 失败原因
+        reason: String,
+    },
+    /// 打开或接受了双向流
+    StreamOpened {
+        /// 对端地址
+        remote_addr: SocketAddr,
+    },
+}
+
+/// 标准连接关闭码
+///
+/// 关闭连接时作为QUIC应用错误码发送给对端，对端的 [`TransportEvent::Disconnected`]
+/// 携带该错误码，用于区分正常关闭和异常关闭
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CloseCode {
+    /// 正常关闭
+    Shutdown,
+    /// 对端违反访问策略
+    PolicyViolation,
+    /// 连接空闲超时
+    IdleTimeout,
+    /// 对端违反协议
+    ProtocolError,
+}
+
+impl CloseCode {
+    /// 对应的QUIC应用错误码
+    pub fn code(self) -> u32 {
+        match self {
+            CloseCode::Shutdown => 0,
+            CloseCode::PolicyViolation => 1,
+            CloseCode::IdleTimeout => 2,
+            CloseCode::ProtocolError => 3,
+        }
+    }
+
+    /// 根据QUIC应用错误码识别标准关闭码，非标准错误码返回 `None`
+    pub fn from_code(code: u32) -> Option<Self> {
+        match code {
+            0 => Some(CloseCode::Shutdown),
+            1 => Some(CloseCode::PolicyViolation),
+            2 => Some(CloseCode::IdleTimeout),
+            3 => Some(CloseCode::ProtocolError),
+            _ => None,
+        }
+    }
+}
+
+/// QUIC 拥塞控制算法
+///
+/// 默认使用 Quinn 的默认算法 Cubic；低延迟的局域网中可改用 BBR 等算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CongestionAlgo {
+    /// CUBIC（Quinn 默认）
+    #[default]
+    Cubic,
+    /// NewReno
+    NewReno,
+    /// BBR（Quinn 中为实验性实现）
+    Bbr,
+}
+
+impl CongestionAlgo {
+    /// 创建对应算法的 Quinn 拥塞控制器工厂
+    pub fn controller_factory(self) -> Arc<dyn quinn::congestion::ControllerFactory + Send + Sync> {
+        match self {
+            CongestionAlgo::Cubic => Arc::new(quinn::congestion::CubicConfig::default()),
+            CongestionAlgo::NewReno => Arc::new(quinn::congestion::NewRenoConfig::default()),
+            CongestionAlgo::Bbr => Arc::new(quinn::congestion::BbrConfig::default()),
+        }
+    }
+}
+
+/// QUIC 连接统计信息
+///
+/// 由 `quinn::Connection::stats()` 转换而来，用于性能诊断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuicConnStats {
+    /// 已发送的UDP字节数
+    pub bytes_sent: u64,
+    /// 已接收的UDP字节数
+    pub bytes_received: u64,
+    /// 当前拥塞窗口（字节）
+    pub congestion_window: u64,
+    /// 拥塞事件次数
+    pub congestion_events: u64,
+    /// 丢失的数据包数量
+    pub lost_packets: u64,
+    /// 当前路径的往返时间
+    pub rtt: Duration,
+}
+
+impl From<quinn::ConnectionStats> for QuicConnStats {
+    fn from(stats: quinn::ConnectionStats) -> Self {
+        Self {
+            bytes_sent: stats.udp_tx.bytes,
+            bytes_received: stats.udp_rx.bytes,
+            congestion_window: stats.path.cwnd,
+            congestion_events: stats.path.congestion_events,
+            lost_packets: stats.path.lost_packets,
+            rtt: stats.path.rtt,
+        }
+    }
+}
+
 /// 安全传输层结果类型
 pub type TransportResult<T> = std::result::Result<T, ErrorInfo>;
 
+/// 默认策略集合ID
+pub const DEFAULT_POLICY_SET_ID: &str = "default";
+
+/// 默认连接服务端名称（SNI）
+pub const DEFAULT_SERVER_NAME: &str = "bey-transport";
+
+/// [`SecureTransport::receive_message`] 接收的单条消息最大长度（字节）
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
 /// 传输层配置
 ///
 /// 配置安全传输层的各种参数
 #[derive(Debug, Clone)]
 pub struct TransportConfig {
+    /// 监听地址
+    bind_address: IpAddr,
     /// 监听端口
     port: u16,
     /// 证书存储目录
@@ -79,8 +233,10 @@ pub struct TransportConfig {
     connection_timeout: Duration,
     /// 最大并发连接数
     max_connections: u32,
-    /// 是否启用客户端证书验证
+    /// 作为服务端时是否要求客户端出示证书
     require_client_cert: bool,
+    /// 作为客户端时是否出示设备证书
+    present_client_cert: bool,
     /// 心跳间隔
     keep_alive_interval: Duration,
     /// 最大空闲超时
@@ -89,20 +245,46 @@ pub struct TransportConfig {
     organization_name: String,
     /// 国家代码
     country_code: String,
+    /// 出站连接池配置
+    pool_config: CompleteConnectionPoolConfig,
+    /// 每个对端的发送队列容量
+    send_queue_capacity: usize,
+    /// 对端默认限速（字节/秒），`None` 表示不限速
+    peer_rate_limit: Option<u64>,
+    /// ALPN协议标识（按优先级排列）
+    alpn_protocols: Vec<Vec<u8>>,
+    /// 连接时使用的服务端名称（SNI）
+    server_name: String,
+    /// 拥塞控制算法
+    congestion_controller: CongestionAlgo,
+    /// 证书存储中没有设备证书时是否自动签发
+    auto_provision_certs: bool,
+    /// 是否按首次使用信任固定对端证书
+    tofu_pinning: bool,
 }
 
 impl Default for TransportConfig {
     fn default() -> Self {
         Self {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             port: 8443,
             certificates_dir: PathBuf::from("./certs"),
             connection_timeout: Duration::from_secs(30),
             max_connections: 100,
             require_client_cert: true,
+            present_client_cert: true,
             keep_alive_interval: Duration::from_secs(10),
             idle_timeout: Duration::from_secs(60),
             organization_name: "BEY".to_string(),
             country_code: "CN".to_string(),
+            pool_config: CompleteConnectionPoolConfig::default(),
+            send_queue_capacity: DEFAULT_SEND_QUEUE_CAPACITY,
+            peer_rate_limit: None,
+            alpn_protocols: vec![DEFAULT_ALPN_PROTOCOL.to_vec()],
+            server_name: DEFAULT_SERVER_NAME.to_string(),
+            congestion_controller: CongestionAlgo::default(),
+            auto_provision_certs: true,
+            tofu_pinning: false,
         }
     }
 }
@@ -113,6 +295,14 @@ impl TransportConfig {
         Self::default()
     }
 
+    /// 设置监听地址
+    ///
+    /// 默认为 `0.0.0.0`（监听所有网络接口），可指定为单个本机接口地址
+    pub fn with_bind_address(mut self, address: IpAddr) -> Self {
+        self.bind_address = address;
+        self
+    }
+
     /// 设置监听端口
     pub fn with_port(mut self, port: u16) -> Self {
         self.port = port;
@@ -138,11 +328,22 @@ impl TransportConfig {
     }
 
     /// 设置是否需要客户端证书
+    ///
+    /// 只影响本传输层作为服务端接受的连接，每个监听器使用各自的配置；
+    /// 为 `false` 时接受匿名客户端，匿名连接的信任级别为 [`TrustLevel::Untrusted`]
     pub fn with_require_client_cert(mut self, require: bool) -> Self {
         self.require_client_cert = require;
         self
     }
 
+    /// 设置连接时是否出示设备证书
+    ///
+    /// 为 `false` 时以匿名客户端身份连接，只能连接不要求客户端证书的监听器
+    pub fn with_present_client_cert(mut self, present: bool) -> Self {
+        self.present_client_cert = present;
+        self
+    }
+
     /// 设置心跳间隔
     pub fn with_keep_alive_interval(mut self, interval: Duration) -> Self {
         self.keep_alive_interval = interval;
@@ -167,6 +368,73 @@ impl TransportConfig {
         self
     }
 
+    /// 设置出站连接池配置
+    pub fn with_pool_config(mut self, pool_config: CompleteConnectionPoolConfig) -> Self {
+        self.pool_config = pool_config;
+        self
+    }
+
+    /// 设置每个对端的发送队列容量
+    pub fn with_send_queue_capacity(mut self, capacity: usize) -> Self {
+        self.send_queue_capacity = capacity;
+        self
+    }
+
+    /// 设置对端默认限速
+    ///
+    /// 发送和接收方向分别限制为该速率，可通过 [`SecureTransport::set_peer_rate_limit`] 按对端覆盖
+    pub fn with_peer_rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.peer_rate_limit = Some(bytes_per_sec);
+        self
+    }
+
+    /// 设置ALPN协议标识
+    ///
+    /// 按优先级排列，握手时双方必须至少有一个共同协议，否则拒绝连接；
+    /// 不同版本的协议可使用不同标识，避免不兼容的节点互相连接
+    pub fn with_alpn(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.alpn_protocols = protocols;
+        self
+    }
+
+    /// 设置连接时使用的服务端名称（SNI）
+    pub fn with_server_name(mut self, name: String) -> Self {
+        self.server_name = name;
+        self
+    }
+
+    /// 设置拥塞控制算法
+    ///
+    /// 同时作用于服务端接受的连接和主动发起的连接
+    pub fn with_congestion_controller(mut self, algo: CongestionAlgo) -> Self {
+        self.congestion_controller = algo;
+        self
+    }
+
+    /// 设置证书存储中没有设备证书时是否自动签发
+    ///
+    /// 默认自动签发；为 `false` 时需先调用 [`SecureTransport::provision_certificates`]
+    /// 初始化证书，否则 [`SecureTransport::start_server`] 返回
+    /// [`error_codes::transport::CERTIFICATES_NOT_INITIALIZED`]
+    pub fn with_auto_provision_certs(mut self, auto_provision: bool) -> Self {
+        self.auto_provision_certs = auto_provision;
+        self
+    }
+
+    /// 设置是否按首次使用信任（TOFU）固定对端证书
+    ///
+    /// 启用后第一次通过验证的对端证书按其设备ID固定，之后该设备出示不同的证书时拒绝握手，
+    /// 连接失败的原因为 [`HandshakeFailure::PinMismatch`]；固定记录保存在证书目录中
+    pub fn with_tofu_pinning(mut self, enable: bool) -> Self {
+        self.tofu_pinning = enable;
+        self
+    }
+
+    /// 获取监听地址
+    pub fn bind_address(&self) -> IpAddr {
+        self.bind_address
+    }
+
     /// 获取监听端口
     pub fn port(&self) -> u16 {
         self.port
@@ -192,6 +460,11 @@ impl TransportConfig {
         self.require_client_cert
     }
 
+    /// 获取连接时是否出示设备证书
+    pub fn present_client_cert(&self) -> bool {
+        self.present_client_cert
+    }
+
     /// 获取心跳间隔
     pub fn keep_alive_interval(&self) -> Duration {
         self.keep_alive_interval
@@ -201,6 +474,55 @@ impl TransportConfig {
     pub fn idle_timeout(&self) -> Duration {
         self.idle_timeout
     }
+
+    /// 获取出站连接池配置
+    pub fn pool_config(&self) -> &CompleteConnectionPoolConfig {
+        &self.pool_config
+    }
+
+    /// 获取每个对端的发送队列容量
+    pub fn send_queue_capacity(&self) -> usize {
+        self.send_queue_capacity
+    }
+
+    /// 获取对端默认限速（字节/秒）
+    pub fn peer_rate_limit(&self) -> Option<u64> {
+        self.peer_rate_limit
+    }
+
+    /// 获取ALPN协议标识
+    pub fn alpn_protocols(&self) -> &[Vec<u8>] {
+        &self.alpn_protocols
+    }
+
+    /// 获取连接时使用的服务端名称
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    /// 获取拥塞控制算法
+    pub fn congestion_controller(&self) -> CongestionAlgo {
+        self.congestion_controller
+    }
+
+    /// 获取是否自动签发设备证书
+    pub fn auto_provision_certs(&self) -> bool {
+        self.auto_provision_certs
+    }
+
+    /// 获取是否按首次使用信任固定对端证书
+    pub fn tofu_pinning(&self) -> bool {
+        self.tofu_pinning
+    }
+
+    /// 构建 Quinn 传输参数
+    ///
+    /// 在 Quinn 默认参数的基础上设置拥塞控制算法
+    pub fn quic_transport_config(&self) -> quinn::TransportConfig {
+        let mut transport_config = quinn::TransportConfig::default();
+        transport_config.congestion_controller_factory(self.congestion_controller.controller_factory());
+        transport_config
+    }
 }
 
 /// 安全传输层
@@ -213,6 +535,8 @@ pub struct SecureTransport {
     endpoint: Option<Endpoint>,
     /// 活跃连接
     connections: Arc<RwLock<HashMap<SocketAddr, Connection>>>,
+    /// 接受的入站连接的信任级别
+    peer_trust: Arc<RwLock<HashMap<SocketAddr, TrustLevel>>>,
     /// 运行状态
     is_running: Arc<RwLock<bool>>,
     /// 设备ID
@@ -221,6 +545,20 @@ pub struct SecureTransport {
     mtls_manager: Arc<CompleteMtlsManager>,
     /// 策略引擎
     policy_engine: Arc<CompletePolicyEngine>,
+    /// 访问控制使用的策略集合ID
+    policy_set_id: String,
+    /// 出站连接池
+    pool: Arc<PeerConnectionPool>,
+    /// 每个对端的发送队列
+    send_queues: Arc<PeerSendQueues<Connection>>,
+    /// 对端限速
+    rate_limiter: Arc<PeerRateLimiter>,
+    /// 连接事件发送端
+    events: broadcast::Sender<TransportEvent>,
+    /// 等待响应的证书状态查询
+    status_queries: Arc<cert_status::PendingStatusQueries>,
+    /// 双向流由其他接受方处理的连接
+    stream_claims: Arc<std::sync::Mutex<HashSet<usize>>>,
 }
 
 impl SecureTransport {
@@ -241,27 +579,6 @@ impl SecureTransport {
         // 只安装一次，多次调用是安全的
         let _ = rustls::crypto::ring::default_provider().install_default();
 
-        // 创建证书管理器
-        use bey_identity::config::CertificateConfig;
-        let cert_config = CertificateConfig::builder()
-            .with_validity_days(365)
-            .with_key_algorithm("ECDSA")
-            .with_key_size(256)
-            .with_ca_common_name("BEY Transport CA")
-            .with_organization_name(&config.organization_name)
-            .with_country_code(&config.country_code)
-            .build()
-            .map_err(|e| ErrorInfo::new(2001, format!("创建证书配置失败: {}", e))
-                .with_category(ErrorCategory::Configuration)
-                .with_severity(ErrorSeverity::Error))?;
-
-        let _certificate_manager = Arc::new(
-            CertificateManager::initialize(cert_config).await
-                .map_err(|e| ErrorInfo::new(2001, format!("初始化证书管理器失败: {}", e))
-                    .with_category(ErrorCategory::Configuration)
-                    .with_severity(ErrorSeverity::Error))?
-        );
-
         // 创建mTLS管理器
         let mtls_config = mtls_manager::MtlsConfig {
             enabled: true,
@@ -272,6 +589,11 @@ impl SecureTransport {
             device_id_prefix: device_id.clone(),
             organization_name: config.organization_name.clone(),
             country_code: config.country_code.clone(),
+            alpn_protocols: config.alpn_protocols.clone(),
+            require_client_cert: config.require_client_cert,
+            present_client_cert: config.present_client_cert,
+            auto_provision_certs: config.auto_provision_certs,
+            enable_tofu: config.tofu_pinning,
         };
 
         let mtls_manager = Arc::new(
@@ -286,14 +608,27 @@ impl SecureTransport {
         let policy_config = policy_engine::PolicyEngineConfig::default();
         let policy_engine = Arc::new(CompletePolicyEngine::new(policy_config));
 
+        let pool = Arc::new(PeerConnectionPool::new(config.pool_config.clone()));
+        let rate_limiter = Arc::new(PeerRateLimiter::new(config.peer_rate_limit));
+        let send_queues = Arc::new(PeerSendQueues::new(config.send_queue_capacity)
+            .with_rate_limiter(Arc::clone(&rate_limiter)));
+
         let transport = Self {
             config,
             endpoint: None,
             connections: Arc::new(RwLock::new(HashMap::new())),
+            peer_trust: Arc::new(RwLock::new(HashMap::new())),
             is_running: Arc::new(RwLock::new(false)),
             device_id,
             mtls_manager,
             policy_engine,
+            policy_set_id: DEFAULT_POLICY_SET_ID.to_string(),
+            pool,
+            send_queues,
+            rate_limiter,
+            events: broadcast::channel(64).0,
+            status_queries: Arc::new(cert_status::PendingStatusQueries::default()),
+            stream_claims: Arc::new(std::sync::Mutex::new(HashSet::new())),
         };
 
         info!("安全传输层初始化完成");
@@ -302,6 +637,9 @@ impl SecureTransport {
 
     /// 启动传输层服务器
     ///
+    /// 证书存储中没有设备证书时，启用自动签发则先签发证书，
+    /// 否则返回 [`error_codes::transport::CERTIFICATES_NOT_INITIALIZED`]
+    ///
     /// # 返回值
     ///
     /// 返回启动结果或错误信息
@@ -312,6 +650,24 @@ impl SecureTransport {
                 .with_severity(ErrorSeverity::Warning));
         }
 
+        // 监听地址必须是本机地址
+        ensure_local_address(self.config.bind_address())?;
+
+        // 服务器证书必须存在
+        if self.mtls_manager.get_local_certificate_info().await.is_none() {
+            if !self.config.auto_provision_certs() {
+                return Err(ErrorInfo::new(
+                    error_codes::transport::CERTIFICATES_NOT_INITIALIZED,
+                    format!("证书存储 {} 中没有设备 {} 的证书", self.config.certificates_dir().display(), self.device_id),
+                )
+                    .with_category(ErrorCategory::Configuration)
+                    .with_severity(ErrorSeverity::Error)
+                    .with_context("启动服务器前请先调用 SecureTransport::provision_certificates 初始化证书，或启用 TransportConfig::with_auto_provision_certs".to_string()));
+            }
+            info!("证书存储中没有设备证书，自动签发");
+            self.provision_certificates().await?;
+        }
+
         // 设置运行状态
         {
             let mut is_running = self.is_running.write().await;
@@ -324,15 +680,12 @@ impl SecureTransport {
                 .with_category(ErrorCategory::Configuration)
                 .with_severity(ErrorSeverity::Error))?;
 
-        // 直接使用mTLS管理器提供的Quinn服务器配置
-        let server_config = rustls_server_config;
+        // 在mTLS管理器提供的Quinn服务器配置上应用传输参数
+        let mut server_config = rustls_server_config;
+        server_config.transport_config(Arc::new(self.config.quic_transport_config()));
 
         // 创建服务器端点
-        let server_addr = format!("0.0.0.0:{}", self.config.port())
-            .parse()
-            .map_err(|e| ErrorInfo::new(2006, format!("解析服务器地址失败: {}", e))
-                .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
+        let server_addr = SocketAddr::new(self.config.bind_address(), self.config.port());
 
         let endpoint = Endpoint::server(server_config, server_addr)
             .map_err(|e| ErrorInfo::new(2007, format!("创建服务器端点失败: {}", e))
@@ -344,7 +697,7 @@ impl SecureTransport {
         // 启动连接接受任务
         self.start_connection_acceptor(endpoint).await;
 
-        info!("安全传输层服务器已启动，监听端口: {}", self.config.port());
+        info!("安全传输层服务器已启动，监听地址: {}", server_addr);
 
         Ok(())
     }
@@ -359,6 +712,67 @@ impl SecureTransport {
     ///
     /// 返回连接对象或错误信息
     pub async fn connect(&self, remote_addr: SocketAddr) -> TransportResult<Connection> {
+        self.evaluate_connect_policy(remote_addr).await?;
+
+        // 优先复用连接池中的健康连接
+        if let Some(connection) = self.pool.acquire(remote_addr).await {
+            self.connections.write().await.insert(remote_addr, connection.clone());
+            debug!("复用已有连接: {}", remote_addr);
+            return Ok(connection);
+        }
+
+        if !self.pool.can_create(remote_addr).await {
+            self.pool.record_failure().await;
+            return Err(ErrorInfo::new(error_codes::pool::POOL_FULL, format!("连接池已满，无法连接到: {}", remote_addr))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Warning));
+        }
+
+        let connection = match self.establish_connection(remote_addr).await {
+            Ok(connection) => connection,
+            Err(e) => {
+                self.pool.record_failure().await;
+                return Err(e);
+            }
+        };
+
+        // 保存握手中新固定的对端证书
+        if let Err(e) = self.mtls_manager.certificate_manager().flush_tofu_pins().await {
+            warn!("保存TOFU固定记录失败: {}", e);
+        }
+
+        cert_status::spawn_status_responder(
+            connection.clone(),
+            self.mtls_manager.certificate_manager(),
+            Arc::clone(&self.status_queries),
+        );
+
+        // 存储连接
+        self.pool.insert(remote_addr, connection.clone()).await;
+        {
+            let mut connections = self.connections.write().await;
+            connections.insert(remote_addr, connection.clone());
+        }
+
+        info!("已连接到远程设备: {}", remote_addr);
+        let _ = self.events.send(TransportEvent::Connected { remote_addr, inbound: false });
+
+        spawn_close_watcher(
+            connection.clone(),
+            Arc::clone(&self.connections),
+            Arc::clone(&self.peer_trust),
+            self.events.clone(),
+        );
+
+        Ok(connection)
+    }
+
+    /// 评估出站连接策略
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 远程设备地址
+    async fn evaluate_connect_policy(&self, remote_addr: SocketAddr) -> TransportResult<()> {
         // 创建策略上下文进行访问控制
         let policy_context = PolicyContext::new()
             .with_requester_id(self.device_id.clone())
@@ -367,7 +781,7 @@ impl SecureTransport {
             .set_field("target_address".to_string(), serde_json::Value::String(remote_addr.to_string()));
 
         // 评估连接策略 - 使用默认策略集合
-        let policy_result = self.policy_engine.evaluate("default", &policy_context).await
+        let policy_result = self.policy_engine.evaluate(&self.policy_set_id, &policy_context).await
             .map_err(|e| ErrorInfo::new(2021, format!("策略评估失败: {}", e))
                 .with_category(ErrorCategory::Configuration)
                 .with_severity(ErrorSeverity::Error))?;
@@ -379,55 +793,247 @@ impl SecureTransport {
         }
 
         debug!("连接策略评估通过: {} -> {}", self.device_id, remote_addr);
+        Ok(())
+    }
 
+    /// 在TCP流上以客户端身份完成mTLS握手（TCP回退）
+    ///
+    /// 与 [`connect`](Self::connect) 一样先评估连接策略，再使用mTLS管理器的证书和验证器握手：
+    /// 服务端证书按同一CA、交叉证书和TOFU固定记录校验，并出示本机设备证书
+    ///
+    /// # 参数
+    ///
+    /// * `stream` - 已建立的TCP流
+    /// * `remote_addr` - 远程设备地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回加密的TLS流或错误
+    pub async fn connect_tcp(&self, stream: TcpStream, remote_addr: SocketAddr) -> TransportResult<TlsStream<TcpStream>> {
+        self.evaluate_connect_policy(remote_addr).await?;
+
+        let client_config = self.mtls_manager.get_tls_client_config().await
+            .map_err(|e| ErrorInfo::new(2008, format!("获取客户端配置失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+        let server_name = rustls::pki_types::ServerName::try_from(self.config.server_name().to_string())
+            .map_err(|e| ErrorInfo::new(2010, format!("服务器名称无效: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+
+        // 清除上一次的握手失败记录
+        let _ = self.mtls_manager.take_handshake_failure();
+
+        let connector = tokio_rustls::TlsConnector::from(client_config);
+        let result = tokio::time::timeout(self.config.connection_timeout(), connector.connect(server_name, stream)).await
+            .map_err(|_| tcp_tls_error(remote_addr, "握手超时"))
+            .and_then(|connected| connected.map_err(|e| match self.mtls_manager.take_handshake_failure() {
+                // 证书验证失败时给出具体原因
+                Some(failure) => failure.to_error_info(2012, &remote_addr.to_string()),
+                None => tcp_tls_error(remote_addr, e),
+            }));
+        self.finish_tcp_handshake(remote_addr, result.map(TlsStream::from)).await
+    }
+
+    /// 在TCP流上以服务端身份完成mTLS握手（TCP回退）
+    ///
+    /// 与QUIC入站连接一样先评估接受策略，再使用mTLS管理器的证书和验证器握手：
+    /// 客户端出示的证书按同一CA、交叉证书和TOFU固定记录校验，
+    /// 不要求客户端证书时接受匿名客户端
+    ///
+    /// # 参数
+    ///
+    /// * `stream` - 已接受的TCP流
+    /// * `remote_addr` - 远程设备地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回加密的TLS流和对端的信任级别或错误
+    pub async fn accept_tcp(&self, stream: TcpStream, remote_addr: SocketAddr) -> TransportResult<(TlsStream<TcpStream>, TrustLevel)> {
+        let policy_context = PolicyContext::new()
+            .with_requester_id("remote".to_string())
+            .with_resource(format!("connection:{}", remote_addr))
+            .with_operation("accept".to_string())
+            .set_field("local_device".to_string(), serde_json::Value::String(self.device_id.clone()));
+        let policy_result = self.policy_engine.evaluate(&self.policy_set_id, &policy_context).await
+            .map_err(|e| ErrorInfo::new(2021, format!("策略评估失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+        if policy_result.final_action != PolicyAction::Allow {
+            return Err(ErrorInfo::new(2022, format!("连接被策略拒绝: {}", policy_result.evaluation_summary))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        let server_config = self.mtls_manager.get_tls_server_config().await
+            .map_err(|e| ErrorInfo::new(2005, format!("获取服务器配置失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+        let acceptor = tokio_rustls::TlsAcceptor::from(server_config);
+        let result = tokio::time::timeout(self.config.connection_timeout(), acceptor.accept(stream)).await
+            .map_err(|_| tcp_tls_error(remote_addr, "握手超时"))
+            .and_then(|accepted| accepted.map_err(|e| tcp_tls_error(remote_addr, e)));
+        let stream = self.finish_tcp_handshake(remote_addr, result.map(TlsStream::from)).await?;
+
+        // 客户端证书已在握手时验证，未出示证书的为匿名连接
+        let trust_level = if stream.get_ref().1.peer_certificates().is_some_and(|certs| !certs.is_empty()) {
+            TrustLevel::Trusted
+        } else {
+            TrustLevel::Untrusted
+        };
+        Ok((stream, trust_level))
+    }
+
+    /// 记录TCP回退连接的mTLS握手结果
+    ///
+    /// 成功时保存握手中新固定的对端证书，失败时发出 [`TransportEvent::HandshakeFailed`]
+    async fn finish_tcp_handshake(
+        &self,
+        remote_addr: SocketAddr,
+        result: TransportResult<TlsStream<TcpStream>>,
+    ) -> TransportResult<TlsStream<TcpStream>> {
+        match &result {
+            Ok(_) => {
+                if let Err(e) = self.mtls_manager.certificate_manager().flush_tofu_pins().await {
+                    warn!("保存TOFU固定记录失败: {}", e);
+                }
+            }
+            Err(e) => {
+                let _ = self.events.send(TransportEvent::HandshakeFailed {
+                    remote_addr,
+                    reason: e.message().to_string(),
+                });
+            }
+        }
+        result
+    }
+
+    /// 连接到设备的多个地址之一
+    ///
+    /// 按连接池配置的负载均衡策略在设备的多个地址中选择一个，并复用已有的健康连接
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对端设备ID
+    /// * `addrs` - 对端设备的地址列表
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接对象或错误信息
+    pub async fn connect_peer(&self, peer_id: &str, addrs: &[SocketAddr]) -> TransportResult<Connection> {
+        let remote_addr = self.pool.select_address(peer_id, addrs).await
+            .ok_or_else(|| ErrorInfo::new(error_codes::transport::CONNECTION_FAILED, format!("设备没有可用地址: {}", peer_id))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        debug!("为设备 {} 选择地址: {}", peer_id, remote_addr);
+        self.connect(remote_addr).await
+    }
+
+    /// 向连接的对端查询证书状态
+    ///
+    /// 对端由连接上的应答任务使用其证书管理器签名作答，
+    /// 响应的签名和随机数由调用方（通常是 [`CertificateManager::query_cert_status`]）校验
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 到签发节点的连接
+    /// * `request` - 查询请求
+    /// * `timeout` - 等待响应的时间
+    ///
+    /// # 返回值
+    ///
+    /// 返回对端的响应，超时返回 [`error_codes::transport::CERT_STATUS_QUERY_FAILED`]
+    pub async fn request_cert_status(
+        &self,
+        connection: &Connection,
+        request: &bey_identity::CertStatusRequest,
+        timeout: Duration,
+    ) -> TransportResult<bey_identity::CertStatusResponse> {
+        cert_status::query_status(connection, &self.status_queries, request, timeout).await
+    }
+
+    /// 获取本设备的证书管理器
+    pub fn certificate_manager(&self) -> Arc<CertificateManager> {
+        self.mtls_manager.certificate_manager()
+    }
+
+    /// 获取出站连接池统计信息
+    pub async fn pool_stats(&self) -> CompleteConnectionStats {
+        self.pool.stats().await
+    }
+
+    /// 建立新的QUIC连接（不经过连接池）
+    async fn establish_connection(&self, remote_addr: SocketAddr) -> TransportResult<Connection> {
         // 获取客户端配置
         let client_config = self.mtls_manager.get_client_config().await
             .map_err(|e| ErrorInfo::new(2008, format!("获取客户端配置失败: {}", e))
                 .with_category(ErrorCategory::Configuration)
                 .with_severity(ErrorSeverity::Error))?;
 
-        // 直接使用mTLS管理器提供的Quinn客户端配置
-        let client_quinn_config = client_config;
+        // 在mTLS管理器提供的Quinn客户端配置上应用传输参数
+        let mut client_quinn_config = client_config;
+        client_quinn_config.transport_config(Arc::new(self.config.quic_transport_config()));
 
         // 创建客户端端点
         let client_endpoint = quinn::Endpoint::client("0.0.0.0:0".parse()
             .map_err(|e| ErrorInfo::new(2009, format!("解析客户端地址失败: {}", e))
                 .with_category(ErrorCategory::Network)
                 .with_severity(ErrorSeverity::Error))?)
-            .map_err(|e| ErrorInfo::new(2009, format!("创建客户端端点失败: {}", e))
+            .map_err(|e| ErrorInfo::new(error_codes::transport::UDP_UNREACHABLE, format!("创建客户端端点失败: {}", e))
                 .with_category(ErrorCategory::Network)
                 .with_severity(ErrorSeverity::Error))?;
 
+        // 清除上一次的握手失败记录
+        let _ = self.mtls_manager.take_handshake_failure();
+
         // 连接到远程设备
-        let connecting = client_endpoint.connect_with(client_quinn_config, remote_addr, "bey-transport")
+        let connecting = client_endpoint.connect_with(client_quinn_config, remote_addr, self.config.server_name())
             .map_err(|e| ErrorInfo::new(2010, format!("发起连接失败: {}", e))
                 .with_category(ErrorCategory::Network)
                 .with_severity(ErrorSeverity::Error))?;
 
-        let connection = tokio::time::timeout(
+        let result = tokio::time::timeout(
             self.config.connection_timeout(),
             connecting
         ).await
-            .map_err(|_| ErrorInfo::new(2011, "连接超时".to_string())
+            .map_err(|_| ErrorInfo::new(error_codes::transport::UDP_UNREACHABLE, format!("连接 {} 超时，UDP可能被阻断", remote_addr))
                 .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?
-            .map_err(|e| ErrorInfo::new(2012, format!("连接失败: {}", e))
-                .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
-
-        // 存储连接
-        {
-            let mut connections = self.connections.write().await;
-            connections.insert(remote_addr, connection.clone());
+                .with_severity(ErrorSeverity::Error))
+            .and_then(|connected| connected.map_err(|e| match self.mtls_manager.take_handshake_failure() {
+                // 证书验证失败时给出具体原因
+                Some(failure) => failure.to_error_info(2012, &remote_addr.to_string()),
+                None if matches!(e, quinn::ConnectionError::TimedOut) => ErrorInfo::new(
+                    error_codes::transport::UDP_UNREACHABLE,
+                    format!("连接 {} 超时，UDP可能被阻断: {}", remote_addr, e),
+                )
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Error),
+                None if is_alpn_mismatch(&e) => ErrorInfo::new(
+                    error_codes::transport::ALPN_MISMATCH,
+                    format!("ALPN协议不匹配，拒绝连接 {}: {}", remote_addr, e),
+                )
+                    .with_category(ErrorCategory::Authentication)
+                    .with_severity(ErrorSeverity::Error),
+                None => ErrorInfo::new(2012, format!("连接失败: {}", e))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Error),
+            }));
+
+        if let Err(e) = &result {
+            let _ = self.events.send(TransportEvent::HandshakeFailed {
+                remote_addr,
+                reason: e.message().to_string(),
+            });
         }
-
-        info!("已连接到远程设备: {}", remote_addr);
-
-        Ok(connection)
+        result
     }
 
     /// 发送消息
     ///
+    /// 消息通过策略检查后放入连接的发送队列，由连接的刷新任务按对端限速写出，
+    /// 不等待写入完成，其他对端的拥塞不会阻塞本次发送。
+    ///
     /// # 参数
     ///
     /// * `connection` - 连接对象
@@ -435,8 +1041,9 @@ impl SecureTransport {
     ///
     /// # 返回值
     ///
-    /// 返回发送结果或错误信息
-    pub async fn send_message(&self, connection: &Connection, message: TransportMessage) -> TransportResult<()> {
+    /// 入队成功返回投递结果，等待它得到消息写到对端的结果；
+    /// 发送队列已满时返回错误，调用方可稍后重试
+    pub async fn send_message(&self, connection: &Connection, message: TransportMessage) -> TransportResult<Delivery> {
         // 创建发送策略上下文
         let policy_context = PolicyContext::new()
             .with_requester_id(self.device_id.clone())
@@ -449,7 +1056,7 @@ impl SecureTransport {
                     .unwrap_or(serde_json::Value::Null));
 
         // 评估发送策略
-        let policy_result = self.policy_engine.evaluate("default", &policy_context).await
+        let policy_result = self.policy_engine.evaluate(&self.policy_set_id, &policy_context).await
             .map_err(|e| ErrorInfo::new(2013, format!("发送策略评估失败: {}", e))
                 .with_category(ErrorCategory::Configuration)
                 .with_severity(ErrorSeverity::Error))?;
@@ -467,23 +1074,11 @@ impl SecureTransport {
                 .with_category(ErrorCategory::Parse)
                 .with_severity(ErrorSeverity::Error))?;
 
-        let mut stream = connection.open_uni().await
-            .map_err(|e| ErrorInfo::new(2012, format!("打开单向流失败: {}", e))
-                .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
-
-        stream.write_all(&message_data).await
-            .map_err(|e| ErrorInfo::new(2013, format!("发送消息失败: {}", e))
-                .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
+        // 对端队列已满（发送方超出对端限速或对端拥塞）时等待空间，而不是返回错误
+        let delivery = self.send_queues.enqueue_wait(connection.clone(), message_data).await?;
 
-        stream.finish()
-            .map_err(|e| ErrorInfo::new(2014, format!("完成发送失败: {}", e))
-                .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
-
-        debug!("消息发送完成: {}", message.id);
-        Ok(())
+        debug!("消息已加入发送队列: {}", message.id);
+        Ok(delivery)
     }
 
     /// 接收消息
@@ -502,11 +1097,24 @@ impl SecureTransport {
                 .with_category(ErrorCategory::Network)
                 .with_severity(ErrorSeverity::Error))?;
 
-        // 读取消息数据
-        let buffer = stream.read_to_end(1024 * 1024).await // 限制为1MB
+        // 逐块读取消息数据，超出对端接收限速时推迟读取下一块，
+        // 未读取的数据留在QUIC接收窗口中，对端随之被流控延后
+        let remote_addr = connection.remote_address();
+        let mut buffer = Vec::new();
+        while let Some(chunk) = stream.read_chunk(MAX_MESSAGE_SIZE, true).await
             .map_err(|e| ErrorInfo::new(2016, format!("读取消息失败: {}", e))
                 .with_category(ErrorCategory::Network)
-                .with_severity(ErrorSeverity::Error))?;
+                .with_severity(ErrorSeverity::Error))?
+        {
+            if buffer.len() + chunk.bytes.len() > MAX_MESSAGE_SIZE {
+                let _ = stream.stop(VarInt::from_u32(0));
+                return Err(ErrorInfo::new(2016, format!("读取消息失败: {} 发送的消息超过 {} 字节", remote_addr, MAX_MESSAGE_SIZE))
+                    .with_category(ErrorCategory::Network)
+                    .with_severity(ErrorSeverity::Error));
+            }
+            buffer.extend_from_slice(&chunk.bytes);
+            self.rate_limiter.acquire(remote_addr, RateDirection::Receive, chunk.bytes.len()).await;
+        }
 
         // 反序列化消息
         let message: TransportMessage = serde_json::from_slice(&buffer)
@@ -523,7 +1131,7 @@ impl SecureTransport {
             .set_field("receiver_id".to_string(), serde_json::Value::String(self.device_id.clone()));
 
         // 评估接收策略
-        let policy_result = self.policy_engine.evaluate("default", &policy_context).await
+        let policy_result = self.policy_engine.evaluate(&self.policy_set_id, &policy_context).await
             .map_err(|e| ErrorInfo::new(2018, format!("接收策略评估失败: {}", e))
                 .with_category(ErrorCategory::Configuration)
                 .with_severity(ErrorSeverity::Error))?;
@@ -540,17 +1148,203 @@ impl SecureTransport {
         Ok(message)
     }
 
+    /// 打开双向流
+    ///
+    /// 在安全通道上打开一条原始双向流，供调用方实现自定义流协议。
+    /// 策略只在打开时评估一次，之后的读写不再经过策略检查。
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 连接对象
+    ///
+    /// # 返回值
+    ///
+    /// 返回流的发送端和接收端或错误信息
+    pub async fn open_stream(&self, connection: &Connection) -> TransportResult<(SendStream, RecvStream)> {
+        let remote_addr = connection.remote_address();
+        self.evaluate_stream_policy(self.device_id.clone(), remote_addr, "open_stream").await?;
+
+        let (send, recv) = connection.open_bi().await
+            .map_err(|e| ErrorInfo::new(error_codes::transport::STREAM_OPEN_FAILED, format!("打开双向流失败 {}: {}", remote_addr, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        debug!("已打开双向流: {} -> {}", self.device_id, remote_addr);
+        let _ = self.events.send(TransportEvent::StreamOpened { remote_addr });
+        Ok((send, recv))
+    }
+
+    /// 接受双向流
+    ///
+    /// 等待对端通过 [`SecureTransport::open_stream`] 打开的双向流，策略在接受时评估一次。
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 连接对象
+    ///
+    /// # 返回值
+    ///
+    /// 返回流的发送端和接收端或错误信息
+    pub async fn accept_stream(&self, connection: &Connection) -> TransportResult<(SendStream, RecvStream)> {
+        let remote_addr = connection.remote_address();
+        if self.lock_stream_claims().contains(&connection.stable_id()) {
+            return Err(ErrorInfo::new(error_codes::transport::STREAM_ACCEPT_FAILED, format!("{} 的双向流已由其他接受方处理", remote_addr))
+                .with_category(ErrorCategory::Validation)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        let (send, recv) = connection.accept_bi().await
+            .map_err(|e| ErrorInfo::new(error_codes::transport::STREAM_ACCEPT_FAILED, format!("接受双向流失败 {}: {}", remote_addr, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        self.evaluate_stream_policy("remote".to_string(), remote_addr, "accept_stream").await?;
+
+        debug!("已接受双向流: {} -> {}", remote_addr, self.device_id);
+        let _ = self.events.send(TransportEvent::StreamOpened { remote_addr });
+        Ok((send, recv))
+    }
+
+    /// 声明连接上对端打开的双向流由调用方接受
+    ///
+    /// 同一连接上的双向流只能有一个接受方，声明后 [`SecureTransport::accept_stream`]
+    /// 对该连接返回错误，不会与调用方争抢流；连接关闭后声明自动解除
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 连接对象
+    ///
+    /// # 返回值
+    ///
+    /// 返回是否为首次声明，连接已被声明时返回 `false`
+    pub fn claim_streams(&self, connection: &Connection) -> bool {
+        let connection_id = connection.stable_id();
+        if !self.lock_stream_claims().insert(connection_id) {
+            return false;
+        }
+
+        let stream_claims = Arc::clone(&self.stream_claims);
+        let connection = connection.clone();
+        tokio::spawn(async move {
+            connection.closed().await;
+            stream_claims.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&connection_id);
+        });
+        true
+    }
+
+    /// 获取流声明的锁，锁被毒化时继续使用其中的数据
+    fn lock_stream_claims(&self) -> std::sync::MutexGuard<'_, HashSet<usize>> {
+        self.stream_claims.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 按对端发送限速向流中写入数据
+    ///
+    /// 通过 [`SecureTransport::open_stream`] 或 [`SecureTransport::accept_stream`] 得到的流
+    /// 使用该方法写入时与 [`SecureTransport::send_message`] 共用对端的发送令牌桶
+    ///
+    /// # 参数
+    ///
+    /// * `connection` - 流所属的连接
+    /// * `stream` - 发送流
+    /// * `data` - 要写入的数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回写入结果或错误信息
+    pub async fn write_stream(&self, connection: &Connection, stream: &mut SendStream, data: &[u8]) -> TransportResult<()> {
+        let remote_addr = connection.remote_address();
+        self.rate_limiter.acquire(remote_addr, RateDirection::Send, data.len()).await;
+        stream.write_all(data).await
+            .map_err(|e| ErrorInfo::new(error_codes::transport::STREAM_WRITE_FAILED, format!("写入流失败 {}: {}", remote_addr, e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))
+    }
+
+    /// 设置对端限速，覆盖配置中的默认限速
+    ///
+    /// 发送方向作用于 [`SecureTransport::send_message`] 和 [`SecureTransport::write_stream`]，
+    /// 接收方向作用于 [`SecureTransport::receive_message`]，超出限速时等待而不返回错误
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 对端地址
+    /// * `bytes_per_sec` - 每个方向的速率上限（字节/秒）
+    pub fn set_peer_rate_limit(&self, remote_addr: SocketAddr, bytes_per_sec: u64) {
+        self.rate_limiter.set_peer_rate_limit(remote_addr, bytes_per_sec);
+    }
+
+    /// 清除对端单独设置的限速，恢复为配置中的默认限速
+    pub fn clear_peer_rate_limit(&self, remote_addr: SocketAddr) {
+        self.rate_limiter.clear_peer_rate_limit(remote_addr);
+    }
+
+    /// 获取对端当前生效的限速（字节/秒），不限速时返回 `None`
+    pub fn peer_rate_limit(&self, remote_addr: SocketAddr) -> Option<u64> {
+        self.rate_limiter.peer_rate_limit(remote_addr)
+    }
+
+    /// 评估双向流策略
+    async fn evaluate_stream_policy(&self, requester_id: String, remote_addr: SocketAddr, operation: &str) -> TransportResult<()> {
+        let policy_context = PolicyContext::new()
+            .with_requester_id(requester_id)
+            .with_resource(format!("stream:{}", remote_addr))
+            .with_operation(operation.to_string())
+            .set_field("local_device".to_string(), serde_json::Value::String(self.device_id.clone()));
+
+        let policy_result = self.policy_engine.evaluate(&self.policy_set_id, &policy_context).await
+            .map_err(|e| ErrorInfo::new(error_codes::policy::POLICY_EVALUATION_FAILED, format!("流策略评估失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+
+        if policy_result.final_action != PolicyAction::Allow {
+            return Err(ErrorInfo::new(error_codes::transport::STREAM_POLICY_DENIED, format!("双向流被策略拒绝: {}", policy_result.evaluation_summary))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error));
+        }
+
+        Ok(())
+    }
+
     /// 断开连接
     ///
+    /// 以 [`CloseCode::Shutdown`] 正常关闭连接
+    ///
     /// # 参数
     ///
     /// * `remote_addr` - 远程地址
     pub async fn disconnect(&self, remote_addr: SocketAddr) -> TransportResult<()> {
+        self.disconnect_with(remote_addr, CloseCode::Shutdown.code(), "disconnect").await
+    }
+
+    /// 使用指定的关闭码断开连接
+    ///
+    /// 关闭码和原因作为QUIC应用关闭帧发送给对端
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 远程地址
+    /// * `code` - QUIC应用错误码，标准关闭码见 [`CloseCode::code`]
+    /// * `reason` - 关闭原因
+    ///
+    /// # 返回值
+    ///
+    /// 返回断开结果，连接不存在时返回错误
+    pub async fn disconnect_with(&self, remote_addr: SocketAddr, code: u32, reason: &str) -> TransportResult<()> {
+        // 连接池中的连接与连接表共享同一个QUIC连接，先关闭的一方决定对端收到的关闭码
+        self.pool.remove_with(remote_addr, code, reason).await;
+        self.send_queues.remove(remote_addr);
+        self.rate_limiter.release(remote_addr);
+        self.peer_trust.write().await.remove(&remote_addr);
         let mut connections = self.connections.write().await;
 
         if let Some(connection) = connections.remove(&remote_addr) {
-            connection.close(0u32.into(), b"disconnect");
-            info!("已断开连接: {}", remote_addr);
+            connection.close(code.into(), reason.as_bytes());
+            info!("已断开连接: {} (关闭码: {}, 原因: {})", remote_addr, code, reason);
+            let _ = self.events.send(TransportEvent::Disconnected {
+                remote_addr,
+                code: Some(code),
+                reason: reason.to_string(),
+            });
             Ok(())
         } else {
             Err(ErrorInfo::new(2018, format!("连接不存在: {}", remote_addr))
@@ -570,22 +1364,78 @@ impl SecureTransport {
         }
 
         // 关闭所有连接
+        self.pool.clear().await;
+        self.send_queues.clear();
+        self.peer_trust.write().await.clear();
         {
             let mut connections = self.connections.write().await;
             for (addr, connection) in connections.drain() {
-                connection.close(0u32.into(), b"shutdown");
+                connection.close(CloseCode::Shutdown.code().into(), b"shutdown");
                 debug!("已关闭连接: {}", addr);
+                let _ = self.events.send(TransportEvent::Disconnected {
+                    remote_addr: addr,
+                    code: Some(CloseCode::Shutdown.code()),
+                    reason: "shutdown".to_string(),
+                });
             }
         }
 
         // 关闭端点
         if let Some(endpoint) = &self.endpoint {
-            endpoint.close(0u32.into(), b"shutdown");
+            endpoint.close(CloseCode::Shutdown.code().into(), b"shutdown");
         }
 
         info!("安全传输层已停止");
     }
 
+    /// 设置访问控制使用的策略集合
+    ///
+    /// 策略集合注册到策略引擎后，连接、接受连接和发送消息都将按该集合评估。
+    /// 需要在启动服务器之前调用，已启动的连接接受任务仍使用原策略集合。
+    ///
+    /// # 参数
+    ///
+    /// * `policy_set` - 策略集合
+    ///
+    /// # 返回值
+    ///
+    /// 返回设置结果或错误信息
+    pub async fn set_policy_set(&mut self, policy_set: PolicySet) -> TransportResult<()> {
+        let policy_set_id = policy_set.id.clone();
+        self.policy_engine.add_policy_set(policy_set).await?;
+        self.policy_set_id = policy_set_id;
+        debug!("传输层使用策略集合: {}", self.policy_set_id);
+        Ok(())
+    }
+
+    /// 订阅连接事件
+    ///
+    /// 出站连接和接受的入站连接建立时发出 [`TransportEvent::Connected`]，
+    /// 断开、停止传输层或入站连接被对端关闭时发出 [`TransportEvent::Disconnected`]，
+    /// QUIC握手失败或超时时发出 [`TransportEvent::HandshakeFailed`]，
+    /// 打开或接受双向流时发出 [`TransportEvent::StreamOpened`]。
+    /// 复用连接池中的已有连接不产生事件。
+    pub fn events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.events.subscribe()
+    }
+
+    /// 获取传输层配置
+    pub fn config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    /// 获取访问控制使用的策略集合ID
+    pub fn policy_set_id(&self) -> &str {
+        &self.policy_set_id
+    }
+
+    /// 获取服务器端点实际绑定的本地地址
+    ///
+    /// 服务器未启动时返回 `None`
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.endpoint.as_ref().and_then(|endpoint| endpoint.local_addr().ok())
+    }
+
     /// 获取活跃连接数量
     pub async fn active_connections_count(&self) -> usize {
         self.connections.read().await.len()
@@ -596,6 +1446,50 @@ impl SecureTransport {
         self.connections.read().await.keys().cloned().collect()
     }
 
+    /// 获取与指定地址的活跃连接
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 远程地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接对象，没有该地址的活跃连接时返回 `None`
+    pub async fn connection(&self, remote_addr: SocketAddr) -> Option<Connection> {
+        self.connections.read().await.get(&remote_addr).cloned()
+    }
+
+    /// 获取入站连接的信任级别
+    ///
+    /// 出示了有效客户端证书的连接为 [`TrustLevel::Trusted`]，
+    /// 不要求客户端证书的监听器接受的匿名连接为 [`TrustLevel::Untrusted`]
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 远程地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回信任级别，没有该地址的入站连接时返回 `None`
+    pub async fn peer_trust_level(&self, remote_addr: SocketAddr) -> Option<TrustLevel> {
+        self.peer_trust.read().await.get(&remote_addr).copied()
+    }
+
+    /// 获取与指定地址的QUIC连接统计信息
+    ///
+    /// # 参数
+    ///
+    /// * `remote_addr` - 远程地址
+    ///
+    /// # 返回值
+    ///
+    /// 返回连接统计信息，没有该地址的活跃连接时返回 `None`
+    pub async fn quic_stats(&self, remote_addr: SocketAddr) -> Option<QuicConnStats> {
+        self.connections.read().await
+            .get(&remote_addr)
+            .map(|connection| QuicConnStats::from(connection.stats()))
+    }
+
     /// 获取mTLS统计信息
     pub async fn get_mtls_stats(&self) -> crate::MtlsStats {
         self.mtls_manager.get_stats().await
@@ -606,6 +1500,53 @@ impl SecureTransport {
         self.policy_engine.get_stats().await
     }
 
+    /// 按传输层使用的策略集合评估策略
+    ///
+    /// 上下文设置了 [`PolicyContext::with_cache_bypass`] 时跳过决策缓存重新评估，
+    /// 可用于排查被拒绝的连接
+    ///
+    /// # 参数
+    ///
+    /// * `context` - 策略上下文
+    ///
+    /// # 返回值
+    ///
+    /// 返回评估结果或错误信息
+    pub async fn evaluate_policy(&self, context: &PolicyContext) -> TransportResult<PolicySetEvaluationResult> {
+        self.policy_engine.evaluate(&self.policy_set_id, context).await
+            .map_err(|e| ErrorInfo::new(error_codes::policy::POLICY_EVALUATION_FAILED, format!("策略评估失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))
+    }
+
+    /// 获取策略决策缓存的快照
+    ///
+    /// # 返回值
+    ///
+    /// 返回缓存中未过期的请求和决策，按评估时间从早到晚排列
+    pub async fn policy_decisions_snapshot(&self) -> Vec<(PolicyRequest, PolicyDecision)> {
+        self.policy_engine.cached_decisions().await
+    }
+
+    /// 清除策略决策缓存，之后的请求都会重新评估
+    pub async fn clear_policy_cache(&self) {
+        self.policy_engine.clear_cache().await;
+    }
+
+    /// 初始化证书
+    ///
+    /// 证书存储中没有设备证书时签发，已存在时不做任何操作
+    ///
+    /// # 返回值
+    ///
+    /// 签发失败时返回 [`error_codes::transport::CERT_PROVISION_FAILED`]
+    pub async fn provision_certificates(&self) -> TransportResult<()> {
+        self.mtls_manager.ensure_device_certificate().await
+            .map_err(|e| ErrorInfo::new(error_codes::transport::CERT_PROVISION_FAILED, format!("初始化证书失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))
+    }
+
     /// 手动更新证书
     pub async fn update_certificates(&self) -> TransportResult<()> {
         self.mtls_manager.update_certificate().await
@@ -617,6 +1558,32 @@ impl SecureTransport {
         Ok(())
     }
 
+    /// 使用本地CA交叉签名另一个设备CA
+    ///
+    /// 合并两个集群时使用：交叉签名后本端信任对方CA签发的设备证书，
+    /// 新建的连接和正在监听的服务端立即生效。对方CA证书可通过对端的
+    /// [`CertificateManager::ca_certificate_der`] 获取，双向连接需要双方互相交叉签名
+    ///
+    /// # 参数
+    ///
+    /// * `other_ca_cert_der` - 对方CA证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回交叉证书或错误
+    pub async fn cross_sign(&self, other_ca_cert_der: &[u8]) -> TransportResult<CertificateData> {
+        let certificate = self.mtls_manager.cross_sign(other_ca_cert_der).await?;
+
+        if let Some(endpoint) = &self.endpoint {
+            let mut server_config = self.mtls_manager.get_server_config().await?;
+            server_config.transport_config(Arc::new(self.config.quic_transport_config()));
+            endpoint.set_server_config(Some(server_config));
+        }
+
+        info!("已交叉签名CA证书: {}", certificate.subject_identifier);
+        Ok(certificate)
+    }
+
     /// 验证远程证书
     pub async fn verify_remote_certificate(&self, cert_der: &[u8]) -> TransportResult<bool> {
         let result = self.mtls_manager.verify_remote_certificate(cert_der).await
@@ -631,9 +1598,14 @@ impl SecureTransport {
     /// 启动连接接受器
     async fn start_connection_acceptor(&self, endpoint: Endpoint) {
         let connections = Arc::clone(&self.connections);
+        let peer_trust = Arc::clone(&self.peer_trust);
         let is_running = Arc::clone(&self.is_running);
         let device_id = self.device_id.clone();
         let policy_engine = Arc::clone(&self.policy_engine);
+        let policy_set_id = self.policy_set_id.clone();
+        let events = self.events.clone();
+        let certificate_manager = self.mtls_manager.certificate_manager();
+        let status_queries = Arc::clone(&self.status_queries);
 
         tokio::spawn(async move {
             while *is_running.read().await {
@@ -648,7 +1620,7 @@ impl SecureTransport {
                         .set_field("local_device".to_string(), serde_json::Value::String(device_id.clone()));
 
                     // 评估连接接受策略
-                    let policy_result = policy_engine.evaluate("default", &policy_context).await;
+                    let policy_result = policy_engine.evaluate(&policy_set_id, &policy_context).await;
                     match policy_result {
                         Ok(result) if result.final_action == PolicyAction::Allow => {
                             debug!("连接接受策略评估通过: {} -> {}", remote_addr, device_id);
@@ -670,34 +1642,55 @@ impl SecureTransport {
                         incoming
                     ).await {
                         Ok(Ok(conn)) => {
+                            // 客户端证书已在握手时验证，未出示证书的为匿名连接
+                            let trust_level = if has_peer_certificate(&conn) {
+                                TrustLevel::Trusted
+                            } else {
+                                TrustLevel::Untrusted
+                            };
+
+                            // 先记录握手结果，连接加入连接表后即可被取出并接受流
+                            peer_trust.write().await.insert(remote_addr, trust_level);
+
+                            if let Err(e) = certificate_manager.flush_tofu_pins().await {
+                                warn!("保存TOFU固定记录失败: {}", e);
+                            }
+
+                            cert_status::spawn_status_responder(
+                                conn.clone(),
+                                Arc::clone(&certificate_manager),
+                                Arc::clone(&status_queries),
+                            );
+
                             // 存储连接
                             {
                                 let mut connections = connections.write().await;
                                 connections.insert(remote_addr, conn.clone());
                             }
 
-                            info!("接受新的连接: {}", remote_addr);
-
-                            // 为每个连接启动处理任务
-                            let connections_clone = Arc::clone(&connections);
-                            let is_running_clone = Arc::clone(&is_running);
-
-                            tokio::spawn(async move {
-                                while *is_running_clone.read().await {
-                                    tokio::time::sleep(Duration::from_secs(1)).await;
-                                    // 这里可以添加连接健康检查逻辑
-                                }
+                            info!("接受新的连接: {}, 信任级别: {:?}", remote_addr, trust_level);
+                            let _ = events.send(TransportEvent::Connected { remote_addr, inbound: true });
 
-                                // 清理连接
-                                connections_clone.write().await.remove(&remote_addr);
-                                info!("连接已断开: {}", remote_addr);
-                            });
+                            spawn_close_watcher(
+                                conn,
+                                Arc::clone(&connections),
+                                Arc::clone(&peer_trust),
+                                events.clone(),
+                            );
                         }
                         Ok(Err(e)) => {
                             debug!("接受连接失败: {} -> {}", remote_addr, e);
+                            let _ = events.send(TransportEvent::HandshakeFailed {
+                                remote_addr,
+                                reason: e.to_string(),
+                            });
                         }
                         Err(_) => {
                             debug!("接受连接超时: {}", remote_addr);
+                            let _ = events.send(TransportEvent::HandshakeFailed {
+                                remote_addr,
+                                reason: "接受连接超时".to_string(),
+                            });
                         }
                     }
                 }
@@ -706,12 +1699,106 @@ impl SecureTransport {
     }
 }
 
+/// 为连接启动关闭监视任务
+///
+/// 对端关闭连接或连接因超时等原因断开后，从连接表中移除该连接并发出
+/// [`TransportEvent::Disconnected`]；已由断开或停止清理、或已被新连接替换的连接不再重复发出事件
+///
+/// # 参数
+///
+/// * `conn` - 已建立的连接
+/// * `connections` - 连接表
+/// * `peer_trust` - 对端信任级别
+/// * `events` - 连接事件发送端
+fn spawn_close_watcher(
+    conn: Connection,
+    connections: Arc<RwLock<HashMap<SocketAddr, Connection>>>,
+    peer_trust: Arc<RwLock<HashMap<SocketAddr, TrustLevel>>>,
+    events: broadcast::Sender<TransportEvent>,
+) {
+    tokio::spawn(async move {
+        let remote_addr = conn.remote_address();
+
+        // 停止传输层或关闭端点时连接随之关闭
+        conn.closed().await;
+
+        let removed = {
+            let mut connections = connections.write().await;
+            match connections.get(&remote_addr) {
+                Some(current) if current.stable_id() == conn.stable_id() => connections.remove(&remote_addr),
+                _ => None,
+            }
+        };
+        if removed.is_some() {
+            peer_trust.write().await.remove(&remote_addr);
+            let (code, reason) = close_code_and_reason(conn.close_reason());
+            let _ = events.send(TransportEvent::Disconnected { remote_addr, code, reason });
+        }
+        info!("连接已断开: {}", remote_addr);
+    });
+}
+
+/// 从连接关闭原因中提取应用关闭码和原因
+///
+/// 对端以应用关闭帧关闭时返回其错误码，其他原因（超时、重置等）关闭码为 `None`
+fn close_code_and_reason(error: Option<quinn::ConnectionError>) -> (Option<u32>, String) {
+    match error {
+        Some(quinn::ConnectionError::ApplicationClosed(close)) => (
+            u32::try_from(close.error_code.into_inner()).ok(),
+            String::from_utf8_lossy(&close.reason).to_string(),
+        ),
+        Some(e) => (None, e.to_string()),
+        None => (None, "传输层已停止".to_string()),
+    }
+}
 
 impl Drop for SecureTransport {
     fn drop(&mut self) {
         // 在析构时确保资源被正确释放
         if let Some(endpoint) = &self.endpoint {
-            endpoint.close(0u32.into(), b"drop");
+            endpoint.close(CloseCode::Shutdown.code().into(), b"drop");
         }
     }
 }
+
+/// 检查地址是否为本机地址
+///
+/// 未指定地址（`0.0.0.0` / `::`）和回环地址总是允许的，
+/// 其他地址通过尝试绑定临时UDP套接字确认其属于本机某个网络接口。
+fn ensure_local_address(address: IpAddr) -> TransportResult<()> {
+    if address.is_unspecified() || address.is_loopback() {
+        return Ok(());
+    }
+
+    std::net::UdpSocket::bind(SocketAddr::new(address, 0))
+        .map(|_| ())
+        .map_err(|e| ErrorInfo::new(error_codes::transport::INVALID_BIND_ADDRESS, format!("监听地址不是本机地址: {} ({})", address, e))
+            .with_category(ErrorCategory::Configuration)
+            .with_severity(ErrorSeverity::Error))
+}
+
+/// 判断对端是否在握手时出示了证书
+fn has_peer_certificate(connection: &Connection) -> bool {
+    connection.peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<rustls::pki_types::CertificateDer<'static>>>().ok())
+        .is_some_and(|certs| !certs.is_empty())
+}
+
+/// TCP回退连接的mTLS握手失败
+fn tcp_tls_error(remote_addr: SocketAddr, error: impl std::fmt::Display) -> ErrorInfo {
+    ErrorInfo::new(error_codes::transport::TCP_TLS_HANDSHAKE_FAILED, format!("与 {} 的TCP回退连接TLS握手失败: {}", remote_addr, error))
+        .with_category(ErrorCategory::Authentication)
+        .with_severity(ErrorSeverity::Error)
+}
+
+/// 判断连接错误是否由ALPN协议不匹配引起
+///
+/// 双方没有共同的ALPN协议时，TLS握手以 `no_application_protocol`（120）告警终止。
+fn is_alpn_mismatch(error: &quinn::ConnectionError) -> bool {
+    let alpn_alert = quinn::TransportErrorCode::crypto(120);
+    match error {
+        quinn::ConnectionError::TransportError(e) => e.code == alpn_alert,
+        quinn::ConnectionError::ConnectionClosed(close) => close.error_code == alpn_alert,
+        _ => false,
+    }
+}
@@ -6,6 +6,19 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::Duration;
 
+/// 默认ALPN协议标识
+pub const DEFAULT_ALPN_PROTOCOL: &[u8] = b"bey-transport/1";
+
+/// 默认ALPN协议列表
+fn default_alpn_protocols() -> Vec<Vec<u8>> {
+    vec![DEFAULT_ALPN_PROTOCOL.to_vec()]
+}
+
+/// 默认启用的证书选项
+fn default_true() -> bool {
+    true
+}
+
 /// mTLS配置
 ///
 /// 配置mTLS双向认证的各项参数
@@ -27,6 +40,21 @@ pub struct MtlsConfig {
     pub organization_name: String,
     /// 国家代码
     pub country_code: String,
+    /// ALPN协议标识（按优先级排列），双方没有共同协议时拒绝握手
+    #[serde(default = "default_alpn_protocols")]
+    pub alpn_protocols: Vec<Vec<u8>>,
+    /// 作为服务端时是否要求客户端出示证书，为 `false` 时接受匿名客户端
+    #[serde(default = "default_true")]
+    pub require_client_cert: bool,
+    /// 作为客户端时是否出示设备证书
+    #[serde(default = "default_true")]
+    pub present_client_cert: bool,
+    /// 证书存储中没有设备证书时是否自动签发
+    #[serde(default = "default_true")]
+    pub auto_provision_certs: bool,
+    /// 是否按首次使用信任固定对端证书，证书变更的对端被拒绝
+    #[serde(default)]
+    pub enable_tofu: bool,
 }
 
 impl Default for MtlsConfig {
@@ -40,6 +68,11 @@ impl Default for MtlsConfig {
             device_id_prefix: "bey".to_string(),
             organization_name: "BEY".to_string(),
             country_code: "CN".to_string(),
+            alpn_protocols: default_alpn_protocols(),
+            require_client_cert: true,
+            present_client_cert: true,
+            auto_provision_certs: true,
+            enable_tofu: false,
         }
     }
 }
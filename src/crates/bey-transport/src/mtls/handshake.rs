@@ -0,0 +1,354 @@
+//! # mTLS握手失败分类
+//!
+//! 将rustls证书验证错误归类为可区分的握手失败类型，
+//! 并提供记录失败原因的服务端证书验证器，便于连接失败时给出具体原因。
+//! 验证器把本地CA签发的交叉证书追加到对端提供的中间证书中，
+//! 其他设备CA签发的证书链经由交叉证书连接到本地CA。
+//! 启用首次使用信任（TOFU）时，验证器在证书链验证通过后按证书的通用名称（设备ID）
+//! 检查固定记录，证书变更作为 [`HandshakeFailure::PinMismatch`] 拒绝握手。
+
+use bey_identity::CertificateManager;
+use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::{CertificateError, DigitallySignedStruct, DistinguishedName, SignatureScheme};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// 握手失败类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeFailure {
+    /// 证书已过期
+    Expired,
+    /// 证书尚未生效
+    NotYetValid,
+    /// 证书颁发者不受信任
+    UntrustedRoot,
+    /// 证书名称与目标不匹配
+    NameMismatch,
+    /// 证书已被吊销
+    Revoked,
+    /// 证书签名无效
+    BadSignature,
+    /// 证书用途不符
+    InvalidPurpose,
+    /// 证书与首次使用时固定的证书不一致
+    PinMismatch,
+    /// 其他证书错误
+    Other(String),
+}
+
+impl HandshakeFailure {
+    /// 从rustls错误中分类握手失败
+    ///
+    /// 非证书相关的错误返回 `None`
+    pub fn from_rustls_error(error: &rustls::Error) -> Option<Self> {
+        match error {
+            rustls::Error::InvalidCertificate(cert_error) => Some(Self::from_certificate_error(cert_error)),
+            _ => None,
+        }
+    }
+
+    /// 从证书错误中分类握手失败
+    pub fn from_certificate_error(error: &CertificateError) -> Self {
+        match error {
+            CertificateError::Expired | CertificateError::ExpiredContext { .. } => Self::Expired,
+            CertificateError::NotValidYet | CertificateError::NotValidYetContext { .. } => Self::NotYetValid,
+            CertificateError::UnknownIssuer => Self::UntrustedRoot,
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. } => Self::NameMismatch,
+            CertificateError::Revoked => Self::Revoked,
+            CertificateError::BadSignature => Self::BadSignature,
+            CertificateError::InvalidPurpose | CertificateError::InvalidPurposeContext { .. } => Self::InvalidPurpose,
+            other => Self::Other(format!("{:?}", other)),
+        }
+    }
+
+    /// 失败类型的机器可读标识
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::NotYetValid => "not_yet_valid",
+            Self::UntrustedRoot => "untrusted_root",
+            Self::NameMismatch => "name_mismatch",
+            Self::Revoked => "revoked",
+            Self::BadSignature => "bad_signature",
+            Self::InvalidPurpose => "invalid_purpose",
+            Self::PinMismatch => "pin_mismatch",
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// 转换为错误信息
+    ///
+    /// # 参数
+    ///
+    /// * `code` - 错误代码
+    /// * `peer` - 对端描述（地址或设备ID）
+    pub fn to_error_info(&self, code: u32, peer: &str) -> ErrorInfo {
+        // 证书变更可能意味着中间人攻击
+        let severity = if *self == Self::PinMismatch { ErrorSeverity::Critical } else { ErrorSeverity::Error };
+        ErrorInfo::new(code, format!("证书验证失败: {}", self))
+            .with_category(ErrorCategory::Authentication)
+            .with_severity(severity)
+            .with_context(format!("handshake_failure={}", self.kind()))
+            .with_context(format!("peer={}", peer))
+    }
+}
+
+impl fmt::Display for HandshakeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expired => write!(f, "已过期"),
+            Self::NotYetValid => write!(f, "尚未生效"),
+            Self::UntrustedRoot => write!(f, "颁发者不受信任"),
+            Self::NameMismatch => write!(f, "名称不匹配"),
+            Self::Revoked => write!(f, "已吊销"),
+            Self::BadSignature => write!(f, "签名无效"),
+            Self::InvalidPurpose => write!(f, "用途不符"),
+            Self::PinMismatch => write!(f, "与固定的证书不一致"),
+            Self::Other(detail) => write!(f, "{}", detail),
+        }
+    }
+}
+
+/// 证书的设备ID（主题通用名称）
+fn certificate_device_id(cert: &CertificateDer<'_>) -> Option<String> {
+    let (_, parsed) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+    let common_name = parsed.subject().iter_common_name().next()?;
+    common_name.as_str().ok().map(str::to_string)
+}
+
+/// 按首次使用信任检查已通过证书链验证的对端证书
+///
+/// 只在内存中检查，第一次见到的证书由连接建立后保存
+fn check_tofu_pin(certificate_manager: &CertificateManager, end_entity: &CertificateDer<'_>) -> Result<(), HandshakeFailure> {
+    let device_id = certificate_device_id(end_entity)
+        .ok_or_else(|| HandshakeFailure::Other("证书缺少设备ID".to_string()))?;
+    certificate_manager.check_tofu(&device_id, end_entity.as_ref())
+        .map(|_| ())
+        .map_err(|_| HandshakeFailure::PinMismatch)
+}
+
+/// 拒绝握手的TOFU验证错误
+fn pin_mismatch_error() -> rustls::Error {
+    rustls::Error::InvalidCertificate(CertificateError::ApplicationVerificationFailure)
+}
+
+/// 对端提供的中间证书后追加本地的交叉证书
+fn with_local_intermediates<'a>(
+    intermediates: &[CertificateDer<'a>],
+    local: &[CertificateDer<'static>],
+) -> Vec<CertificateDer<'a>> {
+    intermediates.iter().cloned().chain(local.iter().cloned()).collect()
+}
+
+/// 记录失败原因的服务端证书验证器
+///
+/// 包装实际的验证器，在验证失败时记录分类后的失败原因
+#[derive(Debug)]
+pub struct RecordingServerVerifier {
+    /// 实际的验证器
+    inner: Arc<dyn ServerCertVerifier>,
+    /// 最近一次握手失败
+    last_failure: Arc<Mutex<Option<HandshakeFailure>>>,
+    /// 本地CA签发的交叉证书
+    intermediates: Vec<CertificateDer<'static>>,
+    /// 启用首次使用信任时检查固定记录的证书管理器
+    tofu: Option<TofuPins>,
+}
+
+/// 验证器使用的TOFU固定记录
+#[derive(Clone)]
+struct TofuPins(Arc<CertificateManager>);
+
+impl fmt::Debug for TofuPins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TofuPins")
+    }
+}
+
+impl RecordingServerVerifier {
+    /// 创建记录验证器
+    ///
+    /// # 参数
+    ///
+    /// * `inner` - 实际的验证器
+    /// * `last_failure` - 共享的失败记录
+    pub fn new(inner: Arc<dyn ServerCertVerifier>, last_failure: Arc<Mutex<Option<HandshakeFailure>>>) -> Self {
+        Self { inner, last_failure, intermediates: Vec::new(), tofu: None }
+    }
+
+    /// 验证时把本地CA签发的交叉证书作为候选中间证书
+    ///
+    /// # 参数
+    ///
+    /// * `intermediates` - 交叉证书（DER格式）
+    pub fn with_intermediates(mut self, intermediates: Vec<CertificateDer<'static>>) -> Self {
+        self.intermediates = intermediates;
+        self
+    }
+
+    /// 证书链验证通过后再按首次使用信任检查服务端证书
+    ///
+    /// # 参数
+    ///
+    /// * `certificate_manager` - 保存固定记录的证书管理器
+    pub fn with_tofu(mut self, certificate_manager: Arc<CertificateManager>) -> Self {
+        self.tofu = Some(TofuPins(certificate_manager));
+        self
+    }
+}
+
+impl ServerCertVerifier for RecordingServerVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let intermediates = with_local_intermediates(intermediates, &self.intermediates);
+        let result = self.inner.verify_server_cert(end_entity, &intermediates, server_name, ocsp_response, now)
+            .map_err(|e| (HandshakeFailure::from_rustls_error(&e), e))
+            .and_then(|verified| match &self.tofu {
+                Some(TofuPins(certificate_manager)) => check_tofu_pin(certificate_manager, end_entity)
+                    .map(|_| verified)
+                    .map_err(|failure| (Some(failure), pin_mismatch_error())),
+                None => Ok(verified),
+            });
+
+        result.map_err(|(failure, e)| {
+            if let (Some(failure), Ok(mut last_failure)) = (failure, self.last_failure.lock()) {
+                *last_failure = Some(failure);
+            }
+            e
+        })
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// 叠加本地信任信息的客户端证书验证器
+///
+/// 包装实际的客户端证书验证器，验证时追加本地的交叉证书，
+/// 启用首次使用信任时在证书链验证通过后再检查固定记录；
+/// 匿名客户端不出示证书，不受影响
+#[derive(Debug)]
+pub struct LocalClientVerifier {
+    /// 实际的验证器
+    inner: Arc<dyn ClientCertVerifier>,
+    /// 本地CA签发的交叉证书
+    intermediates: Vec<CertificateDer<'static>>,
+    /// 启用首次使用信任时检查固定记录的证书管理器
+    tofu: Option<TofuPins>,
+}
+
+impl LocalClientVerifier {
+    /// 创建验证器
+    ///
+    /// # 参数
+    ///
+    /// * `inner` - 实际的验证器
+    pub fn new(inner: Arc<dyn ClientCertVerifier>) -> Self {
+        Self { inner, intermediates: Vec::new(), tofu: None }
+    }
+
+    /// 验证时把本地CA签发的交叉证书作为候选中间证书
+    ///
+    /// # 参数
+    ///
+    /// * `intermediates` - 交叉证书（DER格式）
+    pub fn with_intermediates(mut self, intermediates: Vec<CertificateDer<'static>>) -> Self {
+        self.intermediates = intermediates;
+        self
+    }
+
+    /// 证书链验证通过后再按首次使用信任检查客户端证书
+    ///
+    /// # 参数
+    ///
+    /// * `certificate_manager` - 保存固定记录的证书管理器
+    pub fn with_tofu(mut self, certificate_manager: Arc<CertificateManager>) -> Self {
+        self.tofu = Some(TofuPins(certificate_manager));
+        self
+    }
+}
+
+impl ClientCertVerifier for LocalClientVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.inner.offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        self.inner.client_auth_mandatory()
+    }
+
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        self.inner.root_hint_subjects()
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        let intermediates = with_local_intermediates(intermediates, &self.intermediates);
+        let verified = self.inner.verify_client_cert(end_entity, &intermediates, now)?;
+        match &self.tofu {
+            Some(TofuPins(certificate_manager)) => match check_tofu_pin(certificate_manager, end_entity) {
+                Ok(()) => Ok(verified),
+                Err(failure) => {
+                    warn!("拒绝客户端证书: {}", failure);
+                    Err(pin_mismatch_error())
+                }
+            },
+            None => Ok(verified),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
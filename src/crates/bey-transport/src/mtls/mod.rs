@@ -3,6 +3,8 @@
 //! 提供mTLS双向认证管理功能
 
 pub mod config;
+pub mod handshake;
 
 // 重新导出常用类型
-pub use config::{MtlsConfig, MtlsStats};
+pub use config::{MtlsConfig, MtlsStats, DEFAULT_ALPN_PROTOCOL};
+pub use handshake::{HandshakeFailure, RecordingServerVerifier, LocalClientVerifier};
@@ -1,7 +1,7 @@
 //! # 完整的mTLS双向认证管理器
 //!
 //! 完全依赖 bey_identity 证书管理模块，提供企业级的双向TLS认证功能。
-//! 所有证书相关的操作都由证书管理模块处理，本模块只负责配置QUIC连接。
+//! 所有证书相关的操作都由证书管理模块处理，本模块只负责配置QUIC连接和TCP回退的TLS连接。
 //!
 //! ## 核心特性
 //!
@@ -14,9 +14,13 @@
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use quinn::crypto::rustls::{QuicServerConfig, QuicClientConfig};
-use rustls::{pki_types::CertificateDer, RootCertStore};
-use std::sync::Arc;
-use std::time::Duration;
+use rustls::client::danger::ServerCertVerifier;
+use rustls::client::WebPkiServerVerifier;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::server::WebPkiClientVerifier;
+use rustls::RootCertStore;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 use tracing::{info, debug};
 
@@ -25,6 +29,7 @@ use bey_identity::{CertificateManager, CertificateData};
 
 // 使用mtls模块中的配置类型
 pub use crate::mtls::{MtlsConfig, MtlsStats};
+use crate::mtls::{HandshakeFailure, LocalClientVerifier, RecordingServerVerifier};
 
 // 使用错误代码常量
 use crate::error_codes::mtls as mtls_errors;
@@ -32,7 +37,6 @@ use crate::error_codes::mtls as mtls_errors;
 /// 完整的mTLS管理器
 pub struct CompleteMtlsManager {
     /// 配置信息
-    #[allow(dead_code)]
     config: Arc<MtlsConfig>,
     /// 证书管理器
     certificate_manager: Arc<CertificateManager>,
@@ -44,6 +48,8 @@ pub struct CompleteMtlsManager {
     stats: Arc<RwLock<MtlsStats>>,
     /// 设备ID
     device_id: String,
+    /// 最近一次握手失败
+    last_handshake_failure: Arc<Mutex<Option<HandshakeFailure>>>,
 }
 
 impl CompleteMtlsManager {
@@ -51,6 +57,9 @@ impl CompleteMtlsManager {
     pub async fn new(config: MtlsConfig, device_id: String) -> Result<Self, ErrorInfo> {
         info!("初始化完整mTLS管理器，设备ID: {}", device_id);
 
+        // 构建Rustls配置前安装 CryptoProvider（使用 ring），多次调用是安全的
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
         // 验证配置
         Self::validate_config(&config)?;
 
@@ -83,10 +92,13 @@ impl CompleteMtlsManager {
             client_config_cache: Arc::new(RwLock::new(None)),
             stats: Arc::new(RwLock::new(MtlsStats::default())),
             device_id,
+            last_handshake_failure: Arc::new(Mutex::new(None)),
         };
 
-        // 确保设备证书存在
-        manager.ensure_device_certificate().await?;
+        // 确保设备证书存在，不自动签发时由调用方初始化证书
+        if manager.config.auto_provision_certs {
+            manager.ensure_device_certificate().await?;
+        }
 
         info!("完整mTLS管理器初始化完成");
         Ok(manager)
@@ -109,8 +121,8 @@ impl CompleteMtlsManager {
         Ok(())
     }
 
-    /// 确保设备证书存在
-    async fn ensure_device_certificate(&self) -> Result<(), ErrorInfo> {
+    /// 确保设备证书存在，不存在时签发
+    pub async fn ensure_device_certificate(&self) -> Result<(), ErrorInfo> {
         // 检查设备证书是否存在
         match self.certificate_manager.get_device_certificate(&self.device_id).await {
             Ok(Some(_)) => {
@@ -221,8 +233,38 @@ impl CompleteMtlsManager {
         Ok(client_config)
     }
 
+    /// 获取TCP回退连接的TLS服务器配置
+    ///
+    /// 与QUIC服务器配置使用同样的设备证书和客户端证书验证器（同一CA、交叉证书和TOFU固定），
+    /// 回退连接很少建立，不缓存
+    pub async fn get_tls_server_config(&self) -> Result<Arc<rustls::ServerConfig>, ErrorInfo> {
+        Ok(Arc::new(self.generate_rustls_server_config().await?))
+    }
+
+    /// 获取TCP回退连接的TLS客户端配置
+    ///
+    /// 与QUIC客户端配置使用同样的服务端证书验证器和客户端证书，回退连接很少建立，不缓存
+    pub async fn get_tls_client_config(&self) -> Result<Arc<rustls::ClientConfig>, ErrorInfo> {
+        Ok(Arc::new(self.generate_rustls_client_config().await?))
+    }
+
     /// 生成服务器配置
     async fn generate_server_config(&self) -> Result<quinn::ServerConfig, ErrorInfo> {
+        let rustls_server_config = self.generate_rustls_server_config().await?;
+
+        // 转换为Quinn配置
+        let quinn_server_config = quinn::ServerConfig::with_crypto(Arc::new(
+            QuicServerConfig::try_from(rustls_server_config)
+                .map_err(|e| ErrorInfo::new(5015, format!("转换为Quinn服务器配置失败: {:?}", e))
+                    .with_category(ErrorCategory::Configuration)
+                    .with_severity(ErrorSeverity::Error))?
+        ));
+
+        Ok(quinn_server_config)
+    }
+
+    /// 生成rustls服务器配置
+    async fn generate_rustls_server_config(&self) -> Result<rustls::ServerConfig, ErrorInfo> {
         // 获取设备证书作为服务器证书
         let server_cert = self.certificate_manager
             .get_device_certificate(&self.device_id)
@@ -273,27 +315,52 @@ impl CompleteMtlsManager {
 
         let cert_chain = vec![server_cert_der];
 
+        // 客户端证书由同一CA验证；不要求客户端证书时仍验证出示的证书，但接受匿名客户端
+        let client_verifier_builder = WebPkiClientVerifier::builder(Arc::new(root_store));
+        let client_verifier_builder = if self.config.require_client_cert {
+            client_verifier_builder
+        } else {
+            client_verifier_builder.allow_unauthenticated()
+        };
+        let client_verifier = client_verifier_builder.build()
+            .map_err(|e| ErrorInfo::new(mtls_errors::GENERATE_SERVER_CONFIG_FAILED, format!("创建客户端证书验证器失败: {}", e))
+                .with_category(ErrorCategory::Configuration)
+                .with_severity(ErrorSeverity::Error))?;
+        let client_verifier = LocalClientVerifier::new(client_verifier)
+            .with_intermediates(self.local_intermediates(mtls_errors::GENERATE_SERVER_CONFIG_FAILED).await?);
+        let client_verifier = if self.config.enable_tofu {
+            client_verifier.with_tofu(Arc::clone(&self.certificate_manager))
+        } else {
+            client_verifier
+        };
+
         // 创建rustls服务器配置
-        let rustls_server_config = rustls::ServerConfig::builder()
-            .with_no_client_auth()
+        let mut rustls_server_config = rustls::ServerConfig::builder()
+            .with_client_cert_verifier(Arc::new(client_verifier))
             .with_single_cert(cert_chain, private_key)
             .map_err(|e| ErrorInfo::new(5015, format!("创建服务器配置失败: {}", e))
                 .with_category(ErrorCategory::Configuration)
                 .with_severity(ErrorSeverity::Error))?;
+        rustls_server_config.alpn_protocols = self.config.alpn_protocols.clone();
 
-        // 转换为Quinn配置
-        let quinn_server_config = quinn::ServerConfig::with_crypto(Arc::new(
-            QuicServerConfig::try_from(rustls_server_config)
-                .map_err(|e| ErrorInfo::new(5015, format!("转换为Quinn服务器配置失败: {:?}", e))
-                    .with_category(ErrorCategory::Configuration)
-                    .with_severity(ErrorSeverity::Error))?
-        ));
+        Ok(rustls_server_config)
+    }
 
-        Ok(quinn_server_config)
+    /// 本地CA签发的交叉证书，握手时作为候选中间证书
+    ///
+    /// # 参数
+    ///
+    /// * `code` - 读取失败时的错误代码
+    async fn local_intermediates(&self, code: u32) -> Result<Vec<CertificateDer<'static>>, ErrorInfo> {
+        let intermediates = self.certificate_manager.local_cross_certificates().await
+            .map_err(|e| ErrorInfo::new(code, format!("读取交叉证书失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+        Ok(intermediates.into_iter().map(CertificateDer::from).collect())
     }
 
-    /// 生成客户端配置
-    async fn generate_client_config(&self) -> Result<quinn::ClientConfig, ErrorInfo> {
+    /// 构建客户端信任的CA证书存储
+    async fn client_root_store(&self) -> Result<RootCertStore, ErrorInfo> {
         // 获取本地设备证书来找到CA证书
         let device_cert = self.certificate_manager
             .get_device_certificate(&self.device_id)
@@ -325,10 +392,12 @@ impl CompleteMtlsManager {
             }
         }
 
-        // 创建rustls客户端配置
-        let rustls_client_config = rustls::ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
+        Ok(root_store)
+    }
+
+    /// 生成客户端配置
+    async fn generate_client_config(&self) -> Result<quinn::ClientConfig, ErrorInfo> {
+        let rustls_client_config = self.generate_rustls_client_config().await?;
 
         // 转换为Quinn配置
         let quinn_client_config = quinn::ClientConfig::new(Arc::new(
@@ -341,6 +410,72 @@ impl CompleteMtlsManager {
         Ok(quinn_client_config)
     }
 
+    /// 生成rustls客户端配置
+    async fn generate_rustls_client_config(&self) -> Result<rustls::ClientConfig, ErrorInfo> {
+        let root_store = Arc::new(self.client_root_store().await?);
+        let intermediates = self.local_intermediates(mtls_errors::GENERATE_CLIENT_CONFIG_FAILED).await?;
+
+        // 创建rustls客户端配置，使用记录失败原因的验证器
+        let builder = match WebPkiServerVerifier::builder(Arc::clone(&root_store)).build() {
+            Ok(verifier) => {
+                let verifier = RecordingServerVerifier::new(verifier, Arc::clone(&self.last_handshake_failure))
+                    .with_intermediates(intermediates);
+                let verifier = if self.config.enable_tofu {
+                    verifier.with_tofu(Arc::clone(&self.certificate_manager))
+                } else {
+                    verifier
+                };
+                rustls::ClientConfig::builder()
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(verifier))
+            }
+            Err(e) => {
+                debug!("无法创建证书验证器，使用默认验证: {}", e);
+                rustls::ClientConfig::builder()
+                    .with_root_certificates(root_store)
+            }
+        };
+
+        // 出示设备证书供服务端验证
+        let mut rustls_client_config = match self.client_identity().await? {
+            Some((cert_chain, private_key)) => builder.with_client_auth_cert(cert_chain, private_key)
+                .map_err(|e| ErrorInfo::new(mtls_errors::GENERATE_CLIENT_CONFIG_FAILED, format!("设置客户端证书失败: {}", e))
+                    .with_category(ErrorCategory::Configuration)
+                    .with_severity(ErrorSeverity::Error))?,
+            None => builder.with_no_client_auth(),
+        };
+        rustls_client_config.alpn_protocols = self.config.alpn_protocols.clone();
+
+        Ok(rustls_client_config)
+    }
+
+    /// 获取客户端出示的证书链和私钥
+    ///
+    /// 配置为不出示证书或设备证书缺少私钥时返回 `None`，以匿名客户端身份连接
+    async fn client_identity(&self) -> Result<Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>, ErrorInfo> {
+        if !self.config.present_client_cert {
+            return Ok(None);
+        }
+
+        let device_cert = self.certificate_manager
+            .get_device_certificate(&self.device_id)
+            .await
+            .map_err(|e| ErrorInfo::new(5015, format!("获取设备证书失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+
+        let Some(cert_data) = device_cert else {
+            return Ok(None);
+        };
+        let Some(private_key_pem) = cert_data.private_key_pem.as_ref() else {
+            return Ok(None);
+        };
+
+        let cert_der = CertificateDer::from(self.pem_to_der(&cert_data.certificate_pem)?);
+        let private_key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(self.pem_to_der(private_key_pem)?));
+        Ok(Some((vec![cert_der], private_key)))
+    }
+
     /// PEM格式转DER格式
     fn pem_to_der(&self, pem_data: &str) -> Result<Vec<u8>, ErrorInfo> {
         // 使用base64解码PEM格式
@@ -397,6 +532,52 @@ impl CompleteMtlsManager {
         Ok(is_valid)
     }
 
+    /// 取出最近一次握手失败的原因
+    ///
+    /// # 返回值
+    ///
+    /// 返回失败原因，取出后记录被清空
+    pub fn take_handshake_failure(&self) -> Option<HandshakeFailure> {
+        self.last_handshake_failure.lock().ok().and_then(|mut failure| failure.take())
+    }
+
+    /// 按客户端握手规则验证服务端证书
+    ///
+    /// # 参数
+    ///
+    /// * `cert_der` - 服务端证书（DER格式）
+    /// * `server_name` - 期望的服务端名称
+    /// * `now` - 验证时间
+    ///
+    /// # 返回值
+    ///
+    /// 验证通过返回 Ok(())，失败返回分类后的失败原因
+    pub async fn classify_server_certificate(
+        &self,
+        cert_der: &[u8],
+        server_name: &str,
+        now: SystemTime,
+    ) -> Result<(), HandshakeFailure> {
+        let root_store = self.client_root_store().await
+            .map_err(|e| HandshakeFailure::Other(e.to_string()))?;
+        let intermediates = self.local_intermediates(mtls_errors::CERT_VERIFICATION_FAILED).await
+            .map_err(|e| HandshakeFailure::Other(e.to_string()))?;
+
+        let verifier = WebPkiServerVerifier::builder(Arc::new(root_store)).build()
+            .map_err(|_| HandshakeFailure::UntrustedRoot)?;
+
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|_| HandshakeFailure::NameMismatch)?;
+
+        let now = UnixTime::since_unix_epoch(now.duration_since(UNIX_EPOCH).unwrap_or_default());
+        let end_entity = CertificateDer::from(cert_der.to_vec());
+
+        verifier.verify_server_cert(&end_entity, &intermediates, &server_name, &[], now)
+            .map(|_| ())
+            .map_err(|e| HandshakeFailure::from_rustls_error(&e)
+                .unwrap_or_else(|| HandshakeFailure::Other(e.to_string())))
+    }
+
     /// DER格式转PEM格式
     fn der_to_pem(&self, der_data: &[u8]) -> String {
         // 使用base64编码和PEM格式包装
@@ -453,6 +634,31 @@ impl CompleteMtlsManager {
         info!("mTLS配置缓存已清除");
     }
 
+    /// 使用本地CA交叉签名另一个设备CA
+    ///
+    /// 签发后清除配置缓存，之后生成的配置在握手时接受经由交叉证书连接到本地CA的证书链
+    ///
+    /// # 参数
+    ///
+    /// * `other_ca_cert_der` - 对方CA证书（DER格式）
+    ///
+    /// # 返回值
+    ///
+    /// 返回交叉证书或错误
+    pub async fn cross_sign(&self, other_ca_cert_der: &[u8]) -> Result<CertificateData, ErrorInfo> {
+        let certificate = self.certificate_manager.cross_sign(other_ca_cert_der).await
+            .map_err(|e| ErrorInfo::new(mtls_errors::CROSS_SIGN_FAILED, format!("交叉签名CA证书失败: {}", e))
+                .with_category(ErrorCategory::System)
+                .with_severity(ErrorSeverity::Error))?;
+        self.clear_config_cache().await;
+        Ok(certificate)
+    }
+
+    /// 获取证书管理器
+    pub fn certificate_manager(&self) -> Arc<CertificateManager> {
+        Arc::clone(&self.certificate_manager)
+    }
+
     /// 获取统计信息
     pub async fn get_stats(&self) -> MtlsStats {
         self.stats.read().await.clone()
@@ -557,6 +763,11 @@ mod tests {
             device_id_prefix: "test".to_string(),
             organization_name: "Test BEY".to_string(),
             country_code: "CN".to_string(),
+            alpn_protocols: vec![b"bey-test".to_vec()],
+            require_client_cert: true,
+            present_client_cert: true,
+            auto_provision_certs: true,
+            enable_tofu: false,
         };
         (config, temp_dir)
     }
@@ -649,6 +860,53 @@ mod tests {
         assert!(stats.connections_established > 0, "应该有连接建立统计");
     }
 
+    #[tokio::test]
+    async fn test_handshake_failure_expired_certificate() {
+        init_logging();
+        let (config, _temp_dir) = create_test_mtls_config().await;
+        let manager = CompleteMtlsManager::new(config, "test-device-006".to_string()).await.unwrap();
+
+        let cert_data = manager.get_local_certificate_info().await.unwrap();
+        let cert_der = manager.pem_to_der(&cert_data.certificate_pem).unwrap();
+
+        // 当前时间验证通过
+        manager.classify_server_certificate(&cert_der, "test-device-006.bey.local", SystemTime::now()).await
+            .expect("有效证书应该验证通过");
+
+        // 超过有效期后验证
+        let future = SystemTime::now() + Duration::from_secs(86400 * 400);
+        let failure = manager.classify_server_certificate(&cert_der, "test-device-006.bey.local", future).await
+            .expect_err("过期证书应该验证失败");
+        assert_eq!(failure, HandshakeFailure::Expired);
+
+        let error = failure.to_error_info(2012, "127.0.0.1:8080");
+        assert_eq!(error.message(), "证书验证失败: 已过期");
+        assert!(error.context().iter().any(|c| c == "handshake_failure=expired"));
+    }
+
+    #[tokio::test]
+    async fn test_handshake_failure_untrusted_certificate() {
+        init_logging();
+        let (config, _temp_dir) = create_test_mtls_config().await;
+        let manager = CompleteMtlsManager::new(config, "test-device-007".to_string()).await.unwrap();
+
+        // 由另一个CA签发的证书（不同的CA名称，避免与本地CA混淆）
+        let (mut other_config, _other_temp_dir) = create_test_mtls_config().await;
+        other_config.organization_name = "Other BEY".to_string();
+        let other_manager = CompleteMtlsManager::new(other_config, "test-device-008".to_string()).await.unwrap();
+        let other_cert = other_manager.get_local_certificate_info().await.unwrap();
+        let other_der = other_manager.pem_to_der(&other_cert.certificate_pem).unwrap();
+
+        let failure = manager.classify_server_certificate(&other_der, "test-device-008.bey.local", SystemTime::now()).await
+            .expect_err("不受信任的证书应该验证失败");
+        assert_eq!(failure, HandshakeFailure::UntrustedRoot);
+        assert_ne!(failure, HandshakeFailure::Expired);
+
+        let error = failure.to_error_info(2012, "127.0.0.1:8080");
+        assert_eq!(error.message(), "证书验证失败: 颁发者不受信任");
+        assert!(error.context().iter().any(|c| c == "handshake_failure=untrusted_root"));
+    }
+
     #[tokio::test]
     async fn test_config_cache_operations() {
         init_logging();
@@ -14,7 +14,7 @@
 
 use error::{ErrorInfo, ErrorCategory, ErrorSeverity};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
@@ -342,6 +342,9 @@ pub struct PolicyContext {
     pub timestamp: SystemTime,
     /// 上下文标签
     pub tags: HashSet<String>,
+    /// 是否跳过决策缓存重新评估（评估结果仍会写入缓存）
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 impl PolicyContext {
@@ -354,6 +357,7 @@ impl PolicyContext {
             operation: None,
             timestamp: SystemTime::now(),
             tags: HashSet::new(),
+            bypass_cache: false,
         }
     }
 
@@ -391,6 +395,12 @@ impl PolicyContext {
         self.tags.insert(tag);
         self
     }
+
+    /// 跳过决策缓存，强制重新评估
+    pub fn with_cache_bypass(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
 }
 
 impl Default for PolicyContext {
@@ -560,6 +570,61 @@ pub struct PolicySetEvaluationResult {
     pub total_execution_time_ms: u64,
 }
 
+/// 策略评估请求
+///
+/// 由策略集合ID和上下文中参与评估的内容组成，不包含时间戳，
+/// 内容相同的请求共享同一个缓存的决策
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRequest {
+    /// 策略集合ID
+    pub policy_set_id: String,
+    /// 请求者ID
+    pub requester_id: Option<String>,
+    /// 目标资源
+    pub resource: Option<String>,
+    /// 操作类型
+    pub operation: Option<String>,
+    /// 上下文数据
+    pub data: BTreeMap<String, serde_json::Value>,
+    /// 上下文标签
+    pub tags: BTreeSet<String>,
+}
+
+impl PolicyRequest {
+    /// 从策略上下文创建评估请求
+    pub fn new(policy_set_id: &str, context: &PolicyContext) -> Self {
+        Self {
+            policy_set_id: policy_set_id.to_string(),
+            requester_id: context.requester_id.clone(),
+            resource: context.resource.clone(),
+            operation: context.operation.clone(),
+            data: context.data.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            tags: context.tags.iter().cloned().collect(),
+        }
+    }
+
+    /// 缓存键，内容相同的请求得到相同的键
+    fn cache_key(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| format!("{:?}", self))
+    }
+}
+
+/// 缓存的策略决策
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    /// 评估结果
+    pub result: PolicySetEvaluationResult,
+    /// 评估时间
+    pub decided_at: SystemTime,
+}
+
+impl PolicyDecision {
+    /// 决策是否已超过缓存有效期
+    fn is_expired(&self, ttl: Duration) -> bool {
+        self.decided_at.elapsed().map(|age| age >= ttl).unwrap_or(false)
+    }
+}
+
 /// 策略引擎统计信息
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PolicyEngineStats {
@@ -588,7 +653,7 @@ pub struct CompletePolicyEngine {
     /// 策略集合
     policy_sets: Arc<RwLock<HashMap<String, PolicySet>>>,
     /// 评估缓存
-    evaluation_cache: Arc<RwLock<HashMap<String, PolicySetEvaluationResult>>>,
+    evaluation_cache: Arc<RwLock<HashMap<String, (PolicyRequest, PolicyDecision)>>>,
     /// 缓存TTL
     cache_ttl: Duration,
     /// 最大缓存条目数
@@ -707,16 +772,16 @@ impl CompletePolicyEngine {
     ) -> Result<PolicySetEvaluationResult, ErrorInfo> {
         let start_time = std::time::Instant::now();
 
-        // 生成缓存键
-        let cache_key = if self.config.enable_cache {
-            Some(self.generate_cache_key(policy_set_id, context))
+        // 生成缓存请求
+        let request = if self.config.enable_cache {
+            Some(PolicyRequest::new(policy_set_id, context))
         } else {
             None
         };
 
-        // 检查缓存
-        if let Some(ref cache_key) = cache_key {
-            if let Some(cached_result) = self.get_cached_result(cache_key).await {
+        // 检查缓存，跳过缓存的请求直接重新评估
+        if let Some(request) = request.as_ref().filter(|_| !context.bypass_cache) {
+            if let Some(cached_result) = self.get_cached_result(&request.cache_key()).await {
                 {
                     let mut stats = self.stats.write().await;
                     stats.cache_hits += 1;
@@ -742,8 +807,8 @@ impl CompletePolicyEngine {
         let result = policy_set.evaluate(context)?;
 
         // 缓存结果
-        if let Some(ref cache_key) = cache_key {
-            self.cache_result(cache_key.clone(), result.clone()).await;
+        if let Some(request) = request {
+            self.cache_result(request, result.clone()).await;
         }
 
         // 更新性能统计
@@ -823,30 +888,21 @@ impl CompletePolicyEngine {
         Ok(results)
     }
 
-    /// 生成缓存键
-    fn generate_cache_key(&self, policy_set_id: &str, context: &PolicyContext) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let mut hasher = DefaultHasher::new();
-        policy_set_id.hash(&mut hasher);
-
-        // 序列化上下文数据用于哈希计算
-        if let Ok(context_json) = serde_json::to_string(context) {
-            context_json.hash(&mut hasher);
-        }
-
-        format!("policy_eval_{:x}", hasher.finish())
-    }
-
-    /// 获取缓存结果
+    /// 获取未过期的缓存结果
     async fn get_cached_result(&self, cache_key: &str) -> Option<PolicySetEvaluationResult> {
         let cache = self.evaluation_cache.read().await;
-        cache.get(cache_key).cloned()
+        cache.get(cache_key)
+            .filter(|(_, decision)| !decision.is_expired(self.cache_ttl))
+            .map(|(_, decision)| decision.result.clone())
     }
 
     /// 缓存结果
-    async fn cache_result(&self, cache_key: String, result: PolicySetEvaluationResult) {
+    async fn cache_result(&self, request: PolicyRequest, result: PolicySetEvaluationResult) {
+        let cache_key = request.cache_key();
+        let decision = PolicyDecision {
+            result,
+            decided_at: SystemTime::now(),
+        };
         let mut cache = self.evaluation_cache.write().await;
 
         // 检查缓存大小限制
@@ -857,11 +913,26 @@ impl CompletePolicyEngine {
             }
         }
 
-        cache.insert(cache_key, result);
+        cache.insert(cache_key, (request, decision));
+    }
+
+    /// 获取缓存中未过期的决策
+    ///
+    /// # 返回值
+    ///
+    /// 返回按评估时间从早到晚排列的请求和决策
+    pub async fn cached_decisions(&self) -> Vec<(PolicyRequest, PolicyDecision)> {
+        let cache = self.evaluation_cache.read().await;
+        let mut decisions: Vec<(PolicyRequest, PolicyDecision)> = cache.values()
+            .filter(|(_, decision)| !decision.is_expired(self.cache_ttl))
+            .cloned()
+            .collect();
+        decisions.sort_by_key(|(_, decision)| decision.decided_at);
+        decisions
     }
 
     /// 清除缓存
-    async fn clear_cache(&self) {
+    pub async fn clear_cache(&self) {
         let mut cache = self.evaluation_cache.write().await;
         cache.clear();
         debug!("策略评估缓存已清除");
@@ -0,0 +1,322 @@
+//! # 连接池管理模块
+//!
+//! 为出站连接提供按地址分组的连接复用，并在同一设备的多个地址之间做负载均衡
+
+use quinn::Connection;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+use tokio::sync::RwLock;
+use tracing::debug;
+use super::config::{CompleteConnectionPoolConfig, LoadBalanceStrategy};
+use super::types::{AddressGroup, CompleteConnectionInfo, CompleteConnectionStats, ConnectionHealthStatus};
+
+/// 出站连接池
+///
+/// 按远程地址分组保存已建立的连接，遵守每地址最大连接数和全局最大连接数限制
+pub struct PeerConnectionPool {
+    /// 连接池配置
+    config: CompleteConnectionPoolConfig,
+    /// 连接分组（地址 -> 连接组）
+    groups: RwLock<HashMap<SocketAddr, AddressGroup>>,
+    /// 请求计数统计
+    stats: RwLock<CompleteConnectionStats>,
+    /// 地址选择的轮询计数器
+    lb_counter: AtomicUsize,
+}
+
+impl PeerConnectionPool {
+    /// 创建新的连接池
+    ///
+    /// # 参数
+    ///
+    /// * `config` - 连接池配置
+    pub fn new(config: CompleteConnectionPoolConfig) -> Self {
+        Self {
+            config,
+            groups: RwLock::new(HashMap::new()),
+            stats: RwLock::new(CompleteConnectionStats::default()),
+            lb_counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// 获取连接池配置
+    pub fn config(&self) -> &CompleteConnectionPoolConfig {
+        &self.config
+    }
+
+    /// 尝试获取一个可复用的健康连接
+    ///
+    /// 会先清理已关闭的连接。未启用连接复用时总是返回 `None`。
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 远程地址
+    pub async fn acquire(&self, addr: SocketAddr) -> Option<Connection> {
+        self.stats.write().await.total_requests += 1;
+
+        if !self.config.enable_connection_reuse {
+            return None;
+        }
+
+        let mut groups = self.groups.write().await;
+        let group = groups.get_mut(&addr)?;
+        Self::prune_closed(group);
+
+        let index = Self::select_connection(&self.config.load_balance_strategy, group)?;
+        let now = SystemTime::now();
+        let conn_info = &mut group.connections[index];
+        conn_info.last_used = now;
+        conn_info.usage_count += 1;
+        conn_info.active = true;
+        let connection = conn_info.connection.clone();
+        group.last_used = now;
+
+        self.stats.write().await.successful_requests += 1;
+        debug!("复用连接池中的连接: {} (ID: {})", addr, connection.stable_id());
+        Some(connection)
+    }
+
+    /// 检查是否允许为指定地址新建连接
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 远程地址
+    pub async fn can_create(&self, addr: SocketAddr) -> bool {
+        let mut groups = self.groups.write().await;
+        for group in groups.values_mut() {
+            Self::prune_closed(group);
+        }
+
+        let total: usize = groups.values().map(|g| g.connections.len()).sum();
+        if total >= self.config.max_connections {
+            return false;
+        }
+
+        groups
+            .get(&addr)
+            .map(|g| g.connections.len() < self.config.max_connections_per_addr)
+            .unwrap_or(true)
+    }
+
+    /// 将新建立的连接加入连接池
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 远程地址
+    /// * `connection` - 已建立的连接
+    pub async fn insert(&self, addr: SocketAddr, connection: Connection) {
+        let now = SystemTime::now();
+        let info = CompleteConnectionInfo {
+            connection_id: connection.stable_id().to_string(),
+            connection,
+            created_at: now,
+            last_used: now,
+            usage_count: 1,
+            active: true,
+            remote_addr: addr,
+            health_status: ConnectionHealthStatus::Healthy,
+            error_count: 0,
+            total_response_time_us: 0,
+            last_error: None,
+            active_requests: 0,
+            weight: 1.0,
+            quality_score: 1.0,
+            last_health_check: now,
+            is_warmup: false,
+        };
+
+        let mut groups = self.groups.write().await;
+        let group = groups.entry(addr).or_insert_with(|| AddressGroup {
+            addr,
+            connections: VecDeque::new(),
+            pending_requests: VecDeque::new(),
+            lb_index: 0,
+            last_used: now,
+            weight: 1.0,
+        });
+        group.connections.push_back(info);
+        group.last_used = now;
+
+        self.stats.write().await.successful_requests += 1;
+    }
+
+    /// 记录一次失败的连接请求
+    pub async fn record_failure(&self) {
+        self.stats.write().await.failed_requests += 1;
+    }
+
+    /// 移除并关闭指定地址的所有连接
+    ///
+    /// # 返回值
+    ///
+    /// 返回被移除的连接数
+    pub async fn remove(&self, addr: SocketAddr) -> usize {
+        self.remove_with(addr, 0, "disconnect").await
+    }
+
+    /// 移除指定地址的所有连接，并以给定的应用错误码和原因关闭
+    ///
+    /// # 参数
+    ///
+    /// * `addr` - 对端地址
+    /// * `code` - QUIC应用错误码
+    /// * `reason` - 关闭原因
+    ///
+    /// # 返回值
+    ///
+    /// 返回被移除的连接数
+    pub async fn remove_with(&self, addr: SocketAddr, code: u32, reason: &str) -> usize {
+        let mut groups = self.groups.write().await;
+        match groups.remove(&addr) {
+            Some(group) => {
+                for conn_info in group.connections.iter() {
+                    conn_info.connection.close(code.into(), reason.as_bytes());
+                }
+                group.connections.len()
+            }
+            None => 0,
+        }
+    }
+
+    /// 关闭并清空所有连接
+    pub async fn clear(&self) {
+        let mut groups = self.groups.write().await;
+        for (_, group) in groups.drain() {
+            for conn_info in group.connections.iter() {
+                conn_info.connection.close(0u32.into(), b"shutdown");
+            }
+        }
+    }
+
+    /// 在同一设备的多个地址中按负载均衡策略选择一个地址
+    ///
+    /// # 参数
+    ///
+    /// * `peer_id` - 对端设备ID（用于一致性哈希）
+    /// * `addrs` - 设备的候选地址列表
+    pub async fn select_address(&self, peer_id: &str, addrs: &[SocketAddr]) -> Option<SocketAddr> {
+        if addrs.len() <= 1 {
+            return addrs.first().copied();
+        }
+
+        let groups = self.groups.read().await;
+        let open_count = |addr: &SocketAddr| {
+            groups
+                .get(addr)
+                .map(|g| g.connections.iter().filter(|c| c.connection.close_reason().is_none()).count())
+                .unwrap_or(0)
+        };
+
+        let selected = match self.config.load_balance_strategy {
+            LoadBalanceStrategy::RoundRobin | LoadBalanceStrategy::WeightedRoundRobin => {
+                addrs[self.lb_counter.fetch_add(1, Ordering::Relaxed) % addrs.len()]
+            }
+            LoadBalanceStrategy::Random => addrs[fastrand::usize(0..addrs.len())],
+            LoadBalanceStrategy::ConsistentHash => {
+                use std::collections::hash_map::DefaultHasher;
+                use std::hash::{Hash, Hasher};
+
+                let mut hasher = DefaultHasher::new();
+                peer_id.hash(&mut hasher);
+                addrs[(hasher.finish() as usize) % addrs.len()]
+            }
+            LoadBalanceStrategy::LeastConnections
+            | LoadBalanceStrategy::LeastActiveRequests
+            | LoadBalanceStrategy::ResponseTimeWeighted => {
+                // 优先选择已有健康连接的地址以便复用，否则选择连接数最少的地址
+                let reusable = addrs.iter().find(|addr| {
+                    groups
+                        .get(addr)
+                        .map(|g| g.connections.iter().any(Self::is_healthy))
+                        .unwrap_or(false)
+                });
+                match reusable {
+                    Some(addr) if self.config.enable_connection_reuse => *addr,
+                    _ => *addrs.iter().min_by_key(|addr| open_count(addr))?,
+                }
+            }
+        };
+
+        Some(selected)
+    }
+
+    /// 获取连接池统计信息
+    pub async fn stats(&self) -> CompleteConnectionStats {
+        let mut stats = self.stats.read().await.clone();
+        let groups = self.groups.read().await;
+
+        let open: Vec<&CompleteConnectionInfo> = groups
+            .values()
+            .flat_map(|g| g.connections.iter())
+            .filter(|c| c.connection.close_reason().is_none())
+            .collect();
+
+        stats.total_connections = open.len();
+        stats.active_connections = open.iter().filter(|c| c.active).count();
+        stats.idle_connections = stats.total_connections - stats.active_connections;
+        stats.warmup_connections = open.iter().filter(|c| c.is_warmup).count();
+        stats.active_addresses = groups
+            .values()
+            .filter(|g| g.connections.iter().any(|c| c.connection.close_reason().is_none()))
+            .count();
+        stats.utilization_rate = if self.config.max_connections > 0 {
+            stats.total_connections as f64 / self.config.max_connections as f64
+        } else {
+            0.0
+        };
+        stats.error_rate = if stats.total_requests > 0 {
+            stats.failed_requests as f64 / stats.total_requests as f64
+        } else {
+            0.0
+        };
+        stats.avg_quality_score = if open.is_empty() {
+            1.0
+        } else {
+            open.iter().map(|c| c.quality_score).sum::<f64>() / open.len() as f64
+        };
+
+        stats
+    }
+
+    /// 连接是否可用于复用
+    fn is_healthy(conn_info: &CompleteConnectionInfo) -> bool {
+        conn_info.health_status == ConnectionHealthStatus::Healthy
+            && conn_info.connection.close_reason().is_none()
+    }
+
+    /// 移除已关闭的连接
+    fn prune_closed(group: &mut AddressGroup) {
+        group.connections.retain(|c| c.connection.close_reason().is_none());
+    }
+
+    /// 根据负载均衡策略在同一地址的连接中选择一个
+    fn select_connection(strategy: &LoadBalanceStrategy, group: &mut AddressGroup) -> Option<usize> {
+        let healthy: Vec<usize> = group
+            .connections
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| Self::is_healthy(c))
+            .map(|(i, _)| i)
+            .collect();
+
+        if healthy.is_empty() {
+            return None;
+        }
+
+        let index = match strategy {
+            LoadBalanceStrategy::RoundRobin | LoadBalanceStrategy::WeightedRoundRobin => {
+                let index = healthy[group.lb_index % healthy.len()];
+                group.lb_index = group.lb_index.wrapping_add(1);
+                index
+            }
+            LoadBalanceStrategy::Random => healthy[fastrand::usize(0..healthy.len())],
+            _ => *healthy
+                .iter()
+                .min_by_key(|&&i| group.connections[i].usage_count)?,
+        };
+
+        Some(index)
+    }
+}
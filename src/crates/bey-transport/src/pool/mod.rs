@@ -4,9 +4,11 @@
 
 pub mod config;
 pub mod types;
+pub mod manager;
 
 // 重新导出常用类型
 pub use config::{CompleteConnectionPoolConfig, LoadBalanceStrategy};
+pub use manager::PeerConnectionPool;
 pub use types::{
     CompleteConnectionInfo, ConnectionHealthStatus, CompleteConnectionStats,
     CompletePoolEvent, ConnectionRequest, AddressGroup,
@@ -69,7 +69,7 @@ pub enum ConnectionHealthStatus {
 /// 连接统计信息
 ///
 /// 提供连接池的全面统计数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompleteConnectionStats {
     /// 总连接数
     pub total_connections: usize,
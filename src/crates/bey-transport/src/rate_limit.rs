@@ -0,0 +1,182 @@
+//! # 对端限速模块
+//!
+//! 为每个对端分别维护发送和接收方向的令牌桶，防止单个对端独占带宽。
+//! 超出速率的数据不会报错，而是等待令牌补充后再继续；未设置限速的对端不受影响。
+//! 对端限速与其他对端以及任何全局限制相互独立。
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// 令牌桶允许的突发量（以限速下的传输时长表示）
+pub const RATE_LIMIT_BURST: Duration = Duration::from_millis(200);
+
+/// 限速方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateDirection {
+    /// 发往对端的数据
+    Send,
+    /// 从对端收到的数据
+    Receive,
+}
+
+/// 令牌桶
+///
+/// 取出的字节数可以超过当前余量，超出部分记为欠额，
+/// 调用方等待欠额按速率补足后再继续，因此大消息不会被永久阻塞
+#[derive(Debug)]
+struct TokenBucket {
+    /// 每秒补充的字节数
+    rate: f64,
+    /// 桶容量（字节）
+    capacity: f64,
+    /// 当前余量，为负时表示欠额
+    tokens: f64,
+    /// 上次补充时间
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1) as f64;
+        let capacity = (rate * RATE_LIMIT_BURST.as_secs_f64()).max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// 取出字节数，返回调用方需要等待的时间
+    fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// 单个对端的令牌桶
+struct PeerBuckets {
+    /// 创建令牌桶时的限速（字节/秒）
+    rate: u64,
+    send: TokenBucket,
+    receive: TokenBucket,
+}
+
+/// 按对端划分的限速器
+pub struct PeerRateLimiter {
+    /// 未单独设置的对端使用的默认限速（字节/秒），`None` 表示不限速
+    default_rate: Option<u64>,
+    /// 单独设置的对端限速
+    limits: Mutex<HashMap<SocketAddr, u64>>,
+    /// 对端的令牌桶
+    buckets: Mutex<HashMap<SocketAddr, PeerBuckets>>,
+}
+
+impl PeerRateLimiter {
+    /// 创建限速器
+    ///
+    /// # 参数
+    ///
+    /// * `default_rate` - 默认限速（字节/秒），`None` 表示默认不限速
+    pub fn new(default_rate: Option<u64>) -> Self {
+        Self {
+            default_rate,
+            limits: Mutex::new(HashMap::new()),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 设置对端限速，覆盖默认限速
+    ///
+    /// # 参数
+    ///
+    /// * `peer` - 对端地址
+    /// * `bytes_per_sec` - 发送和接收方向各自的速率上限（字节/秒）
+    pub fn set_peer_rate_limit(&self, peer: SocketAddr, bytes_per_sec: u64) {
+        if let Ok(mut limits) = self.limits.lock() {
+            limits.insert(peer, bytes_per_sec);
+        }
+        debug!("设置对端限速: {} -> {} 字节/秒", peer, bytes_per_sec);
+    }
+
+    /// 清除对端单独设置的限速，恢复为默认限速
+    pub fn clear_peer_rate_limit(&self, peer: SocketAddr) {
+        if let Ok(mut limits) = self.limits.lock() {
+            limits.remove(&peer);
+        }
+    }
+
+    /// 对端当前生效的限速（字节/秒），不限速时返回 `None`
+    pub fn peer_rate_limit(&self, peer: SocketAddr) -> Option<u64> {
+        self.limits.lock()
+            .ok()
+            .and_then(|limits| limits.get(&peer).copied())
+            .or(self.default_rate)
+    }
+
+    /// 按对端限速等待，直到可以传输指定的字节数
+    ///
+    /// # 参数
+    ///
+    /// * `peer` - 对端地址
+    /// * `direction` - 限速方向
+    /// * `bytes` - 要传输的字节数
+    pub async fn acquire(&self, peer: SocketAddr, direction: RateDirection, bytes: usize) {
+        let delay = self.reserve(peer, direction, bytes);
+        if !delay.is_zero() {
+            debug!("对端 {} {:?} 超出限速，等待 {:?}", peer, direction, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// 释放对端的令牌桶，单独设置的限速保留
+    pub fn release(&self, peer: SocketAddr) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            buckets.remove(&peer);
+        }
+    }
+
+    /// 从对端的令牌桶中取出字节数，返回需要等待的时间
+    fn reserve(&self, peer: SocketAddr, direction: RateDirection, bytes: usize) -> Duration {
+        let Some(rate) = self.peer_rate_limit(peer) else {
+            return Duration::ZERO;
+        };
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return Duration::ZERO;
+        };
+
+        // 限速变化后重新创建令牌桶
+        if buckets.get(&peer).is_none_or(|peer_buckets| peer_buckets.rate != rate) {
+            buckets.insert(peer, PeerBuckets {
+                rate,
+                send: TokenBucket::new(rate),
+                receive: TokenBucket::new(rate),
+            });
+        }
+        let Some(peer_buckets) = buckets.get_mut(&peer) else {
+            return Duration::ZERO;
+        };
+        let bucket = match direction {
+            RateDirection::Send => &mut peer_buckets.send,
+            RateDirection::Receive => &mut peer_buckets.receive,
+        };
+        bucket.take(bytes, Instant::now())
+    }
+}
+
+impl Default for PeerRateLimiter {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
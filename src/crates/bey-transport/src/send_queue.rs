@@ -0,0 +1,351 @@
+//! # 对端发送队列模块
+//!
+//! 为每条连接维护独立的有界发送队列和刷新任务，发送方只负责入队，
+//! 实际写入由连接自己的刷新任务完成。某个对端拥塞时只会填满它自己的队列，
+//! 不会拖慢发往其他对端的消息。队列写满时 [`PeerSendQueues::enqueue`] 立即返回错误，
+//! [`PeerSendQueues::enqueue_wait`] 则等待队列腾出空间，只推迟发往该对端的发送方。
+//! 设置限速器后，刷新任务在投递前按对端的发送限速等待，超出限速的发送方随队列写满而被推迟。
+//!
+//! 队列按连接区分而不是按地址区分：对端重连后旧连接的积压不会写到新连接上。
+//! 入队返回 [`Delivery`]，等待它即可得到这条消息的投递结果；
+//! 连接关闭后刷新任务退出，未投递的消息以错误完成，队列随之移除。
+
+use crate::error_codes;
+use crate::rate_limit::{PeerRateLimiter, RateDirection};
+use crate::TransportResult;
+use error::{ErrorCategory, ErrorInfo, ErrorSeverity};
+use quinn::Connection;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::{mpsc, oneshot};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// 默认每个对端的队列容量
+pub const DEFAULT_SEND_QUEUE_CAPACITY: usize = 64;
+
+/// 消息投递目标
+///
+/// 刷新任务通过该接口把队列中的数据写到对端
+pub trait PeerSink: Send + Sync + 'static {
+    /// 对端地址
+    fn remote_address(&self) -> SocketAddr;
+
+    /// 连接标识，同一地址上的不同连接标识不同
+    fn connection_id(&self) -> usize;
+
+    /// 等待连接关闭
+    fn closed(&self) -> impl Future<Output = ()> + Send;
+
+    /// 投递一条消息
+    ///
+    /// # 参数
+    ///
+    /// * `data` - 已序列化的消息数据
+    ///
+    /// # 返回值
+    ///
+    /// 返回投递结果或错误信息
+    fn deliver(&self, data: Vec<u8>) -> impl Future<Output = TransportResult<()>> + Send;
+}
+
+impl PeerSink for Connection {
+    fn remote_address(&self) -> SocketAddr {
+        Connection::remote_address(self)
+    }
+
+    fn connection_id(&self) -> usize {
+        self.stable_id()
+    }
+
+    async fn closed(&self) {
+        Connection::closed(self).await;
+    }
+
+    async fn deliver(&self, data: Vec<u8>) -> TransportResult<()> {
+        let mut stream = self.open_uni().await
+            .map_err(|e| ErrorInfo::new(2012, format!("打开单向流失败: {}", e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        stream.write_all(&data).await
+            .map_err(|e| ErrorInfo::new(2013, format!("发送消息失败: {}", e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        stream.finish()
+            .map_err(|e| ErrorInfo::new(2014, format!("完成发送失败: {}", e))
+                .with_category(ErrorCategory::Network)
+                .with_severity(ErrorSeverity::Error))?;
+
+        Ok(())
+    }
+}
+
+/// 排队等待投递的消息
+struct QueuedMessage {
+    /// 已序列化的消息数据
+    data: Vec<u8>,
+    /// 投递结果通知
+    completion: oneshot::Sender<TransportResult<()>>,
+}
+
+/// 单条消息的投递结果
+///
+/// 等待该值得到消息写到对端的结果；刷新任务退出或队列被移除时以关闭错误完成。
+/// 不等待时消息照常投递
+#[derive(Debug)]
+pub struct Delivery {
+    /// 对端地址
+    peer: SocketAddr,
+    /// 投递结果
+    receiver: oneshot::Receiver<TransportResult<()>>,
+}
+
+impl Future for Delivery {
+    type Output = TransportResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let peer = self.peer;
+        Pin::new(&mut self.receiver).poll(cx).map(|result| {
+            result.unwrap_or_else(|_| Err(closed_error(peer)))
+        })
+    }
+}
+
+/// 单条连接的队列
+struct PeerQueue {
+    /// 对端地址
+    peer: SocketAddr,
+    /// 队列发送端
+    sender: mpsc::Sender<QueuedMessage>,
+    /// 刷新任务
+    task: JoinHandle<()>,
+}
+
+/// 连接标识到队列的映射
+type QueueMap = Mutex<HashMap<usize, PeerQueue>>;
+
+/// 按连接划分的发送队列
+pub struct PeerSendQueues<S: PeerSink> {
+    /// 每条连接的队列容量
+    capacity: usize,
+    /// 连接标识到队列的映射
+    queues: Arc<QueueMap>,
+    /// 对端发送限速
+    rate_limiter: Option<Arc<PeerRateLimiter>>,
+    _sink: std::marker::PhantomData<fn(S)>,
+}
+
+impl<S: PeerSink> PeerSendQueues<S> {
+    /// 创建发送队列集合
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 每个对端的队列容量（至少为1）
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            queues: Arc::new(Mutex::new(HashMap::new())),
+            rate_limiter: None,
+            _sink: std::marker::PhantomData,
+        }
+    }
+
+    /// 设置对端限速器，刷新任务投递每条消息前按对端的发送限速等待
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<PeerRateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// 每条连接的队列容量
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 将消息放入连接的发送队列
+    ///
+    /// 连接没有队列（或刷新任务已退出）时使用 `sink` 创建新队列和刷新任务。
+    /// 该方法不等待投递完成，投递结果通过返回的 [`Delivery`] 获取。
+    ///
+    /// # 参数
+    ///
+    /// * `sink` - 消息所属的连接
+    /// * `data` - 已序列化的消息数据
+    ///
+    /// # 返回值
+    ///
+    /// 入队成功返回投递结果，队列已满时返回错误
+    pub fn enqueue(&self, sink: S, data: Vec<u8>) -> TransportResult<Delivery> {
+        let peer = sink.remote_address();
+        let connection_id = sink.connection_id();
+        let mut queues = self.queues.lock()
+            .map_err(|_| closed_error(peer))?;
+
+        if queues.get(&connection_id).is_none_or(|queue| queue.sender.is_closed()) {
+            queues.insert(connection_id, self.spawn_queue(sink));
+        }
+
+        let queue = queues.get(&connection_id).ok_or_else(|| closed_error(peer))?;
+        let (completion, receiver) = oneshot::channel();
+        match queue.sender.try_send(QueuedMessage { data, completion }) {
+            Ok(()) => Ok(Delivery { peer, receiver }),
+            Err(TrySendError::Full(_)) => Err(ErrorInfo::new(
+                error_codes::transport::SEND_QUEUE_FULL,
+                format!("发送队列已满: {} (容量 {})", peer, self.capacity),
+            )
+            .with_category(ErrorCategory::Network)
+            .with_severity(ErrorSeverity::Warning)),
+            Err(TrySendError::Closed(_)) => Err(closed_error(peer)),
+        }
+    }
+
+    /// 将消息放入连接的发送队列，队列已满时等待空间
+    ///
+    /// 等待期间不持有队列映射的锁，其他对端的入队不受影响。
+    ///
+    /// # 参数
+    ///
+    /// * `sink` - 消息所属的连接
+    /// * `data` - 已序列化的消息数据
+    ///
+    /// # 返回值
+    ///
+    /// 入队成功返回投递结果，连接的刷新任务已退出时返回错误
+    pub async fn enqueue_wait(&self, sink: S, data: Vec<u8>) -> TransportResult<Delivery> {
+        let peer = sink.remote_address();
+        let connection_id = sink.connection_id();
+        let sender = {
+            let mut queues = self.queues.lock()
+                .map_err(|_| closed_error(peer))?;
+            if queues.get(&connection_id).is_none_or(|queue| queue.sender.is_closed()) {
+                queues.insert(connection_id, self.spawn_queue(sink));
+            }
+            queues.get(&connection_id).ok_or_else(|| closed_error(peer))?.sender.clone()
+        };
+
+        let permit = sender.reserve().await.map_err(|_| closed_error(peer))?;
+        let (completion, receiver) = oneshot::channel();
+        permit.send(QueuedMessage { data, completion });
+        Ok(Delivery { peer, receiver })
+    }
+
+    /// 对端所有连接的队列中等待发送的消息数量
+    pub fn queued(&self, peer: SocketAddr) -> usize {
+        self.queues.lock()
+            .map(|queues| queues.values()
+                .filter(|queue| queue.peer == peer)
+                .map(|queue| self.capacity - queue.sender.capacity())
+                .sum())
+            .unwrap_or(0)
+    }
+
+    /// 当前的队列数量
+    pub fn len(&self) -> usize {
+        self.queues.lock().map(|queues| queues.len()).unwrap_or(0)
+    }
+
+    /// 是否没有任何队列
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 移除对端所有连接的队列，未发送的消息以关闭错误完成
+    pub fn remove(&self, peer: SocketAddr) {
+        if let Ok(mut queues) = self.queues.lock() {
+            queues.retain(|_, queue| {
+                if queue.peer != peer {
+                    return true;
+                }
+                queue.task.abort();
+                false
+            });
+            debug!("已移除发送队列: {}", peer);
+        }
+    }
+
+    /// 移除所有队列
+    pub fn clear(&self) {
+        if let Ok(mut queues) = self.queues.lock() {
+            for (_, queue) in queues.drain() {
+                queue.task.abort();
+            }
+        }
+    }
+
+    /// 创建连接的队列并启动刷新任务
+    ///
+    /// 投递失败或连接关闭时任务退出，剩余消息以错误完成，并从队列映射中移除自己；
+    /// 下次入队会重新创建队列
+    fn spawn_queue(&self, sink: S) -> PeerQueue {
+        let peer = sink.remote_address();
+        let connection_id = sink.connection_id();
+        let (sender, mut receiver) = mpsc::channel::<QueuedMessage>(self.capacity);
+        let rate_limiter = self.rate_limiter.clone();
+        let queues = Arc::clone(&self.queues);
+
+        let task = tokio::spawn(async move {
+            let error = loop {
+                let message = tokio::select! {
+                    message = receiver.recv() => message,
+                    _ = sink.closed() => break closed_error(peer),
+                };
+                let Some(QueuedMessage { data, completion }) = message else {
+                    return;
+                };
+                let delivered = async {
+                    if let Some(rate_limiter) = &rate_limiter {
+                        rate_limiter.acquire(peer, RateDirection::Send, data.len()).await;
+                    }
+                    sink.deliver(data).await
+                };
+                let result = tokio::select! {
+                    result = delivered => result,
+                    _ = sink.closed() => Err(closed_error(peer)),
+                };
+                if let Err(e) = result {
+                    let reason = e.to_string();
+                    let _ = completion.send(Err(e));
+                    break closed_error(peer).with_context(reason);
+                }
+                let _ = completion.send(Ok(()));
+            };
+
+            receiver.close();
+            let mut dropped = 0usize;
+            while let Ok(message) = receiver.try_recv() {
+                let _ = message.completion.send(Err(closed_error(peer).with_context(error.to_string())));
+                dropped += 1;
+            }
+            warn!("停止向 {} 投递消息，丢弃 {} 条排队消息: {}", peer, dropped, error);
+
+            // 已被新队列替换时不移除
+            if let Ok(mut queues) = queues.lock() {
+                if queues.get(&connection_id).is_some_and(|queue| queue.sender.is_closed()) {
+                    queues.remove(&connection_id);
+                }
+            }
+        });
+
+        debug!("已创建发送队列: {} (连接 {})", peer, connection_id);
+        PeerQueue { peer, sender, task }
+    }
+}
+
+/// 发送队列已关闭
+fn closed_error(peer: SocketAddr) -> ErrorInfo {
+    ErrorInfo::new(error_codes::transport::SEND_QUEUE_CLOSED, format!("发送队列已关闭: {}", peer))
+        .with_category(ErrorCategory::Network)
+        .with_severity(ErrorSeverity::Error)
+}
+
+impl<S: PeerSink> Drop for PeerSendQueues<S> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
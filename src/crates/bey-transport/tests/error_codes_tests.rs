@@ -80,6 +80,10 @@ fn test_transport_error_codes_uniqueness() {
         transport::CONNECTION_FAILED,
         transport::SEND_MESSAGE_FAILED,
         transport::RECEIVE_MESSAGE_FAILED,
+        transport::INVALID_BIND_ADDRESS,
+        transport::SEND_QUEUE_FULL,
+        transport::SEND_QUEUE_CLOSED,
+        transport::ALPN_MISMATCH,
     ];
     
     for i in 0..codes.len() {
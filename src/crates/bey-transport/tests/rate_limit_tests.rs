@@ -0,0 +1,104 @@
+//! # 对端限速测试
+//!
+//! 测试限速对端的吞吐量被限制在设定速率内，而未限速对端不受影响
+
+use bey_transport::{PeerRateLimiter, PeerSendQueues, PeerSink, RateDirection, TransportResult};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// 测试用投递目标：记录投递完成的数据量
+#[derive(Clone)]
+struct RecordingSink {
+    addr: SocketAddr,
+    delivered: mpsc::UnboundedSender<usize>,
+}
+
+impl PeerSink for RecordingSink {
+    fn remote_address(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn connection_id(&self) -> usize {
+        self.addr.port() as usize
+    }
+
+    async fn closed(&self) {
+        std::future::pending::<()>().await
+    }
+
+    async fn deliver(&self, data: Vec<u8>) -> TransportResult<()> {
+        let _ = self.delivered.send(data.len());
+        Ok(())
+    }
+}
+
+fn peer_addr(port: u16) -> SocketAddr {
+    format!("127.0.0.1:{}", port).parse().expect("地址解析失败")
+}
+
+/// 发送消息并等待全部投递完成，返回耗时
+async fn send_all(queues: &PeerSendQueues<RecordingSink>, peer: SocketAddr, messages: usize, size: usize) -> Duration {
+    let (delivered_tx, mut delivered) = mpsc::unbounded_channel();
+    let sink = RecordingSink { addr: peer, delivered: delivered_tx };
+
+    let start = Instant::now();
+    for _ in 0..messages {
+        queues.enqueue(sink.clone(), vec![0u8; size]).expect("入队失败");
+    }
+    let mut total = 0;
+    while total < messages * size {
+        total += tokio::time::timeout(Duration::from_secs(5), delivered.recv())
+            .await
+            .expect("投递超时")
+            .expect("投递通道已关闭");
+    }
+    start.elapsed()
+}
+
+#[tokio::test]
+async fn test_rate_limited_peer_is_capped_while_unlimited_peer_is_not() {
+    let rate_limiter = Arc::new(PeerRateLimiter::default());
+    let queues = PeerSendQueues::new(64).with_rate_limiter(Arc::clone(&rate_limiter));
+
+    let limited = peer_addr(21001);
+    let unlimited = peer_addr(21002);
+    rate_limiter.set_peer_rate_limit(limited, 50_000);
+    assert_eq!(rate_limiter.peer_rate_limit(limited), Some(50_000));
+    assert_eq!(rate_limiter.peer_rate_limit(unlimited), None);
+
+    // 50KB：突发量（200ms，10KB）之外的40KB需要约800ms
+    let (limited_elapsed, unlimited_elapsed) = tokio::join!(
+        send_all(&queues, limited, 20, 2_500),
+        send_all(&queues, unlimited, 20, 2_500),
+    );
+
+    assert!(limited_elapsed >= Duration::from_millis(700), "限速对端过快: {:?}", limited_elapsed);
+    let throughput = 50_000.0 / limited_elapsed.as_secs_f64();
+    assert!(throughput <= 50_000.0 * 1.3, "限速对端吞吐量超出上限: {:.0} 字节/秒", throughput);
+    assert!(unlimited_elapsed < Duration::from_millis(200), "未限速对端不应被延迟: {:?}", unlimited_elapsed);
+}
+
+#[tokio::test]
+async fn test_default_rate_and_per_peer_override() {
+    let rate_limiter = PeerRateLimiter::new(Some(1_000));
+    let peer = peer_addr(21003);
+    assert_eq!(rate_limiter.peer_rate_limit(peer), Some(1_000));
+
+    // 超出突发量时等待而不是报错
+    let start = Instant::now();
+    rate_limiter.acquire(peer, RateDirection::Receive, 200).await;
+    rate_limiter.acquire(peer, RateDirection::Receive, 100).await;
+    assert!(start.elapsed() >= Duration::from_millis(80), "超出限速应等待: {:?}", start.elapsed());
+
+    // 发送方向使用独立的令牌桶
+    let start = Instant::now();
+    rate_limiter.acquire(peer, RateDirection::Send, 200).await;
+    assert!(start.elapsed() < Duration::from_millis(50));
+
+    rate_limiter.set_peer_rate_limit(peer, 1_000_000);
+    assert_eq!(rate_limiter.peer_rate_limit(peer), Some(1_000_000));
+    rate_limiter.clear_peer_rate_limit(peer);
+    assert_eq!(rate_limiter.peer_rate_limit(peer), Some(1_000));
+}
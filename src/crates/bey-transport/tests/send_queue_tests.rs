@@ -0,0 +1,257 @@
+//! # 对端发送队列测试
+//!
+//! 测试慢速对端不会阻塞发往其他对端的消息、队列写满时的背压，
+//! 以及投递结果、按连接区分队列和连接关闭后移除队列
+
+use bey_transport::error_codes::transport;
+use bey_transport::{PeerSendQueues, PeerSink, TransportResult};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
+
+/// 测试用投递目标：每次投递前等待许可，并记录投递完成的数据
+#[derive(Clone)]
+struct GatedSink {
+    /// 对端地址
+    addr: SocketAddr,
+    /// 连接标识
+    id: usize,
+    /// 关闭该信号量模拟连接关闭
+    closed: Arc<Semaphore>,
+    /// 投递许可，不释放许可时模拟拥塞的对端
+    permits: Arc<Semaphore>,
+    /// 开始投递时通知
+    started: mpsc::UnboundedSender<()>,
+    /// 投递完成的数据
+    delivered: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl PeerSink for GatedSink {
+    fn remote_address(&self) -> SocketAddr {
+        self.addr
+    }
+
+    fn connection_id(&self) -> usize {
+        self.id
+    }
+
+    async fn closed(&self) {
+        let _ = self.closed.acquire().await;
+    }
+
+    async fn deliver(&self, data: Vec<u8>) -> TransportResult<()> {
+        let _ = self.started.send(());
+        let permit = self.permits.acquire().await.expect("获取许可失败");
+        permit.forget();
+        let _ = self.delivered.send(data);
+        Ok(())
+    }
+}
+
+struct TestPeer {
+    addr: SocketAddr,
+    sink: GatedSink,
+    started: mpsc::UnboundedReceiver<()>,
+    delivered: mpsc::UnboundedReceiver<Vec<u8>>,
+}
+
+fn create_peer(port: u16, permits: usize) -> TestPeer {
+    create_connection(port, port as usize, permits)
+}
+
+fn create_connection(port: u16, id: usize, permits: usize) -> TestPeer {
+    let (started_tx, started) = mpsc::unbounded_channel();
+    let (delivered_tx, delivered) = mpsc::unbounded_channel();
+    let addr = format!("127.0.0.1:{}", port).parse().expect("地址解析失败");
+    TestPeer {
+        addr,
+        sink: GatedSink {
+            addr,
+            id,
+            closed: Arc::new(Semaphore::new(0)),
+            permits: Arc::new(Semaphore::new(permits)),
+            started: started_tx,
+            delivered: delivered_tx,
+        },
+        started,
+        delivered,
+    }
+}
+
+#[tokio::test]
+async fn test_slow_peer_does_not_delay_fast_peer() {
+    let queues = PeerSendQueues::new(8);
+    let mut slow = create_peer(20001, 0);
+    let mut fast = create_peer(20002, Semaphore::MAX_PERMITS);
+
+    // 慢速对端的刷新任务卡在第一条消息上，队列中还有积压
+    for i in 0..5u8 {
+        queues.enqueue(slow.sink.clone(), vec![i]).expect("慢速对端入队失败");
+    }
+    tokio::time::timeout(Duration::from_secs(1), slow.started.recv())
+        .await
+        .expect("慢速对端应开始投递");
+
+    let start = Instant::now();
+    for i in 0..5u8 {
+        queues.enqueue(fast.sink.clone(), vec![i]).expect("快速对端入队失败");
+    }
+    assert!(start.elapsed() < Duration::from_millis(100), "入队不应等待投递完成");
+
+    for i in 0..5u8 {
+        let data = tokio::time::timeout(Duration::from_millis(500), fast.delivered.recv())
+            .await
+            .expect("快速对端的消息不应被慢速对端阻塞")
+            .expect("投递通道已关闭");
+        assert_eq!(data, vec![i], "同一对端的消息应保持顺序");
+    }
+
+    assert!(slow.delivered.try_recv().is_err(), "慢速对端不应完成投递");
+    assert_eq!(queues.queued(slow.addr), 4);
+    assert_eq!(queues.queued(fast.addr), 0);
+
+    // 放行慢速对端后积压的消息全部送达
+    slow.sink.permits.add_permits(5);
+    for i in 0..5u8 {
+        let data = tokio::time::timeout(Duration::from_secs(1), slow.delivered.recv())
+            .await
+            .expect("慢速对端的消息应最终送达")
+            .expect("投递通道已关闭");
+        assert_eq!(data, vec![i]);
+    }
+}
+
+#[tokio::test]
+async fn test_full_queue_reports_backpressure() {
+    let queues = PeerSendQueues::new(2);
+    let mut slow = create_peer(20003, 0);
+
+    queues.enqueue(slow.sink.clone(), vec![0]).expect("入队失败");
+    tokio::time::timeout(Duration::from_secs(1), slow.started.recv())
+        .await
+        .expect("应开始投递");
+
+    // 刷新任务持有第一条消息，队列还能容纳两条
+    queues.enqueue(slow.sink.clone(), vec![1]).expect("入队失败");
+    queues.enqueue(slow.sink.clone(), vec![2]).expect("入队失败");
+
+    let error = queues
+        .enqueue(slow.sink.clone(), vec![3])
+        .expect_err("队列已满时应返回错误");
+    assert_eq!(error.code(), transport::SEND_QUEUE_FULL);
+
+    // 队列腾出空间后可以继续入队
+    slow.sink.permits.add_permits(1);
+    tokio::time::timeout(Duration::from_secs(1), slow.delivered.recv())
+        .await
+        .expect("应完成投递")
+        .expect("投递通道已关闭");
+    tokio::time::timeout(Duration::from_secs(1), slow.started.recv())
+        .await
+        .expect("应开始下一次投递");
+    queues.enqueue(slow.sink.clone(), vec![3]).expect("腾出空间后入队失败");
+}
+
+#[tokio::test]
+async fn test_enqueue_wait_delays_until_space() {
+    let queues = Arc::new(PeerSendQueues::new(1));
+    let mut slow = create_peer(20008, 0);
+
+    queues.enqueue(slow.sink.clone(), vec![0]).expect("入队失败");
+    tokio::time::timeout(Duration::from_secs(1), slow.started.recv())
+        .await
+        .expect("应开始投递");
+    queues.enqueue(slow.sink.clone(), vec![1]).expect("入队失败");
+
+    // 队列已满时等待而不是返回错误
+    let waiting = {
+        let queues = Arc::clone(&queues);
+        let sink = slow.sink.clone();
+        tokio::spawn(async move { queues.enqueue_wait(sink, vec![2]).await })
+    };
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!waiting.is_finished(), "队列已满时应等待空间");
+
+    // 其他对端不受影响
+    let fast = create_peer(20009, 1);
+    queues.enqueue_wait(fast.sink.clone(), vec![9]).await.expect("其他对端入队失败");
+
+    slow.sink.permits.add_permits(3);
+    let delivery = tokio::time::timeout(Duration::from_secs(1), waiting)
+        .await
+        .expect("腾出空间后应完成入队")
+        .expect("入队任务失败")
+        .expect("入队失败");
+    tokio::time::timeout(Duration::from_secs(1), delivery)
+        .await
+        .expect("应完成投递")
+        .expect("投递失败");
+    for i in 0..3u8 {
+        assert_eq!(slow.delivered.recv().await, Some(vec![i]));
+    }
+}
+
+#[tokio::test]
+async fn test_delivery_reports_result_per_message() {
+    let queues = PeerSendQueues::new(4);
+    let peer = create_peer(20004, 0);
+
+    let first = queues.enqueue(peer.sink.clone(), vec![0]).expect("入队失败");
+    let mut second = queues.enqueue(peer.sink.clone(), vec![1]).expect("入队失败");
+
+    // 放行一次投递，只有第一条消息完成
+    peer.sink.permits.add_permits(1);
+    tokio::time::timeout(Duration::from_secs(1), first)
+        .await
+        .expect("等待投递结果超时")
+        .expect("第一条消息应投递成功");
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), &mut second).await.is_err(),
+        "第二条消息尚未投递"
+    );
+
+    peer.sink.permits.add_permits(1);
+    tokio::time::timeout(Duration::from_secs(1), second)
+        .await
+        .expect("等待投递结果超时")
+        .expect("第二条消息应投递成功");
+}
+
+#[tokio::test]
+async fn test_queues_keyed_by_connection_and_removed_on_close() {
+    let queues = PeerSendQueues::new(4);
+    let mut old = create_connection(20005, 1, 0);
+    let mut new = create_connection(20005, 2, Semaphore::MAX_PERMITS);
+
+    // 旧连接卡住，同一地址上的新连接使用自己的队列
+    let stuck = queues.enqueue(old.sink.clone(), vec![0]).expect("入队失败");
+    let pending = queues.enqueue(old.sink.clone(), vec![1]).expect("入队失败");
+    tokio::time::timeout(Duration::from_secs(1), old.started.recv())
+        .await
+        .expect("旧连接应开始投递");
+    queues.enqueue(new.sink.clone(), vec![2]).expect("入队失败")
+        .await
+        .expect("新连接的消息应投递成功");
+    let data = new.delivered.try_recv().expect("新连接应收到消息");
+    assert_eq!(data, vec![2], "旧连接的积压不应写到新连接上");
+    assert_eq!(queues.len(), 2);
+    assert_eq!(queues.queued(old.addr), 1);
+
+    // 旧连接关闭后未投递的消息以错误完成，队列被移除
+    old.sink.closed.close();
+    let error = tokio::time::timeout(Duration::from_secs(1), pending)
+        .await
+        .expect("等待投递结果超时")
+        .expect_err("连接关闭后排队的消息应失败");
+    assert_eq!(error.code(), transport::SEND_QUEUE_CLOSED);
+    drop(stuck);
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while queues.len() != 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("关闭的连接的队列应被移除");
+    assert!(old.delivered.try_recv().is_err());
+}
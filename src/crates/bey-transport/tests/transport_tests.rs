@@ -2,7 +2,9 @@
 //!
 //! 测试 SecureTransport 的核心功能
 
-use bey_transport::{SecureTransport, TransportConfig, TransportMessage, TransportResult};
+use bey_transport::{CloseCode, CongestionAlgo, SecureTransport, TransportConfig, TransportEvent, TransportMessage, TransportResult, TrustLevel};
+use bey_transport::error_codes::transport::{ALPN_MISMATCH, CERTIFICATES_NOT_INITIALIZED, STREAM_ACCEPT_FAILED};
+use bey_transport::policy_engine::{PolicyAction, PolicyContext, PolicySet};
 use std::time::Duration;
 
 /// 初始化日志（仅执行一次）
@@ -42,6 +44,34 @@ async fn test_transport_config_creation() {
     assert!(config.require_client_cert());
 }
 
+#[test]
+fn test_congestion_controller_config() {
+    fn built_controller(config: &TransportConfig) -> Box<dyn std::any::Any> {
+        config
+            .congestion_controller()
+            .controller_factory()
+            .build(std::time::Instant::now(), 1200)
+            .into_any()
+    }
+
+    // 默认保持Quinn的默认算法
+    let config = TransportConfig::new();
+    assert_eq!(config.congestion_controller(), CongestionAlgo::Cubic);
+    assert!(built_controller(&config).is::<quinn::congestion::Cubic>());
+
+    let config = TransportConfig::new().with_congestion_controller(CongestionAlgo::NewReno);
+    assert_eq!(config.congestion_controller(), CongestionAlgo::NewReno);
+    assert!(built_controller(&config).is::<quinn::congestion::NewReno>());
+
+    let config = TransportConfig::new().with_congestion_controller(CongestionAlgo::Bbr);
+    assert_eq!(config.congestion_controller(), CongestionAlgo::Bbr);
+    assert!(built_controller(&config).is::<quinn::congestion::Bbr>());
+
+    // 其余传输参数不受影响
+    assert_eq!(config.port(), TransportConfig::new().port());
+    let _ = config.quic_transport_config();
+}
+
 #[tokio::test]
 async fn test_secure_transport_creation() {
     init_logging();
@@ -88,6 +118,51 @@ async fn test_policy_engine_integration() {
     assert_eq!(stats.policy_sets_count, 0, "初始策略集合数量应为0");
 }
 
+#[tokio::test]
+async fn test_policy_decision_cache_snapshot_clear_and_bypass() {
+    init_logging();
+
+    let config = create_test_transport_config(0).await.expect("创建配置失败");
+    let mut transport = SecureTransport::new(config, "test-device-policy-cache".to_string()).await
+        .expect("传输层创建失败");
+    transport.set_policy_set(PolicySet::new(
+        "allow-all".to_string(),
+        "允许所有".to_string(),
+        "测试用允许所有策略".to_string(),
+        PolicyAction::Allow,
+    )).await.expect("设置策略集合失败");
+
+    let context = || PolicyContext::new()
+        .with_requester_id("peer-a".to_string())
+        .with_operation("connect".to_string());
+
+    // 第一次评估写入缓存，时间戳不同的相同请求命中缓存
+    let first = transport.evaluate_policy(&context()).await.expect("策略评估失败");
+    assert_eq!(first.final_action, PolicyAction::Allow);
+    transport.evaluate_policy(&context()).await.expect("策略评估失败");
+    let stats = transport.get_policy_stats().await;
+    assert_eq!((stats.cache_hits, stats.cache_misses), (1, 1));
+
+    let snapshot = transport.policy_decisions_snapshot().await;
+    assert_eq!(snapshot.len(), 1, "相同请求只缓存一个决策");
+    let (request, decision) = &snapshot[0];
+    assert_eq!(request.requester_id.as_deref(), Some("peer-a"));
+    assert_eq!(decision.result.final_action, PolicyAction::Allow);
+
+    // 跳过缓存时重新评估
+    transport.evaluate_policy(&context().with_cache_bypass()).await.expect("策略评估失败");
+    let stats = transport.get_policy_stats().await;
+    assert_eq!((stats.cache_hits, stats.cache_misses), (1, 2));
+    assert_eq!(transport.policy_decisions_snapshot().await.len(), 1);
+
+    // 清除后缓存为空，下一次评估重新计算
+    transport.clear_policy_cache().await;
+    assert!(transport.policy_decisions_snapshot().await.is_empty());
+    transport.evaluate_policy(&context()).await.expect("策略评估失败");
+    let stats = transport.get_policy_stats().await;
+    assert_eq!((stats.cache_hits, stats.cache_misses), (1, 3));
+}
+
 #[tokio::test]
 async fn test_connection_management() {
     init_logging();
@@ -133,3 +208,721 @@ async fn test_message_serialization() {
     assert_eq!(deserialized.message_type, message.message_type);
     assert_eq!(deserialized.sender_id, message.sender_id);
 }
+
+#[tokio::test]
+async fn test_connect_reuses_pooled_connection() {
+    init_logging();
+
+    // 两端共用证书目录，证书由同一CA签发；设备证书签发给 `<设备ID>.bey.local`
+    let temp_dir = tempfile::TempDir::new().expect("创建临时目录失败");
+    let server_config = create_test_transport_config(18443).await.expect("创建配置失败")
+        .with_certificates_dir(temp_dir.path());
+    let mut server = SecureTransport::new(server_config, "test-pool-server".to_string())
+        .await
+        .expect("服务端传输层创建失败");
+    server.set_policy_set(allow_all_policy_set()).await.expect("设置策略集合失败");
+    server.start_server().await.expect("启动服务端失败");
+
+    let client_config = create_test_transport_config(18444).await.expect("创建配置失败")
+        .with_certificates_dir(temp_dir.path())
+        .with_server_name("test-pool-server.bey.local".to_string());
+    let mut client = SecureTransport::new(client_config, "test-pool-client".to_string())
+        .await
+        .expect("客户端传输层创建失败");
+    client.set_policy_set(allow_all_policy_set()).await.expect("设置策略集合失败");
+
+    let server_addr = "127.0.0.1:18443".parse().expect("地址解析失败");
+    let first = client.connect(server_addr).await.expect("首次连接失败");
+    let second = client
+        .connect_peer("test-pool-server", &[server_addr])
+        .await
+        .expect("再次连接失败");
+
+    assert_eq!(first.stable_id(), second.stable_id(), "应复用同一个池化连接");
+
+    let stats = client.pool_stats().await;
+    assert_eq!(stats.total_connections, 1);
+    assert_eq!(stats.total_requests, 2);
+
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_tcp_tls_handshake_checks_policy_and_certificates() {
+    init_logging();
+
+    let temp_dir = tempfile::TempDir::new().expect("创建临时目录失败");
+    let server_config = create_test_transport_config(0).await.expect("创建配置失败")
+        .with_certificates_dir(temp_dir.path());
+    let mut server = SecureTransport::new(server_config, "test-tcp-server".to_string())
+        .await
+        .expect("服务端传输层创建失败");
+    server.provision_certificates().await.expect("签发服务端证书失败");
+
+    let client_config = create_test_transport_config(0).await.expect("创建配置失败")
+        .with_certificates_dir(temp_dir.path())
+        .with_server_name("test-tcp-server.bey.local".to_string());
+    let mut client = SecureTransport::new(client_config, "test-tcp-client".to_string())
+        .await
+        .expect("客户端传输层创建失败");
+    client.provision_certificates().await.expect("签发客户端证书失败");
+    client.set_policy_set(allow_all_policy_set()).await.expect("设置策略集合失败");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("绑定失败");
+    let addr = listener.local_addr().expect("获取地址失败");
+
+    // 服务端没有允许连接的策略时，在TLS握手前拒绝对端
+    let connecting = tokio::spawn(async move {
+        let stream = tokio::net::TcpStream::connect(addr).await.expect("连接失败");
+        let result = client.connect_tcp(stream, addr).await.map(|_| ());
+        (client, result)
+    });
+    let (stream, remote_addr) = listener.accept().await.expect("接受连接失败");
+    let err = server.accept_tcp(stream, remote_addr).await.expect_err("未允许的对端应被拒绝");
+    assert!(matches!(err.code(), 2021 | 2022), "应由策略引擎拒绝: {}", err);
+    let (client, result) = connecting.await.expect("客户端任务失败");
+    assert!(result.is_err(), "被拒绝的TLS握手不应成功");
+
+    // 允许连接后双方按同一CA校验证书，服务端得到客户端的设备证书
+    server.set_policy_set(allow_all_policy_set()).await.expect("设置策略集合失败");
+    let connecting = tokio::spawn(async move {
+        let stream = tokio::net::TcpStream::connect(addr).await.expect("连接失败");
+        client.connect_tcp(stream, addr).await.map(|_| ())
+    });
+    let (stream, remote_addr) = listener.accept().await.expect("接受连接失败");
+    let (_stream, trust_level) = server.accept_tcp(stream, remote_addr).await.expect("TLS握手失败");
+    assert_eq!(trust_level, TrustLevel::Trusted);
+    connecting.await.expect("客户端任务失败").expect("客户端TLS握手失败");
+}
+
+#[tokio::test]
+async fn test_server_binds_to_configured_address() {
+    init_logging();
+
+    // 两端共用证书目录，证书由同一CA签发；设备证书签发给 `<设备ID>.bey.local`
+    let temp_dir = tempfile::TempDir::new().expect("创建临时目录失败");
+    let loopback: std::net::IpAddr = "127.0.0.1".parse().expect("地址解析失败");
+    let server_config = create_test_transport_config(18445)
+        .await
+        .expect("创建配置失败")
+        .with_certificates_dir(temp_dir.path())
+        .with_bind_address(loopback);
+    assert_eq!(server_config.bind_address(), loopback);
+
+    let mut server = SecureTransport::new(server_config, "test-bind-server".to_string())
+        .await
+        .expect("服务端传输层创建失败");
+    server.set_policy_set(allow_all_policy_set()).await.expect("设置策略集合失败");
+    server.start_server().await.expect("启动服务端失败");
+
+    let local_addr = server.local_addr().expect("服务端应有本地地址");
+    assert_eq!(local_addr.ip(), loopback, "端点应绑定到配置的地址");
+    assert_eq!(local_addr.port(), 18445);
+
+    let client_config = create_test_transport_config(18446).await.expect("创建配置失败")
+        .with_certificates_dir(temp_dir.path())
+        .with_server_name("test-bind-server.bey.local".to_string());
+    let mut client = SecureTransport::new(client_config, "test-bind-client".to_string())
+        .await
+        .expect("客户端传输层创建失败");
+    client.set_policy_set(allow_all_policy_set()).await.expect("设置策略集合失败");
+
+    client.connect(local_addr).await.expect("回环连接失败");
+    assert_eq!(client.active_connections_count().await, 1);
+
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_start_server_without_certificates() {
+    init_logging();
+
+    let loopback: std::net::IpAddr = "127.0.0.1".parse().expect("地址解析失败");
+    let empty_config = |dir: &std::path::Path| TransportConfig::new()
+        .with_bind_address(loopback)
+        .with_port(0)
+        .with_certificates_dir(dir);
+
+    // 不自动签发时返回指导调用方初始化证书的错误
+    let temp_dir = tempfile::TempDir::new().expect("创建临时目录失败");
+    let mut server = SecureTransport::new(empty_config(temp_dir.path()).with_auto_provision_certs(false), "test-no-certs".to_string())
+        .await
+        .expect("传输层创建失败");
+    let err = server.start_server().await.expect_err("没有证书时启动应该失败");
+    assert_eq!(err.code(), CERTIFICATES_NOT_INITIALIZED);
+    assert!(err.context().iter().any(|context| context.contains("provision_certificates")));
+    assert!(server.local_addr().is_none());
+
+    // 初始化证书后可以启动
+    server.provision_certificates().await.expect("初始化证书失败");
+    server.start_server().await.expect("初始化证书后启动失败");
+    server.stop().await;
+
+    // 自动签发时直接启动
+    let temp_dir = tempfile::TempDir::new().expect("创建临时目录失败");
+    let mut server = SecureTransport::new(empty_config(temp_dir.path()).with_auto_provision_certs(true), "test-auto-certs".to_string())
+        .await
+        .expect("传输层创建失败");
+    server.start_server().await.expect("自动签发证书后启动失败");
+    assert!(server.local_addr().is_some());
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_default_bind_address_is_unspecified() {
+    let config = TransportConfig::new();
+    assert!(config.bind_address().is_unspecified());
+}
+
+/// 创建允许所有操作的传输层，ALPN 测试中服务端和客户端共用同一证书目录以信任同一 CA
+async fn create_alpn_test_transport(
+    port: u16,
+    certificates_dir: &std::path::Path,
+    device_id: &str,
+    alpn: &[u8],
+) -> SecureTransport {
+    // 设备证书签发给 `<设备ID>.bey.local`
+    let config = TransportConfig::new()
+        .with_port(port)
+        .with_certificates_dir(certificates_dir)
+        .with_connection_timeout(Duration::from_secs(5))
+        .with_alpn(vec![alpn.to_vec()])
+        .with_server_name("test-alpn-server.bey.local".to_string());
+    let mut transport = SecureTransport::new(config, device_id.to_string())
+        .await
+        .expect("传输层创建失败");
+    transport
+        .set_policy_set(allow_all_policy_set())
+        .await
+        .expect("设置策略集合失败");
+    transport
+}
+
+/// 允许所有连接的策略集合
+fn allow_all_policy_set() -> PolicySet {
+    PolicySet::new(
+        "allow-all".to_string(),
+        "允许所有".to_string(),
+        "测试用策略集合".to_string(),
+        PolicyAction::Allow,
+    )
+}
+
+#[tokio::test]
+async fn test_alpn_matching_protocol_connects() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-alpn-match");
+    let mut server =
+        create_alpn_test_transport(18447, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client =
+        create_alpn_test_transport(18448, &certificates_dir, "test-alpn-client", b"bey-test/1").await;
+
+    let server_addr = "127.0.0.1:18447".parse().expect("地址解析失败");
+    let connection = client.connect(server_addr).await.expect("ALPN一致时应连接成功");
+    let negotiated = connection
+        .handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        .and_then(|data| data.protocol);
+    assert_eq!(negotiated.as_deref(), Some(&b"bey-test/1"[..]));
+
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_quic_stats_for_loopback_connection() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-quic-stats");
+    let mut server =
+        create_alpn_test_transport(18451, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client =
+        create_alpn_test_transport(18452, &certificates_dir, "test-alpn-client", b"bey-test/1").await;
+
+    let server_addr = "127.0.0.1:18451".parse().expect("地址解析失败");
+    assert!(client.quic_stats(server_addr).await.is_none(), "未连接时不应有统计信息");
+
+    client.connect(server_addr).await.expect("连接失败");
+    let stats = client.quic_stats(server_addr).await.expect("应有连接统计信息");
+    assert!(stats.bytes_sent > 0, "握手后应已发送数据");
+    assert!(stats.bytes_received > 0, "握手后应已接收数据");
+    assert!(stats.congestion_window > 0);
+    assert!(stats.rtt > Duration::ZERO);
+
+    let unknown_addr = "127.0.0.1:18499".parse().expect("地址解析失败");
+    assert!(client.quic_stats(unknown_addr).await.is_none());
+
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_alpn_mismatched_protocol_rejected() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-alpn-mismatch");
+    let mut server =
+        create_alpn_test_transport(18449, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client =
+        create_alpn_test_transport(18450, &certificates_dir, "test-alpn-client", b"bey-test/2").await;
+
+    let server_addr = "127.0.0.1:18449".parse().expect("地址解析失败");
+    let error = client
+        .connect(server_addr)
+        .await
+        .expect_err("ALPN不一致时应拒绝握手");
+    assert_eq!(error.code(), ALPN_MISMATCH);
+
+    client.stop().await;
+    server.stop().await;
+}
+
+/// 创建客户端证书测试用传输层，服务端和客户端共用同一证书目录以信任同一 CA
+async fn create_client_cert_test_transport(
+    port: u16,
+    certificates_dir: &std::path::Path,
+    device_id: &str,
+    require_client_cert: bool,
+    present_client_cert: bool,
+) -> SecureTransport {
+    let config = TransportConfig::new()
+        .with_port(port)
+        .with_certificates_dir(certificates_dir)
+        .with_connection_timeout(Duration::from_secs(5))
+        .with_require_client_cert(require_client_cert)
+        .with_present_client_cert(present_client_cert)
+        .with_server_name("test-mtls-open.bey.local".to_string());
+    let mut transport = SecureTransport::new(config, device_id.to_string())
+        .await
+        .expect("传输层创建失败");
+    transport
+        .set_policy_set(PolicySet::new(
+            "allow-all".to_string(),
+            "允许所有".to_string(),
+            "客户端证书测试策略".to_string(),
+            PolicyAction::Allow,
+        ))
+        .await
+        .expect("设置策略集合失败");
+    transport
+}
+
+/// 等待服务端记录入站连接并返回其信任级别
+///
+/// 连接出现在连接表中时信任级别应已记录，不再等待
+async fn wait_for_inbound_trust(server: &SecureTransport) -> Option<TrustLevel> {
+    for _ in 0..50 {
+        if let Some(addr) = server.active_connections().await.first() {
+            let trust_level = server.peer_trust_level(*addr).await;
+            assert!(trust_level.is_some(), "连接可见时应已记录握手结果");
+            return trust_level;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    None
+}
+
+#[tokio::test]
+async fn test_client_cert_requirement_per_listener() {
+    init_logging();
+
+    // 两个监听器使用同一设备证书，仅客户端证书要求不同
+    let certificates_dir = std::env::temp_dir().join("bey-test-client-cert");
+    let mut open_listener =
+        create_client_cert_test_transport(18453, &certificates_dir, "test-mtls-open", false, true).await;
+    open_listener.start_server().await.expect("启动开放监听器失败");
+    let mut strict_listener =
+        create_client_cert_test_transport(18454, &certificates_dir, "test-mtls-open", true, true).await;
+    strict_listener.start_server().await.expect("启动严格监听器失败");
+
+    let anonymous =
+        create_client_cert_test_transport(18455, &certificates_dir, "test-mtls-anonymous", true, false).await;
+
+    // 开放监听器接受匿名客户端，并标记为较低的信任级别
+    let open_addr = "127.0.0.1:18453".parse().expect("地址解析失败");
+    anonymous.connect(open_addr).await.expect("开放监听器应接受匿名客户端");
+    assert_eq!(wait_for_inbound_trust(&open_listener).await, Some(TrustLevel::Untrusted));
+
+    // 严格监听器拒绝未出示证书的客户端
+    let strict_addr = "127.0.0.1:18454".parse().expect("地址解析失败");
+    if let Ok(connection) = anonymous.connect(strict_addr).await {
+        // TLS 1.3 中客户端可能先完成握手，随后才被服务端关闭
+        tokio::time::timeout(Duration::from_secs(5), connection.closed())
+            .await
+            .expect("严格监听器应关闭匿名连接");
+    }
+    assert!(strict_listener.active_connections().await.is_empty(), "严格监听器不应记录匿名连接");
+
+    // 出示证书的客户端可以连接严格监听器，信任级别更高
+    let authenticated =
+        create_client_cert_test_transport(18456, &certificates_dir, "test-mtls-client", true, true).await;
+    authenticated.connect(strict_addr).await.expect("出示证书的客户端应连接成功");
+    assert_eq!(wait_for_inbound_trust(&strict_listener).await, Some(TrustLevel::Trusted));
+
+    anonymous.stop().await;
+    authenticated.stop().await;
+    open_listener.stop().await;
+    strict_listener.stop().await;
+}
+
+#[tokio::test]
+async fn test_bidirectional_stream_loopback() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-raw-stream");
+    let mut server =
+        create_alpn_test_transport(18457, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client =
+        create_alpn_test_transport(18458, &certificates_dir, "test-alpn-client", b"bey-test/1").await;
+
+    let server_addr = "127.0.0.1:18457".parse().expect("地址解析失败");
+    let connection = client.connect(server_addr).await.expect("连接失败");
+
+    let payload: Vec<u8> = (0..8 * 1024).map(|i| (i % 251) as u8).collect();
+    let (mut send, mut recv) = client.open_stream(&connection).await.expect("打开双向流失败");
+    send.write_all(&payload).await.expect("写入流失败");
+    send.finish().expect("结束发送失败");
+
+    // 等待服务端记录入站连接
+    let mut inbound = None;
+    for _ in 0..50 {
+        if let Some(addr) = server.active_connections().await.first() {
+            inbound = server.connection(*addr).await;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let inbound = inbound.expect("服务端应有入站连接");
+
+    // 服务端原样回显
+    let (mut server_send, mut server_recv) = server.accept_stream(&inbound).await.expect("接受双向流失败");
+    let received = server_recv.read_to_end(64 * 1024).await.expect("服务端读取失败");
+    assert_eq!(received, payload);
+    server_send.write_all(&received).await.expect("服务端写入失败");
+    server_send.finish().expect("服务端结束发送失败");
+
+    let echoed = recv.read_to_end(64 * 1024).await.expect("客户端读取失败");
+    assert_eq!(echoed, payload);
+
+    // 双向流已由其他接受方声明的连接上不能再接受流
+    assert!(server.claim_streams(&inbound));
+    assert!(!server.claim_streams(&inbound), "同一连接只能声明一次");
+    let err = server.accept_stream(&inbound).await.expect_err("已声明的连接应拒绝接受流");
+    assert_eq!(err.code(), STREAM_ACCEPT_FAILED);
+
+    client.stop().await;
+    server.stop().await;
+}
+
+/// 等待下一个传输层事件
+async fn next_event(events: &mut tokio::sync::broadcast::Receiver<TransportEvent>) -> TransportEvent {
+    tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .expect("等待事件超时")
+        .expect("接收事件失败")
+}
+
+#[tokio::test]
+async fn test_connection_events_in_order() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-events");
+    let mut server =
+        create_alpn_test_transport(18459, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let mut server_events = server.events();
+    let client =
+        create_alpn_test_transport(18460, &certificates_dir, "test-alpn-client", b"bey-test/1").await;
+    let mut client_events = client.events();
+
+    let server_addr = "127.0.0.1:18459".parse().expect("地址解析失败");
+    let connection = client.connect(server_addr).await.expect("连接失败");
+    client.open_stream(&connection).await.expect("打开双向流失败");
+    client.disconnect(server_addr).await.expect("断开连接失败");
+
+    assert_eq!(
+        next_event(&mut client_events).await,
+        TransportEvent::Connected { remote_addr: server_addr, inbound: false }
+    );
+    assert_eq!(next_event(&mut client_events).await, TransportEvent::StreamOpened { remote_addr: server_addr });
+    assert_eq!(
+        next_event(&mut client_events).await,
+        TransportEvent::Disconnected { remote_addr: server_addr, code: Some(0), reason: "disconnect".to_string() }
+    );
+
+    // 服务端观察到入站连接建立，并在客户端关闭后断开
+    let client_addr = match next_event(&mut server_events).await {
+        TransportEvent::Connected { remote_addr, inbound: true } => remote_addr,
+        event => panic!("应为入站连接事件: {:?}", event),
+    };
+    assert_eq!(
+        next_event(&mut server_events).await,
+        TransportEvent::Disconnected { remote_addr: client_addr, code: Some(0), reason: "disconnect".to_string() }
+    );
+    assert!(server.active_connections().await.is_empty());
+
+    // ALPN不一致时双方都观察到握手失败
+    let mismatched =
+        create_alpn_test_transport(18461, &certificates_dir, "test-alpn-client", b"bey-test/2").await;
+    let mut mismatched_events = mismatched.events();
+    mismatched.connect(server_addr).await.expect_err("ALPN不一致时应拒绝握手");
+    match next_event(&mut mismatched_events).await {
+        TransportEvent::HandshakeFailed { remote_addr, reason } => {
+            assert_eq!(remote_addr, server_addr);
+            assert!(reason.contains("ALPN"), "失败原因应说明ALPN不匹配: {}", reason);
+        }
+        event => panic!("应为握手失败事件: {:?}", event),
+    }
+    assert!(matches!(next_event(&mut server_events).await, TransportEvent::HandshakeFailed { .. }));
+
+    mismatched.stop().await;
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_peer_observes_close_code() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-close-code");
+    let mut server =
+        create_alpn_test_transport(18462, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let mut server_events = server.events();
+    let client =
+        create_alpn_test_transport(18463, &certificates_dir, "test-alpn-client", b"bey-test/1").await;
+
+    let server_addr = "127.0.0.1:18462".parse().expect("地址解析失败");
+    client.connect(server_addr).await.expect("连接失败");
+    let client_addr = match next_event(&mut server_events).await {
+        TransportEvent::Connected { remote_addr, inbound: true } => remote_addr,
+        event => panic!("应为入站连接事件: {:?}", event),
+    };
+
+    client
+        .disconnect_with(server_addr, CloseCode::PolicyViolation.code(), "策略禁止")
+        .await
+        .expect("断开连接失败");
+
+    // 服务端从对端的应用关闭帧中读到关闭码和原因
+    match next_event(&mut server_events).await {
+        TransportEvent::Disconnected { remote_addr, code, reason } => {
+            assert_eq!(remote_addr, client_addr);
+            assert_eq!(code.and_then(CloseCode::from_code), Some(CloseCode::PolicyViolation));
+            assert_eq!(reason, "策略禁止");
+        }
+        event => panic!("应为断开事件: {:?}", event),
+    }
+
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_client_observes_server_close() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-server-close");
+    let mut server =
+        create_alpn_test_transport(18466, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let mut server_events = server.events();
+    let client =
+        create_alpn_test_transport(18467, &certificates_dir, "test-alpn-client", b"bey-test/1").await;
+    let mut client_events = client.events();
+
+    let server_addr = "127.0.0.1:18466".parse().expect("地址解析失败");
+    client.connect(server_addr).await.expect("连接失败");
+    assert_eq!(
+        next_event(&mut client_events).await,
+        TransportEvent::Connected { remote_addr: server_addr, inbound: false }
+    );
+    let client_addr = match next_event(&mut server_events).await {
+        TransportEvent::Connected { remote_addr, inbound: true } => remote_addr,
+        event => panic!("应为入站连接事件: {:?}", event),
+    };
+
+    server
+        .disconnect_with(client_addr, CloseCode::PolicyViolation.code(), "策略禁止")
+        .await
+        .expect("断开连接失败");
+
+    // 客户端的出站连接被服务端关闭后发出断开事件并从连接表中移除
+    match next_event(&mut client_events).await {
+        TransportEvent::Disconnected { remote_addr, code, reason } => {
+            assert_eq!(remote_addr, server_addr);
+            assert_eq!(code.and_then(CloseCode::from_code), Some(CloseCode::PolicyViolation));
+            assert_eq!(reason, "策略禁止");
+        }
+        event => panic!("应为断开事件: {:?}", event),
+    }
+    assert!(client.active_connections().await.is_empty());
+
+    client.stop().await;
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn test_cert_status_query_over_connection() {
+    use bey_identity::{CertStatusRequest, CertificateStatus};
+    use bey_transport::TransportCertStatus;
+    use std::sync::Arc;
+
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join("bey-test-cert-status");
+    let mut server =
+        create_alpn_test_transport(18464, &certificates_dir, "test-alpn-server", b"bey-test/1").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client =
+        Arc::new(create_alpn_test_transport(18465, &certificates_dir, "test-alpn-client", b"bey-test/1").await);
+
+    let issuer = server.certificate_manager();
+    let certificate = issuer.issue_device_certificate("status-peer").await.expect("证书签发失败");
+    assert!(issuer.revoke_device_certificate("status-peer").await.expect("吊销失败"));
+
+    // 服务端的应答任务签名作答并回显随机数
+    let server_addr = "127.0.0.1:18464".parse().expect("地址解析失败");
+    let connection = client.connect(server_addr).await.expect("连接失败");
+    let request = CertStatusRequest { fingerprint: certificate.fingerprint.clone(), nonce: 42 };
+    let response = client.request_cert_status(&connection, &request, Duration::from_secs(3))
+        .await
+        .expect("状态查询失败");
+    assert_eq!(response.status, CertificateStatus::Revoked);
+    assert_eq!(response.nonce, 42);
+    assert!(!response.signature.is_empty(), "响应应由签发节点签名");
+
+    // 经由证书管理器查询时校验签名
+    let status_transport = TransportCertStatus::new(Arc::clone(&client));
+    status_transport.set_peer_addrs("test-alpn-server", vec![server_addr]).await;
+    let querier = client.certificate_manager();
+    querier.set_status_transport(Arc::new(status_transport)).await;
+    let status = querier.query_cert_status("test-alpn-server", &certificate.fingerprint)
+        .await
+        .expect("状态查询失败");
+    assert_eq!(status, CertificateStatus::Revoked);
+
+    client.stop().await;
+    server.stop().await;
+}
+
+/// 创建启用TOFU固定的测试用传输层
+async fn create_tofu_test_transport(port: u16, certificates_dir: &std::path::Path, device_id: &str) -> SecureTransport {
+    let config = TransportConfig::new()
+        .with_port(port)
+        .with_certificates_dir(certificates_dir)
+        .with_connection_timeout(Duration::from_secs(5))
+        .with_server_name("test-alpn-server.bey.local".to_string())
+        .with_tofu_pinning(true);
+    let mut transport = SecureTransport::new(config, device_id.to_string())
+        .await
+        .expect("传输层创建失败");
+    transport
+        .set_policy_set(PolicySet::new(
+            "allow-all".to_string(),
+            "允许所有".to_string(),
+            "TOFU测试策略".to_string(),
+            PolicyAction::Allow,
+        ))
+        .await
+        .expect("设置策略集合失败");
+    transport
+}
+
+#[tokio::test]
+async fn test_tofu_rejects_reissued_server_certificate() {
+    init_logging();
+
+    let certificates_dir = std::env::temp_dir().join(format!("bey-test-tofu-{}", std::process::id()));
+    let mut server = create_tofu_test_transport(18468, &certificates_dir, "test-alpn-server").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client = create_tofu_test_transport(18469, &certificates_dir, "test-alpn-client").await;
+
+    // 第一次握手固定服务端证书，之后相同的证书继续被接受
+    let server_addr = "127.0.0.1:18468".parse().expect("地址解析失败");
+    client.connect(server_addr).await.expect("首次连接应被信任");
+    client.disconnect(server_addr).await.expect("断开连接失败");
+    client.connect(server_addr).await.expect("相同的证书应被接受");
+
+    // 服务端以相同的设备ID重新签发证书，证书链仍然有效但与固定的证书不一致
+    server.update_certificates().await.expect("更新证书失败");
+    server.stop().await;
+    let mut reissued = create_tofu_test_transport(18470, &certificates_dir, "test-alpn-server").await;
+    reissued.start_server().await.expect("启动服务端失败");
+
+    let error = client
+        .connect("127.0.0.1:18470".parse().expect("地址解析失败"))
+        .await
+        .expect_err("变更的服务端证书应被拒绝");
+    assert!(error.context().iter().any(|c| c == "handshake_failure=pin_mismatch"));
+    assert_eq!(error.severity(), error::ErrorSeverity::Critical);
+
+    client.stop().await;
+    reissued.stop().await;
+    let _ = std::fs::remove_dir_all(&certificates_dir);
+}
+
+/// 创建使用独立证书目录（即独立CA）的测试用传输层
+async fn create_cluster_test_transport(
+    port: u16,
+    certificates_dir: &std::path::Path,
+    device_id: &str,
+    organization: &str,
+) -> SecureTransport {
+    let config = TransportConfig::new()
+        .with_port(port)
+        .with_certificates_dir(certificates_dir)
+        .with_connection_timeout(Duration::from_secs(5))
+        .with_organization_name(organization.to_string())
+        .with_server_name("test-alpn-server.bey.local".to_string());
+    let mut transport = SecureTransport::new(config, device_id.to_string())
+        .await
+        .expect("传输层创建失败");
+    transport
+        .set_policy_set(PolicySet::new(
+            "allow-all".to_string(),
+            "允许所有".to_string(),
+            "交叉签名测试策略".to_string(),
+            PolicyAction::Allow,
+        ))
+        .await
+        .expect("设置策略集合失败");
+    transport
+}
+
+#[tokio::test]
+async fn test_cross_signed_clusters_complete_handshake() {
+    init_logging();
+
+    let server_dir = std::env::temp_dir().join(format!("bey-test-cross-server-{}", std::process::id()));
+    let client_dir = std::env::temp_dir().join(format!("bey-test-cross-client-{}", std::process::id()));
+    let mut server = create_cluster_test_transport(18471, &server_dir, "test-alpn-server", "Cluster A").await;
+    server.start_server().await.expect("启动服务端失败");
+    let client = create_cluster_test_transport(18472, &client_dir, "test-alpn-client", "Cluster B").await;
+
+    // 两个集群的CA互不信任
+    let server_addr = "127.0.0.1:18471".parse().expect("地址解析失败");
+    let error = client.connect(server_addr).await.expect_err("不同CA的设备不应完成握手");
+    assert!(error.context().iter().any(|c| c == "handshake_failure=untrusted_root"));
+
+    // 互相交叉签名后，双方经由交叉证书验证对端证书链，正在监听的服务端立即生效
+    let server_ca = server.certificate_manager().ca_certificate_der().await.expect("获取CA证书失败");
+    let client_ca = client.certificate_manager().ca_certificate_der().await.expect("获取CA证书失败");
+    client.cross_sign(&server_ca).await.expect("交叉签名失败");
+    server.cross_sign(&client_ca).await.expect("交叉签名失败");
+
+    client.connect(server_addr).await.expect("交叉签名后应完成握手");
+    assert_eq!(wait_for_inbound_trust(&server).await, Some(TrustLevel::Trusted));
+
+    client.stop().await;
+    server.stop().await;
+    let _ = std::fs::remove_dir_all(&server_dir);
+    let _ = std::fs::remove_dir_all(&client_dir);
+}
@@ -0,0 +1,91 @@
+//! # 命令补全
+//!
+//! 命令模式下按已输入的前缀提示可用命令，Tab 补全到所有匹配命令的最长公共前缀。
+
+/// 命令模式可用的命令及说明
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("devices", "列出设备"),
+    ("clear", "清空日志"),
+    ("help", "显示帮助"),
+    ("quit", "退出程序"),
+    ("dash", "显示仪表盘"),
+];
+
+/// 查找以输入为前缀的命令
+///
+/// 输入已包含参数（命令名后有空白）时不再提示
+///
+/// # 参数
+///
+/// * `input` - 已输入的命令（不含前导 `:`）
+///
+/// # 返回
+///
+/// 按命令表顺序返回匹配的命令及说明
+pub fn matching_commands(input: &str) -> Vec<(&'static str, &'static str)> {
+    let prefix = input.trim_start();
+    if prefix.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+
+    COMMANDS
+        .iter()
+        .filter(|(name, _)| name.starts_with(prefix))
+        .copied()
+        .collect()
+}
+
+/// 补全命令到所有匹配命令的最长公共前缀
+///
+/// # 参数
+///
+/// * `input` - 已输入的命令（不含前导 `:`）
+///
+/// # 返回
+///
+/// 能够补全更多字符时返回补全后的命令，否则返回 `None`
+pub fn complete_command(input: &str) -> Option<String> {
+    let prefix = input.trim_start();
+    let matches = matching_commands(prefix);
+    let (first, rest) = matches.split_first()?;
+
+    let common_len = rest.iter().fold(first.0.len(), |len, (name, _)| {
+        first.0.bytes()
+            .zip(name.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    let completed = &first.0[..common_len];
+
+    (completed.len() > prefix.len()).then(|| completed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_match_and_completion() {
+        let names = |input: &str| -> Vec<&str> {
+            matching_commands(input).into_iter().map(|(name, _)| name).collect()
+        };
+
+        // 空输入列出所有命令
+        assert_eq!(names("").len(), COMMANDS.len());
+        assert_eq!(names("d"), vec!["devices", "dash"]);
+        assert_eq!(names("q"), vec!["quit"]);
+        assert!(names("x").is_empty());
+        assert!(names("devices now").is_empty());
+
+        // 唯一匹配补全为完整命令，多个匹配补全到最长公共前缀
+        assert_eq!(complete_command("he"), Some("help".to_string()));
+        assert_eq!(complete_command("  c"), Some("clear".to_string()));
+        assert_eq!(complete_command("d"), None);
+        assert_eq!(complete_command("de"), Some("devices".to_string()));
+
+        // 已完整或无匹配时不补全
+        assert_eq!(complete_command("quit"), None);
+        assert_eq!(complete_command("x"), None);
+    }
+}
@@ -0,0 +1,238 @@
+//! # 仪表盘
+//!
+//! 显示仪表盘时每秒采集一次 CPU、内存使用率以及连接数和传输吞吐量，
+//! 保存在固定窗口的环形缓冲中，供仪表盘模式绘制实时图表。
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// 仪表盘保留的采样点数量（按每秒一次的采样约为 2 分钟）
+pub const DASHBOARD_WINDOW: usize = 120;
+
+/// 固定窗口的采样历史
+///
+/// 采样数达到窗口大小后，新的采样会挤掉最早的采样
+#[derive(Debug, Clone)]
+pub struct SampleHistory {
+    /// 采样值（从旧到新）
+    samples: VecDeque<f64>,
+    /// 窗口大小
+    capacity: usize,
+}
+
+impl SampleHistory {
+    /// 创建采样历史
+    ///
+    /// # 参数
+    ///
+    /// * `capacity` - 窗口大小（至少为1）
+    ///
+    /// # 返回
+    ///
+    /// 返回空的采样历史
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// 追加采样，超出窗口时丢弃最早的采样
+    ///
+    /// # 参数
+    ///
+    /// * `value` - 采样值
+    pub fn push(&mut self, value: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    /// 窗口大小
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 当前采样数
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 是否没有采样
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// 最新的采样值
+    pub fn latest(&self) -> Option<f64> {
+        self.samples.back().copied()
+    }
+
+    /// 窗口内的最大采样值
+    pub fn max(&self) -> f64 {
+        self.samples.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// 按从旧到新的顺序遍历采样值
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples.iter().copied()
+    }
+
+    /// 转换为 `Sparkline` 使用的数据（负值按0处理）
+    pub fn to_sparkline(&self) -> Vec<u64> {
+        self.samples.iter().map(|value| value.max(0.0).round() as u64).collect()
+    }
+
+    /// 转换为 `Chart` 使用的坐标点，横坐标为采样序号
+    pub fn to_points(&self) -> Vec<(f64, f64)> {
+        self.samples
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (i as f64, *value))
+            .collect()
+    }
+}
+
+/// 仪表盘采样数据
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    /// CPU 使用率（百分比）
+    pub cpu: SampleHistory,
+    /// 内存使用率（百分比）
+    pub memory: SampleHistory,
+    /// 活跃连接数
+    pub connections: SampleHistory,
+    /// 传输吞吐量（字节/秒，发送与接收之和）
+    pub throughput: SampleHistory,
+    /// 上一次采样的累计传输字节数和采样时间
+    last_transfer: Option<(u64, Instant)>,
+}
+
+impl Dashboard {
+    /// 创建仪表盘
+    ///
+    /// # 参数
+    ///
+    /// * `window` - 每项指标保留的采样点数量
+    pub fn new(window: usize) -> Self {
+        Self {
+            cpu: SampleHistory::new(window),
+            memory: SampleHistory::new(window),
+            connections: SampleHistory::new(window),
+            throughput: SampleHistory::new(window),
+            last_transfer: None,
+        }
+    }
+
+    /// 记录一次采样
+    ///
+    /// 吞吐量由两次采样之间累计传输字节数的差值计算，第一次采样只记录基准
+    ///
+    /// # 参数
+    ///
+    /// * `cpu_percent` - CPU 使用率
+    /// * `memory_percent` - 内存使用率
+    /// * `active_connections` - 活跃连接数
+    /// * `total_bytes` - 累计发送和接收的字节数
+    /// * `now` - 采样时间
+    pub fn record(
+        &mut self,
+        cpu_percent: f64,
+        memory_percent: f64,
+        active_connections: usize,
+        total_bytes: u64,
+        now: Instant,
+    ) {
+        self.cpu.push(cpu_percent);
+        self.memory.push(memory_percent);
+        self.connections.push(active_connections as f64);
+
+        if let Some((last_bytes, last_at)) = self.last_transfer {
+            let elapsed = now.saturating_duration_since(last_at).as_secs_f64();
+            if elapsed > 0.0 {
+                let delta = total_bytes.saturating_sub(last_bytes) as f64;
+                self.throughput.push(delta / elapsed);
+            }
+        }
+        self.last_transfer = Some((total_bytes, now));
+    }
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self::new(DASHBOARD_WINDOW)
+    }
+}
+
+/// 格式化吞吐量
+///
+/// # 参数
+///
+/// * `bytes_per_sec` - 每秒字节数
+///
+/// # 返回
+///
+/// 返回带单位的吞吐量描述
+pub fn format_throughput(bytes_per_sec: f64) -> String {
+    const UNITS: [&str; 4] = ["B/s", "KB/s", "MB/s", "GB/s"];
+    let mut value = bytes_per_sec.max(0.0);
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sample_history_push_and_cap() {
+        let mut history = SampleHistory::new(3);
+        assert!(history.is_empty());
+        assert_eq!(history.latest(), None);
+
+        for value in [1.0, 2.0, 3.0] {
+            history.push(value);
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+
+        // 达到窗口大小后丢弃最早的采样
+        history.push(4.0);
+        history.push(5.0);
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.capacity(), 3);
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec![3.0, 4.0, 5.0]);
+        assert_eq!(history.latest(), Some(5.0));
+        assert_eq!(history.max(), 5.0);
+        assert_eq!(history.to_sparkline(), vec![3, 4, 5]);
+        assert_eq!(history.to_points(), vec![(0.0, 3.0), (1.0, 4.0), (2.0, 5.0)]);
+
+        // 窗口大小至少为1
+        let mut single = SampleHistory::new(0);
+        single.push(1.0);
+        single.push(2.0);
+        assert_eq!(single.iter().collect::<Vec<_>>(), vec![2.0]);
+    }
+
+    #[test]
+    fn test_dashboard_throughput_from_byte_delta() {
+        let mut dashboard = Dashboard::new(4);
+        let start = Instant::now();
+
+        dashboard.record(10.0, 50.0, 1, 1000, start);
+        assert!(dashboard.throughput.is_empty(), "第一次采样只记录基准");
+
+        dashboard.record(20.0, 51.0, 2, 3000, start + Duration::from_secs(2));
+        assert_eq!(dashboard.throughput.latest(), Some(1000.0));
+        assert_eq!(dashboard.cpu.len(), 2);
+        assert_eq!(dashboard.connections.latest(), Some(2.0));
+        assert_eq!(format_throughput(1536.0), "1.5 KB/s");
+    }
+}
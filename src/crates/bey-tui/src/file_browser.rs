@@ -0,0 +1,332 @@
+//! # 文件浏览器
+//!
+//! 为文件选择模式提供目录列表、分页和导航逻辑，与界面绘制解耦。
+//! 无法读取的目录（如权限不足）不会中断浏览，只记录错误信息并停留在当前目录。
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 目录条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserEntry {
+    /// 条目名称
+    pub name: String,
+    /// 完整路径
+    pub path: PathBuf,
+    /// 是否为目录
+    pub is_dir: bool,
+    /// 文件大小（字节，目录为0）
+    pub size: u64,
+}
+
+/// 激活条目的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowserAction {
+    /// 进入了目录
+    EnteredDirectory(PathBuf),
+    /// 选中了文件
+    SelectedFile(PathBuf),
+    /// 没有发生变化（无条目或目录无法打开）
+    Unchanged,
+}
+
+/// 分页文件浏览器
+#[derive(Debug)]
+pub struct FileBrowser {
+    /// 当前目录
+    current_dir: PathBuf,
+    /// 当前目录的条目（目录在前，按名称排序）
+    entries: Vec<BrowserEntry>,
+    /// 选中的条目索引
+    selected: usize,
+    /// 每页条目数
+    page_size: usize,
+    /// 最近一次导航错误
+    error: Option<String>,
+}
+
+impl FileBrowser {
+    /// 创建文件浏览器
+    ///
+    /// # 参数
+    ///
+    /// * `start_dir` - 起始目录
+    /// * `page_size` - 每页显示的条目数
+    ///
+    /// # 返回
+    ///
+    /// 返回文件浏览器；起始目录无法读取时条目为空并记录错误信息
+    pub fn new(start_dir: impl Into<PathBuf>, page_size: usize) -> Self {
+        let start_dir = start_dir.into();
+        let mut browser = Self {
+            current_dir: start_dir.clone(),
+            entries: Vec::new(),
+            selected: 0,
+            page_size: page_size.max(1),
+            error: None,
+        };
+
+        if let Err(e) = browser.load(start_dir.clone()) {
+            browser.error = Some(describe_error(&start_dir, &e));
+        }
+        browser
+    }
+
+    /// 列出目录内容
+    ///
+    /// 目录排在文件之前，同类条目按名称排序；无法读取元数据的条目会被跳过。
+    ///
+    /// # 参数
+    ///
+    /// * `dir` - 要列出的目录
+    ///
+    /// # 返回
+    ///
+    /// 返回目录条目列表，目录无法打开时返回IO错误
+    pub fn list_directory(dir: &Path) -> io::Result<Vec<BrowserEntry>> {
+        let mut entries: Vec<BrowserEntry> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some(BrowserEntry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    path: entry.path(),
+                    is_dir: metadata.is_dir(),
+                    size: if metadata.is_dir() { 0 } else { metadata.len() },
+                })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    /// 加载目录，失败时保持当前状态不变
+    fn load(&mut self, dir: PathBuf) -> io::Result<()> {
+        self.entries = Self::list_directory(&dir)?;
+        self.current_dir = dir;
+        self.selected = 0;
+        self.error = None;
+        Ok(())
+    }
+
+    /// 当前目录
+    pub fn current_dir(&self) -> &Path {
+        &self.current_dir
+    }
+
+    /// 当前目录的全部条目
+    pub fn entries(&self) -> &[BrowserEntry] {
+        &self.entries
+    }
+
+    /// 选中的条目索引
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// 选中的条目
+    pub fn selected(&self) -> Option<&BrowserEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// 最近一次导航错误
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// 当前页码（从0开始）
+    pub fn page(&self) -> usize {
+        self.selected / self.page_size
+    }
+
+    /// 总页数（至少为1）
+    pub fn page_count(&self) -> usize {
+        self.entries.len().div_ceil(self.page_size).max(1)
+    }
+
+    /// 当前页的条目
+    pub fn page_entries(&self) -> &[BrowserEntry] {
+        let start = (self.page() * self.page_size).min(self.entries.len());
+        let end = (start + self.page_size).min(self.entries.len());
+        &self.entries[start..end]
+    }
+
+    /// 选择下一个条目
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// 选择上一个条目
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// 翻到下一页
+    pub fn next_page(&mut self) {
+        if self.page() + 1 < self.page_count() {
+            self.selected = (self.page() + 1) * self.page_size;
+        }
+    }
+
+    /// 翻到上一页
+    pub fn previous_page(&mut self) {
+        self.selected = self.page().saturating_sub(1) * self.page_size;
+    }
+
+    /// 激活选中的条目
+    ///
+    /// 目录会被打开，文件会被选中。目录无法打开时记录错误并停留在当前目录。
+    pub fn activate(&mut self) -> BrowserAction {
+        let Some(entry) = self.selected().cloned() else {
+            return BrowserAction::Unchanged;
+        };
+
+        if !entry.is_dir {
+            return BrowserAction::SelectedFile(entry.path);
+        }
+
+        match self.load(entry.path.clone()) {
+            Ok(()) => BrowserAction::EnteredDirectory(entry.path),
+            Err(e) => {
+                self.error = Some(describe_error(&entry.path, &e));
+                BrowserAction::Unchanged
+            }
+        }
+    }
+
+    /// 返回上级目录
+    ///
+    /// # 返回
+    ///
+    /// 成功进入上级目录返回 `true`，已在根目录或上级目录无法打开时返回 `false`
+    pub fn go_up(&mut self) -> bool {
+        let Some(parent) = self.current_dir.parent().map(Path::to_path_buf) else {
+            return false;
+        };
+
+        let previous = self.current_dir.clone();
+        match self.load(parent.clone()) {
+            Ok(()) => {
+                // 返回后选中原来所在的目录
+                if let Some(index) = self.entries.iter().position(|entry| entry.path == previous) {
+                    self.selected = index;
+                }
+                true
+            }
+            Err(e) => {
+                self.error = Some(describe_error(&parent, &e));
+                false
+            }
+        }
+    }
+}
+
+/// 生成目录打开失败的提示信息
+fn describe_error(dir: &Path, error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => format!("无权访问目录: {}", dir.display()),
+        io::ErrorKind::NotFound => format!("目录不存在: {}", dir.display()),
+        _ => format!("无法打开目录 {}: {}", dir.display(), error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn create_tree(root: &Path) {
+        std::fs::create_dir(root.join("docs")).expect("创建目录失败");
+        std::fs::create_dir(root.join("assets")).expect("创建目录失败");
+        std::fs::write(root.join("b.txt"), b"bbb").expect("写入文件失败");
+        std::fs::write(root.join("a.txt"), b"a").expect("写入文件失败");
+        std::fs::write(root.join("docs").join("readme.md"), b"# readme").expect("写入文件失败");
+    }
+
+    #[test]
+    fn test_list_directory_sorted_dirs_first() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        create_tree(temp_dir.path());
+
+        let entries = FileBrowser::list_directory(temp_dir.path()).expect("列出目录失败");
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["assets", "docs", "a.txt", "b.txt"]);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[3].size, 3);
+    }
+
+    #[test]
+    fn test_navigate_and_select_file() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        create_tree(temp_dir.path());
+
+        let mut browser = FileBrowser::new(temp_dir.path(), 10);
+        browser.select_next();
+        assert_eq!(browser.activate(), BrowserAction::EnteredDirectory(temp_dir.path().join("docs")));
+        assert_eq!(browser.entries().len(), 1);
+
+        assert_eq!(browser.activate(), BrowserAction::SelectedFile(temp_dir.path().join("docs").join("readme.md")));
+
+        // 返回上级目录后仍选中原目录
+        assert!(browser.go_up());
+        assert_eq!(browser.current_dir(), temp_dir.path());
+        assert_eq!(browser.selected().map(|entry| entry.name.as_str()), Some("docs"));
+    }
+
+    #[test]
+    fn test_pagination() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        for i in 0..7 {
+            std::fs::write(temp_dir.path().join(format!("file{}.txt", i)), b"x").expect("写入文件失败");
+        }
+
+        let mut browser = FileBrowser::new(temp_dir.path(), 3);
+        assert_eq!(browser.page_count(), 3);
+        assert_eq!(browser.page_entries().len(), 3);
+
+        browser.next_page();
+        browser.next_page();
+        assert_eq!(browser.page(), 2);
+        assert_eq!(browser.page_entries().len(), 1);
+        assert_eq!(browser.selected().map(|entry| entry.name.as_str()), Some("file6.txt"));
+
+        // 已是最后一页，不再翻页
+        browser.next_page();
+        assert_eq!(browser.page(), 2);
+
+        browser.previous_page();
+        assert_eq!(browser.selected_index(), 3);
+    }
+
+    #[test]
+    fn test_unreadable_directory_keeps_current_dir() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let missing = temp_dir.path().join("missing");
+
+        let browser = FileBrowser::new(&missing, 10);
+        assert!(browser.entries().is_empty());
+        assert!(browser.error().expect("应该记录错误").contains("目录不存在"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let locked = temp_dir.path().join("locked");
+            std::fs::create_dir(&locked).expect("创建目录失败");
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).expect("设置权限失败");
+
+            // 以特权用户运行时权限不受限制，跳过该检查
+            if std::fs::read_dir(&locked).is_err() {
+                let mut browser = FileBrowser::new(temp_dir.path(), 10);
+                assert_eq!(browser.activate(), BrowserAction::Unchanged);
+                assert_eq!(browser.current_dir(), temp_dir.path());
+                assert!(browser.error().expect("应该记录错误").contains("无权访问目录"));
+            }
+
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).expect("恢复权限失败");
+        }
+    }
+}
@@ -4,13 +4,15 @@
 //!
 //! ## 功能
 //!
+//! - 设备、日志、详情三栏布局（可调整各栏比例）
 //! - 设备列表视图
 //! - 实时日志查看器
 //! - 状态监控面板
+//! - 仪表盘模式（CPU/内存使用率、连接数和吞吐量的实时图表）
 //! - 交互式命令输入
 //! - 消息发送功能（私信、群聊、广播）
 //! - 剪切板同步功能
-//! - 文件传输功能
+//! - 文件传输功能（分页文件浏览器选择本地文件）
 //!
 //! ## 使用示例
 //!
@@ -42,13 +44,35 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
+    symbols,
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType, List, ListItem, Paragraph, Sparkline, Wrap},
     Frame, Terminal,
 };
 use bey_func::BeyFuncManager;
+use sys::SystemInfo;
+use tokio::sync::mpsc;
+
+pub mod commands;
+pub mod dashboard;
+pub mod file_browser;
+pub mod panes;
+
+pub use commands::{complete_command, matching_commands, COMMANDS};
+pub use dashboard::{Dashboard, SampleHistory, DASHBOARD_WINDOW};
+pub use file_browser::{BrowserAction, BrowserEntry, FileBrowser};
+pub use panes::{PaneDivider, PaneLayout, MIN_PANE_PERCENT, PANE_RESIZE_STEP};
 
 pub type TuiResult<T> = Result<T, ErrorInfo>;
 
+/// 通过文件浏览器填写的表单字段名
+const FILE_PATH_FIELD: &str = "文件路径";
+
+/// 文件浏览器每页显示的条目数
+const FILE_PICKER_PAGE_SIZE: usize = 20;
+
+/// 仪表盘的采样间隔
+const DASHBOARD_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
 /// TUI 应用状态
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppMode {
@@ -62,6 +86,10 @@ pub enum AppMode {
     OperationMenu,
     /// 输入表单模式
     InputForm(OperationType),
+    /// 文件选择模式，选中文件后返回对应操作的输入表单
+    FilePicker(OperationType),
+    /// 仪表盘模式
+    Dashboard,
 }
 
 /// 操作类型
@@ -124,6 +152,81 @@ impl LogLevel {
     }
 }
 
+/// 后台操作结果，成功时为日志消息，失败时为错误描述
+pub type OperationOutcome = Result<String, String>;
+
+/// 后台操作执行器
+///
+/// 每个操作在独立的任务中执行，结果通过 mpsc 通道回传，
+/// 由事件循环在定时更新时收取，避免网络调用阻塞界面绘制和输入处理。
+pub struct OperationRunner {
+    /// 结果发送端（每个任务持有一份克隆）
+    sender: mpsc::UnboundedSender<OperationOutcome>,
+    /// 结果接收端
+    receiver: mpsc::UnboundedReceiver<OperationOutcome>,
+    /// 正在执行的操作数量
+    in_flight: usize,
+}
+
+impl OperationRunner {
+    /// 创建新的操作执行器
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            sender,
+            receiver,
+            in_flight: 0,
+        }
+    }
+
+    /// 在后台任务中执行操作
+    ///
+    /// # 参数
+    ///
+    /// * `operation` - 要执行的操作，完成后返回操作结果
+    pub fn spawn<F>(&mut self, operation: F)
+    where
+        F: Future<Output = OperationOutcome> + Send + 'static,
+    {
+        let sender = self.sender.clone();
+        self.in_flight += 1;
+
+        tokio::spawn(async move {
+            // 操作任务异常终止时也要回传结果，保证执行中计数能够归零
+            let outcome = match tokio::spawn(operation).await {
+                Ok(outcome) => outcome,
+                Err(e) => Err(format!("操作任务异常终止: {}", e)),
+            };
+            let _ = sender.send(outcome);
+        });
+    }
+
+    /// 获取正在执行的操作数量
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// 收取所有已完成的操作结果，不会阻塞
+    ///
+    /// # 返回
+    ///
+    /// 返回按完成顺序排列的操作结果
+    pub fn poll_completed(&mut self) -> Vec<OperationOutcome> {
+        let mut completed = Vec::new();
+        while let Ok(outcome) = self.receiver.try_recv() {
+            self.in_flight = self.in_flight.saturating_sub(1);
+            completed.push(outcome);
+        }
+        completed
+    }
+}
+
+impl Default for OperationRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// TUI 应用程序
 pub struct TuiApp {
     /// 功能管理器
@@ -146,6 +249,18 @@ pub struct TuiApp {
     form_fields: Vec<(String, String)>, // (field_name, field_value)
     /// 当前聚焦的表单字段
     focused_field: usize,
+    /// 后台操作执行器
+    operations: OperationRunner,
+    /// 文件选择模式下的文件浏览器
+    file_browser: Option<FileBrowser>,
+    /// 仪表盘采样数据
+    dashboard: Dashboard,
+    /// 系统信息，首次打开仪表盘时创建
+    system: Option<SystemInfo>,
+    /// 上次为仪表盘采样的时间
+    last_sample: Option<Instant>,
+    /// 主界面三栏布局，本次运行期间保持
+    panes: PaneLayout,
 }
 
 impl TuiApp {
@@ -170,6 +285,12 @@ impl TuiApp {
             selected_operation: 0,
             form_fields: Vec::new(),
             focused_field: 0,
+            operations: OperationRunner::new(),
+            file_browser: None,
+            dashboard: Dashboard::default(),
+            system: None,
+            last_sample: None,
+            panes: PaneLayout::default(),
         }
     }
 
@@ -268,11 +389,17 @@ impl TuiApp {
                 ErrorInfo::new(9000, "TUI错误".to_string())
                     .with_context(format!("轮询事件失败: {}", e))
             })? {
-                if let Event::Key(key) = event::read().map_err(|e| {
+                match event::read().map_err(|e| {
                     ErrorInfo::new(9000, "TUI错误".to_string())
                         .with_context(format!("读取事件失败: {}", e))
                 })? {
-                    self.handle_key_event(key).await;
+                    Event::Key(key) => self.handle_key_event(key).await,
+                    // 终端尺寸变化后按新尺寸重绘，各栏按比例重新计算位置
+                    Event::Resize(_, _) => terminal.autoresize().map_err(|e| {
+                        ErrorInfo::new(9000, "TUI错误".to_string())
+                            .with_context(format!("调整终端尺寸失败: {}", e))
+                    })?,
+                    _ => {}
                 }
             }
 
@@ -303,6 +430,13 @@ impl TuiApp {
                         self.command_input.clear();
                     }
                     KeyCode::Char('?') => self.mode = AppMode::Help,
+                    KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.panes.shift_left()
+                    }
+                    KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.panes.shift_right()
+                    }
+                    KeyCode::Tab => self.panes.toggle_divider(),
                     KeyCode::Char('o') | KeyCode::Char('O') => {
                         self.mode = AppMode::OperationMenu;
                         self.selected_operation = 0;
@@ -341,8 +475,11 @@ impl TuiApp {
             }
             AppMode::InputForm(ref op_type) => {
                 match key.code {
+                    KeyCode::Enter if self.focused_file_path_is_empty() => {
+                        self.open_file_picker(op_type.clone());
+                    }
                     KeyCode::Enter => {
-                        self.execute_operation(op_type.clone()).await;
+                        self.execute_operation(op_type.clone());
                         self.mode = AppMode::Normal;
                     }
                     KeyCode::Esc => {
@@ -371,18 +508,28 @@ impl TuiApp {
                     _ => {}
                 }
             }
+            AppMode::FilePicker(ref op_type) => {
+                let op_type = op_type.clone();
+                self.handle_file_picker_key(op_type, key);
+            }
             AppMode::Command => {
                 match key.code {
                     KeyCode::Enter => {
                         let cmd = self.command_input.clone();
-                        self.execute_command(&cmd).await;
                         self.command_input.clear();
+                        // 命令可以切换到其他模式，因此先回到正常模式再执行
                         self.mode = AppMode::Normal;
+                        self.execute_command(&cmd).await;
                     }
                     KeyCode::Esc => {
                         self.command_input.clear();
                         self.mode = AppMode::Normal;
                     }
+                    KeyCode::Tab => {
+                        if let Some(completed) = complete_command(&self.command_input) {
+                            self.command_input = completed;
+                        }
+                    }
                     KeyCode::Char(c) => {
                         self.command_input.push(c);
                     }
@@ -397,6 +544,65 @@ impl TuiApp {
                     self.mode = AppMode::Normal;
                 }
             }
+            AppMode::Dashboard => {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                    self.mode = AppMode::Normal;
+                }
+            }
+        }
+    }
+
+    /// 当前聚焦的字段是否为尚未填写的文件路径
+    fn focused_file_path_is_empty(&self) -> bool {
+        self.form_fields
+            .get(self.focused_field)
+            .is_some_and(|(name, value)| name == FILE_PATH_FIELD && value.is_empty())
+    }
+
+    /// 打开文件浏览器
+    ///
+    /// 从当前工作目录开始浏览，工作目录无法获取时从根目录开始
+    fn open_file_picker(&mut self, op_type: OperationType) {
+        let start_dir = std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("/"));
+        self.file_browser = Some(FileBrowser::new(start_dir, FILE_PICKER_PAGE_SIZE));
+        self.mode = AppMode::FilePicker(op_type);
+    }
+
+    /// 处理文件选择模式下的按键
+    fn handle_file_picker_key(&mut self, op_type: OperationType, key: KeyEvent) {
+        let Some(browser) = self.file_browser.as_mut() else {
+            self.mode = AppMode::InputForm(op_type);
+            return;
+        };
+
+        match key.code {
+            KeyCode::Up => browser.select_previous(),
+            KeyCode::Down => browser.select_next(),
+            KeyCode::PageUp => browser.previous_page(),
+            KeyCode::PageDown => browser.next_page(),
+            KeyCode::Backspace | KeyCode::Left => {
+                browser.go_up();
+            }
+            KeyCode::Enter | KeyCode::Right => match browser.activate() {
+                BrowserAction::SelectedFile(path) => {
+                    if let Some(field) = self.form_fields.iter_mut().find(|(name, _)| name == FILE_PATH_FIELD) {
+                        field.1 = path.display().to_string();
+                    }
+                    self.file_browser = None;
+                    self.mode = AppMode::InputForm(op_type);
+                }
+                BrowserAction::EnteredDirectory(_) => {}
+                BrowserAction::Unchanged => {
+                    if let Some(error) = browser.error().map(str::to_string) {
+                        self.add_log(LogLevel::Warn, error);
+                    }
+                }
+            },
+            KeyCode::Esc => {
+                self.file_browser = None;
+                self.mode = AppMode::InputForm(op_type);
+            }
+            _ => {}
         }
     }
 
@@ -418,6 +624,9 @@ impl TuiApp {
             "help" => {
                 self.mode = AppMode::Help;
             }
+            "dash" => {
+                self.mode = AppMode::Dashboard;
+            }
             "devices" => {
                 let devices = self.manager.engine().list_discovered_devices().await;
                 self.add_log(
@@ -468,8 +677,7 @@ impl TuiApp {
                 OperationType::SyncClipboardToGroup
             }
             6 => {
-                self.form_fields.push(("文件名".to_string(), String::new()));
-                self.form_fields.push(("文件内容".to_string(), String::new()));
+                self.form_fields.push((FILE_PATH_FIELD.to_string(), String::new()));
                 OperationType::UploadToCloud
             }
             7 => {
@@ -478,8 +686,7 @@ impl TuiApp {
             }
             8 => {
                 self.form_fields.push(("设备ID".to_string(), String::new()));
-                self.form_fields.push(("文件名".to_string(), String::new()));
-                self.form_fields.push(("文件内容".to_string(), String::new()));
+                self.form_fields.push((FILE_PATH_FIELD.to_string(), String::new()));
                 OperationType::SendFileToPeer
             }
             _ => return,
@@ -489,201 +696,167 @@ impl TuiApp {
     }
 
     /// 执行操作
-    async fn execute_operation(&mut self, op_type: OperationType) {
+    ///
+    /// 操作在后台任务中执行，结果在定时更新时写入日志，界面不会因网络调用而卡顿
+    fn execute_operation(&mut self, op_type: OperationType) {
+        let fields: Vec<String> = self.form_fields.iter().map(|(_, value)| value.clone()).collect();
+        let manager = Arc::clone(&self.manager);
+
         match op_type {
             OperationType::SendPrivateMessage => {
-                if self.form_fields.len() >= 2 {
-                    let peer_id = &self.form_fields[0].1;
-                    let content = self.form_fields[1].1.as_bytes();
-                    match self.manager.send_private_message(peer_id, content).await {
-                        Ok(msg_id) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("私信已发送到 {}, ID: {}", peer_id, msg_id),
-                            );
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("发送私信失败: {}", e),
-                            );
+                if fields.len() >= 2 {
+                    let (peer_id, content) = (fields[0].clone(), fields[1].clone());
+                    self.operations.spawn(async move {
+                        match manager.send_private_message(&peer_id, content.as_bytes()).await {
+                            Ok(msg_id) => Ok(format!("私信已发送到 {}, ID: {}", peer_id, msg_id)),
+                            Err(e) => Err(format!("发送私信失败: {}", e)),
                         }
-                    }
+                    });
                 }
             }
             OperationType::SendGroupMessage => {
-                if self.form_fields.len() >= 2 {
-                    let group_id = &self.form_fields[0].1;
-                    let content = self.form_fields[1].1.as_bytes();
-                    match self.manager.send_group_message(group_id, content).await {
-                        Ok(msg_id) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("群聊消息已发送到 {}, ID: {}", group_id, msg_id),
-                            );
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("发送群聊消息失败: {}", e),
-                            );
+                if fields.len() >= 2 {
+                    let (group_id, content) = (fields[0].clone(), fields[1].clone());
+                    self.operations.spawn(async move {
+                        match manager.send_group_message(&group_id, content.as_bytes()).await {
+                            Ok(msg_id) => Ok(format!("群聊消息已发送到 {}, ID: {}", group_id, msg_id)),
+                            Err(e) => Err(format!("发送群聊消息失败: {}", e)),
                         }
-                    }
+                    });
                 }
             }
             OperationType::BroadcastMessage => {
-                if !self.form_fields.is_empty() {
-                    let content = self.form_fields[0].1.as_bytes();
-                    match self.manager.broadcast_message(content).await {
-                        Ok(count) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("广播消息已发送到 {} 个设备", count),
-                            );
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("广播消息失败: {}", e),
-                            );
+                if !fields.is_empty() {
+                    let content = fields[0].clone();
+                    self.operations.spawn(async move {
+                        match manager.broadcast_message(content.as_bytes()).await {
+                            Ok(count) => Ok(format!("广播消息已发送到 {} 个设备", count)),
+                            Err(e) => Err(format!("广播消息失败: {}", e)),
                         }
-                    }
+                    });
                 }
             }
             OperationType::AddClipboard => {
-                if self.form_fields.len() >= 2 {
-                    let content_type = &self.form_fields[0].1;
-                    let content = self.form_fields[1].1.as_bytes();
-                    match self.manager.add_clipboard(content_type, content).await {
-                        Ok(entry_id) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("剪切板内容已添加, ID: {}", entry_id),
-                            );
+                if fields.len() >= 2 {
+                    let (content_type, content) = (fields[0].clone(), fields[1].clone());
+                    self.operations.spawn(async move {
+                        match manager.add_clipboard(&content_type, content.as_bytes()).await {
+                            Ok(entry_id) => Ok(format!("剪切板内容已添加, ID: {}", entry_id)),
+                            Err(e) => Err(format!("添加剪切板失败: {}", e)),
                         }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("添加剪切板失败: {}", e),
-                            );
-                        }
-                    }
+                    });
                 }
             }
             OperationType::SyncClipboardToPeer => {
-                if !self.form_fields.is_empty() {
-                    let peer_id = &self.form_fields[0].1;
-                    match self.manager.sync_clipboard_to_peer(peer_id).await {
-                        Ok(_) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("剪切板已同步到 {}", peer_id),
-                            );
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("同步剪切板失败: {}", e),
-                            );
+                if !fields.is_empty() {
+                    let peer_id = fields[0].clone();
+                    self.operations.spawn(async move {
+                        match manager.sync_clipboard_to_peer(&peer_id).await {
+                            Ok(_) => Ok(format!("剪切板已同步到 {}", peer_id)),
+                            Err(e) => Err(format!("同步剪切板失败: {}", e)),
                         }
-                    }
+                    });
                 }
             }
             OperationType::SyncClipboardToGroup => {
-                if !self.form_fields.is_empty() {
-                    let group_id = &self.form_fields[0].1;
-                    match self.manager.sync_clipboard_to_group(group_id).await {
-                        Ok(_) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("剪切板已同步到群组 {}", group_id),
-                            );
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("同步剪切板到群组失败: {}", e),
-                            );
+                if !fields.is_empty() {
+                    let group_id = fields[0].clone();
+                    self.operations.spawn(async move {
+                        match manager.sync_clipboard_to_group(&group_id).await {
+                            Ok(_) => Ok(format!("剪切板已同步到群组 {}", group_id)),
+                            Err(e) => Err(format!("同步剪切板到群组失败: {}", e)),
                         }
-                    }
+                    });
                 }
             }
             OperationType::UploadToCloud => {
-                if self.form_fields.len() >= 2 {
-                    let filename = &self.form_fields[0].1;
-                    let data = self.form_fields[1].1.as_bytes();
-                    match self.manager.upload_to_cloud(filename, data).await {
-                        Ok(file_hash) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("文件已上传到云存储, 哈希: {}", file_hash),
-                            );
+                if !fields.is_empty() {
+                    let path = std::path::PathBuf::from(&fields[0]);
+                    self.operations.spawn(async move {
+                        let (filename, data) = read_selected_file(&path).await?;
+                        match manager.upload_to_cloud(&filename, &data).await {
+                            Ok(file_hash) => Ok(format!("文件已上传到云存储, 哈希: {}", file_hash)),
+                            Err(e) => Err(format!("上传文件失败: {}", e)),
                         }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("上传文件失败: {}", e),
-                            );
-                        }
-                    }
+                    });
                 }
             }
             OperationType::DownloadFromCloud => {
-                if !self.form_fields.is_empty() {
-                    let file_hash = &self.form_fields[0].1;
-                    match self.manager.download_from_cloud(file_hash).await {
-                        Ok(data) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("文件已从云存储下载, 大小: {} 字节", data.len()),
-                            );
+                if !fields.is_empty() {
+                    let file_hash = fields[0].clone();
+                    self.operations.spawn(async move {
+                        match manager.download_from_cloud(&file_hash).await {
+                            Ok(data) => Ok(format!("文件已从云存储下载, 大小: {} 字节", data.len())),
+                            Err(e) => Err(format!("下载文件失败: {}", e)),
                         }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("下载文件失败: {}", e),
-                            );
-                        }
-                    }
+                    });
                 }
             }
             OperationType::SendFileToPeer => {
-                if self.form_fields.len() >= 3 {
-                    let peer_id = &self.form_fields[0].1;
-                    let filename = &self.form_fields[1].1;
-                    let data = self.form_fields[2].1.as_bytes();
-                    match self.manager.send_file_to_peer(peer_id, filename, data).await {
-                        Ok(_) => {
-                            self.add_log(
-                                LogLevel::Info,
-                                format!("文件 {} 已发送到 {}", filename, peer_id),
-                            );
-                        }
-                        Err(e) => {
-                            self.add_log(
-                                LogLevel::Error,
-                                format!("发送文件失败: {}", e),
-                            );
+                if fields.len() >= 2 {
+                    let (peer_id, path) = (fields[0].clone(), std::path::PathBuf::from(&fields[1]));
+                    self.operations.spawn(async move {
+                        let (filename, data) = read_selected_file(&path).await?;
+                        match manager.send_file_to_peer(&peer_id, &filename, &data).await {
+                            Ok(_) => Ok(format!("文件 {} 已发送到 {}", filename, peer_id)),
+                            Err(e) => Err(format!("发送文件失败: {}", e)),
                         }
-                    }
+                    });
                 }
             }
         }
     }
 
     /// 定时更新
+    ///
+    /// 收取已完成的后台操作结果并写入日志；显示仪表盘时按采样间隔采样
     async fn on_tick(&mut self) {
-        // 这里可以添加定时任务，比如更新设备列表等
+        for outcome in self.operations.poll_completed() {
+            match outcome {
+                Ok(message) => self.add_log(LogLevel::Info, message),
+                Err(message) => self.add_log(LogLevel::Error, message),
+            }
+        }
+
+        let now = Instant::now();
+        if dashboard_sample_due(&self.mode, self.last_sample, now) {
+            self.last_sample = Some(now);
+            self.sample_dashboard().await;
+        }
+    }
+
+    /// 采集一次 CPU、内存、连接数和吞吐量
+    async fn sample_dashboard(&mut self) {
+        let system = match self.system.as_mut() {
+            Some(system) => {
+                system.refresh();
+                system
+            }
+            None => self.system.insert(SystemInfo::new().await),
+        };
+        let cpu = system.cpu_usage() as f64;
+        let memory = system.memory_usage_percent() as f64;
+
+        let metrics = self.manager.engine().get_performance_stats().await;
+        self.dashboard.record(
+            cpu,
+            memory,
+            metrics.active_connections,
+            metrics.bytes_sent + metrics.bytes_received,
+            Instant::now(),
+        );
     }
 
     /// 绘制UI
     fn ui(&mut self, f: &mut Frame) {
+        // 命令模式在输入行下方多显示一行命令提示
+        let status_height = if self.mode == AppMode::Command { 4 } else { 3 };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),      // 标题
                 Constraint::Min(10),         // 主内容
-                Constraint::Length(3),       // 状态栏
+                Constraint::Length(status_height), // 状态栏
             ])
             .split(f.area());
 
@@ -692,14 +865,8 @@ impl TuiApp {
 
         match self.mode {
             AppMode::Normal => {
-                // 主内容区域分为左右两部分
-                let main_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-                    .split(chunks[1]);
-
-                self.render_device_list(f, main_chunks[0]);
-                self.render_logs(f, main_chunks[1]);
+                // 主内容区域分为设备、日志、详情三栏
+                self.render_panes(f, chunks[1]);
             }
             AppMode::Help => {
                 self.render_help(f, chunks[1]);
@@ -710,15 +877,15 @@ impl TuiApp {
             AppMode::InputForm(_) => {
                 self.render_input_form(f, chunks[1]);
             }
+            AppMode::FilePicker(_) => {
+                self.render_file_picker(f, chunks[1]);
+            }
+            AppMode::Dashboard => {
+                self.render_dashboard(f, chunks[1]);
+            }
             AppMode::Command => {
                 // 命令模式下也显示主内容
-                let main_chunks = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-                    .split(chunks[1]);
-
-                self.render_device_list(f, main_chunks[0]);
-                self.render_logs(f, main_chunks[1]);
+                self.render_panes(f, chunks[1]);
             }
         }
 
@@ -734,6 +901,43 @@ impl TuiApp {
         f.render_widget(title, area);
     }
 
+    /// 按当前布局渲染设备、日志、详情三栏
+    fn render_panes(&self, f: &mut Frame, area: Rect) {
+        let [devices, logs, details] = self.panes.split(area);
+        self.render_device_list(f, devices);
+        self.render_logs(f, logs);
+        self.render_details(f, details);
+    }
+
+    /// 渲染详情栏
+    fn render_details(&self, f: &mut Frame, area: Rect) {
+        let [devices_width, logs_width, details_width] = self.panes.widths();
+        let divider = match self.panes.active_divider() {
+            PaneDivider::Left => "设备|日志",
+            PaneDivider::Right => "日志|详情",
+        };
+        let details = vec![
+            Line::from(format!("本地设备: {}", self.manager.device_id())),
+            Line::from(format!("选中设备: #{}", self.selected_device + 1)),
+            Line::from(format!("执行中的操作: {}", self.operations.in_flight())),
+            Line::from(format!("日志条目: {}", self.logs.len())),
+            Line::from(""),
+            Line::from(format!("布局: {}/{}/{}", devices_width, logs_width, details_width)),
+            Line::from(format!("调整分隔线: {} (Tab切换)", divider)),
+        ];
+
+        let details_widget = Paragraph::new(details)
+            .block(
+                Block::default()
+                    .title("详情")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Magenta)),
+            )
+            .wrap(Wrap { trim: true });
+
+        f.render_widget(details_widget, area);
+    }
+
     /// 渲染设备列表
     fn render_device_list(&self, f: &mut Frame, area: Rect) {
         let devices: Vec<ListItem> = vec![
@@ -802,8 +1006,11 @@ impl TuiApp {
             Line::from("  Ctrl+C    - 退出程序"),
             Line::from("  o         - 打开操作菜单"),
             Line::from("  :         - 进入命令模式"),
+            Line::from("  Tab       - 命令模式下补全命令"),
             Line::from("  ?         - 显示/隐藏帮助"),
             Line::from("  ↑/↓       - 选择设备"),
+            Line::from("  Ctrl+←/→  - 移动分隔线，调整相邻两栏的比例"),
+            Line::from("  Tab       - 切换要调整的分隔线"),
             Line::from(""),
             Line::from(Span::styled(
                 "命令",
@@ -815,6 +1022,7 @@ impl TuiApp {
             Line::from("  :quit     - 退出程序"),
             Line::from("  :clear    - 清空日志"),
             Line::from("  :devices  - 列出设备"),
+            Line::from("  :dash     - 显示仪表盘"),
             Line::from("  :help     - 显示帮助"),
             Line::from(""),
             Line::from(Span::styled(
@@ -852,16 +1060,25 @@ impl TuiApp {
     /// 渲染状态栏
     fn render_status(&self, f: &mut Frame, area: Rect) {
         let mode_text = match self.mode {
-            AppMode::Normal => "正常模式 | 按 'o' 打开操作菜单 | 按 ':' 输入命令 | 按 '?' 查看帮助 | 按 'q' 退出",
+            AppMode::Normal => "正常模式 | 按 'o' 打开操作菜单 | 按 ':' 输入命令 | Ctrl+←/→ 调整分栏 | 按 '?' 查看帮助 | 按 'q' 退出",
             AppMode::Command => {
                 return self.render_command_input(f, area);
             }
             AppMode::Help => "帮助模式 | 按 '?' 或 ESC 返回",
             AppMode::OperationMenu => "操作菜单 | ↑↓ 选择 | Enter 确认 | ESC 返回",
-            AppMode::InputForm(_) => "输入表单 | Tab 切换字段 | Enter 提交 | ESC 返回菜单",
+            AppMode::InputForm(_) => "输入表单 | Tab 切换字段 | Enter 提交（文件路径为空时打开文件浏览器） | ESC 返回菜单",
+            AppMode::FilePicker(_) => "文件浏览 | ↑↓ 选择 | PgUp/PgDn 翻页 | Enter 打开/选择 | Backspace 上级目录 | ESC 取消",
+            AppMode::Dashboard => "仪表盘 | 每秒采样一次 | 按 'q' 或 ESC 返回",
         };
 
-        let status = Paragraph::new(mode_text)
+        let in_flight = self.operations.in_flight();
+        let status_text = if in_flight > 0 {
+            format!("⏳ {} 个操作执行中 | {}", in_flight, mode_text)
+        } else {
+            mode_text.to_string()
+        };
+
+        let status = Paragraph::new(status_text)
             .style(Style::default().fg(Color::White))
             .block(Block::default().borders(Borders::ALL));
 
@@ -869,13 +1086,31 @@ impl TuiApp {
     }
 
     /// 渲染命令输入
+    ///
+    /// 输入行下方列出匹配当前前缀的命令
     fn render_command_input(&self, f: &mut Frame, area: Rect) {
-        let input = Paragraph::new(format!(":{}", self.command_input))
-            .style(Style::default().fg(Color::Yellow))
+        let matches = matching_commands(&self.command_input);
+        let suggestions = if matches.is_empty() {
+            Line::from(Span::styled("无匹配命令", Style::default().fg(Color::DarkGray)))
+        } else {
+            Line::from(
+                matches
+                    .into_iter()
+                    .map(|(name, description)| {
+                        Span::styled(format!("{} - {}  ", name, description), Style::default().fg(Color::DarkGray))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let input = Paragraph::new(vec![
+            Line::from(Span::styled(format!(":{}", self.command_input), Style::default().fg(Color::Yellow))),
+            suggestions,
+        ])
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("命令 (Enter 执行, ESC 取消)"),
+                    .title("命令 (Enter 执行, Tab 补全, ESC 取消)"),
             );
 
         f.render_widget(input, area);
@@ -972,6 +1207,176 @@ impl TuiApp {
 
         f.render_widget(form, area);
     }
+
+    /// 渲染仪表盘
+    ///
+    /// 上半部分为 CPU 和内存使用率折线图，下半部分为连接数和吞吐量迷你图
+    fn render_dashboard(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area);
+
+        let cpu_points = self.dashboard.cpu.to_points();
+        let memory_points = self.dashboard.memory.to_points();
+        let window = self.dashboard.cpu.capacity().saturating_sub(1).max(1) as f64;
+        let datasets = vec![
+            Dataset::default()
+                .name(format!("CPU {:.1}%", self.dashboard.cpu.latest().unwrap_or(0.0)))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan))
+                .data(&cpu_points),
+            Dataset::default()
+                .name(format!("内存 {:.1}%", self.dashboard.memory.latest().unwrap_or(0.0)))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Magenta))
+                .data(&memory_points),
+        ];
+        let chart = Chart::new(datasets)
+            .block(
+                Block::default()
+                    .title("CPU / 内存使用率")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan)),
+            )
+            .x_axis(Axis::default().style(Style::default().fg(Color::Gray)).bounds([0.0, window]))
+            .y_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .bounds([0.0, 100.0])
+                    .labels(["0%", "50%", "100%"]),
+            );
+        f.render_widget(chart, chunks[0]);
+
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+
+        let connections = self.dashboard.connections.to_sparkline();
+        let connections_widget = Sparkline::default()
+            .block(
+                Block::default()
+                    .title(format!(
+                        "活跃连接 {}",
+                        self.dashboard.connections.latest().unwrap_or(0.0) as u64
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Green)),
+            )
+            .data(&connections)
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(connections_widget, bottom[0]);
+
+        let throughput = self.dashboard.throughput.to_sparkline();
+        let throughput_widget = Sparkline::default()
+            .block(
+                Block::default()
+                    .title(format!(
+                        "传输吞吐量 {} (峰值 {})",
+                        dashboard::format_throughput(self.dashboard.throughput.latest().unwrap_or(0.0)),
+                        dashboard::format_throughput(self.dashboard.throughput.max())
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .data(&throughput)
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(throughput_widget, bottom[1]);
+    }
+
+    /// 渲染文件浏览器
+    fn render_file_picker(&self, f: &mut Frame, area: Rect) {
+        let Some(browser) = self.file_browser.as_ref() else {
+            return;
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(area);
+
+        let page_start = browser.page() * FILE_PICKER_PAGE_SIZE;
+        let items: Vec<ListItem> = browser
+            .page_entries()
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let style = if page_start + i == browser.selected_index() {
+                    Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else if entry.is_dir {
+                    Style::default().fg(Color::Cyan)
+                } else {
+                    Style::default()
+                };
+                let text = if entry.is_dir {
+                    format!("📁 {}/", entry.name)
+                } else {
+                    format!("📄 {} ({} 字节)", entry.name, entry.size)
+                };
+                ListItem::new(text).style(style)
+            })
+            .collect();
+
+        let list = List::new(items).block(
+            Block::default()
+                .title(format!(
+                    "选择文件 - {} (第 {}/{} 页)",
+                    browser.current_dir().display(),
+                    browser.page() + 1,
+                    browser.page_count()
+                ))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Green)),
+        );
+        f.render_widget(list, chunks[0]);
+
+        let (message, color) = match browser.error() {
+            Some(error) => (error.to_string(), Color::Red),
+            None if browser.entries().is_empty() => ("目录为空".to_string(), Color::Gray),
+            None => (format!("共 {} 项", browser.entries().len()), Color::Gray),
+        };
+        let footer = Paragraph::new(message)
+            .style(Style::default().fg(color))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(footer, chunks[1]);
+    }
+}
+
+/// 读取选中的本地文件
+///
+/// # 参数
+///
+/// * `path` - 文件路径
+///
+/// # 返回
+///
+/// 返回文件名和文件内容，读取失败时返回错误描述
+async fn read_selected_file(path: &std::path::Path) -> Result<(String, Vec<u8>), String> {
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("无效的文件路径: {}", path.display()))?;
+    let data = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("读取文件 {} 失败: {}", path.display(), e))?;
+    Ok((filename, data))
+}
+
+/// 本次定时更新是否需要为仪表盘采样
+///
+/// 只在显示仪表盘时采样，距上次采样不足 [`DASHBOARD_SAMPLE_INTERVAL`] 时跳过
+///
+/// # 参数
+///
+/// * `mode` - 当前界面模式
+/// * `last_sample` - 上次采样时间
+/// * `now` - 当前时间
+fn dashboard_sample_due(mode: &AppMode, last_sample: Option<Instant>, now: Instant) -> bool {
+    *mode == AppMode::Dashboard
+        && last_sample.is_none_or(|last| now.saturating_duration_since(last) >= DASHBOARD_SAMPLE_INTERVAL)
 }
 
 #[cfg(test)]
@@ -999,4 +1404,41 @@ mod tests {
         assert_eq!(AppMode::Normal, AppMode::Normal);
         assert_ne!(AppMode::Normal, AppMode::Command);
     }
+
+    #[test]
+    fn test_dashboard_sampled_only_when_shown() {
+        let now = Instant::now();
+        assert!(!dashboard_sample_due(&AppMode::Normal, None, now));
+        assert!(dashboard_sample_due(&AppMode::Dashboard, None, now));
+        assert!(!dashboard_sample_due(&AppMode::Dashboard, Some(now), now + Duration::from_millis(250)));
+        assert!(dashboard_sample_due(&AppMode::Dashboard, Some(now), now + DASHBOARD_SAMPLE_INTERVAL));
+    }
+
+    #[tokio::test]
+    async fn test_slow_operation_does_not_block_tick() {
+        let mut runner = OperationRunner::new();
+
+        let started = Instant::now();
+        runner.spawn(async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok("慢操作完成".to_string())
+        });
+        runner.spawn(async { Err("操作失败".to_string()) });
+
+        // 提交操作立即返回，慢操作仍在执行中
+        assert!(started.elapsed() < Duration::from_millis(100));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(runner.poll_completed(), vec![Err("操作失败".to_string())]);
+        assert_eq!(runner.in_flight(), 1);
+
+        // 结果在后续的定时更新中异步到达
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut completed = Vec::new();
+        while completed.is_empty() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            completed = runner.poll_completed();
+        }
+        assert_eq!(completed, vec![Ok("慢操作完成".to_string())]);
+        assert_eq!(runner.in_flight(), 0);
+    }
 }
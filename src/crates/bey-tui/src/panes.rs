@@ -0,0 +1,150 @@
+//! # 分栏布局
+//!
+//! 主界面分为设备、日志和详情三栏，各栏宽度按百分比分配。
+//! 两条分隔线可以左右移动来调整相邻两栏的比例，任何一栏都不会小于最小宽度。
+//! 布局保存在应用状态中，在本次运行期间切换模式后保持不变；
+//! 每次绘制时按当前终端区域重新计算各栏位置，终端尺寸变化后立即生效。
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+/// 每栏的最小宽度（百分比）
+pub const MIN_PANE_PERCENT: u16 = 15;
+
+/// 每次移动分隔线的步长（百分比）
+pub const PANE_RESIZE_STEP: u16 = 5;
+
+/// 分隔线
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneDivider {
+    /// 设备栏与日志栏之间
+    Left,
+    /// 日志栏与详情栏之间
+    Right,
+}
+
+/// 三栏布局
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaneLayout {
+    /// 设备、日志、详情三栏的宽度（百分比，总和为100）
+    widths: [u16; 3],
+    /// 当前调整的分隔线
+    active: PaneDivider,
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            widths: [25, 50, 25],
+            active: PaneDivider::Left,
+        }
+    }
+}
+
+impl PaneLayout {
+    /// 设备、日志、详情三栏的宽度（百分比）
+    pub fn widths(&self) -> [u16; 3] {
+        self.widths
+    }
+
+    /// 当前调整的分隔线
+    pub fn active_divider(&self) -> PaneDivider {
+        self.active
+    }
+
+    /// 切换要调整的分隔线
+    pub fn toggle_divider(&mut self) {
+        self.active = match self.active {
+            PaneDivider::Left => PaneDivider::Right,
+            PaneDivider::Right => PaneDivider::Left,
+        };
+    }
+
+    /// 把当前分隔线左移一步
+    pub fn shift_left(&mut self) {
+        self.move_divider(-(PANE_RESIZE_STEP as i16));
+    }
+
+    /// 把当前分隔线右移一步
+    pub fn shift_right(&mut self) {
+        self.move_divider(PANE_RESIZE_STEP as i16);
+    }
+
+    /// 移动当前分隔线，只改变相邻两栏的比例
+    ///
+    /// 移动后任何一栏小于 [`MIN_PANE_PERCENT`] 时停在最小宽度处
+    ///
+    /// # 参数
+    ///
+    /// * `delta` - 移动的百分比，正数向右
+    pub fn move_divider(&mut self, delta: i16) {
+        let left = match self.active {
+            PaneDivider::Left => 0,
+            PaneDivider::Right => 1,
+        };
+        let total = self.widths[left] + self.widths[left + 1];
+        let width = (self.widths[left] as i16 + delta)
+            .clamp(MIN_PANE_PERCENT as i16, (total - MIN_PANE_PERCENT) as i16) as u16;
+        self.widths[left] = width;
+        self.widths[left + 1] = total - width;
+    }
+
+    /// 按当前比例划分区域
+    ///
+    /// # 参数
+    ///
+    /// * `area` - 主内容区域
+    ///
+    /// # 返回
+    ///
+    /// 返回设备、日志、详情三栏的区域
+    pub fn split(&self, area: Rect) -> [Rect; 3] {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(self.widths.map(Constraint::Percentage))
+            .split(area);
+        [chunks[0], chunks[1], chunks[2]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divider_moves_are_clamped_to_min_width() {
+        let mut layout = PaneLayout::default();
+
+        // 设备栏缩到最小宽度后不再缩小，多出的宽度归日志栏
+        for _ in 0..10 {
+            layout.shift_left();
+        }
+        assert_eq!(layout.widths(), [MIN_PANE_PERCENT, 75 - MIN_PANE_PERCENT, 25]);
+
+        // 日志栏同样不能小于最小宽度，详情栏不受左分隔线影响
+        for _ in 0..20 {
+            layout.shift_right();
+        }
+        assert_eq!(layout.widths(), [75 - MIN_PANE_PERCENT, MIN_PANE_PERCENT, 25]);
+
+        layout.toggle_divider();
+        assert_eq!(layout.active_divider(), PaneDivider::Right);
+        for _ in 0..10 {
+            layout.shift_right();
+        }
+        assert_eq!(layout.widths(), [75 - MIN_PANE_PERCENT, 40 - MIN_PANE_PERCENT, MIN_PANE_PERCENT]);
+        layout.shift_left();
+        assert_eq!(layout.widths(), [75 - MIN_PANE_PERCENT, 40 - MIN_PANE_PERCENT - PANE_RESIZE_STEP, MIN_PANE_PERCENT + PANE_RESIZE_STEP]);
+        assert_eq!(layout.widths().iter().sum::<u16>(), 100);
+    }
+
+    #[test]
+    fn test_split_follows_area_size() {
+        let layout = PaneLayout::default();
+        for width in [80, 200] {
+            let panes = layout.split(Rect::new(0, 0, width, 20));
+            assert_eq!(panes.iter().map(|pane| pane.width).sum::<u16>(), width);
+            assert_eq!(panes[0].width, width / 4);
+            assert_eq!(panes[2].x + panes[2].width, width);
+        }
+    }
+}
@@ -11,7 +11,7 @@ use uuid::Uuid;
 /// 设备类型枚举
 ///
 /// 定义不同类型的设备，用于权限控制和功能适配
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum DeviceType {
     /// 桌面计算机
     Desktop,
@@ -26,10 +26,52 @@
 //! - 自动实现 `ErrorKind` trait
 //! - 支持格式化字符串的错误消息
 //! - 自动生成错误码
+//! - 可选生成 `From<枚举> for ErrorInfo`，使 `?` 能把模块错误直接转换为 `ErrorInfo`
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit};
+use syn::parse::ParseStream;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, Ident, LitStr, Token};
+
+/// `#[error(...)]` 属性中的选项
+#[derive(Default)]
+struct ErrorAttrs {
+    /// 错误消息
+    message: Option<String>,
+    /// 错误类别（`ErrorCategory` 的变体名）
+    category: Option<Ident>,
+    /// 是否生成 `From<枚举> for ErrorInfo`
+    into_info: bool,
+}
+
+/// 解析 `#[error("消息", category = 类别)]` 或 `#[error(into_info)]` 形式的属性
+fn parse_error_attrs(attrs: &[Attribute]) -> syn::Result<ErrorAttrs> {
+    let mut parsed = ErrorAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("error")) {
+        attr.parse_args_with(|input: ParseStream| {
+            while !input.is_empty() {
+                if input.peek(LitStr) {
+                    parsed.message = Some(input.parse::<LitStr>()?.value());
+                } else {
+                    let key: Ident = input.parse()?;
+                    if key == "into_info" {
+                        parsed.into_info = true;
+                    } else if key == "category" {
+                        input.parse::<Token![=]>()?;
+                        parsed.category = Some(input.parse()?);
+                    } else {
+                        return Err(syn::Error::new_spanned(&key, format!("未知的 error 属性: {}", key)));
+                    }
+                }
+                if !input.is_empty() {
+                    input.parse::<Token![,]>()?;
+                }
+            }
+            Ok(())
+        })?;
+    }
+    Ok(parsed)
+}
 
 /// Error 派生宏
 /// 
@@ -40,6 +82,10 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit};
 /// - `#[error("消息")]` - 指定错误消息，支持格式化占位符
 ///   - `{0}`, `{1}`, ... - 位置参数（用于元组变体）
 ///   - `{field}` - 命名字段（用于结构体变体）
+/// - `#[error(category = Network)]` - 指定变体的 `ErrorCategory`，可与消息写在同一属性中；
+///   写在枚举上时作为所有变体的默认类别
+/// - `#[error(into_info)]` - 写在枚举上，额外生成 `From<枚举> for ErrorInfo`，
+///   转换结果使用变体的错误码、消息和类别，并以原错误作为源错误
 /// 
 /// # 示例
 /// 
@@ -55,6 +101,20 @@ use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit};
 ///     #[error("未知文件错误")]
 ///     Unknown,
 /// }
+///
+/// #[derive(Debug, Error)]
+/// #[error(into_info, category = Network)]
+/// enum PeerError {
+///     #[error("连接超时: {0}")]
+///     Timeout(String),
+///
+///     #[error("证书无效", category = Authentication)]
+///     InvalidCertificate,
+/// }
+///
+/// fn connect() -> error::Result<()> {
+///     Err(PeerError::Timeout("peer-1".to_string()))?
+/// }
 /// ```
 #[proc_macro_derive(Error, attributes(error))]
 pub fn derive_error(input: TokenStream) -> TokenStream {
@@ -74,23 +134,30 @@ pub fn derive_error(input: TokenStream) -> TokenStream {
         }
     };
     
+    // 解析枚举和各变体上的 #[error(...)] 属性
+    let enum_attrs = match parse_error_attrs(&input.attrs) {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let variant_attrs: Vec<ErrorAttrs> = match variants.iter()
+        .map(|variant| parse_error_attrs(&variant.attrs))
+        .collect()
+    {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    if let Some((variant, _)) = variants.iter().zip(&variant_attrs).find(|(_, attrs)| attrs.into_info) {
+        return syn::Error::new_spanned(variant, "into_info 只能用于枚举")
+            .to_compile_error()
+            .into();
+    }
+
     // 为每个变体生成 Display 实现的匹配分支
-    let display_arms = variants.iter().enumerate().map(|(_idx, variant)| {
+    let display_arms = variants.iter().zip(&variant_attrs).map(|(variant, attrs)| {
         let variant_name = &variant.ident;
         
         // 获取 #[error("...")] 属性中的错误消息
-        let error_msg = variant.attrs.iter()
-            .find_map(|attr| {
-                if attr.path().is_ident("error") {
-                    // 尝试解析为 #[error("...")] 格式
-                    if let Ok(lit) = attr.parse_args::<Lit>() {
-                        if let Lit::Str(lit_str) = lit {
-                            return Some(lit_str.value());
-                        }
-                    }
-                }
-                None
-            })
+        let error_msg = attrs.message.clone()
             .unwrap_or_else(|| format!("错误: {}", variant_name));
         
         // 根据字段类型生成不同的匹配模式和格式化字符串
@@ -197,6 +264,44 @@ pub fn derive_error(input: TokenStream) -> TokenStream {
         }
     });
     
+    // 启用 into_info 时生成到 ErrorInfo 的转换
+    let into_info = if enum_attrs.into_info {
+        let category_arms = variants.iter().zip(&variant_attrs).map(|(variant, attrs)| {
+            let variant_name = &variant.ident;
+            let category = attrs.category.as_ref()
+                .or(enum_attrs.category.as_ref())
+                .map(|category| quote! { error::ErrorCategory::#category })
+                .unwrap_or_else(|| quote! { error::ErrorCategory::Other });
+
+            match &variant.fields {
+                Fields::Named(_) => quote! {
+                    #name::#variant_name { .. } => #category
+                },
+                Fields::Unnamed(_) => quote! {
+                    #name::#variant_name(..) => #category
+                },
+                Fields::Unit => quote! {
+                    #name::#variant_name => #category
+                },
+            }
+        });
+
+        quote! {
+            impl From<#name> for error::ErrorInfo {
+                fn from(err: #name) -> Self {
+                    let category = match &err {
+                        #(#category_arms,)*
+                    };
+                    let code = error::ErrorKind::error_code(&err);
+                    let message = error::ErrorKind::error_message(&err);
+                    error::ErrorInfo::with_source(code, message, err).with_category(category)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     // 生成完整的实现代码
     let expanded = quote! {
         impl std::fmt::Display for #name {
@@ -207,7 +312,7 @@ pub fn derive_error(input: TokenStream) -> TokenStream {
             }
         }
         
-        impl error::ErrorKind + Send + Sync for #name {
+        impl error::ErrorKind for #name {
             fn error_code(&self) -> u32 {
                 match self {
                     #(#error_code_arms,)*
@@ -220,6 +325,8 @@ pub fn derive_error(input: TokenStream) -> TokenStream {
                 }
             }
         }
+
+        #into_info
     };
     
     TokenStream::from(expanded)
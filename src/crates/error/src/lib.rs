@@ -9,6 +9,7 @@
 //! - **无隐式转换**: 所有转换都是显式的，确保最高性能
 //! - **完整的错误信息**: 支持错误码、错误消息、错误源等
 //! - **错误链**: 支持错误的嵌套和追踪
+//! - **统一日志**: 按严重程度自动输出 `tracing` 日志事件
 //!
 //! ## 使用示例
 //!
@@ -165,8 +166,13 @@ pub struct ErrorInfo {
     severity: ErrorSeverity,
     /// 错误类别
     category: ErrorCategory,
+    /// 是否可重试（显式指定时覆盖按类别和错误码的判断）
+    retryable: Option<bool>,
 }
 
+/// 由瞬时IO错误转换得到的错误码（连接中断、服务不可用、超时）
+const TRANSIENT_ERROR_CODES: [u32; 3] = [502, 503, 504];
+
 impl Clone for ErrorInfo {
     fn clone(&self) -> Self {
         Self {
@@ -176,6 +182,7 @@ impl Clone for ErrorInfo {
             context: self.context.clone(),
             severity: self.severity,
             category: self.category,
+            retryable: self.retryable,
         }
     }
 }
@@ -187,6 +194,7 @@ impl PartialEq for ErrorInfo {
             && self.context == other.context
             && self.severity == other.severity
             && self.category == other.category
+            && self.retryable == other.retryable
         // 不比较source字段，因为它不容易比较
     }
 }
@@ -219,6 +227,7 @@ impl ErrorInfo {
             context: Vec::new(),
             severity: ErrorSeverity::Error,
             category: ErrorCategory::Other,
+            retryable: None,
         }
     }
     
@@ -246,6 +255,7 @@ impl ErrorInfo {
             context: Vec::new(),
             severity: ErrorSeverity::Error,
             category: ErrorCategory::Other,
+            retryable: None,
         }
     }
     
@@ -313,6 +323,63 @@ impl ErrorInfo {
         self
     }
     
+    /// 指定错误是否可重试
+    ///
+    /// 覆盖 [`ErrorInfo::is_retryable`] 按类别和错误码的默认判断。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use error::{ErrorInfo, ErrorCategory};
+    ///
+    /// let error = ErrorInfo::new(6409, "数据库不可用".to_string())
+    ///     .with_category(ErrorCategory::Database)
+    ///     .with_retryable(true);
+    /// assert!(error.is_retryable());
+    /// ```
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = Some(retryable);
+        self
+    }
+
+    /// 判断错误是否值得重试
+    ///
+    /// 显式指定过 [`ErrorInfo::with_retryable`] 时直接使用指定值；否则：
+    ///
+    /// - 验证、解析、配置、权限、认证、授权和未实现错误不可重试
+    /// - 网络错误可重试
+    /// - 由连接中断、服务不可用、超时转换得到的错误码（502/503/504）可重试
+    /// - 其他错误不可重试
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use error::ErrorInfo;
+    ///
+    /// let timeout = ErrorInfo::from(std::io::Error::from(std::io::ErrorKind::TimedOut));
+    /// assert!(timeout.is_retryable());
+    ///
+    /// let denied = ErrorInfo::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+    /// assert!(!denied.is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        if let Some(retryable) = self.retryable {
+            return retryable;
+        }
+
+        match self.category {
+            ErrorCategory::Validation
+            | ErrorCategory::Parse
+            | ErrorCategory::Configuration
+            | ErrorCategory::Permission
+            | ErrorCategory::Authentication
+            | ErrorCategory::Authorization
+            | ErrorCategory::NotImplemented => false,
+            ErrorCategory::Network => true,
+            _ => TRANSIENT_ERROR_CODES.contains(&self.code),
+        }
+    }
+
     /// 获取错误码
     pub fn code(&self) -> u32 {
         self.code
@@ -347,6 +414,45 @@ impl ErrorInfo {
     pub fn is_warning(&self) -> bool {
         self.severity == ErrorSeverity::Warning
     }
+
+    /// 按严重程度输出日志
+    ///
+    /// 严重和错误级别输出为 `error`，警告输出为 `warn`，信息输出为 `info`，
+    /// 调试输出为 `debug`。日志事件包含错误码、类别和上下文字段。
+    /// 返回自身引用以支持链式调用。
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use error::{ErrorInfo, ErrorSeverity};
+    ///
+    /// let error = ErrorInfo::new(404, "文件未找到".to_string())
+    ///     .with_severity(ErrorSeverity::Warning);
+    /// let code = error.log().code();
+    /// ```
+    pub fn log(&self) -> &Self {
+        let code = self.code;
+        let category = format!("{:?}", self.category);
+        let context = self.context.join("; ");
+        let message = &self.message;
+
+        match self.severity {
+            ErrorSeverity::Critical | ErrorSeverity::Error => {
+                tracing::error!(code, category = %category, context = %context, "{}", message)
+            }
+            ErrorSeverity::Warning => {
+                tracing::warn!(code, category = %category, context = %context, "{}", message)
+            }
+            ErrorSeverity::Info => {
+                tracing::info!(code, category = %category, context = %context, "{}", message)
+            }
+            ErrorSeverity::Debug => {
+                tracing::debug!(code, category = %category, context = %context, "{}", message)
+            }
+        }
+
+        self
+    }
 }
 
 impl fmt::Display for ErrorInfo {
@@ -434,6 +540,58 @@ impl From<std::io::Error> for ErrorInfo {
     }
 }
 
+/// 结果扩展 trait
+///
+/// 为错误类型可转换为 [`ErrorInfo`] 的 `Result` 提供添加上下文的简写，
+/// 错误的类别和严重程度沿用转换得到的默认值，`Ok` 值原样返回。
+///
+/// # 示例
+///
+/// ```rust
+/// use error::ResultExt;
+///
+/// fn read_config(path: &str) -> error::Result<String> {
+///     std::fs::read_to_string(path).with_context_fn(|| format!("读取配置文件失败: {}", path))
+/// }
+///
+/// let error = read_config("/nonexistent/config.toml").unwrap_err();
+/// assert_eq!(error.context(), ["读取配置文件失败: /nonexistent/config.toml"]);
+/// ```
+pub trait ResultExt<T> {
+    /// 出错时将错误转换为 [`ErrorInfo`] 并附加上下文
+    ///
+    /// # 参数
+    ///
+    /// * `context` - 上下文信息
+    fn context(self, context: impl Into<String>) -> Result<T>;
+
+    /// 出错时将错误转换为 [`ErrorInfo`] 并附加延迟生成的上下文
+    ///
+    /// 上下文只在出错时生成，适合需要格式化的上下文信息
+    ///
+    /// # 参数
+    ///
+    /// * `context` - 生成上下文信息的闭包
+    fn with_context_fn<C, F>(self, context: F) -> Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C;
+}
+
+impl<T, E: Into<ErrorInfo>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T> {
+        self.map_err(|err| err.into().with_context(context.into()))
+    }
+
+    fn with_context_fn<C, F>(self, context: F) -> Result<T>
+    where
+        C: Into<String>,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|err| err.into().with_context(context().into()))
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -602,4 +760,136 @@ mod tests {
         assert!(display.contains("上下文"));
         assert!(display.contains("由以下错误引起"));
     }
+
+    #[test]
+    fn test_timeout_is_retryable() {
+        let timeout = ErrorInfo::from(std::io::Error::new(std::io::ErrorKind::TimedOut, "连接超时"));
+        assert_eq!(timeout.code(), 504);
+        assert!(timeout.is_retryable());
+
+        let reset = ErrorInfo::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(reset.is_retryable());
+
+        // 错误码来自超时，即使类别不是网络错误也可重试
+        let storage_timeout = ErrorInfo::new(504, "存储请求超时".to_string())
+            .with_category(ErrorCategory::Storage);
+        assert!(storage_timeout.is_retryable());
+    }
+
+    #[test]
+    fn test_permission_error_is_not_retryable() {
+        let denied = ErrorInfo::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert!(!denied.is_retryable());
+
+        for category in [ErrorCategory::Validation, ErrorCategory::Permission, ErrorCategory::Authentication] {
+            let error = ErrorInfo::new(503, "请求被拒绝".to_string()).with_category(category);
+            assert!(!error.is_retryable(), "{:?} 不应重试", category);
+        }
+
+        assert!(!ErrorInfo::new(500, "内部错误".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_retryable_override() {
+        let network = ErrorInfo::new(4001, "对端拒绝连接".to_string())
+            .with_category(ErrorCategory::Network)
+            .with_retryable(false);
+        assert!(!network.is_retryable());
+
+        let busy = ErrorInfo::new(6409, "数据库不可用".to_string())
+            .with_category(ErrorCategory::Database)
+            .with_retryable(true);
+        assert!(busy.is_retryable());
+        assert!(busy.clone().is_retryable());
+    }
+
+    #[test]
+    fn test_result_context_on_err() {
+        let result: std::result::Result<(), std::io::Error> =
+            Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+        let error = result.context("加载证书").unwrap_err();
+
+        // 转换得到的错误码和类别保持不变，上下文追加在末尾
+        assert_eq!(error.code(), 404);
+        assert_eq!(error.category(), ErrorCategory::FileSystem);
+        assert_eq!(error.context(), ["加载证书"]);
+
+        let chained: Result<()> = Err(error);
+        let error = chained.with_context_fn(|| format!("初始化设备 {}", "device-1")).unwrap_err();
+        assert_eq!(error.context(), ["加载证书", "初始化设备 device-1"]);
+    }
+
+    #[test]
+    fn test_result_context_on_ok_is_noop() {
+        let result: Result<u32> = Ok(7);
+        assert_eq!(result.context("不应出现").expect("Ok 应原样返回"), 7);
+
+        let mut called = false;
+        let result: Result<u32> = Ok(8);
+        let value = result.with_context_fn(|| {
+            called = true;
+            "不应生成"
+        }).expect("Ok 应原样返回");
+        assert_eq!(value, 8);
+        assert!(!called, "Ok 时不应生成上下文");
+    }
+
+    /// 捕获到的日志事件（级别, 字段）
+    type CapturedEvents = std::sync::Arc<std::sync::Mutex<Vec<(tracing::Level, std::collections::HashMap<String, String>)>>>;
+
+    /// 记录日志事件的捕获层
+    #[derive(Clone, Default)]
+    struct CaptureLayer {
+        events: CapturedEvents,
+    }
+
+    struct FieldVisitor<'a>(&'a mut std::collections::HashMap<String, String>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for CaptureLayer {
+        fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+            let mut fields = std::collections::HashMap::new();
+            event.record(&mut FieldVisitor(&mut fields));
+            self.events.lock().unwrap().push((*event.metadata().level(), fields));
+        }
+    }
+
+    #[test]
+    fn test_error_log_levels_and_fields() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let layer = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let error = ErrorInfo::new(4001, "连接失败".to_string())
+                .with_severity(ErrorSeverity::Critical)
+                .with_category(ErrorCategory::Network)
+                .with_context("目标: 127.0.0.1".to_string());
+            assert_eq!(error.log().code(), 4001);
+
+            ErrorInfo::new(4002, "重试".to_string()).with_severity(ErrorSeverity::Warning).log();
+            ErrorInfo::new(4003, "提示".to_string()).with_severity(ErrorSeverity::Info).log();
+            ErrorInfo::new(4004, "调试".to_string()).with_severity(ErrorSeverity::Debug).log();
+        });
+
+        let events = layer.events.lock().unwrap();
+        let levels: Vec<tracing::Level> = events.iter().map(|(level, _)| *level).collect();
+        assert_eq!(levels, vec![tracing::Level::ERROR, tracing::Level::WARN, tracing::Level::INFO, tracing::Level::DEBUG]);
+
+        let fields = &events[0].1;
+        assert_eq!(fields.get("code").map(String::as_str), Some("4001"));
+        assert_eq!(fields.get("category").map(String::as_str), Some("Network"));
+        assert_eq!(fields.get("context").map(String::as_str), Some("目标: 127.0.0.1"));
+        assert_eq!(fields.get("message").map(String::as_str), Some("连接失败"));
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! 测试 `#[derive(Error)]` 宏的各种使用场景
 
-use error::{Error, ErrorKind, ErrorInfo};
+use error::{Error, ErrorCategory, ErrorKind, ErrorInfo};
 
 /// 测试单元变体
 #[derive(Debug, Error)]
@@ -231,3 +231,37 @@ fn test_debug_trait() {
     let debug_str = format!("{:?}", err);
     assert!(debug_str.contains("Simple"));
 }
+
+/// 测试 into_info 生成的 ErrorInfo 转换
+#[derive(Debug, Error)]
+#[error(into_info, category = Network)]
+enum PeerError {
+    #[error("连接超时: {0}")]
+    Timeout(String),
+
+    #[error("证书无效: {peer}", category = Authentication)]
+    InvalidCertificate { peer: String },
+}
+
+fn connect_peer(peer: &str) -> error::Result<()> {
+    if peer.is_empty() {
+        Err(PeerError::Timeout("未知设备".to_string()))?;
+    }
+    Err(PeerError::InvalidCertificate { peer: peer.to_string() })?
+}
+
+#[test]
+fn test_into_info_conversion() {
+    let err = connect_peer("").unwrap_err();
+    assert_eq!(err.code(), 1);
+    assert_eq!(err.message(), "连接超时: 未知设备");
+    assert_eq!(err.category(), ErrorCategory::Network);
+    assert!(err.is_retryable());
+    assert_eq!(err.source().unwrap().error_code(), 1);
+
+    let err = connect_peer("peer-1").unwrap_err();
+    assert_eq!(err.code(), 2);
+    assert_eq!(err.message(), "证书无效: peer-1");
+    assert_eq!(err.category(), ErrorCategory::Authentication);
+    assert!(!err.is_retryable());
+}
@@ -0,0 +1,227 @@
+//! # GPU 检测模块
+//!
+//! 检测本机的 GPU 及其显存大小，用于设备能力声明。
+//!
+//! Linux 上通过 `/sys/class/drm` 枚举挂在 PCI 总线上的显卡，厂商由 PCI 厂商ID确定，
+//! 显存取自驱动导出的 `mem_info_vram_total`（amdgpu 等驱动提供）；
+//! NVIDIA 专有驱动不导出显存信息，名称从 `/proc/driver/nvidia` 读取。
+//! 其他平台暂不支持检测，返回空列表。
+
+use std::path::Path;
+
+/// GPU 厂商
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GpuVendor {
+    /// NVIDIA
+    Nvidia,
+    /// AMD
+    Amd,
+    /// Intel
+    Intel,
+    /// 其他或未知厂商
+    Other,
+}
+
+impl GpuVendor {
+    /// 根据 PCI 厂商ID确定厂商
+    ///
+    /// # 参数
+    ///
+    /// * `vendor_id` - PCI 厂商ID
+    pub fn from_pci_id(vendor_id: u32) -> Self {
+        match vendor_id {
+            0x10de => Self::Nvidia,
+            0x1002 => Self::Amd,
+            0x8086 => Self::Intel,
+            _ => Self::Other,
+        }
+    }
+
+    /// 厂商名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Nvidia => "NVIDIA",
+            Self::Amd => "AMD",
+            Self::Intel => "Intel",
+            Self::Other => "Unknown",
+        }
+    }
+}
+
+/// GPU 信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuInfo {
+    /// GPU 名称
+    pub name: String,
+    /// 显存大小（字节），无法获取时为 0
+    pub vram_bytes: u64,
+    /// GPU 厂商
+    pub vendor: GpuVendor,
+}
+
+/// 检测本机 GPU
+///
+/// # 返回值
+///
+/// 返回检测到的 GPU 列表，未检测到时返回空列表
+pub fn detect() -> Vec<GpuInfo> {
+    #[cfg(target_os = "linux")]
+    {
+        detect_drm(Path::new("/sys/class/drm"), Path::new("/proc/driver/nvidia/gpus"))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// 从 DRM 设备目录枚举 GPU
+///
+/// # 参数
+///
+/// * `drm_root` - DRM 类目录，通常为 `/sys/class/drm`
+/// * `nvidia_root` - NVIDIA 驱动信息目录，通常为 `/proc/driver/nvidia/gpus`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn detect_drm(drm_root: &Path, nvidia_root: &Path) -> Vec<GpuInfo> {
+    let Ok(entries) = std::fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+
+    // 只取 cardN 主设备节点，跳过 card0-HDMI-A-1 之类的连接器节点和 renderD128 之类的渲染节点
+    let mut cards: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("card")
+                .is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(|entry| entry.path().join("device"))
+        .filter(|device| is_pci_device(device))
+        .collect();
+    cards.sort();
+
+    let mut nvidia_names = nvidia_model_names(nvidia_root).into_iter();
+    let mut gpus = Vec::with_capacity(cards.len());
+
+    for device in cards {
+        let Some(vendor_id) = read_hex(&device.join("vendor")) else {
+            continue;
+        };
+        let vendor = GpuVendor::from_pci_id(vendor_id);
+
+        let vram_bytes = std::fs::read_to_string(device.join("mem_info_vram_total"))
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+
+        let model = match vendor {
+            GpuVendor::Nvidia => nvidia_names.next(),
+            _ => None,
+        };
+        let name = model.unwrap_or_else(|| {
+            let device_id = read_hex(&device.join("device")).unwrap_or(0);
+            format!("{} GPU [{:04x}:{:04x}]", vendor.name(), vendor_id, device_id)
+        });
+
+        gpus.push(GpuInfo { name, vram_bytes, vendor });
+    }
+
+    gpus
+}
+
+/// 设备是否挂在 PCI 总线上
+///
+/// vkms 等虚拟显卡没有 `device` 目录，或其 `subsystem` 指向 platform 等其他总线
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn is_pci_device(device: &Path) -> bool {
+    std::fs::read_link(device.join("subsystem"))
+        .ok()
+        .is_some_and(|subsystem| subsystem.file_name().is_some_and(|name| name == "pci"))
+}
+
+/// 读取 NVIDIA 驱动导出的 GPU 型号名称
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn nvidia_model_names(nvidia_root: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(nvidia_root) else {
+        return Vec::new();
+    };
+
+    let mut dirs: Vec<_> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect();
+    dirs.sort();
+
+    dirs.iter()
+        .filter_map(|dir| std::fs::read_to_string(dir.join("information")).ok())
+        .filter_map(|info| {
+            info.lines()
+                .find_map(|line| line.strip_prefix("Model:"))
+                .map(|model| model.trim().to_string())
+                .filter(|model| !model.is_empty())
+        })
+        .collect()
+}
+
+/// 读取 sysfs 中 `0x` 前缀的十六进制值
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn read_hex(path: &Path) -> Option<u32> {
+    let value = std::fs::read_to_string(path).ok()?;
+    let value = value.trim();
+    u32::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn write_card(root: &Path, card: &str, bus: &str, vendor: &str, device: &str, vram: Option<&str>) {
+        let dir = root.join(card).join("device");
+        std::fs::create_dir_all(&dir).expect("创建设备目录失败");
+        std::os::unix::fs::symlink(format!("../../../bus/{}", bus), dir.join("subsystem"))
+            .expect("创建总线链接失败");
+        std::fs::write(dir.join("vendor"), vendor).expect("写入厂商ID失败");
+        std::fs::write(dir.join("device"), device).expect("写入设备ID失败");
+        if let Some(vram) = vram {
+            std::fs::write(dir.join("mem_info_vram_total"), vram).expect("写入显存大小失败");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_drm_reads_vendor_and_vram() {
+        let base = std::env::temp_dir().join(format!("bey-sys-gpu-{}", std::process::id()));
+        let drm = base.join("drm");
+        let nvidia = base.join("nvidia");
+
+        write_card(&drm, "card0", "pci", "0x1002\n", "0x73bf\n", Some("17163091968\n"));
+        write_card(&drm, "card1", "pci", "0x10de\n", "0x2684\n", None);
+        // 连接器节点、渲染节点和虚拟显卡不应被当作显卡
+        std::fs::create_dir_all(drm.join("card0-HDMI-A-1")).expect("创建连接器目录失败");
+        std::fs::create_dir_all(drm.join("card2")).expect("创建虚拟显卡目录失败");
+        write_card(&drm, "card3", "platform", "0x1af4\n", "0x1050\n", None);
+        write_card(&drm, "renderD128", "pci", "0x1002\n", "0x73bf\n", None);
+
+        let gpu_dir = nvidia.join("0000:01:00.0");
+        std::fs::create_dir_all(&gpu_dir).expect("创建NVIDIA目录失败");
+        std::fs::write(gpu_dir.join("information"), "Model: \t\t NVIDIA GeForce RTX 4090\nIRQ: 150\n")
+            .expect("写入NVIDIA信息失败");
+
+        let gpus = detect_drm(&drm, &nvidia);
+        std::fs::remove_dir_all(&base).ok();
+
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].vendor, GpuVendor::Amd);
+        assert_eq!(gpus[0].vram_bytes, 17_163_091_968);
+        assert_eq!(gpus[0].name, "AMD GPU [1002:73bf]");
+        assert_eq!(gpus[1].vendor, GpuVendor::Nvidia);
+        assert_eq!(gpus[1].vram_bytes, 0);
+        assert_eq!(gpus[1].name, "NVIDIA GeForce RTX 4090");
+    }
+
+    #[test]
+    fn test_detect_drm_missing_root_returns_empty() {
+        let gpus = detect_drm(Path::new("/nonexistent/bey/drm"), Path::new("/nonexistent/bey/nvidia"));
+        assert!(gpus.is_empty());
+    }
+}
@@ -35,9 +35,11 @@ use sysinfo::{System, Disks, Components};
 
 pub mod monitor;
 pub mod hooks;
+pub mod gpu;
 
 pub use monitor::HotMonitor;
 pub use hooks::{Hook, HookCondition, HookRegistry};
+pub use gpu::{GpuInfo, GpuVendor};
 
 /// 系统信息监控结果类型
 pub type SysResult<T> = std::result::Result<T, ErrorInfo>;
@@ -318,6 +320,26 @@ impl SystemInfo {
         // 现代 CPU 的缓存行大小通常为 64 字节
         64
     }
+
+    /// 获取 GPU 信息
+    ///
+    /// 检测本机的 GPU 及显存大小，用于确定设备能力。
+    /// 平台不支持或未检测到 GPU 时返回空列表。
+    ///
+    /// # 示例
+    ///
+    /// ```
+    /// # use sys::SystemInfo;
+    /// # async fn example() {
+    /// let sys_info = SystemInfo::new().await;
+    /// for gpu in sys_info.gpu_info() {
+    ///     println!("{}: {} MB", gpu.name, gpu.vram_bytes / (1024 * 1024));
+    /// }
+    /// # }
+    /// ```
+    pub fn gpu_info(&self) -> Vec<GpuInfo> {
+        gpu::detect()
+    }
 }
 
 #[cfg(test)]
@@ -416,4 +438,15 @@ mod tests {
         // 缓存行大小应该为 64 字节
         assert_eq!(cache_line, 64);
     }
+
+    #[tokio::test]
+    async fn test_gpu_info() {
+        let sys_info = SystemInfo::new().await;
+        let gpus = sys_info.gpu_info();
+
+        // 可能没有 GPU，但检测到的条目应该是完整的
+        for gpu in &gpus {
+            assert!(!gpu.name.is_empty());
+        }
+    }
 }
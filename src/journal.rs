@@ -0,0 +1,503 @@
+//! # 事件日志模块
+//!
+//! 订阅各子系统的事件流（网络连接握手事件、存储事件），把事件规范化为统一的
+//! [`JournalRecord`]，以 NDJSON 格式（每行一条 JSON 记录）追加写入磁盘上的日志文件，
+//! 为排查问题提供统一的事件轨迹。
+//!
+//! 当前日志文件超过大小上限时轮转：`journal.ndjson` 重命名为 `journal.ndjson.1`，
+//! 已有的轮转文件序号依次加一，超出保留数量的最旧文件被删除。
+//!
+//! 文件读写都是阻塞操作：订阅任务在阻塞线程池中追加记录，查询只在轮转时等待，
+//! 不阻塞并发的追加。
+
+use crate::AppResult;
+use bey_net::{ConnectionEvent, StateEvent};
+use bey_storage::StorageEvent;
+use error::{ErrorCategory, ErrorInfo, ErrorSeverity};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// 当前日志文件名
+pub const JOURNAL_FILE_NAME: &str = "journal.ndjson";
+
+/// 单个日志文件的默认大小上限（字节）
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// 默认保留的轮转文件数量（不含当前文件）
+pub const DEFAULT_MAX_ROTATED_FILES: usize = 4;
+
+/// 事件来源子系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum JournalSource {
+    /// 网络连接
+    Network,
+    /// 存储
+    Storage,
+}
+
+/// 规范化的日志记录
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct JournalRecord {
+    /// 记录时间（Unix 毫秒时间戳）
+    pub timestamp_ms: u64,
+    /// 事件来源
+    pub source: JournalSource,
+    /// 事件类型，如 `handshake_succeeded`、`object_write`
+    pub kind: String,
+    /// 事件对象，如对端地址、对象ID
+    pub subject: String,
+    /// 事件详情
+    pub detail: String,
+}
+
+impl JournalRecord {
+    /// 以当前时间创建日志记录
+    ///
+    /// # 参数
+    ///
+    /// * `source` - 事件来源
+    /// * `kind` - 事件类型
+    /// * `subject` - 事件对象
+    /// * `detail` - 事件详情
+    pub fn new(
+        source: JournalSource,
+        kind: impl Into<String>,
+        subject: impl Into<String>,
+        detail: impl Into<String>,
+    ) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+
+        Self {
+            timestamp_ms,
+            source,
+            kind: kind.into(),
+            subject: subject.into(),
+            detail: detail.into(),
+        }
+    }
+
+    /// 规范化网络连接握手事件
+    pub fn from_connection_event(event: &ConnectionEvent) -> Self {
+        let kind = match &event.event {
+            StateEvent::Authenticated => "handshake_succeeded",
+            StateEvent::Rejected(_) => "handshake_rejected",
+            StateEvent::Error(_) => "handshake_failed",
+            _ => "connection_state",
+        };
+        Self::new(JournalSource::Network, kind, event.remote_addr.to_string(), event.event.to_string())
+    }
+
+    /// 规范化存储事件
+    pub fn from_storage_event(event: &StorageEvent) -> Self {
+        let kind = format!("{:?}_{:?}", event.storage, event.operation).to_lowercase();
+        let detail = match &event.previous_key {
+            Some(previous_key) => format!("{} 字节，原标识符 {}", event.size, previous_key),
+            None => format!("{} 字节", event.size),
+        };
+        Self::new(JournalSource::Storage, kind, event.key.clone(), detail)
+    }
+}
+
+/// 日志查询条件
+///
+/// 未设置的条件不参与过滤，所有已设置的条件都满足时记录才匹配
+#[derive(Debug, Clone, Default)]
+pub struct JournalFilter {
+    /// 事件来源
+    pub source: Option<JournalSource>,
+    /// 事件类型
+    pub kind: Option<String>,
+    /// 事件对象
+    pub subject: Option<String>,
+    /// 起始时间（Unix 毫秒时间戳，包含）
+    pub since_ms: Option<u64>,
+    /// 最多返回的记录数，超出时保留最新的记录
+    pub limit: Option<usize>,
+}
+
+impl JournalFilter {
+    /// 只匹配指定来源的事件
+    pub fn with_source(mut self, source: JournalSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// 只匹配指定类型的事件
+    pub fn with_kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+
+    /// 只匹配指定对象的事件
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// 只匹配指定时间之后的事件
+    pub fn with_since(mut self, since: SystemTime) -> Self {
+        self.since_ms = since.duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_millis() as u64);
+        self
+    }
+
+    /// 限制返回的记录数
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 判断记录是否满足查询条件（不考虑数量限制）
+    pub fn matches(&self, record: &JournalRecord) -> bool {
+        self.source.is_none_or(|source| record.source == source)
+            && self.kind.as_ref().is_none_or(|kind| &record.kind == kind)
+            && self.subject.as_ref().is_none_or(|subject| &record.subject == subject)
+            && self.since_ms.is_none_or(|since| record.timestamp_ms >= since)
+    }
+}
+
+/// 当前日志文件的写入状态
+struct JournalWriter {
+    /// 当前日志文件
+    file: File,
+    /// 当前日志文件大小
+    size: u64,
+}
+
+/// 应用级事件日志
+///
+/// 通过 [`EventJournal::attach`] 订阅子系统的事件流，事件在后台任务中规范化后追加到日志。
+/// 日志被释放时停止所有订阅任务。
+pub struct EventJournal {
+    /// 日志目录
+    dir: PathBuf,
+    /// 单个日志文件的大小上限
+    max_file_size: u64,
+    /// 保留的轮转文件数量
+    max_rotated_files: usize,
+    /// 当前日志文件
+    writer: Mutex<JournalWriter>,
+    /// 轮转锁：查询期间持有读锁，轮转时持有写锁
+    rotation: RwLock<()>,
+    /// 事件订阅任务
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl EventJournal {
+    /// 打开事件日志，目录不存在时创建
+    ///
+    /// # 参数
+    ///
+    /// * `dir` - 日志目录
+    ///
+    /// # 返回值
+    ///
+    /// 返回事件日志或错误信息
+    pub fn open(dir: impl Into<PathBuf>) -> AppResult<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(|e| journal_error(2020, "创建日志目录", &dir, e))?;
+        let writer = Self::open_writer(&dir)?;
+
+        Ok(Self {
+            dir,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_rotated_files: DEFAULT_MAX_ROTATED_FILES,
+            writer: Mutex::new(writer),
+            rotation: RwLock::new(()),
+            tasks: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// 设置轮转策略
+    ///
+    /// # 参数
+    ///
+    /// * `max_file_size` - 单个日志文件的大小上限（字节）
+    /// * `max_rotated_files` - 保留的轮转文件数量
+    pub fn with_rotation(mut self, max_file_size: u64, max_rotated_files: usize) -> Self {
+        self.max_file_size = max_file_size.max(1);
+        self.max_rotated_files = max_rotated_files;
+        self
+    }
+
+    /// 日志目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 订阅子系统的事件流
+    ///
+    /// 后台任务把收到的事件规范化后在阻塞线程池中追加到日志，事件流关闭或日志被释放时任务结束
+    ///
+    /// # 参数
+    ///
+    /// * `events` - 事件接收端
+    /// * `normalize` - 把事件转换为日志记录
+    pub fn attach<E>(self: &Arc<Self>, mut events: broadcast::Receiver<E>, normalize: fn(&E) -> JournalRecord)
+    where
+        E: Clone + Send + 'static,
+    {
+        let journal: Weak<Self> = Arc::downgrade(self);
+        let task = tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("事件日志订阅处理过慢，丢失 {} 条事件", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+
+                let Some(journal) = journal.upgrade() else {
+                    break;
+                };
+                let record = normalize(&event);
+                match tokio::task::spawn_blocking(move || journal.append(&record)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => tracing::warn!("写入事件日志失败: {}", e),
+                    Err(e) => tracing::warn!("写入事件日志的任务异常终止: {}", e),
+                }
+            }
+        });
+
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(task);
+        }
+    }
+
+    /// 追加一条日志记录，写入后超过大小上限时轮转
+    ///
+    /// 阻塞操作，异步上下文中应在 `spawn_blocking` 中调用
+    ///
+    /// # 参数
+    ///
+    /// * `record` - 日志记录
+    ///
+    /// # 返回值
+    ///
+    /// 成功返回 Ok(())，失败返回错误信息
+    pub fn append(&self, record: &JournalRecord) -> AppResult<()> {
+        let mut line = serde_json::to_vec(record)
+            .map_err(|e| ErrorInfo::new(2021, format!("序列化日志记录失败: {}", e))
+                .with_category(ErrorCategory::Parse)
+                .with_severity(ErrorSeverity::Error))?;
+        line.push(b'\n');
+
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        writer.file.write_all(&line)
+            .map_err(|e| journal_error(2021, "写入日志文件", &self.dir, e))?;
+        writer.size += line.len() as u64;
+
+        if writer.size >= self.max_file_size {
+            let _rotation = self.rotation.write().unwrap_or_else(|e| e.into_inner());
+            self.rotate()?;
+            *writer = Self::open_writer(&self.dir)?;
+        }
+        Ok(())
+    }
+
+    /// 查询日志记录
+    ///
+    /// 按时间顺序读取轮转文件和当前文件，返回满足条件的记录；
+    /// 无法解析的行（包括正在追加的最后一行）被跳过。
+    /// 阻塞操作，异步上下文中应在 `spawn_blocking` 中调用
+    ///
+    /// # 参数
+    ///
+    /// * `filter` - 查询条件
+    ///
+    /// # 返回值
+    ///
+    /// 返回按时间从旧到新排列的记录或错误信息
+    pub fn query(&self, filter: &JournalFilter) -> AppResult<Vec<JournalRecord>> {
+        // 持有轮转读锁，查询期间不会发生轮转，追加不受影响
+        let _rotation = self.rotation.read().unwrap_or_else(|e| e.into_inner());
+
+        let mut files: Vec<PathBuf> = (1..=self.max_rotated_files)
+            .rev()
+            .map(|index| self.rotated_path(index))
+            .collect();
+        files.push(self.dir.join(JOURNAL_FILE_NAME));
+
+        let mut records = Vec::new();
+        for path in files.iter().filter(|path| path.exists()) {
+            let file = File::open(path).map_err(|e| journal_error(2022, "打开日志文件", path, e))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| journal_error(2022, "读取日志文件", path, e))?;
+                match serde_json::from_str::<JournalRecord>(&line) {
+                    Ok(record) if filter.matches(&record) => records.push(record),
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("跳过无法解析的日志行: {}", e),
+                }
+            }
+        }
+
+        if let Some(limit) = filter.limit {
+            let skipped = records.len().saturating_sub(limit);
+            records.drain(..skipped);
+        }
+        Ok(records)
+    }
+
+    /// 轮转日志文件：删除最旧的轮转文件，其余序号加一，当前文件成为第1个轮转文件
+    fn rotate(&self) -> AppResult<()> {
+        let current = self.dir.join(JOURNAL_FILE_NAME);
+        if self.max_rotated_files == 0 {
+            return std::fs::remove_file(&current)
+                .map_err(|e| journal_error(2021, "删除日志文件", &current, e));
+        }
+
+        let oldest = self.rotated_path(self.max_rotated_files);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest).map_err(|e| journal_error(2021, "删除轮转日志文件", &oldest, e))?;
+        }
+        for index in (1..self.max_rotated_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated_path(index + 1))
+                    .map_err(|e| journal_error(2021, "轮转日志文件", &from, e))?;
+            }
+        }
+        std::fs::rename(&current, self.rotated_path(1))
+            .map_err(|e| journal_error(2021, "轮转日志文件", &current, e))
+    }
+
+    /// 第 `index` 个轮转文件的路径（序号越大越旧）
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}", JOURNAL_FILE_NAME, index))
+    }
+
+    /// 以追加方式打开当前日志文件
+    fn open_writer(dir: &Path) -> AppResult<JournalWriter> {
+        let path = dir.join(JOURNAL_FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| journal_error(2020, "打开日志文件", &path, e))?;
+        let size = file.metadata()
+            .map_err(|e| journal_error(2020, "读取日志文件信息", &path, e))?
+            .len();
+        Ok(JournalWriter { file, size })
+    }
+}
+
+impl Drop for EventJournal {
+    fn drop(&mut self) {
+        if let Ok(tasks) = self.tasks.lock() {
+            for task in tasks.iter() {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// 构造日志文件操作错误
+fn journal_error(code: u32, action: &str, path: &Path, e: std::io::Error) -> ErrorInfo {
+    ErrorInfo::new(code, format!("{}失败: {} - {}", action, path.display(), e))
+        .with_category(ErrorCategory::FileSystem)
+        .with_severity(ErrorSeverity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bey_storage::{StorageKind, StorageOperation};
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    /// 等待日志中满足条件的记录达到指定数量
+    async fn wait_for_records(journal: &EventJournal, filter: &JournalFilter, count: usize) -> Vec<JournalRecord> {
+        for _ in 0..100 {
+            let records = journal.query(filter).expect("查询日志失败");
+            if records.len() >= count {
+                return records;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("等待日志记录超时");
+    }
+
+    #[tokio::test]
+    async fn test_journal_collects_events_from_subsystems() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let journal = Arc::new(EventJournal::open(temp_dir.path()).expect("打开事件日志失败"));
+
+        let (network_tx, network_rx) = broadcast::channel(16);
+        let (storage_tx, storage_rx) = broadcast::channel(16);
+        journal.attach(network_rx, JournalRecord::from_connection_event);
+        journal.attach(storage_rx, JournalRecord::from_storage_event);
+
+        let peer: std::net::SocketAddr = "192.168.1.20:8080".parse().expect("解析地址失败");
+        network_tx.send(ConnectionEvent { remote_addr: peer, event: StateEvent::Authenticated })
+            .expect("发送网络事件失败");
+        storage_tx.send(StorageEvent {
+            storage: StorageKind::Object,
+            operation: StorageOperation::Write,
+            key: "object-1".to_string(),
+            size: 42,
+            previous_key: None,
+        }).expect("发送存储事件失败");
+        network_tx.send(ConnectionEvent { remote_addr: peer, event: StateEvent::Rejected("版本不兼容".to_string()) })
+            .expect("发送网络事件失败");
+
+        let all = wait_for_records(&journal, &JournalFilter::default(), 3).await;
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().any(|record| record.source == JournalSource::Network));
+        assert!(all.iter().any(|record| record.source == JournalSource::Storage));
+
+        let storage = journal.query(&JournalFilter::default().with_source(JournalSource::Storage))
+            .expect("查询日志失败");
+        assert_eq!(storage.len(), 1);
+        assert_eq!(storage[0].kind, "object_write");
+        assert_eq!(storage[0].subject, "object-1");
+
+        let rejected = journal.query(&JournalFilter::default()
+            .with_source(JournalSource::Network)
+            .with_kind("handshake_rejected"))
+            .expect("查询日志失败");
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].subject, peer.to_string());
+
+        // 日志落盘后重新打开仍可查询
+        drop(journal);
+        let reopened = EventJournal::open(temp_dir.path()).expect("重新打开事件日志失败");
+        assert_eq!(reopened.query(&JournalFilter::default()).expect("查询日志失败").len(), 3);
+    }
+
+    #[test]
+    fn test_journal_rotates_by_size() {
+        let temp_dir = tempdir().expect("创建临时目录失败");
+        let journal = EventJournal::open(temp_dir.path()).expect("打开事件日志失败")
+            .with_rotation(200, 2);
+
+        for index in 0..20 {
+            let record = JournalRecord::new(JournalSource::Storage, "object_write", format!("object-{}", index), "1 字节");
+            journal.append(&record).expect("追加日志失败");
+        }
+
+        assert!(temp_dir.path().join("journal.ndjson.1").exists());
+        assert!(temp_dir.path().join("journal.ndjson.2").exists());
+        assert!(!temp_dir.path().join("journal.ndjson.3").exists(), "超出保留数量的轮转文件应被删除");
+
+        // 保留的记录按时间顺序排列，且以最新的记录结尾
+        let records = journal.query(&JournalFilter::default()).expect("查询日志失败");
+        assert!(!records.is_empty() && records.len() < 20);
+        assert_eq!(records.last().map(|record| record.subject.as_str()), Some("object-19"));
+
+        let limited = journal.query(&JournalFilter::default().with_limit(2)).expect("查询日志失败");
+        let subjects: Vec<&str> = limited.iter().map(|record| record.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["object-18", "object-19"]);
+    }
+}
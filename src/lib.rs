@@ -21,6 +21,8 @@
 //! │   ├── main.rs         # 主程序入口
 //! │   ├── lib.rs          # 库入口
 //! │   ├── app.rs          # 应用程序管理器
+//! │   ├── cli.rs          # 命令行子命令解析
+//! │   ├── journal.rs      # 跨子系统的事件日志
 //! │   └── crates/
 //! │       ├── error/          # 错误处理框架
 //! │       ├── sys/            # 系统监控模块
@@ -61,6 +63,12 @@
 // 导出应用程序模块
 pub mod app;
 
+// 导出命令行模块
+pub mod cli;
+
+// 导出事件日志模块
+pub mod journal;
+
 // 导出 Tauri API 模块
 pub mod tauri_api;
 
@@ -130,6 +138,8 @@ pub enum Capability {
     StorageContribution,
     /// 证书管理能力
     CertificateManagement,
+    /// GPU 计算能力
+    GpuCompute,
 }
 
 /// BEY 应用程序主结构体
@@ -307,7 +317,7 @@ impl BeyApp {
     ///
     /// 返回设备能力列表
     fn determine_capabilities(device_type: &DeviceType, system_info: &SystemInfo) -> Vec<Capability> {
-        let mut capabilities = Vec::with_capacity(5);
+        let mut capabilities = Vec::with_capacity(6);
 
         // 所有设备都支持基本的消息传递
         capabilities.push(Capability::Messaging);
@@ -339,6 +349,11 @@ impl BeyApp {
             }
         }
 
+        // 检测到 GPU 时声明 GPU 计算能力
+        if !system_info.gpu_info().is_empty() {
+            capabilities.push(Capability::GpuCompute);
+        }
+
         capabilities
     }
 
@@ -1,8 +1,9 @@
 //! # BEY 主程序入口
 //!
-//! 根据编译条件启动 GUI 或 TUI 界面
+//! 解析命令行子命令；不带命令时根据编译条件启动 GUI 或 TUI 界面
 
 use bey::app::{AppConfig, BeyAppManager};
+use bey::cli::{CliCommand, USAGE};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -11,12 +12,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_max_level(tracing::Level::INFO)
         .init();
 
+    let command = match CliCommand::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e.message(), USAGE);
+            std::process::exit(2);
+        }
+    };
+
     tracing::info!("BEY 应用程序启动");
 
     // 加载配置
     let config = load_config()?;
 
-    // 根据编译条件启动不同的界面
+    match command {
+        CliCommand::Default => run_default(config).await?,
+        CliCommand::Serve => run_headless(config).await?,
+        CliCommand::Status => run_status(config).await?,
+        CliCommand::Send { peer_id, message } => run_send(config, &peer_id, &message).await?,
+        CliCommand::ExportIdentity => run_export_identity(config).await?,
+        CliCommand::Help => println!("{}", USAGE),
+    }
+
+    tracing::info!("BEY 应用程序关闭");
+    Ok(())
+}
+
+/// 根据编译条件启动默认界面
+async fn run_default(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(feature = "gui")]
     {
         tracing::info!("启动 GUI 模式");
@@ -35,7 +58,66 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         run_headless(config).await?;
     }
 
-    tracing::info!("BEY 应用程序关闭");
+    Ok(())
+}
+
+/// 打印本设备信息和运行统计
+async fn run_status(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut manager = BeyAppManager::new(config).await?;
+    manager.initialize().await?;
+
+    print_device_info(&manager);
+
+    let stats = manager.func_manager().statistics().await;
+    println!("运行状态: {:?}", manager.state().await);
+    println!("本地消息: {}", stats.messages_stored);
+    println!("剪切板条目: {}", stats.clipboard_entries);
+    println!("云存储文件: {} ({} 字节)", stats.cloud_files, stats.cloud_bytes_used);
+    println!("已发送: {} 个令牌 / {} 字节", stats.tokens_sent, stats.bytes_sent);
+    println!("已接收: {} 个令牌 / {} 字节", stats.tokens_received, stats.bytes_received);
+
+    manager.stop().await?;
+    Ok(())
+}
+
+/// 向对等设备发送私信
+///
+/// 对端确认收到后才算发送成功；消息只进入离线队列时按失败处理，
+/// 进程退出后离线队列中的消息不会再被投递
+async fn run_send(config: AppConfig, peer_id: &str, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut manager = BeyAppManager::new(config).await?;
+    manager.initialize().await?;
+    manager.start().await?;
+
+    let func_manager = manager.func_manager();
+    let result = match func_manager.send_private_message(peer_id, message.as_bytes()).await {
+        Ok(msg_id) if func_manager.pending_outbound(peer_id).await.iter().any(|queued| queued.msg_id == msg_id) => {
+            Err(format!("对方设备不可达，消息 {} 未送达", msg_id))
+        }
+        Ok(msg_id) => Ok(msg_id),
+        Err(e) => Err(format!("消息发送失败: {}", e)),
+    };
+    drop(func_manager);
+
+    // 等待进行中的发送完成后再关闭网络
+    let stopped = manager.shutdown(SHUTDOWN_DRAIN_TIMEOUT).await;
+    let msg_id = result?;
+    stopped?;
+
+    println!("消息已发送: {} -> {}", msg_id, peer_id);
+    Ok(())
+}
+
+/// 打印本设备证书
+async fn run_export_identity(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let mut manager = BeyAppManager::new(config).await?;
+    manager.initialize().await?;
+
+    let certificate = manager.func_manager().device_certificate_pem().await;
+    manager.stop().await?;
+
+    let certificate = certificate.ok_or("本设备尚未签发证书")?;
+    print!("{}", certificate);
     Ok(())
 }
 
@@ -132,7 +214,6 @@ async fn run_tui(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
 }
 
 /// 无界面模式（服务模式）
-#[cfg(not(any(feature = "gui", feature = "tui")))]
 async fn run_headless(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("启动无界面服务模式");
 
@@ -148,6 +229,21 @@ async fn run_headless(config: AppConfig) -> Result<(), Box<dyn std::error::Error
     tracing::info!("应用程序已启动");
 
     // 打印设备信息
+    print_device_info(&manager);
+
+    // 等待退出信号
+    tracing::info!("按 Ctrl+C 停止应用程序");
+    wait_for_shutdown_signal().await?;
+
+    tracing::info!("收到停止信号，正在关闭应用程序...");
+    manager.shutdown(SHUTDOWN_DRAIN_TIMEOUT).await?;
+    tracing::info!("应用程序已停止");
+
+    Ok(())
+}
+
+/// 打印设备信息
+fn print_device_info(manager: &BeyAppManager) {
     let device = manager.local_device();
     println!("\n=== BEY 设备信息 ===");
     println!("设备 ID: {}", device.device_id);
@@ -156,14 +252,27 @@ async fn run_headless(config: AppConfig) -> Result<(), Box<dyn std::error::Error
     println!("网络地址: {}", device.address);
     println!("设备能力: {:?}", device.capabilities);
     println!("====================\n");
+}
 
-    // 等待退出信号
-    tracing::info!("按 Ctrl+C 停止应用程序");
-    tokio::signal::ctrl_c().await?;
+/// 关闭时等待进行中任务完成的最长时间
+const SHUTDOWN_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
-    tracing::info!("收到停止信号，正在关闭应用程序...");
-    manager.stop().await?;
-    tracing::info!("应用程序已停止");
+/// 等待 SIGINT（Ctrl+C）或 SIGTERM
+async fn wait_for_shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
 
-    Ok(())
+        let mut sigterm = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = sigterm.recv() => tracing::info!("收到 SIGTERM"),
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
 }